@@ -0,0 +1,74 @@
+// Unlit-but-for-the-sun shading for vehicles drawn in instanced batches. The model matrix and
+// colour come in per instance; the palette texture is shared by every vehicle of the model.
+#import bevy_pbr::mesh_view_bindings::{view, lights}
+#import bevy_pbr::view_transformations::position_world_to_clip
+#import bevy_render::maths::PI
+
+#ifdef TONEMAP_IN_SHADER
+#import bevy_core_pipeline::tonemapping::{tone_mapping, screen_space_dither}
+#endif
+
+@group(2) @binding(0) var palette_texture: texture_2d<f32>;
+@group(2) @binding(1) var palette_sampler: sampler;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+#ifdef VERTEX_UVS
+    @location(2) uv: vec2<f32>,
+#endif
+
+    @location(8) model_x: vec4<f32>,
+    @location(9) model_y: vec4<f32>,
+    @location(10) model_z: vec4<f32>,
+    @location(11) model_w: vec4<f32>,
+    @location(12) base_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_normal: vec3<f32>,
+    @location(1) uv: vec2<f32>,
+    @location(2) base_color: vec4<f32>,
+};
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let model = mat4x4<f32>(vertex.model_x, vertex.model_y, vertex.model_z, vertex.model_w);
+    let world_position = model * vec4<f32>(vertex.position, 1.0);
+
+    var out: VertexOutput;
+    out.clip_position = position_world_to_clip(world_position.xyz);
+    // Vehicles are only ever scaled uniformly, so the model matrix can turn normals too.
+    out.world_normal = normalize((model * vec4<f32>(vertex.normal, 0.0)).xyz);
+#ifdef VERTEX_UVS
+    out.uv = vertex.uv;
+#endif
+    out.base_color = vertex.base_color;
+    return out;
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> @location(0) vec4<f32> {
+    let albedo = textureSample(palette_texture, palette_sampler, in.uv) * in.base_color;
+    let normal = normalize(in.world_normal);
+
+    var light = lights.ambient_color.rgb;
+    for (var i = 0u; i < lights.n_directional_lights; i = i + 1u) {
+        let sun = lights.directional_lights[i];
+        light += sun.color.rgb * max(dot(normal, sun.direction_to_light), 0.0) / PI;
+    }
+
+    var color = vec4<f32>(albedo.rgb * light * view.exposure, albedo.a);
+
+#ifdef TONEMAP_IN_SHADER
+    color = tone_mapping(color, view.color_grading);
+#ifdef DEBAND_DITHER
+    // Dither in gamma space, as the PBR shader does, so the noise is even across the range.
+    let dithered = pow(color.rgb, vec3<f32>(1.0 / 2.2)) + screen_space_dither(in.clip_position.xy);
+    color = vec4<f32>(pow(max(dithered, vec3<f32>(0.0)), vec3<f32>(2.2)), color.a);
+#endif
+#endif
+
+    return color;
+}
@@ -0,0 +1,16 @@
+use bevy::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MetricsFormat {
+    Csv,
+    Json,
+}
+
+#[derive(Event, Debug)]
+pub struct RequestMetricsExport(pub MetricsFormat);
+
+#[derive(Event, Debug)]
+pub struct OnMetricsExported {
+    pub path: String,
+    pub samples: usize,
+}
@@ -0,0 +1,90 @@
+use crate::{
+    determinism::determinism::SimTick,
+    economy::economy_events::{GrantFunds, SpendFunds},
+    graph::road_graph_events::*,
+    save::save_events::OnGameSaved,
+    scenario::scenario_events::ScenarioMessage,
+    schedule::UpdateStage,
+    tools::{inspect_events::*, road_events::*, toolbar_events::ChangeToolRequest},
+    types::{
+        accident::{OnAccident, OnAccidentCleared},
+        traffic_signal::{RequestAdaptiveTiming, RequestIntersectionControl, RequestSignalOverride},
+        watchdog::OnVehicleUnstuck,
+    },
+};
+use bevy::prelude::*;
+use std::{collections::VecDeque, fmt::Debug};
+
+const MAX_LOG_ENTRIES: usize = 500;
+
+pub struct EventLogPlugin;
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(EventLog::default()).add_systems(
+            Update,
+            (
+                (
+                    log_events::<OnRoadSpawned>,
+                    log_events::<OnIntersectionSpawned>,
+                    log_events::<OnBuildingSpawned>,
+                    log_events::<OnRoadDestroyed>,
+                    log_events::<OnIntersectionDestroyed>,
+                    log_events::<OnBuildingDestroyed>,
+                    log_events::<OnRoadResurfaced>,
+                    log_events::<OnRoadUpgraded>,
+                    log_events::<ChangeToolRequest>,
+                    log_events::<RequestSignalOverride>,
+                    log_events::<RequestIntersectionControl>,
+                ),
+                (
+                    log_events::<RequestSpeedLimit>,
+                    log_events::<RequestOneWay>,
+                    log_events::<RequestTurnRestriction>,
+                    log_events::<RequestVehicleDespawn>,
+                    log_events::<OnAccident>,
+                    log_events::<OnAccidentCleared>,
+                    log_events::<OnVehicleUnstuck>,
+                    log_events::<GrantFunds>,
+                    log_events::<SpendFunds>,
+                    log_events::<OnGameSaved>,
+                    log_events::<ScenarioMessage>,
+                    log_events::<RequestAdaptiveTiming>,
+                ),
+            )
+                .in_set(UpdateStage::Analyze),
+        );
+    }
+}
+
+#[derive(Debug, Clone)]
+struct LogEntry {
+    tick: u64,
+    message: String,
+}
+
+// Keeps only the most recent gameplay events so it can run for a whole session.
+#[derive(Resource, Debug, Default)]
+pub struct EventLog {
+    entries: VecDeque<LogEntry>,
+}
+
+impl EventLog {
+    pub fn push(&mut self, tick: u64, message: String) {
+        if self.entries.len() == MAX_LOG_ENTRIES {
+            self.entries.pop_front();
+        }
+
+        self.entries.push_back(LogEntry { tick, message });
+    }
+
+    pub fn snapshot(&self) -> String {
+        self.entries.iter().map(|entry| format!("[{}] {}\n", entry.tick, entry.message)).collect()
+    }
+}
+
+fn log_events<E: Event + Debug>(mut events: EventReader<E>, mut log: ResMut<EventLog>, tick: Res<SimTick>) {
+    for event in events.read() {
+        log.push(tick.0, format!("{:?}", event));
+    }
+}
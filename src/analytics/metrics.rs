@@ -0,0 +1,209 @@
+use crate::{
+    analytics::{
+        analytics_events::*,
+        trip_stats::{record_trips, TripStats},
+    },
+    clock::unix_seconds,
+    graph::congestion::{track_congestion, Congestion},
+    graphics::weather::TimeOfDay,
+    schedule::UpdateStage,
+    types::{road_segment::RoadSegment, vehicle::Vehicle},
+    ui::notify_events::Notify,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::Serialize;
+use std::{
+    collections::VecDeque,
+    fmt::Write as _,
+    fs,
+    path::{Path, PathBuf},
+};
+
+const METRICS_DIR: &str = "assets/metrics";
+const DEFAULT_INTERVAL_SECONDS: f32 = 60.0;
+// A day of samples at the default interval.
+const MAX_SAMPLES: usize = 24 * 60;
+
+pub struct MetricsPlugin;
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestMetricsExport>()
+            .add_event::<OnMetricsExported>()
+            .insert_resource(MetricsSettings::default())
+            .insert_resource(MetricsRecorder::default())
+            .add_systems(
+                Update,
+                (
+                    sample_metrics
+                        .after(track_congestion)
+                        .after(record_trips)
+                        .run_if(|settings: Res<MetricsSettings>| settings.enabled),
+                    export_metrics,
+                )
+                    .chain()
+                    .in_set(UpdateStage::Analyze),
+            );
+    }
+}
+
+// Off by default, since every sample keeps a congestion reading for each road.
+#[derive(Resource, Debug)]
+pub struct MetricsSettings {
+    pub enabled: bool,
+    pub interval_seconds: f32,
+}
+
+impl Default for MetricsSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_seconds: DEFAULT_INTERVAL_SECONDS,
+        }
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct SegmentSample {
+    pub cell: [i32; 2],
+    pub congestion: f32,
+}
+
+#[derive(Serialize, Debug)]
+pub struct MetricsSample {
+    pub seconds: f32,
+    pub hour: f32,
+    pub vehicles: usize,
+    pub mean_speed: f32,
+    pub completed_trips: u32,
+    pub trips_since_last: u32,
+    pub segments: Vec<SegmentSample>,
+}
+
+// The most recent samples, oldest first.
+#[derive(Resource, Debug, Default)]
+pub struct MetricsRecorder {
+    pub samples: VecDeque<MetricsSample>,
+    elapsed: f32,
+    last_trips: u32,
+}
+
+// Everything a settings panel needs to run the recorder and export what it has.
+#[derive(SystemParam)]
+pub struct MetricsControls<'w> {
+    pub settings: ResMut<'w, MetricsSettings>,
+    pub recorder: Res<'w, MetricsRecorder>,
+    pub export: EventWriter<'w, RequestMetricsExport>,
+}
+
+fn sample_metrics(
+    mut recorder: ResMut<MetricsRecorder>,
+    settings: Res<MetricsSettings>,
+    time: Res<Time>,
+    time_of_day: Res<TimeOfDay>,
+    trips: Res<TripStats>,
+    vehicle_query: Query<&Vehicle>,
+    congestion_query: Query<(&RoadSegment, &Congestion)>,
+) {
+    recorder.elapsed += time.delta_seconds();
+    if recorder.elapsed < settings.interval_seconds {
+        return;
+    }
+    recorder.elapsed = 0.0;
+
+    let vehicles = vehicle_query.iter().count();
+    let mean_speed = match vehicles {
+        0 => 0.0,
+        count => vehicle_query.iter().map(|vehicle| vehicle.speed).sum::<f32>() / count as f32,
+    };
+
+    let mut segments: Vec<SegmentSample> = congestion_query
+        .iter()
+        .map(|(segment, congestion)| SegmentSample {
+            cell: segment.area.min.pos.to_array(),
+            congestion: congestion.ratio,
+        })
+        .collect();
+    segments.sort_by_key(|sample| sample.cell);
+
+    let sample = MetricsSample {
+        seconds: time.elapsed_seconds(),
+        hour: time_of_day.hour,
+        vehicles,
+        mean_speed,
+        completed_trips: trips.total_trips,
+        trips_since_last: trips.total_trips.saturating_sub(recorder.last_trips),
+        segments,
+    };
+    recorder.last_trips = trips.total_trips;
+
+    if recorder.samples.len() == MAX_SAMPLES {
+        recorder.samples.pop_front();
+    }
+    recorder.samples.push_back(sample);
+}
+
+fn export_metrics(
+    mut event: EventReader<RequestMetricsExport>,
+    recorder: Res<MetricsRecorder>,
+    mut exported: EventWriter<OnMetricsExported>,
+    mut notify: EventWriter<Notify>,
+) {
+    for &RequestMetricsExport(format) in event.read() {
+        let dir = Path::new(METRICS_DIR);
+        let name = format!("metrics_{}", unix_seconds());
+
+        let written = fs::create_dir_all(dir).and_then(|_| match format {
+            MetricsFormat::Csv => write_csv(dir, &name, &recorder.samples),
+            MetricsFormat::Json => write_json(dir, &name, &recorder.samples),
+        });
+
+        match written {
+            Ok(path) => {
+                println!("Exported {} metrics samples to {:?}", recorder.samples.len(), path);
+                exported.send(OnMetricsExported {
+                    path: path.display().to_string(),
+                    samples: recorder.samples.len(),
+                });
+            }
+            Err(error) => {
+                println!("Failed to export metrics to {:?}: {}", dir, error);
+                notify.send(Notify::error(format!("Failed to export metrics to {}", METRICS_DIR)));
+            }
+        }
+    }
+}
+
+fn write_json(dir: &Path, name: &str, samples: &VecDeque<MetricsSample>) -> std::io::Result<PathBuf> {
+    let path = dir.join(format!("{}.json", name));
+    fs::write(&path, serde_json::to_vec_pretty(samples)?)?;
+    Ok(path)
+}
+
+// Network totals go in one file with a row per sample. Road congestion goes in a second file with a
+// row per road per sample, keyed by the road's first cell.
+fn write_csv(dir: &Path, name: &str, samples: &VecDeque<MetricsSample>) -> std::io::Result<PathBuf> {
+    let mut network = String::from("seconds,hour,vehicles,mean_speed,completed_trips,trips_since_last\n");
+    let mut roads = String::from("seconds,cell_x,cell_z,congestion\n");
+
+    for sample in samples {
+        let _ = writeln!(
+            network,
+            "{},{},{},{},{},{}",
+            sample.seconds, sample.hour, sample.vehicles, sample.mean_speed, sample.completed_trips, sample.trips_since_last
+        );
+
+        for segment in &sample.segments {
+            let _ = writeln!(
+                roads,
+                "{},{},{},{}",
+                sample.seconds, segment.cell[0], segment.cell[1], segment.congestion
+            );
+        }
+    }
+
+    let path = dir.join(format!("{}.csv", name));
+    fs::write(&path, network)?;
+    fs::write(dir.join(format!("{}_roads.csv", name)), roads)?;
+    Ok(path)
+}
@@ -0,0 +1,7 @@
+pub mod analytics_events;
+pub mod event_log;
+pub mod metrics;
+// The server runs on its own thread, which the web build cannot start.
+#[cfg(all(feature = "telemetry", not(target_arch = "wasm32")))]
+pub mod telemetry;
+pub mod trip_stats;
@@ -0,0 +1,127 @@
+use crate::{
+    analytics::trip_stats::TripStats,
+    determinism::determinism::SimTick,
+    graph::congestion::Congestion,
+    schedule::UpdateStage,
+    types::{pedestrian::Pedestrian, road_segment::RoadSegment, vehicle::Vehicle},
+};
+use bevy::prelude::*;
+use serde::Serialize;
+use std::{
+    net::{TcpListener, TcpStream},
+    sync::mpsc::{self, Receiver, RecvTimeoutError, SyncSender},
+    thread,
+    time::Duration,
+};
+use tungstenite::{Message, WebSocket};
+
+const ADDRESS_VAR: &str = "OVERCAST_TELEMETRY_ADDR";
+const DEFAULT_ADDRESS: &str = "127.0.0.1:9001";
+const CONGESTION_EVERY_TICKS: u64 = 30;
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+const CLIENT_TIMEOUT: Duration = Duration::from_secs(1);
+// Frames the server thread has not taken yet. Once this many are waiting, new ones are dropped.
+const FRAME_BUFFER: usize = 64;
+
+pub struct TelemetryPlugin;
+
+impl Plugin for TelemetryPlugin {
+    fn build(&self, app: &mut App) {
+        let address = std::env::var(ADDRESS_VAR).unwrap_or_else(|_| DEFAULT_ADDRESS.to_string());
+
+        match TcpListener::bind(&address) {
+            Ok(listener) => {
+                println!("Streaming telemetry on ws://{}", address);
+                let (sender, receiver) = mpsc::sync_channel(FRAME_BUFFER);
+                thread::spawn(move || serve(listener, receiver));
+
+                app.insert_resource(Telemetry { sender, last_trips: 0 })
+                    .add_systems(Update, publish_telemetry.in_set(UpdateStage::Analyze));
+            }
+            Err(error) => println!("Failed to start telemetry server on {:?}: {}", address, error),
+        }
+    }
+}
+
+#[derive(Resource)]
+struct Telemetry {
+    sender: SyncSender<String>,
+    last_trips: u32,
+}
+
+#[derive(Debug, Serialize)]
+struct SegmentCongestion {
+    cell: [i32; 2],
+    ratio: f32,
+}
+
+#[derive(Debug, Serialize)]
+struct TelemetryFrame {
+    tick: u64,
+    vehicles: usize,
+    pedestrians: usize,
+    trips_completed: u32,
+    trips_this_tick: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    congestion: Option<Vec<SegmentCongestion>>,
+}
+
+fn publish_telemetry(
+    mut telemetry: ResMut<Telemetry>,
+    tick: Res<SimTick>,
+    trips: Res<TripStats>,
+    vehicle_query: Query<(), With<Vehicle>>,
+    pedestrian_query: Query<(), With<Pedestrian>>,
+    congestion_query: Query<(&RoadSegment, &Congestion)>,
+) {
+    let congestion = tick.0.is_multiple_of(CONGESTION_EVERY_TICKS).then(|| {
+        congestion_query
+            .iter()
+            .map(|(segment, congestion)| SegmentCongestion {
+                cell: segment.area.min.pos.to_array(),
+                ratio: congestion.ratio,
+            })
+            .collect()
+    });
+
+    let frame = TelemetryFrame {
+        tick: tick.0,
+        vehicles: vehicle_query.iter().count(),
+        pedestrians: pedestrian_query.iter().count(),
+        trips_completed: trips.total_trips,
+        trips_this_tick: trips.total_trips.saturating_sub(telemetry.last_trips),
+        congestion,
+    };
+    telemetry.last_trips = trips.total_trips;
+
+    if let Ok(json) = serde_json::to_string(&frame) {
+        let _ = telemetry.sender.try_send(json);
+    }
+}
+
+// Runs on its own thread so slow or stalled dashboards never hold up the simulation.
+fn serve(listener: TcpListener, frames: Receiver<String>) {
+    if listener.set_nonblocking(true).is_err() {
+        return;
+    }
+
+    let mut clients: Vec<WebSocket<TcpStream>> = Vec::new();
+
+    loop {
+        while let Ok((stream, _)) = listener.accept() {
+            let configured = stream.set_nonblocking(false).is_ok()
+                && stream.set_read_timeout(Some(CLIENT_TIMEOUT)).is_ok()
+                && stream.set_write_timeout(Some(CLIENT_TIMEOUT)).is_ok();
+
+            if let (true, Ok(client)) = (configured, tungstenite::accept(stream)) {
+                clients.push(client);
+            }
+        }
+
+        match frames.recv_timeout(POLL_INTERVAL) {
+            Ok(frame) => clients.retain_mut(|client| client.send(Message::text(frame.clone())).is_ok()),
+            Err(RecvTimeoutError::Timeout) => {}
+            Err(RecvTimeoutError::Disconnected) => return,
+        }
+    }
+}
@@ -0,0 +1,109 @@
+use crate::{
+    schedule::UpdateStage,
+    types::vehicle::{OnTripCompleted, VehicleKind},
+};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+const RATE_WINDOW_SECONDS: f32 = 60.0;
+const MAX_RECENT_TRIPS: usize = 1000;
+
+pub struct TripStatsPlugin;
+
+impl Plugin for TripStatsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TripStats::default()).add_systems(Update, record_trips.in_set(UpdateStage::Analyze));
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct TripRecord {
+    pub kind: VehicleKind,
+    pub duration: f32,
+    pub distance: f32,
+    pub average_speed: f32,
+    pub completed_at: f32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct TripStats {
+    pub recent: VecDeque<TripRecord>,
+    pub total_trips: u32,
+    pub trips_by_kind: [u32; 4],
+    now: f32,
+}
+
+impl TripStats {
+    pub fn record(&mut self, kind: VehicleKind, duration: f32, distance: f32, completed_at: f32) {
+        if self.recent.len() == MAX_RECENT_TRIPS {
+            self.recent.pop_front();
+        }
+
+        self.recent.push_back(TripRecord {
+            kind,
+            duration,
+            distance,
+            average_speed: if duration > 0.0 { distance / duration } else { 0.0 },
+            completed_at,
+        });
+        self.total_trips += 1;
+        self.trips_by_kind[kind as usize] += 1;
+    }
+
+    fn recent_mean(&self, value: impl Fn(&TripRecord) -> f32) -> f32 {
+        if self.recent.is_empty() {
+            0.0
+        } else {
+            self.recent.iter().map(value).sum::<f32>() / self.recent.len() as f32
+        }
+    }
+
+    pub fn mean_trip_time(&self) -> f32 {
+        self.recent_mean(|trip| trip.duration)
+    }
+
+    pub fn mean_trip_length(&self) -> f32 {
+        self.recent_mean(|trip| trip.distance)
+    }
+
+    pub fn mean_trip_time_of(&self, kind: VehicleKind) -> f32 {
+        let (count, total) = self
+            .recent
+            .iter()
+            .filter(|trip| trip.kind == kind)
+            .fold((0, 0.0), |(count, total), trip| (count + 1, total + trip.duration));
+
+        if count == 0 {
+            0.0
+        } else {
+            total / count as f32
+        }
+    }
+
+    pub fn mean_speed(&self) -> f32 {
+        self.recent_mean(|trip| trip.average_speed)
+    }
+
+    pub fn trips_per_minute(&self) -> f32 {
+        let window = self.now.min(RATE_WINDOW_SECONDS);
+        if window <= 0.0 {
+            return 0.0;
+        }
+
+        let recent = self.recent.iter().rev().take_while(|trip| self.now - trip.completed_at <= RATE_WINDOW_SECONDS).count();
+        recent as f32 * 60.0 / window
+    }
+}
+
+pub fn record_trips(mut event: EventReader<OnTripCompleted>, mut stats: ResMut<TripStats>, time: Res<Time>) {
+    stats.now = time.elapsed_seconds();
+
+    for &OnTripCompleted {
+        kind,
+        duration,
+        distance,
+    } in event.read()
+    {
+        stats.record(kind, duration, distance, time.elapsed_seconds());
+    }
+}
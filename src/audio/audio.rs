@@ -0,0 +1,271 @@
+use crate::{
+    graphics::{camera::PlayerCameraController, weather::ground_focus},
+    schedule::UpdateStage,
+    types::vehicle::Vehicle,
+};
+use bevy::{audio::Volume, prelude::*, utils::HashMap};
+use rand::Rng;
+use std::f32::consts::TAU;
+
+const SAMPLE_RATE: u32 = 22050;
+const AMBIENT_RADIUS: f32 = 40.0;
+const AMBIENT_FULL_COUNT: f32 = 60.0;
+const ENGINE_HEARING_DISTANCE: f32 = 25.0;
+const HORN_HEARING_DISTANCE: f32 = 35.0;
+const HORN_COOLDOWN_SECONDS: f32 = 10.0;
+const BLOCKED_SPEED: f32 = 0.05;
+pub const MAX_ENGINE_VOICES: usize = 24;
+
+pub struct TrafficAudioPlugin;
+
+impl Plugin for TrafficAudioPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TrafficAudioSettings::default())
+            .insert_resource(BlockedVehicles::default())
+            .add_systems(Startup, (load_traffic_sounds, spawn_audio_voices).chain())
+            .add_systems(
+                Update,
+                (update_ambient_noise, update_engine_voices, sound_horns).in_set(UpdateStage::Visualize),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TrafficAudioSettings {
+    pub enabled: bool,
+    pub volume: f32,
+    pub engine_voices: usize,
+    pub horn_after_seconds: f32,
+}
+
+impl Default for TrafficAudioSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            volume: 0.5,
+            engine_voices: 8,
+            horn_after_seconds: 4.0,
+        }
+    }
+}
+
+// The sounds are synthesized at startup so the game ships without audio assets.
+#[derive(Resource, Debug)]
+struct TrafficSounds {
+    engine: Handle<AudioSource>,
+    horn: Handle<AudioSource>,
+}
+
+#[derive(Component, Debug)]
+struct AmbientNoise;
+
+// Engine loops are pooled and handed to the vehicles nearest the camera each frame.
+#[derive(Component, Debug)]
+struct EngineVoice;
+
+// Seconds each vehicle has been stuck. Goes negative after a horn as a cooldown.
+#[derive(Resource, Debug, Default)]
+struct BlockedVehicles(HashMap<Entity, f32>);
+
+fn load_traffic_sounds(mut commands: Commands, mut sources: ResMut<Assets<AudioSource>>) {
+    let ambient = sources.add(wav_source(&ambient_samples()));
+    commands.spawn((
+        AudioBundle {
+            source: ambient,
+            settings: PlaybackSettings::LOOP.with_volume(Volume::ZERO),
+        },
+        AmbientNoise,
+    ));
+
+    commands.insert_resource(TrafficSounds {
+        engine: sources.add(wav_source(&engine_samples())),
+        horn: sources.add(wav_source(&horn_samples())),
+    });
+}
+
+fn spawn_audio_voices(mut commands: Commands, sounds: Res<TrafficSounds>) {
+    for _ in 0..MAX_ENGINE_VOICES {
+        commands.spawn((
+            AudioBundle {
+                source: sounds.engine.clone(),
+                settings: PlaybackSettings::LOOP.with_volume(Volume::ZERO),
+            },
+            EngineVoice,
+        ));
+    }
+}
+
+fn update_ambient_noise(
+    sink_query: Query<&AudioSink, With<AmbientNoise>>,
+    vehicle_query: Query<&Transform, With<Vehicle>>,
+    camera_query: Query<&GlobalTransform, With<PlayerCameraController>>,
+    settings: Res<TrafficAudioSettings>,
+) {
+    let (Ok(sink), Ok(camera)) = (sink_query.get_single(), camera_query.get_single()) else {
+        return;
+    };
+
+    let focus = ground_focus(camera);
+    let nearby = vehicle_query
+        .iter()
+        .filter(|transform| transform.translation.with_y(0.0).distance(focus) <= AMBIENT_RADIUS)
+        .count();
+
+    let density = (nearby as f32 / AMBIENT_FULL_COUNT).min(1.0);
+    sink.set_volume(if settings.enabled { density * settings.volume } else { 0.0 });
+}
+
+fn update_engine_voices(
+    voice_query: Query<&AudioSink, With<EngineVoice>>,
+    vehicle_query: Query<(&Vehicle, &Transform)>,
+    camera_query: Query<&GlobalTransform, With<PlayerCameraController>>,
+    settings: Res<TrafficAudioSettings>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let focus = ground_focus(camera);
+    let budget = if settings.enabled {
+        settings.engine_voices.min(MAX_ENGINE_VOICES)
+    } else {
+        0
+    };
+
+    let mut nearby: Vec<(f32, f32)> = vehicle_query
+        .iter()
+        .filter(|(vehicle, _)| vehicle.parked.is_none())
+        .map(|(vehicle, transform)| (transform.translation.with_y(0.0).distance(focus), vehicle.speed))
+        .filter(|&(distance, _)| distance <= ENGINE_HEARING_DISTANCE)
+        .collect();
+
+    if nearby.len() > budget {
+        nearby.select_nth_unstable_by(budget, |(a, _), (b, _)| a.total_cmp(b));
+        nearby.truncate(budget);
+    }
+
+    let mut assigned = nearby.into_iter();
+    for sink in &voice_query {
+        match assigned.next() {
+            Some((distance, speed)) => {
+                sink.set_volume(attenuation(distance, ENGINE_HEARING_DISTANCE) * settings.volume * 0.5);
+                sink.set_speed(0.8 + speed.clamp(0.0, 2.0) * 0.4);
+            }
+            None => sink.set_volume(0.0),
+        }
+    }
+}
+
+fn sound_horns(
+    mut commands: Commands,
+    mut blocked: ResMut<BlockedVehicles>,
+    vehicle_query: Query<(Entity, &Vehicle, &Transform)>,
+    camera_query: Query<&GlobalTransform, With<PlayerCameraController>>,
+    sounds: Res<TrafficSounds>,
+    settings: Res<TrafficAudioSettings>,
+    time: Res<Time>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let focus = ground_focus(camera);
+
+    blocked.0.retain(|&entity, _| vehicle_query.contains(entity));
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        // Queuing at a signal or stop sign is expected; only traffic that is stuck honks.
+        if vehicle.speed > BLOCKED_SPEED || vehicle.waiting || vehicle.parked.is_some() {
+            if blocked.0.get(&entity).is_some_and(|&stuck| stuck > 0.0) {
+                blocked.0.remove(&entity);
+            }
+            continue;
+        }
+
+        let stuck = blocked.0.entry(entity).or_insert(0.0);
+        *stuck += time.delta_seconds();
+        if *stuck < settings.horn_after_seconds {
+            continue;
+        }
+        *stuck = -HORN_COOLDOWN_SECONDS;
+
+        let distance = transform.translation.with_y(0.0).distance(focus);
+        if !settings.enabled || distance > HORN_HEARING_DISTANCE {
+            continue;
+        }
+
+        commands.spawn(AudioBundle {
+            source: sounds.horn.clone(),
+            settings: PlaybackSettings::DESPAWN
+                .with_volume(Volume::new(attenuation(distance, HORN_HEARING_DISTANCE) * settings.volume)),
+        });
+    }
+}
+
+fn attenuation(distance: f32, max_distance: f32) -> f32 {
+    (1.0 - distance / max_distance).clamp(0.0, 1.0).powi(2)
+}
+
+// Two seconds of low-passed noise with a slow swell, standing in for distant traffic.
+fn ambient_samples() -> Vec<f32> {
+    let mut rng = rand::thread_rng();
+    let count = SAMPLE_RATE as usize * 2;
+    let mut filtered = 0.0;
+
+    (0..count)
+        .map(|i| {
+            filtered += (rng.gen_range(-1.0..1.0) - filtered) * 0.05;
+            let swell = 0.8 + 0.2 * (TAU * i as f32 / count as f32).sin();
+            filtered * swell * 2.0
+        })
+        .collect()
+}
+
+// Whole cycles of every harmonic fit in the buffer so the loop has no seam.
+fn engine_samples() -> Vec<f32> {
+    (0..SAMPLE_RATE)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            [(45.0, 0.5), (90.0, 0.3), (135.0, 0.15), (180.0, 0.05)]
+                .iter()
+                .map(|&(frequency, gain)| (TAU * frequency * t).sin() * gain)
+                .sum()
+        })
+        .collect()
+}
+
+fn horn_samples() -> Vec<f32> {
+    let count = SAMPLE_RATE as usize * 3 / 5;
+
+    (0..count)
+        .map(|i| {
+            let t = i as f32 / SAMPLE_RATE as f32;
+            let envelope = (i.min(count - i) as f32 / 400.0).min(1.0);
+            let tone = [400.0, 500.0].iter().map(|frequency| (TAU * frequency * t).sin().signum()).sum::<f32>();
+            tone * 0.2 * envelope
+        })
+        .collect()
+}
+
+fn wav_source(samples: &[f32]) -> AudioSource {
+    let data_len = samples.len() as u32 * 2;
+    let mut bytes = Vec::with_capacity(44 + data_len as usize);
+
+    bytes.extend_from_slice(b"RIFF");
+    bytes.extend_from_slice(&(36 + data_len).to_le_bytes());
+    bytes.extend_from_slice(b"WAVEfmt ");
+    bytes.extend_from_slice(&16u32.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&1u16.to_le_bytes());
+    bytes.extend_from_slice(&SAMPLE_RATE.to_le_bytes());
+    bytes.extend_from_slice(&(SAMPLE_RATE * 2).to_le_bytes());
+    bytes.extend_from_slice(&2u16.to_le_bytes());
+    bytes.extend_from_slice(&16u16.to_le_bytes());
+    bytes.extend_from_slice(b"data");
+    bytes.extend_from_slice(&data_len.to_le_bytes());
+
+    for sample in samples {
+        bytes.extend_from_slice(&((sample.clamp(-1.0, 1.0) * i16::MAX as f32) as i16).to_le_bytes());
+    }
+
+    AudioSource { bytes: bytes.into() }
+}
@@ -0,0 +1,202 @@
+use crate::{
+    capture::capture_events::*,
+    clock::unix_seconds,
+    graphics::{
+        camera::{CameraBookmarks, PlayerCameraController},
+        weather::TimeOfDay,
+    },
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    ui::notify_events::Notify,
+};
+use bevy::{prelude::*, render::view::screenshot::ScreenshotManager, window::PrimaryWindow};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+const SCREENSHOT_DIR: &str = "screenshots";
+const DEFAULT_TIMELAPSE_MINUTES: f32 = 30.0;
+
+pub struct CapturePlugin;
+
+impl Plugin for CapturePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestScreenshot>()
+            .add_event::<OnScreenshotSaved>()
+            .insert_resource(ScreenCapture::default())
+            .insert_resource(TimelapseSettings::default())
+            .add_systems(
+                Update,
+                (
+                    screenshot_on_key_press.in_set(UpdateStage::UserInput),
+                    (queue_screenshots, queue_timelapse_frames, take_screenshots).chain().in_set(UpdateStage::Visualize),
+                ),
+            );
+    }
+}
+
+// Frames are saved every `interval_minutes` of in-game time, viewed from the camera bookmark in
+// `bookmark` (1 to 9).
+#[derive(Resource, Debug)]
+pub struct TimelapseSettings {
+    pub enabled: bool,
+    pub interval_minutes: f32,
+    pub bookmark: usize,
+    elapsed_minutes: f32,
+    last_hour: Option<f32>,
+    folder: Option<PathBuf>,
+    frame: u32,
+}
+
+impl Default for TimelapseSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            interval_minutes: DEFAULT_TIMELAPSE_MINUTES,
+            bookmark: 1,
+            elapsed_minutes: 0.0,
+            last_hour: None,
+            folder: None,
+            frame: 0,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PendingCapture {
+    path: PathBuf,
+    timelapse: bool,
+    restore: Option<Transform>,
+    frames: u8,
+    written: bool,
+}
+
+// A capture hides the egui overlays for the frame it is taken in. The request waits a frame first,
+// since the UI may already have been drawn in the frame the capture was asked for.
+#[derive(Resource, Debug, Default)]
+pub struct ScreenCapture {
+    pending: Option<PendingCapture>,
+}
+
+pub fn ui_visible(capture: Option<Res<ScreenCapture>>) -> bool {
+    capture.is_none_or(|capture| capture.pending.is_none())
+}
+
+fn screenshot_on_key_press(actions: Actions, mut event: EventWriter<RequestScreenshot>) {
+    if actions.just_pressed(Action::Screenshot) {
+        event.send(RequestScreenshot);
+    }
+}
+
+fn queue_screenshots(mut requests: EventReader<RequestScreenshot>, mut capture: ResMut<ScreenCapture>) {
+    if requests.read().count() == 0 || capture.pending.is_some() {
+        return;
+    }
+
+    capture.pending = Some(PendingCapture {
+        path: Path::new(SCREENSHOT_DIR).join(format!("screenshot_{}.png", unix_seconds())),
+        timelapse: false,
+        restore: None,
+        frames: 3,
+        written: false,
+    });
+}
+
+// Each timelapse gets its own folder, so frames from different runs never interleave.
+fn queue_timelapse_frames(
+    mut settings: ResMut<TimelapseSettings>,
+    mut capture: ResMut<ScreenCapture>,
+    time_of_day: Res<TimeOfDay>,
+    bookmarks: Res<CameraBookmarks>,
+    mut camera_query: Query<&mut Transform, With<PlayerCameraController>>,
+) {
+    if !settings.enabled {
+        settings.last_hour = None;
+        settings.folder = None;
+        settings.frame = 0;
+        return;
+    }
+
+    let passed = settings.last_hour.map_or(0.0, |last| (time_of_day.hour - last).rem_euclid(24.0) * 60.0);
+    settings.last_hour = Some(time_of_day.hour);
+    settings.elapsed_minutes += passed;
+
+    let first = settings.folder.is_none();
+    if (!first && settings.elapsed_minutes < settings.interval_minutes) || capture.pending.is_some() {
+        return;
+    }
+
+    let Some(bookmark) = bookmarks.slot(settings.bookmark.saturating_sub(1)) else {
+        return;
+    };
+
+    let Ok(mut transform) = camera_query.get_single_mut() else {
+        return;
+    };
+
+    settings.elapsed_minutes = 0.0;
+    settings.frame += 1;
+    let folder = settings
+        .folder
+        .get_or_insert_with(|| Path::new(SCREENSHOT_DIR).join(format!("timelapse_{}", unix_seconds())))
+        .clone();
+
+    capture.pending = Some(PendingCapture {
+        path: folder.join(format!("frame_{:05}.png", settings.frame)),
+        timelapse: true,
+        restore: Some(*transform),
+        frames: 3,
+        written: false,
+    });
+    *transform = bookmark;
+}
+
+fn take_screenshots(
+    mut capture: ResMut<ScreenCapture>,
+    mut manager: ResMut<ScreenshotManager>,
+    window_query: Query<Entity, With<PrimaryWindow>>,
+    mut camera_query: Query<&mut Transform, With<PlayerCameraController>>,
+    mut saved: EventWriter<OnScreenshotSaved>,
+    mut notify: EventWriter<Notify>,
+) {
+    let Some(pending) = capture.pending.as_mut() else {
+        return;
+    };
+
+    pending.frames -= 1;
+
+    match pending.frames {
+        1 => {
+            let written = window_query.get_single().map_err(|error| error.to_string()).and_then(|window| {
+                fs::create_dir_all(pending.path.parent().unwrap_or(Path::new(SCREENSHOT_DIR)))
+                    .map_err(|error| error.to_string())
+                    .and_then(|_| manager.save_screenshot_to_disk(window, &pending.path).map_err(|error| error.to_string()))
+            });
+
+            match written {
+                Ok(()) => pending.written = true,
+                Err(error) => {
+                    println!("Failed to save screenshot to {:?}: {}", pending.path, error);
+                    notify.send(Notify::error(format!("Failed to save screenshot: {}", error)));
+                }
+            }
+        }
+        0 => {
+            if let Some(restore) = pending.restore {
+                if let Ok(mut transform) = camera_query.get_single_mut() {
+                    *transform = restore;
+                }
+            }
+
+            if pending.written {
+                saved.send(OnScreenshotSaved {
+                    path: pending.path.display().to_string(),
+                    timelapse: pending.timelapse,
+                });
+            }
+            capture.pending = None;
+        }
+        _ => {}
+    }
+}
@@ -0,0 +1,10 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct RequestScreenshot;
+
+#[derive(Event, Debug)]
+pub struct OnScreenshotSaved {
+    pub path: String,
+    pub timelapse: bool,
+}
@@ -0,0 +1,2 @@
+pub mod capture;
+pub mod capture_events;
@@ -0,0 +1,7 @@
+use bevy::utils::SystemTime;
+
+// Seconds since the Unix epoch, for stamping file names and records. Bevy's clock is used because
+// `std::time::SystemTime::now` panics in the web build.
+pub fn unix_seconds() -> u64 {
+    SystemTime::now().duration_since(SystemTime::UNIX_EPOCH).map_or(0, |elapsed| elapsed.as_secs())
+}
@@ -0,0 +1,236 @@
+use crate::{
+    schedule::UpdateStage,
+    types::{pedestrian::Pedestrian, vehicle::Vehicle},
+};
+use bevy::{prelude::*, time::TimeUpdateStrategy};
+use rand::{rngs::StdRng, Rng, RngCore, SeedableRng};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::hash_map::DefaultHasher,
+    fs::{self, File},
+    hash::{Hash, Hasher},
+    io::{BufReader, BufWriter},
+    time::Duration,
+};
+
+const SEED_VAR: &str = "OVERCAST_SEED";
+const SEED_ARG: &str = "--seed";
+const FIXED_STEP_VAR: &str = "OVERCAST_FIXED_STEP";
+const FIXED_STEP_ARG: &str = "--fixed-step";
+const SNAPSHOT_TICK_VAR: &str = "OVERCAST_SNAPSHOT_TICK";
+const SNAPSHOT_DIR: &str = "assets/debug";
+const POSITION_QUANTUM: f32 = 1000.0;
+
+pub struct DeterminismPlugin;
+
+impl Plugin for DeterminismPlugin {
+    fn build(&self, app: &mut App) {
+        let seed = launch_option(SEED_ARG, SEED_VAR).unwrap_or_else(|| rand::thread_rng().gen());
+        let fixed_step = launch_option::<f64>(FIXED_STEP_ARG, FIXED_STEP_VAR).filter(|&hz| hz > 0.0);
+        let snapshot_tick = std::env::var(SNAPSHOT_TICK_VAR).ok().and_then(|tick| tick.parse().ok());
+        #[cfg(not(target_arch = "wasm32"))]
+        println!(
+            "Simulation seed: {} (pass {} or set {} to reproduce)",
+            seed, SEED_ARG, SEED_VAR
+        );
+        #[cfg(target_arch = "wasm32")]
+        println!("Simulation seed: {}", seed);
+
+        // Every frame advances the clock by the same amount, so identical seeds and inputs replay
+        // identically regardless of how fast frames are rendered.
+        if let Some(hz) = fixed_step {
+            println!("Fixed timestep: {} steps per second", hz);
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(1.0 / hz)));
+        }
+
+        app.insert_resource(SimRng::new(seed))
+            .insert_resource(SimulationSettings { seed, fixed_step })
+            .insert_resource(SimTick::default())
+            .insert_resource(SnapshotSettings { tick: snapshot_tick })
+            .add_systems(
+                Update,
+                (
+                    advance_tick.in_set(UpdateStage::UpdateView),
+                    snapshot_simulation.in_set(UpdateStage::Analyze).run_if(snapshot_due),
+                ),
+            );
+    }
+}
+
+#[derive(Resource)]
+pub struct SimRng {
+    pub seed: u64,
+    rng: StdRng,
+}
+
+impl SimRng {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            rng: StdRng::seed_from_u64(seed),
+        }
+    }
+
+    pub fn rng(&mut self) -> &mut StdRng {
+        &mut self.rng
+    }
+
+    pub fn fingerprint(&self) -> u64 {
+        self.rng.clone().next_u64()
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct SimulationSettings {
+    pub seed: u64,
+    pub fixed_step: Option<f64>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SimTick(pub u64);
+
+// Command line arguments (`--seed 42` or `--seed=42`) take priority over environment variables.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn launch_option<T: std::str::FromStr>(arg: &str, var: &str) -> Option<T> {
+    let args: Vec<String> = std::env::args().collect();
+    let from_args = args.iter().enumerate().find_map(|(index, current)| match current.strip_prefix(arg) {
+        Some("") => args.get(index + 1).cloned(),
+        Some(value) => value.strip_prefix('=').map(str::to_string),
+        None => None,
+    });
+
+    from_args.or_else(|| std::env::var(var).ok()).and_then(|value| value.parse().ok())
+}
+
+// The web build has no command line or environment, so launch options are native only and it always
+// runs with the defaults.
+#[cfg(target_arch = "wasm32")]
+pub fn launch_option<T: std::str::FromStr>(_arg: &str, _var: &str) -> Option<T> {
+    None
+}
+
+#[derive(Resource, Debug)]
+struct SnapshotSettings {
+    tick: Option<u64>,
+}
+
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct SnapshotEntry {
+    entity: u64,
+    kind: String,
+    hash: u64,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct Snapshot {
+    seed: u64,
+    tick: u64,
+    rng: u64,
+    entries: Vec<SnapshotEntry>,
+}
+
+impl Snapshot {
+    fn first_divergence(&self, other: &Snapshot) -> Option<String> {
+        if self.rng != other.rng {
+            return Some(format!("rng state {} != {}", self.rng, other.rng));
+        }
+
+        for (index, ours) in self.entries.iter().enumerate() {
+            match other.entries.get(index) {
+                Some(theirs) if theirs == ours => {}
+                Some(theirs) => {
+                    return Some(format!(
+                        "{} {} (hash {}) != {} {} (hash {})",
+                        ours.kind,
+                        Entity::from_bits(ours.entity),
+                        ours.hash,
+                        theirs.kind,
+                        Entity::from_bits(theirs.entity),
+                        theirs.hash
+                    ));
+                }
+                None => return Some(format!("{} {} is missing", ours.kind, Entity::from_bits(ours.entity))),
+            }
+        }
+
+        other
+            .entries
+            .get(self.entries.len())
+            .map(|extra| format!("{} {} is unexpected", extra.kind, Entity::from_bits(extra.entity)))
+    }
+}
+
+fn quantize(position: Vec3) -> [i64; 3] {
+    (position * POSITION_QUANTUM).round().as_i64vec3().to_array()
+}
+
+fn entity_hash(path: &[Entity], path_index: usize, translation: Vec3) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    quantize(translation).hash(&mut hasher);
+    path_index.hash(&mut hasher);
+    path.iter().for_each(|step| step.to_bits().hash(&mut hasher));
+    hasher.finish()
+}
+
+fn advance_tick(mut tick: ResMut<SimTick>) {
+    tick.0 += 1;
+}
+
+fn snapshot_due(settings: Res<SnapshotSettings>, tick: Res<SimTick>) -> bool {
+    settings.tick == Some(tick.0)
+}
+
+fn snapshot_simulation(
+    vehicle_query: Query<(Entity, &Vehicle, &Transform)>,
+    pedestrian_query: Query<(Entity, &Pedestrian, &Transform)>,
+    sim_rng: Res<SimRng>,
+    tick: Res<SimTick>,
+) {
+    let mut entries: Vec<SnapshotEntry> = vehicle_query
+        .iter()
+        .map(|(entity, vehicle, transform)| SnapshotEntry {
+            entity: entity.to_bits(),
+            kind: "vehicle".to_string(),
+            hash: entity_hash(&vehicle.path, vehicle.path_index, transform.translation),
+        })
+        .chain(pedestrian_query.iter().map(|(entity, pedestrian, transform)| SnapshotEntry {
+            entity: entity.to_bits(),
+            kind: "pedestrian".to_string(),
+            hash: entity_hash(&pedestrian.path, pedestrian.path_index, transform.translation),
+        }))
+        .collect();
+    entries.sort_by_key(|entry| entry.entity);
+
+    let snapshot = Snapshot {
+        seed: sim_rng.seed,
+        tick: tick.0,
+        rng: sim_rng.fingerprint(),
+        entries,
+    };
+
+    let path = format!("{}/snapshot_{}_{}.json", SNAPSHOT_DIR, snapshot.seed, snapshot.tick);
+
+    if let Ok(file) = File::open(&path) {
+        match serde_json::from_reader::<_, Snapshot>(BufReader::new(file)) {
+            Ok(previous) => match previous.first_divergence(&snapshot) {
+                Some(divergence) => println!("Snapshot diverged from {:?} at tick {}: {}", path, snapshot.tick, divergence),
+                None => println!("Snapshot matches {:?} at tick {}", path, snapshot.tick),
+            },
+            Err(error) => println!("Failed to parse snapshot {:?}: {}", path, error),
+        }
+        return;
+    }
+
+    if fs::create_dir_all(SNAPSHOT_DIR).is_ok() {
+        if let Ok(file) = File::create(&path) {
+            if serde_json::to_writer(BufWriter::new(file), &snapshot).is_ok() {
+                println!(
+                    "Recorded {} entities at tick {} to {:?}",
+                    snapshot.entries.len(),
+                    snapshot.tick,
+                    path
+                );
+            }
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::{
+    determinism::determinism::SimRng,
+    economy::demand::WORKFORCE_SHARE,
+    graph::road_graph_events::*,
+    graphics::weather::TimeOfDay,
+    schedule::UpdateStage,
+    types::building::{Building, Zone},
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+
+const EARLIEST_DEPARTURE: f32 = 6.5;
+const LATEST_DEPARTURE: f32 = 9.0;
+const MIN_SHIFT_HOURS: f32 = 8.0;
+const MAX_SHIFT_HOURS: f32 = 9.5;
+// Commuters who have not left within this many hours of their usual time skip the trip that day.
+const LATE_HOURS: f32 = 2.0;
+
+pub struct CommutePlugin;
+
+impl Plugin for CommutePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Commutes::default()).add_systems(
+            Update,
+            (
+                match_commuters.in_set(UpdateStage::UpdatePathing),
+                start_new_day.in_set(UpdateStage::Analyze),
+            ),
+        );
+    }
+}
+
+// A group of residents of one home who all work at the same place and keep the same hours.
+#[derive(Debug, Clone)]
+pub struct Commute {
+    pub home: Entity,
+    pub work: Entity,
+    pub commuters: u32,
+    pub depart_hour: f32,
+    pub return_hour: f32,
+    departed: u32,
+    returned: u32,
+}
+
+impl Commute {
+    fn outbound_due(&self, hour: f32) -> u32 {
+        match (self.depart_hour..self.depart_hour + LATE_HOURS).contains(&hour) {
+            true => self.commuters - self.departed,
+            false => 0,
+        }
+    }
+
+    fn return_due(&self, hour: f32) -> u32 {
+        match (self.return_hour..self.return_hour + LATE_HOURS).contains(&hour) {
+            true => self.departed - self.returned,
+            false => 0,
+        }
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct Commutes {
+    commutes: Vec<Commute>,
+    last_hour: f32,
+}
+
+impl Commutes {
+    pub fn commuters(&self) -> u32 {
+        self.commutes.iter().map(|commute| commute.commuters).sum()
+    }
+
+    pub fn living_at(&self, home: Entity) -> u32 {
+        self.commutes.iter().filter(|commute| commute.home == home).map(|commute| commute.commuters).sum()
+    }
+
+    pub fn working_at(&self, work: Entity) -> u32 {
+        self.commutes.iter().filter(|commute| commute.work == work).map(|commute| commute.commuters).sum()
+    }
+
+    // Commuters who should be on the road right now but have not set off yet.
+    pub fn due(&self, hour: f32) -> u32 {
+        self.commutes.iter().map(|commute| commute.outbound_due(hour) + commute.return_due(hour)).sum()
+    }
+
+    // Picks one due commuter, weighted by how many are due in each group, and marks the trip as taken.
+    pub fn next_trip(&mut self, hour: f32, rng: &mut impl Rng) -> Option<(Entity, Entity)> {
+        let weights = self.commutes.iter().flat_map(|commute| [commute.outbound_due(hour), commute.return_due(hour)]);
+        let index = WeightedIndex::new(weights).ok()?.sample(rng);
+        let commute = &mut self.commutes[index / 2];
+
+        match index % 2 {
+            0 => {
+                commute.departed += 1;
+                Some((commute.home, commute.work))
+            }
+            _ => {
+                commute.returned += 1;
+                Some((commute.work, commute.home))
+            }
+        }
+    }
+}
+
+// Residents are matched to the nearest workplaces with open jobs whenever buildings or roads
+// change. Only buildings connected to a road take part. Groups that survive a rematch keep their
+// hours and today's progress.
+fn match_commuters(
+    mut commutes: ResMut<Commutes>,
+    building_query: Query<(Entity, &Building)>,
+    mut sim_rng: ResMut<SimRng>,
+    mut building_spawned: EventReader<OnBuildingSpawned>,
+    mut building_destroyed: EventReader<OnBuildingDestroyed>,
+    mut road_spawned: EventReader<OnRoadSpawned>,
+    mut road_destroyed: EventReader<OnRoadDestroyed>,
+) {
+    let removed: HashSet<Entity> = building_destroyed.read().map(|event| event.0).collect();
+    let changed = building_spawned.read().count() + road_spawned.read().count() + road_destroyed.read().count();
+
+    if changed == 0 && removed.is_empty() {
+        return;
+    }
+
+    let mut buildings: Vec<(Entity, &Building)> = building_query
+        .iter()
+        .filter(|(entity, building)| !removed.contains(entity) && !building.roads.is_empty())
+        .collect();
+    buildings.sort_by_key(|(entity, _)| *entity);
+
+    let mut openings: Vec<(Entity, Vec3, u32)> = buildings
+        .iter()
+        .filter(|(_, building)| building.zone() != Zone::Residential && building.jobs() > 0)
+        .map(|(entity, building)| (*entity, building.pos(), building.jobs()))
+        .collect();
+
+    let previous: HashMap<(Entity, Entity), Commute> =
+        commutes.commutes.drain(..).map(|commute| ((commute.home, commute.work), commute)).collect();
+    let rng = sim_rng.rng();
+
+    for (home, building) in buildings.iter().filter(|(_, building)| building.zone() == Zone::Residential) {
+        let mut workers = (building.residents() as f32 * WORKFORCE_SHARE).round() as u32;
+        let mut nearest: Vec<usize> = (0..openings.len()).filter(|&index| openings[index].2 > 0).collect();
+        nearest.sort_by(|&a, &b| {
+            openings[a].1.distance_squared(building.pos()).total_cmp(&openings[b].1.distance_squared(building.pos()))
+        });
+
+        for index in nearest {
+            if workers == 0 {
+                break;
+            }
+
+            let (work, _, open) = &mut openings[index];
+            let hired = workers.min(*open);
+            *open -= hired;
+            workers -= hired;
+
+            let commute = match previous.get(&(*home, *work)) {
+                Some(kept) => Commute {
+                    commuters: hired,
+                    departed: kept.departed.min(hired),
+                    returned: kept.returned.min(hired),
+                    ..kept.clone()
+                },
+                None => {
+                    let depart_hour = rng.gen_range(EARLIEST_DEPARTURE..LATEST_DEPARTURE);
+                    Commute {
+                        home: *home,
+                        work: *work,
+                        commuters: hired,
+                        depart_hour,
+                        return_hour: depart_hour + rng.gen_range(MIN_SHIFT_HOURS..MAX_SHIFT_HOURS),
+                        departed: 0,
+                        returned: 0,
+                    }
+                }
+            };
+            commutes.commutes.push(commute);
+        }
+    }
+}
+
+fn start_new_day(mut commutes: ResMut<Commutes>, time_of_day: Res<TimeOfDay>) {
+    if time_of_day.hour < commutes.last_hour {
+        for commute in &mut commutes.commutes {
+            commute.departed = 0;
+            commute.returned = 0;
+        }
+    }
+
+    commutes.last_hour = time_of_day.hour;
+}
@@ -0,0 +1,144 @@
+use crate::{
+    schedule::UpdateStage,
+    types::{
+        building::{Building, BuildingKind, Zone},
+        vehicle::{OnTripCompleted, OnTripFailed},
+    },
+};
+use bevy::prelude::*;
+
+pub const WORKFORCE_SHARE: f32 = 0.6;
+const COMMERCIAL_SHARE: f32 = 0.25;
+const INDUSTRIAL_SHARE: f32 = 0.35;
+const STARTER_JOBS: f32 = 20.0;
+const DEMAND_SCALE: f32 = 40.0;
+// Trips older than this barely count towards the success rate.
+const TRIP_WINDOW_SECONDS: f32 = 120.0;
+
+pub struct DemandPlugin;
+
+impl Plugin for DemandPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ZoneDemand::default()).add_systems(Update, compute_zone_demand.in_set(UpdateStage::Analyze));
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ZoneDemand {
+    pub residential: f32,
+    pub commercial: f32,
+    pub industrial: f32,
+    pub population: f32,
+    pub jobs: f32,
+    pub trip_success: f32,
+    recent_completed: f32,
+    recent_failed: f32,
+}
+
+impl Default for ZoneDemand {
+    fn default() -> Self {
+        Self {
+            residential: 0.0,
+            commercial: 0.0,
+            industrial: 0.0,
+            population: 0.0,
+            jobs: 0.0,
+            trip_success: 1.0,
+            recent_completed: 0.0,
+            recent_failed: 0.0,
+        }
+    }
+}
+
+impl ZoneDemand {
+    pub fn of(&self, zone: Zone) -> f32 {
+        match zone {
+            Zone::Residential => self.residential,
+            Zone::Commercial => self.commercial,
+            Zone::Industrial => self.industrial,
+        }
+    }
+}
+
+#[derive(Default)]
+struct ZoneCapacity {
+    occupied: f32,
+    total: f32,
+}
+
+impl ZoneCapacity {
+    fn vacancy(&self) -> f32 {
+        if self.total > 0.0 {
+            1.0 - self.occupied / self.total
+        } else {
+            0.0
+        }
+    }
+}
+
+// A city where trips keep failing to find a route is a less attractive place to build, so
+// positive demand shrinks with the trip success rate, down to half when nothing gets through.
+fn demand(shortfall: f32, vacancy: f32, trip_success: f32) -> f32 {
+    let demand = ((shortfall / DEMAND_SCALE).clamp(-1.0, 1.0) - vacancy).clamp(-1.0, 1.0);
+    if demand > 0.0 {
+        demand * (0.5 + 0.5 * trip_success)
+    } else {
+        demand
+    }
+}
+
+fn compute_zone_demand(
+    building_query: Query<&Building>,
+    mut demand_state: ResMut<ZoneDemand>,
+    mut completed: EventReader<OnTripCompleted>,
+    mut failed: EventReader<OnTripFailed>,
+    time: Res<Time>,
+) {
+    let decay = (-time.delta_seconds() / TRIP_WINDOW_SECONDS).exp();
+    let recent_completed = demand_state.recent_completed * decay + completed.read().count() as f32;
+    let recent_failed = demand_state.recent_failed * decay + failed.read().count() as f32;
+    let trip_success = if recent_completed + recent_failed > 0.0 {
+        recent_completed / (recent_completed + recent_failed)
+    } else {
+        1.0
+    };
+
+    let mut housing = ZoneCapacity::default();
+    let mut shops = ZoneCapacity::default();
+    let mut industry = ZoneCapacity::default();
+
+    for building in building_query.iter().filter(|building| building.kind != BuildingKind::Parking) {
+        let capacity = match building.zone() {
+            Zone::Residential => &mut housing,
+            Zone::Commercial => &mut shops,
+            Zone::Industrial => &mut industry,
+        };
+
+        capacity.total += building.occupants();
+        if !building.roads.is_empty() {
+            capacity.occupied += building.occupants();
+        }
+    }
+
+    let population = housing.occupied;
+    let jobs = shops.occupied + industry.occupied;
+
+    *demand_state = ZoneDemand {
+        residential: demand(
+            jobs + STARTER_JOBS - population * WORKFORCE_SHARE,
+            housing.vacancy(),
+            trip_success,
+        ),
+        commercial: demand(population * COMMERCIAL_SHARE - shops.occupied, shops.vacancy(), trip_success),
+        industrial: demand(
+            population * INDUSTRIAL_SHARE - industry.occupied,
+            industry.vacancy(),
+            trip_success,
+        ),
+        population,
+        jobs,
+        trip_success,
+        recent_completed,
+        recent_failed,
+    };
+}
@@ -0,0 +1,145 @@
+use crate::{
+    economy::economy_events::*,
+    economy::{commute::CommutePlugin, demand::DemandPlugin, growth::GrowthPlugin},
+    graphics::weather::TimeOfDay,
+    grid::grid_area::GridArea,
+    schedule::UpdateStage,
+    types::building::{Building, BuildingKind},
+    ui::notify_events::Notify,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+const STARTING_FUNDS: i64 = 50_000;
+const ROAD_COST_PER_CELL: i64 = 25;
+const BRIDGE_COST_PER_CELL: i64 = 120;
+const BUILDING_COST_PER_CELL: i64 = 100;
+const HOURS_PER_DAY: f32 = 24.0;
+const LOW_FUNDS: i64 = 5_000;
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins((DemandPlugin, CommutePlugin, GrowthPlugin))
+            .insert_resource(Funds::new(STARTING_FUNDS))
+            .add_event::<GrantFunds>()
+            .add_event::<SpendFunds>()
+            .add_event::<OnInsufficientFunds>()
+            .add_systems(
+                Update,
+                (
+                    (apply_fund_grants, apply_spending).in_set(UpdateStage::HighLevelSideEffects),
+                    (collect_taxes, warn_low_funds).chain().in_set(UpdateStage::Analyze),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Funds {
+    pub balance: i64,
+    pub income_per_day: i64,
+    unpaid_taxes: f32,
+    last_hour: Option<f32>,
+}
+
+impl Funds {
+    pub fn new(balance: i64) -> Self {
+        Self {
+            balance,
+            income_per_day: 0,
+            unpaid_taxes: 0.0,
+            last_hour: None,
+        }
+    }
+
+    pub fn can_afford(&self, cost: i64) -> bool {
+        self.balance >= cost
+    }
+}
+
+// What a tool needs to charge for something it builds: the balance to check against, and the events
+// that pay for it or report that the player can't.
+#[derive(SystemParam)]
+pub struct Payment<'w> {
+    funds: Res<'w, Funds>,
+    spender: EventWriter<'w, SpendFunds>,
+    rejected: EventWriter<'w, OnInsufficientFunds>,
+}
+
+impl Payment<'_> {
+    pub fn can_afford(&self, cost: i64) -> bool {
+        self.funds.can_afford(cost)
+    }
+
+    pub fn spend(&mut self, cost: i64) {
+        self.spender.send(SpendFunds(cost));
+    }
+
+    pub fn reject(&mut self, cost: i64) {
+        self.rejected.send(OnInsufficientFunds { cost });
+    }
+}
+
+pub fn road_cost(cells: i64) -> i64 {
+    cells * ROAD_COST_PER_CELL
+}
+
+pub fn bridge_cost(cells: i64) -> i64 {
+    cells * BRIDGE_COST_PER_CELL
+}
+
+pub fn building_cost(area: GridArea) -> i64 {
+    area.cell_count() * BUILDING_COST_PER_CELL
+}
+
+fn daily_tax_per_cell(kind: BuildingKind) -> i64 {
+    match kind {
+        BuildingKind::House => 4,
+        BuildingKind::Shop => 8,
+        BuildingKind::Office => 12,
+        BuildingKind::Factory => 10,
+        BuildingKind::Parking => 2,
+        BuildingKind::Stadium => 6,
+        BuildingKind::Mall => 10,
+    }
+}
+
+fn apply_fund_grants(mut event: EventReader<GrantFunds>, mut funds: ResMut<Funds>) {
+    for &GrantFunds(amount) in event.read() {
+        funds.balance += amount;
+    }
+}
+
+fn apply_spending(mut event: EventReader<SpendFunds>, mut funds: ResMut<Funds>) {
+    for &SpendFunds(amount) in event.read() {
+        funds.balance -= amount;
+    }
+}
+
+// Taxes accrue with the in-game clock rather than real time, so pausing the day cycle also
+// pauses income.
+fn collect_taxes(mut funds: ResMut<Funds>, time_of_day: Res<TimeOfDay>, building_query: Query<&Building>) {
+    funds.income_per_day =
+        building_query.iter().map(|building| building.area.cell_count() * daily_tax_per_cell(building.kind)).sum();
+
+    let hour = time_of_day.hour;
+    let elapsed_hours = funds.last_hour.map_or(0.0, |last| (hour - last).rem_euclid(HOURS_PER_DAY));
+    funds.last_hour = Some(hour);
+
+    funds.unpaid_taxes += funds.income_per_day as f32 * elapsed_hours / HOURS_PER_DAY;
+    let paid = funds.unpaid_taxes.floor();
+    funds.unpaid_taxes -= paid;
+    funds.balance += paid as i64;
+}
+
+// Warns once when the balance drops below the low funds mark, and again only after it has recovered.
+fn warn_low_funds(funds: Res<Funds>, mut low: Local<bool>, mut notify: EventWriter<Notify>) {
+    let is_low = funds.balance < LOW_FUNDS;
+
+    if is_low && !*low {
+        notify.send(Notify::warning(format!("Funds are running low (${})", funds.balance)));
+    }
+
+    *low = is_low;
+}
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct GrantFunds(pub i64);
+
+#[derive(Event, Debug)]
+pub struct SpendFunds(pub i64);
+
+#[derive(Event, Debug)]
+pub struct OnInsufficientFunds {
+    pub cost: i64,
+}
@@ -0,0 +1,195 @@
+use crate::{
+    determinism::determinism::SimRng,
+    economy::demand::ZoneDemand,
+    graphics::building_mesh::height_fraction,
+    grid::{
+        grid::Grid, grid_area::GridArea, grid_cell::GridCell, land_value::LandLayers, orientation::GAxis, terrain::Terrain,
+    },
+    schedule::UpdateStage,
+    tools::building_tool::{RequestBuilding, MAX_BUILDING_RELIEF},
+    types::{
+        building::{BuildingKind, Zone},
+        road_segment::RoadSegment,
+    },
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+
+const DEFAULT_INTERVAL_SECONDS: f32 = 8.0;
+const DEFAULT_MIN_DEMAND: f32 = 0.2;
+const LOT_SIZE: i32 = 2;
+const PLACEMENT_ATTEMPTS: usize = 16;
+const SEED_CANDIDATES: usize = 6;
+
+pub struct GrowthPlugin;
+
+impl Plugin for GrowthPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GrowthSettings::default()).add_systems(
+            Update,
+            grow_buildings
+                .in_set(UpdateStage::HighLevelSideEffects)
+                .run_if(|settings: Res<GrowthSettings>| settings.enabled),
+        );
+    }
+}
+
+// Growth pressure builds up with the strongest zone demand, so a city in high demand grows a lot
+// faster than one that is only just short of homes or jobs.
+#[derive(Resource, Debug)]
+pub struct GrowthSettings {
+    pub enabled: bool,
+    pub interval_seconds: f32,
+    pub min_demand: f32,
+}
+
+impl Default for GrowthSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: DEFAULT_INTERVAL_SECONDS,
+            min_demand: DEFAULT_MIN_DEMAND,
+        }
+    }
+}
+
+fn growth_kind(zone: Zone, rng: &mut impl Rng) -> BuildingKind {
+    match zone {
+        Zone::Residential => BuildingKind::House,
+        Zone::Commercial if rng.gen_bool(0.5) => BuildingKind::Shop,
+        Zone::Commercial => BuildingKind::Office,
+        Zone::Industrial => BuildingKind::Factory,
+    }
+}
+
+// A lot on one side of the road, starting at `cell` along the road's edge and extending away from it.
+fn lot_beside(segment: &RoadSegment, cell: i32, far_side: bool) -> GridArea {
+    let area = segment.area();
+    let (min, max) = match (segment.orientation, far_side) {
+        (GAxis::Z, false) => (
+            IVec2::new(area.min.pos.x - LOT_SIZE, cell),
+            IVec2::new(area.min.pos.x - 1, cell + LOT_SIZE - 1),
+        ),
+        (GAxis::Z, true) => (
+            IVec2::new(area.max.pos.x + 1, cell),
+            IVec2::new(area.max.pos.x + LOT_SIZE, cell + LOT_SIZE - 1),
+        ),
+        (GAxis::X, false) => (
+            IVec2::new(cell, area.min.pos.y - LOT_SIZE),
+            IVec2::new(cell + LOT_SIZE - 1, area.min.pos.y - 1),
+        ),
+        (GAxis::X, true) => (
+            IVec2::new(cell, area.max.pos.y + 1),
+            IVec2::new(cell + LOT_SIZE - 1, area.max.pos.y + LOT_SIZE),
+        ),
+    };
+
+    GridArea::new(GridCell::new(min.x, min.y), GridCell::new(max.x, max.y))
+}
+
+// Of a few random seeds, keeps the one whose building height best matches the lot's land value, so
+// valuable land near roads grows taller buildings than noisy or out of the way lots.
+fn seed_for_value(value: f32, rng: &mut impl Rng) -> u64 {
+    (0..SEED_CANDIDATES)
+        .map(|_| rng.gen::<u64>())
+        .min_by(|&a, &b| (height_fraction(a) - value).abs().total_cmp(&(height_fraction(b) - value).abs()))
+        .unwrap_or_default()
+}
+
+// The roads new buildings can grow beside, and the land around them.
+#[derive(SystemParam)]
+struct GrowthSites<'w, 's> {
+    grid_query: Query<'w, 's, &'static Grid>,
+    terrain: Res<'w, Terrain>,
+    layers: Res<'w, LandLayers>,
+    segment_query: Query<'w, 's, (Entity, &'static RoadSegment)>,
+}
+
+impl GrowthSites<'_, '_> {
+    // Straight, open roads that are linked to something, in a stable order.
+    fn roads(&self) -> Vec<&RoadSegment> {
+        let mut roads: Vec<(Entity, &RoadSegment)> = self
+            .segment_query
+            .iter()
+            .filter(|(_, segment)| {
+                segment.is_straight()
+                    && !segment.closed
+                    && (segment.ends.iter().any(Option::is_some) || !segment.dests.is_empty())
+            })
+            .collect();
+
+        roads.sort_by_key(|&(entity, _)| entity);
+        roads.into_iter().map(|(_, segment)| segment).collect()
+    }
+
+    fn can_build(&self, lot: GridArea) -> bool {
+        self.grid_query.single().is_valid_paint_area(lot)
+            && self.terrain.is_dry(lot.iter())
+            && self.terrain.relief(lot) <= MAX_BUILDING_RELIEF
+    }
+}
+
+// Picks one of the zones in demand, weighted by how much, and places a lot of that kind beside a
+// straight road that is already part of the network, so the new building is reachable as soon as it
+// is linked.
+fn grow_buildings(
+    settings: Res<GrowthSettings>,
+    time: Res<Time>,
+    mut pressure: Local<f32>,
+    demand: Res<ZoneDemand>,
+    sites: GrowthSites,
+    mut sim_rng: ResMut<SimRng>,
+    mut builder: EventWriter<RequestBuilding>,
+) {
+    let wanted: Vec<(Zone, f32)> = Zone::ALL
+        .into_iter()
+        .map(|zone| (zone, demand.of(zone)))
+        .filter(|&(_, demand)| demand >= settings.min_demand)
+        .collect();
+
+    let Some(strongest) = wanted.iter().map(|&(_, demand)| demand).reduce(f32::max) else {
+        *pressure = 0.0;
+        return;
+    };
+
+    *pressure += time.delta_seconds() * strongest;
+    if *pressure < settings.interval_seconds {
+        return;
+    }
+
+    let roads = sites.roads();
+    if roads.is_empty() {
+        return;
+    }
+
+    *pressure = 0.0;
+
+    let rng = sim_rng.rng();
+
+    let Ok(distribution) = WeightedIndex::new(wanted.iter().map(|&(_, demand)| demand)) else {
+        return;
+    };
+    let zone = wanted[distribution.sample(rng)].0;
+
+    for _ in 0..PLACEMENT_ATTEMPTS {
+        let segment = roads[rng.gen_range(0..roads.len())];
+        let area = segment.area();
+        let (first, last) = match segment.orientation {
+            GAxis::Z => (area.min.pos.y, area.max.pos.y),
+            GAxis::X => (area.min.pos.x, area.max.pos.x),
+        };
+
+        let cell = rng.gen_range(first..=(last - LOT_SIZE + 1).max(first));
+        let lot = lot_beside(segment, cell, rng.gen_bool(0.5));
+
+        if sites.can_build(lot) {
+            let kind = growth_kind(zone, rng);
+            let seed = seed_for_value(sites.layers.mean_land_value(lot), rng);
+            builder.send(RequestBuilding::of_kind(lot, kind).with_seed(seed));
+            return;
+        }
+    }
+}
@@ -0,0 +1,5 @@
+pub mod commute;
+pub mod demand;
+pub mod economy;
+pub mod economy_events;
+pub mod growth;
@@ -0,0 +1,228 @@
+use crate::{
+    clock::unix_seconds,
+    export::export_events::*,
+    schedule::UpdateStage,
+    types::{building::Building, intersection::Intersection, road_segment::RoadSegment},
+    ui::notify_events::Notify,
+};
+use bevy::{
+    ecs::system::SystemParam,
+    math::Affine2,
+    prelude::*,
+    render::mesh::{PrimitiveTopology, VertexAttributeValues},
+    utils::HashMap,
+};
+use std::{
+    fs::{self, File},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+const EXPORT_DIR: &str = "assets/exports";
+const OBJ_FILE: &str = "city.obj";
+const MTL_FILE: &str = "city.mtl";
+
+pub struct ExportPlugin;
+
+impl Plugin for ExportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestCityExport>()
+            .add_event::<OnCityExported>()
+            .add_systems(Update, export_city.in_set(UpdateStage::Analyze));
+    }
+}
+
+type ExportedRoots = Or<(With<RoadSegment>, With<Intersection>, With<Building>)>;
+
+// One mesh placed in the world, with the material it is drawn with.
+struct ExportedMesh<'a> {
+    name: String,
+    mesh: &'a Mesh,
+    material: AssetId<StandardMaterial>,
+    transform: GlobalTransform,
+}
+
+#[derive(SystemParam)]
+struct CityMeshes<'w, 's> {
+    root_query: Query<'w, 's, Entity, ExportedRoots>,
+    children_query: Query<'w, 's, &'static Children>,
+    mesh_query: Query<
+        'w,
+        's,
+        (
+            &'static Handle<Mesh>,
+            &'static Handle<StandardMaterial>,
+            &'static GlobalTransform,
+        ),
+    >,
+    meshes: Res<'w, Assets<Mesh>>,
+}
+
+impl CityMeshes<'_, '_> {
+    // Every road, intersection and building mesh, along with the meshes attached beneath them.
+    fn parts(&self) -> Vec<ExportedMesh<'_>> {
+        let mut roots: Vec<Entity> = self.root_query.iter().collect();
+        roots.sort();
+
+        let mut parts = Vec::new();
+        for root in roots {
+            for entity in [root].into_iter().chain(self.children_query.iter_descendants(root)) {
+                let Ok((mesh, material, transform)) = self.mesh_query.get(entity) else {
+                    continue;
+                };
+
+                if let Some(mesh) =
+                    self.meshes.get(mesh).filter(|mesh| mesh.primitive_topology() == PrimitiveTopology::TriangleList)
+                {
+                    parts.push(ExportedMesh {
+                        name: format!("{}_{}", root, entity),
+                        mesh,
+                        material: material.id(),
+                        transform: *transform,
+                    });
+                }
+            }
+        }
+        parts
+    }
+}
+
+// Writes the city's meshes into one OBJ file in world space. Material colours go into a companion
+// MTL file, and baked textures are saved as PNGs next to it.
+fn export_city(
+    mut event: EventReader<RequestCityExport>,
+    mut exported: EventWriter<OnCityExported>,
+    mut notify: EventWriter<Notify>,
+    city: CityMeshes,
+    materials: Res<Assets<StandardMaterial>>,
+    images: Res<Assets<Image>>,
+) {
+    let Some(request) = event.read().last() else {
+        return;
+    };
+
+    let parts = city.parts();
+    let dir = Path::new(EXPORT_DIR).join(format!("city_{}", unix_seconds()));
+
+    match write_export(&dir, &parts, &materials, &images, request.bake_textures) {
+        Ok(path) => {
+            println!("Exported {} meshes to {:?}", parts.len(), path);
+            exported.send(OnCityExported {
+                path: path.display().to_string(),
+                meshes: parts.len(),
+            });
+        }
+        Err(error) => {
+            println!("Failed to export the city to {:?}: {}", dir, error);
+            notify.send(Notify::error(format!("Failed to export the city to {}", dir.display())));
+        }
+    }
+}
+
+fn write_export(
+    dir: &Path,
+    parts: &[ExportedMesh],
+    materials: &Assets<StandardMaterial>,
+    images: &Assets<Image>,
+    bake_textures: bool,
+) -> std::io::Result<PathBuf> {
+    fs::create_dir_all(dir)?;
+
+    let mut names: HashMap<AssetId<StandardMaterial>, String> = HashMap::new();
+    let mut mtl = BufWriter::new(File::create(dir.join(MTL_FILE))?);
+
+    for part in parts {
+        if names.contains_key(&part.material) {
+            continue;
+        }
+
+        let name = format!("material_{}", names.len());
+        let material = materials.get(part.material);
+        // MTL colours are read as sRGB, like the textures they tint.
+        let color = material.map_or(Srgba::WHITE, |material| Srgba::from(material.base_color));
+
+        writeln!(mtl, "newmtl {}", name)?;
+        writeln!(mtl, "Kd {} {} {}", color.red, color.green, color.blue)?;
+        writeln!(mtl, "d {}", color.alpha)?;
+
+        let texture = material.and_then(|material| material.base_color_texture.as_ref());
+        if let Some(image) = texture.filter(|_| bake_textures).and_then(|texture| images.get(texture)) {
+            let file = format!("{}.png", name);
+            if image.clone().try_into_dynamic().is_ok_and(|baked| baked.save(dir.join(&file)).is_ok()) {
+                writeln!(mtl, "map_Kd {}", file)?;
+            }
+        }
+
+        writeln!(mtl)?;
+        names.insert(part.material, name);
+    }
+
+    mtl.flush()?;
+
+    let path = dir.join(OBJ_FILE);
+    let mut obj = BufWriter::new(File::create(&path)?);
+    writeln!(obj, "mtllib {}", MTL_FILE)?;
+
+    // OBJ indices are 1-based and count up across the whole file, separately for each attribute.
+    let (mut first_vertex, mut first_normal, mut first_uv) = (1, 1, 1);
+
+    for part in parts {
+        let Some(positions) = part.mesh.attribute(Mesh::ATTRIBUTE_POSITION).and_then(VertexAttributeValues::as_float3)
+        else {
+            continue;
+        };
+        let normals = part.mesh.attribute(Mesh::ATTRIBUTE_NORMAL).and_then(VertexAttributeValues::as_float3);
+        let uvs = match part.mesh.attribute(Mesh::ATTRIBUTE_UV_0) {
+            Some(VertexAttributeValues::Float32x2(uvs)) => Some(uvs),
+            _ => None,
+        };
+
+        let affine = part.transform.affine();
+        let uv_transform = materials.get(part.material).map_or(Affine2::IDENTITY, |material| material.uv_transform);
+        let normal_matrix = affine.matrix3.inverse().transpose();
+
+        writeln!(obj, "o {}", part.name)?;
+        writeln!(obj, "usemtl {}", names[&part.material])?;
+
+        for &position in positions {
+            let p = affine.transform_point3(Vec3::from(position));
+            writeln!(obj, "v {} {} {}", p.x, p.y, p.z)?;
+        }
+        for &normal in normals.into_iter().flatten() {
+            let n = (normal_matrix * Vec3::from(normal)).normalize_or_zero();
+            writeln!(obj, "vn {} {} {}", n.x, n.y, n.z)?;
+        }
+        // OBJ has no texture transform, so the material's is applied to the coordinates here. They also
+        // start at the bottom of the image.
+        for &uv in uvs.into_iter().flatten() {
+            let uv = uv_transform.transform_point2(Vec2::from(uv));
+            writeln!(obj, "vt {} {}", uv.x, 1.0 - uv.y)?;
+        }
+
+        let indices: Vec<usize> = match part.mesh.indices() {
+            Some(indices) => indices.iter().collect(),
+            None => (0..positions.len()).collect(),
+        };
+
+        for triangle in indices.chunks_exact(3) {
+            write!(obj, "f")?;
+            for &index in triangle {
+                let (vertex, normal, uv) = (first_vertex + index, first_normal + index, first_uv + index);
+                match (normals.is_some(), uvs.is_some()) {
+                    (true, true) => write!(obj, " {}/{}/{}", vertex, uv, normal)?,
+                    (true, false) => write!(obj, " {}//{}", vertex, normal)?,
+                    (false, true) => write!(obj, " {}/{}", vertex, uv)?,
+                    (false, false) => write!(obj, " {}", vertex)?,
+                }
+            }
+            writeln!(obj)?;
+        }
+
+        first_vertex += positions.len();
+        first_normal += normals.map_or(0, |normals| normals.len());
+        first_uv += uvs.map_or(0, |uvs| uvs.len());
+    }
+
+    obj.flush()?;
+    Ok(path)
+}
@@ -0,0 +1,12 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct RequestCityExport {
+    pub bake_textures: bool,
+}
+
+#[derive(Event, Debug)]
+pub struct OnCityExported {
+    pub path: String,
+    pub meshes: usize,
+}
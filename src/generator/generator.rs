@@ -0,0 +1,383 @@
+use crate::{
+    determinism::determinism::launch_option,
+    generator::generator_events::*,
+    graph::road_graph_events::{OnBuildingDestroyed, OnIntersectionDestroyed, OnPropDestroyed, OnRoadDestroyed},
+    grid::{grid::Grid, grid_area::GridArea, grid_cell::GridCell, orientation::GAxis, terrain::Terrain},
+    schedule::UpdateStage,
+    tools::{
+        building_tool::{fits_lot, RequestBuilding},
+        road_events::{RequestIntersection, RequestRoad},
+        road_tool::{road_grade, MAX_ROAD_GRADE},
+    },
+    types::{
+        building::{Building, BuildingKind, Zone},
+        intersection::Intersection,
+        prop::Prop,
+        road_segment::{RoadSegment, RoadShape},
+    },
+    ui::notify_events::Notify,
+};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const NEW_CITY_ARG: &str = "--new-city";
+const NEW_CITY_VAR: &str = "OVERCAST_NEW_CITY";
+pub const MIN_BLOCKS: i32 = 2;
+pub const MAX_BLOCKS: i32 = 16;
+pub const MIN_BLOCK_SIZE: i32 = 8;
+pub const MAX_BLOCK_SIZE: i32 = 24;
+pub const MAX_LANDMARKS: u32 = 8;
+const ROAD_WIDTH: i32 = 2;
+const LOT_SIZE: i32 = 2;
+const LANDMARK_SIZE: i32 = 4;
+// Distortion is interpolated between random offsets picked every few grid lines, so neighbouring
+// streets bend together instead of jittering independently.
+const NOISE_PERIOD: usize = 3;
+// At full irregularity this share of the inner streets is left out, merging their blocks.
+const MAX_DROPPED_STREETS: f32 = 0.25;
+const PARKING_CHANCE: f32 = 0.1;
+
+pub struct CityGeneratorPlugin {
+    pub interactive: bool,
+}
+
+impl Plugin for CityGeneratorPlugin {
+    fn build(&self, app: &mut App) {
+        let launch_seed = new_city_seed();
+        let mut setup = NewCitySetup::default();
+        if let Some(seed) = launch_seed {
+            setup.params.seed = seed;
+            setup.open = self.interactive;
+        }
+
+        app.add_event::<RequestNewCity>().insert_resource(setup).insert_resource(PendingCity::default()).add_systems(
+            Update,
+            (
+                pause_during_setup.run_if(resource_changed::<NewCitySetup>),
+                (generate_city, clear_for_new_city).chain().in_set(UpdateStage::HighLevelSideEffects),
+            ),
+        );
+
+        // Without a window there is nobody to confirm the setup, so the city is generated straight away.
+        if launch_seed.is_some() && !self.interactive {
+            app.add_systems(
+                Startup,
+                |setup: Res<NewCitySetup>, mut request: EventWriter<RequestNewCity>| {
+                    request.send(RequestNewCity(setup.params));
+                },
+            );
+        }
+    }
+}
+
+// A seed passed with `--new-city` starts from a generated city instead of the saved one.
+pub fn new_city_seed() -> Option<u64> {
+    launch_option(NEW_CITY_ARG, NEW_CITY_VAR)
+}
+
+// The parameters chosen in the "New City" window, and whether it is showing.
+#[derive(Resource, Debug, Default)]
+pub struct NewCitySetup {
+    pub open: bool,
+    pub params: CityParams,
+}
+
+// A city waiting for the old one to be cleared off the grid before it is placed.
+#[derive(Resource, Debug, Default)]
+struct PendingCity(Option<CityParams>);
+
+#[derive(Debug, Default)]
+struct CityPlan {
+    intersections: Vec<GridArea>,
+    roads: Vec<(GridArea, GAxis)>,
+    buildings: Vec<(GridArea, BuildingKind, u64)>,
+}
+
+fn pause_during_setup(setup: Res<NewCitySetup>, mut time: ResMut<Time<Virtual>>) {
+    match setup.open {
+        true => time.pause(),
+        false => time.unpause(),
+    }
+}
+
+// Everything built is destroyed first, and the new city is placed the frame after, once the grid
+// has been cleared. Painted water stays, and the plan builds around it.
+fn clear_for_new_city(
+    mut requests: EventReader<RequestNewCity>,
+    mut pending: ResMut<PendingCity>,
+    mut city: CityDemolition,
+) {
+    let Some(&RequestNewCity(params)) = requests.read().last() else {
+        return;
+    };
+
+    city.destroy_all();
+    pending.0 = Some(params);
+}
+
+// Everything built on the grid, and the events that tear it down.
+#[derive(SystemParam)]
+struct CityDemolition<'w, 's> {
+    segment_query: Query<'w, 's, Entity, With<RoadSegment>>,
+    inter_query: Query<'w, 's, Entity, With<Intersection>>,
+    building_query: Query<'w, 's, Entity, With<Building>>,
+    prop_query: Query<'w, 's, Entity, With<Prop>>,
+    road_destroyed: EventWriter<'w, OnRoadDestroyed>,
+    inter_destroyed: EventWriter<'w, OnIntersectionDestroyed>,
+    building_destroyed: EventWriter<'w, OnBuildingDestroyed>,
+    prop_destroyed: EventWriter<'w, OnPropDestroyed>,
+}
+
+impl CityDemolition<'_, '_> {
+    fn destroy_all(&mut self) {
+        self.road_destroyed.send_batch(self.segment_query.iter().map(OnRoadDestroyed));
+        self.inter_destroyed.send_batch(self.inter_query.iter().map(OnIntersectionDestroyed));
+        self.building_destroyed.send_batch(self.building_query.iter().map(OnBuildingDestroyed));
+        self.prop_destroyed.send_batch(self.prop_query.iter().map(OnPropDestroyed));
+    }
+}
+
+fn generate_city(
+    mut pending: ResMut<PendingCity>,
+    grid_query: Query<&Grid>,
+    terrain: Res<Terrain>,
+    mut inter_event: EventWriter<RequestIntersection>,
+    mut segment_event: EventWriter<RequestRoad>,
+    mut building_event: EventWriter<RequestBuilding>,
+    mut notify: EventWriter<Notify>,
+) {
+    let Some(params) = pending.0.take() else {
+        return;
+    };
+
+    let plan = plan_city(&params, grid_query.single(), &terrain);
+
+    inter_event.send_batch(plan.intersections.iter().map(|&area| RequestIntersection::new(area)));
+    segment_event.send_batch(plan.roads.iter().map(|&(area, orientation)| RequestRoad::new(area, orientation)));
+    building_event
+        .send_batch(plan.buildings.iter().map(|&(area, kind, seed)| RequestBuilding::of_kind(area, kind).with_seed(seed)));
+
+    notify.send(Notify::info(format!(
+        "Generated city {}: {} roads, {} buildings",
+        params.seed,
+        plan.roads.len(),
+        plan.buildings.len()
+    )));
+}
+
+// Smooth 1D value noise in -1..1, sampled once per grid line.
+fn line_noise(count: usize, rng: &mut StdRng) -> Vec<f32> {
+    let knots: Vec<f32> = (0..=count / NOISE_PERIOD + 1).map(|_| rng.gen_range(-1.0..=1.0)).collect();
+
+    (0..count)
+        .map(|index| {
+            let knot = index / NOISE_PERIOD;
+            let t = (index % NOISE_PERIOD) as f32 / NOISE_PERIOD as f32;
+            let t = t * t * (3.0 - 2.0 * t);
+            knots[knot] + (knots[knot + 1] - knots[knot]) * t
+        })
+        .collect()
+}
+
+// The lowest cell of each road band across the city, centred on the middle of the grid. Lines are
+// pushed about by the noise, but never so far that a block is left with no room for a lot.
+fn grid_lines(params: &CityParams, rng: &mut StdRng) -> Vec<i32> {
+    let spacing = params.block_size + ROAD_WIDTH;
+    let start = -(params.blocks * spacing + ROAD_WIDTH) / 2;
+    let max_shift = ((params.block_size - 2 * LOT_SIZE) / 2) as f32 * params.irregularity;
+
+    line_noise(params.blocks as usize + 1, rng)
+        .into_iter()
+        .enumerate()
+        .map(|(index, noise)| start + index as i32 * spacing + (noise * max_shift).round() as i32)
+        .collect()
+}
+
+fn zone_kind(zone: Zone, rng: &mut StdRng) -> BuildingKind {
+    match zone {
+        Zone::Residential => BuildingKind::House,
+        Zone::Commercial if rng.gen::<f32>() < PARKING_CHANCE => BuildingKind::Parking,
+        Zone::Commercial if rng.gen::<bool>() => BuildingKind::Shop,
+        Zone::Commercial => BuildingKind::Office,
+        Zone::Industrial => BuildingKind::Factory,
+    }
+}
+
+// An arterial grid bent by noise, with some inner streets left out, lined with lots zoned after the
+// nearest district centre. Downtown is always commercial and its blocks closest to the centre get
+// the landmarks. Anything that would land on water, on a slope too steep for it or on something
+// already built is skipped.
+fn plan_city(params: &CityParams, grid: &Grid, terrain: &Terrain) -> CityPlan {
+    let mut rng = StdRng::seed_from_u64(params.seed);
+    let columns = grid_lines(params, &mut rng);
+    let rows = grid_lines(params, &mut rng);
+    let lines = columns.len();
+
+    let inter_area = |i: usize, j: usize| {
+        GridArea::new(
+            GridCell::new(columns[i], rows[j]),
+            GridCell::new(columns[i] + ROAD_WIDTH - 1, rows[j] + ROAD_WIDTH - 1),
+        )
+    };
+
+    // Streets run between neighbouring intersections, (i, j) to (i + 1, j) along x and (i, j) to
+    // (i, j + 1) along z.
+    let mut streets = Vec::new();
+    for i in 0..lines {
+        for j in 0..lines {
+            if i + 1 < lines {
+                streets.push(((i, j), (i + 1, j), GAxis::X));
+            }
+            if j + 1 < lines {
+                streets.push(((i, j), (i, j + 1), GAxis::Z));
+            }
+        }
+    }
+
+    let mut degree = vec![0; lines * lines];
+    for &((i, j), (k, l), _) in &streets {
+        degree[i * lines + j] += 1;
+        degree[k * lines + l] += 1;
+    }
+
+    let outer = |(i, j): (usize, usize), axis: GAxis| match axis {
+        GAxis::X => j == 0 || j == lines - 1,
+        GAxis::Z => i == 0 || i == lines - 1,
+    };
+
+    let drop_chance = params.irregularity * MAX_DROPPED_STREETS;
+    streets.retain(|&(from, to, axis)| {
+        let (a, b) = (from.0 * lines + from.1, to.0 * lines + to.1);
+        let dropped = !outer(from, axis) && degree[a] > 2 && degree[b] > 2 && rng.gen::<f32>() < drop_chance;
+        if dropped {
+            degree[a] -= 1;
+            degree[b] -= 1;
+        }
+        !dropped
+    });
+
+    let fits_road = |area: GridArea| grid.is_valid_paint_area(area) && terrain.is_dry(area.iter());
+    let mut plan = CityPlan::default();
+    let mut used = HashSet::new();
+    let mut connected = vec![false; lines * lines];
+
+    for (from, to, axis) in streets {
+        let (start, end) = (inter_area(from.0, from.1), inter_area(to.0, to.1));
+        let area = match axis {
+            GAxis::X => GridArea::new(
+                GridCell::new(start.max.pos.x + 1, start.min.pos.y),
+                GridCell::new(end.min.pos.x - 1, start.max.pos.y),
+            ),
+            GAxis::Z => GridArea::new(
+                GridCell::new(start.min.pos.x, start.max.pos.y + 1),
+                GridCell::new(start.max.pos.x, end.min.pos.y - 1),
+            ),
+        };
+
+        let graded = road_grade(&RoadSegment::shaped(area, axis, RoadShape::Straight), terrain) <= MAX_ROAD_GRADE;
+        if !(graded && fits_road(area) && fits_road(start) && fits_road(end)) {
+            continue;
+        }
+
+        plan.roads.push((area, axis));
+        used.extend(area.iter().map(|cell| cell.pos));
+        connected[from.0 * lines + from.1] = true;
+        connected[to.0 * lines + to.1] = true;
+    }
+
+    for i in 0..lines {
+        for j in 0..lines {
+            if connected[i * lines + j] {
+                let area = inter_area(i, j);
+                plan.intersections.push(area);
+                used.extend(area.iter().map(|cell| cell.pos));
+            }
+        }
+    }
+
+    let road_cells = used.clone();
+    let extent = (columns[lines - 1] - columns[0]) as f32 / 2.0;
+    let districts: Vec<(Vec2, Zone)> = std::iter::once((Vec2::ZERO, Zone::Commercial))
+        .chain((0..1 + params.blocks * params.blocks / 8).map(|_| {
+            let center = Vec2::new(rng.gen_range(-extent..=extent), rng.gen_range(-extent..=extent));
+            let zone = match rng.gen::<f32>() {
+                roll if roll < 0.5 => Zone::Residential,
+                roll if roll < 0.8 => Zone::Commercial,
+                _ => Zone::Industrial,
+            };
+            (center, zone)
+        }))
+        .collect();
+
+    let mut blocks: Vec<GridArea> = (0..lines - 1)
+        .flat_map(|i| (0..lines - 1).map(move |j| (i, j)))
+        .map(|(i, j)| {
+            GridArea::new(
+                GridCell::new(columns[i] + ROAD_WIDTH, rows[j] + ROAD_WIDTH),
+                GridCell::new(columns[i + 1] - 1, rows[j + 1] - 1),
+            )
+        })
+        .collect();
+    blocks.sort_by(|a, b| a.center().length_squared().total_cmp(&b.center().length_squared()));
+
+    let beside_road =
+        |area: GridArea| area.adjacent_areas().any(|(side, _)| side.iter().any(|cell| road_cells.contains(&cell.pos)));
+    let place = |plan: &mut CityPlan, used: &mut HashSet<IVec2>, area: GridArea, kind: BuildingKind, seed: u64| {
+        if area.iter().any(|cell| used.contains(&cell.pos)) || !beside_road(area) || !fits_lot(area, grid, terrain) {
+            return false;
+        }
+        used.extend(area.iter().map(|cell| cell.pos));
+        plan.buildings.push((area, kind, seed));
+        true
+    };
+
+    let mut landmarks = 0;
+    for &block in &blocks {
+        if landmarks == params.landmarks {
+            break;
+        }
+
+        let size = block.cell_dimensions();
+        if size.x < LANDMARK_SIZE || size.y < LANDMARK_SIZE {
+            continue;
+        }
+
+        let x = block.min.pos.x + (size.x - LANDMARK_SIZE) / 2;
+        let area = GridArea::new(
+            GridCell::new(x, block.min.pos.y),
+            GridCell::new(x + LANDMARK_SIZE - 1, block.min.pos.y + LANDMARK_SIZE - 1),
+        );
+        let kind = [BuildingKind::Stadium, BuildingKind::Mall][landmarks as usize % 2];
+        if place(&mut plan, &mut used, area, kind, rng.gen()) {
+            landmarks += 1;
+        }
+    }
+
+    for block in blocks {
+        let center = block.center().xz();
+        let zone = districts
+            .iter()
+            .min_by(|a, b| a.0.distance_squared(center).total_cmp(&b.0.distance_squared(center)))
+            .map_or(Zone::Residential, |&(_, zone)| zone);
+
+        let lot = |x: i32, z: i32| GridArea::new(GridCell::new(x, z), GridCell::new(x + LOT_SIZE - 1, z + LOT_SIZE - 1));
+        let mut lots = Vec::new();
+        for x in (block.min.pos.x..=block.max.pos.x - LOT_SIZE + 1).step_by(LOT_SIZE as usize) {
+            lots.push(lot(x, block.min.pos.y));
+            lots.push(lot(x, block.max.pos.y - LOT_SIZE + 1));
+        }
+        for z in (block.min.pos.y..=block.max.pos.y - LOT_SIZE + 1).step_by(LOT_SIZE as usize) {
+            lots.push(lot(block.min.pos.x, z));
+            lots.push(lot(block.max.pos.x - LOT_SIZE + 1, z));
+        }
+
+        for area in lots {
+            if rng.gen::<f32>() < params.density {
+                let kind = zone_kind(zone, &mut rng);
+                place(&mut plan, &mut used, area, kind, rng.gen());
+            }
+        }
+    }
+
+    plan
+}
@@ -0,0 +1,29 @@
+use bevy::prelude::*;
+
+// Everything a generated city is planned from. The same parameters always plan the same city on the
+// same terrain.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CityParams {
+    pub seed: u64,
+    pub blocks: i32,
+    pub block_size: i32,
+    pub density: f32,
+    pub irregularity: f32,
+    pub landmarks: u32,
+}
+
+impl Default for CityParams {
+    fn default() -> Self {
+        Self {
+            seed: 0,
+            blocks: 6,
+            block_size: 10,
+            density: 0.6,
+            irregularity: 0.3,
+            landmarks: 3,
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct RequestNewCity(pub CityParams);
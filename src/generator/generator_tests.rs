@@ -0,0 +1,39 @@
+use crate::{
+    generator::generator_events::{CityParams, RequestNewCity},
+    save::{
+        save::{SaveObject, SAVEFILE},
+        save_tests::{area, headless_app, layout, Layout, MemoryStorage},
+        storage::SaveStorage,
+    },
+};
+use std::sync::Arc;
+
+const CITY_FRAMES: usize = 10;
+
+fn generate(seed: u64) -> Layout {
+    let store = Arc::new(MemoryStorage::default());
+    store.write(SAVEFILE, &SaveObject::new().to_json().unwrap()).unwrap();
+
+    let mut app = headless_app(store);
+    app.update();
+    app.world_mut().send_event(RequestNewCity(CityParams {
+        seed,
+        blocks: 3,
+        ..Default::default()
+    }));
+    for _ in 0..CITY_FRAMES {
+        app.update();
+    }
+
+    layout(&mut app, area((-30, -30), (30, 30)))
+}
+
+// The same seed always plans the same city, down to every lot and how it is linked.
+#[test]
+fn seeded_cities_are_identical() {
+    let city = generate(42);
+
+    assert!(city.cells.values().any(|&kind| kind == "road"));
+    assert!(city.cells.values().any(|&kind| kind == "building"));
+    assert_eq!(generate(42), city);
+}
@@ -0,0 +1,4 @@
+pub mod generator;
+pub mod generator_events;
+#[cfg(test)]
+mod generator_tests;
@@ -0,0 +1,201 @@
+use crate::{
+    grid::terrain::Terrain,
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::road_tool::ROAD_HEIGHT,
+    types::road_segment::RoadSegment,
+    types::vehicle::{Vehicle, QUEUED_SPEED},
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::collections::VecDeque;
+
+const SAMPLE_SECONDS: f32 = 0.5;
+const WINDOW_SAMPLES: usize = 20;
+// A road this full of queued vehicles backs up into the roads feeding it, each taking on this share of
+// its spillback, for up to this many roads back.
+const SPILLBACK_FULL: f32 = 0.6;
+const SPILLBACK_DECAY: f32 = 0.7;
+const SPILLBACK_DEPTH: usize = 3;
+const HEATMAP_OFFSET: f32 = ROAD_HEIGHT + 0.02;
+const FREE_COLOR: LinearRgba = LinearRgba::rgb(0.0, 1.0, 0.0);
+const JAMMED_COLOR: LinearRgba = LinearRgba::rgb(1.0, 0.0, 0.0);
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum HeatmapState {
+    Visualize,
+    #[default]
+    Hide,
+}
+
+pub struct CongestionPlugin;
+
+impl Plugin for CongestionPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<HeatmapState>()
+            .insert_resource(CongestionStats {
+                timer: Timer::from_seconds(SAMPLE_SECONDS, TimerMode::Repeating),
+                average: 0.0,
+                peak: 0.0,
+            })
+            .add_systems(
+                Update,
+                (
+                    toggle_heatmap.in_set(UpdateStage::UserInput),
+                    track_congestion.in_set(UpdateStage::Analyze),
+                    visualize_heatmap.in_set(UpdateStage::Visualize).run_if(in_state(HeatmapState::Visualize)),
+                ),
+            );
+    }
+}
+
+#[derive(Component, Debug, Default)]
+pub struct Congestion {
+    samples: VecDeque<f32>,
+    pub ratio: f32,
+}
+
+impl Congestion {
+    fn record(&mut self, sample: f32) {
+        if self.samples.len() == WINDOW_SAMPLES {
+            self.samples.pop_front();
+        }
+
+        self.samples.push_back(sample);
+        self.ratio = self.samples.iter().sum::<f32>() / self.samples.len() as f32;
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct CongestionStats {
+    timer: Timer,
+    pub average: f32,
+    pub peak: f32,
+}
+
+fn toggle_heatmap(actions: Actions, mut next_state: ResMut<NextState<HeatmapState>>, state: Res<State<HeatmapState>>) {
+    if actions.just_pressed(Action::ToggleHeatmap) {
+        next_state.set({
+            match state.get() {
+                HeatmapState::Hide => HeatmapState::Visualize,
+                HeatmapState::Visualize => HeatmapState::Hide,
+            }
+        });
+    }
+}
+
+// Samples how full each road is, counting a road backed up by spillback as at least that full.
+pub fn track_congestion(
+    mut commands: Commands,
+    mut segment_query: Query<(Entity, &mut RoadSegment, Option<&mut Congestion>)>,
+    vehicle_query: Query<&Vehicle>,
+    mut stats: ResMut<CongestionStats>,
+    time: Res<Time>,
+) {
+    stats.timer.tick(time.delta());
+    if !stats.timer.just_finished() {
+        return;
+    }
+
+    let mut occupants = HashMap::<Entity, usize>::new();
+    let mut queued = HashMap::<Entity, usize>::new();
+    let mut feeds = HashSet::<(Entity, Entity)>::new();
+    for vehicle in &vehicle_query {
+        if let Some(&step) = vehicle.path.get(vehicle.path_index) {
+            *occupants.entry(step).or_default() += 1;
+            if vehicle.speed < QUEUED_SPEED && !vehicle.crashed && vehicle.parked.is_none() {
+                *queued.entry(step).or_default() += 1;
+            }
+            if let Some(&downstream) = vehicle.path.get(vehicle.path_index + 2) {
+                feeds.insert((step, downstream));
+            }
+        }
+    }
+
+    let spillback = spillback(&segment_query, &queued, &feeds);
+
+    let mut total_vehicles = 0.0;
+    let mut total_capacity = 0.0;
+    let mut peak: f32 = 0.0;
+
+    for (entity, mut segment, congestion) in &mut segment_query {
+        let backed_up = spillback.get(&entity).copied().unwrap_or(0.0);
+        if segment.spillback != backed_up {
+            segment.spillback = backed_up;
+        }
+
+        let count = occupants.get(&entity).copied().unwrap_or(0) as f32;
+        let sample = (count / segment.capacity()).max(backed_up);
+
+        let ratio = match congestion {
+            Some(mut congestion) => {
+                congestion.record(sample);
+                congestion.ratio
+            }
+            None => {
+                let mut congestion = Congestion::default();
+                congestion.record(sample);
+                let ratio = congestion.ratio;
+                commands.entity(entity).insert(congestion);
+                ratio
+            }
+        };
+
+        total_vehicles += ratio * segment.capacity();
+        total_capacity += segment.capacity();
+        peak = peak.max(ratio);
+    }
+
+    stats.average = if total_capacity > 0.0 {
+        total_vehicles / total_capacity
+    } else {
+        0.0
+    };
+    stats.peak = peak;
+}
+
+// Each road starts from the share of it taken up by its own queue. Roads full enough pass that on,
+// weakened, to the roads vehicles reach them from, which are the ones the queue will back into next.
+fn spillback(
+    segment_query: &Query<(Entity, &mut RoadSegment, Option<&mut Congestion>)>,
+    queued: &HashMap<Entity, usize>,
+    feeds: &HashSet<(Entity, Entity)>,
+) -> HashMap<Entity, f32> {
+    let mut spillback: HashMap<Entity, f32> = segment_query
+        .iter()
+        .map(|(entity, segment, _)| {
+            let count = queued.get(&entity).copied().unwrap_or(0) as f32;
+            (entity, (count / segment.capacity()).min(1.0))
+        })
+        .collect();
+
+    for _ in 0..SPILLBACK_DEPTH {
+        let mut next = spillback.clone();
+        for &(upstream, downstream) in feeds {
+            let (Some(&from), Some(to)) = (spillback.get(&downstream), next.get_mut(&upstream)) else {
+                continue;
+            };
+            if from >= SPILLBACK_FULL {
+                *to = to.max(from * SPILLBACK_DECAY);
+            }
+        }
+        spillback = next;
+    }
+
+    spillback
+}
+
+fn visualize_heatmap(segment_query: Query<(&RoadSegment, &Congestion)>, terrain: Res<Terrain>, mut gizmos: Gizmos) {
+    let lift = |point: Vec3| point.with_y(terrain.road_height(point) + HEATMAP_OFFSET);
+
+    for (segment, congestion) in &segment_query {
+        let color = Color::from(FREE_COLOR.mix(&JAMMED_COLOR, congestion.ratio.clamp(0.0, 1.0)));
+        let samples = segment.centerline_samples();
+
+        gizmos.linestrip(samples.iter().map(|&(point, _)| lift(point)), color);
+        gizmos.linestrip(samples.iter().map(|&(point, lateral)| lift(point + lateral)), color);
+        gizmos.linestrip(samples.iter().map(|&(point, lateral)| lift(point - lateral)), color);
+    }
+}
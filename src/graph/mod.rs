@@ -1,2 +1,11 @@
+pub mod congestion;
+pub mod path_cache;
+pub mod pathfinding;
 pub mod road_graph;
 pub mod road_graph_events;
+pub mod road_network;
+#[cfg(test)]
+pub(crate) mod road_network_tests;
+pub mod validator;
+#[cfg(test)]
+mod validator_tests;
@@ -0,0 +1,118 @@
+use crate::{graph::road_graph_events::*, graph::road_network::RoadNetwork, schedule::UpdateStage};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+// Cached routes are dropped after this long, so trips still pick up changes in congestion.
+const MAX_AGE_SECONDS: f32 = 30.0;
+
+pub struct PathCachePlugin;
+
+impl Plugin for PathCachePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PathCache::default()).add_systems(Update, invalidate_paths.in_set(UpdateStage::Analyze));
+    }
+}
+
+#[derive(Debug)]
+struct CachedPath {
+    steps: Vec<Entity>,
+    created: f32,
+}
+
+// Routes between buildings, keyed by the road leaving the origin and the road reaching the
+// destination. Only the part between those two roads is stored, so every pair of buildings on the
+// same two roads shares one entry.
+#[derive(Resource, Debug, Default)]
+pub struct PathCache {
+    entries: HashMap<(Entity, Entity), CachedPath>,
+    pub hits: u64,
+    pub misses: u64,
+}
+
+impl PathCache {
+    pub fn cached_routes(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn trip(&mut self, network: &RoadNetwork, from: Entity, to: Entity, now: f32) -> Option<Vec<Entity>> {
+        if let Some(steps) = self.lookup(network, from, to, now) {
+            self.hits += 1;
+            return Some([from].into_iter().chain(steps).chain([to]).collect());
+        }
+
+        self.misses += 1;
+        let path = network.path(from, to)?;
+
+        if path.len() >= 4 && network.building(from).is_some() && network.building(to).is_some() {
+            self.entries.insert(
+                (path[1], path[path.len() - 2]),
+                CachedPath {
+                    steps: path[1..path.len() - 1].to_vec(),
+                    created: now,
+                },
+            );
+        }
+
+        Some(path)
+    }
+
+    fn lookup(&mut self, network: &RoadNetwork, from: Entity, to: Entity, now: f32) -> Option<Vec<Entity>> {
+        let starts = network.building(from)?.roads.clone();
+        let ends = &network.building(to)?.roads;
+
+        let mut keys: Vec<(Entity, Entity)> =
+            starts.iter().flat_map(|&start| ends.iter().map(move |&end| (start, end))).collect();
+        keys.sort();
+
+        for key in keys {
+            let Some(cached) = self.entries.get(&key) else {
+                continue;
+            };
+
+            let usable = now - cached.created <= MAX_AGE_SECONDS
+                && cached.steps.iter().all(|&step| match network.segment(step) {
+                    Some(segment) => !segment.closed,
+                    None => network.intersection(step).is_some(),
+                });
+
+            if usable {
+                return Some(cached.steps.clone());
+            }
+
+            self.entries.remove(&key);
+        }
+
+        None
+    }
+
+    pub fn clear(&mut self) {
+        self.entries.clear();
+    }
+
+    fn forget(&mut self, removed: &HashSet<Entity>) {
+        self.entries.retain(|_, cached| !cached.steps.iter().any(|step| removed.contains(step)));
+    }
+}
+
+// New roads can open shorter routes anywhere, so they empty the cache. Removed roads and
+// intersections only drop the routes through them.
+fn invalidate_paths(
+    mut cache: ResMut<PathCache>,
+    mut road_spawned: EventReader<OnRoadSpawned>,
+    mut inter_spawned: EventReader<OnIntersectionSpawned>,
+    mut road_destroyed: EventReader<OnRoadDestroyed>,
+    mut inter_destroyed: EventReader<OnIntersectionDestroyed>,
+) {
+    if road_spawned.read().count() + inter_spawned.read().count() > 0 {
+        cache.clear();
+    }
+
+    let removed: HashSet<Entity> =
+        road_destroyed.read().map(|event| event.0).chain(inter_destroyed.read().map(|event| event.0)).collect();
+
+    if !removed.is_empty() {
+        cache.forget(&removed);
+    }
+}
@@ -0,0 +1,249 @@
+use crate::types::{building::Building, intersection::Intersection, road_segment::RoadSegment};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+const INTERSECTION_SPEED: f32 = 1.0;
+
+pub trait PathGraph {
+    // `from` is the node the search arrived from, so intersections can leave out restricted turns.
+    fn neighbors(&self, node: Entity, from: Option<Entity>, goal: Entity) -> Vec<Entity>;
+    fn position(&self, node: Entity) -> Option<Vec3>;
+    fn segment(&self, node: Entity) -> Option<&RoadSegment>;
+}
+
+pub trait CostProvider: Send + Sync {
+    fn cost(&self, graph: &dyn PathGraph, from: Entity, to: Entity) -> Option<f32>;
+}
+
+pub struct RoadGraphView<B, S, I> {
+    buildings: B,
+    segments: S,
+    intersections: I,
+}
+
+impl<B, S, I> RoadGraphView<B, S, I> {
+    pub fn new(buildings: B, segments: S, intersections: I) -> Self {
+        Self {
+            buildings,
+            segments,
+            intersections,
+        }
+    }
+}
+
+impl PathGraph
+    for RoadGraphView<
+        &Query<'_, '_, (Entity, &Building)>,
+        &Query<'_, '_, (Entity, &RoadSegment)>,
+        &Query<'_, '_, (Entity, &Intersection)>,
+    >
+{
+    fn neighbors(&self, node: Entity, from: Option<Entity>, goal: Entity) -> Vec<Entity> {
+        let open = |road: &Entity| self.segments.get(*road).map_or(true, |(_, segment)| !segment.closed);
+        let enterable = |road: &Entity| self.segments.get(*road).map_or(true, |(_, segment)| segment.enterable_from(node));
+
+        if let Ok((_, building)) = self.buildings.get(node) {
+            building.roads.iter().filter(|road| open(road)).copied().collect()
+        } else if let Ok((_, segment)) = self.segments.get(node) {
+            // A segment is only left the way it was entered by turning around at a dead end, which the
+            // search takes as a step from the segment to itself.
+            let mut next: Vec<Entity> =
+                segment.ends.iter().flatten().filter(|&&end| Some(end) != from && segment.leads_to(end)).copied().collect();
+            if segment.is_dead_end()
+                && segment.one_way.is_none()
+                && from.is_some_and(|from| segment.ends.contains(&Some(from)))
+            {
+                next.push(node);
+            }
+            if segment.dests.contains(&goal) {
+                next.push(goal);
+            }
+            next
+        } else if let Ok((_, intersection)) = self.intersections.get(node) {
+            intersection
+                .roads
+                .iter()
+                .flatten()
+                .filter(|road| {
+                    open(road) && enterable(road) && from.is_none_or(|from| intersection.allows_turn(from, **road))
+                })
+                .copied()
+                .collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    fn position(&self, node: Entity) -> Option<Vec3> {
+        if let Ok((_, building)) = self.buildings.get(node) {
+            Some(building.pos())
+        } else if let Ok((_, segment)) = self.segments.get(node) {
+            Some(segment.pos())
+        } else {
+            self.intersections.get(node).ok().map(|(_, intersection)| intersection.pos())
+        }
+    }
+
+    fn segment(&self, node: Entity) -> Option<&RoadSegment> {
+        self.segments.get(node).ok().map(|(_, segment)| segment)
+    }
+}
+
+pub struct DistanceCost;
+
+impl CostProvider for DistanceCost {
+    fn cost(&self, graph: &dyn PathGraph, from: Entity, to: Entity) -> Option<f32> {
+        // Turning around at a dead end means driving to its end and back.
+        if from == to {
+            return graph.segment(from).map(|segment| segment.drive_length() as f32);
+        }
+
+        Some(graph.position(from)?.distance(graph.position(to)?))
+    }
+}
+
+pub struct TravelTimeCost;
+
+impl CostProvider for TravelTimeCost {
+    fn cost(&self, graph: &dyn PathGraph, from: Entity, to: Entity) -> Option<f32> {
+        let distance = DistanceCost.cost(graph, from, to)?;
+        let speed = graph.segment(to).or(graph.segment(from)).map_or(INTERSECTION_SPEED, |segment| segment.speed_limit());
+        Some(distance / speed.max(f32::EPSILON))
+    }
+}
+
+pub struct CongestionCost {
+    pub per_vehicle: f32,
+}
+
+impl CostProvider for CongestionCost {
+    fn cost(&self, graph: &dyn PathGraph, _from: Entity, to: Entity) -> Option<f32> {
+        let Some(segment) = graph.segment(to) else {
+            return Some(0.0);
+        };
+
+        Some(self.per_vehicle * (segment.observers.len() as f32 / segment.capacity() + segment.spillback))
+    }
+}
+
+pub struct FilterCost<F>(pub F);
+
+impl<F> CostProvider for FilterCost<F>
+where
+    F: Fn(&dyn PathGraph, Entity) -> bool + Send + Sync,
+{
+    fn cost(&self, graph: &dyn PathGraph, _from: Entity, to: Entity) -> Option<f32> {
+        (self.0)(graph, to).then_some(0.0)
+    }
+}
+
+#[derive(Resource, Default)]
+pub struct Pathfinder {
+    costs: Vec<(Box<dyn CostProvider>, f32)>,
+}
+
+impl Pathfinder {
+    pub fn vehicles() -> Self {
+        Self::default().with_cost(TravelTimeCost, 1.0).with_cost(CongestionCost { per_vehicle: 10.0 }, 1.0)
+    }
+
+    pub fn with_cost(mut self, provider: impl CostProvider + 'static, weight: f32) -> Self {
+        self.register(provider, weight);
+        self
+    }
+
+    pub fn register(&mut self, provider: impl CostProvider + 'static, weight: f32) {
+        self.costs.push((Box::new(provider), weight));
+    }
+
+    fn step_cost(&self, graph: &dyn PathGraph, from: Entity, to: Entity) -> Option<f32> {
+        if self.costs.is_empty() {
+            return DistanceCost.cost(graph, from, to);
+        }
+
+        self.costs.iter().try_fold(0.0, |total, (provider, weight)| {
+            Some(total + provider.cost(graph, from, to)? * weight)
+        })
+    }
+
+    // Searches over (node, previous node) pairs rather than nodes alone, since which roads leave an
+    // intersection can depend on the road the search came in on.
+    pub fn find_path(&self, graph: &dyn PathGraph, start: Entity, goal: Entity) -> Option<Vec<Entity>> {
+        let mut frontier = BinaryHeap::<Frontier>::new();
+        let mut best = HashMap::<Arrival, f32>::new();
+        let mut parent_map = HashMap::<Arrival, Arrival>::new();
+        let mut visited = HashSet::<Arrival>::new();
+
+        let origin = (start, None);
+        best.insert(origin, 0.0);
+        frontier.push(Frontier(0.0, origin));
+
+        while let Some(Frontier(cost, curr)) = frontier.pop() {
+            let (node, from) = curr;
+
+            if node == goal {
+                let mut path = vec![goal];
+                let mut step = curr;
+
+                while step != origin {
+                    step = parent_map[&step];
+                    path.push(step.0);
+                }
+
+                path.reverse();
+                return Some(path);
+            }
+
+            if !visited.insert(curr) {
+                continue;
+            }
+
+            for next in graph.neighbors(node, from, goal) {
+                let arrival = (next, Some(node));
+                if visited.contains(&arrival) {
+                    continue;
+                }
+
+                let Some(step_cost) = self.step_cost(graph, node, next) else {
+                    continue;
+                };
+
+                let total = cost + step_cost.max(0.0);
+                if best.get(&arrival).is_none_or(|&known| total < known) {
+                    best.insert(arrival, total);
+                    parent_map.insert(arrival, curr);
+                    frontier.push(Frontier(total, arrival));
+                }
+            }
+        }
+
+        None
+    }
+}
+
+type Arrival = (Entity, Option<Entity>);
+
+struct Frontier(f32, Arrival);
+
+impl PartialEq for Frontier {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Frontier {}
+
+impl PartialOrd for Frontier {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Frontier {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other.0.total_cmp(&self.0).then_with(|| self.1.cmp(&other.1))
+    }
+}
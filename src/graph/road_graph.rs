@@ -1,6 +1,11 @@
 use crate::{
-    graph::road_graph_events::*, grid::grid::Grid, schedule::UpdateStage, types::building::*,
-    types::intersection::Intersection, types::road_segment::RoadSegment,
+    graph::road_graph_events::*,
+    grid::grid::Grid,
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    types::building::*,
+    types::intersection::Intersection,
+    types::road_segment::RoadSegment,
 };
 use bevy::prelude::*;
 
@@ -53,25 +58,7 @@ pub fn add_roads_to_graph(
     let grid = grid_query.single();
 
     for &OnRoadSpawned(entity) in event.read() {
-        if let Ok(mut segment) = segment_query.get_mut(entity) {
-            for (adj_area, gdir) in segment.area().adjacent_areas() {
-                if let Some(adj) = grid.single_entity_in_area(adj_area) {
-                    if let Ok(mut inter) = inter_query.get_mut(adj) {
-                        segment.ends[gdir.binary_index()] = Some(adj);
-                        inter.roads[gdir.inverse().index()] = Some(entity);
-                    }
-                }
-
-                for cell in adj_area.iter() {
-                    if let Ok(Some(adj)) = grid.entity_at(cell) {
-                        if let Ok(mut building) = building_query.get_mut(adj) {
-                            segment.dests.insert(adj);
-                            building.roads.insert(entity);
-                        }
-                    }
-                }
-            }
-        }
+        link_road(entity, grid, &mut segment_query, &mut inter_query, &mut building_query);
     }
 }
 
@@ -84,16 +71,7 @@ pub fn add_intersections_to_graph(
     let grid = grid_query.single();
 
     for &OnIntersectionSpawned(entity) in event.read() {
-        if let Ok(mut inter) = inter_query.get_mut(entity) {
-            for (adj_area, gdir) in inter.area().adjacent_areas() {
-                if let Some(adj) = grid.single_entity_in_area(adj_area) {
-                    if let Ok(mut segment) = segment_query.get_mut(adj) {
-                        inter.roads[gdir.index()] = Some(adj);
-                        segment.ends[gdir.inverse().binary_index()] = Some(entity);
-                    }
-                }
-            }
-        }
+        link_intersection(entity, grid, &mut segment_query, &mut inter_query);
     }
 }
 
@@ -106,13 +84,92 @@ pub fn add_buildings_to_graph(
     let grid = grid_query.single();
 
     for &OnBuildingSpawned(entity) in event.read() {
-        if let Ok(mut building) = building_query.get_mut(entity) {
-            for (adj_area, _gdir) in building.area().adjacent_areas() {
-                if let Some(adj) = grid.single_entity_in_area(adj_area) {
-                    if let Ok(mut segment) = segment_query.get_mut(adj) {
-                        building.roads.insert(adj);
-                        segment.dests.insert(entity);
-                    }
+        link_building(entity, grid, &mut segment_query, &mut building_query);
+    }
+}
+
+// Adjacency is read from the grid, so linking the same entity again only restores links that
+// are missing.
+pub fn link_road(
+    entity: Entity,
+    grid: &Grid,
+    segment_query: &mut Query<&mut RoadSegment>,
+    inter_query: &mut Query<&mut Intersection>,
+    building_query: &mut Query<&mut Building>,
+) {
+    let Ok(mut segment) = segment_query.get_mut(entity) else {
+        return;
+    };
+
+    if !segment.is_straight() {
+        for (index, (adj_area, gdir)) in segment.end_areas().into_iter().enumerate() {
+            if let Some(adj) = grid.single_entity_in_area(adj_area) {
+                if let Ok(mut inter) = inter_query.get_mut(adj) {
+                    segment.ends[index] = Some(adj);
+                    inter.roads[gdir.inverse().index()] = Some(entity);
+                }
+            }
+        }
+        return;
+    }
+
+    for (adj_area, gdir) in segment.area().adjacent_areas() {
+        if let Some(adj) = grid.single_entity_in_area(adj_area) {
+            if let Ok(mut inter) = inter_query.get_mut(adj) {
+                segment.ends[gdir.binary_index()] = Some(adj);
+                inter.roads[gdir.inverse().index()] = Some(entity);
+            }
+        }
+
+        for cell in adj_area.iter() {
+            if let Ok(Some(adj)) = grid.entity_at(cell) {
+                if let Ok(mut building) = building_query.get_mut(adj) {
+                    segment.dests.insert(adj);
+                    building.connect(entity, segment.area());
+                }
+            }
+        }
+    }
+}
+
+pub fn link_intersection(
+    entity: Entity,
+    grid: &Grid,
+    segment_query: &mut Query<&mut RoadSegment>,
+    inter_query: &mut Query<&mut Intersection>,
+) {
+    let Ok(mut inter) = inter_query.get_mut(entity) else {
+        return;
+    };
+
+    for (adj_area, gdir) in inter.area().adjacent_areas() {
+        if let Some(adj) = grid.single_entity_in_area(adj_area) {
+            if let Ok(mut segment) = segment_query.get_mut(adj) {
+                if let Some(slot) = segment.end_slot(gdir.inverse()) {
+                    inter.roads[gdir.index()] = Some(adj);
+                    segment.ends[slot] = Some(entity);
+                }
+            }
+        }
+    }
+}
+
+pub fn link_building(
+    entity: Entity,
+    grid: &Grid,
+    segment_query: &mut Query<&mut RoadSegment>,
+    building_query: &mut Query<&mut Building>,
+) {
+    let Ok(mut building) = building_query.get_mut(entity) else {
+        return;
+    };
+
+    for (adj_area, _gdir) in building.area().adjacent_areas() {
+        if let Some(adj) = grid.single_entity_in_area(adj_area) {
+            if let Ok(mut segment) = segment_query.get_mut(adj) {
+                if segment.is_straight() {
+                    building.connect(adj, segment.area());
+                    segment.dests.insert(entity);
                 }
             }
         }
@@ -143,7 +200,7 @@ pub fn remove_roads_from_graph(
 
             for dest in &segment.dests {
                 if let Ok(mut building) = building_query.get_mut(*dest) {
-                    building.roads.remove(&entity);
+                    building.disconnect(entity);
                 }
             }
         }
@@ -199,11 +256,11 @@ const INTER_RADIUS: f32 = 0.4;
 const BUILDING_RADIUS: f32 = 0.3;
 
 fn toggle_graph_visualization(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     mut next_state: ResMut<NextState<GraphVisualizationState>>,
     state: Res<State<GraphVisualizationState>>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyH) {
+    if actions.just_pressed(Action::ToggleRoadGraph) {
         next_state.set({
             match state.get() {
                 GraphVisualizationState::Hide => GraphVisualizationState::Visualize,
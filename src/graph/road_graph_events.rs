@@ -53,3 +53,39 @@ impl AsRef<Entity> for OnBuildingDestroyed {
         &self.0
     }
 }
+
+#[derive(Event, Debug)]
+pub struct OnWaterSpawned(pub Entity);
+
+impl AsRef<Entity> for OnWaterSpawned {
+    fn as_ref(&self) -> &Entity {
+        &self.0
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct OnWaterDestroyed(pub Entity);
+
+impl AsRef<Entity> for OnWaterDestroyed {
+    fn as_ref(&self) -> &Entity {
+        &self.0
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct OnPropSpawned(pub Entity);
+
+impl AsRef<Entity> for OnPropSpawned {
+    fn as_ref(&self) -> &Entity {
+        &self.0
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct OnPropDestroyed(pub Entity);
+
+impl AsRef<Entity> for OnPropDestroyed {
+    fn as_ref(&self) -> &Entity {
+        &self.0
+    }
+}
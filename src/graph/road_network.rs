@@ -0,0 +1,88 @@
+use crate::{
+    graph::pathfinding::{PathGraph, Pathfinder, RoadGraphView},
+    types::{building::Building, intersection::Intersection, road_segment::RoadSegment},
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+pub type RoadGraph<'a, 'w, 's> = RoadGraphView<
+    &'a Query<'w, 's, (Entity, &'static Building)>,
+    &'a Query<'w, 's, (Entity, &'static RoadSegment)>,
+    &'a Query<'w, 's, (Entity, &'static Intersection)>,
+>;
+
+// Read-only view of the road graph for tools, AI and UI. Systems that also need to mutate
+// segments or intersections (such as marking observers) keep their own queries.
+#[derive(SystemParam)]
+pub struct RoadNetwork<'w, 's> {
+    buildings: Query<'w, 's, (Entity, &'static Building)>,
+    segments: Query<'w, 's, (Entity, &'static RoadSegment)>,
+    intersections: Query<'w, 's, (Entity, &'static Intersection)>,
+    pathfinder: Res<'w, Pathfinder>,
+}
+
+impl<'w, 's> RoadNetwork<'w, 's> {
+    pub fn graph(&self) -> RoadGraph<'_, 'w, 's> {
+        RoadGraphView::new(&self.buildings, &self.segments, &self.intersections)
+    }
+
+    pub fn building(&self, entity: Entity) -> Option<&Building> {
+        self.buildings.get(entity).ok().map(|(_, building)| building)
+    }
+
+    pub fn segment(&self, entity: Entity) -> Option<&RoadSegment> {
+        self.segments.get(entity).ok().map(|(_, segment)| segment)
+    }
+
+    pub fn intersection(&self, entity: Entity) -> Option<&Intersection> {
+        self.intersections.get(entity).ok().map(|(_, intersection)| intersection)
+    }
+
+    pub fn buildings(&self) -> impl Iterator<Item = (Entity, &Building)> {
+        self.buildings.iter()
+    }
+
+    pub fn segments(&self) -> impl Iterator<Item = (Entity, &RoadSegment)> {
+        self.segments.iter()
+    }
+
+    pub fn position(&self, entity: Entity) -> Option<Vec3> {
+        self.graph().position(entity)
+    }
+
+    // Everything directly connected to `entity`, ignoring closures. Segments list both their
+    // end intersections and the buildings they serve.
+    pub fn neighbors(&self, entity: Entity) -> Vec<Entity> {
+        if let Some(building) = self.building(entity) {
+            building.roads.iter().copied().collect()
+        } else if let Some(segment) = self.segment(entity) {
+            segment.ends.iter().flatten().chain(&segment.dests).copied().collect()
+        } else if let Some(intersection) = self.intersection(entity) {
+            intersection.roads.iter().flatten().copied().collect()
+        } else {
+            Vec::new()
+        }
+    }
+
+    pub fn segment_between(&self, a: Entity, b: Entity) -> Option<Entity> {
+        self.neighbors(a)
+            .into_iter()
+            .filter(|&road| self.segments.contains(road))
+            .find(|&road| self.neighbors(road).contains(&b))
+    }
+
+    pub fn nearest_road(&self, pos: Vec3) -> Option<Entity> {
+        self.segments
+            .iter()
+            .map(|(entity, segment)| (entity, segment.area.distance_to_point_3d(pos)))
+            .min_by(|(_, a), (_, b)| a.total_cmp(b))
+            .map(|(entity, _)| entity)
+    }
+
+    pub fn path(&self, from: Entity, to: Entity) -> Option<Vec<Entity>> {
+        self.path_with(&self.pathfinder, from, to)
+    }
+
+    pub fn path_with(&self, pathfinder: &Pathfinder, from: Entity, to: Entity) -> Option<Vec<Entity>> {
+        pathfinder.find_path(&self.graph(), from, to)
+    }
+}
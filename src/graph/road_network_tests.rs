@@ -0,0 +1,134 @@
+use crate::{
+    graph::road_network::RoadNetwork,
+    grid::{grid::Grid, grid_area::GridArea, orientation::GAxis},
+    save::{
+        save::{SaveObject, SAVEFILE},
+        save_tests::{area, headless_app, MemoryStorage},
+        storage::SaveStorage,
+    },
+    tools::{
+        building_tool::RequestBuilding,
+        road_events::{RequestIntersection, RequestRoad},
+    },
+    types::{building::BuildingKind, road_segment::RoadSegment},
+};
+use bevy::{ecs::system::SystemState, prelude::*};
+use std::sync::Arc;
+
+// Two intersections joined by a road along X, a road running off the first one along Z, and a
+// building beside each road.
+pub(crate) fn small_town() -> App {
+    let store = Arc::new(MemoryStorage::default());
+    store.write(SAVEFILE, &SaveObject::new().to_json().unwrap()).unwrap();
+
+    let mut app = headless_app(store);
+    app.update();
+    app.world_mut().send_event(RequestIntersection::new(area((0, 0), (1, 1))));
+    app.world_mut().send_event(RequestIntersection::new(area((10, 0), (11, 1))));
+    app.world_mut().send_event(RequestRoad::new(area((2, 0), (9, 1)), GAxis::X));
+    app.world_mut().send_event(RequestRoad::new(area((0, 2), (1, 9)), GAxis::Z));
+    app.update();
+    app.world_mut().send_event(RequestBuilding::new(area((3, 2), (4, 3))).with_seed(1));
+    app.world_mut().send_event(RequestBuilding::of_kind(area((2, 4), (3, 5)), BuildingKind::Shop).with_seed(2));
+    for _ in 0..5 {
+        app.update();
+    }
+    app
+}
+
+pub(crate) fn at(app: &mut App, area: GridArea) -> Entity {
+    let world = app.world_mut();
+    world.query::<&Grid>().single(world).entity_at(area.min).unwrap().unwrap()
+}
+
+fn with_network<T>(app: &mut App, query: impl FnOnce(&RoadNetwork) -> T) -> T {
+    let mut state = SystemState::<RoadNetwork>::new(app.world_mut());
+    query(&state.get(app.world()))
+}
+
+#[test]
+fn neighbors_list_what_is_connected() {
+    let mut app = small_town();
+    let corner = at(&mut app, area((0, 0), (1, 1)));
+    let along_x = at(&mut app, area((2, 0), (9, 1)));
+    let along_z = at(&mut app, area((0, 2), (1, 9)));
+    let house = at(&mut app, area((3, 2), (4, 3)));
+
+    let corner_neighbors = with_network(&mut app, |network| network.neighbors(corner));
+    assert!(corner_neighbors.contains(&along_x) && corner_neighbors.contains(&along_z));
+    assert_eq!(with_network(&mut app, |network| network.neighbors(house)), vec![along_x]);
+    assert!(with_network(&mut app, |network| network.neighbors(along_x)).contains(&house));
+}
+
+#[test]
+fn segment_between_finds_the_joining_road() {
+    let mut app = small_town();
+    let corner = at(&mut app, area((0, 0), (1, 1)));
+    let far_corner = at(&mut app, area((10, 0), (11, 1)));
+    let along_x = at(&mut app, area((2, 0), (9, 1)));
+    let shop = at(&mut app, area((2, 4), (3, 5)));
+
+    assert_eq!(
+        with_network(&mut app, |network| network.segment_between(corner, far_corner)),
+        Some(along_x)
+    );
+    assert_eq!(
+        with_network(&mut app, |network| network.segment_between(far_corner, shop)),
+        None
+    );
+}
+
+#[test]
+fn nearest_road_is_the_closest_segment() {
+    let mut app = small_town();
+    let along_x = at(&mut app, area((2, 0), (9, 1)));
+    let along_z = at(&mut app, area((0, 2), (1, 9)));
+
+    let beside_x = area((7, 3), (7, 3)).center();
+    let beside_z = area((-2, 8), (-2, 8)).center();
+    assert_eq!(
+        with_network(&mut app, |network| network.nearest_road(beside_x)),
+        Some(along_x)
+    );
+    assert_eq!(
+        with_network(&mut app, |network| network.nearest_road(beside_z)),
+        Some(along_z)
+    );
+}
+
+#[test]
+fn path_steps_only_between_neighbors() {
+    let mut app = small_town();
+    let house = at(&mut app, area((3, 2), (4, 3)));
+    let shop = at(&mut app, area((2, 4), (3, 5)));
+
+    let path = with_network(&mut app, |network| {
+        let path = network.path(house, shop).unwrap();
+        assert!(path.windows(2).all(|step| network.neighbors(step[0]).contains(&step[1])));
+        path
+    });
+    assert_eq!((path.first(), path.last()), (Some(&house), Some(&shop)));
+}
+
+#[test]
+fn one_way_roads_are_only_driven_towards_their_exit() {
+    let mut app = small_town();
+    let corner = at(&mut app, area((0, 0), (1, 1)));
+    let along_z = at(&mut app, area((0, 2), (1, 9)));
+    let house = at(&mut app, area((3, 2), (4, 3)));
+    let shop = at(&mut app, area((2, 4), (3, 5)));
+
+    let set_one_way = |app: &mut App, towards_corner: bool| {
+        let mut segment = app.world_mut().get_mut::<RoadSegment>(along_z).unwrap();
+        let corner_slot = segment.ends.iter().position(|&end| end == Some(corner)).unwrap();
+        segment.one_way = Some(if towards_corner { corner_slot } else { 1 - corner_slot });
+    };
+
+    set_one_way(&mut app, true);
+    assert!(with_network(&mut app, |network| network.path(house, shop)).is_none());
+    assert!(with_network(&mut app, |network| network.path(shop, house)).is_some());
+
+    set_one_way(&mut app, false);
+    assert!(with_network(&mut app, |network| network.path(house, shop)).is_some());
+    assert!(with_network(&mut app, |network| network.path(shop, house)).is_none());
+}
@@ -0,0 +1,252 @@
+use crate::{
+    determinism::determinism::SimTick,
+    graph::road_graph::{link_building, link_intersection, link_road},
+    grid::grid::Grid,
+    schedule::UpdateStage,
+    types::{building::Building, intersection::Intersection, road_segment::RoadSegment},
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::Serialize;
+
+const DEFAULT_INTERVAL_SECONDS: f32 = 5.0;
+
+pub struct GraphValidatorPlugin;
+
+impl Plugin for GraphValidatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GraphValidatorSettings::default()).add_systems(
+            Update,
+            validate_graph.in_set(UpdateStage::UpdateView).run_if(|settings: Res<GraphValidatorSettings>| settings.enabled),
+        );
+    }
+}
+
+// On by default in debug builds only, since a scan walks every link in the graph.
+#[derive(Resource, Debug)]
+pub struct GraphValidatorSettings {
+    pub enabled: bool,
+    pub interval_seconds: f32,
+    pub auto_repair: bool,
+}
+
+impl Default for GraphValidatorSettings {
+    fn default() -> Self {
+        Self {
+            enabled: cfg!(debug_assertions),
+            interval_seconds: DEFAULT_INTERVAL_SECONDS,
+            auto_repair: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+pub enum GraphIssue {
+    // `from` links to an entity that is gone or is not the kind of node the link expects.
+    Dangling { from: Entity, to: Entity },
+    // `from` links to `to`, but `to` does not link back.
+    Asymmetric { from: Entity, to: Entity },
+    OrphanedIntersection(Entity),
+}
+
+#[derive(Debug, Serialize)]
+struct GraphReport {
+    tick: u64,
+    issues: Vec<GraphIssue>,
+    repaired: bool,
+    remaining: Vec<GraphIssue>,
+}
+
+struct GraphNodes {
+    segments: Vec<Entity>,
+    intersections: Vec<Entity>,
+    buildings: Vec<Entity>,
+}
+
+// Every node of the road graph and the links between them.
+#[derive(SystemParam)]
+pub struct GraphLinks<'w, 's> {
+    grid_query: Query<'w, 's, &'static Grid>,
+    segments: Query<'w, 's, Entity, With<RoadSegment>>,
+    intersections: Query<'w, 's, Entity, With<Intersection>>,
+    buildings: Query<'w, 's, Entity, With<Building>>,
+    segment_query: Query<'w, 's, &'static mut RoadSegment>,
+    inter_query: Query<'w, 's, &'static mut Intersection>,
+    building_query: Query<'w, 's, &'static mut Building>,
+}
+
+impl GraphLinks<'_, '_> {
+    fn nodes(&self) -> GraphNodes {
+        GraphNodes {
+            segments: sorted(&self.segments),
+            intersections: sorted(&self.intersections),
+            buildings: sorted(&self.buildings),
+        }
+    }
+
+    pub fn scan(&self) -> Vec<GraphIssue> {
+        scan_graph(&self.nodes(), &self.segment_query, &self.inter_query, &self.building_query)
+    }
+
+    pub fn relink(&mut self) {
+        relink_graph(
+            &self.nodes(),
+            self.grid_query.single(),
+            &mut self.segment_query,
+            &mut self.inter_query,
+            &mut self.building_query,
+        );
+    }
+}
+
+// Runs in `UpdateStage::UpdateView`, the first stage of the frame. The last frame's destroys have all
+// been applied by then and nothing this frame has relinked the graph yet, so entities half way
+// through being destroyed are never reported.
+fn validate_graph(
+    settings: Res<GraphValidatorSettings>,
+    time: Res<Time>,
+    tick: Res<SimTick>,
+    mut elapsed: Local<f32>,
+    mut links: GraphLinks,
+) {
+    *elapsed += time.delta_seconds();
+    if *elapsed < settings.interval_seconds {
+        return;
+    }
+    *elapsed = 0.0;
+
+    let issues = links.scan();
+    if issues.is_empty() {
+        return;
+    }
+
+    let mut report = GraphReport {
+        tick: tick.0,
+        issues,
+        repaired: false,
+        remaining: Vec::new(),
+    };
+
+    if settings.auto_repair {
+        links.relink();
+        report.repaired = true;
+        report.remaining = links.scan();
+    }
+
+    match serde_json::to_string(&report) {
+        Ok(json) => println!("Graph validator: {}", json),
+        Err(error) => println!("Failed to serialize graph report: {}", error),
+    }
+}
+
+fn sorted<T: Component>(query: &Query<Entity, With<T>>) -> Vec<Entity> {
+    let mut entities: Vec<Entity> = query.iter().collect();
+    entities.sort();
+    entities
+}
+
+fn scan_graph(
+    nodes: &GraphNodes,
+    segment_query: &Query<&mut RoadSegment>,
+    inter_query: &Query<&mut Intersection>,
+    building_query: &Query<&mut Building>,
+) -> Vec<GraphIssue> {
+    let mut issues = Vec::new();
+
+    for &entity in &nodes.segments {
+        let Ok(segment) = segment_query.get(entity) else {
+            continue;
+        };
+
+        for &end in segment.ends.iter().flatten() {
+            match inter_query.get(end) {
+                Err(_) => issues.push(GraphIssue::Dangling { from: entity, to: end }),
+                Ok(inter) if !inter.roads.contains(&Some(entity)) => {
+                    issues.push(GraphIssue::Asymmetric { from: entity, to: end })
+                }
+                Ok(_) => {}
+            }
+        }
+
+        for &dest in &segment.dests {
+            match building_query.get(dest) {
+                Err(_) => issues.push(GraphIssue::Dangling { from: entity, to: dest }),
+                Ok(building) if !building.roads.contains(&entity) => {
+                    issues.push(GraphIssue::Asymmetric { from: entity, to: dest })
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+
+    for &entity in &nodes.intersections {
+        let Ok(inter) = inter_query.get(entity) else {
+            continue;
+        };
+
+        if inter.roads.iter().all(Option::is_none) {
+            issues.push(GraphIssue::OrphanedIntersection(entity));
+        }
+
+        for &road in inter.roads.iter().flatten() {
+            match segment_query.get(road) {
+                Err(_) => issues.push(GraphIssue::Dangling { from: entity, to: road }),
+                Ok(segment) if !segment.ends.contains(&Some(entity)) => {
+                    issues.push(GraphIssue::Asymmetric { from: entity, to: road })
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+
+    for &entity in &nodes.buildings {
+        let Ok(building) = building_query.get(entity) else {
+            continue;
+        };
+
+        for &road in &building.roads {
+            match segment_query.get(road) {
+                Err(_) => issues.push(GraphIssue::Dangling { from: entity, to: road }),
+                Ok(segment) if !segment.dests.contains(&entity) => {
+                    issues.push(GraphIssue::Asymmetric { from: entity, to: road })
+                }
+                Ok(_) => {}
+            }
+        }
+    }
+
+    issues
+}
+
+// Throws away every link and rebuilds them from what the grid says is adjacent.
+fn relink_graph(
+    nodes: &GraphNodes,
+    grid: &Grid,
+    segment_query: &mut Query<&mut RoadSegment>,
+    inter_query: &mut Query<&mut Intersection>,
+    building_query: &mut Query<&mut Building>,
+) {
+    for mut segment in segment_query.iter_mut() {
+        segment.ends = [None; 2];
+        segment.dests.clear();
+    }
+
+    for mut inter in inter_query.iter_mut() {
+        inter.roads = [None; 4];
+    }
+
+    for mut building in building_query.iter_mut() {
+        building.roads.clear();
+    }
+
+    for &entity in &nodes.segments {
+        link_road(entity, grid, segment_query, inter_query, building_query);
+    }
+
+    for &entity in &nodes.intersections {
+        link_intersection(entity, grid, segment_query, inter_query);
+    }
+
+    for &entity in &nodes.buildings {
+        link_building(entity, grid, segment_query, building_query);
+    }
+}
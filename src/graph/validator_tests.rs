@@ -0,0 +1,53 @@
+use crate::{
+    graph::{
+        road_network_tests::{at, small_town},
+        validator::{GraphIssue, GraphLinks},
+    },
+    save::save_tests::area,
+    types::{building::Building, intersection::Intersection, road_segment::RoadSegment},
+};
+use bevy::{ecs::system::SystemState, prelude::*};
+
+fn with_links<T>(app: &mut App, run: impl FnOnce(&mut GraphLinks) -> T) -> T {
+    let mut state = SystemState::<GraphLinks>::new(app.world_mut());
+    let result = run(&mut state.get_mut(app.world_mut()));
+    state.apply(app.world_mut());
+    result
+}
+
+#[test]
+fn relink_restores_broken_links() {
+    let mut app = small_town();
+    let corner = at(&mut app, area((0, 0), (1, 1)));
+    let along_x = at(&mut app, area((2, 0), (9, 1)));
+    let house = at(&mut app, area((3, 2), (4, 3)));
+
+    assert!(with_links(&mut app, |links| links.scan()).is_empty());
+    let roads = app.world().get::<Intersection>(corner).unwrap().roads;
+    let dests = app.world().get::<RoadSegment>(along_x).unwrap().dests.clone();
+
+    // The corner forgets the road along X, and the road forgets the house beside it.
+    for road in &mut app.world_mut().get_mut::<Intersection>(corner).unwrap().roads {
+        if *road == Some(along_x) {
+            *road = None;
+        }
+    }
+    app.world_mut().get_mut::<RoadSegment>(along_x).unwrap().dests.retain(|&dest| dest != house);
+
+    let issues = with_links(&mut app, |links| links.scan());
+    assert!(issues.contains(&GraphIssue::Asymmetric {
+        from: along_x,
+        to: corner
+    }));
+    assert!(issues.contains(&GraphIssue::Asymmetric {
+        from: house,
+        to: along_x
+    }));
+
+    with_links(&mut app, |links| links.relink());
+
+    assert!(with_links(&mut app, |links| links.scan()).is_empty());
+    assert_eq!(app.world().get::<Intersection>(corner).unwrap().roads, roads);
+    assert_eq!(app.world().get::<RoadSegment>(along_x).unwrap().dests, dests);
+    assert!(app.world().get::<Building>(house).unwrap().roads.contains(&along_x));
+}
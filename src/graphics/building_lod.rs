@@ -0,0 +1,193 @@
+use crate::{
+    graph::road_graph_events::{OnBuildingDestroyed, OnBuildingSpawned},
+    graphics::{camera::PlayerCameraController, weather::BuildingWindows},
+    grid::grid::Grid,
+    schedule::UpdateStage,
+    types::building::Building,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+const DEFAULT_LOD_DISTANCE: f32 = 80.0;
+
+pub struct BuildingLodPlugin;
+
+impl Plugin for BuildingLodPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BuildingLodSettings::default()).insert_resource(BuildingLod::default()).add_systems(
+            Update,
+            (track_building_chunks, rebuild_merged_chunks, swap_building_lod).chain().in_set(UpdateStage::Visualize),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct BuildingLodSettings {
+    pub enabled: bool,
+    pub distance: f32,
+}
+
+impl Default for BuildingLodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            distance: DEFAULT_LOD_DISTANCE,
+        }
+    }
+}
+
+// Buildings are grouped by grid chunk. Each chunk keeps one merged mesh per material, drawn in place
+// of its individual buildings (and their roof details) once the camera is far enough away.
+#[derive(Resource, Debug, Default)]
+struct BuildingLod {
+    chunk_of: HashMap<Entity, IVec2>,
+    members: HashMap<IVec2, Vec<Entity>>,
+    merged: HashMap<IVec2, Vec<Entity>>,
+    far: HashMap<IVec2, bool>,
+    dirty: HashSet<IVec2>,
+}
+
+#[derive(Component, Debug)]
+struct MergedBuildings;
+
+struct MergedGroup {
+    mesh: Mesh,
+    material: Handle<StandardMaterial>,
+    windows: BuildingWindows,
+}
+
+fn track_building_chunks(
+    mut lod: ResMut<BuildingLod>,
+    building_query: Query<&Building>,
+    mut spawned: EventReader<OnBuildingSpawned>,
+    mut destroyed: EventReader<OnBuildingDestroyed>,
+) {
+    for &OnBuildingSpawned(entity) in spawned.read() {
+        let Ok(building) = building_query.get(entity) else {
+            continue;
+        };
+
+        let chunk = Grid::chunk_of(building.area.min);
+        lod.chunk_of.insert(entity, chunk);
+        lod.members.entry(chunk).or_default().push(entity);
+        lod.dirty.insert(chunk);
+    }
+
+    for &OnBuildingDestroyed(entity) in destroyed.read() {
+        let Some(chunk) = lod.chunk_of.remove(&entity) else {
+            continue;
+        };
+
+        if let Some(members) = lod.members.get_mut(&chunk) {
+            members.retain(|&member| member != entity);
+        }
+        lod.dirty.insert(chunk);
+    }
+}
+
+fn rebuild_merged_chunks(
+    mut commands: Commands,
+    mut lod: ResMut<BuildingLod>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    building_query: Query<(&Handle<Mesh>, &Handle<StandardMaterial>, &Transform, &BuildingWindows)>,
+) {
+    let dirty: Vec<IVec2> = lod.dirty.drain().collect();
+
+    for chunk in dirty {
+        for merged in lod.merged.remove(&chunk).into_iter().flatten() {
+            commands.entity(merged).despawn_recursive();
+        }
+        lod.far.remove(&chunk);
+
+        let mut groups: HashMap<AssetId<StandardMaterial>, MergedGroup> = HashMap::new();
+
+        for &member in lod.members.get(&chunk).into_iter().flatten() {
+            let Ok((mesh, material, transform, windows)) = building_query.get(member) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh).map(|mesh| mesh.clone().transformed_by(*transform)) else {
+                continue;
+            };
+
+            match groups.get_mut(&windows.day.id()) {
+                Some(group) => group.mesh.merge(&mesh),
+                None => {
+                    let windows = BuildingWindows {
+                        day: windows.day.clone(),
+                        night: windows.night.clone(),
+                    };
+                    groups.insert(
+                        windows.day.id(),
+                        MergedGroup {
+                            mesh,
+                            material: material.clone(),
+                            windows,
+                        },
+                    );
+                }
+            }
+        }
+
+        let merged = groups
+            .into_values()
+            .map(|group| {
+                commands
+                    .spawn((
+                        PbrBundle {
+                            mesh: meshes.add(group.mesh),
+                            material: group.material,
+                            visibility: Visibility::Hidden,
+                            ..default()
+                        },
+                        group.windows,
+                        MergedBuildings,
+                    ))
+                    .id()
+            })
+            .collect();
+        lod.merged.insert(chunk, merged);
+    }
+}
+
+fn swap_building_lod(
+    mut lod: ResMut<BuildingLod>,
+    settings: Res<BuildingLodSettings>,
+    camera_query: Query<&GlobalTransform, With<PlayerCameraController>>,
+    mut visibility_query: Query<&mut Visibility>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+    let eye = camera.translation();
+
+    let BuildingLod {
+        members, merged, far, ..
+    } = &mut *lod;
+
+    for (&chunk, merged) in merged.iter() {
+        let distant = settings.enabled && Grid::chunk_area(chunk).center().distance(eye) > settings.distance;
+        if far.get(&chunk) == Some(&distant) {
+            continue;
+        }
+        far.insert(chunk, distant);
+
+        let (shown, hidden) = match distant {
+            true => (Visibility::Inherited, Visibility::Hidden),
+            false => (Visibility::Hidden, Visibility::Inherited),
+        };
+
+        for &entity in merged {
+            if let Ok(mut visibility) = visibility_query.get_mut(entity) {
+                *visibility = shown;
+            }
+        }
+
+        for &entity in members.get(&chunk).into_iter().flatten() {
+            if let Ok(mut visibility) = visibility_query.get_mut(entity) {
+                *visibility = hidden;
+            }
+        }
+    }
+}
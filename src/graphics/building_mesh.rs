@@ -0,0 +1,199 @@
+use crate::types::building::BuildingKind;
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+
+const FLOOR_HEIGHT: f32 = 0.3;
+const WINDOW_SPACING: f32 = 0.25;
+const MIN_TIER_SIZE: f32 = 0.35;
+const ROOF_PROP_MARGIN: f32 = 0.15;
+
+pub enum RoofProp {
+    AirConditioner(Vec3),
+    WaterTower(Vec3),
+}
+
+// A building's generated shape, in local space with the ground at y = 0. `roof` is the top centre
+// of the highest tier and `roof_size` its footprint, so roof details can be fitted on top.
+pub struct BuildingShape {
+    pub mesh: Mesh,
+    pub material_index: usize,
+    pub roof: Vec3,
+    pub roof_size: Vec2,
+    pub props: Vec<RoofProp>,
+}
+
+// Where in its kind's height range a building from `seed` ends up, from 0 (lowest) to 1 (tallest).
+// Height is the first thing drawn from the seed in `generate_building`.
+pub fn height_fraction(seed: u64) -> f32 {
+    StdRng::seed_from_u64(seed).gen_range(0.0..1.0)
+}
+
+// Everything random about a building comes from its seed, so the same seed always rebuilds the
+// same building after a reload.
+pub fn generate_building(
+    kind: BuildingKind,
+    footprint: Vec2,
+    heights: (f32, f32),
+    materials: usize,
+    seed: u64,
+) -> BuildingShape {
+    let mut rng = StdRng::seed_from_u64(seed);
+    let height = rng.gen_range(heights.0..heights.1);
+    let material_index = rng.gen_range(0..materials.max(1));
+
+    let tiers = match kind {
+        BuildingKind::Office => rng.gen_range(1..=3),
+        BuildingKind::Shop => rng.gen_range(1..=2),
+        _ => 1,
+    };
+
+    let base = footprint * Vec2::new(rng.gen_range(0.8..=1.0), rng.gen_range(0.8..=1.0));
+    let slack = (footprint - base) / 2.0;
+    let mut center = Vec2::new(rng.gen_range(-slack.x..=slack.x), rng.gen_range(-slack.y..=slack.y));
+    let mut size = base;
+    let mut bottom = 0.0;
+
+    let mut builder = BlockMeshBuilder::new(height, kind != BuildingKind::Parking);
+
+    for tier in 0..tiers {
+        let top = match tier + 1 == tiers {
+            true => height,
+            false => bottom + (height - bottom) * rng.gen_range(0.4..0.7),
+        };
+        builder.push_block(center, size, bottom, top);
+
+        let next = (size * rng.gen_range(0.6..0.8)).max(Vec2::splat(MIN_TIER_SIZE)).min(size);
+        let room = (size - next) / 2.0;
+        center += Vec2::new(rng.gen_range(-room.x..=room.x), rng.gen_range(-room.y..=room.y));
+        size = next;
+        bottom = top;
+    }
+
+    let roof_size = builder.last_size;
+    let roof = builder.last_center.extend(height).xzy();
+    let props = roof_props(kind, roof, roof_size, &mut rng);
+
+    BuildingShape {
+        mesh: builder.build(),
+        material_index,
+        roof,
+        roof_size,
+        props,
+    }
+}
+
+fn roof_props(kind: BuildingKind, roof: Vec3, size: Vec2, rng: &mut StdRng) -> Vec<RoofProp> {
+    if matches!(kind, BuildingKind::House | BuildingKind::Parking) {
+        return Vec::new();
+    }
+
+    let usable = (size / 2.0 - Vec2::splat(ROOF_PROP_MARGIN)).max(Vec2::ZERO);
+    let air_conditioners = rng.gen_range(0..=3);
+    let water_tower = matches!(kind, BuildingKind::Office | BuildingKind::Shop) && rng.gen_bool(0.35);
+
+    let mut spot = || roof + Vec3::new(rng.gen_range(-usable.x..=usable.x), 0.0, rng.gen_range(-usable.y..=usable.y));
+    let mut props: Vec<RoofProp> = (0..air_conditioners).map(|_| RoofProp::AirConditioner(spot())).collect();
+
+    if water_tower {
+        props.push(RoofProp::WaterTower(spot()));
+    }
+
+    props
+}
+
+struct BlockMeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    uvs: Vec<[f32; 2]>,
+    indices: Vec<u32>,
+    floor_height: f32,
+    windows: bool,
+    last_center: Vec2,
+    last_size: Vec2,
+}
+
+impl BlockMeshBuilder {
+    // Window rows repeat once per floor, so taller buildings show more rows rather than stretched ones.
+    fn new(height: f32, windows: bool) -> Self {
+        let floors = (height / FLOOR_HEIGHT).round().max(1.0);
+
+        Self {
+            positions: Vec::new(),
+            normals: Vec::new(),
+            uvs: Vec::new(),
+            indices: Vec::new(),
+            floor_height: height / floors,
+            windows,
+            last_center: Vec2::ZERO,
+            last_size: Vec2::ZERO,
+        }
+    }
+
+    fn push_block(&mut self, center: Vec2, size: Vec2, bottom: f32, top: f32) {
+        let half = Vec3::new(size.x / 2.0, (top - bottom) / 2.0, size.y / 2.0);
+        let middle = Vec3::new(center.x, (top + bottom) / 2.0, center.y);
+
+        for normal in [Vec3::X, Vec3::NEG_X, Vec3::Z, Vec3::NEG_Z] {
+            let right = Vec3::Y.cross(normal);
+            let face = middle + normal * half;
+            let width = right.abs().dot(half);
+            let up = Vec3::Y * half.y;
+
+            let (u, v) = match self.windows {
+                true => (
+                    2.0 * width / WINDOW_SPACING,
+                    (bottom / self.floor_height, top / self.floor_height),
+                ),
+                false => (0.0, (0.0, 0.0)),
+            };
+
+            self.push_quad(
+                [
+                    face - right * width - up,
+                    face + right * width - up,
+                    face + right * width + up,
+                    face - right * width + up,
+                ],
+                normal,
+                [[0.0, v.0], [u, v.0], [u, v.1], [0.0, v.1]],
+            );
+        }
+
+        let roof = middle.with_y(top);
+        self.push_quad(
+            [
+                roof + Vec3::new(-half.x, 0.0, half.z),
+                roof + Vec3::new(half.x, 0.0, half.z),
+                roof + Vec3::new(half.x, 0.0, -half.z),
+                roof + Vec3::new(-half.x, 0.0, -half.z),
+            ],
+            Vec3::Y,
+            [[0.0; 2]; 4],
+        );
+
+        self.last_center = center;
+        self.last_size = size;
+    }
+
+    fn push_quad(&mut self, corners: [Vec3; 4], normal: Vec3, uvs: [[f32; 2]; 4]) {
+        let start = self.positions.len() as u32;
+        self.positions.extend(corners.map(|corner| corner.to_array()));
+        self.normals.extend([normal.to_array(); 4]);
+        self.uvs.extend(uvs);
+        self.indices.extend([0, 1, 2, 0, 2, 3].map(|index| start + index));
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, self.uvs)
+            .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
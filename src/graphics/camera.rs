@@ -1,12 +1,21 @@
 use std::ops::Range;
 
-use crate::grid::grid::*;
+use crate::{
+    graph::road_network::RoadNetwork,
+    graphics::camera_events::FocusOn,
+    grid::terrain::Terrain,
+    input::keymap::{Action, Actions},
+    tools::toolbar::ToolState,
+    types::vehicle::Vehicle,
+    ui::egui::MouseOver,
+};
 use bevy::{
     core_pipeline::{bloom::BloomSettings, tonemapping::Tonemapping},
     core_pipeline::{
         fxaa::Fxaa,
         prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass},
     },
+    ecs::system::SystemParam,
     input::mouse::MouseWheel,
     pbr::ClusterConfig,
     prelude::*,
@@ -17,6 +26,21 @@ const KEYBOARD_PAN_SPEED: f32 = 10.0;
 const KEYBOARD_ROTATE_SPEED: f32 = 1.0;
 const MOUSE_PAN_SPEED: f32 = 5.0;
 const MOUSE_ROTATE_SPEED: f32 = 0.25;
+const FOLLOW_SMOOTHING: f32 = 5.0;
+const GLIDE_FRICTION: f32 = 4.0;
+const GLIDE_STOP_SPEED: f32 = 0.05;
+const TWEEN_SECONDS: f32 = 0.75;
+const BOOKMARK_KEYS: [KeyCode; 9] = [
+    KeyCode::Digit1,
+    KeyCode::Digit2,
+    KeyCode::Digit3,
+    KeyCode::Digit4,
+    KeyCode::Digit5,
+    KeyCode::Digit6,
+    KeyCode::Digit7,
+    KeyCode::Digit8,
+    KeyCode::Digit9,
+];
 
 #[cfg(target_arch = "wasm32")]
 const SCROLL_SPEED: f32 = 5.0;
@@ -33,6 +57,18 @@ pub struct PlayerCameraController {
     camera_center_ground_position: Vec3,
     pub keyboard_panning_in_progress: bool,
     pub keyboard_rotating_in_progress: bool,
+    pub edge_panning_in_progress: bool,
+    grab_point: Option<Vec3>,
+    glide: Vec3,
+    pub following: Option<Entity>,
+    tween: Option<CameraTween>,
+}
+
+#[derive(Clone, Copy, Debug)]
+struct CameraTween {
+    from: Transform,
+    to: Transform,
+    elapsed: f32,
 }
 
 impl PlayerCameraController {
@@ -45,11 +81,36 @@ impl PlayerCameraController {
             camera_center_ground_position: Vec3::ZERO,
             keyboard_panning_in_progress: false,
             keyboard_rotating_in_progress: false,
+            edge_panning_in_progress: false,
+            grab_point: None,
+            glide: Vec3::ZERO,
+            following: None,
+            tween: None,
         }
     }
 }
 
 impl PlayerCameraController {
+    pub fn tween_to(&mut self, from: &Transform, to: Transform) {
+        self.tween = Some(CameraTween {
+            from: *from,
+            to,
+            elapsed: 0.0,
+        });
+        self.following = None;
+    }
+
+    // Slides the camera without turning it so that `target` ends up in the middle of the view.
+    pub fn focus(&mut self, transform: &Transform, target: Vec3) {
+        let mut to = *transform;
+        to.translation += (target - self.camera_center_ground_position).with_y(0.0);
+        self.tween_to(transform, to);
+    }
+
+    pub fn ground_focus(&self) -> Vec3 {
+        self.camera_center_ground_position
+    }
+
     pub fn is_moving(&self) -> bool {
         self.mouse_panning_in_progress
             || self.mouse_rotating_in_progress
@@ -58,17 +119,125 @@ impl PlayerCameraController {
     }
 }
 
+// The ground under the pointer, found by casting from the player camera onto the terrain. Tools that
+// place, paint or pick under the cursor all start from here.
+#[derive(SystemParam)]
+pub struct CursorPick<'w, 's> {
+    camera_query: Query<'w, 's, (&'static Camera, &'static GlobalTransform, &'static PlayerCameraController)>,
+    terrain: Res<'w, Terrain>,
+    actions: Actions<'w>,
+}
+
+impl CursorPick<'_, '_> {
+    // The pointer ray along with how far down it the ground is.
+    pub fn ground_hit(&self) -> Option<(Ray3d, f32)> {
+        let (camera, camera_transform, _) = self.camera_query.get_single().ok()?;
+        ground_hit(camera, camera_transform, &self.terrain, self.actions.pointer_position()?)
+    }
+
+    pub fn ground(&self) -> Option<Vec3> {
+        self.ground_hit().map(|(ray, distance)| ray.get_point(distance))
+    }
+
+    pub fn camera_moving(&self) -> bool {
+        self.camera_query.get_single().is_ok_and(|(_, _, controller)| controller.is_moving())
+    }
+
+    pub fn terrain(&self) -> &Terrain {
+        &self.terrain
+    }
+}
+
+// Where the ray through `position` on screen meets the terrain, and how far along the ray that is.
+fn ground_hit(
+    camera: &Camera,
+    camera_transform: &GlobalTransform,
+    terrain: &Terrain,
+    position: Vec2,
+) -> Option<(Ray3d, f32)> {
+    let ray = camera.viewport_to_world(camera_transform, position)?;
+    Some((ray, terrain.intersect(ray)?))
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum PanMode {
+    Drag,
+    Grab,
+}
+
+impl PanMode {
+    pub const ALL: [PanMode; 2] = [PanMode::Drag, PanMode::Grab];
+}
+
+// `Drag` moves the camera by how far the cursor moved. `Grab` keeps the ground point that was
+// clicked pinned under the cursor and lets the camera glide on for a moment after letting go.
+// Edge panning moves the camera while the cursor rests within `edge_margin` pixels of the window
+// border.
+#[derive(Resource, Debug)]
+pub struct CameraPanSettings {
+    pub mode: PanMode,
+    pub edge_panning: bool,
+    pub edge_margin: f32,
+    pub edge_speed: f32,
+}
+
+impl Default for CameraPanSettings {
+    fn default() -> Self {
+        Self {
+            mode: PanMode::Drag,
+            edge_panning: false,
+            edge_margin: 12.0,
+            edge_speed: KEYBOARD_PAN_SPEED,
+        }
+    }
+}
+
 pub struct CameraPlugin;
 
 impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_camera).add_systems(
-            Update,
-            (
-                update_camera_raycast,
-                (keyboard_panning, mouse_zoom, mouse_panning, keyboard_rotating, mouse_rotating),
-            ),
-        );
+        app.insert_resource(CameraBookmarks::default())
+            .insert_resource(CameraPanSettings::default())
+            .add_event::<FocusOn>()
+            .add_systems(Startup, spawn_camera)
+            .add_systems(
+                Update,
+                (
+                    (
+                        update_camera_raycast,
+                        toggle_vehicle_follow,
+                        follow_vehicle,
+                        store_bookmarks,
+                        recall_bookmarks.run_if(in_state(ToolState::View)),
+                        focus_on_entities,
+                        advance_camera_tween,
+                    )
+                        .chain(),
+                    (
+                        keyboard_panning,
+                        edge_panning,
+                        mouse_zoom,
+                        mouse_panning,
+                        keyboard_rotating,
+                        mouse_rotating,
+                    ),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct CameraBookmarks {
+    slots: [Option<Transform>; 9],
+}
+
+impl CameraBookmarks {
+    pub fn recalls(&self, key: KeyCode) -> bool {
+        BOOKMARK_KEYS.iter().position(|&bookmark| bookmark == key).is_some_and(|slot| self.slots[slot].is_some())
+    }
+
+    pub fn slot(&self, slot: usize) -> Option<Transform> {
+        self.slots.get(slot).copied().flatten()
     }
 }
 
@@ -142,27 +311,27 @@ fn spawn_camera(mut commands: Commands) {
     ));
 }
 
-fn keyboard_panning(
-    mut query: Query<(&mut Transform, &mut PlayerCameraController)>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-) {
+fn keyboard_panning(mut query: Query<(&mut Transform, &mut PlayerCameraController)>, actions: Actions, time: Res<Time>) {
     if let Ok((mut transform, mut controller)) = query.get_single_mut() {
         let mut delta = Vec3::ZERO;
 
-        if keyboard.pressed(KeyCode::KeyW) {
+        if actions.pressed(Action::PanForward) {
             delta += transform.forward().as_vec3().with_y(0.0).normalize();
         }
-        if keyboard.pressed(KeyCode::KeyS) {
+        if actions.pressed(Action::PanBack) {
             delta += transform.back().as_vec3().with_y(0.0).normalize();
         }
-        if keyboard.pressed(KeyCode::KeyA) {
+        if actions.pressed(Action::PanLeft) {
             delta += transform.left().as_vec3().with_y(0.0).normalize();
         }
-        if keyboard.pressed(KeyCode::KeyD) {
+        if actions.pressed(Action::PanRight) {
             delta += transform.right().as_vec3().with_y(0.0).normalize();
         }
 
+        let stick = actions.pan_axis();
+        delta += transform.forward().as_vec3().with_y(0.0).normalize() * stick.y;
+        delta += transform.right().as_vec3().with_y(0.0).normalize() * stick.x;
+
         transform.translation += delta * KEYBOARD_PAN_SPEED * time.delta_seconds();
 
         controller.keyboard_panning_in_progress = delta != Vec3::ZERO;
@@ -172,69 +341,144 @@ fn keyboard_panning(
 fn mouse_zoom(
     mut query: Query<&mut Transform, With<PlayerCameraController>>,
     mut mouse_wheel: EventReader<MouseWheel>,
+    actions: Actions,
     time: Res<Time>,
 ) {
     if let Ok(mut transform) = query.get_single_mut() {
-        let mut delta = Vec3::ZERO;
+        // Pinches and the right stick zoom in the same steps as the wheel.
+        let lines = mouse_wheel.read().map(|scroll| scroll.y).sum::<f32>() + actions.zoom();
+        let forward = transform.forward().as_vec3();
+        transform.translation += forward * lines * SCROLL_SPEED * time.delta_seconds();
+    }
+}
 
-        for scroll in mouse_wheel.read() {
-            delta += transform.forward().as_vec3() * scroll.y * SCROLL_SPEED * time.delta_seconds();
-        }
+// Pans while the cursor rests near the window border, unless the cursor is over the UI or a button
+// is held, so drags that reach the edge do not drift the view.
+fn edge_panning(
+    mut query: Query<(&mut Transform, &mut PlayerCameraController)>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    mouse_over: Option<Res<State<MouseOver>>>,
+    windows: Query<&Window>,
+    settings: Res<CameraPanSettings>,
+    time: Res<Time>,
+) {
+    let Ok((mut transform, mut controller)) = query.get_single_mut() else {
+        return;
+    };
+
+    controller.edge_panning_in_progress = false;
+    let over_world = mouse_over.is_some_and(|state| *state.get() == MouseOver::World);
+    if !settings.edge_panning || !over_world || controller.is_moving() || mouse.get_pressed().next().is_some() {
+        return;
+    }
 
-        transform.translation += delta;
+    let Some((window, cursor)) = windows.get_single().ok().and_then(|window| Some((window, window.cursor_position()?)))
+    else {
+        return;
+    };
+
+    let mut delta = Vec3::ZERO;
+    if cursor.x < settings.edge_margin {
+        delta += transform.left().as_vec3().with_y(0.0).normalize();
+    }
+    if cursor.x > window.width() - settings.edge_margin {
+        delta += transform.right().as_vec3().with_y(0.0).normalize();
     }
+    if cursor.y < settings.edge_margin {
+        delta += transform.forward().as_vec3().with_y(0.0).normalize();
+    }
+    if cursor.y > window.height() - settings.edge_margin {
+        delta += transform.back().as_vec3().with_y(0.0).normalize();
+    }
+
+    transform.translation += delta.normalize_or_zero() * settings.edge_speed * time.delta_seconds();
+    controller.edge_panning_in_progress = delta != Vec3::ZERO;
 }
 
 fn mouse_panning(
-    mut query: Query<(&mut Transform, &mut PlayerCameraController)>,
+    mut query: Query<(&mut Transform, &mut PlayerCameraController, &Camera, &GlobalTransform)>,
     mouse: Res<ButtonInput<MouseButton>>,
     keyboard: Res<ButtonInput<KeyCode>>,
     windows: Query<&Window>,
+    terrain: Res<Terrain>,
+    settings: Res<CameraPanSettings>,
     time: Res<Time>,
 ) {
-    if let Ok((mut transform, mut controller)) = query.get_single_mut() {
+    if let Ok((mut transform, mut controller, camera, camera_transform)) = query.get_single_mut() {
         if mouse.just_pressed(MouseButton::Right)
             || (mouse.just_pressed(MouseButton::Left) && keyboard.pressed(KeyCode::AltLeft))
         {
             if let Some(cursor_position) = windows.single().cursor_position() {
                 controller.mouse_panning_last_position = cursor_position;
                 controller.mouse_panning_in_progress = true;
+                controller.grab_point = ground_hit(camera, camera_transform, &terrain, cursor_position)
+                    .map(|(ray, distance)| ray.get_point(distance));
+                controller.glide = Vec3::ZERO;
             }
         } else if mouse.just_released(MouseButton::Right) || (mouse.just_released(MouseButton::Left)) {
             controller.mouse_panning_in_progress = false;
+            controller.grab_point = None;
+        }
+
+        if mouse.get_just_pressed().next().is_some() {
+            controller.glide = Vec3::ZERO;
         }
 
         if controller.mouse_panning_in_progress {
             if let Some(cursor_position) = windows.single().cursor_position() {
-                let delta_mouse_drag = cursor_position - controller.mouse_panning_last_position;
-                let vertical = transform.forward().with_y(0.0).normalize() * delta_mouse_drag.y;
-                let horizontal = transform.left().with_y(0.0).normalize() * delta_mouse_drag.x;
-                let delta = (vertical + horizontal) * MOUSE_PAN_SPEED * time.delta_seconds();
+                let delta = match (settings.mode, controller.grab_point) {
+                    (PanMode::Grab, Some(grab_point)) => {
+                        grab_delta(camera, camera_transform, cursor_position, grab_point).unwrap_or(Vec3::ZERO)
+                    }
+                    _ => {
+                        let delta_mouse_drag = cursor_position - controller.mouse_panning_last_position;
+                        let vertical = transform.forward().with_y(0.0).normalize() * delta_mouse_drag.y;
+                        let horizontal = transform.left().with_y(0.0).normalize() * delta_mouse_drag.x;
+                        (vertical + horizontal) * MOUSE_PAN_SPEED * time.delta_seconds()
+                    }
+                };
                 transform.translation += delta;
                 controller.mouse_panning_last_position = cursor_position;
+
+                if settings.mode == PanMode::Grab {
+                    let velocity = delta / time.delta_seconds().max(f32::EPSILON);
+                    controller.glide = controller.glide.lerp(velocity, 0.5);
+                }
+            }
+        } else if controller.glide != Vec3::ZERO {
+            transform.translation += controller.glide * time.delta_seconds();
+            controller.glide *= (-GLIDE_FRICTION * time.delta_seconds()).exp();
+
+            if controller.glide.length() < GLIDE_STOP_SPEED {
+                controller.glide = Vec3::ZERO;
             }
         }
     }
 }
 
-fn keyboard_rotating(
-    mut query: Query<(&mut Transform, &mut PlayerCameraController)>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    time: Res<Time>,
-) {
+// How far the camera has to slide for `grab_point` to sit under the cursor again, measured on the
+// level of the grabbed point so the pan does not jump over hills.
+fn grab_delta(camera: &Camera, camera_transform: &GlobalTransform, cursor: Vec2, grab_point: Vec3) -> Option<Vec3> {
+    let ray = camera.viewport_to_world(camera_transform, cursor)?;
+    let distance = ray.intersect_plane(grab_point, InfinitePlane3d::new(Vec3::Y))?;
+    Some((grab_point - ray.get_point(distance)).with_y(0.0))
+}
+
+fn keyboard_rotating(mut query: Query<(&mut Transform, &mut PlayerCameraController)>, actions: Actions, time: Res<Time>) {
     if let Ok((mut transform, mut controller)) = query.get_single_mut() {
         let mut delta_angle = 0.0f32;
 
-        if keyboard.pressed(KeyCode::KeyQ) {
+        if actions.pressed(Action::RotateLeft) {
             delta_angle += KEYBOARD_ROTATE_SPEED;
         }
-        if keyboard.pressed(KeyCode::KeyE) {
+        if actions.pressed(Action::RotateRight) {
             delta_angle -= KEYBOARD_ROTATE_SPEED;
         }
 
-        if delta_angle != 0.0 {
+        let angle = delta_angle * time.delta_seconds() + actions.rotate();
+        if angle != 0.0 {
             let rotate_point = controller.camera_center_ground_position.with_y(transform.translation.y);
-            let quat = Quat::from_rotation_y(delta_angle * time.delta_seconds());
+            let quat = Quat::from_rotation_y(angle);
             transform.rotate_around(rotate_point, quat);
             controller.keyboard_rotating_in_progress = true;
         } else {
@@ -285,24 +529,148 @@ fn mouse_rotating(
 fn update_camera_raycast(
     camera_query: Query<(&Camera, &GlobalTransform)>,
     mut controller_query: Query<&mut PlayerCameraController>,
-    ground_query: Query<&GlobalTransform, With<Ground>>,
+    terrain: Res<Terrain>,
     windows: Query<&Window>,
 ) {
     let (camera, camera_transform) = camera_query.single();
     let mut controller = controller_query.single_mut();
-    let ground = ground_query.single();
 
     let Ok(window) = windows.get_single() else {
         return;
     };
 
     let window_center = Vec2::new(window.width() / 2.0, window.height() / 2.0);
-    let Some(ray_center) = camera.viewport_to_world(camera_transform, window_center) else {
+    if let Some((ray_center, center_distance)) = ground_hit(camera, camera_transform, &terrain, window_center) {
+        controller.camera_center_ground_position = ray_center.get_point(center_distance);
+    }
+}
+
+fn toggle_vehicle_follow(
+    mut controller_query: Query<&mut PlayerCameraController>,
+    vehicle_query: Query<(Entity, &Transform), With<Vehicle>>,
+    actions: Actions,
+) {
+    if !actions.just_pressed(Action::FollowVehicle) {
+        return;
+    }
+
+    let mut controller = controller_query.single_mut();
+
+    if controller.following.is_some() {
+        controller.following = None;
+    } else {
+        let center = controller.camera_center_ground_position;
+        controller.following = vehicle_query
+            .iter()
+            .min_by(|(_, a), (_, b)| a.translation.distance(center).total_cmp(&b.translation.distance(center)))
+            .map(|(entity, _)| entity);
+    }
+}
+
+fn follow_vehicle(
+    mut controller_query: Query<(&mut Transform, &mut PlayerCameraController), Without<Vehicle>>,
+    vehicle_query: Query<&Transform, With<Vehicle>>,
+    time: Res<Time>,
+) {
+    let (mut transform, mut controller) = controller_query.single_mut();
+
+    let Some(entity) = controller.following else {
+        return;
+    };
+
+    if controller.mouse_panning_in_progress || controller.keyboard_panning_in_progress || controller.edge_panning_in_progress
+    {
+        controller.following = None;
+        return;
+    }
+
+    let Ok(vehicle_transform) = vehicle_query.get(entity) else {
+        controller.following = None;
+        return;
+    };
+
+    let delta = (vehicle_transform.translation - controller.camera_center_ground_position).with_y(0.0);
+    transform.translation += delta * (FOLLOW_SMOOTHING * time.delta_seconds()).min(1.0);
+}
+
+fn store_bookmarks(
+    query: Query<&Transform, With<PlayerCameraController>>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    mut bookmarks: ResMut<CameraBookmarks>,
+) {
+    if !keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    let Ok(transform) = query.get_single() else {
+        return;
+    };
+
+    for (slot, &key) in BOOKMARK_KEYS.iter().enumerate() {
+        if keyboard.just_pressed(key) {
+            bookmarks.slots[slot] = Some(*transform);
+        }
+    }
+}
+
+fn recall_bookmarks(
+    mut query: Query<(&Transform, &mut PlayerCameraController)>,
+    keyboard: Res<ButtonInput<KeyCode>>,
+    bookmarks: Res<CameraBookmarks>,
+) {
+    if keyboard.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    let Ok((transform, mut controller)) = query.get_single_mut() else {
+        return;
+    };
+
+    for (slot, &key) in BOOKMARK_KEYS.iter().enumerate() {
+        if let Some(bookmark) = bookmarks.slots[slot].filter(|_| keyboard.just_pressed(key)) {
+            controller.tween_to(transform, bookmark);
+        }
+    }
+}
+
+fn focus_on_entities(
+    mut query: Query<(&Transform, &mut PlayerCameraController)>,
+    mut event: EventReader<FocusOn>,
+    network: RoadNetwork,
+    transform_query: Query<&GlobalTransform>,
+) {
+    let Ok((transform, mut controller)) = query.get_single_mut() else {
+        return;
+    };
+
+    for &FocusOn(entity) in event.read() {
+        let target =
+            network.position(entity).or_else(|| transform_query.get(entity).ok().map(|target| target.translation()));
+
+        if let Some(target) = target {
+            controller.focus(transform, target);
+        }
+    }
+}
+
+fn advance_camera_tween(mut query: Query<(&mut Transform, &mut PlayerCameraController)>, time: Res<Time>) {
+    let Ok((mut transform, mut controller)) = query.get_single_mut() else {
         return;
     };
 
-    if let Some(center_distance) = ray_center.intersect_plane(ground.translation(), InfinitePlane3d::new(ground.up())) {
-        let center_point = ray_center.get_point(center_distance);
-        controller.camera_center_ground_position = center_point;
+    if controller.is_moving() {
+        controller.tween = None;
+    }
+
+    let Some(mut tween) = controller.tween else {
+        return;
     };
+
+    tween.elapsed += time.delta_seconds();
+    let t = (tween.elapsed / TWEEN_SECONDS).clamp(0.0, 1.0);
+    let eased = t * t * (3.0 - 2.0 * t);
+
+    transform.translation = tween.from.translation.lerp(tween.to.translation, eased);
+    transform.rotation = tween.from.rotation.slerp(tween.to.rotation, eased);
+    controller.tween = (t < 1.0).then_some(tween);
 }
@@ -0,0 +1,4 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct FocusOn(pub Entity);
@@ -0,0 +1,90 @@
+use crate::{
+    graphics::models::Models,
+    grid::{grid_area::GridArea, grid_cell::GridCell},
+    schedule::UpdateStage,
+    types::{building::Building, road_segment::RoadSegment},
+};
+use bevy::prelude::*;
+
+const DRIVEWAY_WIDTH: f32 = 0.4;
+const DRIVEWAY_LENGTH: f32 = 0.35;
+const DRIVEWAY_HEIGHT: f32 = 0.02;
+
+pub struct DrivewaysPlugin;
+
+impl Plugin for DrivewaysPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_driveways.in_set(UpdateStage::Visualize));
+    }
+}
+
+// The entrances the driveways were laid for. They are replaced whenever those change.
+#[derive(Component, Debug)]
+pub struct Driveways {
+    entrances: Vec<(GridCell, GridArea)>,
+    quads: Vec<Entity>,
+}
+
+// A short paved strip at each entrance, running in from the edge of the lot that faces its road.
+fn update_driveways(
+    mut commands: Commands,
+    building_query: Query<(Entity, &Building, Option<&Driveways>)>,
+    segment_query: Query<&RoadSegment>,
+    models: Res<Models>,
+) {
+    for (entity, building, driveways) in &building_query {
+        let mut entrances: Vec<(GridCell, GridArea)> = building
+            .entrances
+            .iter()
+            .filter_map(|(&road, &cell)| Some((cell, segment_query.get(road).ok()?.area)))
+            .collect();
+        entrances.sort_by_key(|(cell, road)| (cell.pos.x, cell.pos.y, road.min.pos.x, road.min.pos.y));
+
+        if driveways.is_some_and(|driveways| driveways.entrances == entrances) {
+            continue;
+        }
+
+        for &quad in driveways.iter().flat_map(|driveways| &driveways.quads) {
+            commands.entity(quad).despawn_recursive();
+        }
+
+        let origin = building.area.center();
+        let quads: Vec<Entity> = entrances
+            .iter()
+            .map(|&(cell, road)| {
+                let outward = facing(building.area, road);
+                let center = cell.center() + outward * (0.5 - DRIVEWAY_LENGTH / 2.0);
+                let size = match outward.x != 0.0 {
+                    true => Vec3::new(DRIVEWAY_LENGTH, 1.0, DRIVEWAY_WIDTH),
+                    false => Vec3::new(DRIVEWAY_WIDTH, 1.0, DRIVEWAY_LENGTH),
+                };
+
+                let quad = commands
+                    .spawn(PbrBundle {
+                        mesh: models.driveway_mesh.clone(),
+                        material: models.driveway_material.clone(),
+                        transform: Transform::from_translation((center - origin).with_y(DRIVEWAY_HEIGHT)).with_scale(size),
+                        ..default()
+                    })
+                    .id();
+                commands.entity(entity).add_child(quad);
+                quad
+            })
+            .collect();
+
+        commands.entity(entity).insert(Driveways { entrances, quads });
+    }
+}
+
+// The direction from the building out to a road along one of its sides.
+fn facing(area: GridArea, road: GridArea) -> Vec3 {
+    if road.max.pos.x < area.min.pos.x {
+        Vec3::NEG_X
+    } else if road.min.pos.x > area.max.pos.x {
+        Vec3::X
+    } else if road.max.pos.y < area.min.pos.y {
+        Vec3::NEG_Z
+    } else {
+        Vec3::Z
+    }
+}
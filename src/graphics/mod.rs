@@ -1,3 +1,12 @@
+pub mod building_lod;
+pub mod building_mesh;
 pub mod camera;
+pub mod camera_events;
+pub mod driveways;
 pub mod models;
+pub mod particles;
+pub mod quality;
+pub mod road_markings;
+pub mod speed_signs;
+pub mod vehicle_instancing;
 pub mod weather;
@@ -1,4 +1,33 @@
-use bevy::prelude::*;
+use crate::types::{
+    building::BuildingKind,
+    prop::PropKind,
+    road_segment::RoadSurface,
+    trailer::TRAILER_LENGTH,
+    vehicle::{TripPurpose, VehicleKind},
+};
+use bevy::{
+    math::Affine2,
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::{ImageAddressMode, ImageSampler, ImageSamplerDescriptor},
+    },
+};
+use rand::{distributions::WeightedIndex, prelude::Distribution, Rng};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::{FRAC_1_SQRT_2, FRAC_PI_4};
+use std::fs::File;
+use std::io::BufReader;
+
+const VEHICLE_MANIFEST: &str = "assets/models/vehicles.json";
+const ROAD_SURFACE_MANIFEST: &str = "assets/models/road_surfaces.json";
+pub const SIGN_POLE_HEIGHT: f32 = 0.6;
+const WINDOW_GLOW: LinearRgba = LinearRgba::rgb(2.7, 1.95, 0.9);
+const WINDOW_TEXTURE_SIZE: u32 = 8;
+const WINDOW_PATTERN: u32 = 4;
+// One row of bits per floor of the window pattern, set for the windows that are lit at night.
+const LIT_WINDOWS: [u8; WINDOW_PATTERN as usize] = [0b1011, 0b0110, 0b1101, 0b0011];
 
 pub struct ModelPlugin;
 
@@ -13,36 +42,542 @@ pub struct VehicleModelData {
     pub material: Handle<StandardMaterial>,
     pub scale: f32,
     pub vertical_offset: f32,
+    pub tags: Vec<String>,
 }
 
 impl VehicleModelData {
-    pub fn from_voxcar(i: i32, scale: f32, vertical_offset: f32, asset_server: &Res<AssetServer>) -> Self {
+    pub fn from_voxcar(i: i32, scale: f32, vertical_offset: f32, asset_server: Option<&AssetServer>) -> Self {
         VehicleModelData {
-            mesh: asset_server.load(format!("models/voxcar-{:?}.gltf#Mesh0/Primitive0", i)),
-            material: asset_server.load(format!("models/voxcar-{:?}.gltf#Material0", i)),
+            mesh: load_render_asset(asset_server, format!("models/voxcar-{:?}.gltf#Mesh0/Primitive0", i)),
+            material: load_render_asset(asset_server, format!("models/voxcar-{:?}.gltf#Material0", i)),
             scale,
             vertical_offset,
+            tags: Vec::new(),
         }
     }
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+struct VehicleManifestEntry {
+    voxcar: i32,
+    scale: f32,
+    vertical_offset: f32,
+    #[serde(default)]
+    tags: Vec<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct VehicleModelRule {
+    pub tag: String,
+    #[serde(default)]
+    pub purpose: Option<TripPurpose>,
+    #[serde(default)]
+    pub hours: Option<(f32, f32)>,
+    pub weight: f32,
+}
+
+impl VehicleModelRule {
+    fn applies(&self, model: &VehicleModelData, purpose: TripPurpose, hour: f32) -> bool {
+        let in_hours = self.hours.is_none_or(|(start, end)| {
+            if start <= end {
+                (start..end).contains(&hour)
+            } else {
+                hour >= start || hour < end
+            }
+        });
+
+        model.tags.contains(&self.tag) && self.purpose.is_none_or(|rule| rule == purpose) && in_hours
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VehicleManifest {
+    models: Vec<VehicleManifestEntry>,
+    #[serde(default)]
+    rules: Vec<VehicleModelRule>,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct RoadSurfaceData {
+    pub surface: RoadSurface,
+    pub tint: [f32; 3],
+    pub roughness: f32,
+}
+
+impl RoadSurfaceData {
+    fn fallback(surface: RoadSurface) -> Self {
+        let tint = match surface {
+            RoadSurface::Asphalt => [1.0, 1.0, 1.0],
+            RoadSurface::Cobblestone => [0.8, 0.68, 0.56],
+        };
+
+        Self {
+            surface,
+            tint,
+            roughness: 0.9,
+        }
+    }
+
+    pub fn apply(&self, material: &mut StandardMaterial) {
+        material.base_color = Color::srgb(self.tint[0], self.tint[1], self.tint[2]);
+        material.perceptual_roughness = self.roughness;
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct RoadSurfaceManifest {
+    surfaces: Vec<RoadSurfaceData>,
+}
+
+pub struct BuildingModelData {
+    pub materials: Vec<Handle<StandardMaterial>>,
+    pub night_materials: Vec<Handle<StandardMaterial>>,
+    pub heights: (f32, f32),
+    pub detail: Option<(Handle<Mesh>, Handle<StandardMaterial>)>,
+}
+
 #[derive(Resource)]
 pub struct Models {
     pub vehicle_models: Vec<VehicleModelData>,
+    pub vehicle_rules: Vec<VehicleModelRule>,
+    pub building_models: Vec<BuildingModelData>,
+    pub road_surfaces: Vec<RoadSurfaceData>,
+    pub pedestrian_mesh: Handle<Mesh>,
+    pub pedestrian_material: Handle<StandardMaterial>,
+    pub cone_mesh: Handle<Mesh>,
+    pub cone_material: Handle<StandardMaterial>,
+    pub indicator_mesh: Handle<Mesh>,
+    pub trailer_mesh: Handle<Mesh>,
+    pub trailer_material: Handle<StandardMaterial>,
+    pub marking_material: Handle<StandardMaterial>,
+    pub indicator_off_material: Handle<StandardMaterial>,
+    pub indicator_on_material: Handle<StandardMaterial>,
+    pub rain_mesh: Handle<Mesh>,
+    pub rain_material: Handle<StandardMaterial>,
+    pub snow_mesh: Handle<Mesh>,
+    pub snow_material: Handle<StandardMaterial>,
+    pub air_conditioner_mesh: Handle<Mesh>,
+    pub water_tower_mesh: Handle<Mesh>,
+    pub roof_prop_material: Handle<StandardMaterial>,
+    pub sign_pole_mesh: Handle<Mesh>,
+    pub sign_plate_mesh: Handle<Mesh>,
+    pub sign_rim_material: Handle<StandardMaterial>,
+    pub garbage_bag_mesh: Handle<Mesh>,
+    pub garbage_bag_material: Handle<StandardMaterial>,
+    pub driveway_mesh: Handle<Mesh>,
+    pub driveway_material: Handle<StandardMaterial>,
+    pub prop_models: Vec<Vec<(Handle<Mesh>, Handle<StandardMaterial>)>>,
+}
+
+impl Default for Models {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Models {
     pub fn new() -> Self {
         Models {
             vehicle_models: Vec::new(),
+            vehicle_rules: Vec::new(),
+            building_models: Vec::new(),
+            road_surfaces: RoadSurface::ALL.map(RoadSurfaceData::fallback).to_vec(),
+            pedestrian_mesh: Handle::default(),
+            pedestrian_material: Handle::default(),
+            cone_mesh: Handle::default(),
+            cone_material: Handle::default(),
+            indicator_mesh: Handle::default(),
+            trailer_mesh: Handle::default(),
+            trailer_material: Handle::default(),
+            marking_material: Handle::default(),
+            indicator_off_material: Handle::default(),
+            indicator_on_material: Handle::default(),
+            rain_mesh: Handle::default(),
+            rain_material: Handle::default(),
+            snow_mesh: Handle::default(),
+            snow_material: Handle::default(),
+            air_conditioner_mesh: Handle::default(),
+            water_tower_mesh: Handle::default(),
+            roof_prop_material: Handle::default(),
+            sign_pole_mesh: Handle::default(),
+            sign_plate_mesh: Handle::default(),
+            sign_rim_material: Handle::default(),
+            garbage_bag_mesh: Handle::default(),
+            garbage_bag_material: Handle::default(),
+            driveway_mesh: Handle::default(),
+            driveway_material: Handle::default(),
+            prop_models: PropKind::ALL.map(|_| Vec::new()).to_vec(),
         }
     }
+
+    pub fn building(&self, kind: BuildingKind) -> &BuildingModelData {
+        &self.building_models[kind as usize]
+    }
+
+    // The parts a prop is drawn with, each a mesh and its material.
+    pub fn prop(&self, kind: PropKind) -> &[(Handle<Mesh>, Handle<StandardMaterial>)] {
+        &self.prop_models[kind as usize]
+    }
+
+    pub fn road_surface(&self, surface: RoadSurface) -> &RoadSurfaceData {
+        &self.road_surfaces[surface as usize]
+    }
+
+    // Kinds with a model tag only pick from models carrying it, when the manifest has any.
+    pub fn choose_vehicle(&self, kind: VehicleKind, purpose: TripPurpose, hour: f32, rng: &mut impl Rng) -> usize {
+        let tagged = |model: &VehicleModelData| kind.model_tag().is_some_and(|tag| model.tags.iter().any(|t| t == tag));
+        let restricted = self.vehicle_models.iter().any(tagged);
+
+        let weights = self.vehicle_models.iter().map(|model| {
+            if restricted && !tagged(model) {
+                return 0.0;
+            }
+
+            self.vehicle_rules
+                .iter()
+                .filter(|rule| rule.applies(model, purpose, hour))
+                .fold(1.0, |weight, rule| weight * rule.weight.max(0.0))
+        });
+
+        match WeightedIndex::new(weights) {
+            Ok(distribution) => distribution.sample(rng),
+            Err(_) => rng.gen_range(0..self.vehicle_models.len()),
+        }
+    }
+}
+
+// Render assets only exist when the app runs with a renderer. Headless runs hand out placeholder
+// handles instead so spawn systems build the same bundles either way.
+pub fn add_render_asset<A: Asset>(assets: &mut Option<ResMut<Assets<A>>>, asset: impl Into<A>) -> Handle<A> {
+    assets.as_mut().map_or_else(Handle::default, |assets| assets.add(asset))
+}
+
+pub fn load_render_asset<A: Asset>(asset_server: Option<&AssetServer>, path: String) -> Handle<A> {
+    asset_server.map_or_else(Handle::default, |server| server.load(path))
+}
+
+// Four sided cone turned so its corners land on the corners of a unit square.
+fn hip_roof_mesh() -> Mesh {
+    Cone {
+        radius: FRAC_1_SQRT_2,
+        height: 0.6,
+    }
+    .mesh()
+    .resolution(4)
+    .build()
+    .rotated_by(Quat::from_rotation_y(FRAC_PI_4))
+}
+
+fn bench_mesh() -> Mesh {
+    let mut mesh = Cuboid::new(0.5, 0.04, 0.16).mesh().build().translated_by(Vec3::Y * 0.14);
+    mesh.merge(&Cuboid::new(0.5, 0.14, 0.03).mesh().build().translated_by(Vec3::new(0.0, 0.23, -0.07)));
+
+    for x in [-0.2, 0.2] {
+        mesh.merge(&Cuboid::new(0.03, 0.12, 0.14).mesh().build().translated_by(Vec3::new(x, 0.06, 0.0)));
+    }
+
+    mesh
+}
+
+fn water_tower_mesh() -> Mesh {
+    let mut mesh = Cylinder::new(0.16, 0.3).mesh().build().translated_by(Vec3::Y * 0.35);
+    let cap = Cone {
+        radius: 0.18,
+        height: 0.12,
+    };
+    mesh.merge(&cap.mesh().build().translated_by(Vec3::Y * 0.56));
+
+    for (x, z) in [(-0.1, -0.1), (-0.1, 0.1), (0.1, -0.1), (0.1, 0.1)] {
+        mesh.merge(&Cuboid::new(0.03, 0.2, 0.03).mesh().build().translated_by(Vec3::new(x, 0.1, z)));
+    }
+
+    mesh
 }
 
-fn load_models(asset_server: Res<AssetServer>, mut models: ResMut<Models>) {
-    models.vehicle_models.push(VehicleModelData::from_voxcar(1, 1.0, 0.0, &asset_server));
-    models.vehicle_models.push(VehicleModelData::from_voxcar(2, 1.0, 0.0, &asset_server));
-    models.vehicle_models.push(VehicleModelData::from_voxcar(3, 1.5, 0.2, &asset_server));
-    models.vehicle_models.push(VehicleModelData::from_voxcar(4, 1.2, 0.01, &asset_server));
-    models.vehicle_models.push(VehicleModelData::from_voxcar(5, 1.0, 0.0, &asset_server));
+// A `WINDOW_PATTERN` square of floors, each with a window per column, repeated across building faces.
+// `window` colors each window by its column and floor, so the glow variant can be black except for
+// the windows that are lit.
+fn window_texture(wall: [u8; 4], window: impl Fn(u32, u32) -> [u8; 4]) -> Image {
+    let cell = WINDOW_TEXTURE_SIZE;
+    let size = cell * WINDOW_PATTERN;
+    let data = (0..size * size)
+        .flat_map(|i| {
+            let (x, y) = (i % size, i / size);
+            let in_window = (2..cell - 2).contains(&(x % cell)) && (2..cell - 3).contains(&(y % cell));
+            if in_window {
+                window(x / cell, y / cell)
+            } else {
+                wall
+            }
+        })
+        .collect();
+
+    let mut image = Image::new(
+        Extent3d {
+            width: size,
+            height: size,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        data,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::Descriptor(ImageSamplerDescriptor {
+        address_mode_u: ImageAddressMode::Repeat,
+        address_mode_v: ImageAddressMode::Repeat,
+        ..ImageSamplerDescriptor::nearest()
+    });
+    image
+}
+
+fn load_models(
+    asset_server: Option<Res<AssetServer>>,
+    mut models: ResMut<Models>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+    mut images: Option<ResMut<Assets<Image>>>,
+) {
+    let asset_server = asset_server.as_deref();
+
+    match File::open(VEHICLE_MANIFEST).map(|file| serde_json::from_reader::<_, VehicleManifest>(BufReader::new(file))) {
+        Ok(Ok(manifest)) if !manifest.models.is_empty() => {
+            for entry in manifest.models {
+                let mut model =
+                    VehicleModelData::from_voxcar(entry.voxcar, entry.scale, entry.vertical_offset, asset_server);
+                model.tags = entry.tags;
+                models.vehicle_models.push(model);
+            }
+            models.vehicle_rules = manifest.rules;
+        }
+        result => {
+            if let Ok(Err(error)) = result {
+                println!("Failed to parse vehicle manifest {:?}: {}", VEHICLE_MANIFEST, error);
+            }
+
+            models.vehicle_models.push(VehicleModelData::from_voxcar(1, 1.0, 0.0, asset_server));
+            models.vehicle_models.push(VehicleModelData::from_voxcar(2, 1.0, 0.0, asset_server));
+            models.vehicle_models.push(VehicleModelData::from_voxcar(3, 1.5, 0.2, asset_server));
+            models.vehicle_models.push(VehicleModelData::from_voxcar(4, 1.2, 0.01, asset_server));
+            models.vehicle_models.push(VehicleModelData::from_voxcar(5, 1.0, 0.0, asset_server));
+        }
+    }
+
+    match File::open(ROAD_SURFACE_MANIFEST)
+        .map(|file| serde_json::from_reader::<_, RoadSurfaceManifest>(BufReader::new(file)))
+    {
+        Ok(Ok(manifest)) => {
+            for data in manifest.surfaces {
+                models.road_surfaces[data.surface as usize] = data;
+            }
+        }
+        Ok(Err(error)) => println!("Failed to parse road surface manifest {:?}: {}", ROAD_SURFACE_MANIFEST, error),
+        Err(_) => {}
+    }
+
+    let window_albedo = add_render_asset(&mut images, window_texture([255; 4], |_, _| [70, 80, 100, 255]));
+    let window_glow = add_render_asset(
+        &mut images,
+        window_texture([0, 0, 0, 255], |column, floor| {
+            match LIT_WINDOWS[floor as usize] >> column & 1 {
+                1 => [255; 4],
+                _ => [0, 0, 0, 255],
+            }
+        }),
+    );
+    // Building meshes measure UVs in windows and floors, and the textures hold a pattern of several.
+    let window_uvs = Affine2::from_scale(Vec2::splat(1.0 / WINDOW_PATTERN as f32));
+
+    for kind in BuildingKind::ALL {
+        let (colors, heights, detail): (&[Color], _, _) = match kind {
+            BuildingKind::House => (
+                &[
+                    Color::srgb(0.75, 0.68, 0.58),
+                    Color::srgb(0.62, 0.7, 0.6),
+                    Color::srgb(0.8, 0.8, 0.75),
+                ],
+                (0.5, 1.2),
+                Some((
+                    add_render_asset(&mut meshes, hip_roof_mesh()),
+                    add_render_asset(&mut materials, Color::srgb(0.45, 0.2, 0.15)),
+                )),
+            ),
+            BuildingKind::Shop => (
+                &[
+                    Color::srgb(0.85, 0.55, 0.35),
+                    Color::srgb(0.4, 0.6, 0.75),
+                    Color::srgb(0.85, 0.8, 0.45),
+                ],
+                (0.6, 1.0),
+                None,
+            ),
+            BuildingKind::Office => (
+                &[
+                    Color::srgb(0.35, 0.45, 0.6),
+                    Color::srgb(0.25, 0.3, 0.38),
+                    Color::srgb(0.55, 0.6, 0.65),
+                ],
+                (3.0, 7.0),
+                None,
+            ),
+            BuildingKind::Factory => (
+                &[Color::srgb(0.45, 0.42, 0.38), Color::srgb(0.5, 0.45, 0.3)],
+                (1.0, 2.0),
+                Some((
+                    add_render_asset(&mut meshes, Cylinder::new(0.15, 1.0)),
+                    add_render_asset(&mut materials, Color::srgb(0.3, 0.28, 0.26)),
+                )),
+            ),
+            BuildingKind::Parking => (&[Color::srgb(0.32, 0.32, 0.34)], (0.02, 0.04), None),
+            BuildingKind::Stadium => (&[Color::srgb(0.78, 0.78, 0.8), Color::srgb(0.7, 0.35, 0.3)], (0.8, 1.4), None),
+            BuildingKind::Mall => (&[Color::srgb(0.9, 0.85, 0.75), Color::srgb(0.55, 0.7, 0.8)], (1.0, 1.6), None),
+        };
+
+        let windows = kind != BuildingKind::Parking;
+        let albedo = windows.then(|| window_albedo.clone());
+        let glow = windows.then(|| window_glow.clone());
+
+        models.building_models.push(BuildingModelData {
+            materials: colors
+                .iter()
+                .map(|&color| {
+                    add_render_asset(
+                        &mut materials,
+                        StandardMaterial {
+                            base_color: color,
+                            base_color_texture: albedo.clone(),
+                            uv_transform: window_uvs,
+                            ..default()
+                        },
+                    )
+                })
+                .collect(),
+            night_materials: colors
+                .iter()
+                .map(|&color| {
+                    add_render_asset(
+                        &mut materials,
+                        StandardMaterial {
+                            base_color: color,
+                            base_color_texture: albedo.clone(),
+                            emissive: if windows { WINDOW_GLOW } else { LinearRgba::BLACK },
+                            emissive_texture: glow.clone(),
+                            uv_transform: window_uvs,
+                            ..default()
+                        },
+                    )
+                })
+                .collect(),
+            heights,
+            detail,
+        });
+    }
+
+    models.air_conditioner_mesh = add_render_asset(&mut meshes, Cuboid::new(0.2, 0.12, 0.16));
+    models.water_tower_mesh = add_render_asset(&mut meshes, water_tower_mesh());
+    models.roof_prop_material = add_render_asset(&mut materials, Color::srgb(0.55, 0.55, 0.55));
+    models.pedestrian_mesh = add_render_asset(&mut meshes, Capsule3d::new(0.05, 0.15));
+    models.pedestrian_material = add_render_asset(&mut materials, Color::srgb(0.9, 0.6, 0.3));
+    models.cone_mesh = add_render_asset(
+        &mut meshes,
+        Cone {
+            radius: 0.06,
+            height: 0.15,
+        },
+    );
+    models.cone_material = add_render_asset(&mut materials, Color::srgb(1.0, 0.4, 0.0));
+    models.indicator_mesh = add_render_asset(&mut meshes, Cuboid::new(0.04, 0.04, 0.06));
+    models.trailer_mesh = add_render_asset(&mut meshes, Cuboid::new(0.4, 0.35, TRAILER_LENGTH));
+    models.trailer_material = add_render_asset(&mut materials, Color::srgb(0.8, 0.8, 0.75));
+    models.marking_material = add_render_asset(
+        &mut materials,
+        StandardMaterial {
+            base_color: Color::srgb(0.92, 0.92, 0.88),
+            perceptual_roughness: 0.8,
+            cull_mode: None,
+            ..default()
+        },
+    );
+    models.sign_pole_mesh = add_render_asset(&mut meshes, Cylinder::new(0.015, SIGN_POLE_HEIGHT));
+    models.sign_plate_mesh = add_render_asset(&mut meshes, Cylinder::new(0.12, 0.01));
+    models.sign_rim_material = add_render_asset(&mut materials, Color::srgb(0.8, 0.1, 0.1));
+    models.garbage_bag_mesh = add_render_asset(&mut meshes, Sphere::new(0.08).mesh().ico(1).unwrap());
+    models.garbage_bag_material = add_render_asset(&mut materials, Color::srgb(0.12, 0.16, 0.12));
+    models.driveway_mesh = add_render_asset(&mut meshes, Plane3d::default().mesh().size(1.0, 1.0));
+    models.driveway_material = add_render_asset(&mut materials, Color::srgb(0.55, 0.55, 0.52));
+    models.indicator_off_material = add_render_asset(&mut materials, Color::srgb(0.35, 0.2, 0.05));
+    models.indicator_on_material = add_render_asset(
+        &mut materials,
+        StandardMaterial {
+            base_color: Color::srgb(1.0, 0.6, 0.1),
+            emissive: LinearRgba::rgb(4.0, 2.0, 0.2),
+            ..default()
+        },
+    );
+    models.prop_models = PropKind::ALL
+        .map(|kind| match kind {
+            PropKind::Tree => vec![
+                (
+                    add_render_asset(
+                        &mut meshes,
+                        Cylinder::new(0.05, 0.4).mesh().build().translated_by(Vec3::Y * 0.2),
+                    ),
+                    add_render_asset(&mut materials, Color::srgb(0.4, 0.28, 0.18)),
+                ),
+                (
+                    add_render_asset(
+                        &mut meshes,
+                        Sphere::new(0.3).mesh().ico(1).unwrap().translated_by(Vec3::Y * 0.6),
+                    ),
+                    add_render_asset(&mut materials, Color::srgb(0.2, 0.5, 0.2)),
+                ),
+            ],
+            PropKind::Bench => vec![(
+                add_render_asset(&mut meshes, bench_mesh()),
+                add_render_asset(&mut materials, Color::srgb(0.55, 0.38, 0.22)),
+            )],
+            PropKind::Fountain => vec![
+                (
+                    add_render_asset(
+                        &mut meshes,
+                        Cylinder::new(0.8, 0.2).mesh().build().translated_by(Vec3::Y * 0.1),
+                    ),
+                    add_render_asset(&mut materials, Color::srgb(0.7, 0.7, 0.68)),
+                ),
+                (
+                    add_render_asset(
+                        &mut meshes,
+                        Cylinder::new(0.7, 0.02).mesh().build().translated_by(Vec3::Y * 0.2),
+                    ),
+                    add_render_asset(&mut materials, Color::srgb(0.25, 0.5, 0.7)),
+                ),
+                (
+                    add_render_asset(
+                        &mut meshes,
+                        Cylinder::new(0.08, 0.4).mesh().build().translated_by(Vec3::Y * 0.2),
+                    ),
+                    add_render_asset(&mut materials, Color::srgb(0.7, 0.7, 0.68)),
+                ),
+            ],
+        })
+        .to_vec();
+    models.rain_mesh = add_render_asset(&mut meshes, Cuboid::new(0.01, 0.3, 0.01));
+    models.rain_material = add_render_asset(
+        &mut materials,
+        StandardMaterial {
+            base_color: Color::srgba(0.6, 0.7, 0.9, 0.5),
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        },
+    );
+    models.snow_mesh = add_render_asset(&mut meshes, Sphere::new(0.03).mesh().ico(0).unwrap());
+    models.snow_material = add_render_asset(
+        &mut materials,
+        StandardMaterial {
+            base_color: Color::WHITE,
+            unlit: true,
+            ..default()
+        },
+    );
 }
@@ -0,0 +1,190 @@
+use crate::{graphics::models::add_render_asset, schedule::UpdateStage};
+use bevy::prelude::*;
+use rand::Rng;
+use std::f32::consts::PI;
+
+const MAX_PARTICLES: usize = 1500;
+const MAX_SPAWNS_PER_FRAME: usize = 60;
+
+pub struct ParticlePlugin;
+
+impl Plugin for ParticlePlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_particle_assets).add_systems(
+            Update,
+            (emit_particles, advance_particles).chain().in_set(UpdateStage::Visualize),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ParticleStyle {
+    Smoke,
+    Spray,
+}
+
+impl ParticleStyle {
+    fn per_second(&self) -> f32 {
+        match self {
+            ParticleStyle::Smoke => 3.0,
+            ParticleStyle::Spray => 25.0,
+        }
+    }
+
+    fn lifetime(&self) -> f32 {
+        match self {
+            ParticleStyle::Smoke => 6.0,
+            ParticleStyle::Spray => 1.0,
+        }
+    }
+
+    fn launch(&self, rng: &mut impl Rng) -> Vec3 {
+        let around = Vec2::from_angle(rng.gen_range(0.0..2.0 * PI));
+        match self {
+            ParticleStyle::Smoke => (around * 0.05).extend(rng.gen_range(0.4..0.6)).xzy(),
+            ParticleStyle::Spray => (around * rng.gen_range(0.2..0.5)).extend(rng.gen_range(1.4..1.8)).xzy(),
+        }
+    }
+
+    fn gravity(&self) -> f32 {
+        match self {
+            ParticleStyle::Smoke => 0.0,
+            ParticleStyle::Spray => -4.0,
+        }
+    }
+
+    // Size over the particle's life, from 0 when it is born to 1 when it is gone.
+    fn size(&self, life: f32) -> f32 {
+        match self {
+            ParticleStyle::Smoke => 0.08 + life * 0.3,
+            ParticleStyle::Spray => 0.03 * (1.0 - life * 0.5),
+        }
+    }
+}
+
+// Sends out particles from `offset` above the entity it is on, at the pace of its style. Emitters that
+// were not on screen last frame send nothing.
+#[derive(Component, Debug)]
+pub struct ParticleEmitter {
+    pub style: ParticleStyle,
+    pub offset: Vec3,
+    owed: f32,
+}
+
+impl ParticleEmitter {
+    pub fn new(style: ParticleStyle, offset: Vec3) -> Self {
+        Self {
+            style,
+            offset,
+            owed: 0.0,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct Particle {
+    style: ParticleStyle,
+    velocity: Vec3,
+    age: f32,
+}
+
+#[derive(Resource)]
+struct ParticleAssets {
+    mesh: Handle<Mesh>,
+    smoke: Handle<StandardMaterial>,
+    spray: Handle<StandardMaterial>,
+}
+
+fn spawn_particle_assets(
+    mut commands: Commands,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+) {
+    let mut material = |color: Color| {
+        add_render_asset(
+            &mut materials,
+            StandardMaterial {
+                base_color: color,
+                alpha_mode: AlphaMode::Blend,
+                unlit: true,
+                ..default()
+            },
+        )
+    };
+
+    commands.insert_resource(ParticleAssets {
+        mesh: add_render_asset(&mut meshes, Sphere::new(1.0).mesh().ico(1).unwrap()),
+        smoke: material(Color::srgba(0.55, 0.55, 0.55, 0.35)),
+        spray: material(Color::srgba(0.75, 0.85, 1.0, 0.6)),
+    });
+}
+
+// Particles share one mesh and a material per style, so the whole effect is drawn with a handful of
+// materials however many are alive. Once `MAX_PARTICLES` are alive, emitters wait.
+fn emit_particles(
+    mut commands: Commands,
+    mut emitter_query: Query<(&mut ParticleEmitter, &GlobalTransform, Option<&ViewVisibility>)>,
+    particle_query: Query<(), With<Particle>>,
+    assets: Res<ParticleAssets>,
+    time: Res<Time>,
+) {
+    let mut rng = rand::thread_rng();
+    let mut room = MAX_PARTICLES.saturating_sub(particle_query.iter().count()).min(MAX_SPAWNS_PER_FRAME);
+
+    for (mut emitter, transform, visibility) in &mut emitter_query {
+        if visibility.is_some_and(|visibility| !visibility.get()) {
+            emitter.owed = 0.0;
+            continue;
+        }
+
+        emitter.owed += emitter.style.per_second() * time.delta_seconds();
+        while emitter.owed >= 1.0 && room > 0 {
+            emitter.owed -= 1.0;
+            room -= 1;
+
+            let style = emitter.style;
+            let material = match style {
+                ParticleStyle::Smoke => assets.smoke.clone(),
+                ParticleStyle::Spray => assets.spray.clone(),
+            };
+            commands.spawn((
+                PbrBundle {
+                    mesh: assets.mesh.clone(),
+                    material,
+                    transform: Transform::from_translation(transform.translation() + emitter.offset)
+                        .with_scale(Vec3::splat(style.size(0.0))),
+                    ..default()
+                },
+                Particle {
+                    style,
+                    velocity: style.launch(&mut rng),
+                    age: 0.0,
+                },
+            ));
+        }
+
+        emitter.owed = emitter.owed.min(1.0);
+    }
+}
+
+fn advance_particles(
+    mut commands: Commands,
+    mut particle_query: Query<(Entity, &mut Particle, &mut Transform)>,
+    time: Res<Time>,
+) {
+    let delta = time.delta_seconds();
+
+    for (entity, mut particle, mut transform) in &mut particle_query {
+        particle.age += delta;
+        let life = particle.age / particle.style.lifetime();
+
+        if life >= 1.0 || transform.translation.y < 0.0 {
+            commands.entity(entity).despawn();
+            continue;
+        }
+
+        particle.velocity.y += particle.style.gravity() * delta;
+        transform.translation += particle.velocity * delta;
+        transform.scale = Vec3::splat(particle.style.size(life));
+    }
+}
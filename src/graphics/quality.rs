@@ -0,0 +1,199 @@
+use crate::{
+    graphics::{camera::PlayerCameraController, weather::WeatherState},
+    schedule::UpdateStage,
+};
+use bevy::{
+    core_pipeline::{bloom::BloomSettings, fxaa::Fxaa},
+    ecs::system::SystemParam,
+    pbr::{CascadeShadowConfig, CascadeShadowConfigBuilder, DirectionalLightShadowMap},
+    prelude::*,
+    window::{PresentMode, PrimaryWindow, WindowMode},
+};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+const GRAPHICS_DIR: &str = "assets/profile";
+const GRAPHICS_FILE: &str = "assets/profile/graphics.json";
+const SHADOW_DISTANCE: f32 = 100.0;
+pub const MAX_SHADOW_CASCADES: usize = 4;
+pub const SHADOW_RESOLUTIONS: [usize; 3] = [1024, 2048, 4096];
+
+pub struct GraphicsQualityPlugin;
+
+impl Plugin for GraphicsQualityPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GraphicsSettings::load()).add_systems(
+            Update,
+            apply_graphics_settings.in_set(UpdateStage::Visualize).run_if(resource_changed::<GraphicsSettings>),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+}
+
+impl QualityPreset {
+    pub const ALL: [QualityPreset; 3] = [QualityPreset::Low, QualityPreset::Medium, QualityPreset::High];
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum DisplayMode {
+    Windowed,
+    Borderless,
+    Fullscreen,
+}
+
+impl DisplayMode {
+    pub const ALL: [DisplayMode; 3] = [DisplayMode::Windowed, DisplayMode::Borderless, DisplayMode::Fullscreen];
+
+    fn window_mode(&self) -> WindowMode {
+        match self {
+            DisplayMode::Windowed => WindowMode::Windowed,
+            DisplayMode::Borderless => WindowMode::BorderlessFullscreen,
+            DisplayMode::Fullscreen => WindowMode::Fullscreen,
+        }
+    }
+}
+
+// Presets only cover the rendering options. Vsync and the window mode are left as the player set them.
+#[derive(Resource, Clone, PartialEq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct GraphicsSettings {
+    pub fog: bool,
+    pub bloom: bool,
+    pub fxaa: bool,
+    pub shadow_cascades: usize,
+    pub shadow_resolution: usize,
+    pub vsync: bool,
+    pub display_mode: DisplayMode,
+}
+
+impl Default for GraphicsSettings {
+    fn default() -> Self {
+        Self {
+            fog: true,
+            bloom: true,
+            fxaa: true,
+            shadow_cascades: MAX_SHADOW_CASCADES,
+            shadow_resolution: 4096,
+            vsync: true,
+            display_mode: DisplayMode::Windowed,
+        }
+    }
+}
+
+impl GraphicsSettings {
+    fn load() -> Self {
+        if let Ok(file) = File::open(GRAPHICS_FILE) {
+            if let Ok(settings) = serde_json::from_reader::<BufReader<File>, GraphicsSettings>(BufReader::new(file)) {
+                return settings;
+            }
+        }
+
+        GraphicsSettings::default()
+    }
+
+    fn write(&self) {
+        if std::fs::create_dir_all(GRAPHICS_DIR).is_ok() {
+            if let Ok(file) = File::create(GRAPHICS_FILE) {
+                let mut writer = BufWriter::new(file);
+                if serde_json::to_writer_pretty(&mut writer, &self).is_err() || writer.flush().is_err() {
+                    println!("Failed to write the graphics settings to {:?}", GRAPHICS_FILE);
+                }
+            }
+        }
+    }
+
+    pub fn apply_preset(&mut self, preset: QualityPreset) {
+        (self.fog, self.bloom, self.fxaa, self.shadow_cascades, self.shadow_resolution) = match preset {
+            QualityPreset::Low => (false, false, false, 1, 1024),
+            QualityPreset::Medium => (true, false, true, 2, 2048),
+            QualityPreset::High => (true, true, true, MAX_SHADOW_CASCADES, 4096),
+        };
+    }
+
+    // The preset these settings match exactly, if the player has not tuned anything by hand.
+    pub fn preset(&self) -> Option<QualityPreset> {
+        QualityPreset::ALL.into_iter().find(|&preset| {
+            let mut settings = self.clone();
+            settings.apply_preset(preset);
+            settings == *self
+        })
+    }
+}
+
+// The sun's shadow cascades and the shadow map they are drawn into.
+#[derive(SystemParam)]
+struct Shadows<'w, 's> {
+    light_query: Query<'w, 's, &'static mut CascadeShadowConfig, With<DirectionalLight>>,
+    shadow_map: ResMut<'w, DirectionalLightShadowMap>,
+}
+
+impl Shadows<'_, '_> {
+    fn apply(&mut self, settings: &GraphicsSettings) {
+        for mut cascades in &mut self.light_query {
+            *cascades = CascadeShadowConfigBuilder {
+                num_cascades: settings.shadow_cascades.clamp(1, MAX_SHADOW_CASCADES),
+                maximum_distance: SHADOW_DISTANCE,
+                ..default()
+            }
+            .into();
+        }
+
+        self.shadow_map.size = settings.shadow_resolution;
+    }
+}
+
+// Fog is taken off the camera rather than thinned out, and kept aside so it comes back the same way
+// it was spawned, with the falloff of whatever the weather is by then.
+fn apply_graphics_settings(
+    mut commands: Commands,
+    settings: Res<GraphicsSettings>,
+    weather: Res<WeatherState>,
+    mut stored_fog: Local<Option<FogSettings>>,
+    mut camera_query: Query<(Entity, Option<&FogSettings>, &mut Fxaa), With<PlayerCameraController>>,
+    mut shadows: Shadows,
+    mut window_query: Query<&mut Window, With<PrimaryWindow>>,
+) {
+    for (camera, fog, mut fxaa) in &mut camera_query {
+        match (settings.fog, fog, stored_fog.take()) {
+            (false, Some(fog), _) => {
+                *stored_fog = Some(fog.clone());
+                commands.entity(camera).remove::<FogSettings>();
+            }
+            (true, None, Some(fog)) => {
+                commands.entity(camera).insert(FogSettings {
+                    falloff: weather.fog_falloff(),
+                    ..fog
+                });
+            }
+            (_, _, stored) => *stored_fog = stored,
+        }
+
+        match settings.bloom {
+            true => commands.entity(camera).insert(BloomSettings::NATURAL),
+            false => commands.entity(camera).remove::<BloomSettings>(),
+        };
+
+        fxaa.enabled = settings.fxaa;
+    }
+
+    shadows.apply(&settings);
+
+    for mut window in &mut window_query {
+        window.present_mode = match settings.vsync {
+            true => PresentMode::AutoVsync,
+            false => PresentMode::AutoNoVsync,
+        };
+        window.mode = settings.display_mode.window_mode();
+    }
+
+    if !settings.is_added() {
+        settings.write();
+    }
+}
@@ -0,0 +1,284 @@
+use crate::{
+    graphics::models::{add_render_asset, Models},
+    grid::{grid_area::GridArea, terrain::Terrain},
+    schedule::UpdateStage,
+    tools::road_tool::ROAD_HEIGHT,
+    types::{
+        intersection::{Intersection, IntersectionControl},
+        road_segment::RoadSegment,
+        traffic_signal::{Movement, Turn},
+        vehicle::{direction_to_area, get_lane_for_turn, has_left_arm},
+    },
+};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+};
+
+const MARKING_LIFT: f32 = 0.01;
+const CROSSWALK_DEPTH: f32 = 0.6;
+const CROSSWALK_STRIPE: f32 = 0.14;
+const CROSSWALK_SPACING: f32 = 0.32;
+const STOP_LINE_SETBACK: f32 = 0.8;
+const STOP_LINE_DEPTH: f32 = 0.12;
+const LANE_HALF_WIDTH: f32 = 0.4;
+const ARROW_SETBACK: f32 = 2.2;
+const ARROW_LENGTH: f32 = 0.7;
+const ARROW_WIDTH: f32 = 0.07;
+const ARROW_HEAD: f32 = 0.22;
+const ARROW_SPUR: f32 = 0.25;
+// Roads shorter than this have no room for arrows behind the stop line.
+const MIN_ARROW_ROAD_LENGTH: i32 = 4;
+
+pub struct RoadMarkingsPlugin;
+
+impl Plugin for RoadMarkingsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_road_markings.in_set(UpdateStage::Visualize));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct MarkedEnd {
+    intersection: Entity,
+    control: IntersectionControl,
+    roads: [Option<Entity>; 4],
+    restricted_turns: u16,
+}
+
+// What a road's markings were last generated from. The decal is rebuilt whenever any of it changes,
+// which covers roads being split, extended or widened as well as intersections changing control,
+// gaining and losing exits, or having turns restricted.
+#[derive(Component, Debug)]
+pub struct RoadMarkings {
+    area: GridArea,
+    ends: [Option<MarkedEnd>; 2],
+    decal: Option<Entity>,
+}
+
+fn update_road_markings(
+    mut commands: Commands,
+    segment_query: Query<(Entity, &RoadSegment, Option<&RoadMarkings>)>,
+    inter_query: Query<&Intersection>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    models: Res<Models>,
+    terrain: Res<Terrain>,
+) {
+    for (entity, segment, markings) in &segment_query {
+        let ends = segment.ends.map(|end| {
+            let intersection = end?;
+            inter_query.get(intersection).ok().map(|inter| MarkedEnd {
+                intersection,
+                control: inter.control,
+                roads: inter.roads,
+                restricted_turns: inter.restricted_turns.iter().fold(0, |mask, &(from, to)| mask | 1 << (from * 4 + to)),
+            })
+        });
+
+        if markings.is_some_and(|markings| markings.area == segment.area && markings.ends == ends) {
+            continue;
+        }
+
+        if let Some(decal) = markings.and_then(|markings| markings.decal) {
+            commands.entity(decal).despawn_recursive();
+        }
+
+        let mut builder = MarkingMeshBuilder::new(segment.area.center(), &terrain);
+        for end in ends.iter().flatten() {
+            if let Ok(intersection) = inter_query.get(end.intersection) {
+                mark_end(&mut builder, entity, segment, intersection, &segment_query);
+            }
+        }
+
+        let decal = builder.build().map(|mesh| {
+            let decal = commands
+                .spawn(PbrBundle {
+                    mesh: add_render_asset(&mut meshes, mesh),
+                    material: models.marking_material.clone(),
+                    ..default()
+                })
+                .id();
+            commands.entity(entity).add_child(decal);
+            decal
+        });
+
+        commands.entity(entity).insert(RoadMarkings {
+            area: segment.area,
+            ends,
+            decal,
+        });
+    }
+}
+
+// Zebra stripes across the whole road where it meets the intersection, a stop line across the
+// inbound lanes when the intersection has signs or signals, and an arrow in each inbound lane for
+// every exit vehicles in that lane may take.
+fn mark_end(
+    builder: &mut MarkingMeshBuilder,
+    entity: Entity,
+    segment: &RoadSegment,
+    intersection: &Intersection,
+    segment_query: &Query<(Entity, &RoadSegment, Option<&RoadMarkings>)>,
+) {
+    let samples = segment.centerline_samples();
+    let (first, last) = (samples[0], samples[samples.len() - 1]);
+    let toward = intersection.pos();
+    let (point, lateral, inner) = match first.0.distance(toward) <= last.0.distance(toward) {
+        true => (first.0, first.1, samples[1].0),
+        false => (last.0, last.1, samples[samples.len() - 2].0),
+    };
+
+    let Some(outward) = (point - inner).with_y(0.0).try_normalize() else {
+        return;
+    };
+    let half_width = lateral.length();
+    let across = lateral.normalize();
+
+    let mut offset = -half_width + CROSSWALK_SPACING / 2.0;
+    while offset < half_width {
+        builder.push_rect(
+            point - outward * CROSSWALK_DEPTH / 2.0 + across * offset,
+            outward,
+            CROSSWALK_DEPTH,
+            CROSSWALK_STRIPE,
+        );
+        offset += CROSSWALK_SPACING;
+    }
+
+    let approach = direction_to_area(segment, intersection.area);
+
+    if intersection.control != IntersectionControl::Uncontrolled {
+        let line = point - outward * STOP_LINE_SETBACK;
+        let offsets: Vec<f32> =
+            (0..segment.num_lanes()).map(|lane| (segment.clamp_to_lane(approach, lane, line) - line).dot(across)).collect();
+        let low = (offsets.iter().copied().fold(f32::INFINITY, f32::min) - LANE_HALF_WIDTH).max(-half_width);
+        let high = (offsets.iter().copied().fold(f32::NEG_INFINITY, f32::max) + LANE_HALF_WIDTH).min(half_width);
+
+        if low < high {
+            builder.push_rect(line + across * (low + high) / 2.0, across, high - low, STOP_LINE_DEPTH);
+        }
+    }
+
+    let (Some(from), true) = (intersection.slot_of(entity), segment.drive_length() >= MIN_ARROW_ROAD_LENGTH) else {
+        return;
+    };
+
+    for (to, exit) in intersection.roads.iter().enumerate().filter_map(|(slot, road)| road.map(|road| (slot, road))) {
+        if exit == entity || intersection.restricted_turns.contains(&(from, to)) {
+            continue;
+        }
+
+        let Ok((_, exit_segment, _)) = segment_query.get(exit) else {
+            continue;
+        };
+
+        let turn = Movement { from, to }.turn();
+        let lanes = match turn {
+            Turn::Straight if !has_left_arm(intersection, from) => 0..=segment.num_lanes() - 1,
+            Turn::Straight => 0..=(segment.num_lanes() - 2).max(0),
+            _ => {
+                let lane = get_lane_for_turn(segment, exit_segment, segment, 0);
+                lane..=lane
+            }
+        };
+
+        let heading = (exit_segment.pos() - toward).with_y(0.0);
+        let side = (heading - outward * heading.dot(outward)).normalize_or_zero();
+
+        for lane in lanes {
+            let base = segment.clamp_to_lane(approach, lane, point - outward * ARROW_SETBACK);
+            builder.push_arrow(base, outward, (turn != Turn::Straight).then_some(side));
+        }
+    }
+}
+
+// Flat quads and triangles laid just above the road surface, in the road's local space.
+struct MarkingMeshBuilder<'a> {
+    origin: Vec3,
+    terrain: &'a Terrain,
+    positions: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl<'a> MarkingMeshBuilder<'a> {
+    fn new(origin: Vec3, terrain: &'a Terrain) -> Self {
+        Self {
+            origin,
+            terrain,
+            positions: Vec::new(),
+            indices: Vec::new(),
+        }
+    }
+
+    fn push_triangle(&mut self, corners: [Vec3; 3]) {
+        let start = self.positions.len() as u32;
+        for corner in corners {
+            let height = self.terrain.road_height(corner) + ROAD_HEIGHT + MARKING_LIFT;
+            self.positions.push((corner - self.origin).with_y(height).to_array());
+        }
+        self.indices.extend([start, start + 1, start + 2]);
+    }
+
+    // A rectangle centred on `center`, `length` along `along` and `width` across it.
+    fn push_rect(&mut self, center: Vec3, along: Vec3, length: f32, width: f32) {
+        let half = along * length / 2.0;
+        let side = Vec3::Y.cross(along).normalize_or_zero() * width / 2.0;
+        let corners = [
+            center - half - side,
+            center - half + side,
+            center + half + side,
+            center + half - side,
+        ];
+
+        self.push_triangle([corners[0], corners[1], corners[2]]);
+        self.push_triangle([corners[0], corners[2], corners[3]]);
+    }
+
+    // Straight arrows point along `forward`. Turn arrows bend off towards `side` at the tip.
+    fn push_arrow(&mut self, center: Vec3, forward: Vec3, side: Option<Vec3>) {
+        let across = Vec3::Y.cross(forward).normalize_or_zero();
+
+        match side {
+            None => {
+                let tip = center + forward * ARROW_LENGTH / 2.0;
+                let neck = tip - forward * ARROW_HEAD;
+                self.push_rect(
+                    center - forward * ARROW_HEAD / 2.0,
+                    forward,
+                    ARROW_LENGTH - ARROW_HEAD,
+                    ARROW_WIDTH,
+                );
+                self.push_triangle([tip, neck + across * ARROW_HEAD / 2.0, neck - across * ARROW_HEAD / 2.0]);
+            }
+            Some(side) => {
+                let elbow = center + forward * (ARROW_LENGTH - ARROW_WIDTH) / 2.0;
+                let neck = elbow + side * ARROW_SPUR;
+                self.push_rect(center, forward, ARROW_LENGTH, ARROW_WIDTH);
+                self.push_rect(elbow + side * ARROW_SPUR / 2.0, side, ARROW_SPUR, ARROW_WIDTH);
+                self.push_triangle([
+                    neck + side * ARROW_HEAD,
+                    neck + forward * ARROW_HEAD / 2.0,
+                    neck - forward * ARROW_HEAD / 2.0,
+                ]);
+            }
+        }
+    }
+
+    fn build(self) -> Option<Mesh> {
+        if self.positions.is_empty() {
+            return None;
+        }
+
+        let normals = vec![[0.0, 1.0, 0.0]; self.positions.len()];
+
+        Some(
+            Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+                .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+                .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+                .with_inserted_indices(Indices::U32(self.indices)),
+        )
+    }
+}
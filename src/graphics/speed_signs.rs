@@ -0,0 +1,110 @@
+use crate::{
+    graphics::models::{Models, SIGN_POLE_HEIGHT},
+    grid::{grid_area::GridArea, terrain::Terrain},
+    schedule::UpdateStage,
+    tools::road_tool::ROAD_HEIGHT,
+    types::road_segment::RoadSegment,
+};
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+const PLATE_GAP: f32 = 0.012;
+const CURB_CLEARANCE: f32 = 0.15;
+// Signs stand this far back from the road's end so they clear the crosswalk.
+const END_SETBACK: f32 = 1.0;
+
+pub struct SpeedSignsPlugin;
+
+impl Plugin for SpeedSignsPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, update_speed_signs.in_set(UpdateStage::Visualize));
+    }
+}
+
+// The override and area the signs were placed for. They are replaced whenever either changes.
+#[derive(Component, Debug)]
+pub struct SpeedSigns {
+    limit: Option<f32>,
+    area: GridArea,
+    signs: Vec<Entity>,
+}
+
+// Roads with a speed limit set by hand get a sign at each end, on the curb to the right of the
+// traffic entering there and facing it. Roads on their default limit have none.
+fn update_speed_signs(
+    mut commands: Commands,
+    segment_query: Query<(Entity, &RoadSegment, Option<&SpeedSigns>)>,
+    models: Res<Models>,
+    terrain: Res<Terrain>,
+) {
+    for (entity, segment, signs) in &segment_query {
+        let limit = segment.speed_limit_override;
+        if signs.is_some_and(|signs| signs.limit == limit && signs.area == segment.area) {
+            continue;
+        }
+
+        for &sign in signs.iter().flat_map(|signs| &signs.signs) {
+            commands.entity(sign).despawn_recursive();
+        }
+
+        let mut placed = Vec::new();
+        if limit.is_some() {
+            let samples = segment.centerline_samples();
+            let origin = segment.area.center();
+            let last = samples.len() - 1;
+            let ends = [
+                (samples[0].0, samples[1].0, samples[0].1.length()),
+                (samples[last].0, samples[last - 1].0, samples[last].1.length()),
+            ];
+
+            for (end, inner, half_width) in ends {
+                let Some(inward) = (inner - end).with_y(0.0).try_normalize() else {
+                    continue;
+                };
+
+                let right = inward.cross(Vec3::Y);
+                let curb = end + inward * END_SETBACK + right * (half_width + CURB_CLEARANCE);
+                let base = curb.with_y(terrain.road_height(curb) + ROAD_HEIGHT);
+                let sign = spawn_sign(&mut commands, &models, base - origin.with_y(0.0), -inward);
+                commands.entity(entity).add_child(sign);
+                placed.push(sign);
+            }
+        }
+
+        commands.entity(entity).insert(SpeedSigns {
+            limit,
+            area: segment.area,
+            signs: placed,
+        });
+    }
+}
+
+fn spawn_sign(commands: &mut Commands, models: &Models, base: Vec3, facing: Vec3) -> Entity {
+    let plate = Transform::from_translation(Vec3::Y * SIGN_POLE_HEIGHT).with_rotation(Quat::from_rotation_x(FRAC_PI_2));
+
+    commands
+        .spawn(SpatialBundle::from_transform(
+            Transform::from_translation(base).looking_to(facing, Vec3::Y),
+        ))
+        .with_children(|parent| {
+            parent.spawn(PbrBundle {
+                mesh: models.sign_pole_mesh.clone(),
+                material: models.roof_prop_material.clone(),
+                transform: Transform::from_translation(Vec3::Y * SIGN_POLE_HEIGHT / 2.0),
+                ..default()
+            });
+            parent.spawn(PbrBundle {
+                mesh: models.sign_plate_mesh.clone(),
+                material: models.sign_rim_material.clone(),
+                transform: plate,
+                ..default()
+            });
+            parent.spawn(PbrBundle {
+                mesh: models.sign_plate_mesh.clone(),
+                material: models.marking_material.clone(),
+                transform: plate.with_translation(plate.translation + Vec3::NEG_Z * PLATE_GAP).with_scale(Vec3::splat(0.75)),
+                ..default()
+            });
+        })
+        .id()
+}
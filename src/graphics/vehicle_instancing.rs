@@ -0,0 +1,477 @@
+use crate::{graphics::models::Models, schedule::UpdateStage, types::vehicle::Vehicle};
+use bevy::{
+    core_pipeline::{
+        core_3d::Transparent3d,
+        prepass::{DeferredPrepass, DepthPrepass, MotionVectorPrepass, NormalPrepass},
+        tonemapping::{DebandDither, Tonemapping},
+    },
+    ecs::{
+        query::QueryItem,
+        system::{lifetimeless::*, SystemParam, SystemParamItem},
+    },
+    pbr::{
+        tonemapping_pipeline_key, MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+    },
+    prelude::*,
+    render::{
+        extract_component::{ExtractComponent, ExtractComponentPlugin},
+        mesh::{GpuBufferInfo, GpuMesh, MeshVertexBufferLayoutRef},
+        render_asset::RenderAssets,
+        render_phase::{
+            AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand, RenderCommandResult,
+            SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+        },
+        render_resource::{binding_types::*, *},
+        renderer::RenderDevice,
+        texture::{FallbackImage, GpuImage},
+        view::{ExtractedView, NoFrustumCulling},
+        Render, RenderApp, RenderSet,
+    },
+    utils::HashMap,
+};
+
+const SHADER_PATH: &str = "shaders/vehicle_instancing.wgsl";
+// The mesh pipeline puts vertex attributes at locations 0 to 7, so instances start after them.
+const FIRST_INSTANCE_LOCATION: u32 = 8;
+const INSTANCE_VECTORS: u32 = 5;
+
+// Draws every vehicle of a model in one instanced call: the model's mesh and texture are bound once
+// and each vehicle only adds its transform and colour to a per-frame instance buffer. Vehicles drawn
+// this way give up their own mesh so the PBR pass skips them. Their shading only takes the sun and
+// ambient light, so they cast no shadows and headlights and street lamps don't light them.
+pub struct VehicleInstancingPlugin;
+
+impl Plugin for VehicleInstancingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(VehicleInstancingSettings::default())
+            .add_plugins(ExtractComponentPlugin::<VehicleInstances>::default())
+            .add_systems(
+                Update,
+                (spawn_vehicle_batches, sync_vehicle_meshes, gather_vehicle_instances)
+                    .chain()
+                    .in_set(UpdateStage::Visualize),
+            );
+
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+
+        render_app
+            .add_render_command::<Transparent3d, DrawVehicles>()
+            .init_resource::<SpecializedMeshPipelines<VehicleInstancingPipeline>>()
+            .add_systems(
+                Render,
+                (
+                    queue_vehicle_batches.in_set(RenderSet::QueueMeshes),
+                    prepare_instance_buffers.in_set(RenderSet::PrepareResources),
+                    prepare_texture_bind_groups.in_set(RenderSet::PrepareBindGroups),
+                ),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<VehicleInstancingPipeline>();
+        }
+    }
+}
+
+// Off by default: the instanced shading is much cheaper for large fleets but goes dark at night.
+#[derive(Resource, Debug, Default)]
+pub struct VehicleInstancingSettings {
+    pub enabled: bool,
+}
+
+#[derive(Component, Debug)]
+struct VehicleBatch(usize);
+
+#[derive(Clone, Copy, Debug)]
+struct VehicleInstance {
+    transform: Mat4,
+    color: LinearRgba,
+}
+
+impl VehicleInstance {
+    fn write(&self, bytes: &mut Vec<u8>) {
+        let floats = self.transform.to_cols_array().into_iter().chain(self.color.to_f32_array());
+        bytes.extend(floats.flat_map(f32::to_ne_bytes));
+    }
+}
+
+// The vehicles of one model seen this frame, and the texture of the model's material.
+#[derive(Component, Clone, Debug, Default)]
+pub struct VehicleInstances {
+    texture: Option<AssetId<Image>>,
+    instances: Vec<VehicleInstance>,
+}
+
+impl ExtractComponent for VehicleInstances {
+    type QueryData = &'static VehicleInstances;
+    type QueryFilter = ();
+    type Out = Self;
+
+    fn extract_component(item: QueryItem<'_, Self::QueryData>) -> Option<Self> {
+        (!item.instances.is_empty()).then(|| item.clone())
+    }
+}
+
+// One batch per vehicle model, added as models come in from the manifest. The batch sits at the
+// origin and its instances are spread over the whole city, so it must never be frustum culled.
+fn spawn_vehicle_batches(mut commands: Commands, models: Res<Models>, batch_query: Query<&VehicleBatch>) {
+    let spawned = batch_query.iter().count();
+
+    for (index, model) in models.vehicle_models.iter().enumerate().skip(spawned) {
+        commands.spawn((
+            model.mesh.clone(),
+            SpatialBundle::INHERITED_IDENTITY,
+            NoFrustumCulling,
+            VehicleBatch(index),
+            VehicleInstances::default(),
+        ));
+    }
+}
+
+// Vehicles give their mesh back when instancing is turned off, so switching needs no respawn.
+fn sync_vehicle_meshes(
+    mut commands: Commands,
+    settings: Res<VehicleInstancingSettings>,
+    models: Res<Models>,
+    vehicle_query: Query<(Entity, &Vehicle, Has<Handle<Mesh>>)>,
+) {
+    for (entity, vehicle, has_mesh) in &vehicle_query {
+        match (settings.enabled, has_mesh) {
+            (true, true) => {
+                commands.entity(entity).remove::<Handle<Mesh>>();
+            }
+            (false, false) => {
+                if let Some(model) = models.vehicle_models.get(vehicle.model) {
+                    commands.entity(entity).insert(model.mesh.clone());
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+// Hidden vehicles stay out of the buffers.
+fn gather_vehicle_instances(
+    settings: Res<VehicleInstancingSettings>,
+    models: Res<Models>,
+    materials: Res<Assets<StandardMaterial>>,
+    vehicle_query: Query<(&Vehicle, &Transform, &InheritedVisibility)>,
+    mut batch_query: Query<(&VehicleBatch, &mut VehicleInstances)>,
+) {
+    let mut batches: HashMap<usize, Mut<VehicleInstances>> =
+        batch_query.iter_mut().map(|(batch, instances)| (batch.0, instances)).collect();
+
+    let mut colors = HashMap::new();
+    for (&index, instances) in &mut batches {
+        let material = models.vehicle_models.get(index).and_then(|model| materials.get(&model.material));
+        instances.texture = material.and_then(|material| material.base_color_texture.as_ref()).map(Handle::id);
+        instances.instances.clear();
+        colors.insert(
+            index,
+            material.map_or(LinearRgba::WHITE, |material| material.base_color.into()),
+        );
+    }
+
+    if !settings.enabled {
+        return;
+    }
+
+    for (vehicle, transform, visibility) in &vehicle_query {
+        if let (true, Some(instances)) = (visibility.get(), batches.get_mut(&vehicle.model)) {
+            instances.instances.push(VehicleInstance {
+                transform: transform.compute_matrix(),
+                color: colors[&vehicle.model],
+            });
+        }
+    }
+}
+
+#[derive(Component)]
+struct VehicleInstanceBuffer {
+    buffer: Buffer,
+    length: u32,
+}
+
+fn prepare_instance_buffers(
+    mut commands: Commands,
+    batch_query: Query<(Entity, &VehicleInstances)>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, batch) in &batch_query {
+        let mut contents = Vec::new();
+        for instance in &batch.instances {
+            instance.write(&mut contents);
+        }
+
+        let buffer = render_device.create_buffer_with_data(&BufferInitDescriptor {
+            label: Some("vehicle instance buffer"),
+            contents: &contents,
+            usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+        });
+        commands.entity(entity).insert(VehicleInstanceBuffer {
+            buffer,
+            length: batch.instances.len() as u32,
+        });
+    }
+}
+
+#[derive(Component)]
+struct VehicleTextureBindGroup(BindGroup);
+
+// Models still loading their texture are drawn with the plain white fallback until it arrives.
+fn prepare_texture_bind_groups(
+    mut commands: Commands,
+    batch_query: Query<(Entity, &VehicleInstances)>,
+    pipeline: Res<VehicleInstancingPipeline>,
+    images: Res<RenderAssets<GpuImage>>,
+    fallback: Res<FallbackImage>,
+    render_device: Res<RenderDevice>,
+) {
+    for (entity, batch) in &batch_query {
+        let image = batch.texture.and_then(|texture| images.get(texture)).unwrap_or(&fallback.d2);
+        let bind_group = render_device.create_bind_group(
+            "vehicle_texture_bind_group",
+            &pipeline.texture_layout,
+            &BindGroupEntries::sequential((&image.texture_view, &image.sampler)),
+        );
+        commands.entity(entity).insert(VehicleTextureBindGroup(bind_group));
+    }
+}
+
+#[derive(Resource)]
+struct VehicleInstancingPipeline {
+    shader: Handle<Shader>,
+    mesh_pipeline: MeshPipeline,
+    texture_layout: BindGroupLayout,
+}
+
+impl FromWorld for VehicleInstancingPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let texture_layout = world.resource::<RenderDevice>().create_bind_group_layout(
+            "vehicle_texture_layout",
+            &BindGroupLayoutEntries::sequential(
+                ShaderStages::FRAGMENT,
+                (
+                    texture_2d(TextureSampleType::Float { filterable: true }),
+                    sampler(SamplerBindingType::Filtering),
+                ),
+            ),
+        );
+
+        Self {
+            shader: world.load_asset(SHADER_PATH),
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            texture_layout,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for VehicleInstancingPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+
+        // Four columns of the vehicle's transform, then its colour.
+        let vector = VertexFormat::Float32x4;
+        descriptor.vertex.shader = self.shader.clone();
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: vector.size() * INSTANCE_VECTORS as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: (0..INSTANCE_VECTORS)
+                .map(|index| VertexAttribute {
+                    format: vector,
+                    offset: vector.size() * index as u64,
+                    shader_location: FIRST_INSTANCE_LOCATION + index,
+                })
+                .collect(),
+        });
+        descriptor.layout.push(self.texture_layout.clone());
+        if let Some(fragment) = descriptor.fragment.as_mut() {
+            fragment.shader = self.shader.clone();
+        }
+        Ok(descriptor)
+    }
+}
+
+#[derive(SystemParam)]
+struct VehiclePipelines<'w> {
+    draw_functions: Res<'w, DrawFunctions<Transparent3d>>,
+    pipeline: Res<'w, VehicleInstancingPipeline>,
+    pipelines: ResMut<'w, SpecializedMeshPipelines<VehicleInstancingPipeline>>,
+    pipeline_cache: Res<'w, PipelineCache>,
+}
+
+type ViewKeyQuery = (
+    Entity,
+    &'static ExtractedView,
+    Option<&'static Tonemapping>,
+    Option<&'static DebandDither>,
+    Has<NormalPrepass>,
+    Has<DepthPrepass>,
+    Has<MotionVectorPrepass>,
+    Has<DeferredPrepass>,
+);
+
+// The view bind group layout depends on the prepasses the camera runs, so the key has to name them
+// the way the material pipelines do.
+fn view_key(
+    msaa: &Msaa,
+    (_, view, tonemapping, dither, normal, depth, motion, deferred): QueryItem<ViewKeyQuery>,
+) -> MeshPipelineKey {
+    let mut key = MeshPipelineKey::from_msaa_samples(msaa.samples()) | MeshPipelineKey::from_hdr(view.hdr);
+
+    for (enabled, flag) in [
+        (normal, MeshPipelineKey::NORMAL_PREPASS),
+        (depth, MeshPipelineKey::DEPTH_PREPASS),
+        (motion, MeshPipelineKey::MOTION_VECTOR_PREPASS),
+        (deferred, MeshPipelineKey::DEFERRED_PREPASS),
+    ] {
+        if enabled {
+            key |= flag;
+        }
+    }
+
+    if !view.hdr {
+        if let Some(&tonemapping) = tonemapping {
+            key |= MeshPipelineKey::TONEMAP_IN_SHADER | tonemapping_pipeline_key(tonemapping);
+        }
+        if let Some(DebandDither::Enabled) = dither {
+            key |= MeshPipelineKey::DEBAND_DITHER;
+        }
+    }
+
+    key
+}
+
+fn queue_vehicle_batches(
+    mut pipelines: VehiclePipelines,
+    msaa: Res<Msaa>,
+    meshes: Res<RenderAssets<GpuMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    batch_query: Query<Entity, With<VehicleInstances>>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    view_query: Query<ViewKeyQuery>,
+) {
+    let draw_vehicles = pipelines.draw_functions.read().id::<DrawVehicles>();
+
+    for view in &view_query {
+        let (view_entity, extracted_view) = (view.0, view.1);
+        let Some(phase) = phases.get_mut(&view_entity) else {
+            continue;
+        };
+
+        let rangefinder = extracted_view.rangefinder3d();
+        let key = view_key(&msaa, view);
+
+        for entity in &batch_query {
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(entity) else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+
+            let key = key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            let VehiclePipelines {
+                pipeline,
+                pipelines,
+                pipeline_cache,
+                ..
+            } = &mut pipelines;
+            let Ok(pipeline) = pipelines.specialize(pipeline_cache, pipeline, key, &mesh.layout) else {
+                continue;
+            };
+
+            phase.add(Transparent3d {
+                entity,
+                pipeline,
+                draw_function: draw_vehicles,
+                distance: rangefinder.distance_translation(&mesh_instance.translation),
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+type DrawVehicles = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    SetVehicleTextureBindGroup<2>,
+    DrawVehiclesInstanced,
+);
+
+struct SetVehicleTextureBindGroup<const I: usize>;
+
+impl<P: PhaseItem, const I: usize> RenderCommand<P> for SetVehicleTextureBindGroup<I> {
+    type Param = ();
+    type ViewQuery = ();
+    type ItemQuery = Read<VehicleTextureBindGroup>;
+
+    fn render<'w>(
+        _item: &P,
+        _view: (),
+        bind_group: Option<&'w VehicleTextureBindGroup>,
+        _param: SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(VehicleTextureBindGroup(bind_group)) = bind_group else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_bind_group(I, bind_group, &[]);
+        RenderCommandResult::Success
+    }
+}
+
+struct DrawVehiclesInstanced;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawVehiclesInstanced {
+    type Param = (SRes<RenderAssets<GpuMesh>>, SRes<RenderMeshInstances>);
+    type ViewQuery = ();
+    type ItemQuery = Read<VehicleInstanceBuffer>;
+
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        instance_buffer: Option<&'w VehicleInstanceBuffer>,
+        (meshes, render_mesh_instances): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.entity()) else {
+            return RenderCommandResult::Failure;
+        };
+        let (Some(gpu_mesh), Some(instance_buffer)) =
+            (meshes.into_inner().get(mesh_instance.mesh_asset_id), instance_buffer)
+        else {
+            return RenderCommandResult::Failure;
+        };
+
+        pass.set_vertex_buffer(0, gpu_mesh.vertex_buffer.slice(..));
+        pass.set_vertex_buffer(1, instance_buffer.buffer.slice(..));
+
+        match &gpu_mesh.buffer_info {
+            GpuBufferInfo::Indexed {
+                buffer,
+                index_format,
+                count,
+            } => {
+                pass.set_index_buffer(buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(0..*count, 0, 0..instance_buffer.length);
+            }
+            GpuBufferInfo::NonIndexed => {
+                pass.draw(0..gpu_mesh.vertex_count, 0..instance_buffer.length);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
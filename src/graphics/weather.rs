@@ -1,11 +1,323 @@
-use crate::schedule::UpdateStage;
-use bevy::{pbr::CascadeShadowConfigBuilder, prelude::*};
+use crate::{
+    graphics::{camera::PlayerCameraController, models::Models},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    types::vehicle::Vehicle,
+};
+use bevy::{pbr::CascadeShadowConfigBuilder, prelude::*, utils::HashSet};
+use rand::Rng;
+use std::f32::consts::PI;
+
+const DEFAULT_DAY_LENGTH_SECONDS: f32 = 720.0;
+const STARTING_HOUR: f32 = 8.0;
+const SUNRISE_HOUR: f32 = 6.0;
+const SUNSET_HOUR: f32 = 18.0;
+const NIGHT_DAYLIGHT: f32 = 0.1;
+const PEAK_ILLUMINANCE: f32 = 10_000.0;
+const MOON_ILLUMINANCE: f32 = 300.0;
+const PEAK_AMBIENT: f32 = 500.0;
+const NIGHT_AMBIENT: f32 = 80.0;
+const NOON_COLOR: Color = Color::srgb(1.0, 0.98, 0.92);
+const HORIZON_COLOR: Color = Color::srgb(1.0, 0.6, 0.35);
+const MOON_COLOR: Color = Color::srgb(0.55, 0.65, 1.0);
+const HEADLIGHT_INTENSITY: f32 = 40_000.0;
+const HEADLIGHT_RANGE: f32 = 6.0;
+const HEADLIGHT_BUDGET: usize = 48;
+const HEADLIGHT_LOD_DISTANCE: f32 = 40.0;
+const PRECIPITATION_RADIUS: f32 = 20.0;
+const PRECIPITATION_HEIGHT: f32 = 12.0;
+const PRECIPITATION_SPAWNS_PER_FRAME: usize = 40;
 
 pub struct WeatherPlugin;
 
 impl Plugin for WeatherPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_lights).add_systems(Update, adjust_weather.in_set(UpdateStage::UserInput));
+        app.insert_resource(TimeOfDay { hour: STARTING_HOUR })
+            .insert_resource(DayCycleSettings::default())
+            .insert_resource(WeatherState::default())
+            .insert_resource(WeatherSettings::default())
+            .insert_resource(HeadlightSettings::default())
+            .add_systems(Startup, spawn_lights)
+            .add_systems(
+                Update,
+                (
+                    advance_time_of_day.in_set(UpdateStage::AiBehavior),
+                    adjust_weather.in_set(UpdateStage::UserInput),
+                    (
+                        animate_sun,
+                        switch_headlights,
+                        switch_building_windows,
+                        apply_weather_fog.run_if(resource_changed::<WeatherState>),
+                        update_precipitation,
+                    )
+                        .in_set(UpdateStage::Visualize),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TimeOfDay {
+    pub hour: f32,
+}
+
+impl TimeOfDay {
+    // Height of the sun from 0 at sunrise and sunset to 1 at noon, 0 all night.
+    pub fn daylight(&self) -> f32 {
+        let progress = (self.hour - SUNRISE_HOUR) / (SUNSET_HOUR - SUNRISE_HOUR);
+        if (0.0..=1.0).contains(&progress) {
+            (progress * PI).sin()
+        } else {
+            0.0
+        }
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.daylight() < NIGHT_DAYLIGHT
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct DayCycleSettings {
+    pub enabled: bool,
+    pub day_length_seconds: f32,
+    pub peak_illuminance: f32,
+}
+
+impl Default for DayCycleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            day_length_seconds: DEFAULT_DAY_LENGTH_SECONDS,
+            peak_illuminance: PEAK_ILLUMINANCE,
+        }
+    }
+}
+
+#[derive(Resource, Default, Clone, Copy, PartialEq, Eq, Debug)]
+pub enum WeatherState {
+    #[default]
+    Clear,
+    Rain,
+    Snow,
+}
+
+impl WeatherState {
+    pub const ALL: [WeatherState; 3] = [WeatherState::Clear, WeatherState::Rain, WeatherState::Snow];
+
+    fn next(&self) -> Self {
+        match self {
+            WeatherState::Clear => WeatherState::Rain,
+            WeatherState::Rain => WeatherState::Snow,
+            WeatherState::Snow => WeatherState::Clear,
+        }
+    }
+
+    fn visibility(&self) -> f32 {
+        match self {
+            WeatherState::Clear => 35.0,
+            WeatherState::Rain => 22.0,
+            WeatherState::Snow => 14.0,
+        }
+    }
+
+    pub fn fog_falloff(&self) -> FogFalloff {
+        let (extinction, inscattering) = match self {
+            WeatherState::Clear => (Color::srgb(0.5, 0.5, 0.6), Color::srgb(0.8, 0.8, 0.9)),
+            WeatherState::Rain => (Color::srgb(0.4, 0.42, 0.48), Color::srgb(0.55, 0.58, 0.65)),
+            WeatherState::Snow => (Color::srgb(0.75, 0.78, 0.82), Color::srgb(0.92, 0.93, 0.96)),
+        };
+
+        FogFalloff::from_visibility_colors(self.visibility(), extinction, inscattering)
+    }
+
+    fn particle_count(&self) -> usize {
+        match self {
+            WeatherState::Clear => 0,
+            WeatherState::Rain => 600,
+            WeatherState::Snow => 400,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct WeatherSettings {
+    pub rain_speed_factor: f32,
+    pub snow_acceleration_factor: f32,
+}
+
+impl Default for WeatherSettings {
+    fn default() -> Self {
+        Self {
+            rain_speed_factor: 0.75,
+            snow_acceleration_factor: 0.4,
+        }
+    }
+}
+
+impl WeatherSettings {
+    pub fn speed_factor(&self, state: WeatherState) -> f32 {
+        match state {
+            WeatherState::Rain => self.rain_speed_factor,
+            _ => 1.0,
+        }
+    }
+
+    pub fn acceleration_factor(&self, state: WeatherState) -> f32 {
+        match state {
+            WeatherState::Snow => self.snow_acceleration_factor,
+            _ => 1.0,
+        }
+    }
+}
+
+// Spot lights are expensive per light, so only the vehicles closest to the middle of the view get one.
+#[derive(Resource, Debug)]
+pub struct HeadlightSettings {
+    pub enabled: bool,
+    pub budget: usize,
+    pub lod_distance: f32,
+}
+
+impl Default for HeadlightSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            budget: HEADLIGHT_BUDGET,
+            lod_distance: HEADLIGHT_LOD_DISTANCE,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+struct Precipitation {
+    velocity: Vec3,
+    phase: f32,
+}
+
+#[derive(Component, Debug)]
+pub struct Headlight;
+
+#[derive(Component, Debug)]
+pub struct BuildingWindows {
+    pub day: Handle<StandardMaterial>,
+    pub night: Handle<StandardMaterial>,
+}
+
+fn headlight_bundle() -> (SpotLightBundle, Headlight) {
+    (
+        SpotLightBundle {
+            spot_light: SpotLight {
+                intensity: HEADLIGHT_INTENSITY,
+                range: HEADLIGHT_RANGE,
+                outer_angle: 0.5,
+                inner_angle: 0.3,
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, 0.1, -0.3).with_rotation(Quat::from_rotation_x(-0.25)),
+            ..default()
+        },
+        Headlight,
+    )
+}
+
+fn advance_time_of_day(mut time_of_day: ResMut<TimeOfDay>, settings: Res<DayCycleSettings>, time: Res<Time>) {
+    if settings.enabled {
+        let seconds_per_hour = settings.day_length_seconds.max(1.0) / 24.0;
+        time_of_day.hour = (time_of_day.hour + time.delta_seconds() / seconds_per_hour) % 24.0;
+    }
+}
+
+fn animate_sun(
+    mut light_query: Query<(&mut DirectionalLight, &mut Transform)>,
+    mut ambient: ResMut<AmbientLight>,
+    time_of_day: Res<TimeOfDay>,
+    settings: Res<DayCycleSettings>,
+) {
+    let daylight = time_of_day.daylight();
+    let azimuth = (time_of_day.hour / 24.0) * 2.0 * PI;
+    let elevation = if time_of_day.is_night() {
+        0.9
+    } else {
+        daylight.asin().max(0.15)
+    };
+    let direction = Vec3::new(
+        azimuth.cos() * elevation.cos(),
+        elevation.sin(),
+        azimuth.sin() * elevation.cos(),
+    );
+
+    for (mut light, mut transform) in &mut light_query {
+        *transform = Transform::from_translation(direction).looking_at(Vec3::ZERO, Vec3::Y);
+
+        if time_of_day.is_night() {
+            light.color = MOON_COLOR;
+            light.illuminance = MOON_ILLUMINANCE;
+        } else {
+            light.color = HORIZON_COLOR.mix(&NOON_COLOR, daylight);
+            light.illuminance = MOON_ILLUMINANCE.max(settings.peak_illuminance * daylight);
+        }
+    }
+
+    ambient.color = if time_of_day.is_night() { MOON_COLOR } else { Color::WHITE };
+    ambient.brightness = NIGHT_AMBIENT + (PEAK_AMBIENT - NIGHT_AMBIENT) * daylight;
+}
+
+fn switch_headlights(
+    mut commands: Commands,
+    headlight_query: Query<(Entity, &Parent), With<Headlight>>,
+    vehicle_query: Query<(Entity, &Transform), With<Vehicle>>,
+    camera_query: Query<&GlobalTransform, With<PlayerCameraController>>,
+    time_of_day: Res<TimeOfDay>,
+    settings: Res<HeadlightSettings>,
+) {
+    let mut lit = HashSet::new();
+
+    if let (true, true, Ok(camera)) = (settings.enabled, time_of_day.is_night(), camera_query.get_single()) {
+        let focus = ground_focus(camera);
+        let mut nearby: Vec<(Entity, f32)> = vehicle_query
+            .iter()
+            .map(|(entity, transform)| (entity, transform.translation.with_y(0.0).distance(focus)))
+            .filter(|&(_, distance)| distance <= settings.lod_distance)
+            .collect();
+
+        if nearby.len() > settings.budget {
+            nearby.select_nth_unstable_by(settings.budget, |(_, a), (_, b)| a.total_cmp(b));
+            nearby.truncate(settings.budget);
+        }
+        lit.extend(nearby.into_iter().map(|(entity, _)| entity));
+    }
+
+    let mut has_light = HashSet::new();
+    for (light, parent) in &headlight_query {
+        if lit.contains(&parent.get()) {
+            has_light.insert(parent.get());
+        } else {
+            commands.entity(light).despawn_recursive();
+        }
+    }
+
+    for &vehicle in lit.difference(&has_light) {
+        commands.entity(vehicle).with_children(|builder| {
+            builder.spawn(headlight_bundle());
+        });
+    }
+}
+
+fn switch_building_windows(
+    mut building_query: Query<(&BuildingWindows, &mut Handle<StandardMaterial>)>,
+    time_of_day: Res<TimeOfDay>,
+) {
+    for (windows, mut material) in &mut building_query {
+        let target = if time_of_day.is_night() {
+            &windows.night
+        } else {
+            &windows.day
+        };
+
+        if *material != *target {
+            *material = target.clone();
+        }
     }
 }
 
@@ -26,12 +338,98 @@ fn spawn_lights(mut commands: Commands) {
     });
 }
 
-fn adjust_weather(mut dir_light_query: Query<&mut DirectionalLight>, keyboard: Res<ButtonInput<KeyCode>>) {
-    for mut light in &mut dir_light_query {
-        if keyboard.just_pressed(KeyCode::KeyK) {
-            light.illuminance += 1_000.0;
-        } else if keyboard.just_pressed(KeyCode::KeyM) {
-            light.illuminance -= 1_000.0;
+fn adjust_weather(mut settings: ResMut<DayCycleSettings>, mut weather: ResMut<WeatherState>, actions: Actions) {
+    if actions.just_pressed(Action::SunUp) {
+        settings.peak_illuminance += 1_000.0;
+    } else if actions.just_pressed(Action::SunDown) {
+        settings.peak_illuminance = (settings.peak_illuminance - 1_000.0).max(0.0);
+    } else if actions.just_pressed(Action::CycleWeather) {
+        *weather = weather.next();
+    }
+}
+
+fn apply_weather_fog(mut fog_query: Query<&mut FogSettings, With<PlayerCameraController>>, weather: Res<WeatherState>) {
+    for mut fog in &mut fog_query {
+        fog.falloff = weather.fog_falloff();
+    }
+}
+
+// Where the camera's line of sight meets the ground, or right below it when looking at the horizon.
+pub fn ground_focus(camera: &GlobalTransform) -> Vec3 {
+    let forward = camera.forward().as_vec3();
+    let eye = camera.translation();
+    if forward.y < -0.01 {
+        eye - forward * (eye.y / forward.y)
+    } else {
+        eye
+    }
+    .with_y(0.0)
+}
+
+fn update_precipitation(
+    mut commands: Commands,
+    mut particle_query: Query<(Entity, &mut Precipitation, &mut Transform)>,
+    camera_query: Query<&GlobalTransform, With<PlayerCameraController>>,
+    weather: Res<WeatherState>,
+    models: Res<Models>,
+    time: Res<Time>,
+) {
+    let Ok(camera) = camera_query.get_single() else {
+        return;
+    };
+
+    let focus = ground_focus(camera);
+    let mut rng = rand::thread_rng();
+    let mut random_point = |height: f32| {
+        let offset = Vec2::from_angle(rng.gen_range(0.0..2.0 * PI)) * PRECIPITATION_RADIUS * rng.gen::<f32>().sqrt();
+        focus + Vec3::new(offset.x, height, offset.y)
+    };
+
+    if weather.is_changed() {
+        for (entity, _, _) in &particle_query {
+            commands.entity(entity).despawn_recursive();
+        }
+        return;
+    }
+
+    let count = particle_query.iter().count();
+    let target = weather.particle_count();
+
+    for (entity, _, _) in particle_query.iter().skip(target) {
+        commands.entity(entity).despawn_recursive();
+    }
+
+    let (mesh, material, velocity) = match *weather {
+        WeatherState::Rain => (&models.rain_mesh, &models.rain_material, Vec3::new(0.0, -14.0, 0.0)),
+        _ => (&models.snow_mesh, &models.snow_material, Vec3::new(0.0, -1.2, 0.0)),
+    };
+
+    for _ in count..target.min(count + PRECIPITATION_SPAWNS_PER_FRAME) {
+        commands.spawn((
+            PbrBundle {
+                mesh: mesh.clone(),
+                material: material.clone(),
+                transform: Transform::from_translation(random_point(PRECIPITATION_HEIGHT * rand::random::<f32>())),
+                ..default()
+            },
+            Precipitation {
+                velocity,
+                phase: rand::random::<f32>() * 2.0 * PI,
+            },
+        ));
+    }
+
+    for (_, mut particle, mut transform) in &mut particle_query {
+        particle.phase += time.delta_seconds();
+        let sway = if *weather == WeatherState::Snow {
+            Vec3::new(particle.phase.sin(), 0.0, particle.phase.cos()) * 0.3
+        } else {
+            Vec3::ZERO
+        };
+        transform.translation += (particle.velocity + sway) * time.delta_seconds();
+
+        if transform.translation.y < 0.0 || transform.translation.with_y(0.0).distance(focus) > PRECIPITATION_RADIUS {
+            transform.translation = random_point(PRECIPITATION_HEIGHT);
         }
     }
 }
@@ -0,0 +1,119 @@
+use crate::{
+    grid::{grid_area::GridArea, orientation::GAxis},
+    schedule::UpdateStage,
+    types::{intersection::Intersection, road_segment::RoadSegment},
+};
+use bevy::{prelude::*, utils::HashMap};
+
+pub struct AlignmentPlugin;
+
+impl Plugin for AlignmentPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(RoadAlignment::default())
+            .add_systems(Update, update_road_alignment.in_set(UpdateStage::Analyze));
+    }
+}
+
+// A line across the grid that an existing road edge or intersection centre lies on, and where
+// along the line that feature is.
+#[derive(Clone, Copy, Debug)]
+pub struct Guide {
+    pub anchor: Vec3,
+    pub center: bool,
+}
+
+// Lines are keyed in half cells, so edges fall on even keys and the centres of odd sized
+// intersections on odd ones.
+#[derive(Resource, Debug, Default)]
+pub struct RoadAlignment {
+    rows: HashMap<i32, Vec<Guide>>,
+    columns: HashMap<i32, Vec<Guide>>,
+    straights: Vec<(GridArea, GAxis)>,
+}
+
+fn half_cells(value: f32) -> i32 {
+    (value * 2.0).round() as i32
+}
+
+impl RoadAlignment {
+    // Guides on the line of constant z through `z`.
+    pub fn row(&self, z: f32) -> &[Guide] {
+        self.rows.get(&half_cells(z)).map_or(&[], Vec::as_slice)
+    }
+
+    // Guides on the line of constant x through `x`.
+    pub fn column(&self, x: f32) -> &[Guide] {
+        self.columns.get(&half_cells(x)).map_or(&[], Vec::as_slice)
+    }
+
+    // Straight roads running along `orientation` across exactly the same cells as `lateral`, which
+    // a new road on those cells would continue in a straight line.
+    pub fn collinear(&self, lateral: GridArea, orientation: GAxis) -> impl Iterator<Item = GridArea> + '_ {
+        self.straights.iter().filter_map(move |&(area, axis)| {
+            let matches = axis == orientation
+                && match orientation {
+                    GAxis::Z => area.min.pos.x == lateral.min.pos.x && area.max.pos.x == lateral.max.pos.x,
+                    GAxis::X => area.min.pos.y == lateral.min.pos.y && area.max.pos.y == lateral.max.pos.y,
+                };
+            matches.then_some(area)
+        })
+    }
+
+    fn add_edges(&mut self, area: GridArea) {
+        let (min, max) = (area.min.min_corner(), area.max.max_corner());
+        let center = area.center();
+
+        for z in [min.z, max.z] {
+            self.rows.entry(half_cells(z)).or_default().push(Guide {
+                anchor: center.with_z(z),
+                center: false,
+            });
+        }
+
+        for x in [min.x, max.x] {
+            self.columns.entry(half_cells(x)).or_default().push(Guide {
+                anchor: center.with_x(x),
+                center: false,
+            });
+        }
+    }
+
+    fn add_center(&mut self, center: Vec3) {
+        let guide = Guide {
+            anchor: center,
+            center: true,
+        };
+        self.rows.entry(half_cells(center.z)).or_default().push(guide);
+        self.columns.entry(half_cells(center.x)).or_default().push(guide);
+    }
+}
+
+// Roads and intersections are only ever replaced rather than edited in place, so the index is only
+// rebuilt when one is added or removed.
+fn update_road_alignment(
+    mut alignment: ResMut<RoadAlignment>,
+    segment_query: Query<&RoadSegment>,
+    inter_query: Query<&Intersection>,
+    added_segments: Query<(), Added<RoadSegment>>,
+    added_intersections: Query<(), Added<Intersection>>,
+    mut removed_segments: RemovedComponents<RoadSegment>,
+    mut removed_intersections: RemovedComponents<Intersection>,
+) {
+    let removed = removed_segments.read().count() + removed_intersections.read().count();
+    if removed == 0 && added_segments.is_empty() && added_intersections.is_empty() {
+        return;
+    }
+
+    *alignment = RoadAlignment::default();
+
+    for segment in &segment_query {
+        alignment.add_edges(segment.area);
+        if segment.is_straight() {
+            alignment.straights.push((segment.area, segment.orientation));
+        }
+    }
+
+    for intersection in &inter_query {
+        alignment.add_center(intersection.area.center());
+    }
+}
@@ -1,38 +1,70 @@
-use crate::{graph::road_graph_events::*, grid::grid_area::*, grid::grid_cell::*, schedule::UpdateStage};
-use bevy::{prelude::*, utils::HashMap};
+use crate::{
+    graph::road_graph_events::*,
+    graphics::models::add_render_asset,
+    grid::grid_area::*,
+    grid::grid_cell::*,
+    grid::terrain::Terrain,
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        RenderPlugin,
+    },
+    utils::{HashMap, HashSet},
+};
 use bevy_infinite_grid::{InfiniteGrid, InfiniteGridBundle};
-use std::{f32::consts::FRAC_PI_2, fmt};
+use std::fmt;
 
-pub const GRID_RADIUS: i32 = 100;
+pub const GRID_RADIUS: i32 = 500;
 pub const GRID_DIAMETER: i32 = GRID_RADIUS * 2;
-pub const NUM_CELLS: i32 = GRID_DIAMETER * GRID_DIAMETER;
+pub const CHUNK_SIZE: i32 = 16;
+const CHUNKS_PER_SIDE: i32 = (GRID_DIAMETER + CHUNK_SIZE - 1) / CHUNK_SIZE;
+const CELLS_PER_CHUNK: usize = (CHUNK_SIZE * CHUNK_SIZE) as usize;
+const OCCUPANCY_LIFT: f32 = 0.01;
+const OCCUPANCY_INSET: f32 = 0.05;
 
 pub struct GridPlugin;
 
 impl Plugin for GridPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(bevy_infinite_grid::InfiniteGridPlugin)
-            .add_systems(Startup, (spawn_grid, spawn_ground, spawn_grid_visualization))
-            .add_systems(
-                Update,
+        if app.is_plugin_added::<RenderPlugin>() {
+            app.add_plugins(bevy_infinite_grid::InfiniteGridPlugin);
+        }
+
+        app.insert_resource(Terrain::generate()).add_systems(Startup, (spawn_grid, spawn_grid_visualization)).add_systems(
+            Update,
+            (
                 (
-                    (
-                        clear_erased_objects_from_grid::<OnRoadDestroyed>,
-                        clear_erased_objects_from_grid::<OnIntersectionDestroyed>,
-                        clear_erased_objects_from_grid::<OnBuildingDestroyed>,
-                    )
-                        .in_set(UpdateStage::SoftDestroy),
-                    (toggle_grid_visualization, visualize_occupancy).in_set(UpdateStage::Visualize),
-                ),
-            );
+                    clear_erased_objects_from_grid::<OnRoadDestroyed>,
+                    clear_erased_objects_from_grid::<OnIntersectionDestroyed>,
+                    clear_erased_objects_from_grid::<OnBuildingDestroyed>,
+                    clear_erased_objects_from_grid::<OnPropDestroyed>,
+                )
+                    .in_set(UpdateStage::SoftDestroy),
+                (toggle_grid_visualization, update_occupancy_overlay).chain().in_set(UpdateStage::Visualize),
+            ),
+        );
     }
 }
 
+// Cells are stored in fixed-size chunks that are only allocated once something is placed in them,
+// so large, mostly empty maps stay cheap to hold and to scan.
+#[derive(Default)]
+struct GridChunk {
+    cells: Option<Box<[Option<Entity>]>>,
+    occupied: u32,
+    dirty: bool,
+    revision: u32,
+}
+
 #[derive(Component)]
 pub struct Grid {
-    entities: Vec<Option<Entity>>,
+    chunks: Vec<GridChunk>,
     addresses: HashMap<Entity, Vec<GridCell>>,
-    center: IVec2,
 }
 
 #[derive(Debug, Clone)]
@@ -47,23 +79,143 @@ impl fmt::Display for GridBoundsError {
 impl Grid {
     fn new() -> Self {
         Self {
-            entities: vec![None; NUM_CELLS as usize],
+            chunks: Grid::all_chunks()
+                .map(|_| GridChunk {
+                    dirty: true,
+                    ..default()
+                })
+                .collect(),
             addresses: HashMap::new(),
-            center: IVec2::new(GRID_RADIUS, GRID_RADIUS),
         }
     }
 
-    fn coordinate(offset: IVec2) -> usize {
-        (offset.y * GRID_DIAMETER + offset.x) as usize
+    pub fn all_chunks() -> impl Iterator<Item = IVec2> {
+        (0..CHUNKS_PER_SIDE).flat_map(move |y| (0..CHUNKS_PER_SIDE).map(move |x| IVec2::new(x, y)))
+    }
+
+    pub fn chunk_area(chunk: IVec2) -> GridArea {
+        let min = chunk * CHUNK_SIZE - IVec2::splat(GRID_RADIUS);
+        let max = (min + IVec2::splat(CHUNK_SIZE - 1)).min(IVec2::splat(GRID_RADIUS - 1));
+        GridArea::new(GridCell { pos: min }, GridCell { pos: max })
+    }
+
+    pub fn chunk_of(cell: GridCell) -> IVec2 {
+        (cell.pos + IVec2::splat(GRID_RADIUS)) / CHUNK_SIZE
+    }
+
+    pub fn take_dirty_chunks(&mut self) -> Vec<IVec2> {
+        Grid::all_chunks()
+            .zip(&mut self.chunks)
+            .filter_map(|(position, chunk)| std::mem::take(&mut chunk.dirty).then_some(position))
+            .collect()
+    }
+
+    // Bumped every time a cell in the chunk changes, so any number of views can each tell which
+    // chunks changed since they last looked.
+    pub fn chunk_revision(&self, chunk: IVec2) -> u32 {
+        self.chunks[(chunk.y * CHUNKS_PER_SIDE + chunk.x) as usize].revision
+    }
+
+    fn slot(cell: GridCell) -> Result<(usize, usize), GridBoundsError> {
+        let offset = cell.pos + IVec2::splat(GRID_RADIUS);
+        if offset.x < 0 || offset.x >= GRID_DIAMETER || offset.y < 0 || offset.y >= GRID_DIAMETER {
+            return Err(GridBoundsError);
+        }
+
+        let chunk = offset / CHUNK_SIZE;
+        let local = offset % CHUNK_SIZE;
+        Ok((
+            (chunk.y * CHUNKS_PER_SIDE + chunk.x) as usize,
+            (local.y * CHUNK_SIZE + local.x) as usize,
+        ))
+    }
+
+    fn set(&mut self, cell: GridCell, entity: Option<Entity>) {
+        let Ok((chunk_index, cell_index)) = Grid::slot(cell) else {
+            return;
+        };
+
+        let chunk = &mut self.chunks[chunk_index];
+        let cells = chunk.cells.get_or_insert_with(|| vec![None; CELLS_PER_CHUNK].into_boxed_slice());
+        let previous = std::mem::replace(&mut cells[cell_index], entity);
+
+        match (previous.is_some(), entity.is_some()) {
+            (false, true) => chunk.occupied += 1,
+            (true, false) => chunk.occupied -= 1,
+            _ => {}
+        }
+        chunk.dirty = true;
+        chunk.revision = chunk.revision.wrapping_add(1);
     }
 
     pub fn entity_at(&self, cell: GridCell) -> Result<Option<Entity>, GridBoundsError> {
-        let offset = self.center + cell.pos;
-        if offset.x >= 0 && offset.x < GRID_DIAMETER && offset.y >= 0 && offset.y < GRID_DIAMETER {
-            Ok(self.entities[Grid::coordinate(offset)])
-        } else {
-            Err(GridBoundsError)
+        let (chunk_index, cell_index) = Grid::slot(cell)?;
+        Ok(self.chunks[chunk_index].cells.as_ref().and_then(|cells| cells[cell_index]))
+    }
+
+    // Distinct entities with at least one cell centered within `radius` of `center`, nearest cells first.
+    pub fn entities_in_radius(&self, center: Vec3, radius: f32) -> Vec<Entity> {
+        let reach = Vec3::new(radius, 0.0, radius);
+        let area = GridArea::new(GridCell::at(center - reach), GridCell::at(center + reach));
+
+        let mut cells: Vec<(GridCell, f32)> = area
+            .iter()
+            .map(|cell| (cell, cell.center().distance(center.with_y(0.0))))
+            .filter(|&(_, distance)| distance <= radius)
+            .collect();
+        cells.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let mut seen = HashSet::new();
+        cells
+            .into_iter()
+            .filter_map(|(cell, _)| self.entity_at(cell).ok().flatten())
+            .filter(|&entity| seen.insert(entity))
+            .collect()
+    }
+
+    // Walks the cells under a ray in the order it crosses them, pairing each with the distance along
+    // the ray at which it is entered. `direction` is expected to be normalized.
+    pub fn raycast_cells(&self, origin: Vec3, direction: Vec3, max_distance: f32) -> Vec<(GridCell, f32)> {
+        let heading = direction.xz();
+        let start = origin.xz();
+        let mut cell = GridCell::at(origin);
+        let step = IVec2::new(heading.x.signum() as i32, heading.y.signum() as i32);
+
+        let boundary = |position: f32, index: i32, towards: f32| match towards {
+            t if t > 0.0 => (index as f32 + 1.0 - position) / t,
+            t if t < 0.0 => (index as f32 - position) / t,
+            _ => f32::INFINITY,
+        };
+        let mut next = Vec2::new(
+            boundary(start.x, cell.pos.x, heading.x),
+            boundary(start.y, cell.pos.y, heading.y),
+        );
+        let delta = Vec2::new(1.0 / heading.x.abs(), 1.0 / heading.y.abs());
+
+        let mut cells = Vec::new();
+        let mut distance = 0.0;
+
+        while distance <= max_distance {
+            if Grid::slot(cell).is_ok() {
+                cells.push((cell, distance));
+            }
+
+            if next.x < next.y {
+                distance = next.x;
+                next.x += delta.x;
+                cell.pos.x += step.x;
+            } else {
+                distance = next.y;
+                next.y += delta.y;
+                cell.pos.y += step.y;
+            }
+
+            if !distance.is_finite() {
+                break;
+            }
         }
+
+        cells
     }
 
     pub fn is_occupied(&self, cell: GridCell) -> Result<bool, GridBoundsError> {
@@ -72,7 +224,11 @@ impl Grid {
     }
 
     pub fn is_valid_paint_area(&self, area: GridArea) -> bool {
-        for cell in area.iter() {
+        self.is_valid_paint_cells(area.iter())
+    }
+
+    pub fn is_valid_paint_cells(&self, cells: impl IntoIterator<Item = GridCell>) -> bool {
+        for cell in cells {
             if let Ok(occupancy) = self.is_occupied(cell) {
                 if occupancy {
                     return false;
@@ -108,21 +264,27 @@ impl Grid {
     }
 
     pub fn mark_area_occupied(&mut self, area: GridArea, entity: Entity) {
-        for cell in area.iter() {
-            self.entities[Grid::coordinate(self.center + cell.pos)] = Some(entity);
+        self.mark_cells_occupied(area.iter(), entity);
+    }
+
+    pub fn mark_cells_occupied(&mut self, cells: impl IntoIterator<Item = GridCell>, entity: Entity) {
+        let cells: Vec<GridCell> = cells.into_iter().collect();
+        for &cell in &cells {
+            self.set(cell, Some(entity));
         }
 
-        self.addresses.entry(entity).or_insert(Vec::new()).extend(area.iter());
+        self.addresses.entry(entity).or_insert(Vec::new()).extend(cells);
+    }
+
+    pub fn anchor_of(&self, entity: Entity) -> Option<GridCell> {
+        self.addresses.get(&entity).and_then(|cells| cells.first().copied())
     }
 
     pub fn erase(&mut self, entity: Entity) {
-        if let Some(address_list) = self.addresses.get(&entity) {
+        if let Some(address_list) = self.addresses.remove(&entity) {
             for cell in address_list {
-                let offset = self.center + cell.pos;
-                self.entities[Grid::coordinate(offset)] = None;
+                self.set(cell, None);
             }
-
-            self.addresses.remove(&entity);
         }
     }
 }
@@ -131,25 +293,40 @@ fn spawn_grid(mut commands: Commands) {
     commands.spawn(Grid::new());
 }
 
-#[derive(Component)]
-pub struct Ground;
-
-fn spawn_ground(mut commands: Commands, mut meshes: ResMut<Assets<Mesh>>, mut materials: ResMut<Assets<StandardMaterial>>) {
-    commands.spawn((
-        PbrBundle {
-            mesh: meshes.add(Plane3d::default().mesh().size(GRID_DIAMETER as f32 * 100.0, GRID_DIAMETER as f32 * 100.0)),
-            material: materials.add(Color::srgb(0.2, 0.4, 0.2)),
-            ..default()
-        },
-        Ground,
-    ));
-}
-
-fn spawn_grid_visualization(mut commands: Commands) {
+fn spawn_grid_visualization(mut commands: Commands, mut materials: Option<ResMut<Assets<StandardMaterial>>>) {
     commands.spawn(InfiniteGridBundle {
         visibility: Visibility::Hidden,
         ..default()
     });
+
+    let root = commands
+        .spawn(SpatialBundle {
+            visibility: Visibility::Hidden,
+            ..default()
+        })
+        .id();
+
+    commands.insert_resource(OccupancyOverlay {
+        root,
+        material: add_render_asset(
+            &mut materials,
+            StandardMaterial {
+                base_color: Color::linear_rgba(0.75, 0.0, 0.0, 1.0),
+                unlit: true,
+                ..default()
+            },
+        ),
+        chunks: HashMap::new(),
+    });
+}
+
+// One outline mesh per occupied chunk, shown alongside the grid. A chunk's mesh is only rebuilt
+// when its revision moves on, and none are rebuilt while the grid is hidden.
+#[derive(Resource)]
+struct OccupancyOverlay {
+    root: Entity,
+    material: Handle<StandardMaterial>,
+    chunks: HashMap<IVec2, (u32, Option<Entity>)>,
 }
 
 fn clear_erased_objects_from_grid<E>(mut destroy_event: EventReader<E>, mut grid_query: Query<&mut Grid>)
@@ -164,11 +341,8 @@ where
     }
 }
 
-fn toggle_grid_visualization(
-    mut infinite_grid_query: Query<&mut Visibility, With<InfiniteGrid>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-) {
-    if keyboard.just_pressed(KeyCode::KeyG) {
+fn toggle_grid_visualization(mut infinite_grid_query: Query<&mut Visibility, With<InfiniteGrid>>, actions: Actions) {
+    if actions.just_pressed(Action::ToggleGrid) {
         let mut viz = infinite_grid_query.single_mut();
         *viz = match *viz {
             Visibility::Hidden => Visibility::Visible,
@@ -178,30 +352,88 @@ fn toggle_grid_visualization(
     }
 }
 
-fn visualize_occupancy(
+fn update_occupancy_overlay(
+    mut commands: Commands,
+    mut overlay: ResMut<OccupancyOverlay>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
     grid_query: Query<&Grid>,
-    ground_query: Query<&GlobalTransform, With<Ground>>,
+    terrain: Res<Terrain>,
     infinite_grid_query: Query<&Visibility, With<InfiniteGrid>>,
-    mut gizmos: Gizmos,
+    mut root_query: Query<&mut Visibility, Without<InfiniteGrid>>,
 ) {
-    let visible = infinite_grid_query.single();
-    if visible == Visibility::Visible {
-        let grid = grid_query.single();
-        let ground = ground_query.single();
-        for i in (-GRID_RADIUS)..(GRID_RADIUS) {
-            for j in (-GRID_RADIUS)..(GRID_RADIUS) {
-                let cell = GridCell::new(i, j);
-                if let Ok(occupancy) = grid.is_occupied(cell) {
-                    if occupancy {
-                        gizmos.rounded_rect(
-                            cell.center() + ground.up() * 0.01,
-                            Quat::from_rotation_x(FRAC_PI_2),
-                            Vec2::new(1.0, 1.0),
-                            Color::linear_rgba(0.75, 0.0, 0.0, 1.0),
-                        );
-                    }
-                }
-            }
+    let visible = *infinite_grid_query.single();
+    if let Ok(mut root) = root_query.get_mut(overlay.root) {
+        if *root != visible {
+            *root = visible;
+        }
+    }
+
+    if visible != Visibility::Visible {
+        return;
+    }
+
+    let grid = grid_query.single();
+    for chunk in Grid::all_chunks() {
+        let revision = grid.chunk_revision(chunk);
+        let (seen, mesh_entity) = overlay.chunks.get(&chunk).copied().unwrap_or((0, None));
+        if seen == revision {
+            continue;
+        }
+
+        if let Some(entity) = mesh_entity {
+            commands.entity(entity).despawn_recursive();
+        }
+
+        let mesh_entity = occupancy_mesh(grid, chunk, &terrain).map(|mesh| {
+            let entity = commands
+                .spawn(PbrBundle {
+                    mesh: add_render_asset(&mut meshes, mesh),
+                    material: overlay.material.clone(),
+                    ..default()
+                })
+                .id();
+            commands.entity(overlay.root).add_child(entity);
+            entity
+        });
+
+        overlay.chunks.insert(chunk, (revision, mesh_entity));
+    }
+}
+
+fn occupancy_mesh(grid: &Grid, chunk: IVec2, terrain: &Terrain) -> Option<Mesh> {
+    let mut positions: Vec<[f32; 3]> = Vec::new();
+    let mut indices: Vec<u32> = Vec::new();
+
+    for cell in Grid::chunk_area(chunk).iter() {
+        if !matches!(grid.is_occupied(cell), Ok(true)) {
+            continue;
         }
+
+        let height = terrain.elevation(cell) + OCCUPANCY_LIFT;
+        let (min, max) = (
+            cell.min_corner() + Vec3::splat(OCCUPANCY_INSET),
+            cell.max_corner() - Vec3::splat(OCCUPANCY_INSET),
+        );
+        let start = positions.len() as u32;
+        positions.extend([
+            [min.x, height, min.z],
+            [max.x, height, min.z],
+            [max.x, height, max.z],
+            [min.x, height, max.z],
+        ]);
+        indices.extend([start, start + 1, start + 1, start + 2, start + 2, start + 3, start + 3, start]);
+    }
+
+    if positions.is_empty() {
+        return None;
     }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+    Some(
+        Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_indices(Indices::U32(indices)),
+    )
 }
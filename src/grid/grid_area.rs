@@ -2,7 +2,7 @@ use crate::grid::{grid_cell::*, orientation::*};
 use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
 
-#[derive(Copy, Clone, Debug, Serialize, Deserialize)]
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
 pub struct GridArea {
     pub min: GridCell,
     pub max: GridCell,
@@ -65,6 +65,11 @@ impl GridArea {
         }
     }
 
+    pub fn cell_count(&self) -> i64 {
+        let dimensions = self.cell_dimensions();
+        dimensions.x as i64 * dimensions.y as i64
+    }
+
     pub fn contains_point_3d(&self, point: Vec3) -> bool {
         self.min.min_corner().x <= point.x
             && self.max.max_corner().x >= point.x
@@ -72,6 +77,18 @@ impl GridArea {
             && self.max.max_corner().z >= point.z
     }
 
+    pub fn distance_to_point_3d(&self, point: Vec3) -> f32 {
+        let min = self.min.min_corner();
+        let max = self.max.max_corner();
+        let dx = (min.x - point.x).max(point.x - max.x).max(0.0);
+        let dz = (min.z - point.z).max(point.z - max.z).max(0.0);
+        Vec2::new(dx, dz).length()
+    }
+
+    pub fn overlaps(&self, other: GridArea) -> bool {
+        self.min.pos.cmple(other.max.pos).all() && other.min.pos.cmple(self.max.pos).all()
+    }
+
     pub fn union(&self, other: GridArea) -> GridArea {
         GridArea {
             min: GridCell {
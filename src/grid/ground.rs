@@ -0,0 +1,226 @@
+use crate::{
+    graphics::models::add_render_asset,
+    grid::{
+        grid::{GRID_DIAMETER, GRID_RADIUS},
+        grid_area::GridArea,
+        grid_cell::GridCell,
+        terrain::Terrain,
+    },
+    schedule::UpdateStage,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+    utils::{HashMap, HashSet},
+};
+
+pub const GROUND_CHUNK_SIZE: i32 = 64;
+const GROUND_CHUNKS_PER_SIDE: i32 = (GRID_DIAMETER + GROUND_CHUNK_SIZE - 1) / GROUND_CHUNK_SIZE;
+const GROUND_COLOR: Srgba = Srgba::rgb(0.2, 0.4, 0.2);
+const MAX_CHUNKS_PAINTED_PER_FRAME: usize = 4;
+
+pub struct GroundPlugin;
+
+impl Plugin for GroundPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GroundPaint::default())
+            .add_systems(Startup, spawn_ground)
+            .add_systems(Update, paint_visible_chunks.in_set(UpdateStage::Visualize));
+    }
+}
+
+// Layers are laid over the ground in this order, so later ones cover earlier ones.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum GroundLayer {
+    Noise,
+    LandValue,
+    District,
+}
+
+impl GroundLayer {
+    pub const ALL: [GroundLayer; 3] = [GroundLayer::District, GroundLayer::Noise, GroundLayer::LandValue];
+}
+
+// One piece of the ground, with its own texture holding a texel per cell. Chunks are culled like any
+// other mesh, and `painted` is the revision of the paint its texture was last drawn from.
+#[derive(Component, Debug)]
+pub struct GroundChunk {
+    pub chunk: IVec2,
+    image: Handle<Image>,
+    painted: u32,
+}
+
+// Per cell colors laid over the ground, one map per layer, blended by their alpha. Any change that
+// shows bumps the revision of the chunk it lands in, and a chunk's texture is only drawn again once
+// it is on screen and behind.
+#[derive(Resource, Debug, Default)]
+pub struct GroundPaint {
+    layers: HashMap<GroundLayer, HashMap<IVec2, LinearRgba>>,
+    shown: HashSet<GroundLayer>,
+    revisions: HashMap<IVec2, u32>,
+}
+
+impl GroundPaint {
+    pub fn chunk_of(cell: GridCell) -> IVec2 {
+        (cell.pos + IVec2::splat(GRID_RADIUS)) / GROUND_CHUNK_SIZE
+    }
+
+    pub fn chunk_area(chunk: IVec2) -> GridArea {
+        let min = chunk * GROUND_CHUNK_SIZE - IVec2::splat(GRID_RADIUS);
+        let max = (min + IVec2::splat(GROUND_CHUNK_SIZE - 1)).min(IVec2::splat(GRID_RADIUS - 1));
+        GridArea::new(GridCell { pos: min }, GridCell { pos: max })
+    }
+
+    pub fn set(&mut self, layer: GroundLayer, cell: GridCell, color: Option<LinearRgba>) {
+        let cells = self.layers.entry(layer).or_default();
+        let changed = match color {
+            Some(color) => cells.insert(cell.pos, color) != Some(color),
+            None => cells.remove(&cell.pos).is_some(),
+        };
+
+        if changed && self.shown.contains(&layer) {
+            self.touch(GroundPaint::chunk_of(cell));
+        }
+    }
+
+    pub fn show(&mut self, layer: GroundLayer, shown: bool) {
+        let changed = match shown {
+            true => self.shown.insert(layer),
+            false => self.shown.remove(&layer),
+        };
+        if !changed {
+            return;
+        }
+
+        let chunks: HashSet<IVec2> = self
+            .layers
+            .get(&layer)
+            .into_iter()
+            .flat_map(|cells| cells.keys())
+            .map(|&pos| GroundPaint::chunk_of(GridCell { pos }))
+            .collect();
+        for chunk in chunks {
+            self.touch(chunk);
+        }
+    }
+
+    pub fn revision(&self, chunk: IVec2) -> u32 {
+        self.revisions.get(&chunk).copied().unwrap_or(0)
+    }
+
+    pub fn color(&self, cell: GridCell) -> LinearRgba {
+        GroundLayer::ALL
+            .iter()
+            .filter(|layer| self.shown.contains(*layer))
+            .filter_map(|layer| self.layers.get(layer)?.get(&cell.pos))
+            .fold(LinearRgba::from(GROUND_COLOR), |below, &above| {
+                below.mix(&above, above.alpha).with_alpha(1.0)
+            })
+    }
+
+    fn touch(&mut self, chunk: IVec2) {
+        *self.revisions.entry(chunk).or_default() += 1;
+    }
+}
+
+fn texel(color: LinearRgba) -> [u8; 4] {
+    Color::from(color).to_srgba().to_u8_array()
+}
+
+fn spawn_ground(
+    mut commands: Commands,
+    terrain: Res<Terrain>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+    mut images: Option<ResMut<Assets<Image>>>,
+) {
+    for y in 0..GROUND_CHUNKS_PER_SIDE {
+        for x in 0..GROUND_CHUNKS_PER_SIDE {
+            let chunk = IVec2::new(x, y);
+            let area = GroundPaint::chunk_area(chunk);
+            let size = area.cell_dimensions();
+
+            let mut image = Image::new_fill(
+                Extent3d {
+                    width: size.x as u32,
+                    height: size.y as u32,
+                    depth_or_array_layers: 1,
+                },
+                TextureDimension::D2,
+                &GROUND_COLOR.to_u8_array(),
+                TextureFormat::Rgba8UnormSrgb,
+                RenderAssetUsages::default(),
+            );
+            image.sampler = ImageSampler::nearest();
+            let image = add_render_asset(&mut images, image);
+
+            commands.spawn((
+                PbrBundle {
+                    mesh: add_render_asset(&mut meshes, terrain.chunk_mesh(area)),
+                    material: add_render_asset(
+                        &mut materials,
+                        StandardMaterial {
+                            base_color_texture: Some(image.clone()),
+                            ..default()
+                        },
+                    ),
+                    ..default()
+                },
+                GroundChunk {
+                    chunk,
+                    image,
+                    painted: 0,
+                },
+            ));
+        }
+    }
+
+    commands.spawn(PbrBundle {
+        mesh: add_render_asset(
+            &mut meshes,
+            Plane3d::default().mesh().size(GRID_DIAMETER as f32 * 100.0, GRID_DIAMETER as f32 * 100.0),
+        ),
+        material: add_render_asset(&mut materials, Color::from(GROUND_COLOR)),
+        transform: Transform::from_xyz(0.0, -0.01, 0.0),
+        ..default()
+    });
+}
+
+// Draws the paint into the textures of chunks that were on screen last frame and have fallen behind,
+// a few at a time. Chunks out of view keep their old texture until they come back into view.
+fn paint_visible_chunks(
+    mut chunk_query: Query<(&mut GroundChunk, &ViewVisibility)>,
+    paint: Res<GroundPaint>,
+    images: Option<ResMut<Assets<Image>>>,
+) {
+    let Some(mut images) = images else {
+        return;
+    };
+
+    let mut painted = 0;
+    for (mut ground, visibility) in &mut chunk_query {
+        let revision = paint.revision(ground.chunk);
+        if painted >= MAX_CHUNKS_PAINTED_PER_FRAME || !visibility.get() || ground.painted == revision {
+            continue;
+        }
+
+        let Some(image) = images.get_mut(&ground.image) else {
+            continue;
+        };
+
+        let area = GroundPaint::chunk_area(ground.chunk);
+        let width = area.cell_dimensions().x;
+        for cell in area.iter() {
+            let local = cell.pos - area.min.pos;
+            let index = ((local.y * width + local.x) * 4) as usize;
+            image.data[index..index + 4].copy_from_slice(&texel(paint.color(cell)));
+        }
+
+        ground.painted = revision;
+        painted += 1;
+    }
+}
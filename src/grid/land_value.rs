@@ -0,0 +1,199 @@
+use crate::{
+    graph::congestion::{track_congestion, Congestion},
+    grid::{
+        grid_area::GridArea,
+        grid_cell::GridCell,
+        ground::{GroundLayer, GroundPaint},
+    },
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    types::road_segment::RoadSegment,
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+const NOISE_RADIUS: f32 = 5.0;
+const ACCESS_RADIUS: f32 = 8.0;
+// Congestion is rounded to this step before it counts as noise, so small swings in traffic do not
+// repaint the cells around a road every sample.
+const NOISE_STEP: f32 = 0.1;
+const NOISE_PENALTY: f32 = 0.6;
+const EMPTY_ACCESS: f32 = 0.001;
+const OVERLAY_ALPHA: f32 = 0.7;
+const QUIET_COLOR: LinearRgba = LinearRgba::rgb(0.1, 0.4, 1.0);
+const LOUD_COLOR: LinearRgba = LinearRgba::rgb(1.0, 0.1, 0.6);
+const LOW_VALUE_COLOR: LinearRgba = LinearRgba::rgb(0.6, 0.3, 0.1);
+const HIGH_VALUE_COLOR: LinearRgba = LinearRgba::rgb(0.2, 1.0, 0.3);
+
+#[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
+pub enum OverlayState {
+    #[default]
+    Hide,
+    Noise,
+    LandValue,
+}
+
+pub struct LandValuePlugin;
+
+impl Plugin for LandValuePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_state::<OverlayState>().insert_resource(LandLayers::default()).add_systems(
+            Update,
+            (
+                cycle_overlay.in_set(UpdateStage::UserInput),
+                update_land_layers.after(track_congestion).in_set(UpdateStage::Analyze),
+                (
+                    show_overlay.run_if(state_changed::<OverlayState>),
+                    paint_overlay.run_if(resource_changed::<LandLayers>),
+                )
+                    .in_set(UpdateStage::Visualize),
+            ),
+        );
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default)]
+struct CellLayers {
+    noise: f32,
+    access: f32,
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct LayerSource {
+    area: GridArea,
+    loudness: f32,
+}
+
+// Every road adds noise and road access to the cells around it, fading out with distance. Only the
+// roads that changed are taken back out and added again, so the layers stay cheap to keep current.
+// Cells out of reach of every road are not stored, and cells that changed are kept until painted.
+#[derive(Resource, Debug, Default)]
+pub struct LandLayers {
+    cells: HashMap<IVec2, CellLayers>,
+    sources: HashMap<Entity, LayerSource>,
+    litter: HashMap<IVec2, f32>,
+    changed: HashSet<IVec2>,
+}
+
+impl LandLayers {
+    pub fn noise(&self, cell: GridCell) -> f32 {
+        self.cells.get(&cell.pos).map_or(0.0, |layers| layers.noise.clamp(0.0, 1.0))
+    }
+
+    // Highest right next to a road and falls off with distance from one, with traffic noise, and with
+    // uncollected garbage nearby.
+    pub fn land_value(&self, cell: GridCell) -> f32 {
+        let litter = self.litter.get(&cell.pos).copied().unwrap_or(0.0);
+        self.cells.get(&cell.pos).map_or(0.0, |layers| {
+            (layers.access.min(1.0) - layers.noise.clamp(0.0, 1.0) * NOISE_PENALTY - litter).clamp(0.0, 1.0)
+        })
+    }
+
+    // Replaces the penalty for garbage, each area taking the worst of the amounts covering it.
+    pub fn set_litter(&mut self, sources: impl IntoIterator<Item = (GridArea, f32)>) {
+        self.changed.extend(self.litter.drain().map(|(pos, _)| pos));
+        for (area, amount) in sources.into_iter().filter(|&(_, amount)| amount > 0.0) {
+            for cell in area.iter() {
+                self.changed.insert(cell.pos);
+                let litter = self.litter.entry(cell.pos).or_default();
+                *litter = litter.max(amount);
+            }
+        }
+    }
+
+    pub fn mean_land_value(&self, area: GridArea) -> f32 {
+        area.iter().map(|cell| self.land_value(cell)).sum::<f32>() / area.cell_count().max(1) as f32
+    }
+
+    fn apply(&mut self, source: LayerSource, sign: f32) {
+        let reach = ACCESS_RADIUS.ceil() as i32;
+        let bounds = GridArea::new(
+            GridCell::new(source.area.min.pos.x - reach, source.area.min.pos.y - reach),
+            GridCell::new(source.area.max.pos.x + reach, source.area.max.pos.y + reach),
+        );
+
+        for cell in bounds.iter() {
+            let distance = source.area.distance_to_point_3d(cell.center());
+            let access = (1.0 - distance / ACCESS_RADIUS).max(0.0);
+            if access <= 0.0 {
+                continue;
+            }
+
+            self.changed.insert(cell.pos);
+            let layers = self.cells.entry(cell.pos).or_default();
+            layers.access += access * sign;
+            layers.noise += source.loudness * (1.0 - distance / NOISE_RADIUS).max(0.0) * sign;
+
+            if layers.access < EMPTY_ACCESS {
+                self.cells.remove(&cell.pos);
+            }
+        }
+    }
+}
+
+fn cycle_overlay(actions: Actions, mut next_state: ResMut<NextState<OverlayState>>, state: Res<State<OverlayState>>) {
+    if actions.just_pressed(Action::CycleOverlay) {
+        next_state.set(match state.get() {
+            OverlayState::Hide => OverlayState::Noise,
+            OverlayState::Noise => OverlayState::LandValue,
+            OverlayState::LandValue => OverlayState::Hide,
+        });
+    }
+}
+
+fn update_land_layers(mut layers: ResMut<LandLayers>, segment_query: Query<(Entity, &RoadSegment, Option<&Congestion>)>) {
+    let mut present = HashSet::new();
+
+    for (entity, segment, congestion) in &segment_query {
+        present.insert(entity);
+
+        let ratio = congestion.map_or(0.0, |congestion| congestion.ratio.clamp(0.0, 1.0));
+        let source = LayerSource {
+            area: segment.area(),
+            loudness: (ratio / NOISE_STEP).round() * NOISE_STEP,
+        };
+
+        match layers.sources.get(&entity).copied() {
+            Some(previous) if previous == source => continue,
+            Some(previous) => layers.apply(previous, -1.0),
+            None => {}
+        }
+
+        layers.apply(source, 1.0);
+        layers.sources.insert(entity, source);
+    }
+
+    let mut removed: Vec<Entity> = layers.sources.keys().filter(|entity| !present.contains(*entity)).copied().collect();
+    removed.sort();
+
+    for entity in removed {
+        if let Some(previous) = layers.sources.remove(&entity) {
+            layers.apply(previous, -1.0);
+        }
+    }
+}
+
+fn show_overlay(state: Res<State<OverlayState>>, mut paint: ResMut<GroundPaint>) {
+    paint.show(GroundLayer::Noise, *state.get() == OverlayState::Noise);
+    paint.show(GroundLayer::LandValue, *state.get() == OverlayState::LandValue);
+}
+
+// Paints the cells that changed into both ground layers, whichever of them is showing.
+fn paint_overlay(mut layers: ResMut<LandLayers>, mut paint: ResMut<GroundPaint>) {
+    if layers.changed.is_empty() {
+        return;
+    }
+
+    let changed: Vec<IVec2> = layers.changed.drain().collect();
+    for pos in changed {
+        let cell = GridCell { pos };
+        let stored = layers.cells.contains_key(&pos);
+        let noise = QUIET_COLOR.mix(&LOUD_COLOR, layers.noise(cell)).with_alpha(OVERLAY_ALPHA);
+        let value = LOW_VALUE_COLOR.mix(&HIGH_VALUE_COLOR, layers.land_value(cell)).with_alpha(OVERLAY_ALPHA);
+
+        paint.set(GroundLayer::Noise, cell, stored.then_some(noise));
+        paint.set(GroundLayer::LandValue, cell, stored.then_some(value));
+    }
+}
@@ -1,4 +1,8 @@
+pub mod alignment;
 pub mod grid;
 pub mod grid_area;
 pub mod grid_cell;
+pub mod ground;
+pub mod land_value;
 pub mod orientation;
+pub mod terrain;
@@ -0,0 +1,290 @@
+use crate::grid::{
+    grid::{GRID_DIAMETER, GRID_RADIUS},
+    grid_area::GridArea,
+    grid_cell::GridCell,
+};
+use bevy::{
+    prelude::*,
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+    },
+    utils::HashMap,
+};
+
+const TERRAIN_SEED: u32 = 0x5eed;
+const SAMPLE_SPACING: i32 = 4;
+const SAMPLES_PER_SIDE: i32 = GRID_DIAMETER / SAMPLE_SPACING + 1;
+const EDGE_FALLOFF: f32 = 48.0;
+const RAY_STEP: f32 = 0.25;
+// (wavelength in cells, amplitude) for each octave of the heightmap.
+const OCTAVES: [(f32, f32); 3] = [(160.0, 3.0), (70.0, 1.2), (28.0, 0.3)];
+const WATER_LEVEL: f32 = 0.03;
+const BRIDGE_CLEARANCE: f32 = 0.45;
+const BRIDGE_RAMP: f32 = 3.0;
+
+// Heightmap sampled every few cells and interpolated in between. Heights never drop below zero and
+// fall off to zero towards the edge of the grid, where the flat backdrop plane takes over.
+// Painted water is kept per cell as the number of water bodies covering it, so overlapping bodies
+// can be drained independently.
+#[derive(Resource)]
+pub struct Terrain {
+    heights: Vec<f32>,
+    max_height: f32,
+    water: HashMap<IVec2, u32>,
+}
+
+impl Terrain {
+    pub fn generate() -> Self {
+        let amplitude: f32 = OCTAVES.iter().map(|&(_, amplitude)| amplitude).sum();
+
+        let heights: Vec<f32> = (0..SAMPLES_PER_SIDE * SAMPLES_PER_SIDE)
+            .map(|i| {
+                let x = (i % SAMPLES_PER_SIDE * SAMPLE_SPACING - GRID_RADIUS) as f32;
+                let z = (i / SAMPLES_PER_SIDE * SAMPLE_SPACING - GRID_RADIUS) as f32;
+
+                let noise: f32 = OCTAVES
+                    .iter()
+                    .enumerate()
+                    .map(|(octave, &(wavelength, amplitude))| {
+                        value_noise(x / wavelength, z / wavelength, TERRAIN_SEED + octave as u32) * amplitude
+                    })
+                    .sum();
+
+                let edge = GRID_RADIUS as f32 - x.abs().max(z.abs());
+                let falloff = (edge / EDGE_FALLOFF).clamp(0.0, 1.0);
+                (noise + amplitude) / 2.0 * falloff * falloff * (3.0 - 2.0 * falloff)
+            })
+            .collect();
+
+        Self {
+            max_height: heights.iter().copied().fold(0.0, f32::max),
+            heights,
+            water: HashMap::new(),
+        }
+    }
+
+    fn sample(&self, x: i32, z: i32) -> f32 {
+        let x = x.clamp(0, SAMPLES_PER_SIDE - 1);
+        let z = z.clamp(0, SAMPLES_PER_SIDE - 1);
+        self.heights[(z * SAMPLES_PER_SIDE + x) as usize]
+    }
+
+    pub fn height_at(&self, pos: Vec3) -> f32 {
+        let x = (pos.x + GRID_RADIUS as f32) / SAMPLE_SPACING as f32;
+        let z = (pos.z + GRID_RADIUS as f32) / SAMPLE_SPACING as f32;
+        let (x0, z0) = (x.floor() as i32, z.floor() as i32);
+        let (tx, tz) = (x - x0 as f32, z - z0 as f32);
+
+        let near = self.sample(x0, z0).lerp(self.sample(x0 + 1, z0), tx);
+        let far = self.sample(x0, z0 + 1).lerp(self.sample(x0 + 1, z0 + 1), tx);
+        near.lerp(far, tz)
+    }
+
+    pub fn elevation(&self, cell: GridCell) -> f32 {
+        self.height_at(cell.center())
+    }
+
+    // Height of a road surface at `pos`. Roads over water rise into bridge decks, ramping up from the
+    // shore so they meet the roads on land at ground level.
+    pub fn road_height(&self, pos: Vec3) -> f32 {
+        self.height_at(pos) + self.bridge_lift(pos)
+    }
+
+    pub fn bridge_lift(&self, pos: Vec3) -> f32 {
+        let cell = GridCell::at(pos);
+        if !self.is_water(cell) {
+            return 0.0;
+        }
+
+        let reach = BRIDGE_RAMP.ceil() as i32;
+        let shore = GridArea::new(
+            GridCell::new(cell.pos.x - reach, cell.pos.y - reach),
+            GridCell::new(cell.pos.x + reach, cell.pos.y + reach),
+        )
+        .iter()
+        .filter(|&nearby| !self.is_water(nearby))
+        .map(|nearby| GridArea::new(nearby, nearby).distance_to_point_3d(pos))
+        .fold(BRIDGE_RAMP, f32::min);
+
+        let t = shore / BRIDGE_RAMP;
+        BRIDGE_CLEARANCE * t * t * (3.0 - 2.0 * t)
+    }
+
+    pub fn is_water(&self, cell: GridCell) -> bool {
+        self.water.contains_key(&cell.pos)
+    }
+
+    pub fn is_dry(&self, cells: impl IntoIterator<Item = GridCell>) -> bool {
+        cells.into_iter().all(|cell| !self.is_water(cell))
+    }
+
+    pub fn has_water(&self) -> bool {
+        !self.water.is_empty()
+    }
+
+    pub fn add_water(&mut self, area: GridArea) {
+        for cell in area.iter() {
+            *self.water.entry(cell.pos).or_insert(0) += 1;
+        }
+    }
+
+    pub fn remove_water(&mut self, area: GridArea) {
+        for cell in area.iter() {
+            if let Some(count) = self.water.get_mut(&cell.pos) {
+                *count -= 1;
+                if *count == 0 {
+                    self.water.remove(&cell.pos);
+                }
+            }
+        }
+    }
+
+    // Lowest and highest terrain under the corners of every cell in the area.
+    pub fn bounds(&self, area: GridArea) -> (f32, f32) {
+        let (min, max) = (area.min.min_corner(), area.max.max_corner());
+
+        (min.z as i32..=max.z as i32)
+            .flat_map(|z| (min.x as i32..=max.x as i32).map(move |x| Vec3::new(x as f32, 0.0, z as f32)))
+            .map(|corner| self.height_at(corner))
+            .fold((f32::MAX, f32::MIN), |(low, high), height| {
+                (low.min(height), high.max(height))
+            })
+    }
+
+    pub fn relief(&self, area: GridArea) -> f32 {
+        let (low, high) = self.bounds(area);
+        high - low
+    }
+
+    // Steepest rise or fall between consecutive points, as height change per unit of ground distance.
+    pub fn max_grade(&self, points: &[Vec3]) -> f32 {
+        points
+            .windows(2)
+            .map(|pair| {
+                let run = pair[0].with_y(0.0).distance(pair[1].with_y(0.0));
+                let rise = (self.height_at(pair[1]) - self.height_at(pair[0])).abs();
+                if run > f32::EPSILON {
+                    rise / run
+                } else {
+                    0.0
+                }
+            })
+            .fold(0.0, f32::max)
+    }
+
+    // Distance along the ray to the first point below the terrain surface.
+    pub fn intersect(&self, ray: Ray3d) -> Option<f32> {
+        let top = InfinitePlane3d::new(Vec3::Y);
+        let start = match ray.origin.y > self.max_height {
+            true => ray.intersect_plane(Vec3::Y * self.max_height, top)?,
+            false => 0.0,
+        };
+        let end = ray.intersect_plane(Vec3::ZERO, top)?;
+        let above = |t: f32| {
+            let point = ray.get_point(t);
+            point.y > self.height_at(point)
+        };
+
+        let mut t = start;
+        while t < end {
+            let next = (t + RAY_STEP).min(end);
+            if !above(next) {
+                let (mut low, mut high) = (t, next);
+                for _ in 0..8 {
+                    let mid = (low + high) / 2.0;
+                    if above(mid) {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                return Some(high);
+            }
+            t = next;
+        }
+
+        Some(end)
+    }
+
+    // The heightmap under one area whose corners sit on sample points, with UVs running from 0 to 1
+    // across the area so a texture can be laid over it one texel per cell.
+    pub fn chunk_mesh(&self, area: GridArea) -> Mesh {
+        let sample_of = |world: f32| (world as i32 + GRID_RADIUS) / SAMPLE_SPACING;
+        let (min, max) = (area.min.min_corner(), area.max.max_corner());
+        let (x0, z0) = (sample_of(min.x), sample_of(min.z));
+        let (x1, z1) = (sample_of(max.x), sample_of(max.z));
+        let columns = (x1 - x0 + 1) as u32;
+
+        let mut positions = Vec::new();
+        let mut normals = Vec::new();
+        let mut uvs = Vec::new();
+
+        for z in z0..=z1 {
+            for x in x0..=x1 {
+                let world = |value: i32| (value * SAMPLE_SPACING - GRID_RADIUS) as f32;
+                positions.push([world(x), self.sample(x, z), world(z)]);
+
+                let slope_x = (self.sample(x + 1, z) - self.sample(x - 1, z)) / (2 * SAMPLE_SPACING) as f32;
+                let slope_z = (self.sample(x, z + 1) - self.sample(x, z - 1)) / (2 * SAMPLE_SPACING) as f32;
+                normals.push(Vec3::new(-slope_x, 1.0, -slope_z).normalize().to_array());
+                uvs.push([(world(x) - min.x) / (max.x - min.x), (world(z) - min.z) / (max.z - min.z)]);
+            }
+        }
+
+        let mut indices = Vec::new();
+        for z in 0..(z1 - z0) as u32 {
+            for x in 0..columns - 1 {
+                let a = z * columns + x;
+                let b = a + columns;
+                indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+            }
+        }
+
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+
+    // One quad per water cell, hugging the terrain just above the ground.
+    pub fn water_mesh(&self) -> Mesh {
+        let mut positions = Vec::with_capacity(self.water.len() * 4);
+        let mut indices = Vec::with_capacity(self.water.len() * 6);
+
+        for &pos in self.water.keys() {
+            let start = positions.len() as u32;
+            let min = GridCell { pos }.min_corner();
+            for corner in [Vec3::ZERO, Vec3::Z, Vec3::X + Vec3::Z, Vec3::X] {
+                let point = min + corner;
+                positions.push(point.with_y(self.height_at(point) + WATER_LEVEL).to_array());
+            }
+            indices.extend([0, 1, 2, 0, 2, 3].map(|index| start + index));
+        }
+
+        let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+            .with_inserted_indices(Indices::U32(indices))
+    }
+}
+
+fn lattice(x: i32, z: i32, seed: u32) -> f32 {
+    let mut hash = (x as u32).wrapping_mul(0x27d4_eb2d) ^ (z as u32).wrapping_mul(0x1656_67b1) ^ seed;
+    hash = (hash ^ (hash >> 15)).wrapping_mul(0x85eb_ca6b);
+    hash ^= hash >> 13;
+    hash as f32 / u32::MAX as f32 * 2.0 - 1.0
+}
+
+fn value_noise(x: f32, z: f32, seed: u32) -> f32 {
+    let (x0, z0) = (x.floor() as i32, z.floor() as i32);
+    let smooth = |t: f32| t * t * (3.0 - 2.0 * t);
+    let (tx, tz) = (smooth(x - x0 as f32), smooth(z - z0 as f32));
+
+    let near = lattice(x0, z0, seed).lerp(lattice(x0 + 1, z0, seed), tx);
+    let far = lattice(x0, z0 + 1, seed).lerp(lattice(x0 + 1, z0 + 1, seed), tx);
+    near.lerp(far, tz)
+}
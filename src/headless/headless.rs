@@ -0,0 +1,116 @@
+use crate::{
+    analytics::trip_stats::TripStats,
+    determinism::determinism::{launch_option, SimRng, SimTick},
+    graph::congestion::CongestionStats,
+    schedule::UpdateStage,
+    types::{pedestrian::Pedestrian, vehicle::Vehicle},
+};
+use bevy::{
+    app::AppExit, ecs::system::SystemParam, hierarchy::HierarchyPlugin, input::InputPlugin, prelude::*,
+    state::app::StatesPlugin, time::TimeUpdateStrategy, transform::TransformPlugin,
+};
+use serde::Serialize;
+use std::time::Duration;
+
+const HEADLESS_VAR: &str = "OVERCAST_HEADLESS";
+const HEADLESS_ARG: &str = "--headless";
+const DEFAULT_STEP_HZ: f64 = 60.0;
+
+// Runs the simulation without a window, renderer or egui for a fixed number of ticks, then prints
+// a JSON report and exits. Used by CI and benchmarks (`--headless 3600`).
+pub struct HeadlessPlugin {
+    pub ticks: u64,
+}
+
+impl HeadlessPlugin {
+    pub fn from_args() -> Option<Self> {
+        launch_option(HEADLESS_ARG, HEADLESS_VAR).map(|ticks| Self { ticks })
+    }
+}
+
+impl Plugin for HeadlessPlugin {
+    fn build(&self, app: &mut App) {
+        println!("Running headless for {} ticks", self.ticks);
+
+        // Headless runs always use a fixed step unless `--fixed-step` asks for a different one, so
+        // results don't depend on how fast the machine happens to be.
+        app.add_plugins((MinimalPlugins, TransformPlugin, HierarchyPlugin, InputPlugin, StatesPlugin))
+            .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_secs_f64(
+                1.0 / DEFAULT_STEP_HZ,
+            )))
+            .insert_resource(HeadlessRun { ticks: self.ticks })
+            .configure_sets(Update, UpdateStage::Visualize.run_if(never))
+            .add_systems(Update, finish_headless_run.in_set(UpdateStage::Analyze));
+    }
+}
+
+#[derive(Resource, Debug)]
+struct HeadlessRun {
+    ticks: u64,
+}
+
+#[derive(Debug, Serialize)]
+struct HeadlessReport {
+    seed: u64,
+    ticks: u64,
+    simulated_seconds: f32,
+    wall_seconds: f32,
+    vehicles: usize,
+    pedestrians: usize,
+    trips_completed: u32,
+    mean_trip_time: f32,
+    mean_trip_length: f32,
+    congestion_average: f32,
+    congestion_peak: f32,
+    rng_fingerprint: u64,
+}
+
+fn never() -> bool {
+    false
+}
+
+#[derive(SystemParam)]
+struct RunStats<'w, 's> {
+    tick: Res<'w, SimTick>,
+    sim_rng: Res<'w, SimRng>,
+    trips: Res<'w, TripStats>,
+    congestion: Res<'w, CongestionStats>,
+    time: Res<'w, Time>,
+    real_time: Res<'w, Time<Real>>,
+    vehicle_query: Query<'w, 's, (), With<Vehicle>>,
+    pedestrian_query: Query<'w, 's, (), With<Pedestrian>>,
+}
+
+impl RunStats<'_, '_> {
+    fn report(&self) -> HeadlessReport {
+        HeadlessReport {
+            seed: self.sim_rng.seed,
+            ticks: self.tick.0,
+            simulated_seconds: self.time.elapsed_seconds(),
+            wall_seconds: self.real_time.elapsed_seconds(),
+            vehicles: self.vehicle_query.iter().count(),
+            pedestrians: self.pedestrian_query.iter().count(),
+            trips_completed: self.trips.total_trips,
+            mean_trip_time: self.trips.mean_trip_time(),
+            mean_trip_length: self.trips.mean_trip_length(),
+            congestion_average: self.congestion.average,
+            congestion_peak: self.congestion.peak,
+            rng_fingerprint: self.sim_rng.fingerprint(),
+        }
+    }
+}
+
+fn finish_headless_run(run: Res<HeadlessRun>, stats: RunStats, mut exit: EventWriter<AppExit>) {
+    if stats.tick.0 < run.ticks {
+        return;
+    }
+
+    let report = stats.report();
+
+    match serde_json::to_string_pretty(&report) {
+        Ok(json) => println!("{}", json),
+        Err(error) => println!("Failed to serialize headless report: {}", error),
+    }
+
+    exit.send(AppExit::Success);
+}
@@ -0,0 +1,271 @@
+use crate::{
+    input::pointer::{Pointer, PointerButton, PointerSource},
+    schedule::UpdateStage,
+};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+const KEYMAP_DIR: &str = "assets/profile";
+const KEYMAP_FILE: &str = "assets/profile/keymap.json";
+
+const DEFAULT_BINDINGS: [(Action, KeyCode); 53] = [
+    (Action::ToolView, KeyCode::Backquote),
+    (Action::ToolBuilding, KeyCode::Digit1),
+    (Action::ToolRoad, KeyCode::Digit2),
+    (Action::ToolEraser, KeyCode::Digit3),
+    (Action::ToolConnect, KeyCode::Digit4),
+    (Action::ToolTransit, KeyCode::Digit5),
+    (Action::ToolInspect, KeyCode::Digit6),
+    (Action::ToolWater, KeyCode::Digit7),
+    (Action::ToolBlueprint, KeyCode::Digit8),
+    (Action::ToolDistrict, KeyCode::Digit9),
+    (Action::ToolProps, KeyCode::Digit0),
+    (Action::AdjustToolUp, KeyCode::KeyR),
+    (Action::AdjustToolDown, KeyCode::KeyF),
+    (Action::WidenTool, KeyCode::BracketRight),
+    (Action::NarrowTool, KeyCode::BracketLeft),
+    (Action::LengthenTool, KeyCode::Equal),
+    (Action::ShortenTool, KeyCode::Minus),
+    (Action::RotateTool, KeyCode::Tab),
+    (Action::CycleRoadShape, KeyCode::KeyC),
+    (Action::CycleRoadSurface, KeyCode::KeyY),
+    (Action::ResurfaceDistrict, KeyCode::KeyU),
+    (Action::ForceJunction, KeyCode::ShiftLeft),
+    (Action::PlaceHouse, KeyCode::KeyZ),
+    (Action::PlaceShop, KeyCode::KeyX),
+    (Action::PlaceOffice, KeyCode::KeyC),
+    (Action::PlaceFactory, KeyCode::KeyB),
+    (Action::PlaceParking, KeyCode::KeyJ),
+    (Action::PlaceStadium, KeyCode::Comma),
+    (Action::PlaceMall, KeyCode::Period),
+    (Action::Confirm, KeyCode::Enter),
+    (Action::Cancel, KeyCode::Escape),
+    (Action::RemoveLastStop, KeyCode::Backspace),
+    (Action::CycleIntersectionControl, KeyCode::KeyI),
+    (Action::SpawnVehicle, KeyCode::KeyP),
+    (Action::ToggleSpawning, KeyCode::KeyL),
+    (Action::ToggleAiView, KeyCode::KeyV),
+    (Action::ToggleRoadGraph, KeyCode::KeyH),
+    (Action::ToggleGrid, KeyCode::KeyG),
+    (Action::ToggleHeatmap, KeyCode::KeyT),
+    (Action::CycleOverlay, KeyCode::F7),
+    (Action::FollowVehicle, KeyCode::KeyO),
+    (Action::PanForward, KeyCode::KeyW),
+    (Action::PanBack, KeyCode::KeyS),
+    (Action::PanLeft, KeyCode::KeyA),
+    (Action::PanRight, KeyCode::KeyD),
+    (Action::RotateLeft, KeyCode::KeyQ),
+    (Action::RotateRight, KeyCode::KeyE),
+    (Action::SunUp, KeyCode::KeyK),
+    (Action::SunDown, KeyCode::KeyM),
+    (Action::CycleWeather, KeyCode::KeyN),
+    (Action::SaveGame, KeyCode::F5),
+    (Action::ReportBug, KeyCode::F9),
+    (Action::Screenshot, KeyCode::F12),
+];
+
+pub struct KeymapPlugin;
+
+impl Plugin for KeymapPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Keymap::load()).add_systems(Update, capture_rebinding.in_set(UpdateStage::Analyze));
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Action {
+    ToolView,
+    ToolBuilding,
+    ToolRoad,
+    ToolEraser,
+    ToolConnect,
+    ToolTransit,
+    ToolInspect,
+    ToolWater,
+    ToolBlueprint,
+    ToolDistrict,
+    ToolProps,
+    AdjustToolUp,
+    AdjustToolDown,
+    WidenTool,
+    NarrowTool,
+    LengthenTool,
+    ShortenTool,
+    RotateTool,
+    CycleRoadShape,
+    CycleRoadSurface,
+    ResurfaceDistrict,
+    ForceJunction,
+    PlaceHouse,
+    PlaceShop,
+    PlaceOffice,
+    PlaceFactory,
+    PlaceParking,
+    PlaceStadium,
+    PlaceMall,
+    Confirm,
+    Cancel,
+    RemoveLastStop,
+    CycleIntersectionControl,
+    SpawnVehicle,
+    ToggleSpawning,
+    ToggleAiView,
+    ToggleRoadGraph,
+    ToggleGrid,
+    ToggleHeatmap,
+    CycleOverlay,
+    FollowVehicle,
+    PanForward,
+    PanBack,
+    PanLeft,
+    PanRight,
+    RotateLeft,
+    RotateRight,
+    SunUp,
+    SunDown,
+    CycleWeather,
+    SaveGame,
+    ReportBug,
+    Screenshot,
+}
+
+#[derive(Resource, Debug, Serialize, Deserialize)]
+pub struct Keymap {
+    bindings: HashMap<Action, KeyCode>,
+    #[serde(skip)]
+    pub rebinding: Option<Action>,
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        Self {
+            bindings: DEFAULT_BINDINGS.into_iter().collect(),
+            rebinding: None,
+        }
+    }
+}
+
+impl Keymap {
+    // Actions added since the file was written keep their default key.
+    fn load() -> Self {
+        let mut keymap = Keymap::default();
+
+        if let Ok(file) = File::open(KEYMAP_FILE) {
+            if let Ok(saved) = serde_json::from_reader::<BufReader<File>, Keymap>(BufReader::new(file)) {
+                keymap.bindings.extend(saved.bindings);
+            }
+        }
+
+        keymap
+    }
+
+    fn write(&self) {
+        if std::fs::create_dir_all(KEYMAP_DIR).is_ok() {
+            if let Ok(file) = File::create(KEYMAP_FILE) {
+                let mut writer = BufWriter::new(file);
+                if serde_json::to_writer_pretty(&mut writer, &self).is_err() || writer.flush().is_err() {
+                    println!("Failed to write the keymap to {:?}", KEYMAP_FILE);
+                }
+            }
+        }
+    }
+
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.bindings.get(&action).copied()
+    }
+
+    // Every action in display order, paired with its current key.
+    pub fn bindings(&self) -> impl Iterator<Item = (Action, Option<KeyCode>)> + '_ {
+        DEFAULT_BINDINGS.iter().map(|&(action, _)| (action, self.key(action)))
+    }
+
+    pub fn reset(&mut self) {
+        *self = Keymap::default();
+        self.write();
+    }
+}
+
+// Keyboard access by action rather than by key, and pointer clicks and camera moves from whichever device
+// is in use. Keyed actions are muted while the player is rebinding a key.
+#[derive(SystemParam)]
+pub struct Actions<'w> {
+    keyboard: Res<'w, ButtonInput<KeyCode>>,
+    keymap: Res<'w, Keymap>,
+    pointer: Res<'w, Pointer>,
+}
+
+impl Actions<'_> {
+    pub fn key(&self, action: Action) -> Option<KeyCode> {
+        self.keymap.key(action)
+    }
+
+    pub fn just_pressed(&self, action: Action) -> bool {
+        self.keymap.rebinding.is_none() && self.key(action).is_some_and(|key| self.keyboard.just_pressed(key))
+    }
+
+    pub fn pressed(&self, action: Action) -> bool {
+        self.keymap.rebinding.is_none() && self.key(action).is_some_and(|key| self.keyboard.pressed(key))
+    }
+
+    // Alt and Ctrl turn the left mouse button into camera controls.
+    pub fn mouse_modifier_held(&self) -> bool {
+        self.pointer.source == PointerSource::Mouse && self.keyboard.any_pressed([KeyCode::AltLeft, KeyCode::ControlLeft])
+    }
+
+    pub fn pointer_position(&self) -> Option<Vec2> {
+        self.pointer.position
+    }
+
+    pub fn primary_just_pressed(&self) -> bool {
+        self.pointer.buttons.just_pressed(PointerButton::Primary)
+    }
+
+    pub fn primary_just_released(&self) -> bool {
+        self.pointer.buttons.just_released(PointerButton::Primary)
+    }
+
+    pub fn secondary_just_pressed(&self) -> bool {
+        self.pointer.buttons.just_pressed(PointerButton::Secondary)
+    }
+
+    pub fn secondary_just_released(&self) -> bool {
+        self.pointer.buttons.just_released(PointerButton::Secondary)
+    }
+
+    // Panning from a stick, with forward as positive y, on top of the pan keys.
+    pub fn pan_axis(&self) -> Vec2 {
+        self.pointer.pan
+    }
+
+    // Zoom in scroll wheel lines and turn in radians, both for this frame.
+    pub fn zoom(&self) -> f32 {
+        self.pointer.zoom
+    }
+
+    pub fn rotate(&self) -> f32 {
+        self.pointer.rotate
+    }
+
+    pub fn tool_cycle(&self) -> i32 {
+        self.pointer.tool_cycle
+    }
+}
+
+fn capture_rebinding(mut keymap: ResMut<Keymap>, keyboard: Res<ButtonInput<KeyCode>>) {
+    let Some(action) = keymap.rebinding else {
+        return;
+    };
+
+    let Some(&key) = keyboard.get_just_pressed().next() else {
+        return;
+    };
+
+    keymap.rebinding = None;
+
+    if key != KeyCode::Escape || action == Action::Cancel {
+        keymap.bindings.insert(action, key);
+        keymap.write();
+    }
+}
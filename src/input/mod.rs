@@ -0,0 +1,2 @@
+pub mod keymap;
+pub mod pointer;
@@ -0,0 +1,200 @@
+use bevy::{
+    input::{
+        gamepad::{GamepadAxisType, GamepadButtonType},
+        touch::Touch,
+        InputSystem,
+    },
+    prelude::*,
+    window::CursorMoved,
+};
+
+const STICK_DEADZONE: f32 = 0.2;
+const GAMEPAD_ZOOM_LINES: f32 = 0.1;
+const GAMEPAD_ROTATE_SPEED: f32 = 1.0;
+const TAP_SLOP_PIXELS: f32 = 12.0;
+const PINCH_PIXELS_PER_LINE: f32 = 40.0;
+const GAMEPAD_BUTTONS: [(GamepadButtonType, PointerButton); 2] = [
+    (GamepadButtonType::South, PointerButton::Primary),
+    (GamepadButtonType::East, PointerButton::Secondary),
+];
+const MOUSE_BUTTONS: [(MouseButton, PointerButton); 2] = [
+    (MouseButton::Left, PointerButton::Primary),
+    (MouseButton::Right, PointerButton::Secondary),
+];
+
+pub struct PointerPlugin;
+
+impl Plugin for PointerPlugin {
+    fn build(&self, app: &mut App) {
+        // Headless runs have no window plugin to register cursor events.
+        app.add_event::<CursorMoved>().insert_resource(Pointer::default()).add_systems(
+            PreUpdate,
+            (reset_pointer, mouse_pointer, gamepad_pointer, touch_pointer).chain().after(InputSystem),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum PointerButton {
+    Primary,
+    Secondary,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum PointerSource {
+    #[default]
+    Mouse,
+    Gamepad,
+    Touch,
+}
+
+// Where tools aim and the buttons they act on, fed by whichever device was used last: the mouse, a
+// gamepad aiming at the middle of the screen, or a finger. Camera moves from sticks and two finger
+// gestures are gathered here too, as this frame's share.
+#[derive(Resource, Debug, Default)]
+pub struct Pointer {
+    pub source: PointerSource,
+    pub position: Option<Vec2>,
+    pub buttons: ButtonInput<PointerButton>,
+    pub pan: Vec2,
+    pub zoom: f32,
+    pub rotate: f32,
+    pub tool_cycle: i32,
+    gesture: bool,
+}
+
+impl Pointer {
+    // Buttons held on the device being left are let go, so no tool is stuck mid drag.
+    fn use_source(&mut self, source: PointerSource) {
+        if self.source != source {
+            self.buttons.release_all();
+            self.source = source;
+        }
+    }
+}
+
+fn reset_pointer(mut pointer: ResMut<Pointer>) {
+    pointer.buttons.clear();
+    pointer.pan = Vec2::ZERO;
+    pointer.zoom = 0.0;
+    pointer.rotate = 0.0;
+    pointer.tool_cycle = 0;
+}
+
+fn mouse_pointer(
+    mut pointer: ResMut<Pointer>,
+    mut cursor_moved: EventReader<CursorMoved>,
+    mouse: Res<ButtonInput<MouseButton>>,
+    windows: Query<&Window>,
+) {
+    if cursor_moved.read().count() > 0 || mouse.get_just_pressed().next().is_some() {
+        pointer.use_source(PointerSource::Mouse);
+    }
+
+    if pointer.source != PointerSource::Mouse {
+        return;
+    }
+
+    pointer.position = windows.get_single().ok().and_then(|window| window.cursor_position());
+
+    for (mouse_button, button) in MOUSE_BUTTONS {
+        if mouse.just_pressed(mouse_button) {
+            pointer.buttons.press(button);
+        }
+        if mouse.just_released(mouse_button) {
+            pointer.buttons.release(button);
+        }
+    }
+}
+
+// The left stick pans, the right stick zooms and turns, A and B click and the shoulder buttons step
+// through the tools.
+fn gamepad_pointer(
+    mut pointer: ResMut<Pointer>,
+    gamepads: Res<Gamepads>,
+    buttons: Res<ButtonInput<GamepadButton>>,
+    axes: Res<Axis<GamepadAxis>>,
+    windows: Query<&Window>,
+    time: Res<Time>,
+) {
+    for gamepad in gamepads.iter() {
+        let stick = |x: GamepadAxisType, y: GamepadAxisType| {
+            let axis = |kind| axes.get(GamepadAxis::new(gamepad, kind)).unwrap_or(0.0);
+            Some(Vec2::new(axis(x), axis(y))).filter(|stick| stick.length() > STICK_DEADZONE).unwrap_or(Vec2::ZERO)
+        };
+        let left = stick(GamepadAxisType::LeftStickX, GamepadAxisType::LeftStickY);
+        let right = stick(GamepadAxisType::RightStickX, GamepadAxisType::RightStickY);
+
+        if left != Vec2::ZERO || right != Vec2::ZERO || buttons.get_just_pressed().any(|button| button.gamepad == gamepad) {
+            pointer.use_source(PointerSource::Gamepad);
+        }
+
+        if pointer.source != PointerSource::Gamepad {
+            continue;
+        }
+
+        pointer.pan += left;
+        pointer.zoom += right.y * GAMEPAD_ZOOM_LINES;
+        pointer.rotate -= right.x * GAMEPAD_ROTATE_SPEED * time.delta_seconds();
+
+        for (kind, button) in GAMEPAD_BUTTONS {
+            if buttons.just_pressed(GamepadButton::new(gamepad, kind)) {
+                pointer.buttons.press(button);
+            }
+            if buttons.just_released(GamepadButton::new(gamepad, kind)) {
+                pointer.buttons.release(button);
+            }
+        }
+
+        if buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::RightTrigger)) {
+            pointer.tool_cycle += 1;
+        }
+        if buttons.just_pressed(GamepadButton::new(gamepad, GamepadButtonType::LeftTrigger)) {
+            pointer.tool_cycle -= 1;
+        }
+    }
+
+    if pointer.source == PointerSource::Gamepad {
+        pointer.position = windows.get_single().ok().map(|window| window.size() / 2.0);
+    }
+}
+
+// One finger aims and a quick tap clicks. Two fingers pinch to zoom and twist to turn, and the
+// touches that made up such a gesture never count as a tap.
+fn touch_pointer(mut pointer: ResMut<Pointer>, touches: Res<Touches>) {
+    if touches.iter_just_pressed().next().is_some() {
+        pointer.use_source(PointerSource::Touch);
+    }
+
+    if pointer.source != PointerSource::Touch {
+        return;
+    }
+
+    let held: Vec<&Touch> = touches.iter().collect();
+    match held.as_slice() {
+        [touch] => pointer.position = Some(touch.position()),
+        [first, second, ..] => {
+            pointer.gesture = true;
+
+            let before = second.previous_position() - first.previous_position();
+            let after = second.position() - first.position();
+            pointer.zoom += (after.length() - before.length()) / PINCH_PIXELS_PER_LINE;
+            if before != Vec2::ZERO && after != Vec2::ZERO {
+                pointer.rotate += before.angle_between(after);
+            }
+        }
+        [] => {}
+    }
+
+    for touch in touches.iter_just_released() {
+        if !pointer.gesture && touch.start_position().distance(touch.position()) < TAP_SLOP_PIXELS {
+            pointer.position = Some(touch.position());
+            pointer.buttons.press(PointerButton::Primary);
+            pointer.buttons.release(PointerButton::Primary);
+        }
+    }
+
+    if held.is_empty() {
+        pointer.gesture = false;
+    }
+}
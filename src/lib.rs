@@ -0,0 +1,112 @@
+// The city simulation as a library. The binary only picks the base plugins and adds
+// `SimulationPlugin`, and other Bevy apps can embed the simulation the same way.
+pub mod analytics;
+pub mod audio;
+pub mod capture;
+pub mod clock;
+pub mod determinism;
+pub mod economy;
+pub mod export;
+pub mod generator;
+pub mod graph;
+pub mod graphics;
+pub mod grid;
+pub mod headless;
+pub mod input;
+pub mod profile;
+pub mod report;
+pub mod save;
+pub mod scenario;
+pub mod schedule;
+pub mod tools;
+pub mod types;
+pub mod ui;
+
+use bevy::prelude::*;
+
+pub mod prelude {
+    pub use crate::{
+        graph::{road_graph::RoadGraphPlugin, road_network::RoadNetwork},
+        grid::{grid::Grid, grid::GridPlugin, grid_area::GridArea, grid_cell::GridCell, orientation::GAxis},
+        headless::headless::HeadlessPlugin,
+        save::save::SavePlugin,
+        schedule::UpdateStage,
+        tools::toolbar::{ToolState, ToolbarPlugin},
+        types::{
+            building::{Building, BuildingKind},
+            intersection::Intersection,
+            road_segment::RoadSegment,
+            vehicle::{Vehicle, VehiclePlugin},
+        },
+        SimulationPlugin,
+    };
+}
+
+// Everything on top of the base plugins, so a host app adds `DefaultPlugins` or `HeadlessPlugin`
+// first and this after. Rendering and UI only come in with a window, so tests and embedders can
+// build the same world on `HeadlessPlugin`.
+pub struct SimulationPlugin {
+    pub windowed: bool,
+}
+
+impl Plugin for SimulationPlugin {
+    fn build(&self, app: &mut App) {
+        add_simulation(app, self.windowed);
+    }
+}
+
+fn add_simulation(app: &mut App, windowed: bool) {
+    app.add_plugins(schedule::SchedulePlugin)
+        .add_plugins(determinism::determinism::DeterminismPlugin)
+        .add_plugins(input::keymap::KeymapPlugin)
+        .add_plugins(input::pointer::PointerPlugin)
+        .add_plugins(graph::road_graph::RoadGraphPlugin)
+        .add_plugins(graph::congestion::CongestionPlugin)
+        .add_plugins(graph::path_cache::PathCachePlugin)
+        .add_plugins(graph::validator::GraphValidatorPlugin)
+        .add_plugins(graphics::models::ModelPlugin)
+        .add_plugins(grid::grid::GridPlugin)
+        .add_plugins(grid::ground::GroundPlugin)
+        .add_plugins(grid::alignment::AlignmentPlugin)
+        .add_plugins(grid::land_value::LandValuePlugin)
+        .add_plugins(types::vehicle::VehiclePlugin)
+        .add_plugins(types::trailer::TrailerPlugin)
+        .add_plugins(types::parking::ParkingPlugin)
+        .add_plugins(types::traffic_signal::TrafficSignalPlugin)
+        .add_plugins(types::pedestrian::PedestrianPlugin)
+        .add_plugins(types::work_zone::WorkZonePlugin)
+        .add_plugins(types::accident::AccidentPlugin)
+        .add_plugins(types::watchdog::VehicleWatchdogPlugin)
+        .add_plugins(types::vehicle_lod::VehicleLodPlugin)
+        .add_plugins(types::garbage::GarbagePlugin)
+        .add_plugins(types::landmark::LandmarkPlugin)
+        .add_plugins(types::district::DistrictPlugin)
+        .add_plugins(tools::toolbar::ToolbarPlugin)
+        .add_plugins(graphics::weather::WeatherPlugin)
+        .add_plugins(save::save::SavePlugin)
+        .add_plugins(economy::economy::EconomyPlugin)
+        .add_plugins(analytics::trip_stats::TripStatsPlugin)
+        .add_plugins(analytics::event_log::EventLogPlugin)
+        .add_plugins(analytics::metrics::MetricsPlugin)
+        .add_plugins(scenario::scenario::ScenarioPlugin)
+        .add_plugins(generator::generator::CityGeneratorPlugin { interactive: windowed })
+        .add_plugins(ui::notify::NotifyPlugin);
+
+    if windowed {
+        app.add_plugins(graphics::camera::CameraPlugin)
+            .add_plugins(graphics::vehicle_instancing::VehicleInstancingPlugin)
+            .add_plugins(graphics::building_lod::BuildingLodPlugin)
+            .add_plugins(graphics::road_markings::RoadMarkingsPlugin)
+            .add_plugins(graphics::speed_signs::SpeedSignsPlugin)
+            .add_plugins(graphics::driveways::DrivewaysPlugin)
+            .add_plugins(graphics::particles::ParticlePlugin)
+            .add_plugins(graphics::quality::GraphicsQualityPlugin)
+            .add_plugins(audio::audio::TrafficAudioPlugin)
+            .add_plugins(profile::profile::ProfilePlugin)
+            .add_plugins(report::report::BugReportPlugin)
+            .add_plugins(capture::capture::CapturePlugin)
+            .add_plugins(export::export::ExportPlugin)
+            .add_plugins(ui::egui::UiPlugin)
+            .add_plugins(ui::minimap::MinimapPlugin);
+    }
+}
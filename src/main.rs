@@ -1,29 +1,24 @@
-mod graph;
-mod graphics;
-mod grid;
-mod save;
-mod schedule;
-mod tools;
-mod types;
-mod ui;
-
 use bevy::prelude::*;
+use overcast_core::{headless::headless::HeadlessPlugin, SimulationPlugin};
 
 fn main() {
-    App::new()
-        .add_plugins(DefaultPlugins.set(AssetPlugin {
+    let mut app = App::new();
+
+    let headless = HeadlessPlugin::from_args();
+    let windowed = headless.is_none();
+
+    match headless {
+        Some(headless) => app.add_plugins(headless),
+        None => app.add_plugins(DefaultPlugins.set(AssetPlugin {
             meta_check: bevy::asset::AssetMetaCheck::Never,
             ..default()
-        }))
-        .add_plugins(schedule::SchedulePlugin)
-        .add_plugins(graph::road_graph::RoadGraphPlugin)
-        .add_plugins(graphics::camera::CameraPlugin)
-        .add_plugins(graphics::models::ModelPlugin)
-        .add_plugins(grid::grid::GridPlugin)
-        .add_plugins(types::vehicle::VehiclePlugin)
-        .add_plugins(tools::toolbar::ToolbarPlugin)
-        .add_plugins(graphics::weather::WeatherPlugin)
-        .add_plugins(save::save::SavePlugin)
-        .add_plugins(ui::egui::UiPlugin)
-        .run();
+        })),
+    };
+
+    app.add_plugins(SimulationPlugin { windowed });
+
+    #[cfg(all(feature = "telemetry", not(target_arch = "wasm32")))]
+    app.add_plugins(overcast_core::analytics::telemetry::TelemetryPlugin);
+
+    app.run();
 }
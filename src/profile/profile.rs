@@ -0,0 +1,170 @@
+use crate::{schedule::UpdateStage, tools::road_events::OnRoadBuilt, types::vehicle::OnTripCompleted};
+use bevy::{app::AppExit, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Write};
+
+const PROFILE_DIR: &str = "assets/profile";
+const PROFILE_FILE: &str = "assets/profile/profile.json";
+const PROFILE_WRITE_SECONDS: f32 = 10.0;
+
+pub struct ProfilePlugin;
+
+impl Plugin for ProfilePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Profile::load())
+            .insert_resource(ProfileWriteTimer {
+                timer: Timer::from_seconds(PROFILE_WRITE_SECONDS, TimerMode::Repeating),
+            })
+            .add_systems(Startup, start_session)
+            .add_systems(
+                Update,
+                (
+                    (record_roads_built, record_trips_completed, unlock_achievements).chain().in_set(UpdateStage::Analyze),
+                    write_profile_periodically,
+                    write_profile_on_exit,
+                ),
+            );
+    }
+}
+
+#[derive(Debug, Default, Clone, Serialize, Deserialize)]
+pub struct LifetimeStats {
+    pub sessions: u32,
+    pub roads_built: u32,
+    pub trips_completed: u32,
+    pub longest_commute: f32,
+}
+
+pub struct Achievement {
+    pub id: &'static str,
+    pub name: &'static str,
+    pub description: &'static str,
+    unlocked: fn(&LifetimeStats) -> bool,
+}
+
+pub const ACHIEVEMENTS: [Achievement; 6] = [
+    Achievement {
+        id: "first_road",
+        name: "Groundbreaking",
+        description: "Build your first road",
+        unlocked: |stats| stats.roads_built >= 1,
+    },
+    Achievement {
+        id: "road_crew",
+        name: "Road Crew",
+        description: "Build 100 roads",
+        unlocked: |stats| stats.roads_built >= 100,
+    },
+    Achievement {
+        id: "first_trip",
+        name: "Arrived",
+        description: "A vehicle completes its trip",
+        unlocked: |stats| stats.trips_completed >= 1,
+    },
+    Achievement {
+        id: "busy_streets",
+        name: "Busy Streets",
+        description: "Vehicles complete 1000 trips",
+        unlocked: |stats| stats.trips_completed >= 1000,
+    },
+    Achievement {
+        id: "long_haul",
+        name: "Long Haul",
+        description: "Survive a commute longer than 2 minutes",
+        unlocked: |stats| stats.longest_commute >= 120.0,
+    },
+    Achievement {
+        id: "regular",
+        name: "Regular",
+        description: "Play 10 sessions",
+        unlocked: |stats| stats.sessions >= 10,
+    },
+];
+
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Profile {
+    pub stats: LifetimeStats,
+    pub achievements: Vec<String>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Profile {
+    fn load() -> Self {
+        if let Ok(file) = File::open(PROFILE_FILE) {
+            if let Ok(profile) = serde_json::from_reader::<BufReader<File>, Profile>(BufReader::new(file)) {
+                return profile;
+            }
+        }
+
+        Profile::default()
+    }
+
+    fn write(&mut self) {
+        if std::fs::create_dir_all(PROFILE_DIR).is_ok() {
+            if let Ok(file) = File::create(PROFILE_FILE) {
+                let mut writer = BufWriter::new(file);
+                if serde_json::to_writer(&mut writer, &self).is_ok() && writer.flush().is_ok() {
+                    self.dirty = false;
+                }
+            }
+        }
+    }
+
+    pub fn is_unlocked(&self, achievement: &Achievement) -> bool {
+        self.achievements.iter().any(|id| id == achievement.id)
+    }
+}
+
+#[derive(Resource, Debug)]
+struct ProfileWriteTimer {
+    timer: Timer,
+}
+
+fn start_session(mut profile: ResMut<Profile>) {
+    profile.stats.sessions += 1;
+    profile.dirty = true;
+}
+
+fn record_roads_built(mut event: EventReader<OnRoadBuilt>, mut profile: ResMut<Profile>) {
+    for _ in event.read() {
+        profile.stats.roads_built += 1;
+        profile.dirty = true;
+    }
+}
+
+fn record_trips_completed(mut event: EventReader<OnTripCompleted>, mut profile: ResMut<Profile>) {
+    for &OnTripCompleted { duration, .. } in event.read() {
+        profile.stats.trips_completed += 1;
+        profile.stats.longest_commute = profile.stats.longest_commute.max(duration);
+        profile.dirty = true;
+    }
+}
+
+fn unlock_achievements(mut profile: ResMut<Profile>) {
+    if !profile.dirty {
+        return;
+    }
+
+    for achievement in &ACHIEVEMENTS {
+        if !profile.is_unlocked(achievement) && (achievement.unlocked)(&profile.stats) {
+            println!("Achievement unlocked: {}", achievement.name);
+            profile.achievements.push(achievement.id.to_string());
+        }
+    }
+}
+
+fn write_profile_periodically(mut profile: ResMut<Profile>, mut timer: ResMut<ProfileWriteTimer>, time: Res<Time>) {
+    timer.timer.tick(time.delta());
+
+    if timer.timer.just_finished() && profile.dirty {
+        profile.write();
+    }
+}
+
+fn write_profile_on_exit(mut event: EventReader<AppExit>, mut profile: ResMut<Profile>) {
+    if event.read().count() > 0 && profile.dirty {
+        profile.write();
+    }
+}
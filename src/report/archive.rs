@@ -0,0 +1,85 @@
+// Just enough of the zip format to bundle a few small text files. Entries are stored without
+// compression, which every unzip tool accepts.
+const LOCAL_HEADER: u32 = 0x04034b50;
+const CENTRAL_HEADER: u32 = 0x02014b50;
+const END_OF_CENTRAL_DIRECTORY: u32 = 0x06054b50;
+const VERSION: u16 = 20;
+// 1980-01-01, the earliest date a zip entry can carry.
+const DOS_DATE: u16 = 0x21;
+
+#[derive(Default)]
+pub struct ZipArchive {
+    data: Vec<u8>,
+    central_directory: Vec<u8>,
+    entries: u16,
+}
+
+impl ZipArchive {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, name: &str, contents: &[u8]) {
+        let offset = self.data.len() as u32;
+        let crc = crc32fast::hash(contents);
+        let size = contents.len() as u32;
+        let name_length = name.len() as u16;
+
+        put_u32(&mut self.data, LOCAL_HEADER);
+        put_u16(&mut self.data, VERSION);
+        put_entry_fields(&mut self.data, crc, size, name_length);
+        put_u16(&mut self.data, 0);
+        self.data.extend_from_slice(name.as_bytes());
+        self.data.extend_from_slice(contents);
+
+        let central = &mut self.central_directory;
+        put_u32(central, CENTRAL_HEADER);
+        put_u16(central, VERSION);
+        put_u16(central, VERSION);
+        put_entry_fields(central, crc, size, name_length);
+        for _ in 0..4 {
+            put_u16(central, 0);
+        }
+        put_u32(central, 0);
+        put_u32(central, offset);
+        central.extend_from_slice(name.as_bytes());
+
+        self.entries += 1;
+    }
+
+    pub fn finish(mut self) -> Vec<u8> {
+        let offset = self.data.len() as u32;
+        let size = self.central_directory.len() as u32;
+        self.data.append(&mut self.central_directory);
+
+        put_u32(&mut self.data, END_OF_CENTRAL_DIRECTORY);
+        put_u16(&mut self.data, 0);
+        put_u16(&mut self.data, 0);
+        put_u16(&mut self.data, self.entries);
+        put_u16(&mut self.data, self.entries);
+        put_u32(&mut self.data, size);
+        put_u32(&mut self.data, offset);
+        put_u16(&mut self.data, 0);
+        self.data
+    }
+}
+
+// Flags, method, time, date, checksum, sizes and name length, shared by both header kinds.
+fn put_entry_fields(out: &mut Vec<u8>, crc: u32, size: u32, name_length: u16) {
+    put_u16(out, 0);
+    put_u16(out, 0);
+    put_u16(out, 0);
+    put_u16(out, DOS_DATE);
+    put_u32(out, crc);
+    put_u32(out, size);
+    put_u32(out, size);
+    put_u16(out, name_length);
+}
+
+fn put_u16(out: &mut Vec<u8>, value: u16) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(out: &mut Vec<u8>, value: u32) {
+    out.extend_from_slice(&value.to_le_bytes());
+}
@@ -0,0 +1,89 @@
+use crate::report::archive::ZipArchive;
+
+const LOCAL_HEADER_SIZE: usize = 30;
+const CENTRAL_HEADER_SIZE: usize = 46;
+const END_OF_CENTRAL_DIRECTORY_SIZE: usize = 22;
+
+fn u16_at(data: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([data[at], data[at + 1]])
+}
+
+fn u32_at(data: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([data[at], data[at + 1], data[at + 2], data[at + 3]])
+}
+
+// Reads every entry back through the central directory the way an unzip tool would, checking each
+// local header agrees with it and that the stored checksums match the contents.
+fn read_back(data: &[u8]) -> Vec<(String, Vec<u8>)> {
+    let end = data.len() - END_OF_CENTRAL_DIRECTORY_SIZE;
+    assert_eq!(u32_at(data, end), 0x06054b50);
+    let entries = u16_at(data, end + 10) as usize;
+    assert_eq!(u16_at(data, end + 8) as usize, entries);
+    let directory_size = u32_at(data, end + 12) as usize;
+    let directory_offset = u32_at(data, end + 16) as usize;
+    assert_eq!(directory_offset + directory_size, end);
+
+    let mut files = Vec::new();
+    let mut central = directory_offset;
+    let mut expected_offset = 0;
+
+    for _ in 0..entries {
+        assert_eq!(u32_at(data, central), 0x02014b50);
+        let crc = u32_at(data, central + 16);
+        let size = u32_at(data, central + 20) as usize;
+        assert_eq!(u32_at(data, central + 24) as usize, size);
+        let name_length = u16_at(data, central + 28) as usize;
+        let local = u32_at(data, central + 42) as usize;
+        let name = &data[central + CENTRAL_HEADER_SIZE..central + CENTRAL_HEADER_SIZE + name_length];
+
+        assert_eq!(local, expected_offset);
+        assert_eq!(u32_at(data, local), 0x04034b50);
+        assert_eq!(u32_at(data, local + 14), crc);
+        assert_eq!(u32_at(data, local + 18) as usize, size);
+        assert_eq!(u16_at(data, local + 26) as usize, name_length);
+        let local_name_start = local + LOCAL_HEADER_SIZE;
+        assert_eq!(&data[local_name_start..local_name_start + name_length], name);
+
+        let contents_start = local_name_start + name_length + u16_at(data, local + 28) as usize;
+        let contents = &data[contents_start..contents_start + size];
+        assert_eq!(crc32fast::hash(contents), crc);
+
+        files.push((String::from_utf8(name.to_vec()).unwrap(), contents.to_vec()));
+        central += CENTRAL_HEADER_SIZE + name_length;
+        expected_offset = contents_start + size;
+    }
+
+    assert_eq!(central, end);
+    assert_eq!(expected_offset, directory_offset);
+    files
+}
+
+#[test]
+fn archive_reads_back_entries() {
+    let mut archive = ZipArchive::new();
+    archive.add("save.json", br#"{"roads":[]}"#);
+    archive.add("empty.txt", b"");
+    archive.add("event_log.txt", "tick 12: road built\ntick 40: vehicle spawned\n".as_bytes());
+
+    let files = read_back(&archive.finish());
+
+    assert_eq!(
+        files,
+        vec![
+            ("save.json".to_string(), br#"{"roads":[]}"#.to_vec()),
+            ("empty.txt".to_string(), Vec::new()),
+            (
+                "event_log.txt".to_string(),
+                b"tick 12: road built\ntick 40: vehicle spawned\n".to_vec()
+            ),
+        ]
+    );
+}
+
+#[test]
+fn empty_archive_is_only_an_end_record() {
+    let data = ZipArchive::new().finish();
+
+    assert_eq!(data.len(), END_OF_CENTRAL_DIRECTORY_SIZE);
+    assert!(read_back(&data).is_empty());
+}
@@ -0,0 +1,5 @@
+pub mod archive;
+#[cfg(test)]
+mod archive_tests;
+pub mod report;
+pub mod report_events;
@@ -0,0 +1,131 @@
+use crate::{
+    analytics::event_log::EventLog,
+    clock::unix_seconds,
+    determinism::determinism::{SimTick, SimulationSettings},
+    graph::road_network::RoadNetwork,
+    graphics::weather::{DayCycleSettings, WeatherSettings, WeatherState},
+    input::keymap::{Action, Actions},
+    report::{archive::ZipArchive, report_events::*},
+    save::save::{AutosaveSettings, WorldSnapshot},
+    schedule::UpdateStage,
+    types::{
+        pedestrian::Pedestrian,
+        vehicle::{GapAcceptanceSettings, LaneChangeSettings, Vehicle, VehicleKindSettings},
+        work_zone::ConstructionSettings,
+    },
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use std::{fs, path::Path};
+
+const REPORT_DIR: &str = "assets/bug_reports";
+
+pub struct BugReportPlugin;
+
+impl Plugin for BugReportPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestBugReport>().add_event::<OnBugReportWritten>().add_systems(
+            Update,
+            (
+                report_bug_on_key_press.in_set(UpdateStage::UserInput),
+                write_bug_report.in_set(UpdateStage::Analyze),
+            ),
+        );
+    }
+}
+
+#[derive(SystemParam)]
+struct SettingsSnapshot<'w> {
+    simulation: Res<'w, SimulationSettings>,
+    autosave: Res<'w, AutosaveSettings>,
+    construction: Res<'w, ConstructionSettings>,
+    day_cycle: Res<'w, DayCycleSettings>,
+    weather: Res<'w, WeatherState>,
+    weather_settings: Res<'w, WeatherSettings>,
+    lane_change: Res<'w, LaneChangeSettings>,
+    gap_acceptance: Res<'w, GapAcceptanceSettings>,
+    vehicle_kinds: Res<'w, VehicleKindSettings>,
+}
+
+impl SettingsSnapshot<'_> {
+    fn snapshot(&self) -> String {
+        format!(
+            "{:#?}\n{:#?}\n{:#?}\n{:#?}\n{:#?}\n{:#?}\n{:#?}\n{:#?}\n{:#?}\n",
+            *self.simulation,
+            *self.autosave,
+            *self.construction,
+            *self.day_cycle,
+            *self.weather,
+            *self.weather_settings,
+            *self.lane_change,
+            *self.gap_acceptance,
+            *self.vehicle_kinds,
+        )
+    }
+}
+
+fn report_bug_on_key_press(actions: Actions, mut event: EventWriter<RequestBugReport>) {
+    if actions.just_pressed(Action::ReportBug) {
+        event.send(RequestBugReport);
+    }
+}
+
+#[derive(SystemParam)]
+struct Diagnostics<'w, 's> {
+    tick: Res<'w, SimTick>,
+    time: Res<'w, Time<Real>>,
+    network: RoadNetwork<'w, 's>,
+    vehicle_query: Query<'w, 's, (), With<Vehicle>>,
+    pedestrian_query: Query<'w, 's, (), With<Pedestrian>>,
+}
+
+impl Diagnostics<'_, '_> {
+    fn snapshot(&self) -> String {
+        format!(
+            "overcast {}\nos: {} ({})\ncpus: {}\ntick: {}\nuptime seconds: {:.1}\nroad segments: {}\nbuildings: {}\nvehicles: {}\npedestrians: {}\n",
+            env!("CARGO_PKG_VERSION"),
+            std::env::consts::OS,
+            std::env::consts::ARCH,
+            std::thread::available_parallelism().map_or(0, |cpus| cpus.get()),
+            self.tick.0,
+            self.time.elapsed_seconds(),
+            self.network.segments().count(),
+            self.network.buildings().count(),
+            self.vehicle_query.iter().count(),
+            self.pedestrian_query.iter().count(),
+        )
+    }
+}
+
+fn write_bug_report(
+    mut request: EventReader<RequestBugReport>,
+    mut written: EventWriter<OnBugReportWritten>,
+    world: WorldSnapshot,
+    settings: SettingsSnapshot,
+    log: Res<EventLog>,
+    diagnostics: Diagnostics,
+) {
+    if request.read().count() == 0 {
+        return;
+    }
+
+    let mut archive = ZipArchive::new();
+    match world.to_json() {
+        Ok(save) => archive.add("save.json", &save),
+        Err(error) => println!("Failed to snapshot the world for the bug report: {}", error),
+    }
+    archive.add("settings.txt", settings.snapshot().as_bytes());
+    archive.add("event_log.txt", log.snapshot().as_bytes());
+    archive.add("diagnostics.txt", diagnostics.snapshot().as_bytes());
+
+    let path = Path::new(REPORT_DIR).join(format!("bug_report_{}.zip", unix_seconds()));
+
+    match fs::create_dir_all(REPORT_DIR).and_then(|_| fs::write(&path, archive.finish())) {
+        Ok(()) => {
+            println!("Wrote bug report to {:?}", path);
+            written.send(OnBugReportWritten {
+                path: path.display().to_string(),
+            });
+        }
+        Err(error) => println!("Failed to write bug report to {:?}: {}", path, error),
+    }
+}
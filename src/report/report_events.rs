@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct RequestBugReport;
+
+#[derive(Event, Debug)]
+pub struct OnBugReportWritten {
+    pub path: String,
+}
@@ -0,0 +1,261 @@
+use crate::{
+    graph::road_graph_events::*,
+    grid::{grid_area::GridArea, orientation::GAxis},
+    tools::road_events::{OnRoadResurfaced, OnRoadUpgraded},
+    types::{
+        building::{Building, BuildingKind},
+        intersection::Intersection,
+        prop::{Prop, PropKind},
+        road_segment::{RoadSegment, RoadShape, RoadSurface},
+        water::WaterBody,
+    },
+};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+// A building as it is saved. Fields added after the first save format are optional, and saves from
+// before buildings had kinds list only their areas, which load as houses. Buildings without a seed
+// use the one derived from their area.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedBuilding")]
+pub struct BuildingRecord {
+    pub area: GridArea,
+    pub kind: BuildingKind,
+    pub seed: Option<u64>,
+}
+
+impl BuildingRecord {
+    pub fn new(area: GridArea, kind: BuildingKind) -> Self {
+        Self { area, kind, seed: None }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
+    }
+
+    fn of(building: &Building) -> Self {
+        Self {
+            area: building.area(),
+            kind: building.kind,
+            seed: (!building.has_legacy_seed()).then_some(building.seed),
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedBuilding {
+    Record {
+        area: GridArea,
+        #[serde(default)]
+        kind: BuildingKind,
+        #[serde(default)]
+        seed: Option<u64>,
+    },
+    Area(GridArea),
+}
+
+impl From<SavedBuilding> for BuildingRecord {
+    fn from(saved: SavedBuilding) -> Self {
+        match saved {
+            SavedBuilding::Record { area, kind, seed } => Self { area, kind, seed },
+            SavedBuilding::Area(area) => Self::new(area, BuildingKind::House),
+        }
+    }
+}
+
+// A road as it is saved, with the same optional fields as `BuildingRecord`. Saves from before roads
+// had shapes hold only the area and orientation, which load as straight asphalt.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(from = "SavedRoad")]
+pub struct RoadRecord {
+    pub area: GridArea,
+    pub orientation: GAxis,
+    pub shape: RoadShape,
+    pub surface: RoadSurface,
+}
+
+impl RoadRecord {
+    pub fn new(area: GridArea, orientation: GAxis) -> Self {
+        Self {
+            area,
+            orientation,
+            shape: RoadShape::Straight,
+            surface: RoadSurface::Asphalt,
+        }
+    }
+
+    fn of(segment: &RoadSegment) -> Self {
+        Self {
+            area: segment.area(),
+            orientation: segment.orientation,
+            shape: segment.shape,
+            surface: segment.surface,
+        }
+    }
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum SavedRoad {
+    Record {
+        area: GridArea,
+        orientation: GAxis,
+        #[serde(default)]
+        shape: RoadShape,
+        #[serde(default)]
+        surface: RoadSurface,
+    },
+    Area(GridArea, GAxis),
+}
+
+impl From<SavedRoad> for RoadRecord {
+    fn from(saved: SavedRoad) -> Self {
+        match saved {
+            SavedRoad::Record {
+                area,
+                orientation,
+                shape,
+                surface,
+            } => Self {
+                area,
+                orientation,
+                shape,
+                surface,
+            },
+            SavedRoad::Area(area, orientation) => Self::new(area, orientation),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum SaveRecord {
+    Building(BuildingRecord),
+    Intersection(GridArea),
+    Road(RoadRecord),
+    Water(GridArea),
+    Prop(GridArea, PropKind),
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum SaveDelta {
+    Added(SaveRecord),
+    Removed(SaveRecord),
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct SaveJournal {
+    pub records: HashMap<Entity, SaveRecord>,
+    pub pending: Vec<SaveDelta>,
+    pub loading: Vec<SaveRecord>,
+    pub appended_batches: u32,
+}
+
+impl SaveJournal {
+    fn added(&mut self, entity: Entity, record: SaveRecord) {
+        if let Some(index) = self.loading.iter().position(|loaded| *loaded == record) {
+            self.loading.swap_remove(index);
+        } else {
+            self.pending.push(SaveDelta::Added(record.clone()));
+        }
+
+        self.records.insert(entity, record);
+    }
+
+    fn removed(&mut self, entity: Entity) {
+        if let Some(record) = self.records.remove(&entity) {
+            self.pending.push(SaveDelta::Removed(record));
+        }
+    }
+}
+
+// The events that change what a save holds.
+#[derive(SystemParam)]
+pub struct SaveChanges<'w, 's> {
+    road_spawned: EventReader<'w, 's, OnRoadSpawned>,
+    inter_spawned: EventReader<'w, 's, OnIntersectionSpawned>,
+    building_spawned: EventReader<'w, 's, OnBuildingSpawned>,
+    water_spawned: EventReader<'w, 's, OnWaterSpawned>,
+    road_destroyed: EventReader<'w, 's, OnRoadDestroyed>,
+    inter_destroyed: EventReader<'w, 's, OnIntersectionDestroyed>,
+    building_destroyed: EventReader<'w, 's, OnBuildingDestroyed>,
+    water_destroyed: EventReader<'w, 's, OnWaterDestroyed>,
+    road_resurfaced: EventReader<'w, 's, OnRoadResurfaced>,
+    road_upgraded: EventReader<'w, 's, OnRoadUpgraded>,
+}
+
+pub fn record_save_deltas(
+    mut journal: ResMut<SaveJournal>,
+    building_query: Query<&Building>,
+    segment_query: Query<&RoadSegment>,
+    inter_query: Query<&Intersection>,
+    water_query: Query<&WaterBody>,
+    mut changes: SaveChanges,
+) {
+    for &OnRoadDestroyed(entity) in changes.road_destroyed.read() {
+        journal.removed(entity);
+    }
+
+    for &OnIntersectionDestroyed(entity) in changes.inter_destroyed.read() {
+        journal.removed(entity);
+    }
+
+    for &OnBuildingDestroyed(entity) in changes.building_destroyed.read() {
+        journal.removed(entity);
+    }
+
+    for &OnWaterDestroyed(entity) in changes.water_destroyed.read() {
+        journal.removed(entity);
+    }
+
+    for &OnWaterSpawned(entity) in changes.water_spawned.read() {
+        if let Ok(water) = water_query.get(entity) {
+            journal.added(entity, SaveRecord::Water(water.area()));
+        }
+    }
+
+    for &OnRoadSpawned(entity) in changes.road_spawned.read() {
+        if let Ok(segment) = segment_query.get(entity) {
+            journal.added(entity, SaveRecord::Road(RoadRecord::of(segment)));
+        }
+    }
+
+    let resurfaced = changes.road_resurfaced.read().map(|event| event.0);
+    let changed_roads = resurfaced.chain(changes.road_upgraded.read().map(|event| event.0));
+    for entity in changed_roads {
+        if let Ok(segment) = segment_query.get(entity) {
+            journal.removed(entity);
+            journal.added(entity, SaveRecord::Road(RoadRecord::of(segment)));
+        }
+    }
+
+    for &OnIntersectionSpawned(entity) in changes.inter_spawned.read() {
+        if let Ok(inter) = inter_query.get(entity) {
+            journal.added(entity, SaveRecord::Intersection(inter.area()));
+        }
+    }
+
+    for &OnBuildingSpawned(entity) in changes.building_spawned.read() {
+        if let Ok(building) = building_query.get(entity) {
+            journal.added(entity, SaveRecord::Building(BuildingRecord::of(building)));
+        }
+    }
+}
+
+pub fn record_prop_deltas(
+    mut journal: ResMut<SaveJournal>,
+    prop_query: Query<&Prop>,
+    mut prop_spawned: EventReader<OnPropSpawned>,
+    mut prop_destroyed: EventReader<OnPropDestroyed>,
+) {
+    for &OnPropDestroyed(entity) in prop_destroyed.read() {
+        journal.removed(entity);
+    }
+
+    for &OnPropSpawned(entity) in prop_spawned.read() {
+        if let Ok(prop) = prop_query.get(entity) {
+            journal.added(entity, SaveRecord::Prop(prop.area(), prop.kind));
+        }
+    }
+}
@@ -1,3 +1,8 @@
 mod fallback;
+mod journal;
+pub mod persistent_id;
 pub mod save;
 pub mod save_events;
+#[cfg(test)]
+pub(crate) mod save_tests;
+pub mod storage;
@@ -0,0 +1,114 @@
+use crate::{
+    graph::road_graph_events::*,
+    grid::{grid::Grid, grid_cell::GridCell},
+};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashMap};
+use serde::{Deserialize, Serialize};
+
+const RESTORE_ATTEMPTS: u32 = 10;
+
+// A number that names a road, intersection or building for as long as it exists, and keeps naming it
+// after the world is saved and loaded again, unlike its `Entity`. Ids are never handed out twice.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug, Serialize, Deserialize)]
+pub struct PersistentId(pub u64);
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct PersistentIdRecord {
+    next: u64,
+    ids: Vec<(GridCell, PersistentId)>,
+}
+
+// Ids from a loaded save wait in `pending` under the anchor cell of what they belong to, and are given
+// back to whatever spawns there while the save streams in.
+#[derive(Resource, Debug, Default)]
+pub struct PersistentIds {
+    next: u64,
+    entities: HashMap<PersistentId, Entity>,
+    ids: HashMap<Entity, PersistentId>,
+    pending: HashMap<IVec2, PersistentId>,
+    attempts: u32,
+}
+
+impl PersistentIds {
+    pub fn id(&self, entity: Entity) -> Option<PersistentId> {
+        self.ids.get(&entity).copied()
+    }
+
+    pub fn entity(&self, id: PersistentId) -> Option<Entity> {
+        self.entities.get(&id).copied()
+    }
+
+    pub fn restore(&mut self, record: PersistentIdRecord) {
+        let highest = record.ids.iter().map(|&(_, id)| id.0 + 1).max().unwrap_or(0);
+        self.next = self.next.max(record.next).max(highest);
+        self.pending = record.ids.into_iter().map(|(cell, id)| (cell.pos, id)).collect();
+        self.attempts = 0;
+    }
+
+    pub fn record(&self, grid: &Grid) -> PersistentIdRecord {
+        let mut ids: Vec<(GridCell, PersistentId)> =
+            self.ids.iter().filter_map(|(&entity, &id)| Some((grid.anchor_of(entity)?, id))).collect();
+        ids.sort_by_key(|&(_, id)| id);
+        PersistentIdRecord { next: self.next, ids }
+    }
+
+    fn assign(&mut self, entity: Entity, anchor: Option<GridCell>) {
+        let id = match anchor.and_then(|cell| self.pending.remove(&cell.pos)) {
+            Some(id) => id,
+            None => {
+                self.next += 1;
+                PersistentId(self.next - 1)
+            }
+        };
+
+        self.entities.insert(id, entity);
+        self.ids.insert(entity, id);
+    }
+
+    fn release(&mut self, entity: Entity) {
+        if let Some(id) = self.ids.remove(&entity) {
+            self.entities.remove(&id);
+        }
+    }
+}
+
+// Roads, intersections and buildings coming and going.
+#[derive(SystemParam)]
+pub struct GraphNodeEvents<'w, 's> {
+    road_spawned: EventReader<'w, 's, OnRoadSpawned>,
+    inter_spawned: EventReader<'w, 's, OnIntersectionSpawned>,
+    building_spawned: EventReader<'w, 's, OnBuildingSpawned>,
+    road_destroyed: EventReader<'w, 's, OnRoadDestroyed>,
+    inter_destroyed: EventReader<'w, 's, OnIntersectionDestroyed>,
+    building_destroyed: EventReader<'w, 's, OnBuildingDestroyed>,
+}
+
+pub fn assign_persistent_ids(mut ids: ResMut<PersistentIds>, grid_query: Query<&Grid>, mut events: GraphNodeEvents) {
+    let grid = grid_query.single();
+
+    let destroyed = events.road_destroyed.read().map(|event| event.0);
+    let destroyed = destroyed.chain(events.inter_destroyed.read().map(|event| event.0));
+    for entity in destroyed.chain(events.building_destroyed.read().map(|event| event.0)) {
+        ids.release(entity);
+    }
+
+    let spawned = events.road_spawned.read().map(|event| event.0);
+    let spawned = spawned.chain(events.inter_spawned.read().map(|event| event.0));
+    for entity in spawned.chain(events.building_spawned.read().map(|event| event.0)) {
+        ids.assign(entity, grid.anchor_of(entity));
+    }
+}
+
+// Saved ids whose cells never spawned anything are dropped after a while, so nothing built there later
+// picks up an id that belonged to something else.
+pub fn drop_unclaimed_ids(mut ids: ResMut<PersistentIds>) {
+    if ids.pending.is_empty() {
+        return;
+    }
+
+    ids.attempts += 1;
+    if ids.attempts >= RESTORE_ATTEMPTS {
+        println!("Dropped {} saved ids that nothing spawned to claim", ids.pending.len());
+        ids.pending.clear();
+    }
+}
@@ -1,37 +1,260 @@
 use crate::{
-    grid::{grid_area::*, orientation::GAxis},
-    save::save_events::*,
+    generator::generator::new_city_seed,
+    grid::{grid::Grid, grid_area::*, grid_cell::GridCell},
+    input::keymap::{Action, Actions},
+    save::{journal::*, persistent_id::*, save_events::*, storage::*},
+    scenario::scenario::Scenario,
     schedule::UpdateStage,
     tools::{
         building_tool::RequestBuilding,
+        prop_tool::RequestProp,
         road_events::{RequestIntersection, RequestRoad},
+        transit_tool::TransitLines,
+        water_tool::RequestWater,
     },
-    types::{building::*, intersection::Intersection, road_segment::RoadSegment},
+    types::{
+        building::Building,
+        district::{DistrictRecord, Districts},
+        driver::driver_profile,
+        intersection::Intersection,
+        prop::PropKind,
+        road_segment::RoadSegment,
+        vehicle::{RequestVehicleRestore, Vehicle, VehicleKind, VehicleSpawnConfig},
+    },
+    ui::notify_events::Notify,
+};
+use bevy::{
+    app::AppExit,
+    ecs::system::SystemParam,
+    prelude::*,
+    tasks::{block_on, poll_once, AsyncComputeTaskPool, Task},
 };
-use bevy::prelude::*;
 use serde::{Deserialize, Serialize};
-use std::fs::File;
-use std::io::{BufReader, BufWriter, Write};
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead},
+};
 
 use super::fallback;
 
-const SAVEFILE: &str = "assets/saves/world.json";
+pub const SAVEFILE: &str = "world.json";
+const SAVELOG: &str = "world.log";
+const AUTOSAVE_SECONDS: f32 = 30.0;
+const COMPACT_AFTER_BATCHES: u32 = 10;
+const VEHICLE_RESTORE_ATTEMPTS: u32 = 10;
+const AUTOSAVE_SLOTS: u32 = 3;
+const DEFAULT_AUTOSAVE_MINUTES: f32 = 5.0;
+const LOAD_RECORDS_PER_FRAME: usize = 250;
 
 pub struct SavePlugin;
 
 impl Plugin for SavePlugin {
     fn build(&self, app: &mut App) {
         app.add_event::<SaveRequest>()
+            .add_event::<OnGameSaved>()
+            .insert_resource(SaveJournal::default())
+            .insert_resource(PendingVehicles::default())
+            .insert_resource(PendingBusLines::default())
+            .insert_resource(PendingTurnRestrictions::default())
+            .insert_resource(PendingSpeedLimits::default())
+            .insert_resource(PendingOneWays::default())
+            .insert_resource(PersistentIds::default())
+            .insert_resource(AutosaveTimer {
+                timer: Timer::from_seconds(AUTOSAVE_SECONDS, TimerMode::Repeating),
+            })
+            .insert_resource(AutosaveSettings::default())
+            .insert_resource(SaveStore::from_env())
+            .insert_resource(SaveProgress::default())
             .add_systems(PostStartup, load_from_disk)
-            .add_systems(Update, (save_on_key_press.in_set(UpdateStage::UserInput), save_to_disk));
+            .add_systems(
+                Update,
+                (
+                    save_on_key_press.in_set(UpdateStage::UserInput),
+                    stream_loaded_world.in_set(UpdateStage::HighLevelSideEffects),
+                    (
+                        restore_saved_vehicles,
+                        restore_bus_lines,
+                        restore_turn_restrictions,
+                        restore_speed_limits,
+                        restore_one_way_roads,
+                        drop_unclaimed_ids,
+                    )
+                        .in_set(UpdateStage::AfterSpawning)
+                        .run_if(|progress: Res<SaveProgress>| progress.loading.is_empty()),
+                    (
+                        assign_persistent_ids,
+                        record_save_deltas,
+                        record_prop_deltas,
+                        autosave_deltas,
+                        autosave_snapshots,
+                        finish_saves,
+                        save_to_disk,
+                    )
+                        .chain()
+                        .in_set(UpdateStage::Analyze),
+                ),
+            )
+            .add_systems(Last, finish_saves_on_exit);
     }
 }
 
+#[derive(Resource, Debug)]
+struct AutosaveTimer {
+    timer: Timer,
+}
+
+#[derive(Resource, Debug)]
+pub struct AutosaveSettings {
+    pub enabled: bool,
+    pub interval_minutes: f32,
+    elapsed: f32,
+    next_slot: u32,
+}
+
+impl Default for AutosaveSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_minutes: DEFAULT_AUTOSAVE_MINUTES,
+            elapsed: 0.0,
+            next_slot: 1,
+        }
+    }
+}
+
+// A save being serialized and written off the main thread, and the changes to the journal it covers.
+struct RunningSave {
+    request: SaveRequest,
+    slot: String,
+    covered_deltas: usize,
+    task: Task<io::Result<(WriteOutcome, bool)>>,
+}
+
+// Saves run one at a time, later requests waiting their turn, and a loaded world is spawned a batch
+// of records per frame rather than all at once.
+#[derive(Resource, Default)]
+pub struct SaveProgress {
+    running: Option<RunningSave>,
+    queued: VecDeque<SaveRequest>,
+    loading: VecDeque<SaveRecord>,
+}
+
+impl SaveProgress {
+    pub fn saving(&self) -> bool {
+        self.running.is_some() || !self.queued.is_empty()
+    }
+
+    pub fn records_to_load(&self) -> usize {
+        self.loading.len()
+    }
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PendingVehicles {
+    vehicles: Vec<VehicleRecord>,
+    attempts: u32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PendingBusLines {
+    lines: Vec<BusLineRecord>,
+    attempts: u32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PendingTurnRestrictions {
+    restrictions: Vec<TurnRestrictionRecord>,
+    attempts: u32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PendingSpeedLimits {
+    limits: Vec<SpeedLimitRecord>,
+    attempts: u32,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
-struct SaveObject {
-    buildings: Vec<GridArea>,
+struct SpeedLimitRecord {
+    road: GridCell,
+    limit: f32,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct PendingOneWays {
+    roads: Vec<OneWayRecord>,
+    attempts: u32,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct OneWayRecord {
+    road: GridCell,
+    exit: usize,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct TurnRestrictionRecord {
+    intersection: GridCell,
+    turns: Vec<(usize, usize)>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct BusLineRecord {
+    name: String,
+    stops: Vec<GridCell>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+struct VehicleRecord {
+    path: Vec<GridCell>,
+    path_index: usize,
+    translation: [f32; 3],
+    rotation: [f32; 4],
+    speed: f32,
+    speed_multiplier: f32,
+    lane: i32,
+    model: usize,
+    trip_time: f32,
+    #[serde(default)]
+    trip_distance: f32,
+    #[serde(default)]
+    kind: VehicleKind,
+    #[serde(default)]
+    trailer: bool,
+    #[serde(default)]
+    driver: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct SaveObject {
+    buildings: Vec<BuildingRecord>,
     intersections: Vec<GridArea>,
-    roads: Vec<(GridArea, GAxis)>,
+    roads: Vec<RoadRecord>,
+    #[serde(default)]
+    water: Vec<GridArea>,
+    #[serde(default)]
+    props: Vec<(GridArea, PropKind)>,
+    #[serde(default)]
+    vehicles: Vec<VehicleRecord>,
+    #[serde(default)]
+    bus_lines: Vec<BusLineRecord>,
+    #[serde(default)]
+    turn_restrictions: Vec<TurnRestrictionRecord>,
+    #[serde(default)]
+    speed_limits: Vec<SpeedLimitRecord>,
+    #[serde(default)]
+    one_way_roads: Vec<OneWayRecord>,
+    #[serde(default)]
+    vehicle_spawn: Option<VehicleSpawnConfig>,
+    #[serde(default)]
+    persistent_ids: PersistentIdRecord,
+    #[serde(default)]
+    districts: DistrictRecord,
+}
+
+impl Default for SaveObject {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl SaveObject {
@@ -40,85 +263,707 @@ impl SaveObject {
             buildings: Vec::new(),
             intersections: Vec::new(),
             roads: Vec::new(),
+            water: Vec::new(),
+            props: Vec::new(),
+            vehicles: Vec::new(),
+            bus_lines: Vec::new(),
+            turn_restrictions: Vec::new(),
+            speed_limits: Vec::new(),
+            one_way_roads: Vec::new(),
+            vehicle_spawn: None,
+            persistent_ids: PersistentIdRecord::default(),
+            districts: DistrictRecord::default(),
+        }
+    }
+
+    pub fn from_json(data: &[u8]) -> serde_json::Result<Self> {
+        serde_json::from_slice(data)
+    }
+
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        serde_json::to_vec(self)
+    }
+
+    // Scenario worlds are written by hand, so unlike saves they are rejected when they hold a field the
+    // save format does not have, instead of that part of the world silently going missing.
+    pub fn from_scenario_world(world: &serde_json::Value) -> Result<Self, String> {
+        let loaded = serde_json::from_value::<Self>(world.clone()).map_err(|error| error.to_string())?;
+        let known = serde_json::to_value(&loaded).map_err(|error| error.to_string())?;
+
+        if let (Some(fields), Some(known)) = (world.as_object(), known.as_object()) {
+            if let Some(field) = fields.keys().find(|field| !known.contains_key(*field)) {
+                return Err(format!("unknown field `{}`", field));
+            }
+        }
+
+        Ok(loaded)
+    }
+
+    pub fn records(&self) -> Vec<SaveRecord> {
+        let buildings = self.buildings.iter().map(|&building| SaveRecord::Building(building));
+        let intersections = self.intersections.iter().map(|&area| SaveRecord::Intersection(area));
+        let roads = self.roads.iter().map(|&road| SaveRecord::Road(road));
+        let water = self.water.iter().map(|&area| SaveRecord::Water(area));
+        let props = self.props.iter().map(|&(area, kind)| SaveRecord::Prop(area, kind));
+        buildings.chain(intersections).chain(roads).chain(water).chain(props).collect()
+    }
+
+    // The records in the order they are spawned on load. Water goes first so nothing is built where
+    // it will be.
+    pub fn spawn_order(&self) -> VecDeque<SaveRecord> {
+        let mut records: VecDeque<SaveRecord> = self.records().into();
+        records.make_contiguous().sort_by_key(|record| !matches!(record, SaveRecord::Water(_)));
+        records
+    }
+
+    pub fn insert(&mut self, record: &SaveRecord) {
+        match *record {
+            SaveRecord::Building(building) => self.buildings.push(building),
+            SaveRecord::Intersection(area) => self.intersections.push(area),
+            SaveRecord::Road(road) => self.roads.push(road),
+            SaveRecord::Water(area) => self.water.push(area),
+            SaveRecord::Prop(area, kind) => self.props.push((area, kind)),
+        }
+    }
+
+    fn remove(&mut self, record: &SaveRecord) {
+        match *record {
+            SaveRecord::Building(building) => remove_first(&mut self.buildings, &building),
+            SaveRecord::Intersection(area) => remove_first(&mut self.intersections, &area),
+            SaveRecord::Road(road) => remove_first(&mut self.roads, &road),
+            SaveRecord::Water(area) => remove_first(&mut self.water, &area),
+            SaveRecord::Prop(area, kind) => remove_first(&mut self.props, &(area, kind)),
+        }
+    }
+
+    pub fn apply(&mut self, delta: &SaveDelta) {
+        match delta {
+            SaveDelta::Added(record) => self.insert(record),
+            SaveDelta::Removed(record) => self.remove(record),
         }
     }
 }
 
+fn remove_first<T: PartialEq>(items: &mut Vec<T>, item: &T) {
+    if let Some(index) = items.iter().position(|other| other == item) {
+        items.swap_remove(index);
+    }
+}
+
+fn read_save_log(storage: &dyn SaveStorage) -> Vec<SaveDelta> {
+    let mut deltas = Vec::new();
+
+    if let Ok(data) = storage.read(SAVELOG) {
+        for line in data.lines().map_while(Result::ok) {
+            if let Ok(batch) = serde_json::from_str::<Vec<SaveDelta>>(&line) {
+                deltas.extend(batch);
+            }
+        }
+    }
+
+    deltas
+}
+
+fn read_save(storage: &dyn SaveStorage, notify: &mut EventWriter<Notify>) -> Option<(SaveObject, Vec<SaveDelta>)> {
+    let data = storage.read(SAVEFILE).ok()?;
+    let loaded = match SaveObject::from_json(&data) {
+        Ok(loaded) => loaded,
+        Err(error) => {
+            println!("Failed to load the game from {:?}: {}", storage.location(SAVEFILE), error);
+            notify.send(Notify::error(format!(
+                "Failed to load the game from {}",
+                storage.location(SAVEFILE)
+            )));
+            return None;
+        }
+    };
+    println!("Loaded the game from {:?}", storage.location(SAVEFILE));
+    Some((loaded, read_save_log(storage)))
+}
+
+// Reading the save is quick next to spawning what is in it, so it happens here before the first frame
+// and the records are queued for `stream_loaded_world`.
 pub fn load_from_disk(
-    mut building_event: EventWriter<RequestBuilding>,
-    mut inter_event: EventWriter<RequestIntersection>,
-    mut segment_event: EventWriter<RequestRoad>,
+    mut world: WorldRestore,
+    store: Res<SaveStore>,
+    scenario: Res<Scenario>,
+    mut notify: EventWriter<Notify>,
 ) {
-    if let Ok(file) = File::open(SAVEFILE) {
-        let reader = BufReader::new(file);
-        if let Ok(save_data) = serde_json::from_reader::<std::io::BufReader<File>, SaveObject>(reader) {
-            for area in save_data.buildings {
-                building_event.send(RequestBuilding::new(area));
-            }
+    // A new city is generated on empty land instead of loading a save.
+    if new_city_seed().is_some() {
+        return;
+    }
 
-            for area in save_data.intersections {
-                inter_event.send(RequestIntersection::new(area));
-            }
+    // A challenge that has not been saved yet starts from the scenario's own world.
+    let save_data = match scenario.challenge {
+        true => read_save(store.0.as_ref(), &mut notify).or_else(|| {
+            let loaded = match SaveObject::from_scenario_world(scenario.world.as_ref()?) {
+                Ok(loaded) => loaded,
+                Err(error) => {
+                    println!("Failed to load the world of scenario {:?}: {}", scenario.name, error);
+                    notify.send(Notify::error(format!(
+                        "Failed to load the world of scenario {}",
+                        scenario.name
+                    )));
+                    return None;
+                }
+            };
+            println!("Loaded the game from scenario {:?}", scenario.name);
+            Some((loaded, Vec::new()))
+        }),
+        false => read_save(store.0.as_ref(), &mut notify)
+            .or_else(|| read_save(&FolderStorage::new(LEGACY_SAVE_DIR), &mut notify))
+            .or_else(|| {
+                let loaded = SaveObject::from_json(fallback::FALLBACK_SAVE_DATA.as_bytes()).ok()?;
+                println!("Loaded the game from fallback");
+                Some((loaded, Vec::new()))
+            }),
+    };
 
-            for (area, orient) in save_data.roads {
-                segment_event.send(RequestRoad::new(area, orient));
-            }
+    let Some((save_data, deltas)) = save_data else {
+        return;
+    };
+
+    world.restore(save_data, &deltas);
+}
+
+// Everything a loaded save is handed out to before the first frame.
+#[derive(SystemParam)]
+pub struct WorldRestore<'w> {
+    progress: ResMut<'w, SaveProgress>,
+    journal: ResMut<'w, SaveJournal>,
+    pending: ResMut<'w, PendingVehicles>,
+    pending_lines: ResMut<'w, PendingBusLines>,
+    pending_restrictions: ResMut<'w, PendingTurnRestrictions>,
+    pending_limits: ResMut<'w, PendingSpeedLimits>,
+    pending_one_ways: ResMut<'w, PendingOneWays>,
+    spawn_config: ResMut<'w, VehicleSpawnConfig>,
+    persistent_ids: ResMut<'w, PersistentIds>,
+    districts: ResMut<'w, Districts>,
+}
 
-            println!("Loaded the game from {:?}", SAVEFILE);
+impl WorldRestore<'_> {
+    fn restore(&mut self, mut save_data: SaveObject, deltas: &[SaveDelta]) {
+        for delta in deltas {
+            save_data.apply(delta);
         }
-    } else {
-        if let Ok(save_data) = serde_json::from_str::<SaveObject>(fallback::FALLBACK_SAVE_DATA) {
-            for area in save_data.buildings {
-                building_event.send(RequestBuilding::new(area));
-            }
 
-            for area in save_data.intersections {
-                inter_event.send(RequestIntersection::new(area));
-            }
+        if !deltas.is_empty() {
+            println!("Replayed {} autosave changes from {:?}", deltas.len(), SAVELOG);
+        }
+
+        self.journal.loading = save_data.records();
+        self.progress.loading = save_data.spawn_order();
+        self.pending.vehicles = std::mem::take(&mut save_data.vehicles);
+        self.pending_lines.lines = std::mem::take(&mut save_data.bus_lines);
+        self.pending_restrictions.restrictions = std::mem::take(&mut save_data.turn_restrictions);
+        self.pending_limits.limits = std::mem::take(&mut save_data.speed_limits);
+        self.pending_one_ways.roads = std::mem::take(&mut save_data.one_way_roads);
+        self.persistent_ids.restore(std::mem::take(&mut save_data.persistent_ids));
+        self.districts.restore(std::mem::take(&mut save_data.districts));
 
-            for (area, orient) in save_data.roads {
-                segment_event.send(RequestRoad::new(area, orient));
+        // Saves from before spawning was configurable keep the defaults.
+        if let Some(config) = save_data.vehicle_spawn {
+            *self.spawn_config = config;
+        }
+    }
+}
+
+// Turns saved records back into the same spawn requests the tools send, so a loaded world goes
+// through the regular spawn systems and needs nothing from the renderer.
+#[derive(SystemParam)]
+pub struct RecordSpawner<'w> {
+    building_event: EventWriter<'w, RequestBuilding>,
+    inter_event: EventWriter<'w, RequestIntersection>,
+    segment_event: EventWriter<'w, RequestRoad>,
+    water_event: EventWriter<'w, RequestWater>,
+    prop_event: EventWriter<'w, RequestProp>,
+}
+
+impl RecordSpawner<'_> {
+    pub fn spawn(&mut self, record: SaveRecord) {
+        match record {
+            SaveRecord::Water(area) => {
+                self.water_event.send(RequestWater::new(area));
+            }
+            SaveRecord::Building(BuildingRecord { area, kind, seed }) => {
+                let seed = seed.unwrap_or_else(|| Building::legacy_seed(area));
+                self.building_event.send(RequestBuilding::of_kind(area, kind).with_seed(seed));
+            }
+            SaveRecord::Intersection(area) => {
+                self.inter_event.send(RequestIntersection::new(area));
+            }
+            SaveRecord::Road(RoadRecord {
+                area,
+                orientation,
+                shape,
+                surface,
+            }) => {
+                self.segment_event.send(RequestRoad::shaped(area, orientation, shape).with_surface(surface));
+            }
+            SaveRecord::Prop(area, kind) => {
+                self.prop_event.send(RequestProp::new(area, kind));
             }
+        }
+    }
+}
 
-            println!("Loaded the game from fallback");
+fn stream_loaded_world(mut progress: ResMut<SaveProgress>, mut spawner: RecordSpawner) {
+    let batch = progress.loading.len().min(LOAD_RECORDS_PER_FRAME);
+    for record in progress.loading.drain(..batch) {
+        spawner.spawn(record);
+    }
+}
+
+pub fn save_on_key_press(actions: Actions, mut event: EventWriter<SaveRequest>) {
+    if actions.just_pressed(Action::SaveGame) {
+        event.send(SaveRequest::World);
+    }
+}
+
+fn autosave_deltas(
+    mut journal: ResMut<SaveJournal>,
+    mut timer: ResMut<AutosaveTimer>,
+    time: Res<Time>,
+    mut event: EventWriter<SaveRequest>,
+    store: Res<SaveStore>,
+    progress: Res<SaveProgress>,
+) {
+    timer.timer.tick(time.delta());
+
+    // Appending while a save is being written could lose changes when it clears the log.
+    if !timer.timer.just_finished() || progress.saving() || !progress.loading.is_empty() {
+        return;
+    }
+
+    journal.loading.clear();
+
+    if journal.pending.is_empty() {
+        return;
+    }
+
+    if journal.appended_batches >= COMPACT_AFTER_BATCHES {
+        event.send(SaveRequest::World);
+        return;
+    }
+
+    if let Ok(mut batch) = serde_json::to_vec(&journal.pending) {
+        batch.push(b'\n');
+
+        if store.0.append(SAVELOG, &batch).is_ok() {
+            println!(
+                "Autosaved {} changes to {:?}",
+                journal.pending.len(),
+                store.0.location(SAVELOG)
+            );
+            journal.pending.clear();
+            journal.appended_batches += 1;
         }
     }
 }
 
-pub fn save_on_key_press(keyboard: Res<ButtonInput<KeyCode>>, mut event: EventWriter<SaveRequest>) {
-    if keyboard.just_pressed(KeyCode::F5) {
-        event.send(SaveRequest);
+fn autosave_snapshots(mut settings: ResMut<AutosaveSettings>, time: Res<Time>, mut event: EventWriter<SaveRequest>) {
+    if !settings.enabled {
+        settings.elapsed = 0.0;
+        return;
+    }
+
+    settings.elapsed += time.delta_seconds();
+
+    if settings.elapsed >= settings.interval_minutes * 60.0 {
+        settings.elapsed = 0.0;
+        event.send(SaveRequest::Autosave);
     }
 }
 
-pub fn save_to_disk(
-    building_query: Query<&Building>,
-    segment_query: Query<&RoadSegment>,
-    inter_query: Query<&Intersection>,
-    mut event: EventReader<SaveRequest>,
+fn restore_saved_vehicles(
+    mut pending: ResMut<PendingVehicles>,
+    grid_query: Query<&Grid>,
+    mut restore: EventWriter<RequestVehicleRestore>,
+) {
+    if pending.vehicles.is_empty() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    pending.attempts += 1;
+
+    pending.vehicles.retain(|record| {
+        let path: Option<Vec<Entity>> = record.path.iter().map(|&cell| grid.entity_at(cell).ok().flatten()).collect();
+
+        if let Some(path) = path {
+            restore.send(RequestVehicleRestore {
+                path,
+                path_index: record.path_index,
+                translation: Vec3::from_array(record.translation),
+                rotation: Quat::from_array(record.rotation),
+                speed: record.speed,
+                speed_multiplier: record.speed_multiplier,
+                lane: record.lane,
+                model: record.model,
+                kind: record.kind,
+                trip_time: record.trip_time,
+                trip_distance: record.trip_distance,
+                trailer: record.trailer,
+                driver: driver_profile(&record.driver),
+            });
+            false
+        } else {
+            true
+        }
+    });
+
+    if pending.attempts >= VEHICLE_RESTORE_ATTEMPTS && !pending.vehicles.is_empty() {
+        println!("Dropped {} saved vehicles with unresolvable paths", pending.vehicles.len());
+        pending.vehicles.clear();
+    }
+}
+
+fn restore_turn_restrictions(
+    mut pending: ResMut<PendingTurnRestrictions>,
+    grid_query: Query<&Grid>,
+    mut inter_query: Query<&mut Intersection>,
+) {
+    if pending.restrictions.is_empty() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    pending.attempts += 1;
+
+    pending.restrictions.retain(|record| {
+        let entity = grid.entity_at(record.intersection).ok().flatten();
+
+        if let Some(mut intersection) = entity.and_then(|entity| inter_query.get_mut(entity).ok()) {
+            intersection.restricted_turns.extend(record.turns.iter().copied());
+            false
+        } else {
+            true
+        }
+    });
+
+    if pending.attempts >= VEHICLE_RESTORE_ATTEMPTS && !pending.restrictions.is_empty() {
+        println!(
+            "Dropped turn restrictions for {} missing intersections",
+            pending.restrictions.len()
+        );
+        pending.restrictions.clear();
+    }
+}
+
+fn restore_speed_limits(
+    mut pending: ResMut<PendingSpeedLimits>,
+    grid_query: Query<&Grid>,
+    mut segment_query: Query<&mut RoadSegment>,
 ) {
-    for _ in event.read() {
+    if pending.limits.is_empty() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    pending.attempts += 1;
+
+    pending.limits.retain(|record| {
+        let entity = grid.entity_at(record.road).ok().flatten();
+
+        if let Some(mut segment) = entity.and_then(|entity| segment_query.get_mut(entity).ok()) {
+            segment.speed_limit_override = Some(record.limit);
+            false
+        } else {
+            true
+        }
+    });
+
+    if pending.attempts >= VEHICLE_RESTORE_ATTEMPTS && !pending.limits.is_empty() {
+        println!("Dropped speed limits for {} missing roads", pending.limits.len());
+        pending.limits.clear();
+    }
+}
+
+fn restore_one_way_roads(
+    mut pending: ResMut<PendingOneWays>,
+    grid_query: Query<&Grid>,
+    mut segment_query: Query<&mut RoadSegment>,
+) {
+    if pending.roads.is_empty() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    pending.attempts += 1;
+
+    pending.roads.retain(|record| {
+        let entity = grid.entity_at(record.road).ok().flatten();
+
+        if let Some(mut segment) = entity.and_then(|entity| segment_query.get_mut(entity).ok()) {
+            segment.one_way = Some(record.exit);
+            false
+        } else {
+            true
+        }
+    });
+
+    if pending.attempts >= VEHICLE_RESTORE_ATTEMPTS && !pending.roads.is_empty() {
+        println!("Dropped one-way directions for {} missing roads", pending.roads.len());
+        pending.roads.clear();
+    }
+}
+
+fn restore_bus_lines(mut pending: ResMut<PendingBusLines>, grid_query: Query<&Grid>, mut lines: ResMut<TransitLines>) {
+    if pending.lines.is_empty() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    pending.attempts += 1;
+
+    pending.lines.retain(|record| {
+        let stops: Option<Vec<Entity>> = record.stops.iter().map(|&cell| grid.entity_at(cell).ok().flatten()).collect();
+
+        if let Some(stops) = stops {
+            lines.add(record.name.clone(), stops);
+            false
+        } else {
+            true
+        }
+    });
+
+    if pending.attempts >= VEHICLE_RESTORE_ATTEMPTS && !pending.lines.is_empty() {
+        println!("Dropped {} saved bus lines with unresolvable stops", pending.lines.len());
+        pending.lines.clear();
+    }
+}
+
+// Read-only access to the world as it would be saved right now. Saves and bug reports both take their
+// copy through it.
+#[derive(SystemParam)]
+pub struct WorldSnapshot<'w, 's> {
+    journal: Res<'w, SaveJournal>,
+    vehicle_query: Query<'w, 's, (&'static Vehicle, &'static Transform)>,
+    grid_query: Query<'w, 's, &'static Grid>,
+    lines: Res<'w, TransitLines>,
+    inter_query: Query<'w, 's, (Entity, &'static Intersection)>,
+    segment_query: Query<'w, 's, (Entity, &'static RoadSegment)>,
+    spawn_config: Res<'w, VehicleSpawnConfig>,
+    persistent_ids: Res<'w, PersistentIds>,
+    districts: Res<'w, Districts>,
+}
+
+impl WorldSnapshot<'_, '_> {
+    pub fn to_json(&self) -> serde_json::Result<Vec<u8>> {
+        self.save_object().to_json()
+    }
+
+    fn save_object(&self) -> SaveObject {
+        let grid = self.grid_query.single();
         let mut save_data = SaveObject::new();
+        save_data.vehicle_spawn = Some(*self.spawn_config);
+        save_data.persistent_ids = self.persistent_ids.record(grid);
+        save_data.districts = self.districts.record();
 
-        for building in &building_query {
-            save_data.buildings.push(building.area());
+        for record in self.journal.records.values() {
+            save_data.insert(record);
         }
 
-        for inter in &inter_query {
-            save_data.intersections.push(inter.area());
+        for (vehicle, transform) in &self.vehicle_query {
+            let path: Option<Vec<GridCell>> = vehicle.path.iter().map(|&step| grid.anchor_of(step)).collect();
+
+            if let Some(path) = path {
+                save_data.vehicles.push(VehicleRecord {
+                    path,
+                    path_index: vehicle.path_index,
+                    translation: transform.translation.to_array(),
+                    rotation: transform.rotation.to_array(),
+                    speed: vehicle.speed,
+                    speed_multiplier: vehicle.speed_multiplier,
+                    lane: vehicle.lane,
+                    model: vehicle.model,
+                    trip_time: vehicle.trip_time,
+                    trip_distance: vehicle.trip_distance,
+                    kind: vehicle.kind,
+                    trailer: vehicle.trailer.is_some(),
+                    driver: vehicle.driver.name().to_string(),
+                });
+            }
         }
 
-        for segment in &segment_query {
-            save_data.roads.push((segment.area(), segment.orientation));
+        for (entity, intersection) in
+            self.inter_query.iter().filter(|(_, intersection)| !intersection.restricted_turns.is_empty())
+        {
+            if let Some(cell) = grid.anchor_of(entity) {
+                let mut turns: Vec<(usize, usize)> = intersection.restricted_turns.iter().copied().collect();
+                turns.sort();
+                save_data.turn_restrictions.push(TurnRestrictionRecord {
+                    intersection: cell,
+                    turns,
+                });
+            }
         }
 
-        if std::fs::create_dir_all("saves").is_ok() {
-            if let Ok(file) = File::create(SAVEFILE) {
-                let mut writer = BufWriter::new(file);
-                if serde_json::to_writer(&mut writer, &save_data).is_ok() && writer.flush().is_ok() {
-                    println!("Saved the game to {:?}", SAVEFILE);
-                }
+        for (entity, segment) in &self.segment_query {
+            if let (Some(limit), Some(cell)) = (segment.speed_limit_override, grid.anchor_of(entity)) {
+                save_data.speed_limits.push(SpeedLimitRecord { road: cell, limit });
             }
+            if let (Some(exit), Some(cell)) = (segment.one_way, grid.anchor_of(entity)) {
+                save_data.one_way_roads.push(OneWayRecord { road: cell, exit });
+            }
+        }
+
+        for line in &self.lines.lines {
+            let stops: Option<Vec<GridCell>> = line.stops.iter().map(|&stop| grid.anchor_of(stop)).collect();
+
+            if let Some(stops) = stops {
+                save_data.bus_lines.push(BusLineRecord {
+                    name: line.name.clone(),
+                    stops,
+                });
+            }
+        }
+
+        save_data
+    }
+}
+
+// Takes a snapshot of the world for the next queued save and hands serializing and writing it to a
+// background task, so large cities do not stall the frame.
+pub fn save_to_disk(
+    mut event: EventReader<SaveRequest>,
+    mut progress: ResMut<SaveProgress>,
+    world: WorldSnapshot,
+    settings: Res<AutosaveSettings>,
+    store: Res<SaveStore>,
+) {
+    for &request in event.read() {
+        if !progress.queued.contains(&request) {
+            progress.queued.push_back(request);
         }
     }
+
+    if progress.running.is_some() {
+        return;
+    }
+
+    let Some(request) = progress.queued.pop_front() else {
+        return;
+    };
+
+    let save_data = world.save_object();
+
+    let slot = match request {
+        SaveRequest::Autosave => format!("autosave_{}.json", settings.next_slot),
+        SaveRequest::World => SAVEFILE.to_string(),
+    };
+
+    // A full save makes the autosave log redundant, so it is cleared once the save is written.
+    let storage = store.0.clone();
+    let task_slot = slot.clone();
+    let task = AsyncComputeTaskPool::get().spawn(async move {
+        let data = save_data.to_json()?;
+        let outcome = storage.write(&task_slot, &data)?;
+        let log_cleared = request == SaveRequest::Autosave || storage.write(SAVELOG, &[]).is_ok();
+        Ok((outcome, log_cleared))
+    });
+
+    progress.running = Some(RunningSave {
+        request,
+        slot,
+        covered_deltas: world.journal.pending.len(),
+        task,
+    });
+}
+
+fn finish_saves(
+    mut progress: ResMut<SaveProgress>,
+    mut journal: ResMut<SaveJournal>,
+    mut settings: ResMut<AutosaveSettings>,
+    mut saved: EventWriter<OnGameSaved>,
+    mut notify: EventWriter<Notify>,
+    store: Res<SaveStore>,
+) {
+    let Some(running) = progress.running.as_mut() else {
+        return;
+    };
+
+    let Some(result) = block_on(poll_once(&mut running.task)) else {
+        return;
+    };
+
+    let running = progress.running.take().unwrap();
+    report_save(running, result, &mut journal, &mut settings, &mut saved, &mut notify, &store);
+}
+
+// Quitting would drop a save still being written, so it is waited for.
+fn finish_saves_on_exit(
+    mut exit: EventReader<AppExit>,
+    mut progress: ResMut<SaveProgress>,
+    mut journal: ResMut<SaveJournal>,
+    mut settings: ResMut<AutosaveSettings>,
+    mut saved: EventWriter<OnGameSaved>,
+    mut notify: EventWriter<Notify>,
+    store: Res<SaveStore>,
+) {
+    if exit.read().count() == 0 {
+        return;
+    }
+
+    if let Some(mut running) = progress.running.take() {
+        let result = block_on(&mut running.task);
+        report_save(running, result, &mut journal, &mut settings, &mut saved, &mut notify, &store);
+    }
+}
+
+fn report_save(
+    running: RunningSave,
+    result: io::Result<(WriteOutcome, bool)>,
+    journal: &mut SaveJournal,
+    settings: &mut AutosaveSettings,
+    saved: &mut EventWriter<OnGameSaved>,
+    notify: &mut EventWriter<Notify>,
+    store: &SaveStore,
+) {
+    let RunningSave {
+        request,
+        slot,
+        covered_deltas,
+        ..
+    } = running;
+
+    let (outcome, log_cleared) = match result {
+        Ok(result) => result,
+        Err(error) => {
+            println!("Failed to save the game to {:?}: {}", store.0.location(&slot), error);
+            notify.send(Notify::error(format!(
+                "Failed to save the game to {}",
+                store.0.location(&slot)
+            )));
+            return;
+        }
+    };
+
+    let conflict = match outcome {
+        WriteOutcome::ConflictPreserved(copy) => {
+            println!("{:?} was changed elsewhere, kept that copy as {:?}", slot, copy);
+            Some(copy.display().to_string())
+        }
+        WriteOutcome::Written => None,
+    };
+
+    if request == SaveRequest::Autosave {
+        println!("Autosaved the game to {:?}", store.0.location(&slot));
+        settings.next_slot = settings.next_slot % AUTOSAVE_SLOTS + 1;
+    } else if log_cleared {
+        // Changes made while the save was being written are not in it, so they stay pending.
+        println!("Saved the game to {:?}", store.0.location(&slot));
+        let covered = covered_deltas.min(journal.pending.len());
+        journal.pending.drain(..covered);
+        journal.appended_batches = 0;
+    } else {
+        return;
+    }
+
+    saved.send(OnGameSaved {
+        path: store.0.location(&slot),
+        autosave: request == SaveRequest::Autosave,
+        conflict,
+    });
 }
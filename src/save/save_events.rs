@@ -1,4 +1,14 @@
 use bevy::prelude::*;
 
+#[derive(Event, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SaveRequest {
+    World,
+    Autosave,
+}
+
 #[derive(Event, Debug)]
-pub struct SaveRequest;
+pub struct OnGameSaved {
+    pub path: String,
+    pub autosave: bool,
+    pub conflict: Option<String>,
+}
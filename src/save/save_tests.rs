@@ -0,0 +1,307 @@
+use crate::{
+    grid::{grid::Grid, grid_area::GridArea, grid_cell::GridCell, orientation::GAxis},
+    headless::headless::HeadlessPlugin,
+    save::{journal::*, persistent_id::PersistentIds, save::*, save_events::SaveRequest, storage::*},
+    scenario::scenario::Scenario,
+    tools::{
+        building_tool::RequestBuilding,
+        road_events::{RequestIntersection, RequestRoad},
+    },
+    types::{
+        building::{Building, BuildingKind},
+        intersection::Intersection,
+        road_segment::{RoadSegment, RoadShape, RoadSurface},
+    },
+};
+use bevy::{prelude::*, utils::HashMap};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+const MAX_FRAMES: usize = 600;
+
+// Keeps slots in memory so tests never touch the player's saves.
+#[derive(Default)]
+pub(crate) struct MemoryStorage {
+    slots: Mutex<HashMap<String, Vec<u8>>>,
+}
+
+impl SaveStorage for MemoryStorage {
+    fn location(&self, slot: &str) -> String {
+        format!("memory:{}", slot)
+    }
+
+    fn read(&self, slot: &str) -> io::Result<Vec<u8>> {
+        let slots = self.slots.lock().unwrap();
+        slots.get(slot).cloned().ok_or_else(|| io::Error::from(io::ErrorKind::NotFound))
+    }
+
+    fn write(&self, slot: &str, data: &[u8]) -> io::Result<WriteOutcome> {
+        self.slots.lock().unwrap().insert(slot.to_string(), data.to_vec());
+        Ok(WriteOutcome::Written)
+    }
+
+    fn append(&self, slot: &str, data: &[u8]) -> io::Result<()> {
+        self.slots.lock().unwrap().entry(slot.to_string()).or_default().extend_from_slice(data);
+        Ok(())
+    }
+
+    fn modified(&self, _slot: &str) -> Option<SystemTime> {
+        None
+    }
+}
+
+pub(crate) fn area(min: (i32, i32), max: (i32, i32)) -> GridArea {
+    GridArea::new(GridCell::new(min.0, min.1), GridCell::new(max.0, max.1))
+}
+
+pub(crate) fn headless_app(store: Arc<MemoryStorage>) -> App {
+    let mut app = App::new();
+    app.add_plugins(HeadlessPlugin { ticks: u64::MAX });
+    app.add_plugins(crate::SimulationPlugin { windowed: false });
+    app.insert_resource(SaveStore(store));
+    app
+}
+
+pub(crate) fn update_until(app: &mut App, done: impl Fn(&mut App) -> bool) {
+    for _ in 0..MAX_FRAMES {
+        app.update();
+        if done(app) {
+            return;
+        }
+    }
+    panic!("gave up after {} frames", MAX_FRAMES);
+}
+
+// What sits on every occupied cell, and for every road, intersection and building the anchor cells of
+// what it is connected to. Both are keyed by cell so worlds from different apps can be compared.
+#[derive(Debug, PartialEq)]
+pub(crate) struct Layout {
+    pub(crate) cells: BTreeMap<(i32, i32), &'static str>,
+    pub(crate) links: BTreeMap<(i32, i32), BTreeSet<(i32, i32)>>,
+}
+
+pub(crate) fn layout(app: &mut App, bounds: GridArea) -> Layout {
+    let world = app.world_mut();
+    let grid = world.query::<&Grid>().single(world);
+    let key = |entity: Entity| grid.anchor_of(entity).map(|cell| (cell.pos.x, cell.pos.y));
+
+    let mut cells = BTreeMap::new();
+    let mut links = BTreeMap::new();
+    for cell in bounds.iter() {
+        let Some(entity) = grid.entity_at(cell).unwrap() else {
+            continue;
+        };
+
+        let (kind, neighbors): (&str, Vec<Entity>) = if let Some(building) = world.get::<Building>(entity) {
+            ("building", building.roads.iter().copied().collect())
+        } else if let Some(intersection) = world.get::<Intersection>(entity) {
+            ("intersection", intersection.roads.iter().flatten().copied().collect())
+        } else if let Some(segment) = world.get::<RoadSegment>(entity) {
+            ("road", segment.ends.iter().flatten().chain(&segment.dests).copied().collect())
+        } else {
+            ("other", Vec::new())
+        };
+
+        cells.insert((cell.pos.x, cell.pos.y), kind);
+        if let Some(anchor) = key(entity) {
+            links.insert(anchor, neighbors.into_iter().filter_map(key).collect());
+        }
+    }
+
+    Layout { cells, links }
+}
+
+// The persistent id of everything in `bounds`, keyed by its anchor cell.
+fn persistent_ids(app: &mut App, bounds: GridArea) -> BTreeMap<(i32, i32), u64> {
+    let world = app.world_mut();
+    let grid = world.query::<&Grid>().single(world);
+    let ids = world.resource::<PersistentIds>();
+
+    bounds
+        .iter()
+        .filter_map(|cell| grid.entity_at(cell).unwrap())
+        .filter_map(|entity| Some((grid.anchor_of(entity)?, ids.id(entity)?.0)))
+        .map(|(cell, id)| ((cell.pos.x, cell.pos.y), id))
+        .collect()
+}
+
+#[test]
+fn save_object_round_trips_through_json() {
+    let mut save_data = SaveObject::new();
+    save_data.insert(&SaveRecord::Intersection(area((0, 0), (1, 1))));
+    save_data.insert(&SaveRecord::Road(RoadRecord {
+        area: area((2, 0), (9, 1)),
+        orientation: GAxis::X,
+        shape: RoadShape::Diagonal { shift: 1, width: 2 },
+        surface: RoadSurface::Cobblestone,
+    }));
+    save_data.insert(&SaveRecord::Building(
+        BuildingRecord::new(area((3, 2), (4, 3)), BuildingKind::Shop).with_seed(7),
+    ));
+
+    let loaded = SaveObject::from_json(&save_data.to_json().unwrap()).unwrap();
+    assert_eq!(loaded.records(), save_data.records());
+}
+
+// Saves from before records had fields list buildings as bare areas and roads as area and
+// orientation pairs.
+#[test]
+fn legacy_records_load_with_defaults() {
+    let loaded = SaveObject::from_json(crate::save::fallback::FALLBACK_SAVE_DATA.as_bytes()).unwrap();
+    let roads: Vec<SaveRecord> =
+        loaded.records().into_iter().filter(|record| matches!(record, SaveRecord::Road(_))).collect();
+    let buildings: Vec<SaveRecord> =
+        loaded.records().into_iter().filter(|record| matches!(record, SaveRecord::Building(_))).collect();
+
+    assert!(!roads.is_empty());
+    assert!(roads.iter().all(|record| matches!(
+        record,
+        SaveRecord::Road(road) if road.shape == RoadShape::Straight && road.surface == RoadSurface::Asphalt
+    )));
+    assert!(!buildings.is_empty());
+    assert!(buildings
+        .iter()
+        .all(|record| matches!(record, SaveRecord::Building(building) if building.kind == BuildingKind::House && building.seed.is_none())));
+}
+
+#[test]
+fn deltas_replay_onto_the_snapshot() {
+    let road = SaveRecord::Road(RoadRecord::new(area((2, 0), (9, 1)), GAxis::X));
+    let house = SaveRecord::Building(BuildingRecord::new(area((3, 2), (4, 3)), BuildingKind::House));
+
+    let mut save_data = SaveObject::new();
+    save_data.insert(&road);
+    save_data.apply(&SaveDelta::Added(house.clone()));
+    save_data.apply(&SaveDelta::Removed(road));
+
+    assert_eq!(save_data.records(), vec![house]);
+}
+
+#[test]
+fn water_spawns_before_anything_else() {
+    let mut save_data = SaveObject::new();
+    save_data.insert(&SaveRecord::Building(BuildingRecord::new(
+        area((3, 2), (4, 3)),
+        BuildingKind::Office,
+    )));
+    save_data.insert(&SaveRecord::Water(area((20, 20), (25, 25))));
+
+    assert!(matches!(save_data.spawn_order().front(), Some(SaveRecord::Water(_))));
+}
+
+// Appends rewrite both copies of the log, so they never drift apart.
+#[test]
+fn sync_storage_keeps_both_copies_equal() {
+    let root = std::env::temp_dir().join(format!("overcast-sync-test-{}", std::process::id()));
+    let (local, sync) = (root.join("local"), root.join("sync"));
+    let storage = SyncFolderStorage::new(&local, &sync);
+
+    storage.write("world.json", b"{}").unwrap();
+    storage.append("world.log", b"first\n").unwrap();
+    storage.append("world.log", b"second\n").unwrap();
+
+    for slot in ["world.json", "world.log"] {
+        assert_eq!(
+            std::fs::read(local.join(slot)).unwrap(),
+            std::fs::read(sync.join(slot)).unwrap()
+        );
+    }
+    assert_eq!(storage.read("world.log").unwrap(), b"first\nsecond\n");
+    assert!(!local.join("world.tmp").exists() && !sync.join("world.tmp").exists());
+
+    std::fs::remove_dir_all(root).unwrap();
+}
+
+// Builds a small town in one headless app, saves it, loads the save into a second app and checks
+// both end up with the same cells taken and the same connections between them.
+#[test]
+fn saved_world_reloads_with_the_same_layout() {
+    let store = Arc::new(MemoryStorage::default());
+    store.write(SAVEFILE, &SaveObject::new().to_json().unwrap()).unwrap();
+    let bounds = area((-4, -4), (16, 16));
+
+    let mut original = headless_app(store.clone());
+    original.update();
+    original.world_mut().send_event(RequestIntersection::new(area((0, 0), (1, 1))));
+    original.world_mut().send_event(RequestIntersection::new(area((10, 0), (11, 1))));
+    original.world_mut().send_event(RequestRoad::new(area((2, 0), (9, 1)), GAxis::X));
+    original.world_mut().send_event(RequestRoad::new(area((0, 2), (1, 9)), GAxis::Z));
+    original.update();
+    original.world_mut().send_event(RequestBuilding::new(area((3, 2), (4, 3))).with_seed(1));
+    original.world_mut().send_event(RequestBuilding::of_kind(area((2, 4), (3, 5)), BuildingKind::Shop).with_seed(2));
+    for _ in 0..5 {
+        original.update();
+    }
+
+    original.world_mut().send_event(SaveRequest::World);
+    original.update();
+    update_until(&mut original, |app| !app.world().resource::<SaveProgress>().saving());
+    let expected = layout(&mut original, bounds);
+    let expected_ids = persistent_ids(&mut original, bounds);
+
+    assert_eq!(expected.cells.values().filter(|&&kind| kind == "intersection").count(), 8);
+    assert_eq!(expected.cells.values().filter(|&&kind| kind == "building").count(), 8);
+    assert!(expected.links.values().all(|links| !links.is_empty()));
+    assert_eq!(expected_ids.len(), expected.links.len());
+
+    let mut reloaded = headless_app(store);
+    update_until(&mut reloaded, |app| {
+        app.world().resource::<SaveProgress>().records_to_load() == 0
+    });
+    for _ in 0..5 {
+        reloaded.update();
+    }
+
+    assert_eq!(layout(&mut reloaded, bounds), expected);
+    assert_eq!(persistent_ids(&mut reloaded, bounds), expected_ids);
+}
+
+// Every world shipped with a scenario parses in the current save format, with one record per entry.
+#[test]
+fn scenario_worlds_load_every_record() {
+    let mut worlds = 0;
+
+    for entry in std::fs::read_dir("assets/scenarios").unwrap() {
+        let path = entry.unwrap().path();
+        let scenario: Scenario = serde_json::from_slice(&std::fs::read(&path).unwrap()).unwrap();
+        let Some(world) = scenario.world else {
+            continue;
+        };
+
+        let loaded = SaveObject::from_scenario_world(&world).unwrap_or_else(|error| panic!("{:?}: {}", path, error));
+        let count = |field: &str| world.get(field).and_then(|records| records.as_array()).map_or(0, Vec::len);
+        let records = loaded.records();
+
+        assert_eq!(
+            records.iter().filter(|record| matches!(record, SaveRecord::Building(_))).count(),
+            count("buildings"),
+            "{:?}",
+            path
+        );
+        assert_eq!(
+            records.len(),
+            ["buildings", "intersections", "roads", "water", "props"].into_iter().map(count).sum::<usize>(),
+            "{:?}",
+            path
+        );
+        worlds += 1;
+    }
+
+    assert!(worlds > 0);
+}
+
+#[test]
+fn scenario_worlds_reject_unknown_fields() {
+    let world = serde_json::json!({
+        "buildings": [],
+        "typed_buildings": [],
+        "intersections": [],
+        "roads": [],
+    });
+
+    assert!(SaveObject::from_scenario_world(&world).is_err());
+}
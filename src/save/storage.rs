@@ -0,0 +1,273 @@
+use crate::{clock::unix_seconds, scenario::scenario::scenario_slot_prefix};
+use bevy::{prelude::*, utils::HashMap};
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+pub const LEGACY_SAVE_DIR: &str = "assets/saves";
+const SAVE_DIR_VAR: &str = "OVERCAST_SAVE_DIR";
+const SYNC_DIR_VAR: &str = "OVERCAST_SYNC_DIR";
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteOutcome {
+    Written,
+    ConflictPreserved(PathBuf),
+}
+
+pub trait SaveStorage: Send + Sync {
+    fn location(&self, slot: &str) -> String;
+    fn read(&self, slot: &str) -> io::Result<Vec<u8>>;
+    fn write(&self, slot: &str, data: &[u8]) -> io::Result<WriteOutcome>;
+    fn append(&self, slot: &str, data: &[u8]) -> io::Result<()>;
+    fn modified(&self, slot: &str) -> Option<SystemTime>;
+}
+
+#[derive(Resource)]
+pub struct SaveStore(pub Arc<dyn SaveStorage>);
+
+impl SaveStore {
+    pub fn from_env() -> Self {
+        let local = std::env::var(SAVE_DIR_VAR).map(PathBuf::from).unwrap_or_else(|_| default_save_dir());
+
+        let storage: Box<dyn SaveStorage> = match std::env::var(SYNC_DIR_VAR) {
+            Ok(sync) => {
+                println!("Syncing saves between {:?} and {:?}", local, sync);
+                Box::new(SyncFolderStorage::new(local, sync))
+            }
+            Err(_) => Box::new(FolderStorage::new(local)),
+        };
+
+        match scenario_slot_prefix() {
+            Some(prefix) => Self(Arc::new(PrefixedStorage::new(prefix, storage))),
+            None => Self(storage.into()),
+        }
+    }
+}
+
+fn default_save_dir() -> PathBuf {
+    let data_dir = std::env::var_os("XDG_DATA_HOME")
+        .map(PathBuf::from)
+        .or_else(|| std::env::var_os("APPDATA").map(PathBuf::from))
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")));
+
+    match data_dir {
+        Some(dir) => dir.join("overcast").join("saves"),
+        None => PathBuf::from(LEGACY_SAVE_DIR),
+    }
+}
+
+pub struct FolderStorage {
+    root: PathBuf,
+}
+
+impl FolderStorage {
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        Self { root: root.into() }
+    }
+
+    fn path(&self, slot: &str) -> PathBuf {
+        self.root.join(slot)
+    }
+
+    // Writes `data` beside the slot, to be moved over it by `commit` once everything is written.
+    fn stage(&self, slot: &str, data: &[u8]) -> io::Result<PathBuf> {
+        fs::create_dir_all(&self.root)?;
+
+        let staging = self.path(slot).with_extension("tmp");
+        fs::write(&staging, data)?;
+        Ok(staging)
+    }
+
+    fn commit(&self, staging: PathBuf, slot: &str) -> io::Result<()> {
+        fs::rename(staging, self.path(slot))
+    }
+}
+
+impl SaveStorage for FolderStorage {
+    fn location(&self, slot: &str) -> String {
+        self.path(slot).display().to_string()
+    }
+
+    fn read(&self, slot: &str) -> io::Result<Vec<u8>> {
+        fs::read(self.path(slot))
+    }
+
+    fn write(&self, slot: &str, data: &[u8]) -> io::Result<WriteOutcome> {
+        let staging = self.stage(slot, data)?;
+        self.commit(staging, slot)?;
+        Ok(WriteOutcome::Written)
+    }
+
+    fn append(&self, slot: &str, data: &[u8]) -> io::Result<()> {
+        fs::create_dir_all(&self.root)?;
+        OpenOptions::new().create(true).append(true).open(self.path(slot))?.write_all(data)
+    }
+
+    fn modified(&self, slot: &str) -> Option<SystemTime> {
+        fs::metadata(self.path(slot)).and_then(|metadata| metadata.modified()).ok()
+    }
+}
+
+// Mirrors every save into a folder that another tool (Dropbox, Syncthing, ...) keeps in sync
+// between machines. A sync copy that changed since this session last touched it is never
+// silently overwritten; it is kept beside ours as a timestamped conflict file. The sync copy is
+// the one that counts, and a write that only reaches it is reported as a failure.
+pub struct SyncFolderStorage {
+    local: FolderStorage,
+    sync: FolderStorage,
+    seen: Mutex<HashMap<String, SystemTime>>,
+}
+
+impl SyncFolderStorage {
+    pub fn new(local: impl Into<PathBuf>, sync: impl Into<PathBuf>) -> Self {
+        Self {
+            local: FolderStorage::new(local),
+            sync: FolderStorage::new(sync),
+            seen: Mutex::new(HashMap::new()),
+        }
+    }
+
+    fn remember(&self, slot: &str) {
+        if let (Some(modified), Ok(mut seen)) = (self.sync.modified(slot), self.seen.lock()) {
+            seen.insert(slot.to_string(), modified);
+        }
+    }
+
+    fn changed_elsewhere(&self, slot: &str) -> bool {
+        let Some(remote) = self.sync.modified(slot) else {
+            return false;
+        };
+
+        let seen = self.seen.lock().ok().and_then(|seen| seen.get(slot).copied());
+        let baseline = seen.into_iter().chain(self.local.modified(slot)).max();
+        baseline.is_some_and(|baseline| remote > baseline)
+    }
+
+    fn preserve_conflict(&self, slot: &str) -> io::Result<PathBuf> {
+        let stamp = unix_seconds();
+        let path = Path::new(slot);
+        let stem = path.file_stem().and_then(|stem| stem.to_str()).unwrap_or(slot);
+        let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or("json");
+        let copy = format!("{}.conflict-{}.{}", stem, stamp, extension);
+
+        self.sync.write(&copy, &self.sync.read(slot)?)?;
+        Ok(self.sync.path(&copy))
+    }
+
+    // Both copies are staged before either is replaced, so a failed write leaves both as they were.
+    fn write_both(&self, slot: &str, data: &[u8]) -> io::Result<()> {
+        let sync_staging = self.sync.stage(slot, data)?;
+        let local_staging = match self.local.stage(slot, data) {
+            Ok(staging) => staging,
+            Err(error) => {
+                let _ = fs::remove_file(sync_staging);
+                return Err(error);
+            }
+        };
+
+        if let Err(error) = self.sync.commit(sync_staging, slot) {
+            let _ = fs::remove_file(local_staging);
+            return Err(error);
+        }
+
+        self.local.commit(local_staging, slot).map_err(|error| {
+            io::Error::new(
+                error.kind(),
+                format!("wrote {} but not the local copy: {}", self.sync.location(slot), error),
+            )
+        })
+    }
+}
+
+impl SaveStorage for SyncFolderStorage {
+    fn location(&self, slot: &str) -> String {
+        self.sync.location(slot)
+    }
+
+    fn read(&self, slot: &str) -> io::Result<Vec<u8>> {
+        let data = match (self.local.modified(slot), self.sync.modified(slot)) {
+            (Some(local), Some(remote)) if local >= remote => self.local.read(slot),
+            (_, Some(_)) => self.sync.read(slot),
+            _ => self.local.read(slot),
+        };
+
+        self.remember(slot);
+        data
+    }
+
+    fn write(&self, slot: &str, data: &[u8]) -> io::Result<WriteOutcome> {
+        let outcome = match self.changed_elsewhere(slot) {
+            true => WriteOutcome::ConflictPreserved(self.preserve_conflict(slot)?),
+            false => WriteOutcome::Written,
+        };
+
+        self.write_both(slot, data)?;
+        self.remember(slot);
+        Ok(outcome)
+    }
+
+    // Appending to each copy in turn could leave them with different logs, so the log is rewritten
+    // whole instead. Autosaves keep it short by compacting it into the save.
+    fn append(&self, slot: &str, data: &[u8]) -> io::Result<()> {
+        let mut log = match self.read(slot) {
+            Ok(log) => log,
+            Err(error) if error.kind() == io::ErrorKind::NotFound => Vec::new(),
+            Err(error) => return Err(error),
+        };
+
+        log.extend_from_slice(data);
+        self.write_both(slot, &log)?;
+        self.remember(slot);
+        Ok(())
+    }
+
+    fn modified(&self, slot: &str) -> Option<SystemTime> {
+        self.local.modified(slot).max(self.sync.modified(slot))
+    }
+}
+
+// Keeps a separate set of slots inside another storage by prefixing their names, so a scenario run
+// never touches the player's own world.
+pub struct PrefixedStorage {
+    prefix: String,
+    inner: Box<dyn SaveStorage>,
+}
+
+impl PrefixedStorage {
+    pub fn new(prefix: impl Into<String>, inner: Box<dyn SaveStorage>) -> Self {
+        Self {
+            prefix: prefix.into(),
+            inner,
+        }
+    }
+
+    fn slot(&self, slot: &str) -> String {
+        format!("{}_{}", self.prefix, slot)
+    }
+}
+
+impl SaveStorage for PrefixedStorage {
+    fn location(&self, slot: &str) -> String {
+        self.inner.location(&self.slot(slot))
+    }
+
+    fn read(&self, slot: &str) -> io::Result<Vec<u8>> {
+        self.inner.read(&self.slot(slot))
+    }
+
+    fn write(&self, slot: &str, data: &[u8]) -> io::Result<WriteOutcome> {
+        self.inner.write(&self.slot(slot), data)
+    }
+
+    fn append(&self, slot: &str, data: &[u8]) -> io::Result<()> {
+        self.inner.append(&self.slot(slot), data)
+    }
+
+    fn modified(&self, slot: &str) -> Option<SystemTime> {
+        self.inner.modified(&self.slot(slot))
+    }
+}
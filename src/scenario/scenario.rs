@@ -0,0 +1,340 @@
+use crate::{
+    analytics::trip_stats::TripStats,
+    clock::unix_seconds,
+    determinism::determinism::launch_option,
+    economy::{economy::Funds, economy_events::GrantFunds},
+    graph::congestion::Congestion,
+    grid::{grid::Grid, grid_cell::GridCell},
+    save::storage::SaveStore,
+    scenario::scenario_events::*,
+    schedule::UpdateStage,
+    tools::toolbar::ToolState,
+    types::{building::Building, vehicle::*},
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use serde::{Deserialize, Serialize};
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+const SCENARIO_FILE: &str = "assets/scenarios/scenario.json";
+const SCENARIO_ARG: &str = "--scenario";
+const SCENARIO_VAR: &str = "OVERCAST_SCENARIO";
+const RESULTS_SLOT: &str = "results.jsonl";
+
+pub struct ScenarioPlugin;
+
+impl Plugin for ScenarioPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<ScenarioMessage>()
+            .add_event::<OnScenarioCompleted>()
+            .insert_resource(ScenarioLog::default())
+            .insert_resource(Scenario::default())
+            .add_systems(Startup, load_scenario)
+            .add_systems(
+                Update,
+                (
+                    (evaluate_triggers, evaluate_objectives).in_set(UpdateStage::Analyze),
+                    record_scenario_messages,
+                )
+                    .chain(),
+            );
+    }
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub enum TriggerCondition {
+    PopulationAtLeast(u32),
+    TimeElapsed(f32),
+    SegmentCongestionAbove { cell: GridCell, ratio: f32 },
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub enum TriggerAction {
+    GrantMoney(i64),
+    SpawnVehicles(u32),
+    ShowMessage(String),
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Trigger {
+    pub condition: TriggerCondition,
+    pub actions: Vec<TriggerAction>,
+    #[serde(default)]
+    pub repeat: bool,
+    #[serde(skip)]
+    active: bool,
+    #[serde(skip)]
+    fired: bool,
+}
+
+// Win conditions of a challenge. They all have to hold at the same time.
+#[derive(Debug, Serialize, Deserialize)]
+pub enum Objective {
+    CompletedTrips(u32),
+    MeanTripSecondsBelow(f32),
+    PopulationAtLeast(u32),
+    FundsAtLeast(i64),
+}
+
+#[derive(Debug, Clone)]
+pub struct ObjectiveStatus {
+    pub label: String,
+    pub met: bool,
+}
+
+// A scenario file either adds triggers to the sandbox, or, when passed with `--scenario`, sets up a
+// challenge. A challenge starts from its own `world` (in the save file format) instead of the
+// player's, and keeps its saves apart from theirs.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default)]
+    pub starting_money: Option<i64>,
+    #[serde(default)]
+    pub world: Option<serde_json::Value>,
+    #[serde(default)]
+    pub objectives: Vec<Objective>,
+    #[serde(default)]
+    pub locked_tools: Vec<ToolState>,
+    #[serde(default)]
+    pub triggers: Vec<Trigger>,
+    #[serde(skip)]
+    pub challenge: bool,
+    #[serde(skip)]
+    pub status: Vec<ObjectiveStatus>,
+    #[serde(skip)]
+    pub completed: bool,
+}
+
+impl Scenario {
+    pub fn is_locked(&self, tool: ToolState) -> bool {
+        self.locked_tools.contains(&tool)
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct ScenarioResult<'a> {
+    scenario: &'a str,
+    completed_at: u64,
+    play_seconds: f32,
+    completed_trips: u32,
+    mean_trip_seconds: f32,
+    population: i32,
+    balance: i64,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct ScenarioLog {
+    pub messages: Vec<String>,
+}
+
+fn challenge_path() -> Option<String> {
+    launch_option(SCENARIO_ARG, SCENARIO_VAR)
+}
+
+// Saves of a challenge are kept under the name of its file.
+pub fn scenario_slot_prefix() -> Option<String> {
+    let path = challenge_path()?;
+    let stem = Path::new(&path).file_stem()?.to_str()?;
+    Some(format!("scenario_{}", stem))
+}
+
+fn population(building_query: &Query<&Building>) -> i32 {
+    building_query.iter().map(|building| building.area().cell_dimensions().element_product()).sum()
+}
+
+fn load_scenario(mut scenario: ResMut<Scenario>, mut funds: ResMut<Funds>) {
+    let challenge = challenge_path();
+    let path = challenge.clone().unwrap_or_else(|| SCENARIO_FILE.to_string());
+
+    let file = match File::open(&path) {
+        Ok(file) => file,
+        Err(error) => {
+            if challenge.is_some() {
+                println!("Failed to open scenario {:?}: {}", path, error);
+            }
+            return;
+        }
+    };
+
+    match serde_json::from_reader::<BufReader<File>, Scenario>(BufReader::new(file)) {
+        Ok(loaded) => {
+            println!(
+                "Loaded {} scenario triggers and {} objectives from {:?}",
+                loaded.triggers.len(),
+                loaded.objectives.len(),
+                path
+            );
+            *scenario = loaded;
+            scenario.challenge = challenge.is_some();
+
+            if let Some(money) = scenario.starting_money {
+                funds.balance = money;
+            }
+        }
+        Err(error) => println!("Failed to parse scenario {:?}: {}", path, error),
+    }
+}
+
+#[derive(SystemParam)]
+struct TriggerEffects<'w> {
+    funds: EventWriter<'w, GrantFunds>,
+    spawner: EventWriter<'w, RequestVehicleSpawn>,
+    messages: EventWriter<'w, ScenarioMessage>,
+}
+
+impl TriggerEffects<'_> {
+    fn apply(&mut self, action: &TriggerAction) {
+        match action {
+            TriggerAction::GrantMoney(amount) => {
+                self.funds.send(GrantFunds(*amount));
+            }
+            TriggerAction::SpawnVehicles(count) => {
+                for _ in 0..*count {
+                    self.spawner.send(RequestVehicleSpawn);
+                }
+            }
+            TriggerAction::ShowMessage(text) => {
+                self.messages.send(ScenarioMessage(text.clone()));
+            }
+        }
+    }
+}
+
+fn evaluate_triggers(
+    mut scenario: ResMut<Scenario>,
+    building_query: Query<&Building>,
+    congestion_query: Query<&Congestion>,
+    grid_query: Query<&Grid>,
+    time: Res<Time>,
+    mut effects: TriggerEffects,
+) {
+    if scenario.triggers.is_empty() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    let population = population(&building_query);
+
+    for trigger in &mut scenario.triggers {
+        if trigger.fired && !trigger.repeat {
+            continue;
+        }
+
+        let satisfied = match &trigger.condition {
+            TriggerCondition::PopulationAtLeast(target) => population >= *target as i32,
+            TriggerCondition::TimeElapsed(seconds) => time.elapsed_seconds() >= *seconds,
+            TriggerCondition::SegmentCongestionAbove { cell, ratio } => {
+                let road = grid.entity_at(*cell).ok().flatten();
+                road.and_then(|entity| congestion_query.get(entity).ok()).is_some_and(|congestion| congestion.ratio > *ratio)
+            }
+        };
+
+        if satisfied && !trigger.active {
+            for action in &trigger.actions {
+                effects.apply(action);
+            }
+            trigger.fired = true;
+        }
+
+        trigger.active = satisfied;
+    }
+}
+
+// The parts of the city that objectives are measured against.
+#[derive(SystemParam)]
+struct CityProgress<'w, 's> {
+    building_query: Query<'w, 's, &'static Building>,
+    stats: Res<'w, TripStats>,
+    funds: Res<'w, Funds>,
+    time: Res<'w, Time>,
+}
+
+// Once every objective holds, the challenge is complete and a result record is appended to the
+// scenario's results slot.
+fn evaluate_objectives(
+    mut scenario: ResMut<Scenario>,
+    progress: CityProgress,
+    store: Res<SaveStore>,
+    mut messages: EventWriter<ScenarioMessage>,
+    mut completed: EventWriter<OnScenarioCompleted>,
+) {
+    if scenario.objectives.is_empty() || scenario.completed {
+        return;
+    }
+
+    let CityProgress {
+        building_query,
+        stats,
+        funds,
+        time,
+    } = progress;
+
+    let population = population(&building_query);
+    let mean_trip_seconds = stats.mean_trip_time();
+
+    scenario.status = scenario
+        .objectives
+        .iter()
+        .map(|objective| match *objective {
+            Objective::CompletedTrips(target) => ObjectiveStatus {
+                label: format!("Complete {} trips ({}/{})", target, stats.total_trips.min(target), target),
+                met: stats.total_trips >= target,
+            },
+            Objective::MeanTripSecondsBelow(limit) => ObjectiveStatus {
+                label: format!("Average trip under {:.0}s ({:.1}s)", limit, mean_trip_seconds),
+                met: stats.total_trips > 0 && mean_trip_seconds < limit,
+            },
+            Objective::PopulationAtLeast(target) => ObjectiveStatus {
+                label: format!("Reach {} population ({}/{})", target, population.min(target as i32), target),
+                met: population >= target as i32,
+            },
+            Objective::FundsAtLeast(target) => ObjectiveStatus {
+                label: format!("Save up ${} (${})", target, funds.balance),
+                met: funds.balance >= target,
+            },
+        })
+        .collect();
+
+    if !scenario.status.iter().all(|status| status.met) {
+        return;
+    }
+
+    scenario.completed = true;
+
+    let result = ScenarioResult {
+        scenario: &scenario.name,
+        completed_at: unix_seconds(),
+        play_seconds: time.elapsed_seconds(),
+        completed_trips: stats.total_trips,
+        mean_trip_seconds,
+        population,
+        balance: funds.balance,
+    };
+
+    let written = serde_json::to_vec(&result).map_err(|error| error.to_string()).and_then(|mut line| {
+        line.push(b'\n');
+        store.0.append(RESULTS_SLOT, &line).map_err(|error| error.to_string())
+    });
+
+    if let Err(error) = written {
+        println!(
+            "Failed to record the scenario result to {:?}: {}",
+            store.0.location(RESULTS_SLOT),
+            error
+        );
+    }
+
+    messages.send(ScenarioMessage(format!("Scenario \"{}\" complete!", scenario.name)));
+    completed.send(OnScenarioCompleted {
+        name: scenario.name.clone(),
+    });
+}
+
+fn record_scenario_messages(mut event: EventReader<ScenarioMessage>, mut log: ResMut<ScenarioLog>) {
+    for ScenarioMessage(text) in event.read() {
+        log.messages.push(text.clone());
+    }
+}
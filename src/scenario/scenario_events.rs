@@ -0,0 +1,9 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct ScenarioMessage(pub String);
+
+#[derive(Event, Debug)]
+pub struct OnScenarioCompleted {
+    pub name: String,
+}
@@ -0,0 +1,537 @@
+use crate::{
+    economy::economy::*,
+    graphics::camera::*,
+    grid::{grid::*, grid_area::*, grid_cell::GridCell, orientation::GAxis, terrain::Terrain},
+    input::keymap::{Action, Actions},
+    save::storage::SaveStore,
+    schedule::UpdateStage,
+    tools::{
+        building_tool::{RequestBuilding, MAX_BUILDING_RELIEF},
+        context_menu::ContextMenu,
+        road_events::{RequestIntersection, RequestRoad},
+        road_tool::{road_grade, MAX_ROAD_GRADE},
+        toolbar::ToolState,
+    },
+    types::{building::*, intersection::*, road_segment::*},
+    ui::egui::MouseOver,
+};
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
+
+const BLUEPRINT_FILE: &str = "blueprints.json";
+
+pub struct BlueprintToolPlugin;
+
+impl Plugin for BlueprintToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(BlueprintTool::default())
+            .insert_resource(Blueprints::default())
+            .add_systems(Startup, load_blueprints)
+            .add_systems(
+                Update,
+                (
+                    (
+                        (update_ground_position).in_set(UpdateStage::UpdateView).run_if(in_state(MouseOver::World)),
+                        (handle_copy_action, handle_stamp_action)
+                            .in_set(UpdateStage::UserInput)
+                            .run_if(in_state(MouseOver::World)),
+                    )
+                        .run_if(in_state(ToolState::Blueprint)),
+                    save_blueprints.in_set(UpdateStage::Analyze).run_if(|blueprints: Res<Blueprints>| blueprints.dirty),
+                ),
+            );
+    }
+}
+
+// One road, intersection or building of a blueprint, with its area relative to the blueprint's
+// minimum corner.
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum BlueprintPiece {
+    Road {
+        area: GridArea,
+        orientation: GAxis,
+        shape: RoadShape,
+        surface: RoadSurface,
+    },
+    Intersection {
+        area: GridArea,
+    },
+    Building {
+        area: GridArea,
+        kind: BuildingKind,
+        seed: u64,
+    },
+}
+
+impl BlueprintPiece {
+    pub fn area(&self) -> GridArea {
+        match *self {
+            BlueprintPiece::Road { area, .. } => area,
+            BlueprintPiece::Intersection { area } => area,
+            BlueprintPiece::Building { area, .. } => area,
+        }
+    }
+
+    fn with_area(mut self, new_area: GridArea) -> Self {
+        match &mut self {
+            BlueprintPiece::Road { area, .. } => *area = new_area,
+            BlueprintPiece::Intersection { area } => *area = new_area,
+            BlueprintPiece::Building { area, .. } => *area = new_area,
+        }
+        self
+    }
+
+    fn segment(&self) -> Option<RoadSegment> {
+        match *self {
+            BlueprintPiece::Road {
+                area,
+                orientation,
+                shape,
+                surface,
+            } => {
+                let mut segment = RoadSegment::shaped(area, orientation, shape);
+                segment.surface = surface;
+                Some(segment)
+            }
+            _ => None,
+        }
+    }
+
+    fn cells(&self) -> Vec<GridCell> {
+        match self.segment() {
+            Some(segment) => segment.cells(),
+            None => self.area().iter().collect(),
+        }
+    }
+
+    fn cost(&self) -> i64 {
+        match self {
+            BlueprintPiece::Building { area, .. } => building_cost(*area),
+            _ => road_cost(self.cells().len() as i64),
+        }
+    }
+
+    fn offset(self, by: IVec2) -> Self {
+        let area = self.area();
+        self.with_area(GridArea::new(
+            GridCell::new(area.min.pos.x + by.x, area.min.pos.y + by.y),
+            GridCell::new(area.max.pos.x + by.x, area.max.pos.y + by.y),
+        ))
+    }
+
+    // Quarter turn inside a blueprint `size` cells across, mapping cell (x, y) to (size.y - 1 - y, x).
+    fn rotated(self, size: IVec2) -> Self {
+        let area = self.area();
+        let rotated = self.with_area(GridArea::new(
+            GridCell::new(size.y - 1 - area.max.pos.y, area.min.pos.x),
+            GridCell::new(size.y - 1 - area.min.pos.y, area.max.pos.x),
+        ));
+
+        match rotated {
+            BlueprintPiece::Road {
+                area,
+                orientation,
+                shape,
+                surface,
+            } => BlueprintPiece::Road {
+                area,
+                orientation: match orientation {
+                    GAxis::X => GAxis::Z,
+                    GAxis::Z => GAxis::X,
+                },
+                shape: match shape {
+                    RoadShape::Straight => RoadShape::Straight,
+                    RoadShape::Diagonal { shift, width } => RoadShape::Diagonal { shift: -shift, width },
+                    RoadShape::Curve { pivot, width } => RoadShape::Curve {
+                        pivot: IVec2::new(-pivot.y, pivot.x),
+                        width,
+                    },
+                },
+                surface,
+            },
+            piece => piece,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Blueprint {
+    pub name: String,
+    pub size: IVec2,
+    pub pieces: Vec<BlueprintPiece>,
+}
+
+impl Blueprint {
+    fn rotated(&self) -> Self {
+        Self {
+            name: self.name.clone(),
+            size: IVec2::new(self.size.y, self.size.x),
+            pieces: self.pieces.iter().map(|piece| piece.rotated(self.size)).collect(),
+        }
+    }
+
+    pub fn cost(&self) -> i64 {
+        self.pieces.iter().map(BlueprintPiece::cost).sum()
+    }
+}
+
+// Saved alongside the world saves but kept out of them, so blueprints carry over between worlds.
+#[derive(Resource, Debug, Default, Serialize, Deserialize)]
+pub struct Blueprints {
+    pub blueprints: Vec<Blueprint>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl Blueprints {
+    pub fn add(&mut self, blueprint: Blueprint) -> usize {
+        self.blueprints.push(blueprint);
+        self.dirty = true;
+        self.blueprints.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) {
+        if index < self.blueprints.len() {
+            self.blueprints.remove(index);
+            self.dirty = true;
+        }
+    }
+}
+
+// Without a selected blueprint the tool copies whatever is dragged over. With one it stamps it at
+// the cursor.
+#[derive(Resource, Debug, Default)]
+pub struct BlueprintTool {
+    pub name: String,
+    selected: Option<usize>,
+    rotation: u8,
+    ground_position: Vec3,
+    dragging: bool,
+    drag_start_ground_position: Vec3,
+}
+
+impl BlueprintTool {
+    pub fn selected(&self) -> Option<usize> {
+        self.selected
+    }
+
+    pub fn select(&mut self, index: Option<usize>) {
+        self.selected = index;
+        self.rotation = 0;
+        self.dragging = false;
+    }
+
+    // Keeps the selection pointing at the same blueprint after `index` is deleted.
+    pub fn forget(&mut self, index: usize) {
+        self.selected = match self.selected {
+            Some(selected) if selected == index => None,
+            Some(selected) if selected > index => Some(selected - 1),
+            selected => selected,
+        };
+    }
+
+    fn selection_area(&self) -> GridArea {
+        let area = GridArea::at(self.ground_position, 1, 1);
+
+        if self.dragging {
+            area.union(GridArea::at(self.drag_start_ground_position, 1, 1))
+        } else {
+            area
+        }
+    }
+
+    // The selected blueprint turned and moved so it is centered on the cursor.
+    fn placement(&self, blueprints: &Blueprints) -> Option<Vec<BlueprintPiece>> {
+        let mut blueprint = blueprints.blueprints.get(self.selected?)?.clone();
+        for _ in 0..self.rotation {
+            blueprint = blueprint.rotated();
+        }
+
+        let origin = GridArea::at(self.ground_position, blueprint.size.x, blueprint.size.y).min.pos;
+        Some(blueprint.pieces.into_iter().map(|piece| piece.offset(origin)).collect())
+    }
+}
+
+fn can_place(pieces: &[BlueprintPiece], grid: &Grid, terrain: &Terrain) -> bool {
+    let cells: Vec<GridCell> = pieces.iter().flat_map(BlueprintPiece::cells).collect();
+
+    grid.is_valid_paint_cells(cells.iter().copied())
+        && terrain.is_dry(cells)
+        && pieces.iter().all(|piece| match piece {
+            BlueprintPiece::Building { area, .. } => terrain.relief(*area) <= MAX_BUILDING_RELIEF,
+            _ => piece.segment().is_none_or(|segment| road_grade(&segment, terrain) <= MAX_ROAD_GRADE),
+        })
+}
+
+fn load_blueprints(mut blueprints: ResMut<Blueprints>, store: Res<SaveStore>) {
+    let Ok(data) = store.0.read(BLUEPRINT_FILE) else {
+        return;
+    };
+
+    match serde_json::from_slice::<Blueprints>(&data) {
+        Ok(loaded) => *blueprints = loaded,
+        Err(error) => println!(
+            "Failed to read blueprints from {:?}: {}",
+            store.0.location(BLUEPRINT_FILE),
+            error
+        ),
+    }
+}
+
+fn save_blueprints(mut blueprints: ResMut<Blueprints>, store: Res<SaveStore>) {
+    blueprints.dirty = false;
+
+    let written = serde_json::to_vec_pretty(blueprints.as_ref())
+        .map_err(|error| error.to_string())
+        .and_then(|data| store.0.write(BLUEPRINT_FILE, &data).map_err(|error| error.to_string()));
+
+    if let Err(error) = written {
+        println!(
+            "Failed to save blueprints to {:?}: {}",
+            store.0.location(BLUEPRINT_FILE),
+            error
+        );
+    }
+}
+
+fn update_ground_position(
+    cursor: CursorPick,
+    mut tool: ResMut<BlueprintTool>,
+    blueprints: Res<Blueprints>,
+    grid_query: Query<&Grid>,
+    funds: Res<Funds>,
+    mut gizmos: Gizmos,
+) {
+    let terrain = cursor.terrain();
+
+    let Some(point) = cursor.ground() else {
+        return;
+    };
+
+    tool.ground_position = point;
+
+    let Some(pieces) = tool.placement(&blueprints) else {
+        let area = tool.selection_area();
+        let mut gizmo_color = Color::linear_rgba(0.2, 0.8, 0.2, 0.8);
+
+        if cursor.camera_moving() {
+            gizmo_color = gizmo_color.with_alpha(0.25);
+        }
+
+        gizmos.rect(
+            area.center().with_y(terrain.bounds(area).1 + 0.01),
+            Quat::from_rotation_x(FRAC_PI_2),
+            area.dimensions(),
+            gizmo_color,
+        );
+        return;
+    };
+
+    let cost: i64 = pieces.iter().map(BlueprintPiece::cost).sum();
+    let mut gizmo_color = if funds.can_afford(cost) && can_place(&pieces, grid_query.single(), terrain) {
+        Color::linear_rgba(0.2, 0.8, 0.2, 0.8)
+    } else {
+        Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
+    };
+
+    if cursor.camera_moving() {
+        gizmo_color = gizmo_color.with_alpha(0.25);
+    }
+
+    for piece in &pieces {
+        let area = piece.area();
+        gizmos.rect(
+            area.center().with_y(terrain.bounds(area).1 + 0.01),
+            Quat::from_rotation_x(FRAC_PI_2),
+            area.dimensions(),
+            gizmo_color,
+        );
+    }
+}
+
+#[derive(SystemParam)]
+struct PlacedPieces<'w, 's> {
+    grid_query: Query<'w, 's, &'static Grid>,
+    segment_query: Query<'w, 's, &'static RoadSegment>,
+    inter_query: Query<'w, 's, &'static Intersection>,
+    building_query: Query<'w, 's, &'static Building>,
+}
+
+impl PlacedPieces<'_, '_> {
+    // Only pieces that lie entirely inside the area are copied, so a blueprint never holds half a road.
+    fn copy(&self, area: GridArea) -> Vec<BlueprintPiece> {
+        let grid = self.grid_query.single();
+        let mut touched: Vec<Entity> = area
+            .iter()
+            .filter_map(|cell| grid.entity_at(cell).ok().flatten())
+            .collect::<HashSet<Entity>>()
+            .into_iter()
+            .collect();
+        touched.sort();
+
+        let inside = |cells: &[GridCell]| {
+            cells.iter().all(|cell| {
+                (area.min.pos.x..=area.max.pos.x).contains(&cell.pos.x)
+                    && (area.min.pos.y..=area.max.pos.y).contains(&cell.pos.y)
+            })
+        };
+
+        touched
+            .into_iter()
+            .filter_map(|entity| {
+                if let Ok(segment) = self.segment_query.get(entity) {
+                    Some(BlueprintPiece::Road {
+                        area: segment.area(),
+                        orientation: segment.orientation,
+                        shape: segment.shape,
+                        surface: segment.surface,
+                    })
+                } else if let Ok(inter) = self.inter_query.get(entity) {
+                    Some(BlueprintPiece::Intersection { area: inter.area() })
+                } else if let Ok(building) = self.building_query.get(entity) {
+                    Some(BlueprintPiece::Building {
+                        area: building.area(),
+                        kind: building.kind,
+                        seed: building.seed,
+                    })
+                } else {
+                    None
+                }
+            })
+            .filter(|piece| inside(&piece.cells()))
+            .map(|piece| piece.offset(-area.min.pos))
+            .collect()
+    }
+}
+
+fn handle_copy_action(
+    mut tool: ResMut<BlueprintTool>,
+    mut blueprints: ResMut<Blueprints>,
+    placed: PlacedPieces,
+    actions: Actions,
+    mut menu: ResMut<ContextMenu>,
+) {
+    if tool.selected.is_some() {
+        return;
+    }
+
+    if tool.dragging && actions.secondary_just_pressed() {
+        tool.dragging = false;
+        menu.consume_press();
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
+        tool.dragging = true;
+        tool.drag_start_ground_position = tool.ground_position;
+    }
+
+    if actions.just_pressed(Action::Cancel) {
+        tool.dragging = false;
+    }
+
+    if !tool.dragging || !actions.primary_just_released() {
+        return;
+    }
+
+    let area = tool.selection_area();
+    tool.dragging = false;
+
+    let pieces = placed.copy(area);
+    if pieces.is_empty() {
+        return;
+    }
+
+    let name = match tool.name.trim() {
+        "" => format!("Blueprint {}", blueprints.blueprints.len() + 1),
+        name => name.to_string(),
+    };
+
+    let index = blueprints.add(Blueprint {
+        name,
+        size: area.cell_dimensions(),
+        pieces,
+    });
+    tool.name.clear();
+    tool.select(Some(index));
+}
+
+#[derive(SystemParam)]
+struct Stamper<'w, 's> {
+    grid_query: Query<'w, 's, &'static Grid>,
+    terrain: Res<'w, Terrain>,
+    builder: EventWriter<'w, RequestBuilding>,
+    intersector: EventWriter<'w, RequestIntersection>,
+    roader: EventWriter<'w, RequestRoad>,
+}
+
+impl Stamper<'_, '_> {
+    fn can_place(&self, pieces: &[BlueprintPiece]) -> bool {
+        can_place(pieces, self.grid_query.single(), &self.terrain)
+    }
+
+    fn stamp(&mut self, pieces: Vec<BlueprintPiece>) {
+        for piece in pieces {
+            match piece {
+                BlueprintPiece::Road {
+                    area,
+                    orientation,
+                    shape,
+                    surface,
+                } => {
+                    self.roader.send(RequestRoad::shaped(area, orientation, shape).with_surface(surface));
+                }
+                BlueprintPiece::Intersection { area } => {
+                    self.intersector.send(RequestIntersection::new(area));
+                }
+                BlueprintPiece::Building { area, kind, seed } => {
+                    self.builder.send(RequestBuilding::of_kind(area, kind).with_seed(seed));
+                }
+            }
+        }
+    }
+}
+
+fn handle_stamp_action(
+    mut tool: ResMut<BlueprintTool>,
+    blueprints: Res<Blueprints>,
+    actions: Actions,
+    mut menu: ResMut<ContextMenu>,
+    mut stamper: Stamper,
+    mut payment: Payment,
+) {
+    if tool.selected.is_none() {
+        return;
+    }
+
+    if actions.just_pressed(Action::Cancel) || actions.secondary_just_pressed() {
+        tool.select(None);
+        menu.consume_press();
+        return;
+    }
+
+    if actions.just_pressed(Action::RotateTool) {
+        tool.rotation = (tool.rotation + 1) % 4;
+    }
+
+    if !actions.primary_just_pressed() || actions.mouse_modifier_held() {
+        return;
+    }
+
+    let Some(pieces) = tool.placement(&blueprints) else {
+        return;
+    };
+
+    if !stamper.can_place(&pieces) {
+        return;
+    }
+
+    let cost: i64 = pieces.iter().map(BlueprintPiece::cost).sum();
+    if !payment.can_afford(cost) {
+        payment.reject(cost);
+        return;
+    }
+
+    payment.spend(cost);
+    stamper.stamp(pieces);
+}
@@ -1,15 +1,29 @@
 use crate::{
+    determinism::determinism::SimRng,
+    economy::economy::*,
     graph::road_graph_events::*,
-    graphics::camera::*,
-    grid::{grid::*, grid_area::*},
+    graphics::{
+        building_mesh::{generate_building, RoofProp},
+        camera::*,
+        models::{add_render_asset, Models},
+        particles::{ParticleEmitter, ParticleStyle},
+        weather::BuildingWindows,
+    },
+    grid::{grid::*, grid_area::*, grid_cell::GridCell, terrain::Terrain},
+    input::keymap::{Action, Actions},
     schedule::UpdateStage,
     tools::toolbar::ToolState,
-    types::building::*,
+    types::{building::*, parking::ParkingLot},
     ui::egui::MouseOver,
 };
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use rand::Rng;
 
+const ROOF_DETAIL_INSET: f32 = 0.3;
+pub const MAX_BUILDING_RELIEF: f32 = 0.35;
+pub const MAX_BUILDING_GAP: i32 = 4;
+const MAX_DRAG_LOTS: usize = 256;
+
 pub struct BuildingToolPlugin;
 
 impl Plugin for BuildingToolPlugin {
@@ -32,6 +46,9 @@ impl Plugin for BuildingToolPlugin {
 pub struct BuildingTool {
     dimensions: IVec2,
     ground_position: Vec3,
+    drag_start: Option<Vec3>,
+    pub kind: BuildingKind,
+    pub gap: i32,
 }
 
 impl BuildingTool {
@@ -39,18 +56,71 @@ impl BuildingTool {
         Self {
             dimensions: IVec2::ONE,
             ground_position: Vec3::ZERO,
+            drag_start: None,
+            kind: BuildingKind::House,
+            gap: 1,
         }
     }
+
+    fn footprint_at(&self, position: Vec3) -> GridArea {
+        GridArea::at(position, self.dimensions.x, self.dimensions.y)
+    }
+
+    // Footprints tiled from the one under the drag start towards the cursor, `gap` cells apart. Only
+    // footprints that fit entirely inside the dragged region are kept.
+    fn lots(&self) -> Vec<GridArea> {
+        let last = self.footprint_at(self.ground_position);
+        let Some(start) = self.drag_start else {
+            return vec![last];
+        };
+
+        let first = self.footprint_at(start);
+        let step = self.dimensions + IVec2::splat(self.gap);
+        let delta = last.min.pos - first.min.pos;
+        let count = delta.abs() / step + IVec2::ONE;
+        let direction = delta.signum();
+
+        (0..count.y)
+            .flat_map(|row| (0..count.x).map(move |column| IVec2::new(column, row)))
+            .take(MAX_DRAG_LOTS)
+            .map(|index| {
+                let offset = index * step * direction;
+                GridArea::new(
+                    GridCell {
+                        pos: first.min.pos + offset,
+                    },
+                    GridCell {
+                        pos: first.max.pos + offset,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+pub fn fits_lot(area: GridArea, grid: &Grid, terrain: &Terrain) -> bool {
+    grid.is_valid_paint_area(area) && terrain.is_dry(area.iter()) && terrain.relief(area) <= MAX_BUILDING_RELIEF
 }
 
 #[derive(Event, Debug)]
 pub struct RequestBuilding {
     pub area: GridArea,
+    pub kind: BuildingKind,
+    pub seed: Option<u64>,
 }
 
 impl RequestBuilding {
     pub fn new(area: GridArea) -> Self {
-        Self { area }
+        Self::of_kind(area, BuildingKind::House)
+    }
+
+    pub fn of_kind(area: GridArea, kind: BuildingKind) -> Self {
+        Self { area, kind, seed: None }
+    }
+
+    pub fn with_seed(mut self, seed: u64) -> Self {
+        self.seed = Some(seed);
+        self
     }
 }
 
@@ -59,124 +129,239 @@ fn spawn_tool(mut commands: Commands) {
 }
 
 fn update_ground_position(
-    camera_query: Query<(&Camera, &PlayerCameraController, &GlobalTransform)>,
+    cursor: CursorPick,
     mut tool_query: Query<&mut BuildingTool>,
-    ground_query: Query<&GlobalTransform, With<Ground>>,
     grid_query: Query<&Grid>,
-    windows: Query<&Window>,
+    funds: Res<Funds>,
     mut gizmos: Gizmos,
 ) {
-    let (camera, controller, camera_transform) = camera_query.single();
     let mut tool = tool_query.single_mut();
-    let ground = ground_query.single();
+    let terrain = cursor.terrain();
 
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
-
-    let Some(cursor_position) = window.cursor_position() else {
-        return;
-    };
+    if let Some(point) = cursor.ground() {
+        tool.ground_position = point;
 
-    let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
-        return;
-    };
+        let grid = grid_query.single();
+        let mut total = 0;
 
-    if let Some(distance) = ray.intersect_plane(ground.translation(), InfinitePlane3d::new(ground.up())) {
-        let point = ray.get_point(distance);
+        for area in tool.lots() {
+            let fits = fits_lot(area, grid, terrain);
+            if fits {
+                total += building_cost(area);
+            }
 
-        tool.ground_position = point;
+            let mut gizmo_color = if fits && funds.can_afford(total) {
+                Color::linear_rgba(0.0, 1.0, 1.0, 0.8)
+            } else {
+                Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
+            };
 
-        let area = GridArea::at(tool.ground_position, tool.dimensions.x, tool.dimensions.y);
+            if cursor.camera_moving() {
+                gizmo_color = gizmo_color.with_alpha(0.25);
+            }
 
-        let mut gizmo_color = if grid_query.single().is_valid_paint_area(area) {
-            Color::linear_rgba(0.0, 1.0, 1.0, 0.8)
-        } else {
-            Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
-        };
-
-        if controller.is_moving() {
-            gizmo_color = gizmo_color.with_alpha(0.25);
+            let (low, high) = terrain.bounds(area);
+            gizmos.cuboid(
+                Transform::from_translation(area.center().with_y((low + high + 1.0) / 2.0)).with_scale(Vec3::new(
+                    area.dimensions().x,
+                    high - low + 1.0,
+                    area.dimensions().y,
+                )),
+                gizmo_color,
+            );
         }
-
-        gizmos.cuboid(
-            Transform::from_translation(area.center().with_y(0.5)).with_scale(Vec3::new(
-                area.dimensions().x,
-                1.0,
-                area.dimensions().y,
-            )),
-            gizmo_color,
-        );
     }
 }
 
-fn adjust_tool_size(mut query: Query<&mut BuildingTool>, keyboard: Res<ButtonInput<KeyCode>>) {
+fn adjust_tool_size(mut query: Query<&mut BuildingTool>, actions: Actions) {
     let mut tool = query.single_mut();
 
-    if keyboard.just_pressed(KeyCode::KeyR) {
+    if actions.just_pressed(Action::AdjustToolUp) {
         tool.dimensions.x += 1;
         tool.dimensions.y += 1;
     }
-    if keyboard.just_pressed(KeyCode::KeyF) {
+    if actions.just_pressed(Action::AdjustToolDown) {
         tool.dimensions.x -= 1;
         tool.dimensions.y -= 1;
     }
 
-    if keyboard.just_pressed(KeyCode::BracketRight) {
+    if actions.just_pressed(Action::WidenTool) {
         tool.dimensions.x += 1;
     }
-    if keyboard.just_pressed(KeyCode::BracketLeft) {
+    if actions.just_pressed(Action::NarrowTool) {
         tool.dimensions.x -= 1;
     }
 
-    if keyboard.just_pressed(KeyCode::Equal) {
+    if actions.just_pressed(Action::LengthenTool) {
         tool.dimensions.y += 1;
     }
-    if keyboard.just_pressed(KeyCode::Minus) {
+    if actions.just_pressed(Action::ShortenTool) {
         tool.dimensions.y -= 1;
     }
 
+    if actions.just_pressed(Action::PlaceHouse) {
+        tool.kind = BuildingKind::House;
+    }
+    if actions.just_pressed(Action::PlaceShop) {
+        tool.kind = BuildingKind::Shop;
+    }
+    if actions.just_pressed(Action::PlaceOffice) {
+        tool.kind = BuildingKind::Office;
+    }
+    if actions.just_pressed(Action::PlaceFactory) {
+        tool.kind = BuildingKind::Factory;
+    }
+    if actions.just_pressed(Action::PlaceParking) {
+        tool.kind = BuildingKind::Parking;
+    }
+    if actions.just_pressed(Action::PlaceStadium) {
+        tool.kind = BuildingKind::Stadium;
+    }
+    if actions.just_pressed(Action::PlaceMall) {
+        tool.kind = BuildingKind::Mall;
+    }
+
     tool.dimensions = tool.dimensions.max(IVec2::new(1, 1));
 }
 
+// Pressing starts a drag and releasing fills the dragged region, so a plain click still places a
+// single building. Lots that do not fit are skipped, and placement stops at the first lot the city
+// can no longer afford.
 fn handle_tool_action(
-    query: Query<&mut BuildingTool>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
+    mut query: Query<&mut BuildingTool>,
+    actions: Actions,
+    grid_query: Query<&Grid>,
+    terrain: Res<Terrain>,
     mut builder: EventWriter<RequestBuilding>,
+    mut payment: Payment,
 ) {
-    let tool = query.single();
+    let mut tool = query.single_mut();
+
+    if actions.secondary_just_pressed() || actions.just_pressed(Action::Cancel) {
+        tool.drag_start = None;
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
+        tool.drag_start = Some(tool.ground_position);
+    }
+
+    if !actions.primary_just_released() || tool.drag_start.is_none() {
+        return;
+    }
+
+    let grid = grid_query.single();
+    let mut total = 0;
+    let mut requests = Vec::new();
+
+    for area in tool.lots() {
+        if !fits_lot(area, grid, &terrain) {
+            continue;
+        }
+
+        let cost = building_cost(area);
+        if !payment.can_afford(total + cost) {
+            payment.reject(cost);
+            break;
+        }
+
+        total += cost;
+        requests.push(RequestBuilding::of_kind(area, tool.kind));
+    }
+
+    tool.drag_start = None;
 
-    if mouse.just_pressed(MouseButton::Left) && !keyboard.any_pressed([KeyCode::AltLeft, KeyCode::ControlLeft]) {
-        let area = GridArea::at(tool.ground_position, tool.dimensions.x, tool.dimensions.y);
-        builder.send(RequestBuilding::new(area));
+    if !requests.is_empty() {
+        builder.send_batch(requests);
+        payment.spend(total);
     }
 }
 
+// Buildings sit on the terrain, so the system that builds them needs it next to the mesh store, which
+// is missing in headless runs.
+#[derive(SystemParam)]
+struct BuildingAssets<'w> {
+    meshes: Option<ResMut<'w, Assets<Mesh>>>,
+    models: Res<'w, Models>,
+    terrain: Res<'w, Terrain>,
+}
+
 fn spawn_buildings(
     mut commands: Commands,
     mut grid_query: Query<&mut Grid>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut assets: BuildingAssets,
     mut event: EventWriter<OnBuildingSpawned>,
     mut builder: EventReader<RequestBuilding>,
+    mut sim_rng: ResMut<SimRng>,
 ) {
     let mut grid = grid_query.single_mut();
 
-    for &RequestBuilding { area } in builder.read() {
-        let rheight = rand::thread_rng().gen_range(0.5..6.0);
-        let rgray = rand::thread_rng().gen_range(0.05..0.25);
+    for &RequestBuilding { area, kind, seed } in builder.read() {
+        let data = assets.models.building(kind);
+        let seed = seed.unwrap_or_else(|| sim_rng.rng().gen());
         let crop = 0.5;
+        let footprint = area.dimensions() - Vec2::splat(crop);
+
+        if grid.is_valid_paint_area(area) && assets.terrain.is_dry(area.iter()) {
+            let shape = generate_building(kind, footprint, data.heights, data.materials.len(), seed);
+            let material = data.materials[shape.material_index].clone();
 
-        if grid.is_valid_paint_area(area) {
             let model = PbrBundle {
-                mesh: meshes.add(Cuboid::new(area.dimensions().x - crop, rheight, area.dimensions().y - crop)),
-                material: materials.add(Color::linear_rgb(rgray, rgray, rgray)),
-                transform: Transform::from_translation(area.center().with_y(rheight / 2.0)),
+                mesh: add_render_asset(&mut assets.meshes, shape.mesh),
+                material: material.clone(),
+                transform: Transform::from_translation(area.center().with_y(assets.terrain.bounds(area).0)),
                 ..default()
             };
 
-            let entity = commands.spawn((model, Building::new(area))).id();
+            let windows = BuildingWindows {
+                day: material.clone(),
+                night: data.night_materials[shape.material_index].clone(),
+            };
+            let entity = commands.spawn((model, Building::new(area, kind, seed), windows)).id();
+            if kind == BuildingKind::Parking {
+                commands.entity(entity).insert(ParkingLot::new(area));
+            }
+
+            let roof = shape.roof;
+            let detail = data.detail.as_ref().map(|(mesh, material)| {
+                let transform = match kind {
+                    BuildingKind::Factory => Transform::from_translation(
+                        roof + (shape.roof_size / 2.0 - Vec2::splat(ROOF_DETAIL_INSET)).extend(0.5).xzy(),
+                    ),
+                    _ => Transform::from_translation(roof + Vec3::Y * 0.3).with_scale(shape.roof_size.extend(1.0).xzy()),
+                };
+                (mesh.clone(), material.clone(), transform)
+            });
+
+            let props = shape.props.iter().map(|prop| match *prop {
+                RoofProp::AirConditioner(pos) => (
+                    assets.models.air_conditioner_mesh.clone(),
+                    assets.models.roof_prop_material.clone(),
+                    Transform::from_translation(pos + Vec3::Y * 0.06),
+                ),
+                RoofProp::WaterTower(pos) => (
+                    assets.models.water_tower_mesh.clone(),
+                    assets.models.roof_prop_material.clone(),
+                    Transform::from_translation(pos),
+                ),
+            });
+
+            let smoke_stack = detail.is_some() && kind.zone() == Zone::Industrial;
+            commands.entity(entity).with_children(|parent| {
+                for (index, (mesh, material, transform)) in detail.into_iter().chain(props).enumerate() {
+                    let mut part = parent.spawn(PbrBundle {
+                        mesh,
+                        material,
+                        transform,
+                        ..default()
+                    });
+
+                    // The detail on a factory roof is its smoke stack.
+                    if index == 0 && smoke_stack {
+                        part.insert(ParticleEmitter::new(ParticleStyle::Smoke, Vec3::Y * 0.5));
+                    }
+                }
+            });
+
             grid.mark_area_occupied(area, entity);
             event.send(OnBuildingSpawned(entity));
         }
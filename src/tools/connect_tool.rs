@@ -0,0 +1,338 @@
+use crate::{
+    economy::economy::*,
+    graph::road_network::RoadNetwork,
+    graphics::camera::*,
+    grid::{grid::*, grid_area::GridArea, grid_cell::GridCell, orientation::GAxis, terrain::Terrain},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::{road_events::*, toolbar::ToolState},
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+
+const ASSIST_ROAD_WIDTH: i32 = 2;
+const MAX_CONNECT_LENGTH: i32 = 48;
+const SELECTED_COLOR: Color = Color::linear_rgba(1.0, 1.0, 1.0, 0.8);
+const SUGGESTION_COLOR: Color = Color::linear_rgba(0.2, 1.0, 0.4, 0.9);
+const UNAFFORDABLE_COLOR: Color = Color::linear_rgba(1.0, 0.0, 0.0, 0.6);
+
+pub struct ConnectToolPlugin;
+
+impl Plugin for ConnectToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ConnectTool::default())
+            .add_systems(OnExit(ToolState::Connect), clear_connect_tool)
+            .add_systems(
+                Update,
+                (
+                    select_connect_targets.in_set(UpdateStage::UserInput).run_if(in_state(MouseOver::World)),
+                    build_suggested_connection.in_set(UpdateStage::UserInput),
+                    visualize_connect_tool.in_set(UpdateStage::Visualize),
+                )
+                    .run_if(in_state(ToolState::Connect)),
+            );
+    }
+}
+
+#[derive(Resource, Default, Debug)]
+pub struct ConnectTool {
+    pub selection: Vec<Entity>,
+    pub suggestion: Option<Connection>,
+}
+
+#[derive(Clone, Debug)]
+pub struct Connection {
+    pub legs: Vec<(GridArea, GAxis)>,
+    pub corner: Option<GridArea>,
+    pub attachments: Vec<(Entity, GridArea)>,
+}
+
+impl Connection {
+    pub fn cost(&self) -> i32 {
+        self.areas().map(|area| area.cell_dimensions().x * area.cell_dimensions().y).sum()
+    }
+
+    fn areas(&self) -> impl Iterator<Item = GridArea> + '_ {
+        self.legs.iter().map(|(area, _)| *area).chain(self.corner)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+struct Port {
+    area: GridArea,
+    step: IVec2,
+    target: Entity,
+    road: bool,
+}
+
+impl Port {
+    fn axis(&self) -> GAxis {
+        if self.step.y != 0 {
+            GAxis::Z
+        } else {
+            GAxis::X
+        }
+    }
+
+    fn extend(&self, length: i32) -> GridArea {
+        let offset = self.step * (length - 1);
+        self.area.union(GridArea::new(
+            GridCell::new(self.area.min.pos.x + offset.x, self.area.min.pos.y + offset.y),
+            GridCell::new(self.area.max.pos.x + offset.x, self.area.max.pos.y + offset.y),
+        ))
+    }
+
+    fn distance_to(&self, area: GridArea) -> i32 {
+        match (self.step.x, self.step.y) {
+            (0, 1) => area.min.pos.y - self.area.min.pos.y,
+            (0, _) => self.area.max.pos.y - area.max.pos.y,
+            (1, _) => area.min.pos.x - self.area.min.pos.x,
+            _ => self.area.max.pos.x - area.max.pos.x,
+        }
+    }
+}
+
+fn ports(target: Entity, area: GridArea, road: Option<GAxis>) -> Vec<Port> {
+    let (min, max) = (area.min.pos, area.max.pos);
+    let width = ASSIST_ROAD_WIDTH;
+    let mut ports = Vec::new();
+
+    if road != Some(GAxis::Z) {
+        for x in min.x..=max.x - width + 1 {
+            for (y, step) in [(max.y + 1, IVec2::Y), (min.y - 1, IVec2::NEG_Y)] {
+                let area = GridArea::new(GridCell::new(x, y), GridCell::new(x + width - 1, y));
+                ports.push(Port {
+                    area,
+                    step,
+                    target,
+                    road: road.is_some(),
+                });
+            }
+        }
+    }
+
+    if road != Some(GAxis::X) {
+        for y in min.y..=max.y - width + 1 {
+            for (x, step) in [(max.x + 1, IVec2::X), (min.x - 1, IVec2::NEG_X)] {
+                let area = GridArea::new(GridCell::new(x, y), GridCell::new(x, y + width - 1));
+                ports.push(Port {
+                    area,
+                    step,
+                    target,
+                    road: road.is_some(),
+                });
+            }
+        }
+    }
+
+    ports
+}
+
+fn straight_connection(a: &Port, b: &Port) -> Option<Connection> {
+    if a.step != -b.step {
+        return None;
+    }
+
+    let offset = b.area.min.pos - a.area.min.pos;
+    let along = offset.dot(a.step);
+    if offset != a.step * along || !(0..MAX_CONNECT_LENGTH).contains(&along) {
+        return None;
+    }
+
+    let leg = a.extend(along + 1);
+    Some(Connection {
+        legs: vec![(leg, a.axis())],
+        corner: None,
+        attachments: [a, b].into_iter().filter(|port| port.road).map(|port| (port.target, leg)).collect(),
+    })
+}
+
+fn l_connection(vertical: &Port, horizontal: &Port) -> Option<Connection> {
+    if vertical.step.y == 0 || horizontal.step.x == 0 {
+        return None;
+    }
+
+    let corner = GridArea::new(
+        GridCell::new(vertical.area.min.pos.x, horizontal.area.min.pos.y),
+        GridCell::new(vertical.area.max.pos.x, horizontal.area.max.pos.y),
+    );
+
+    let vertical_length = vertical.distance_to(corner);
+    let horizontal_length = horizontal.distance_to(corner);
+    if !(1..MAX_CONNECT_LENGTH).contains(&vertical_length) || !(1..MAX_CONNECT_LENGTH).contains(&horizontal_length) {
+        return None;
+    }
+
+    let vertical_leg = vertical.extend(vertical_length);
+    let horizontal_leg = horizontal.extend(horizontal_length);
+    let attachments = [(vertical, vertical_leg), (horizontal, horizontal_leg)]
+        .into_iter()
+        .filter(|(port, _)| port.road)
+        .map(|(port, leg)| (port.target, leg))
+        .collect();
+
+    Some(Connection {
+        legs: vec![(vertical_leg, GAxis::Z), (horizontal_leg, GAxis::X)],
+        corner: Some(corner),
+        attachments,
+    })
+}
+
+fn cheapest_connection(grid: &Grid, terrain: &Terrain, from: &[Port], to: &[Port]) -> Option<Connection> {
+    from.iter()
+        .flat_map(|a| to.iter().map(move |b| (a, b)))
+        .flat_map(|(a, b)| [straight_connection(a, b), l_connection(a, b), l_connection(b, a)])
+        .flatten()
+        .filter(|connection| connection.areas().all(|area| grid.is_valid_paint_area(area) && terrain.is_dry(area.iter())))
+        .min_by_key(|connection| connection.cost())
+}
+
+fn target_ports(entity: Entity, network: &RoadNetwork) -> Vec<Port> {
+    if let Some(building) = network.building(entity) {
+        ports(entity, building.area, None)
+    } else if let Some(segment) = network.segment(entity) {
+        if segment.is_straight() {
+            ports(entity, segment.area, Some(segment.orientation))
+        } else {
+            Vec::new()
+        }
+    } else {
+        Vec::new()
+    }
+}
+
+fn suggest_connection(tool: &ConnectTool, grid: &Grid, terrain: &Terrain, network: &RoadNetwork) -> Option<Connection> {
+    match tool.selection[..] {
+        [first, second] => cheapest_connection(grid, terrain, &target_ports(first, network), &target_ports(second, network)),
+        [building] if network.building(building).is_some_and(|building| building.roads.is_empty()) => {
+            let area = network.building(building)?.area;
+            let from = target_ports(building, network);
+            let reach = (MAX_CONNECT_LENGTH + ASSIST_ROAD_WIDTH) as f32;
+
+            grid.entities_in_radius(area.center(), reach + area.dimensions().length())
+                .into_iter()
+                .filter(|&entity| network.segment(entity).is_some_and(|segment| segment.is_straight()))
+                .filter_map(|entity| cheapest_connection(grid, terrain, &from, &target_ports(entity, network)))
+                .min_by_key(|connection| connection.cost())
+        }
+        _ => None,
+    }
+}
+
+fn select_connect_targets(
+    cursor: CursorPick,
+    grid_query: Query<&Grid>,
+    network: RoadNetwork,
+    actions: Actions,
+    mut tool: ResMut<ConnectTool>,
+) {
+    if actions.just_pressed(Action::Cancel) {
+        tool.selection.clear();
+        tool.suggestion = None;
+    }
+
+    if !actions.primary_just_pressed() || actions.mouse_modifier_held() {
+        return;
+    }
+
+    let Some(point) = cursor.ground() else {
+        return;
+    };
+
+    let grid = grid_query.single();
+    let Some(entity) = grid
+        .entity_at(GridCell::at(point))
+        .ok()
+        .flatten()
+        .filter(|&entity| network.building(entity).is_some() || network.segment(entity).is_some())
+    else {
+        return;
+    };
+
+    if tool.selection.len() >= 2 || tool.selection.contains(&entity) {
+        tool.selection.clear();
+    }
+
+    tool.selection.push(entity);
+    tool.suggestion = suggest_connection(&tool, grid, cursor.terrain(), &network);
+}
+
+fn build_suggested_connection(
+    actions: Actions,
+    mut tool: ResMut<ConnectTool>,
+    mut creator: EventWriter<RequestRoad>,
+    mut tee: EventWriter<RequestTeeJunction>,
+    mut intersector: EventWriter<RequestIntersection>,
+    mut built: EventWriter<OnRoadBuilt>,
+    mut payment: Payment,
+) {
+    if !actions.just_pressed(Action::Confirm) {
+        return;
+    }
+
+    let Some(cost) = tool.suggestion.as_ref().map(|connection| road_cost(connection.cost() as i64)) else {
+        return;
+    };
+
+    if !payment.can_afford(cost) {
+        payment.reject(cost);
+        return;
+    }
+
+    let Some(connection) = tool.suggestion.take() else {
+        return;
+    };
+
+    for &(entity, leg) in &connection.attachments {
+        tee.send(RequestTeeJunction::new(entity, leg));
+    }
+
+    if let Some(corner) = connection.corner {
+        intersector.send(RequestIntersection::new(corner));
+    }
+
+    for &(leg, orientation) in &connection.legs {
+        creator.send(RequestRoad::new(leg, orientation).under_construction());
+    }
+
+    payment.spend(cost);
+    built.send(OnRoadBuilt);
+    tool.selection.clear();
+}
+
+fn clear_connect_tool(mut tool: ResMut<ConnectTool>) {
+    tool.selection.clear();
+    tool.suggestion = None;
+}
+
+fn visualize_connect_tool(tool: Res<ConnectTool>, network: RoadNetwork, funds: Res<Funds>, mut gizmos: Gizmos) {
+    let mut outline = |area: GridArea, height: f32, color: Color| {
+        gizmos.cuboid(
+            Transform::from_translation(area.center().with_y(height / 2.0)).with_scale(Vec3::new(
+                area.dimensions().x,
+                height,
+                area.dimensions().y,
+            )),
+            color,
+        );
+    };
+
+    for &entity in &tool.selection {
+        if let Some(building) = network.building(entity) {
+            outline(building.area, 1.0, SELECTED_COLOR);
+        } else if let Some(segment) = network.segment(entity) {
+            outline(segment.area, 0.2, SELECTED_COLOR);
+        }
+    }
+
+    if let Some(connection) = &tool.suggestion {
+        let color = match funds.can_afford(road_cost(connection.cost() as i64)) {
+            true => SUGGESTION_COLOR,
+            false => UNAFFORDABLE_COLOR,
+        };
+
+        for area in connection.areas() {
+            outline(area, 0.1, color);
+        }
+    }
+}
@@ -0,0 +1,96 @@
+use crate::{
+    graphics::camera::*,
+    grid::{grid::*, grid_cell::GridCell},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::context_menu_events::*,
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+
+const CLICK_SLOP_PIXELS: f32 = 4.0;
+
+pub struct ContextMenuPlugin;
+
+impl Plugin for ContextMenuPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ContextMenu::default()).add_event::<RequestDemolish>().add_systems(
+            Update,
+            (
+                track_right_press.in_set(UpdateStage::UpdateView).run_if(in_state(MouseOver::World)),
+                open_context_menu.in_set(UpdateStage::UserInput),
+            ),
+        );
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct ContextTarget {
+    pub screen_position: Vec2,
+    pub cell: GridCell,
+    pub entity: Option<Entity>,
+}
+
+// Right clicks that do not turn into a camera pan open a menu for whatever is under the cursor.
+// Tools that are mid-drag take the press to cancel the drag instead, see `consume_press`.
+#[derive(Resource, Debug, Default)]
+pub struct ContextMenu {
+    press: Option<Vec2>,
+    pub target: Option<ContextTarget>,
+}
+
+impl ContextMenu {
+    pub fn consume_press(&mut self) {
+        self.press = None;
+    }
+
+    pub fn close(&mut self) {
+        self.target = None;
+    }
+}
+
+fn track_right_press(actions: Actions, mut menu: ResMut<ContextMenu>) {
+    if actions.secondary_just_pressed() {
+        menu.press = actions.pointer_position();
+    }
+}
+
+fn open_context_menu(
+    cursor: CursorPick,
+    grid_query: Query<&Grid>,
+    actions: Actions,
+    mouse_over: Option<Res<State<MouseOver>>>,
+    mut menu: ResMut<ContextMenu>,
+) {
+    if actions.just_pressed(Action::Cancel) {
+        menu.close();
+    }
+
+    if !actions.secondary_just_released() {
+        return;
+    }
+
+    let Some(press) = menu.press.take() else {
+        return;
+    };
+
+    let Some(cursor_position) = actions.pointer_position() else {
+        return;
+    };
+
+    let over_world = mouse_over.is_some_and(|state| *state.get() == MouseOver::World);
+    if !over_world || cursor_position.distance(press) > CLICK_SLOP_PIXELS {
+        return;
+    }
+
+    let Some(point) = cursor.ground() else {
+        return;
+    };
+
+    let cell = GridCell::at(point);
+    menu.target = Some(ContextTarget {
+        screen_position: cursor_position,
+        cell,
+        entity: grid_query.single().entity_at(cell).ok().flatten(),
+    });
+}
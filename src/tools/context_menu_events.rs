@@ -0,0 +1,8 @@
+use crate::grid::grid_area::GridArea;
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct RequestDemolish {
+    pub entity: Entity,
+    pub area: GridArea,
+}
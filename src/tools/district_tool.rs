@@ -0,0 +1,154 @@
+use crate::{
+    graphics::camera::*,
+    grid::{
+        grid_area::*,
+        ground::{GroundLayer, GroundPaint},
+    },
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::{context_menu::ContextMenu, toolbar::ToolState},
+    types::district::{Districts, RequestDistrictPaint},
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+pub struct DistrictToolPlugin;
+
+impl Plugin for DistrictToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, spawn_tool).add_systems(
+            Update,
+            (
+                (
+                    (update_ground_position).in_set(UpdateStage::UpdateView).run_if(in_state(MouseOver::World)),
+                    (adjust_tool_size, handle_tool_action).in_set(UpdateStage::UserInput).run_if(in_state(MouseOver::World)),
+                )
+                    .run_if(in_state(ToolState::District)),
+                show_overlay.in_set(UpdateStage::Visualize).run_if(state_changed::<ToolState>),
+            ),
+        );
+    }
+}
+
+// Paints the selected district over the dragged out area, or clears districts there when none is
+// selected.
+#[derive(Component, Debug)]
+pub struct DistrictTool {
+    pub selected: Option<u32>,
+    dimensions: IVec2,
+    ground_position: Vec3,
+    dragging: bool,
+    drag_start_ground_position: Vec3,
+}
+
+impl DistrictTool {
+    fn new() -> Self {
+        Self {
+            selected: None,
+            dimensions: IVec2::ONE,
+            ground_position: Vec3::ZERO,
+            dragging: false,
+            drag_start_ground_position: Vec3::ZERO,
+        }
+    }
+
+    fn area(&self) -> GridArea {
+        let area = GridArea::at(self.ground_position, self.dimensions.x, self.dimensions.y);
+
+        if self.dragging {
+            area.union(GridArea::at(
+                self.drag_start_ground_position,
+                self.dimensions.x,
+                self.dimensions.y,
+            ))
+        } else {
+            area
+        }
+    }
+}
+
+fn spawn_tool(mut commands: Commands) {
+    commands.spawn(DistrictTool::new());
+}
+
+fn show_overlay(state: Res<State<ToolState>>, mut paint: ResMut<GroundPaint>) {
+    paint.show(GroundLayer::District, *state.get() == ToolState::District);
+}
+
+fn update_ground_position(
+    cursor: CursorPick,
+    mut tool_query: Query<&mut DistrictTool>,
+    districts: Res<Districts>,
+    mut gizmos: Gizmos,
+) {
+    let mut tool = tool_query.single_mut();
+
+    if let Some(point) = cursor.ground() {
+        tool.ground_position = point;
+        let area = tool.area();
+
+        let mut gizmo_color = match tool.selected.and_then(|id| districts.get(id)) {
+            Some(district) => Color::from(district.color().with_alpha(0.8)),
+            None => Color::linear_rgba(1.0, 1.0, 1.0, 0.5),
+        };
+
+        if cursor.camera_moving() {
+            gizmo_color = gizmo_color.with_alpha(0.25);
+        }
+
+        gizmos.rect(
+            area.center().with_y(cursor.terrain().bounds(area).1 + 0.01),
+            Quat::from_rotation_x(FRAC_PI_2),
+            area.dimensions(),
+            gizmo_color,
+        );
+    }
+}
+
+fn adjust_tool_size(mut query: Query<&mut DistrictTool>, actions: Actions) {
+    let mut tool = query.single_mut();
+
+    if actions.just_pressed(Action::AdjustToolUp) {
+        tool.dimensions.x += 1;
+        tool.dimensions.y += 1;
+    }
+    if actions.just_pressed(Action::AdjustToolDown) {
+        tool.dimensions.x -= 1;
+        tool.dimensions.y -= 1;
+    }
+
+    tool.dimensions = tool.dimensions.max(IVec2::new(1, 1));
+}
+
+fn handle_tool_action(
+    mut query: Query<&mut DistrictTool>,
+    actions: Actions,
+    mut painter: EventWriter<RequestDistrictPaint>,
+    mut menu: ResMut<ContextMenu>,
+) {
+    let mut tool = query.single_mut();
+
+    if tool.dragging && actions.secondary_just_pressed() {
+        tool.dragging = false;
+        menu.consume_press();
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
+        tool.dragging = true;
+        tool.drag_start_ground_position = tool.ground_position;
+    }
+
+    if actions.just_pressed(Action::Cancel) {
+        tool.dragging = false;
+    }
+
+    if tool.dragging && actions.primary_just_released() {
+        let area = tool.area();
+        tool.dragging = false;
+        painter.send(RequestDistrictPaint {
+            area,
+            district: tool.selected,
+        });
+    }
+}
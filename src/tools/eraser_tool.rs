@@ -2,12 +2,15 @@ use crate::{
     graph::road_graph_events::*,
     graphics::camera::*,
     grid::{grid::*, grid_area::*},
+    input::keymap::{Action, Actions},
     schedule::UpdateStage,
-    tools::toolbar::ToolState,
-    types::{building::*, intersection::*, road_segment::*},
+    tools::{
+        context_menu::ContextMenu, context_menu_events::RequestDemolish, road_events::RequestRoadSplit, toolbar::ToolState,
+    },
+    types::{building::*, intersection::*, prop::Prop, road_segment::*, water::WaterBody},
     ui::egui::MouseOver,
 };
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*, utils::HashSet};
 
 pub struct EraserToolPlugin;
 
@@ -21,10 +24,13 @@ impl Plugin for EraserToolPlugin {
                     (adjust_tool_size, handle_tool_action).in_set(UpdateStage::UserInput).run_if(in_state(MouseOver::World)),
                 )
                     .run_if(in_state(ToolState::Eraser)),
+                apply_demolish_requests.in_set(UpdateStage::HighLevelSideEffects),
                 (
                     despawn_erased_entities::<OnRoadDestroyed>,
                     despawn_erased_entities::<OnIntersectionDestroyed>,
                     despawn_erased_entities::<OnBuildingDestroyed>,
+                    despawn_erased_entities::<OnWaterDestroyed>,
+                    despawn_erased_entities::<OnPropDestroyed>,
                 )
                     .in_set(UpdateStage::DestroyEntities),
             ),
@@ -36,6 +42,8 @@ impl Plugin for EraserToolPlugin {
 pub struct EraserTool {
     dimensions: IVec2,
     ground_position: Vec3,
+    dragging: bool,
+    drag_start_ground_position: Vec3,
 }
 
 impl EraserTool {
@@ -43,6 +51,22 @@ impl EraserTool {
         Self {
             dimensions: IVec2::ONE,
             ground_position: Vec3::ZERO,
+            dragging: false,
+            drag_start_ground_position: Vec3::ZERO,
+        }
+    }
+
+    fn area(&self) -> GridArea {
+        let area = GridArea::at(self.ground_position, self.dimensions.x, self.dimensions.y);
+
+        if self.dragging {
+            area.union(GridArea::at(
+                self.drag_start_ground_position,
+                self.dimensions.x,
+                self.dimensions.y,
+            ))
+        } else {
+            area
         }
     }
 }
@@ -51,43 +75,23 @@ fn spawn_tool(mut commands: Commands) {
     commands.spawn(EraserTool::new());
 }
 
-fn update_ground_position(
-    camera_query: Query<(&Camera, &PlayerCameraController, &GlobalTransform)>,
-    mut tool_query: Query<&mut EraserTool>,
-    ground_query: Query<&GlobalTransform, With<Ground>>,
-    windows: Query<&Window>,
-    mut gizmos: Gizmos,
-) {
-    let (camera, controller, camera_transform) = camera_query.single();
+fn update_ground_position(cursor: CursorPick, mut tool_query: Query<&mut EraserTool>, mut gizmos: Gizmos) {
     let mut tool = tool_query.single_mut();
-    let ground = ground_query.single();
-
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
-
-    let Some(cursor_position) = window.cursor_position() else {
-        return;
-    };
-
-    let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
-        return;
-    };
 
-    if let Some(distance) = ray.intersect_plane(ground.translation(), InfinitePlane3d::new(ground.up())) {
-        let point = ray.get_point(distance);
+    if let Some(point) = cursor.ground() {
         tool.ground_position = point;
-        let area = GridArea::at(tool.ground_position, tool.dimensions.x, tool.dimensions.y);
+        let area = tool.area();
         let mut gizmo_color = Color::linear_rgba(1.0, 1.0, 0.0, 0.8);
 
-        if controller.is_moving() {
+        if cursor.camera_moving() {
             gizmo_color = gizmo_color.with_alpha(0.25);
         }
 
+        let (low, high) = cursor.terrain().bounds(area);
         gizmos.cuboid(
-            Transform::from_translation(area.center().with_y(0.5)).with_scale(Vec3::new(
+            Transform::from_translation(area.center().with_y((low + high + 1.0) / 2.0)).with_scale(Vec3::new(
                 area.dimensions().x,
-                1.0,
+                high - low + 1.0,
                 area.dimensions().y,
             )),
             gizmo_color,
@@ -95,14 +99,14 @@ fn update_ground_position(
     }
 }
 
-fn adjust_tool_size(mut query: Query<&mut EraserTool>, keyboard: Res<ButtonInput<KeyCode>>) {
+fn adjust_tool_size(mut query: Query<&mut EraserTool>, actions: Actions) {
     let mut tool = query.single_mut();
 
-    if keyboard.just_pressed(KeyCode::KeyR) {
+    if actions.just_pressed(Action::AdjustToolUp) {
         tool.dimensions.x += 1;
         tool.dimensions.y += 1;
     }
-    if keyboard.just_pressed(KeyCode::KeyF) {
+    if actions.just_pressed(Action::AdjustToolDown) {
         tool.dimensions.x -= 1;
         tool.dimensions.y -= 1;
     }
@@ -111,32 +115,84 @@ fn adjust_tool_size(mut query: Query<&mut EraserTool>, keyboard: Res<ButtonInput
 }
 
 fn handle_tool_action(
-    query: Query<&mut EraserTool>,
+    mut query: Query<&mut EraserTool>,
     grid_query: Query<&Grid>,
-    segment_query: Query<&RoadSegment>,
-    inter_query: Query<&Intersection>,
-    building_query: Query<&Building>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    mut segment_event: EventWriter<OnRoadDestroyed>,
-    mut inter_event: EventWriter<OnIntersectionDestroyed>,
-    mut building_event: EventWriter<OnBuildingDestroyed>,
+    water_query: Query<(Entity, &WaterBody)>,
+    actions: Actions,
+    mut demolisher: EventWriter<RequestDemolish>,
+    mut menu: ResMut<ContextMenu>,
 ) {
-    let tool = query.single();
+    let mut tool = query.single_mut();
     let grid = grid_query.single();
 
-    if mouse.just_pressed(MouseButton::Left) && !keyboard.any_pressed([KeyCode::AltLeft, KeyCode::ControlLeft]) {
-        let area = GridArea::at(tool.ground_position, tool.dimensions.x, tool.dimensions.y);
-
-        for cell in area.iter() {
-            if let Ok(Some(entity)) = grid.entity_at(cell) {
-                if building_query.contains(entity) {
-                    building_event.send(OnBuildingDestroyed(entity));
-                } else if segment_query.contains(entity) {
-                    segment_event.send(OnRoadDestroyed(entity));
-                } else if inter_query.contains(entity) {
-                    inter_event.send(OnIntersectionDestroyed(entity));
-                }
+    if tool.dragging && actions.secondary_just_pressed() {
+        tool.dragging = false;
+        menu.consume_press();
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
+        tool.dragging = true;
+        tool.drag_start_ground_position = tool.ground_position;
+    }
+
+    if actions.just_pressed(Action::Cancel) {
+        tool.dragging = false;
+    }
+
+    if tool.dragging && actions.primary_just_released() {
+        let area = tool.area();
+        tool.dragging = false;
+
+        let mut touched: HashSet<Entity> = area.iter().filter_map(|cell| grid.entity_at(cell).ok().flatten()).collect();
+        touched.extend(water_query.iter().filter(|(_, water)| water.area().overlaps(area)).map(|(entity, _)| entity));
+
+        for entity in touched {
+            demolisher.send(RequestDemolish { entity, area });
+        }
+    }
+}
+
+// Everything the eraser can remove.
+#[derive(SystemParam)]
+struct Erasable<'w, 's> {
+    grid_query: Query<'w, 's, &'static Grid>,
+    segment_query: Query<'w, 's, &'static RoadSegment>,
+    inter_query: Query<'w, 's, &'static Intersection>,
+    building_query: Query<'w, 's, &'static Building>,
+    water_query: Query<'w, 's, &'static WaterBody>,
+    prop_query: Query<'w, 's, (), With<Prop>>,
+}
+
+// The events that remove each kind of thing.
+#[derive(SystemParam)]
+struct Demolitions<'w> {
+    segment_event: EventWriter<'w, OnRoadDestroyed>,
+    inter_event: EventWriter<'w, OnIntersectionDestroyed>,
+    building_event: EventWriter<'w, OnBuildingDestroyed>,
+    water_event: EventWriter<'w, OnWaterDestroyed>,
+    prop_event: EventWriter<'w, OnPropDestroyed>,
+    splitter: EventWriter<'w, RequestRoadSplit>,
+}
+
+// Straight roads only lose the part inside `area`, everything else is removed whole. Water stays
+// while anything is built over it, so bridges are never left standing on dry ground.
+fn apply_demolish_requests(mut requests: EventReader<RequestDemolish>, erasable: Erasable, mut demolitions: Demolitions) {
+    for &RequestDemolish { entity, area } in requests.read() {
+        if erasable.building_query.contains(entity) {
+            demolitions.building_event.send(OnBuildingDestroyed(entity));
+        } else if erasable.prop_query.contains(entity) {
+            demolitions.prop_event.send(OnPropDestroyed(entity));
+        } else if let Ok(segment) = erasable.segment_query.get(entity) {
+            if segment.is_straight() {
+                demolitions.splitter.send(RequestRoadSplit::new(entity, area));
+            } else {
+                demolitions.segment_event.send(OnRoadDestroyed(entity));
+            }
+        } else if erasable.inter_query.contains(entity) {
+            demolitions.inter_event.send(OnIntersectionDestroyed(entity));
+        } else if let Ok(water) = erasable.water_query.get(entity) {
+            if erasable.grid_query.single().is_valid_paint_area(water.area()) {
+                demolitions.water_event.send(OnWaterDestroyed(entity));
             }
         }
     }
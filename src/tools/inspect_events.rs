@@ -0,0 +1,25 @@
+use bevy::prelude::*;
+
+#[derive(Event, Debug)]
+pub struct RequestSpeedLimit {
+    pub entity: Entity,
+    pub limit: Option<f32>,
+}
+
+// `exit` indexes the road's `ends`; None makes the road two-way again.
+#[derive(Event, Debug)]
+pub struct RequestOneWay {
+    pub entity: Entity,
+    pub exit: Option<usize>,
+}
+
+#[derive(Event, Debug)]
+pub struct RequestVehicleDespawn(pub Entity);
+
+#[derive(Event, Debug)]
+pub struct RequestTurnRestriction {
+    pub entity: Entity,
+    pub from: usize,
+    pub to: usize,
+    pub restricted: bool,
+}
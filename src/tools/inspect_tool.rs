@@ -0,0 +1,144 @@
+use crate::{
+    graph::{path_cache::PathCache, road_network::RoadNetwork},
+    schedule::UpdateStage,
+    tools::{inspect_events::*, selection::Selection, toolbar::ToolState},
+    types::{intersection::Intersection, road_segment::RoadSegment, vehicle::Vehicle},
+};
+use bevy::prelude::*;
+
+const SELECTED_COLOR: Color = Color::linear_rgba(1.0, 1.0, 1.0, 0.8);
+const PATH_COLOR: Color = Color::linear_rgba(1.0, 0.8, 0.2, 0.8);
+
+pub struct InspectToolPlugin;
+
+impl Plugin for InspectToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestSpeedLimit>()
+            .add_event::<RequestOneWay>()
+            .add_event::<RequestVehicleDespawn>()
+            .add_event::<RequestTurnRestriction>()
+            .add_systems(
+                Update,
+                (
+                    (
+                        apply_speed_limits,
+                        apply_one_way_roads,
+                        apply_turn_restrictions,
+                        despawn_inspected_vehicles,
+                    )
+                        .in_set(UpdateStage::HighLevelSideEffects),
+                    visualize_inspect_target.in_set(UpdateStage::Visualize).run_if(in_state(ToolState::Inspect)),
+                ),
+            );
+    }
+}
+
+fn apply_speed_limits(mut event: EventReader<RequestSpeedLimit>, mut segment_query: Query<&mut RoadSegment>) {
+    for &RequestSpeedLimit { entity, limit } in event.read() {
+        if let Ok(mut segment) = segment_query.get_mut(entity) {
+            segment.speed_limit_override = limit;
+        }
+    }
+}
+
+// Like turn restrictions, cached routes may now drive against the traffic and are dropped.
+fn apply_one_way_roads(
+    mut event: EventReader<RequestOneWay>,
+    mut segment_query: Query<&mut RoadSegment>,
+    mut cache: ResMut<PathCache>,
+) {
+    for &RequestOneWay { entity, exit } in event.read() {
+        let Ok(mut segment) = segment_query.get_mut(entity) else {
+            continue;
+        };
+
+        if segment.one_way != exit {
+            segment.one_way = exit;
+            cache.clear();
+        }
+    }
+}
+
+// Cached routes may take a turn that has just been restricted, so they are all dropped. Vehicles
+// already on their way finish the route they have.
+fn apply_turn_restrictions(
+    mut event: EventReader<RequestTurnRestriction>,
+    mut inter_query: Query<&mut Intersection>,
+    mut cache: ResMut<PathCache>,
+) {
+    for &RequestTurnRestriction {
+        entity,
+        from,
+        to,
+        restricted,
+    } in event.read()
+    {
+        let Ok(mut intersection) = inter_query.get_mut(entity) else {
+            continue;
+        };
+
+        let changed = match restricted {
+            true => intersection.restricted_turns.insert((from, to)),
+            false => intersection.restricted_turns.remove(&(from, to)),
+        };
+
+        if changed {
+            cache.clear();
+        }
+    }
+}
+
+fn despawn_inspected_vehicles(
+    mut commands: Commands,
+    mut event: EventReader<RequestVehicleDespawn>,
+    vehicle_query: Query<(), With<Vehicle>>,
+) {
+    for &RequestVehicleDespawn(entity) in event.read() {
+        if vehicle_query.contains(entity) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
+
+fn visualize_inspect_target(
+    selection: Res<Selection>,
+    network: RoadNetwork,
+    vehicle_query: Query<(&Vehicle, &Transform)>,
+    mut gizmos: Gizmos,
+) {
+    let Some(entity) = selection.entity else {
+        return;
+    };
+
+    let mut outline = |center: Vec3, size: Vec3| {
+        gizmos.cuboid(Transform::from_translation(center).with_scale(size), SELECTED_COLOR);
+    };
+
+    if let Ok((vehicle, transform)) = vehicle_query.get(entity) {
+        outline(transform.translation, Vec3::splat(0.6));
+
+        let remaining = vehicle.path.iter().skip(vehicle.path_index + 1).filter_map(|&step| network.position(step));
+        gizmos.linestrip(
+            std::iter::once(transform.translation).chain(remaining.map(|pos| pos.with_y(transform.translation.y))),
+            PATH_COLOR,
+        );
+    } else if let Some(building) = network.building(entity) {
+        let area = building.area;
+        outline(
+            area.center().with_y(0.5),
+            Vec3::new(area.dimensions().x, 1.0, area.dimensions().y),
+        );
+    } else if let Some(segment) = network.segment(entity) {
+        let area = segment.area;
+        outline(
+            area.center().with_y(0.1),
+            Vec3::new(area.dimensions().x, 0.2, area.dimensions().y),
+        );
+    } else if let Some(intersection) = network.intersection(entity) {
+        let area = intersection.area;
+        outline(
+            area.center().with_y(0.1),
+            Vec3::new(area.dimensions().x, 0.2, area.dimensions().y),
+        );
+    }
+}
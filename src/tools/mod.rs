@@ -1,6 +1,19 @@
+pub mod blueprint_tool;
 pub mod building_tool;
+pub mod connect_tool;
+pub mod context_menu;
+pub mod context_menu_events;
+pub mod district_tool;
 pub mod eraser_tool;
+pub mod inspect_events;
+pub mod inspect_tool;
+pub mod prop_tool;
 pub mod road_events;
 pub mod road_tool;
+pub mod selection;
 pub mod toolbar;
 pub mod toolbar_events;
+pub mod transit_tool;
+pub mod vehicle_debug;
+pub mod view_tool;
+pub mod water_tool;
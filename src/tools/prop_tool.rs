@@ -0,0 +1,250 @@
+use crate::{
+    graph::road_graph_events::*,
+    graphics::{
+        camera::*,
+        models::Models,
+        particles::{ParticleEmitter, ParticleStyle},
+    },
+    grid::{grid::*, grid_area::*, grid_cell::GridCell, terrain::Terrain},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::{context_menu::ContextMenu, toolbar::ToolState},
+    types::{
+        prop::{Prop, PropKind},
+        road_segment::RoadSegment,
+    },
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+const PROP_GAP: i32 = 1;
+const MAX_DRAG_PROPS: usize = 128;
+
+pub struct PropToolPlugin;
+
+impl Plugin for PropToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestProp>()
+            .add_event::<OnPropSpawned>()
+            .add_event::<OnPropDestroyed>()
+            .add_systems(Startup, spawn_tool)
+            .add_systems(
+                Update,
+                (
+                    (
+                        (update_ground_position).in_set(UpdateStage::UpdateView).run_if(in_state(MouseOver::World)),
+                        (select_prop_kind, handle_tool_action)
+                            .in_set(UpdateStage::UserInput)
+                            .run_if(in_state(MouseOver::World)),
+                    )
+                        .run_if(in_state(ToolState::Props)),
+                    spawn_props.in_set(UpdateStage::Spawning),
+                ),
+            );
+    }
+}
+
+// Places one prop on a click, or a row of them spaced `PROP_GAP` apart when dragged, which is how
+// streets get lined with trees.
+#[derive(Component, Debug)]
+pub struct PropTool {
+    pub kind: PropKind,
+    ground_position: Vec3,
+    dragging: bool,
+    drag_start_ground_position: Vec3,
+}
+
+impl PropTool {
+    fn new() -> Self {
+        Self {
+            kind: PropKind::Tree,
+            ground_position: Vec3::ZERO,
+            dragging: false,
+            drag_start_ground_position: Vec3::ZERO,
+        }
+    }
+
+    fn lots(&self) -> Vec<GridArea> {
+        let size = self.kind.size();
+        let end = GridArea::at(self.ground_position, size.x, size.y);
+        if !self.dragging {
+            return vec![end];
+        }
+
+        let start = GridArea::at(self.drag_start_ground_position, size.x, size.y);
+        let step = size + IVec2::splat(PROP_GAP);
+        let span = end.min.pos - start.min.pos;
+        let count = (span.abs() / step).max_element() as usize + 1;
+
+        // Rows follow whichever way the drag went furthest.
+        let direction = match span.x.abs() >= span.y.abs() {
+            true => IVec2::new(span.x.signum() * step.x, 0),
+            false => IVec2::new(0, span.y.signum() * step.y),
+        };
+
+        (0..count.min(MAX_DRAG_PROPS) as i32)
+            .map(|index| {
+                let min = start.min.pos + direction * index;
+                GridArea::new(
+                    GridCell { pos: min },
+                    GridCell {
+                        pos: min + size - IVec2::ONE,
+                    },
+                )
+            })
+            .collect()
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct RequestProp {
+    pub area: GridArea,
+    pub kind: PropKind,
+}
+
+impl RequestProp {
+    pub fn new(area: GridArea, kind: PropKind) -> Self {
+        Self { area, kind }
+    }
+}
+
+fn spawn_tool(mut commands: Commands) {
+    commands.spawn(PropTool::new());
+}
+
+fn fits(area: GridArea, grid: &Grid, terrain: &Terrain) -> bool {
+    grid.is_valid_paint_area(area) && terrain.is_dry(area.iter())
+}
+
+fn update_ground_position(
+    cursor: CursorPick,
+    mut tool_query: Query<&mut PropTool>,
+    grid_query: Query<&Grid>,
+    mut gizmos: Gizmos,
+) {
+    let mut tool = tool_query.single_mut();
+    let terrain = cursor.terrain();
+
+    if let Some(point) = cursor.ground() {
+        tool.ground_position = point;
+
+        for area in tool.lots() {
+            let mut gizmo_color = if fits(area, grid_query.single(), terrain) {
+                Color::linear_rgba(0.2, 1.0, 0.3, 0.8)
+            } else {
+                Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
+            };
+
+            if cursor.camera_moving() {
+                gizmo_color = gizmo_color.with_alpha(0.25);
+            }
+
+            gizmos.rect(
+                area.center().with_y(terrain.bounds(area).1 + 0.01),
+                Quat::from_rotation_x(FRAC_PI_2),
+                area.dimensions(),
+                gizmo_color,
+            );
+        }
+    }
+}
+
+fn select_prop_kind(mut query: Query<&mut PropTool>, actions: Actions) {
+    let mut tool = query.single_mut();
+
+    if actions.just_pressed(Action::PlaceHouse) {
+        tool.kind = PropKind::Tree;
+    } else if actions.just_pressed(Action::PlaceShop) {
+        tool.kind = PropKind::Bench;
+    } else if actions.just_pressed(Action::PlaceOffice) {
+        tool.kind = PropKind::Fountain;
+    }
+}
+
+fn handle_tool_action(
+    mut query: Query<&mut PropTool>,
+    actions: Actions,
+    mut placer: EventWriter<RequestProp>,
+    mut menu: ResMut<ContextMenu>,
+) {
+    let mut tool = query.single_mut();
+
+    if tool.dragging && actions.secondary_just_pressed() {
+        tool.dragging = false;
+        menu.consume_press();
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
+        tool.dragging = true;
+        tool.drag_start_ground_position = tool.ground_position;
+    }
+
+    if actions.just_pressed(Action::Cancel) {
+        tool.dragging = false;
+    }
+
+    if tool.dragging && actions.primary_just_released() {
+        let lots = tool.lots();
+        tool.dragging = false;
+
+        let kind = tool.kind;
+        placer.send_batch(lots.into_iter().map(|area| RequestProp::new(area, kind)));
+    }
+}
+
+// Benches turn to face the first road beside them. Everything else faces the same way.
+fn facing(area: GridArea, grid: &Grid, segment_query: &Query<(), With<RoadSegment>>) -> Quat {
+    area.adjacent_areas()
+        .find(|(side, _)| {
+            side.iter().any(|cell| grid.entity_at(cell).ok().flatten().is_some_and(|e| segment_query.contains(e)))
+        })
+        .map(|(_, dir)| Quat::from_rotation_arc(Vec3::Z, dir.as_vec3()))
+        .unwrap_or_default()
+}
+
+fn spawn_props(
+    mut commands: Commands,
+    mut grid_query: Query<&mut Grid>,
+    segment_query: Query<(), With<RoadSegment>>,
+    mut requests: EventReader<RequestProp>,
+    mut event: EventWriter<OnPropSpawned>,
+    models: Res<Models>,
+    terrain: Res<Terrain>,
+) {
+    let mut grid = grid_query.single_mut();
+
+    for &RequestProp { area, kind } in requests.read() {
+        if !fits(area, &grid, &terrain) {
+            continue;
+        }
+
+        let rotation = match kind {
+            PropKind::Bench => facing(area, &grid, &segment_query),
+            _ => Quat::IDENTITY,
+        };
+        let transform = Transform::from_translation(area.center().with_y(terrain.bounds(area).0)).with_rotation(rotation);
+
+        let parts = models.prop(kind);
+        let entity = commands
+            .spawn((SpatialBundle::from_transform(transform), Prop::new(area, kind)))
+            .with_children(|parent| {
+                for (index, (mesh, material)) in parts.iter().enumerate() {
+                    let mut part = parent.spawn(PbrBundle {
+                        mesh: mesh.clone(),
+                        material: material.clone(),
+                        ..default()
+                    });
+
+                    // The spout is the last part of a fountain.
+                    if kind == PropKind::Fountain && index == parts.len() - 1 {
+                        part.insert(ParticleEmitter::new(ParticleStyle::Spray, Vec3::Y * 0.4));
+                    }
+                }
+            })
+            .id();
+
+        grid.mark_area_occupied(area, entity);
+        event.send(OnPropSpawned(entity));
+    }
+}
@@ -1,15 +1,42 @@
-use crate::{grid::grid_area::*, grid::orientation::*};
+use crate::{
+    grid::grid_area::*,
+    grid::orientation::*,
+    types::road_segment::{RoadShape, RoadSurface},
+};
 use bevy::prelude::*;
 
 #[derive(Event, Debug)]
 pub struct RequestRoad {
     pub area: GridArea,
     pub orientation: GAxis,
+    pub shape: RoadShape,
+    pub surface: RoadSurface,
+    pub construction: bool,
 }
 
 impl RequestRoad {
     pub fn new(area: GridArea, orientation: GAxis) -> Self {
-        Self { area, orientation }
+        Self::shaped(area, orientation, RoadShape::Straight)
+    }
+
+    pub fn shaped(area: GridArea, orientation: GAxis, shape: RoadShape) -> Self {
+        Self {
+            area,
+            orientation,
+            shape,
+            surface: RoadSurface::Asphalt,
+            construction: false,
+        }
+    }
+
+    pub fn with_surface(mut self, surface: RoadSurface) -> Self {
+        self.surface = surface;
+        self
+    }
+
+    pub fn under_construction(mut self) -> Self {
+        self.construction = true;
+        self
     }
 }
 
@@ -36,15 +63,51 @@ impl RequestRoadSplit {
     }
 }
 
+// A road ending against the side of `road`. The road is split around where `branch` meets it and a
+// three-way intersection fills the gap.
+#[derive(Event, Debug)]
+pub struct RequestTeeJunction {
+    pub road: Entity,
+    pub branch: GridArea,
+}
+
+impl RequestTeeJunction {
+    pub fn new(road: Entity, branch: GridArea) -> Self {
+        Self { road, branch }
+    }
+}
+
+// How a road meets a new stretch lined up with its end: merged into one longer road, or left as it
+// is with an intersection carved out of that end for the new stretch to start from.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum RoadJoin {
+    #[default]
+    Merge,
+    Junction,
+}
+
 #[derive(Event, Debug)]
 pub struct RequestRoadExtend {
     pub entity: Entity,
     pub extension: GridArea,
+    pub join: RoadJoin,
 }
 
 impl RequestRoadExtend {
     pub fn new(entity: Entity, extension: GridArea) -> Self {
-        Self { entity, extension }
+        Self {
+            entity,
+            extension,
+            join: RoadJoin::Merge,
+        }
+    }
+
+    pub fn junction(entity: Entity, extension: GridArea) -> Self {
+        Self {
+            entity,
+            extension,
+            join: RoadJoin::Junction,
+        }
     }
 }
 
@@ -59,3 +122,30 @@ impl RequestRoadBridge {
         Self { first, second }
     }
 }
+
+#[derive(Event, Debug)]
+pub struct RequestRoadSurface {
+    pub area: GridArea,
+    pub surface: RoadSurface,
+}
+
+impl RequestRoadSurface {
+    pub fn new(area: GridArea, surface: RoadSurface) -> Self {
+        Self { area, surface }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct RequestRoadUpgrade {
+    pub entity: Entity,
+    pub area: GridArea,
+}
+
+#[derive(Event, Debug)]
+pub struct OnRoadResurfaced(pub Entity);
+
+#[derive(Event, Debug)]
+pub struct OnRoadUpgraded(pub Entity);
+
+#[derive(Event, Debug)]
+pub struct OnRoadBuilt;
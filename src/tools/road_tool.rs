@@ -1,21 +1,40 @@
 use crate::{
+    economy::economy::*,
     graph::road_graph_events::*,
-    graphics::camera::*,
-    grid::{grid::*, grid_area::*, grid_cell::*, orientation::*},
+    graphics::{
+        camera::*,
+        models::{add_render_asset, load_render_asset, Models},
+    },
+    grid::{alignment::RoadAlignment, grid::*, grid_area::*, grid_cell::*, orientation::*, terrain::Terrain},
+    input::keymap::{Action, Actions},
     schedule::UpdateStage,
-    tools::{road_events::*, toolbar::ToolState},
-    types::{intersection::*, road_segment::*},
+    tools::{context_menu::ContextMenu, road_events::*, toolbar::ToolState},
+    types::{building::Building, intersection::*, road_segment::*, work_zone::*},
     ui::egui::MouseOver,
 };
 use bevy::{
-    math::Affine2,
+    ecs::system::SystemParam,
     prelude::*,
-    render::texture::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor},
+    render::{
+        mesh::{Indices, PrimitiveTopology},
+        render_asset::RenderAssetUsages,
+        texture::{ImageAddressMode, ImageLoaderSettings, ImageSampler, ImageSamplerDescriptor},
+    },
 };
 use std::f32::consts::FRAC_PI_2;
 
 pub const ROAD_HEIGHT: f32 = 0.05;
 pub const ROAD_TEXTURE_STRETCH: f32 = 5.0;
+const MAX_ROAD_WIDTH: i32 = 6;
+pub const MAX_ROAD_GRADE: f32 = 0.1;
+const DECK_THICKNESS: f32 = 0.12;
+const PILLAR_SPACING: f32 = 3.0;
+const PILLAR_SIZE: f32 = 0.2;
+const PILLAR_INSET: f32 = 0.7;
+const SNAP_CELLS: i32 = 2;
+const GUIDE_LIFT: f32 = 0.03;
+const EDGE_GUIDE_COLOR: Color = Color::linear_rgba(0.2, 0.8, 1.0, 0.7);
+const CENTER_GUIDE_COLOR: Color = Color::linear_rgba(1.0, 0.8, 0.2, 0.7);
 
 pub struct RoadToolPlugin;
 
@@ -25,25 +44,60 @@ impl Plugin for RoadToolPlugin {
             .add_event::<RequestRoad>()
             .add_event::<RequestIntersection>()
             .add_event::<RequestRoadSplit>()
+            .add_event::<RequestTeeJunction>()
             .add_event::<RequestRoadExtend>()
             .add_event::<RequestRoadBridge>()
+            .add_event::<RequestRoadSurface>()
+            .add_event::<RequestRoadUpgrade>()
+            .add_event::<OnRoadResurfaced>()
+            .add_event::<OnRoadUpgraded>()
+            .add_event::<OnRoadBuilt>()
             .add_systems(
                 Update,
                 (
                     (
                         (update_ground_position).in_set(UpdateStage::UpdateView).run_if(in_state(MouseOver::World)),
-                        (adjust_tool_size, change_orientation, handle_action)
+                        (
+                            adjust_tool_size,
+                            change_orientation,
+                            change_draw_mode,
+                            change_surface,
+                            handle_action,
+                        )
                             .in_set(UpdateStage::UserInput)
                             .run_if(in_state(MouseOver::World)),
                     )
                         .run_if(in_state(ToolState::Road)),
-                    (split_roads, extend_roads, bridge_roads).in_set(UpdateStage::HighLevelSideEffects),
+                    (
+                        split_roads,
+                        build_tee_junctions,
+                        extend_roads,
+                        merge_across_erased_intersections.before(bridge_roads),
+                        bridge_roads,
+                        resurface_roads,
+                        upgrade_roads,
+                    )
+                        .in_set(UpdateStage::HighLevelSideEffects),
                     (spawn_roads, spawn_intersections).in_set(UpdateStage::Spawning),
+                    spawn_bridge_supports.in_set(UpdateStage::AfterSpawning),
                 ),
             );
     }
 }
 
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default)]
+pub enum RoadDrawMode {
+    #[default]
+    Straight,
+    Diagonal,
+    Curve,
+    Bridge,
+    Upgrade,
+}
+
+#[derive(Component, Debug)]
+pub struct BridgeSupports;
+
 #[derive(Component, Debug)]
 pub struct RoadTool {
     width: i32,
@@ -52,6 +106,13 @@ pub struct RoadTool {
     dragging: bool,
     drag_area: GridArea,
     orientation: GAxis,
+    mode: RoadDrawMode,
+    upgrade_target: Option<(Entity, GridArea)>,
+    graded: bool,
+    sited: bool,
+    force_junction: bool,
+    lined_up: bool,
+    pub surface: RoadSurface,
 }
 
 impl RoadTool {
@@ -63,6 +124,121 @@ impl RoadTool {
             dragging: false,
             drag_area: GridArea::at(Vec3::ZERO, 0, 0),
             orientation: GAxis::Z,
+            mode: RoadDrawMode::Straight,
+            upgrade_target: None,
+            graded: true,
+            sited: true,
+            force_junction: false,
+            lined_up: false,
+            surface: RoadSurface::Asphalt,
+        }
+    }
+
+    // What happens where the drag meets a road of its width lined up with it, shown beside the cursor.
+    pub fn join_hint(&self) -> Option<RoadJoin> {
+        match self.force_junction {
+            _ if !self.lined_up => None,
+            true => Some(RoadJoin::Junction),
+            false => Some(RoadJoin::Merge),
+        }
+    }
+
+    fn lines_up_with(&self, grid: &Grid, segment_query: &Query<&mut RoadSegment>, attach_area: GridArea) -> bool {
+        grid.single_entity_in_area(attach_area)
+            .and_then(|entity| segment_query.get(entity).ok())
+            .is_some_and(|adj| adj.is_straight() && adj.orientation == self.orientation && adj.drive_width() == self.width)
+    }
+
+    fn shaped_preview(&self) -> Option<RoadSegment> {
+        if !self.dragging
+            || matches!(
+                self.mode,
+                RoadDrawMode::Straight | RoadDrawMode::Bridge | RoadDrawMode::Upgrade
+            )
+        {
+            return None;
+        }
+
+        let start = self.drag_start_area();
+        let delta = GridCell::at(self.ground_position).pos - GridCell::at(self.drag_start_ground_position).pos;
+        let (along, lateral) = match self.orientation {
+            GAxis::Z => (delta.y, delta.x),
+            GAxis::X => (delta.x, delta.y),
+        };
+        let step = if along < 0 { -1 } else { 1 };
+        let side = if lateral < 0 { -1 } else { 1 };
+
+        let (min_along, max_along, min_side, max_side) = match self.orientation {
+            GAxis::Z => (start.min.pos.y, start.max.pos.y, start.min.pos.x, start.max.pos.x),
+            GAxis::X => (start.min.pos.x, start.max.pos.x, start.min.pos.y, start.max.pos.y),
+        };
+
+        let (along_range, side_range, shape) = if self.mode == RoadDrawMode::Diagonal {
+            let n = along.abs();
+            let along_range = (min_along.min(min_along + step * n), max_along.max(max_along + step * n));
+            let side_range = (min_side + (side * n).min(0), max_side + (side * n).max(0));
+            let shape = RoadShape::Diagonal {
+                shift: side * step,
+                width: self.width,
+            };
+            (along_range, side_range, shape)
+        } else {
+            let size = (along.abs().max(lateral.abs()) + 1).max(self.width);
+            let along_range = match step > 0 {
+                true => (min_along, min_along + size - 1),
+                false => (min_along - size + 1, min_along),
+            };
+            let side_range = match side > 0 {
+                true => (min_side, min_side + size - 1),
+                false => (max_side - size + 1, max_side),
+            };
+            let pivot = match self.orientation {
+                GAxis::Z => IVec2::new(side, -step),
+                GAxis::X => IVec2::new(-step, side),
+            };
+            (
+                along_range,
+                side_range,
+                RoadShape::Curve {
+                    pivot,
+                    width: self.width,
+                },
+            )
+        };
+
+        let area = match self.orientation {
+            GAxis::Z => GridArea::new(
+                GridCell::new(side_range.0, along_range.0),
+                GridCell::new(side_range.1, along_range.1),
+            ),
+            GAxis::X => GridArea::new(
+                GridCell::new(along_range.0, side_range.0),
+                GridCell::new(along_range.1, side_range.1),
+            ),
+        };
+
+        Some(RoadSegment::shaped(area, self.orientation, shape))
+    }
+
+    fn placement_cost(&self) -> i64 {
+        match self.shaped_preview() {
+            Some(preview) => road_cost(preview.cells().len() as i64),
+            None if self.mode == RoadDrawMode::Bridge => bridge_cost(self.area().cell_count()),
+            None => road_cost(self.area().cell_count()),
+        }
+    }
+
+    // Roads stay on dry land, except bridges, which have to cross water and come down on dry land at
+    // both ends.
+    fn fits_terrain(&self, terrain: &Terrain) -> bool {
+        match self.shaped_preview() {
+            Some(preview) => terrain.is_dry(preview.cells()),
+            None if self.mode == RoadDrawMode::Bridge && self.dragging => {
+                !terrain.is_dry(self.area().iter())
+                    && terrain.is_dry(self.drag_start_area().iter())
+                    && terrain.is_dry(self.drag_end_area().iter())
+            }
+            None => terrain.is_dry(self.area().iter()),
         }
     }
 
@@ -90,6 +266,37 @@ impl RoadTool {
         }
     }
 
+    // Pulls the drag end up against a straight road on the same cells ahead of or behind the start,
+    // so the new road extends it rather than stopping just short of it or running into it.
+    fn snap_to_collinear(&mut self, alignment: &RoadAlignment) {
+        let orientation = self.orientation;
+        let along = |cell: GridCell| match orientation {
+            GAxis::Z => cell.pos.y,
+            GAxis::X => cell.pos.x,
+        };
+
+        let start = self.drag_start_area();
+        let start_along = along(start.min);
+        let end_along = along(GridCell::at(self.ground_position));
+
+        let snapped = alignment
+            .collinear(start, orientation)
+            .filter_map(|area| match (along(area.min), along(area.max)) {
+                (min, _) if min > start_along => Some(min - 1),
+                (_, max) if max < start_along => Some(max + 1),
+                _ => None,
+            })
+            .filter(|target| (target - end_along).abs() <= SNAP_CELLS)
+            .min_by_key(|target| (target - end_along).abs());
+
+        if let Some(target) = snapped {
+            match orientation {
+                GAxis::Z => self.ground_position.z = target as f32 + 0.5,
+                GAxis::X => self.ground_position.x = target as f32 + 0.5,
+            }
+        }
+    }
+
     fn hover_area(&self) -> GridArea {
         if self.orientation == GAxis::Z {
             GridArea::at(self.ground_position, self.width, 1)
@@ -142,32 +349,59 @@ fn spawn_tool(mut commands: Commands) {
 }
 
 fn update_ground_position(
-    camera_query: Query<(&Camera, &PlayerCameraController, &GlobalTransform)>,
+    cursor: CursorPick,
     mut tool_query: Query<&mut RoadTool>,
-    ground_query: Query<&GlobalTransform, With<Ground>>,
     grid_query: Query<&Grid>,
-    windows: Query<&Window>,
+    segment_query: Query<&RoadSegment>,
+    funds: Res<Funds>,
+    alignment: Res<RoadAlignment>,
     mut gizmos: Gizmos,
 ) {
-    let (camera, controller, camera_transform) = camera_query.single();
     let mut tool = tool_query.single_mut();
-    let ground = ground_query.single();
+    let terrain = cursor.terrain();
 
-    let Ok(window) = windows.get_single() else {
-        return;
-    };
+    if let Some(point) = cursor.ground() {
+        tool.ground_position = point;
 
-    let Some(cursor_position) = window.cursor_position() else {
-        return;
-    };
+        if tool.mode == RoadDrawMode::Upgrade {
+            let grid = grid_query.single();
+            let hovered = grid.entity_at(GridCell::at(point)).ok().flatten();
+            tool.upgrade_target = hovered.and_then(|entity| {
+                let segment = segment_query.get(entity).ok()?;
+                widened_area(segment, grid, terrain).map(|area| (entity, area))
+            });
 
-    let Some(ray) = camera.viewport_to_world(camera_transform, cursor_position) else {
-        return;
-    };
+            let Some((entity, area)) = tool.upgrade_target else {
+                if let Some(segment) = hovered.and_then(|entity| segment_query.get(entity).ok()) {
+                    gizmos.rect(
+                        segment.area.center().with_y(terrain.bounds(segment.area).1 + 0.01),
+                        Quat::from_rotation_x(FRAC_PI_2),
+                        segment.area.dimensions(),
+                        Color::linear_rgba(1.0, 0.0, 0.0, 0.25),
+                    );
+                }
+                return;
+            };
 
-    if let Some(distance) = ray.intersect_plane(ground.translation(), InfinitePlane3d::new(ground.up())) {
-        let point = ray.get_point(distance);
-        tool.ground_position = point;
+            let added = upgrade_cost(segment_query.get(entity).map_or(0, |segment| segment.area.cell_count()), area);
+            let gizmo_color = match funds.can_afford(added) {
+                true => Color::linear_rgba(0.5, 0.0, 0.85, 0.8),
+                false => Color::linear_rgba(1.0, 0.0, 0.0, 0.25),
+            };
+            gizmos.rect(
+                area.center().with_y(terrain.bounds(area).1 + 0.01),
+                Quat::from_rotation_x(FRAC_PI_2),
+                area.dimensions(),
+                gizmo_color,
+            );
+            return;
+        }
+
+        let straight_drag = tool.dragging && tool.shaped_preview().is_none();
+        if straight_drag {
+            tool.snap_to_collinear(&alignment);
+            draw_alignment_guides(&tool, &alignment, terrain, &mut gizmos);
+        }
 
         let area = tool.area();
 
@@ -175,18 +409,51 @@ fn update_ground_position(
             tool.drag_area = area;
         }
 
-        let mut gizmo_color = if grid_query.single().is_valid_paint_area(area) {
+        // Steep drags stay red and cannot be placed until they are rerouted along a gentler slope.
+        let preview = tool.shaped_preview();
+        let grade = match &preview {
+            Some(preview) => road_grade(preview, terrain),
+            None => road_grade(&RoadSegment::shaped(area, tool.orientation, RoadShape::Straight), terrain),
+        };
+        tool.graded = grade <= MAX_ROAD_GRADE;
+        tool.sited = tool.fits_terrain(terrain);
+
+        let affordable = funds.can_afford(tool.placement_cost()) && tool.graded && tool.sited;
+        let mut gizmo_color = if affordable && grid_query.single().is_valid_paint_area(area) {
             Color::linear_rgba(0.5, 0.0, 0.85, 0.8)
         } else {
             Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
         };
 
-        if controller.is_moving() {
+        if let Some(preview) = preview {
+            let cells = preview.cells();
+            gizmo_color = if affordable && grid_query.single().is_valid_paint_cells(cells.iter().copied()) {
+                Color::linear_rgba(0.5, 0.0, 0.85, 0.8)
+            } else {
+                Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
+            };
+
+            if cursor.camera_moving() {
+                gizmo_color = gizmo_color.with_alpha(0.25);
+            }
+
+            for cell in cells {
+                gizmos.rect(
+                    cell.center().with_y(terrain.elevation(cell) + 0.01),
+                    Quat::from_rotation_x(FRAC_PI_2),
+                    Vec2::ONE,
+                    gizmo_color,
+                );
+            }
+            return;
+        }
+
+        if cursor.camera_moving() {
             gizmo_color = gizmo_color.with_alpha(0.25);
         }
 
         gizmos.rect(
-            area.center() + ground.up() * 0.01,
+            area.center().with_y(terrain.bounds(area).1 + 0.01),
             Quat::from_rotation_x(FRAC_PI_2),
             area.dimensions(),
             gizmo_color,
@@ -194,23 +461,55 @@ fn update_ground_position(
     }
 }
 
-fn adjust_tool_size(mut query: Query<&mut RoadTool>, keyboard: Res<ButtonInput<KeyCode>>) {
+// Draws a line from the drag end to the nearest road edge or intersection centre lying on the same
+// row or column as the end's leading edge, middle or trailing edge.
+fn draw_alignment_guides(tool: &RoadTool, alignment: &RoadAlignment, terrain: &Terrain, gizmos: &mut Gizmos) {
+    let area = tool.area();
+    let end = tool.drag_end_area();
+    let center = end.center();
+    let (min, max) = (end.min.min_corner(), end.max.max_corner());
+
+    let lines = match tool.orientation {
+        GAxis::Z => [min.z, center.z, max.z].map(|z| (center.with_z(z), alignment.row(z))),
+        GAxis::X => [min.x, center.x, max.x].map(|x| (center.with_x(x), alignment.column(x))),
+    };
+
+    for (from, guides) in lines {
+        let nearest = guides
+            .iter()
+            .filter(|guide| area.distance_to_point_3d(guide.anchor) > 0.0)
+            .min_by(|a, b| a.anchor.distance(from).total_cmp(&b.anchor.distance(from)));
+
+        let Some(guide) = nearest else {
+            continue;
+        };
+
+        let color = if guide.center { CENTER_GUIDE_COLOR } else { EDGE_GUIDE_COLOR };
+        gizmos.line(
+            from.with_y(terrain.height_at(from) + GUIDE_LIFT),
+            guide.anchor.with_y(terrain.height_at(guide.anchor) + GUIDE_LIFT),
+            color,
+        );
+    }
+}
+
+fn adjust_tool_size(mut query: Query<&mut RoadTool>, actions: Actions) {
     let mut tool = query.single_mut();
 
-    if keyboard.just_pressed(KeyCode::KeyR) {
+    if actions.just_pressed(Action::AdjustToolUp) {
         tool.width += 2;
     }
-    if keyboard.just_pressed(KeyCode::KeyF) {
+    if actions.just_pressed(Action::AdjustToolDown) {
         tool.width -= 2;
     }
 
     tool.width = tool.width.max(2);
 }
 
-fn change_orientation(mut query: Query<&mut RoadTool>, keyboard: Res<ButtonInput<KeyCode>>) {
+fn change_orientation(mut query: Query<&mut RoadTool>, actions: Actions) {
     let mut tool = query.single_mut();
 
-    if keyboard.just_pressed(KeyCode::Tab) {
+    if actions.just_pressed(Action::RotateTool) {
         tool.orientation = match tool.orientation {
             GAxis::X => GAxis::Z,
             GAxis::Z => GAxis::X,
@@ -218,40 +517,108 @@ fn change_orientation(mut query: Query<&mut RoadTool>, keyboard: Res<ButtonInput
     }
 }
 
+fn change_draw_mode(mut query: Query<&mut RoadTool>, actions: Actions) {
+    let mut tool = query.single_mut();
+
+    if actions.just_pressed(Action::CycleRoadShape) {
+        tool.mode = match tool.mode {
+            RoadDrawMode::Straight => RoadDrawMode::Diagonal,
+            RoadDrawMode::Diagonal => RoadDrawMode::Curve,
+            RoadDrawMode::Curve => RoadDrawMode::Bridge,
+            RoadDrawMode::Bridge => RoadDrawMode::Upgrade,
+            RoadDrawMode::Upgrade => RoadDrawMode::Straight,
+        }
+    }
+}
+
+fn change_surface(mut query: Query<&mut RoadTool>, actions: Actions, mut resurfacer: EventWriter<RequestRoadSurface>) {
+    let mut tool = query.single_mut();
+
+    if actions.just_pressed(Action::CycleRoadSurface) {
+        tool.surface = tool.surface.next();
+    }
+
+    if actions.just_pressed(Action::ResurfaceDistrict) {
+        let district = Grid::chunk_of(GridCell::at(tool.ground_position));
+        resurfacer.send(RequestRoadSurface::new(Grid::chunk_area(district), tool.surface));
+    }
+}
+
+// Every request the road tool can send when a drag or an upgrade click is committed.
+#[derive(SystemParam)]
+struct RoadRequests<'w> {
+    creator: EventWriter<'w, RequestRoad>,
+    splitter: EventWriter<'w, RequestRoadSplit>,
+    extender: EventWriter<'w, RequestRoadExtend>,
+    intersector: EventWriter<'w, RequestIntersection>,
+    bridge: EventWriter<'w, RequestRoadBridge>,
+    tee: EventWriter<'w, RequestTeeJunction>,
+    upgrader: EventWriter<'w, RequestRoadUpgrade>,
+    built: EventWriter<'w, OnRoadBuilt>,
+}
+
 fn handle_action(
     mut query: Query<&mut RoadTool>,
     mut grid_query: Query<&mut Grid>,
     segment_query: Query<&mut RoadSegment>,
-    mouse: Res<ButtonInput<MouseButton>>,
-    keyboard: Res<ButtonInput<KeyCode>>,
-    creator: EventWriter<RequestRoad>,
-    splitter: EventWriter<RequestRoadSplit>,
-    extender: EventWriter<RequestRoadExtend>,
-    intersector: EventWriter<RequestIntersection>,
-    bridge: EventWriter<RequestRoadBridge>,
+    actions: Actions,
+    mut requests: RoadRequests,
+    mut payment: Payment,
+    mut menu: ResMut<ContextMenu>,
 ) {
     let mut tool = query.single_mut();
     let mut grid = grid_query.single_mut();
 
-    if mouse.just_pressed(MouseButton::Left) && !keyboard.any_pressed([KeyCode::AltLeft, KeyCode::ControlLeft]) {
+    if tool.dragging && actions.secondary_just_pressed() {
+        tool.dragging = false;
+        menu.consume_press();
+    }
+
+    tool.force_junction = actions.pressed(Action::ForceJunction);
+    tool.lined_up = tool.dragging
+        && tool.shaped_preview().is_none()
+        && [tool.drag_start_attach_area(), tool.drag_end_attach_area()]
+            .into_iter()
+            .any(|area| tool.lines_up_with(&grid, &segment_query, area));
+
+    if tool.mode == RoadDrawMode::Upgrade {
+        tool.dragging = false;
+
+        if let (true, false, Some((entity, area))) = (
+            actions.primary_just_pressed(),
+            actions.mouse_modifier_held(),
+            tool.upgrade_target,
+        ) {
+            let cost = upgrade_cost(segment_query.get(entity).map_or(0, |segment| segment.area.cell_count()), area);
+
+            if payment.can_afford(cost) {
+                requests.upgrader.send(RequestRoadUpgrade { entity, area });
+                payment.spend(cost);
+            } else {
+                payment.reject(cost);
+            }
+        }
+        return;
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
         if !tool.dragging {
             tool.dragging = true;
             tool.drag_start_ground_position = tool.ground_position;
         } else {
-            handle_end_drag(
-                &mut tool,
-                &mut grid,
-                segment_query,
-                creator,
-                splitter,
-                extender,
-                intersector,
-                bridge,
-            );
+            let cost = tool.placement_cost();
+
+            if !payment.can_afford(cost) {
+                payment.reject(cost);
+                tool.dragging = false;
+            } else if tool.graded && tool.sited && handle_end_drag(&mut tool, &mut grid, &segment_query, &mut requests) {
+                payment.spend(cost);
+                requests.built.send(OnRoadBuilt);
+            }
         }
     }
 
-    if keyboard.just_pressed(KeyCode::Escape) {
+    if actions.just_pressed(Action::Cancel) {
         tool.dragging = false;
     }
 }
@@ -259,24 +626,38 @@ fn handle_action(
 fn handle_end_drag(
     tool: &mut RoadTool,
     grid: &mut Grid,
-    segment_query: Query<&mut RoadSegment>,
-    mut creator: EventWriter<RequestRoad>,
-    mut splitter: EventWriter<RequestRoadSplit>,
-    mut extender: EventWriter<RequestRoadExtend>,
-    mut intersector: EventWriter<RequestIntersection>,
-    mut bridge: EventWriter<RequestRoadBridge>,
-) {
+    segment_query: &Query<&mut RoadSegment>,
+    requests: &mut RoadRequests,
+) -> bool {
+    let mut built = false;
+
+    if let Some(preview) = tool.shaped_preview() {
+        if grid.is_valid_paint_cells(preview.cells()) {
+            for ((cap, gdir), (attach_area, _)) in preview.caps().into_iter().zip(preview.end_areas()) {
+                attach_shaped_end(grid, segment_query, cap, gdir, attach_area, requests);
+            }
+
+            requests.creator.send(
+                RequestRoad::shaped(preview.area, preview.orientation, preview.shape)
+                    .with_surface(tool.surface)
+                    .under_construction(),
+            );
+            built = true;
+        }
+
+        tool.dragging = false;
+        return built;
+    }
+
     if grid.is_valid_paint_area(tool.drag_area) {
         let mut extend_start = false;
         let mut extend_end = false;
         let mut extend_entities = Vec::<Entity>::new();
 
         if let Some(adjacent_entity) = grid.single_entity_in_area(tool.drag_start_attach_area()) {
-            if let Ok(adj) = segment_query.get(adjacent_entity) {
+            if let Some(adj) = segment_query.get(adjacent_entity).ok().filter(|adj| adj.is_straight()) {
                 if adj.orientation != tool.orientation {
-                    let intersection_area = adj.get_intersection_area(tool.drag_area);
-                    splitter.send(RequestRoadSplit::new(adjacent_entity, intersection_area));
-                    intersector.send(RequestIntersection::new(intersection_area));
+                    requests.tee.send(RequestTeeJunction::new(adjacent_entity, tool.drag_area));
                 } else if adj.drive_width() == tool.width {
                     extend_start = true;
                     extend_entities.push(adjacent_entity);
@@ -285,11 +666,9 @@ fn handle_end_drag(
         }
 
         if let Some(adjacent_entity) = grid.single_entity_in_area(tool.drag_end_attach_area()) {
-            if let Ok(adj) = segment_query.get(adjacent_entity) {
+            if let Some(adj) = segment_query.get(adjacent_entity).ok().filter(|adj| adj.is_straight()) {
                 if adj.orientation != tool.orientation {
-                    let intersection_area = adj.get_intersection_area(tool.drag_area);
-                    splitter.send(RequestRoadSplit::new(adjacent_entity, intersection_area));
-                    intersector.send(RequestIntersection::new(intersection_area));
+                    requests.tee.send(RequestTeeJunction::new(adjacent_entity, tool.drag_area));
                 } else if adj.drive_width() == tool.width {
                     extend_end = true;
                     extend_entities.push(adjacent_entity);
@@ -297,18 +676,358 @@ fn handle_end_drag(
             }
         }
 
-        if !extend_start && !extend_end {
-            creator.send(RequestRoad::new(tool.drag_area, tool.orientation));
+        // A forced junction keeps the new road separate, with an intersection at each road it meets.
+        if tool.force_junction && !extend_entities.is_empty() {
+            requests
+                .creator
+                .send(RequestRoad::new(tool.drag_area, tool.orientation).with_surface(tool.surface).under_construction());
+            for adjacent_entity in extend_entities {
+                requests.extender.send(RequestRoadExtend::junction(adjacent_entity, tool.drag_area));
+            }
+        } else if !extend_start && !extend_end {
+            requests
+                .creator
+                .send(RequestRoad::new(tool.drag_area, tool.orientation).with_surface(tool.surface).under_construction());
         } else if extend_start && extend_end {
-            bridge.send(RequestRoadBridge::new(extend_entities[0], extend_entities[1]));
+            requests.bridge.send(RequestRoadBridge::new(extend_entities[0], extend_entities[1]));
         } else {
             for adjacent_entity in extend_entities {
-                extender.send(RequestRoadExtend::new(adjacent_entity, tool.drag_area));
+                requests.extender.send(RequestRoadExtend::new(adjacent_entity, tool.drag_area));
             }
         }
+
+        built = true;
     }
 
     tool.dragging = false;
+    built
+}
+
+fn attach_shaped_end(
+    grid: &Grid,
+    segment_query: &Query<&mut RoadSegment>,
+    cap: GridArea,
+    gdir: GDir,
+    attach_area: GridArea,
+    requests: &mut RoadRequests,
+) {
+    let Some(adjacent_entity) = grid.single_entity_in_area(attach_area) else {
+        return;
+    };
+
+    let Some(adj) = segment_query.get(adjacent_entity).ok().filter(|adj| adj.is_straight()) else {
+        return;
+    };
+
+    let cap_axis = match gdir {
+        GDir::North | GDir::South => GAxis::Z,
+        GDir::West | GDir::East => GAxis::X,
+    };
+
+    let width = cap.cell_dimensions().max_element();
+    let (min, max) = (adj.area.min.pos, adj.area.max.pos);
+
+    let intersection_area = if adj.orientation != cap_axis {
+        requests.tee.send(RequestTeeJunction::new(adjacent_entity, cap));
+        return;
+    } else if adj.drive_width() == width {
+        match gdir {
+            GDir::North => GridArea::new(adj.area.min, GridCell::new(max.x, (min.y + width - 1).min(max.y))),
+            GDir::South => GridArea::new(GridCell::new(min.x, (max.y - width + 1).max(min.y)), adj.area.max),
+            GDir::East => GridArea::new(adj.area.min, GridCell::new((min.x + width - 1).min(max.x), max.y)),
+            GDir::West => GridArea::new(GridCell::new((max.x - width + 1).max(min.x), min.y), adj.area.max),
+        }
+    } else {
+        return;
+    };
+
+    requests.splitter.send(RequestRoadSplit::new(adjacent_entity, intersection_area));
+    requests.intersector.send(RequestIntersection::new(intersection_area));
+}
+
+// Straight centerlines only carry their two end points, so they are filled in at roughly one point
+// per cell for the surface to follow the terrain.
+fn terrain_samples(segment: &RoadSegment) -> Vec<(Vec3, Vec3)> {
+    let samples = segment.centerline_samples();
+    let mut dense = vec![samples[0]];
+
+    for pair in samples.windows(2) {
+        let steps = pair[0].0.distance(pair[1].0).ceil().max(1.0) as usize;
+        dense.extend((1..=steps).map(|step| {
+            let t = step as f32 / steps as f32;
+            (pair[0].0.lerp(pair[1].0, t), pair[0].1.lerp(pair[1].1, t))
+        }));
+    }
+
+    dense
+}
+
+pub fn road_grade(segment: &RoadSegment, terrain: &Terrain) -> f32 {
+    let points: Vec<Vec3> = terrain_samples(segment).into_iter().map(|(point, _)| point).collect();
+    terrain.max_grade(&points)
+}
+
+fn road_mesh(segment: &RoadSegment, terrain: &Terrain) -> Mesh {
+    let origin = segment.area().center();
+    let samples = terrain_samples(segment);
+    let surface = |point: Vec3| (point - origin).with_y(terrain.road_height(point) + ROAD_HEIGHT).to_array();
+
+    let mut positions = Vec::<[f32; 3]>::new();
+    let mut uvs = Vec::<[f32; 2]>::new();
+    let mut travelled = 0.0;
+
+    for (i, &(point, lateral)) in samples.iter().enumerate() {
+        if i > 0 {
+            travelled += point.distance(samples[i - 1].0);
+        }
+
+        let u = travelled / ROAD_TEXTURE_STRETCH;
+        positions.push(surface(point - lateral));
+        positions.push(surface(point + lateral));
+        uvs.push([u, 0.0]);
+        uvs.push([u, 1.0]);
+    }
+
+    let mut indices = Vec::<u32>::new();
+    for i in 0..(samples.len() as u32 - 1) {
+        let (a, b, c, d) = (i * 2, i * 2 + 1, i * 2 + 2, i * 2 + 3);
+        for triangle in [[a, b, c], [b, d, c]] {
+            let [p0, p1, p2] = triangle.map(|index| Vec3::from_array(positions[index as usize]));
+            if (p1 - p0).cross(p2 - p0).y >= 0.0 {
+                indices.extend(triangle);
+            } else {
+                indices.extend([triangle[0], triangle[2], triangle[1]]);
+            }
+        }
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+fn road_texture(asset_server: Option<&AssetServer>, width: i32) -> Option<Handle<Image>> {
+    let texture = match width {
+        6 => "textures/three_lanes.png",
+        4 => "textures/two_lanes.png",
+        _ => "textures/one_lane.png",
+    };
+
+    asset_server.map(|server| {
+        server.load_with_settings(texture, |s: &mut _| {
+            *s = ImageLoaderSettings {
+                sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
+                    address_mode_u: ImageAddressMode::Repeat,
+                    address_mode_v: ImageAddressMode::Repeat,
+                    ..default()
+                }),
+                ..default()
+            }
+        })
+    })
+}
+
+// Intersections are a grid of quads over their area so they sit on the terrain like the roads do.
+fn intersection_mesh(area: GridArea, terrain: &Terrain) -> Mesh {
+    let origin = area.center();
+    let (min, max) = (area.min.min_corner(), area.max.max_corner());
+    let size = area.cell_dimensions() + IVec2::ONE;
+
+    let mut positions = Vec::<[f32; 3]>::new();
+    let mut uvs = Vec::<[f32; 2]>::new();
+    for z in 0..size.y {
+        for x in 0..size.x {
+            let point = min + Vec3::new(x as f32, 0.0, z as f32);
+            positions.push((point - origin).with_y(terrain.road_height(point) + ROAD_HEIGHT).to_array());
+            uvs.push([(point.x - min.x) / (max.x - min.x), (point.z - min.z) / (max.z - min.z)]);
+        }
+    }
+
+    let mut indices = Vec::<u32>::new();
+    for z in 0..size.y as u32 - 1 {
+        for x in 0..size.x as u32 - 1 {
+            let a = z * size.x as u32 + x;
+            let b = a + size.x as u32;
+            indices.extend([a, b, a + 1, a + 1, b, b + 1]);
+        }
+    }
+
+    let normals = vec![[0.0, 1.0, 0.0]; positions.len()];
+
+    Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+        .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, positions)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, normals)
+        .with_inserted_attribute(Mesh::ATTRIBUTE_UV_0, uvs)
+        .with_inserted_indices(Indices::U32(indices))
+}
+
+// Deck sides and underside for the stretches of a road lifted over water, plus pairs of pillars down
+// to the ground at regular intervals. Roads that never leave the ground have no supports.
+fn bridge_mesh(segment: &RoadSegment, terrain: &Terrain) -> Option<Mesh> {
+    let origin = segment.area().center();
+    let samples = terrain_samples(segment);
+    let lifted = |point: Vec3| terrain.bridge_lift(point) > 0.0;
+
+    if !samples.iter().any(|&(point, _)| lifted(point)) {
+        return None;
+    }
+
+    let deck = |point: Vec3| (point - origin).with_y(terrain.road_height(point) + ROAD_HEIGHT);
+    let below = Vec3::Y * DECK_THICKNESS;
+    let mut builder = SupportMeshBuilder::default();
+
+    for pair in samples.windows(2) {
+        let [(a, a_lateral), (b, b_lateral)] = [pair[0], pair[1]];
+        if !lifted(a) && !lifted(b) {
+            continue;
+        }
+
+        let (a_left, a_right) = (deck(a - a_lateral), deck(a + a_lateral));
+        let (b_left, b_right) = (deck(b - b_lateral), deck(b + b_lateral));
+        builder.push_quad([a_left, b_left, b_left - below, a_left - below]);
+        builder.push_quad([b_right, a_right, a_right - below, b_right - below]);
+        builder.push_quad([a_left - below, b_left - below, b_right - below, a_right - below]);
+    }
+
+    let mut travelled = 0.0;
+    let mut next_pillar = PILLAR_SPACING / 2.0;
+    for (i, &(point, lateral)) in samples.iter().enumerate() {
+        if i > 0 {
+            travelled += point.distance(samples[i - 1].0);
+        }
+
+        if travelled < next_pillar || terrain.bridge_lift(point) <= DECK_THICKNESS {
+            continue;
+        }
+
+        for side in [-PILLAR_INSET, PILLAR_INSET] {
+            let base = point + lateral * side;
+            let top = deck(base) - below;
+            builder.push_pillar(top.with_y(terrain.height_at(base)), top.y);
+        }
+        next_pillar = travelled + PILLAR_SPACING;
+    }
+
+    Some(builder.build())
+}
+
+#[derive(Default)]
+struct SupportMeshBuilder {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    indices: Vec<u32>,
+}
+
+impl SupportMeshBuilder {
+    fn push_quad(&mut self, corners: [Vec3; 4]) {
+        let normal = (corners[1] - corners[0]).cross(corners[3] - corners[0]).normalize_or_zero();
+        let start = self.positions.len() as u32;
+        self.positions.extend(corners.map(|corner| corner.to_array()));
+        self.normals.extend([normal.to_array(); 4]);
+        self.indices.extend([0, 1, 2, 0, 2, 3].map(|index| start + index));
+    }
+
+    fn push_pillar(&mut self, base: Vec3, top: f32) {
+        let half = PILLAR_SIZE / 2.0;
+        let corners = [
+            Vec3::new(-half, 0.0, -half),
+            Vec3::new(half, 0.0, -half),
+            Vec3::new(half, 0.0, half),
+            Vec3::new(-half, 0.0, half),
+        ];
+
+        for side in 0..4 {
+            let (a, b) = (base + corners[side], base + corners[(side + 1) % 4]);
+            self.push_quad([a, b, b.with_y(top), a.with_y(top)]);
+        }
+    }
+
+    fn build(self) -> Mesh {
+        Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::default())
+            .with_inserted_attribute(Mesh::ATTRIBUTE_POSITION, self.positions)
+            .with_inserted_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals)
+            .with_inserted_indices(Indices::U32(self.indices))
+    }
+}
+
+// One lane per direction more, growing into free cells on both sides when possible and otherwise
+// two cells into whichever side is free. Only straight roads can be upgraded, and only bridges
+// can widen out over water.
+fn widened_area(segment: &RoadSegment, grid: &Grid, terrain: &Terrain) -> Option<GridArea> {
+    if !segment.is_straight() || segment.drive_width() + 2 > MAX_ROAD_WIDTH {
+        return None;
+    }
+
+    let across = match segment.orientation {
+        GAxis::Z => IVec2::X,
+        GAxis::X => IVec2::Y,
+    };
+    let bridge = !terrain.is_dry(segment.area.iter());
+
+    [(1, 1), (2, 0), (0, 2)]
+        .into_iter()
+        .map(|(low, high)| {
+            GridArea::new(
+                GridCell {
+                    pos: segment.area.min.pos - across * low,
+                },
+                GridCell {
+                    pos: segment.area.max.pos + across * high,
+                },
+            )
+        })
+        .find(|area| {
+            let added: Vec<GridCell> = area.iter().filter(|cell| !segment.area.contains_point_3d(cell.center())).collect();
+            grid.is_valid_paint_cells(added.iter().copied()) && (bridge || terrain.is_dry(added))
+        })
+}
+
+fn upgrade_cost(current_cells: i64, widened: GridArea) -> i64 {
+    road_cost(widened.cell_count() - current_cells)
+}
+
+// The upgrade the road tool makes on click, for widening a road picked some other way.
+#[derive(SystemParam)]
+pub struct RoadUpgrader<'w, 's> {
+    grid_query: Query<'w, 's, &'static Grid>,
+    terrain: Res<'w, Terrain>,
+    upgrader: EventWriter<'w, RequestRoadUpgrade>,
+    payment: Payment<'w>,
+}
+
+impl RoadUpgrader<'_, '_> {
+    pub fn widened_area(&self, segment: &RoadSegment) -> Option<GridArea> {
+        widened_area(segment, self.grid_query.single(), &self.terrain)
+    }
+
+    pub fn upgrade(&mut self, entity: Entity, segment: &RoadSegment) {
+        let Some(area) = self.widened_area(segment) else {
+            return;
+        };
+        let cost = upgrade_cost(segment.area.cell_count(), area);
+
+        if self.payment.can_afford(cost) {
+            self.upgrader.send(RequestRoadUpgrade { entity, area });
+            self.payment.spend(cost);
+        } else {
+            self.payment.reject(cost);
+        }
+    }
+}
+
+// Road meshes follow the terrain, so every system that builds one needs it next to the asset stores,
+// which are missing in headless runs.
+#[derive(SystemParam)]
+struct RoadAssets<'w> {
+    meshes: Option<ResMut<'w, Assets<Mesh>>>,
+    materials: Option<ResMut<'w, Assets<StandardMaterial>>>,
+    asset_server: Option<Res<'w, AssetServer>>,
+    models: Res<'w, Models>,
+    terrain: Res<'w, Terrain>,
 }
 
 fn spawn_roads(
@@ -316,61 +1035,43 @@ fn spawn_roads(
     mut event: EventWriter<OnRoadSpawned>,
     mut commands: Commands,
     mut grid_query: Query<&mut Grid>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
+    mut assets: RoadAssets,
+    construction: Res<ConstructionSettings>,
 ) {
     let mut grid = grid_query.single_mut();
 
-    for &RequestRoad { area, orientation } in spawner.read() {
-        let width = match orientation {
-            GAxis::Z => area.cell_dimensions().x,
-            GAxis::X => area.cell_dimensions().y,
-        };
+    for &RequestRoad {
+        area,
+        orientation,
+        shape,
+        surface,
+        construction: under_construction,
+    } in spawner.read()
+    {
+        let mut segment = RoadSegment::shaped(area, orientation, shape);
+        segment.surface = surface;
+        let work_zone = (under_construction && construction.enabled).then(|| WorkZone::new(&segment, &construction));
+        segment.closed = work_zone.is_some();
 
-        let length = match orientation {
-            GAxis::Z => area.cell_dimensions().y,
-            GAxis::X => area.cell_dimensions().x,
-        };
-
-        let texture = match width {
-            6 => "textures/three_lanes.png",
-            4 => "textures/two_lanes.png",
-            _ => "textures/one_lane.png",
-        };
-
-        let material = StandardMaterial {
-            base_color_texture: Some(asset_server.load_with_settings(texture, |s: &mut _| {
-                *s = ImageLoaderSettings {
-                    sampler: ImageSampler::Descriptor(ImageSamplerDescriptor {
-                        address_mode_u: ImageAddressMode::Repeat,
-                        address_mode_v: ImageAddressMode::Repeat,
-                        ..default()
-                    }),
-                    ..default()
-                }
-            })),
-            uv_transform: Affine2::from_scale(Vec2::new(length as f32 / ROAD_TEXTURE_STRETCH, 1.0)),
+        let mut material = StandardMaterial {
+            base_color_texture: road_texture(assets.asset_server.as_deref(), segment.drive_width()),
             ..default()
         };
+        assets.models.road_surface(surface).apply(&mut material);
 
         let model = PbrBundle {
-            mesh: meshes.add(match orientation {
-                GAxis::Z => Cuboid::new(area.dimensions().y, ROAD_HEIGHT, area.dimensions().x),
-                GAxis::X => Cuboid::new(area.dimensions().x, ROAD_HEIGHT, area.dimensions().y),
-            }),
-            material: materials.add(material),
-            transform: Transform::from_translation(area.center().with_y(ROAD_HEIGHT / 2.0)).with_rotation(
-                match orientation {
-                    GAxis::Z => Quat::from_rotation_y(std::f32::consts::PI / 2.0),
-                    GAxis::X => Quat::IDENTITY,
-                },
-            ),
+            mesh: add_render_asset(&mut assets.meshes, road_mesh(&segment, &assets.terrain)),
+            material: add_render_asset(&mut assets.materials, material),
+            transform: Transform::from_translation(area.center()),
             ..default()
         };
 
-        let entity = commands.spawn((model, RoadSegment::new(area, orientation))).id();
-        grid.mark_area_occupied(area, entity);
+        let cells = segment.cells();
+        let entity = commands.spawn((model, segment, LaneOccupancy::default())).id();
+        if let Some(work_zone) = work_zone {
+            commands.entity(entity).insert(work_zone);
+        }
+        grid.mark_cells_occupied(cells, entity);
         event.send(OnRoadSpawned(entity));
     }
 }
@@ -380,15 +1081,16 @@ fn spawn_intersections(
     mut event: EventWriter<OnIntersectionSpawned>,
     mut commands: Commands,
     mut grid_query: Query<&mut Grid>,
-    mut meshes: ResMut<Assets<Mesh>>,
-    mut materials: ResMut<Assets<StandardMaterial>>,
-    asset_server: Res<AssetServer>,
+    mut assets: RoadAssets,
 ) {
     for &RequestIntersection { area } in spawner.read() {
         let model = PbrBundle {
-            mesh: meshes.add(Cuboid::new(area.dimensions().x, ROAD_HEIGHT, area.dimensions().y)),
-            material: materials.add(asset_server.load("textures/intersection.png")),
-            transform: Transform::from_translation(area.center().with_y(ROAD_HEIGHT / 2.0)),
+            mesh: add_render_asset(&mut assets.meshes, intersection_mesh(area, &assets.terrain)),
+            material: add_render_asset(
+                &mut assets.materials,
+                load_render_asset::<Image>(assets.asset_server.as_deref(), "textures/intersection.png".to_string()),
+            ),
+            transform: Transform::from_translation(area.center()),
             ..default()
         };
 
@@ -406,47 +1108,105 @@ fn split_roads(
 ) {
     for &RequestRoadSplit { entity, split_area } in split_event.read() {
         if let Ok(segment) = segment_query.get(entity) {
-            if segment.orientation == GAxis::Z {
-                if segment.area.min.pos.y < split_area.min.pos.y {
-                    let split_max = GridCell::new(segment.area.max.pos.x, split_area.adjacent_bottom().min.pos.y);
-                    let road_area = GridArea::new(segment.area.min, split_max);
-                    roads.send(RequestRoad::new(road_area, segment.orientation));
-                }
+            split_segment(segment, split_area, &mut roads);
+            destroyer.send(OnRoadDestroyed(entity));
+        }
+    }
+}
 
-                if segment.area.max.pos.y > split_area.max.pos.y {
-                    let split_min = GridCell::new(segment.area.min.pos.x, split_area.adjacent_top().max.pos.y);
-                    let road_area = GridArea::new(split_min, segment.area.max);
-                    roads.send(RequestRoad::new(road_area, segment.orientation));
-                }
-            } else {
-                if segment.area.min.pos.x < split_area.min.pos.x {
-                    let split_max = GridCell::new(split_area.adjacent_left().min.pos.x, segment.area.max.pos.y);
-                    let road_area = GridArea::new(segment.area.min, split_max);
-                    roads.send(RequestRoad::new(road_area, segment.orientation));
-                }
+// Requests the parts of a road on either side of `split_area`. The road itself is left for the
+// caller to destroy.
+fn split_segment(segment: &RoadSegment, split_area: GridArea, roads: &mut EventWriter<RequestRoad>) {
+    if segment.orientation == GAxis::Z {
+        if segment.area.min.pos.y < split_area.min.pos.y {
+            let split_max = GridCell::new(segment.area.max.pos.x, split_area.adjacent_bottom().min.pos.y);
+            let road_area = GridArea::new(segment.area.min, split_max);
+            roads.send(RequestRoad::new(road_area, segment.orientation).with_surface(segment.surface));
+        }
 
-                if segment.area.max.pos.x > split_area.max.pos.x {
-                    let split_min = GridCell::new(split_area.adjacent_right().max.pos.x, segment.area.min.pos.y);
-                    let road_area = GridArea::new(split_min, segment.area.max);
-                    roads.send(RequestRoad::new(road_area, segment.orientation));
-                }
-            }
+        if segment.area.max.pos.y > split_area.max.pos.y {
+            let split_min = GridCell::new(segment.area.min.pos.x, split_area.adjacent_top().max.pos.y);
+            let road_area = GridArea::new(split_min, segment.area.max);
+            roads.send(RequestRoad::new(road_area, segment.orientation).with_surface(segment.surface));
+        }
+    } else {
+        if segment.area.min.pos.x < split_area.min.pos.x {
+            let split_max = GridCell::new(split_area.adjacent_left().min.pos.x, segment.area.max.pos.y);
+            let road_area = GridArea::new(segment.area.min, split_max);
+            roads.send(RequestRoad::new(road_area, segment.orientation).with_surface(segment.surface));
+        }
 
-            destroyer.send(OnRoadDestroyed(entity));
+        if segment.area.max.pos.x > split_area.max.pos.x {
+            let split_min = GridCell::new(split_area.adjacent_right().max.pos.x, segment.area.min.pos.y);
+            let road_area = GridArea::new(split_min, segment.area.max);
+            roads.send(RequestRoad::new(road_area, segment.orientation).with_surface(segment.surface));
         }
     }
 }
 
+// The square at the end of a straight road facing `toward`, as deep as the road is wide.
+fn end_junction_area(segment: &RoadSegment, toward: GridArea) -> GridArea {
+    let width = segment.drive_width();
+    let (min, max) = (segment.area.min.pos, segment.area.max.pos);
+
+    match segment.orientation {
+        GAxis::Z if toward.min.pos.y > max.y => {
+            GridArea::new(GridCell::new(min.x, (max.y - width + 1).max(min.y)), segment.area.max)
+        }
+        GAxis::Z => GridArea::new(segment.area.min, GridCell::new(max.x, (min.y + width - 1).min(max.y))),
+        GAxis::X if toward.min.pos.x > max.x => {
+            GridArea::new(GridCell::new((max.x - width + 1).max(min.x), min.y), segment.area.max)
+        }
+        GAxis::X => GridArea::new(segment.area.min, GridCell::new((min.x + width - 1).min(max.x), max.y)),
+    }
+}
+
+fn build_tee_junctions(
+    mut tee_event: EventReader<RequestTeeJunction>,
+    mut destroyer: EventWriter<OnRoadDestroyed>,
+    segment_query: Query<&mut RoadSegment>,
+    mut roads: EventWriter<RequestRoad>,
+    mut intersector: EventWriter<RequestIntersection>,
+) {
+    for &RequestTeeJunction { road, branch } in tee_event.read() {
+        let Ok(segment) = segment_query.get(road) else {
+            continue;
+        };
+
+        // A branch that cannot meet the road cleanly is left as a dead end rather than joined
+        // through an intersection nothing can drive out of.
+        let Some(junction) = segment.tee_area(branch) else {
+            continue;
+        };
+
+        split_segment(segment, junction, &mut roads);
+        intersector.send(RequestIntersection::new(junction));
+        destroyer.send(OnRoadDestroyed(road));
+    }
+}
+
 fn extend_roads(
     mut extend_event: EventReader<RequestRoadExtend>,
     mut destroyer: EventWriter<OnRoadDestroyed>,
     segment_query: Query<&mut RoadSegment>,
     mut roads: EventWriter<RequestRoad>,
+    mut intersector: EventWriter<RequestIntersection>,
 ) {
-    for &RequestRoadExtend { entity, extension } in extend_event.read() {
+    for &RequestRoadExtend { entity, extension, join } in extend_event.read() {
         if let Ok(original_segment) = segment_query.get(entity) {
-            let extended_area = original_segment.area.union(extension);
-            roads.send(RequestRoad::new(extended_area, original_segment.orientation));
+            match join {
+                RoadJoin::Merge => {
+                    let extended_area = original_segment.area.union(extension);
+                    roads.send(
+                        RequestRoad::new(extended_area, original_segment.orientation).with_surface(original_segment.surface),
+                    );
+                }
+                RoadJoin::Junction => {
+                    let junction = end_junction_area(original_segment, extension);
+                    split_segment(original_segment, junction, &mut roads);
+                    intersector.send(RequestIntersection::new(junction));
+                }
+            }
             destroyer.send(OnRoadDestroyed(entity));
         }
     }
@@ -462,10 +1222,177 @@ fn bridge_roads(
         if let Ok(first_segment) = segment_query.get(first) {
             if let Ok(second_segment) = segment_query.get(second) {
                 let extended_area = first_segment.area.union(second_segment.area);
-                roads.send(RequestRoad::new(extended_area, first_segment.orientation));
+                roads.send(RequestRoad::new(extended_area, first_segment.orientation).with_surface(first_segment.surface));
                 destroyer.send(OnRoadDestroyed(first));
                 destroyer.send(OnRoadDestroyed(second));
             }
         }
     }
 }
+
+// Erasing an intersection that joins just two roads running straight through it, end to end and
+// lined up side to side, bridges them into one road across the gap it leaves.
+fn merge_across_erased_intersections(
+    mut erased_intersections: EventReader<OnIntersectionDestroyed>,
+    mut erased_roads: EventReader<OnRoadDestroyed>,
+    intersection_query: Query<&Intersection>,
+    segment_query: Query<&RoadSegment>,
+    mut bridges: EventWriter<RequestRoadBridge>,
+) {
+    let erased: Vec<Entity> = erased_roads.read().map(|event| event.0).collect();
+
+    for &OnIntersectionDestroyed(entity) in erased_intersections.read() {
+        let Ok(intersection) = intersection_query.get(entity) else {
+            continue;
+        };
+
+        let remaining = intersection.roads.map(|road| road.filter(|road| !erased.contains(road)));
+        let (first, second) = match remaining {
+            [Some(first), Some(second), None, None] | [None, None, Some(first), Some(second)] => (first, second),
+            _ => continue,
+        };
+
+        let (Ok(first_segment), Ok(second_segment)) = (segment_query.get(first), segment_query.get(second)) else {
+            continue;
+        };
+
+        let lined_up = match first_segment.orientation {
+            GAxis::X => {
+                first_segment.area.min.pos.y == second_segment.area.min.pos.y
+                    && first_segment.area.max.pos.y == second_segment.area.max.pos.y
+            }
+            GAxis::Z => {
+                first_segment.area.min.pos.x == second_segment.area.min.pos.x
+                    && first_segment.area.max.pos.x == second_segment.area.max.pos.x
+            }
+        };
+
+        if first_segment.is_straight()
+            && second_segment.is_straight()
+            && first_segment.orientation == second_segment.orientation
+            && lined_up
+        {
+            bridges.send(RequestRoadBridge::new(first, second));
+        }
+    }
+}
+
+fn resurface_roads(
+    mut resurface_event: EventReader<RequestRoadSurface>,
+    mut resurfaced: EventWriter<OnRoadResurfaced>,
+    mut segment_query: Query<(Entity, &mut RoadSegment, &Handle<StandardMaterial>)>,
+    mut materials: Option<ResMut<Assets<StandardMaterial>>>,
+    models: Res<Models>,
+) {
+    for &RequestRoadSurface { area, surface } in resurface_event.read() {
+        for (entity, mut segment, material) in &mut segment_query {
+            if segment.surface == surface || !area.contains_point_3d(segment.pos()) {
+                continue;
+            }
+
+            segment.surface = surface;
+            if let Some(material) = materials.as_mut().and_then(|materials| materials.get_mut(material)) {
+                models.road_surface(surface).apply(material);
+            }
+            resurfaced.send(OnRoadResurfaced(entity));
+        }
+    }
+}
+
+// Widens a road in place, keeping its entity so its connections and the vehicles on it carry over.
+// Vehicles steer onto the new lane positions on their own since lanes are derived from the area.
+fn upgrade_roads(
+    mut upgrade_event: EventReader<RequestRoadUpgrade>,
+    mut upgraded: EventWriter<OnRoadUpgraded>,
+    mut grid_query: Query<&mut Grid>,
+    mut segment_query: Query<(&mut RoadSegment, &mut Transform, &mut Handle<Mesh>, &Handle<StandardMaterial>)>,
+    mut building_query: Query<&mut Building>,
+    mut assets: RoadAssets,
+) {
+    let mut grid = grid_query.single_mut();
+
+    for &RequestRoadUpgrade { entity, area } in upgrade_event.read() {
+        let Ok((mut segment, mut transform, mut mesh, material)) = segment_query.get_mut(entity) else {
+            continue;
+        };
+
+        if widened_area(&segment, &grid, &assets.terrain) != Some(area) {
+            continue;
+        }
+
+        let added: Vec<GridCell> = area.iter().filter(|cell| !segment.area.contains_point_3d(cell.center())).collect();
+        grid.mark_cells_occupied(added, entity);
+        segment.area = area;
+
+        for (adj_area, _) in area.adjacent_areas() {
+            for cell in adj_area.iter() {
+                if let Ok(Some(adj)) = grid.entity_at(cell) {
+                    if let Ok(mut building) = building_query.get_mut(adj) {
+                        segment.dests.insert(adj);
+                        building.connect(entity, area);
+                    }
+                }
+            }
+        }
+
+        transform.translation = area.center();
+        if let Some(meshes) = assets.meshes.as_mut() {
+            *mesh = meshes.add(road_mesh(&segment, &assets.terrain));
+        }
+        if let Some(material) = assets.materials.as_mut().and_then(|materials| materials.get_mut(material)) {
+            material.base_color_texture = road_texture(assets.asset_server.as_deref(), segment.drive_width());
+        }
+
+        upgraded.send(OnRoadUpgraded(entity));
+    }
+}
+
+// Supports are rebuilt whenever a road is spawned or widened, since either can change how much of it
+// runs over water.
+fn spawn_bridge_supports(
+    mut spawned: EventReader<OnRoadSpawned>,
+    mut upgraded: EventReader<OnRoadUpgraded>,
+    mut commands: Commands,
+    segment_query: Query<(&RoadSegment, Option<&Children>)>,
+    support_query: Query<(), With<BridgeSupports>>,
+    mut assets: RoadAssets,
+) {
+    let changed = spawned.read().map(|event| event.0).chain(upgraded.read().map(|event| event.0));
+
+    for entity in changed {
+        let Ok((segment, children)) = segment_query.get(entity) else {
+            continue;
+        };
+
+        for &child in children.into_iter().flatten() {
+            if support_query.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+
+        let Some(mesh) = bridge_mesh(segment, &assets.terrain) else {
+            continue;
+        };
+
+        let supports = commands
+            .spawn((
+                PbrBundle {
+                    mesh: add_render_asset(&mut assets.meshes, mesh),
+                    material: add_render_asset(
+                        &mut assets.materials,
+                        StandardMaterial {
+                            base_color: Color::srgb(0.55, 0.55, 0.52),
+                            perceptual_roughness: 0.9,
+                            double_sided: true,
+                            cull_mode: None,
+                            ..default()
+                        },
+                    ),
+                    ..default()
+                },
+                BridgeSupports,
+            ))
+            .id();
+        commands.entity(entity).add_child(supports);
+    }
+}
@@ -0,0 +1,79 @@
+use crate::{
+    graphics::camera::CursorPick,
+    grid::grid::*,
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::toolbar::ToolState,
+    types::vehicle::Vehicle,
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+
+const VEHICLE_PICK_RADIUS: f32 = 1.0;
+const STRUCTURE_PICK_HEIGHT: f32 = 1.0;
+
+pub struct SelectionPlugin;
+
+impl Plugin for SelectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Selection::default()).add_systems(OnExit(ToolState::Inspect), clear_selection).add_systems(
+            Update,
+            pick_selection
+                .in_set(UpdateStage::UserInput)
+                .run_if(in_state(ToolState::Inspect).or_else(in_state(ToolState::View)))
+                .run_if(in_state(MouseOver::World)),
+        );
+    }
+}
+
+// Whatever the player last clicked on with the view or inspect tool. The inspect panel, the
+// intersection inspector and the vehicle debugger each show it when it is something they know.
+#[derive(Resource, Debug, Default)]
+pub struct Selection {
+    pub entity: Option<Entity>,
+}
+
+fn pick_selection(
+    cursor: CursorPick,
+    grid_query: Query<&Grid>,
+    vehicle_query: Query<(Entity, &Transform), With<Vehicle>>,
+    actions: Actions,
+    mut selection: ResMut<Selection>,
+) {
+    if actions.just_pressed(Action::Cancel) {
+        selection.entity = None;
+    }
+
+    if !actions.primary_just_pressed() || actions.mouse_modifier_held() {
+        return;
+    }
+
+    let Some((ray, distance)) = cursor.ground_hit() else {
+        return;
+    };
+
+    let point = ray.get_point(distance);
+
+    // Vehicles sit on top of roads, so they take priority over whatever occupies the cell beneath.
+    let vehicle = vehicle_query
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation.xz().distance(point.xz())))
+        .filter(|&(_, distance)| distance < VEHICLE_PICK_RADIUS)
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+        .map(|(entity, _)| entity);
+
+    // Otherwise take the first occupied cell the ray passes through low enough to hit what stands on it.
+    let grid = grid_query.single();
+    let structure = || {
+        grid.raycast_cells(ray.origin, *ray.direction, distance + 1.0)
+            .windows(2)
+            .filter(|pair| ray.get_point(pair[1].1).y <= STRUCTURE_PICK_HEIGHT)
+            .find_map(|pair| grid.entity_at(pair[0].0).ok().flatten())
+    };
+
+    selection.entity = vehicle.or_else(structure);
+}
+
+fn clear_selection(mut selection: ResMut<Selection>) {
+    selection.entity = None;
+}
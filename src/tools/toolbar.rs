@@ -1,16 +1,46 @@
 use crate::{
+    graphics::camera::CameraBookmarks,
+    input::keymap::{Action, Actions},
+    scenario::scenario::Scenario,
     schedule::UpdateStage,
     tools::{
-        building_tool::BuildingToolPlugin, eraser_tool::EraserToolPlugin, road_tool::RoadToolPlugin, toolbar_events::*,
+        blueprint_tool::BlueprintToolPlugin, building_tool::BuildingToolPlugin, connect_tool::ConnectToolPlugin,
+        context_menu::ContextMenuPlugin, district_tool::DistrictToolPlugin, eraser_tool::EraserToolPlugin,
+        inspect_tool::InspectToolPlugin, prop_tool::PropToolPlugin, road_tool::RoadToolPlugin, selection::SelectionPlugin,
+        toolbar_events::*, transit_tool::TransitToolPlugin, vehicle_debug::VehicleDebugPlugin, view_tool::ViewToolPlugin,
+        water_tool::WaterToolPlugin,
     },
 };
 use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
 
-#[derive(States, Default, Debug, Copy, Clone, PartialEq, Eq, Hash)]
+// The order the shoulder buttons step through the tools in.
+const TOOL_CYCLE: [ToolState; 11] = [
+    ToolState::View,
+    ToolState::Building,
+    ToolState::Road,
+    ToolState::Eraser,
+    ToolState::Connect,
+    ToolState::Transit,
+    ToolState::Inspect,
+    ToolState::Water,
+    ToolState::Blueprint,
+    ToolState::District,
+    ToolState::Props,
+];
+
+#[derive(States, Default, Debug, Copy, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
 pub enum ToolState {
     Building,
     Road,
     Eraser,
+    Connect,
+    Transit,
+    Inspect,
+    Water,
+    Blueprint,
+    District,
+    Props,
     #[default]
     View,
 }
@@ -21,7 +51,22 @@ impl Plugin for ToolbarPlugin {
     fn build(&self, app: &mut App) {
         app.init_state::<ToolState>()
             .add_event::<ChangeToolRequest>()
-            .add_plugins((BuildingToolPlugin, RoadToolPlugin, EraserToolPlugin))
+            .add_plugins((
+                BuildingToolPlugin,
+                RoadToolPlugin,
+                EraserToolPlugin,
+                ConnectToolPlugin,
+                TransitToolPlugin,
+                InspectToolPlugin,
+                ViewToolPlugin,
+                WaterToolPlugin,
+                BlueprintToolPlugin,
+                DistrictToolPlugin,
+                PropToolPlugin,
+                ContextMenuPlugin,
+                VehicleDebugPlugin,
+                SelectionPlugin,
+            ))
             .add_systems(
                 Update,
                 (
@@ -33,20 +78,70 @@ impl Plugin for ToolbarPlugin {
     }
 }
 
-pub fn change_tool_on_keypress(keyboard_input: Res<ButtonInput<KeyCode>>, mut change_tool: EventWriter<ChangeToolRequest>) {
-    if keyboard_input.just_pressed(KeyCode::Digit1) {
+pub fn change_tool_on_keypress(
+    keyboard_input: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
+    mut change_tool: EventWriter<ChangeToolRequest>,
+    state: Res<State<ToolState>>,
+    bookmarks: Option<Res<CameraBookmarks>>,
+    scenario: Res<Scenario>,
+) {
+    // Tools the scenario has locked are skipped over.
+    let step = actions.tool_cycle().signum();
+    if step != 0 {
+        let current = TOOL_CYCLE.iter().position(|tool| tool == state.get()).unwrap_or(0) as i32;
+        let next = (1..TOOL_CYCLE.len() as i32)
+            .map(|offset| TOOL_CYCLE[(current + offset * step).rem_euclid(TOOL_CYCLE.len() as i32) as usize])
+            .find(|&tool| !scenario.is_locked(tool));
+
+        if let Some(tool) = next {
+            change_tool.send(ChangeToolRequest(tool));
+        }
+        return;
+    }
+
+    if keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        return;
+    }
+
+    // In View mode, digits holding a camera bookmark recall it instead of switching tools.
+    let pressed = |action: Action| {
+        let recalls_bookmark = *state.get() == ToolState::View
+            && actions.key(action).is_some_and(|key| bookmarks.as_ref().is_some_and(|bookmarks| bookmarks.recalls(key)));
+        actions.just_pressed(action) && !recalls_bookmark
+    };
+
+    if pressed(Action::ToolBuilding) {
         change_tool.send(ChangeToolRequest(ToolState::Building));
-    } else if keyboard_input.just_pressed(KeyCode::Digit2) {
+    } else if pressed(Action::ToolRoad) {
         change_tool.send(ChangeToolRequest(ToolState::Road));
-    } else if keyboard_input.just_pressed(KeyCode::Digit3) {
+    } else if pressed(Action::ToolEraser) {
         change_tool.send(ChangeToolRequest(ToolState::Eraser));
-    } else if keyboard_input.just_pressed(KeyCode::Backquote) {
+    } else if pressed(Action::ToolConnect) {
+        change_tool.send(ChangeToolRequest(ToolState::Connect));
+    } else if pressed(Action::ToolTransit) {
+        change_tool.send(ChangeToolRequest(ToolState::Transit));
+    } else if pressed(Action::ToolInspect) {
+        change_tool.send(ChangeToolRequest(ToolState::Inspect));
+    } else if pressed(Action::ToolWater) {
+        change_tool.send(ChangeToolRequest(ToolState::Water));
+    } else if pressed(Action::ToolBlueprint) {
+        change_tool.send(ChangeToolRequest(ToolState::Blueprint));
+    } else if pressed(Action::ToolDistrict) {
+        change_tool.send(ChangeToolRequest(ToolState::District));
+    } else if pressed(Action::ToolProps) {
+        change_tool.send(ChangeToolRequest(ToolState::Props));
+    } else if pressed(Action::ToolView) {
         change_tool.send(ChangeToolRequest(ToolState::View));
     }
 }
 
-pub fn handle_change_tool_requests(mut event: EventReader<ChangeToolRequest>, mut next_state: ResMut<NextState<ToolState>>) {
-    for &ChangeToolRequest(mode) in event.read() {
+pub fn handle_change_tool_requests(
+    mut event: EventReader<ChangeToolRequest>,
+    mut next_state: ResMut<NextState<ToolState>>,
+    scenario: Res<Scenario>,
+) {
+    for &ChangeToolRequest(mode) in event.read().filter(|request| !scenario.is_locked(request.0)) {
         next_state.set(mode);
     }
 }
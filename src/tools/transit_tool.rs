@@ -0,0 +1,268 @@
+use crate::{
+    determinism::determinism::SimRng,
+    graph::{road_graph_events::*, road_network::RoadNetwork},
+    graphics::camera::*,
+    grid::{grid::*, grid_cell::GridCell, terrain::Terrain},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::toolbar::ToolState,
+    types::vehicle::{bus_path, TripSpawner, VehicleKind, VehicleSpawnState},
+    ui::egui::MouseOver,
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+
+const BUSES_PER_LINE: usize = 2;
+const LINE_HEADWAY_SECONDS: f32 = 8.0;
+const OVERLAY_HEIGHT: f32 = 0.35;
+const STOP_RADIUS: f32 = 0.6;
+const DRAFT_COLOR: Color = Color::linear_rgba(1.0, 1.0, 1.0, 0.8);
+const LINE_COLORS: [Color; 6] = [
+    Color::linear_rgb(0.9, 0.2, 0.2),
+    Color::linear_rgb(0.2, 0.5, 1.0),
+    Color::linear_rgb(0.2, 0.8, 0.3),
+    Color::linear_rgb(1.0, 0.7, 0.1),
+    Color::linear_rgb(0.7, 0.3, 0.9),
+    Color::linear_rgb(0.1, 0.8, 0.8),
+];
+
+pub struct TransitToolPlugin;
+
+impl Plugin for TransitToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(TransitTool::default())
+            .insert_resource(TransitLines::default())
+            .add_systems(OnExit(ToolState::Transit), clear_transit_tool)
+            .add_systems(
+                Update,
+                (
+                    (
+                        select_transit_stops.run_if(in_state(MouseOver::World)),
+                        finish_line_on_key_press,
+                    )
+                        .in_set(UpdateStage::UserInput)
+                        .run_if(in_state(ToolState::Transit)),
+                    run_line_buses.in_set(UpdateStage::Spawning).run_if(in_state(VehicleSpawnState::On)),
+                    refresh_line_routes.in_set(UpdateStage::UpdatePathing),
+                    visualize_transit_lines.in_set(UpdateStage::Visualize),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct TransitTool {
+    pub name: String,
+    pub stops: Vec<Entity>,
+}
+
+impl Default for TransitTool {
+    fn default() -> Self {
+        Self {
+            name: "Line 1".to_string(),
+            stops: Vec::new(),
+        }
+    }
+}
+
+impl TransitTool {
+    pub fn finish(&mut self, lines: &mut TransitLines) {
+        if self.stops.len() < 2 {
+            return;
+        }
+
+        lines.add(self.name.trim().to_string(), std::mem::take(&mut self.stops));
+        self.name = format!("Line {}", lines.lines.len() + 1);
+    }
+}
+
+#[derive(Debug)]
+pub struct BusLine {
+    pub id: u32,
+    pub name: String,
+    pub stops: Vec<Entity>,
+    pub route: Vec<Entity>,
+    since_departure: f32,
+}
+
+impl BusLine {
+    pub fn color(&self) -> Color {
+        LINE_COLORS[self.id as usize % LINE_COLORS.len()]
+    }
+}
+
+// Player-defined bus lines. Each line keeps `BUSES_PER_LINE` buses circulating over its cached route,
+// which is rebuilt whenever the road graph changes.
+#[derive(Resource, Debug, Default)]
+pub struct TransitLines {
+    pub lines: Vec<BusLine>,
+    next_id: u32,
+    stale: bool,
+}
+
+impl TransitLines {
+    pub fn add(&mut self, name: String, stops: Vec<Entity>) {
+        self.lines.push(BusLine {
+            id: self.next_id,
+            name,
+            stops,
+            route: Vec::new(),
+            since_departure: LINE_HEADWAY_SECONDS,
+        });
+        self.next_id += 1;
+        self.stale = true;
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.lines.retain(|line| line.id != id);
+    }
+}
+
+// Marks buses that belong to a line so the line can keep count of its fleet.
+#[derive(Component, Debug)]
+pub struct LineBus(pub u32);
+
+fn select_transit_stops(
+    cursor: CursorPick,
+    grid_query: Query<&Grid>,
+    network: RoadNetwork,
+    actions: Actions,
+    mut tool: ResMut<TransitTool>,
+) {
+    if actions.just_pressed(Action::Cancel) {
+        tool.stops.clear();
+    }
+
+    if actions.just_pressed(Action::RemoveLastStop) {
+        tool.stops.pop();
+    }
+
+    if !actions.primary_just_pressed() || actions.mouse_modifier_held() {
+        return;
+    }
+
+    let Some(point) = cursor.ground() else {
+        return;
+    };
+
+    let grid = grid_query.single();
+    let Some(entity) = grid.entity_at(GridCell::at(point)).ok().flatten() else {
+        return;
+    };
+
+    // Buses only stop at buildings, so clicking a road picks the closest building it serves.
+    let stop = match network.segment(entity) {
+        Some(segment) => segment.dests.iter().copied().min_by(|&a, &b| {
+            let distance_to = |stop| network.position(stop).map_or(f32::MAX, |pos| pos.distance(point));
+            distance_to(a).total_cmp(&distance_to(b))
+        }),
+        None => network.building(entity).map(|_| entity),
+    };
+
+    if let Some(stop) = stop.filter(|&stop| tool.stops.last() != Some(&stop)) {
+        tool.stops.push(stop);
+    }
+}
+
+fn finish_line_on_key_press(actions: Actions, mut tool: ResMut<TransitTool>, mut lines: ResMut<TransitLines>) {
+    if actions.just_pressed(Action::Confirm) {
+        tool.finish(&mut lines);
+    }
+}
+
+fn clear_transit_tool(mut tool: ResMut<TransitTool>) {
+    tool.stops.clear();
+}
+
+// Everything that can change the route between two stops.
+#[derive(SystemParam)]
+struct NetworkChanges<'w, 's> {
+    building_destroyed: EventReader<'w, 's, OnBuildingDestroyed>,
+    road_destroyed: EventReader<'w, 's, OnRoadDestroyed>,
+    intersection_destroyed: EventReader<'w, 's, OnIntersectionDestroyed>,
+    road_spawned: EventReader<'w, 's, OnRoadSpawned>,
+    intersection_spawned: EventReader<'w, 's, OnIntersectionSpawned>,
+    building_spawned: EventReader<'w, 's, OnBuildingSpawned>,
+}
+
+fn refresh_line_routes(mut lines: ResMut<TransitLines>, network: RoadNetwork, mut changes: NetworkChanges) {
+    for OnBuildingDestroyed(building) in changes.building_destroyed.read() {
+        for line in &mut lines.lines {
+            line.stops.retain(|stop| stop != building);
+        }
+        lines.stale = true;
+    }
+
+    if changes.road_destroyed.read().count()
+        + changes.intersection_destroyed.read().count()
+        + changes.road_spawned.read().count()
+        + changes.intersection_spawned.read().count()
+        + changes.building_spawned.read().count()
+        > 0
+    {
+        lines.stale = true;
+    }
+
+    if !lines.stale {
+        return;
+    }
+
+    for line in &mut lines.lines {
+        line.route = bus_path(&network, &line.stops).unwrap_or_default();
+    }
+    lines.stale = false;
+}
+
+fn run_line_buses(
+    mut trips: TripSpawner,
+    mut lines: ResMut<TransitLines>,
+    mut sim_rng: ResMut<SimRng>,
+    time: Res<Time>,
+    bus_query: Query<&LineBus>,
+) {
+    for line in &mut lines.lines {
+        line.since_departure += time.delta_seconds();
+
+        let fleet = bus_query.iter().filter(|bus| bus.0 == line.id).count();
+        if line.route.is_empty() || fleet >= BUSES_PER_LINE || line.since_departure < LINE_HEADWAY_SECONDS {
+            continue;
+        }
+
+        let bus = trips.spawn(sim_rng.rng(), VehicleKind::Bus, line.route.clone());
+
+        if let Some(bus) = bus {
+            trips.commands.entity(bus).insert(LineBus(line.id));
+            line.since_departure = 0.0;
+        }
+    }
+}
+
+fn visualize_transit_lines(
+    lines: Res<TransitLines>,
+    tool: Res<TransitTool>,
+    network: RoadNetwork,
+    terrain: Res<Terrain>,
+    state: Res<State<ToolState>>,
+    mut gizmos: Gizmos,
+) {
+    let lift = |entity: Entity| network.position(entity).map(|pos| pos.with_y(terrain.road_height(pos) + OVERLAY_HEIGHT));
+
+    for line in &lines.lines {
+        let color = line.color();
+        gizmos.linestrip(line.route.iter().filter_map(|&step| lift(step)), color);
+
+        for pos in line.stops.iter().filter_map(|&stop| lift(stop)) {
+            gizmos.circle(pos, Dir3::Y, STOP_RADIUS, color);
+        }
+    }
+
+    if *state.get() != ToolState::Transit {
+        return;
+    }
+
+    let draft: Vec<Vec3> = tool.stops.iter().filter_map(|&stop| lift(stop)).collect();
+    gizmos.linestrip(draft.iter().copied(), DRAFT_COLOR);
+
+    for &pos in &draft {
+        gizmos.circle(pos, Dir3::Y, STOP_RADIUS, DRAFT_COLOR);
+    }
+}
@@ -0,0 +1,122 @@
+use crate::{graph::road_network::RoadNetwork, schedule::UpdateStage, tools::selection::Selection, types::vehicle::Vehicle};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+const MAX_SPEED_SAMPLES: usize = 300;
+const PATH_HEIGHT: f32 = 0.6;
+const TRAVELLED_COLOR: Color = Color::linear_rgba(0.5, 0.5, 0.5, 0.6);
+const REMAINING_COLOR: Color = Color::linear_rgba(0.2, 0.8, 1.0, 0.9);
+const TARGET_COLOR: Color = Color::linear_rgb(0.0, 1.0, 0.0);
+const CHECKPOINT_COLOR: Color = Color::linear_rgb(1.0, 1.0, 0.0);
+const RAY_CLEAR_COLOR: Color = Color::linear_rgba(1.0, 1.0, 1.0, 0.5);
+const RAY_HIT_COLOR: Color = Color::linear_rgb(1.0, 0.2, 0.2);
+
+pub struct VehicleDebugPlugin;
+
+impl Plugin for VehicleDebugPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(VehicleDebug::default()).add_systems(
+            Update,
+            (
+                step_debug_frames.in_set(UpdateStage::UserInput),
+                record_debug_speed.in_set(UpdateStage::Analyze),
+                visualize_debug_vehicle.in_set(UpdateStage::Visualize),
+            ),
+        );
+    }
+}
+
+// Recent speeds of the selected vehicle, and any single frame step asked for while the simulation
+// is paused.
+#[derive(Resource, Debug, Default)]
+pub struct VehicleDebug {
+    pub speeds: VecDeque<f32>,
+    pub step_requested: bool,
+    stepping: bool,
+    sampled: Option<Entity>,
+}
+
+// A step unpauses virtual time for exactly one frame. Time advances at the start of the frame, so
+// pausing again during the stepped frame still lets that frame's systems see the step.
+fn step_debug_frames(mut debug: ResMut<VehicleDebug>, mut time: ResMut<Time<Virtual>>) {
+    if debug.stepping {
+        time.pause();
+        debug.stepping = false;
+    }
+
+    if debug.step_requested {
+        debug.step_requested = false;
+        if time.is_paused() {
+            time.unpause();
+            debug.stepping = true;
+        }
+    }
+}
+
+// Speeds are kept for one vehicle at a time, starting over whenever another one is selected.
+fn record_debug_speed(
+    mut debug: ResMut<VehicleDebug>,
+    selection: Res<Selection>,
+    vehicle_query: Query<&Vehicle>,
+    time: Res<Time>,
+) {
+    let selected = selection.entity.and_then(|entity| Some((entity, vehicle_query.get(entity).ok()?)));
+    if debug.sampled != selected.map(|(entity, _)| entity) {
+        debug.sampled = selected.map(|(entity, _)| entity);
+        debug.speeds.clear();
+    }
+
+    let Some((_, vehicle)) = selected else {
+        return;
+    };
+
+    if time.delta_seconds() <= 0.0 {
+        return;
+    }
+
+    debug.speeds.push_back(vehicle.speed);
+    while debug.speeds.len() > MAX_SPEED_SAMPLES {
+        debug.speeds.pop_front();
+    }
+}
+
+// The whole route, greyed out behind the vehicle, the lane point it steers towards and its next
+// checkpoint, and the look ahead it uses to find a leader, ending on the leader when it has one.
+fn visualize_debug_vehicle(
+    selection: Res<Selection>,
+    network: RoadNetwork,
+    vehicle_query: Query<(&Vehicle, &Transform)>,
+    mut gizmos: Gizmos,
+) {
+    let Some((vehicle, transform)) = selection.entity.and_then(|entity| vehicle_query.get(entity).ok()) else {
+        return;
+    };
+
+    let position = transform.translation;
+    let step_points = |steps: &[Entity]| {
+        steps.iter().filter_map(|&step| network.position(step)).map(|pos| pos.with_y(PATH_HEIGHT)).collect::<Vec<_>>()
+    };
+
+    let split = (vehicle.path_index + 1).min(vehicle.path.len());
+    gizmos.linestrip(step_points(&vehicle.path[..split]), TRAVELLED_COLOR);
+    gizmos.linestrip(
+        std::iter::once(position.with_y(PATH_HEIGHT)).chain(step_points(&vehicle.path[split..])),
+        REMAINING_COLOR,
+    );
+
+    gizmos.arrow(position, vehicle.follow, TARGET_COLOR);
+    gizmos.sphere(vehicle.follow, Quat::IDENTITY, 0.1, TARGET_COLOR);
+    gizmos.line(position, vehicle.checkpoint, CHECKPOINT_COLOR);
+
+    let leader = vehicle.blocked_by.and_then(|leader| vehicle_query.get(leader).ok());
+    match leader {
+        Some((_, leader)) => {
+            gizmos.line(position, leader.translation, RAY_HIT_COLOR);
+            gizmos.sphere(leader.translation, Quat::IDENTITY, 0.2, RAY_HIT_COLOR);
+        }
+        None => {
+            let reach = vehicle.length / 2.0 + vehicle.look_ahead();
+            gizmos.line(position, position + transform.forward().as_vec3() * reach, RAY_CLEAR_COLOR);
+        }
+    }
+}
@@ -0,0 +1,141 @@
+use crate::{
+    graph::road_network::RoadNetwork,
+    graphics::camera::*,
+    grid::{grid::*, grid_cell::GridCell},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::{selection::Selection, toolbar::ToolState},
+    types::{spatial_hash::VehicleSpatialHash, traffic_signal::RequestIntersectionControl},
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+
+const DEFAULT_OVERRIDE_SECONDS: f32 = 30.0;
+const HOVER_DELAY_SECONDS: f32 = 0.5;
+const HOVER_VEHICLE_RADIUS: f32 = 0.5;
+const OPEN_LINK_COLOR: Color = Color::linear_rgba(0.3, 0.9, 1.0, 0.8);
+const CLOSED_LINK_COLOR: Color = Color::linear_rgba(1.0, 0.3, 0.2, 0.8);
+
+pub struct ViewToolPlugin;
+
+impl Plugin for ViewToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SignalOverrideSettings::default()).insert_resource(Hovered::default()).add_systems(
+            Update,
+            (
+                track_hovered.in_set(UpdateStage::UserInput),
+                cycle_control_on_key_press.in_set(UpdateStage::UserInput),
+                visualize_inspected.in_set(UpdateStage::Visualize),
+            )
+                .run_if(in_state(ToolState::View)),
+        );
+    }
+}
+
+// How long a signal phase forced from the intersection inspector holds.
+#[derive(Resource, Debug)]
+pub struct SignalOverrideSettings {
+    pub seconds: f32,
+}
+
+impl Default for SignalOverrideSettings {
+    fn default() -> Self {
+        Self {
+            seconds: DEFAULT_OVERRIDE_SECONDS,
+        }
+    }
+}
+
+// What the pointer has rested on and for how long. Found from the grid cell under the cursor rather
+// than by picking meshes, with vehicles looked up in the spatial hash around that point first.
+#[derive(Resource, Debug, Default)]
+pub struct Hovered {
+    pub entity: Option<Entity>,
+    pub seconds: f32,
+}
+
+impl Hovered {
+    pub fn settled(&self) -> Option<Entity> {
+        self.entity.filter(|_| self.seconds >= HOVER_DELAY_SECONDS)
+    }
+}
+
+fn track_hovered(
+    cursor: CursorPick,
+    grid_query: Query<&Grid>,
+    hash: Res<VehicleSpatialHash>,
+    mouse_over: Option<Res<State<MouseOver>>>,
+    time: Res<Time>,
+    mut hovered: ResMut<Hovered>,
+) {
+    let over_world = mouse_over.is_some_and(|state| *state.get() == MouseOver::World);
+    let point = cursor.ground().filter(|_| over_world);
+
+    let entity = point.and_then(|point| {
+        let vehicle = hash
+            .nearby(point, HOVER_VEHICLE_RADIUS)
+            .min_by(|a, b| a.pos.distance(point).total_cmp(&b.pos.distance(point)))
+            .map(|vehicle| vehicle.entity);
+        vehicle.or_else(|| grid_query.get_single().ok()?.entity_at(GridCell::at(point)).ok().flatten())
+    });
+
+    if entity == hovered.entity {
+        hovered.seconds += time.delta_seconds();
+    } else {
+        hovered.entity = entity;
+        hovered.seconds = 0.0;
+    }
+}
+
+fn cycle_control_on_key_press(
+    actions: Actions,
+    selection: Res<Selection>,
+    network: RoadNetwork,
+    mut request: EventWriter<RequestIntersectionControl>,
+) {
+    if !actions.just_pressed(Action::CycleIntersectionControl) {
+        return;
+    }
+
+    if let Some((entity, intersection)) = selection.entity.and_then(|entity| Some((entity, network.intersection(entity)?))) {
+        request.send(RequestIntersectionControl {
+            entity,
+            control: intersection.control.next(),
+        });
+    }
+}
+
+fn visualize_inspected(selection: Res<Selection>, network: RoadNetwork, mut gizmos: Gizmos) {
+    let Some((entity, intersection)) = selection.entity.and_then(|entity| Some((entity, network.intersection(entity)?)))
+    else {
+        return;
+    };
+
+    let area = intersection.area();
+    gizmos.cuboid(
+        Transform::from_translation(area.center().with_y(0.5)).with_scale(Vec3::new(
+            area.dimensions().x,
+            1.0,
+            area.dimensions().y,
+        )),
+        Color::linear_rgba(1.0, 1.0, 1.0, 0.8),
+    );
+
+    // Link the inspected intersection to each neighbouring intersection through the road between them.
+    let neighbors = network.neighbors(entity).into_iter().flat_map(|road| network.neighbors(road));
+    for other in neighbors.filter(|&other| other != entity && network.intersection(other).is_some()) {
+        let (Some(road), Some(from), Some(to)) = (
+            network.segment_between(entity, other),
+            network.position(entity),
+            network.position(other),
+        ) else {
+            continue;
+        };
+
+        let color = match network.segment(road).is_some_and(|segment| segment.closed) {
+            true => CLOSED_LINK_COLOR,
+            false => OPEN_LINK_COLOR,
+        };
+        gizmos.line(from.with_y(0.5), to.with_y(0.5), color);
+    }
+}
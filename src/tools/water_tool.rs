@@ -0,0 +1,231 @@
+use crate::{
+    graph::road_graph_events::*,
+    graphics::{camera::*, models::add_render_asset},
+    grid::{grid::*, grid_area::*, terrain::Terrain},
+    input::keymap::{Action, Actions},
+    schedule::UpdateStage,
+    tools::{context_menu::ContextMenu, toolbar::ToolState},
+    types::water::WaterBody,
+    ui::egui::MouseOver,
+};
+use bevy::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+pub struct WaterToolPlugin;
+
+impl Plugin for WaterToolPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestWater>()
+            .add_event::<OnWaterSpawned>()
+            .add_event::<OnWaterDestroyed>()
+            .add_systems(Startup, (spawn_tool, spawn_water_surface))
+            .add_systems(
+                Update,
+                (
+                    (
+                        (update_ground_position).in_set(UpdateStage::UpdateView).run_if(in_state(MouseOver::World)),
+                        (adjust_tool_size, handle_tool_action)
+                            .in_set(UpdateStage::UserInput)
+                            .run_if(in_state(MouseOver::World)),
+                    )
+                        .run_if(in_state(ToolState::Water)),
+                    drain_water.in_set(UpdateStage::SoftDestroy),
+                    spawn_water.in_set(UpdateStage::Spawning),
+                    update_water_surface.in_set(UpdateStage::Visualize),
+                ),
+            );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct WaterTool {
+    dimensions: IVec2,
+    ground_position: Vec3,
+    dragging: bool,
+    drag_start_ground_position: Vec3,
+}
+
+impl WaterTool {
+    fn new() -> Self {
+        Self {
+            dimensions: IVec2::ONE,
+            ground_position: Vec3::ZERO,
+            dragging: false,
+            drag_start_ground_position: Vec3::ZERO,
+        }
+    }
+
+    fn area(&self) -> GridArea {
+        let area = GridArea::at(self.ground_position, self.dimensions.x, self.dimensions.y);
+
+        if self.dragging {
+            area.union(GridArea::at(
+                self.drag_start_ground_position,
+                self.dimensions.x,
+                self.dimensions.y,
+            ))
+        } else {
+            area
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct RequestWater {
+    pub area: GridArea,
+}
+
+impl RequestWater {
+    pub fn new(area: GridArea) -> Self {
+        Self { area }
+    }
+}
+
+#[derive(Component)]
+pub struct WaterSurface;
+
+fn spawn_tool(mut commands: Commands) {
+    commands.spawn(WaterTool::new());
+}
+
+fn spawn_water_surface(mut commands: Commands, mut materials: Option<ResMut<Assets<StandardMaterial>>>) {
+    commands.spawn((
+        PbrBundle {
+            material: add_render_asset(
+                &mut materials,
+                StandardMaterial {
+                    base_color: Color::srgba(0.1, 0.35, 0.55, 0.85),
+                    perceptual_roughness: 0.1,
+                    reflectance: 0.6,
+                    alpha_mode: AlphaMode::Blend,
+                    ..default()
+                },
+            ),
+            visibility: Visibility::Hidden,
+            ..default()
+        },
+        WaterSurface,
+    ));
+}
+
+fn update_ground_position(
+    cursor: CursorPick,
+    mut tool_query: Query<&mut WaterTool>,
+    grid_query: Query<&Grid>,
+    mut gizmos: Gizmos,
+) {
+    let mut tool = tool_query.single_mut();
+
+    if let Some(point) = cursor.ground() {
+        tool.ground_position = point;
+        let area = tool.area();
+
+        let mut gizmo_color = if grid_query.single().is_valid_paint_area(area) {
+            Color::linear_rgba(0.0, 0.4, 1.0, 0.8)
+        } else {
+            Color::linear_rgba(1.0, 0.0, 0.0, 0.25)
+        };
+
+        if cursor.camera_moving() {
+            gizmo_color = gizmo_color.with_alpha(0.25);
+        }
+
+        gizmos.rect(
+            area.center().with_y(cursor.terrain().bounds(area).1 + 0.01),
+            Quat::from_rotation_x(FRAC_PI_2),
+            area.dimensions(),
+            gizmo_color,
+        );
+    }
+}
+
+fn adjust_tool_size(mut query: Query<&mut WaterTool>, actions: Actions) {
+    let mut tool = query.single_mut();
+
+    if actions.just_pressed(Action::AdjustToolUp) {
+        tool.dimensions.x += 1;
+        tool.dimensions.y += 1;
+    }
+    if actions.just_pressed(Action::AdjustToolDown) {
+        tool.dimensions.x -= 1;
+        tool.dimensions.y -= 1;
+    }
+
+    tool.dimensions = tool.dimensions.max(IVec2::new(1, 1));
+}
+
+// Water can only be painted over empty ground. It is removed again with the bulldozer.
+fn handle_tool_action(
+    mut query: Query<&mut WaterTool>,
+    grid_query: Query<&Grid>,
+    actions: Actions,
+    mut painter: EventWriter<RequestWater>,
+    mut menu: ResMut<ContextMenu>,
+) {
+    let mut tool = query.single_mut();
+
+    if tool.dragging && actions.secondary_just_pressed() {
+        tool.dragging = false;
+        menu.consume_press();
+    }
+
+    if actions.primary_just_pressed() && !actions.mouse_modifier_held() {
+        tool.dragging = true;
+        tool.drag_start_ground_position = tool.ground_position;
+    }
+
+    if actions.just_pressed(Action::Cancel) {
+        tool.dragging = false;
+    }
+
+    if tool.dragging && actions.primary_just_released() {
+        let area = tool.area();
+        tool.dragging = false;
+
+        if grid_query.single().is_valid_paint_area(area) {
+            painter.send(RequestWater::new(area));
+        }
+    }
+}
+
+fn spawn_water(
+    mut spawner: EventReader<RequestWater>,
+    mut event: EventWriter<OnWaterSpawned>,
+    mut commands: Commands,
+    mut terrain: ResMut<Terrain>,
+) {
+    for &RequestWater { area } in spawner.read() {
+        terrain.add_water(area);
+        let entity = commands.spawn(WaterBody::new(area)).id();
+        event.send(OnWaterSpawned(entity));
+    }
+}
+
+fn drain_water(mut destroyed: EventReader<OnWaterDestroyed>, water_query: Query<&WaterBody>, mut terrain: ResMut<Terrain>) {
+    for &OnWaterDestroyed(entity) in destroyed.read() {
+        if let Ok(water) = water_query.get(entity) {
+            terrain.remove_water(water.area());
+        }
+    }
+}
+
+fn update_water_surface(
+    terrain: Res<Terrain>,
+    mut surface_query: Query<(&mut Handle<Mesh>, &mut Visibility), With<WaterSurface>>,
+    mut meshes: Option<ResMut<Assets<Mesh>>>,
+) {
+    if !terrain.is_changed() {
+        return;
+    }
+
+    let Ok((mut mesh, mut visibility)) = surface_query.get_single_mut() else {
+        return;
+    };
+
+    if terrain.has_water() {
+        *mesh = add_render_asset(&mut meshes, terrain.water_mesh());
+        *visibility = Visibility::Visible;
+    } else {
+        *visibility = Visibility::Hidden;
+    }
+}
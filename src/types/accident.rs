@@ -0,0 +1,338 @@
+use crate::{
+    determinism::determinism::SimRng,
+    graph::road_network::RoadNetwork,
+    graphics::models::Models,
+    grid::terrain::Terrain,
+    schedule::UpdateStage,
+    tools::road_tool::ROAD_HEIGHT,
+    types::{
+        road_segment::RoadSegment,
+        spatial_hash::VehicleSpatialHash,
+        vehicle::{observe_path, update_speed, TripSpawner, Vehicle, VehicleKind},
+        work_zone::WorkZone,
+    },
+};
+use bevy::{ecs::system::SystemParam, prelude::*};
+use rand::Rng;
+
+const CRASH_MIN_SPEED: f32 = 0.2;
+const MARKER_HEIGHT: f32 = 0.8;
+const MARKER_SCALE: f32 = 2.0;
+const RESPONDER_CLEAR_SECONDS: f32 = 5.0;
+const RESPONDER_ORIGINS: usize = 8;
+
+pub struct AccidentPlugin;
+
+impl Plugin for AccidentPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OnAccident>()
+            .add_event::<OnAccidentCleared>()
+            .insert_resource(AccidentSettings::default())
+            .add_systems(
+                Update,
+                (
+                    detect_collisions.after(update_speed).in_set(UpdateStage::AiBehavior),
+                    clear_accidents.in_set(UpdateStage::HighLevelSideEffects),
+                    respond_to_accidents.in_set(UpdateStage::UpdatePathing),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct AccidentSettings {
+    pub enabled: bool,
+    pub chance_per_second: f32,
+    pub overlap_distance: f32,
+    pub block_seconds: f32,
+    pub dispatch_emergency: bool,
+}
+
+impl Default for AccidentSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            chance_per_second: 0.1,
+            overlap_distance: 0.4,
+            block_seconds: 30.0,
+            dispatch_emergency: true,
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct OnAccident {
+    pub segment: Entity,
+    pub lane: i32,
+}
+
+#[derive(Event, Debug)]
+pub struct OnAccidentCleared {
+    pub segment: Entity,
+}
+
+// The crash marker. The vehicles involved stay where they stopped until it is cleared.
+#[derive(Component, Debug)]
+pub struct Accident {
+    pub segment: Entity,
+    pub lane: i32,
+    pub vehicles: [Entity; 2],
+    pub remaining: f32,
+    pub responder: Option<Entity>,
+    closed_road: bool,
+}
+
+// Marks where a crash happened and announces it.
+#[derive(SystemParam)]
+struct AccidentSites<'w, 's> {
+    commands: Commands<'w, 's>,
+    models: Res<'w, Models>,
+    terrain: Res<'w, Terrain>,
+    occurred: EventWriter<'w, OnAccident>,
+}
+
+impl AccidentSites<'_, '_> {
+    fn mark(&mut self, site: Vec3, accident: Accident) {
+        self.occurred.send(OnAccident {
+            segment: accident.segment,
+            lane: accident.lane,
+        });
+
+        self.commands.spawn((
+            PbrBundle {
+                mesh: self.models.cone_mesh.clone(),
+                material: self.models.cone_material.clone(),
+                transform: Transform::from_translation(
+                    site.with_y(self.terrain.road_height(site) + ROAD_HEIGHT + MARKER_HEIGHT),
+                )
+                .with_scale(Vec3::splat(MARKER_SCALE)),
+                ..default()
+            },
+            accident,
+        ));
+    }
+}
+
+// Two vehicles on the same road that end up closer than the overlap distance have a small chance
+// per second of colliding. A single lane road is closed outright, since nobody can pass the wreck.
+fn detect_collisions(
+    mut sites: AccidentSites,
+    mut vehicle_query: Query<(Entity, &mut Vehicle, &Transform)>,
+    mut segment_query: Query<&mut RoadSegment>,
+    hash: Res<VehicleSpatialHash>,
+    settings: Res<AccidentSettings>,
+    mut sim_rng: ResMut<SimRng>,
+    time: Res<Time>,
+) {
+    if !settings.enabled {
+        return;
+    }
+
+    let can_crash =
+        |vehicle: &Vehicle| !vehicle.crashed && vehicle.parked.is_none() && vehicle.kind != VehicleKind::Emergency;
+
+    let mut pairs = Vec::new();
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        let step = vehicle.path.get(vehicle.path_index).copied();
+        if !can_crash(vehicle) || !step.is_some_and(|step| segment_query.contains(step)) {
+            continue;
+        }
+
+        for other in hash.nearby(transform.translation, settings.overlap_distance) {
+            if other.entity <= entity || other.step != step {
+                continue;
+            }
+
+            let Ok((_, other_vehicle, _)) = vehicle_query.get(other.entity) else {
+                continue;
+            };
+
+            if can_crash(other_vehicle) && vehicle.speed.max(other_vehicle.speed) > CRASH_MIN_SPEED {
+                pairs.push((entity, other.entity));
+            }
+        }
+    }
+
+    let chance = (settings.chance_per_second * time.delta_seconds()).clamp(0.0, 1.0) as f64;
+
+    for (a, b) in pairs {
+        if !sim_rng.rng().gen_bool(chance) {
+            continue;
+        }
+
+        let Ok([(_, mut first, first_transform), (_, mut second, second_transform)]) = vehicle_query.get_many_mut([a, b])
+        else {
+            continue;
+        };
+
+        if first.crashed || second.crashed {
+            continue;
+        }
+
+        let segment_entity = first.path[first.path_index];
+        let lane = first.lane;
+        let site = first_transform.translation.lerp(second_transform.translation, 0.5);
+
+        for vehicle in [&mut first, &mut second] {
+            vehicle.crashed = true;
+            vehicle.speed = 0.0;
+            vehicle.indicator = None;
+        }
+
+        let Ok(mut segment) = segment_query.get_mut(segment_entity) else {
+            continue;
+        };
+
+        let closed_road = segment.num_lanes() <= 1;
+        if closed_road {
+            segment.closed = true;
+        }
+
+        sites.mark(
+            site,
+            Accident {
+                segment: segment_entity,
+                lane,
+                vehicles: [a, b],
+                remaining: settings.block_seconds,
+                responder: None,
+                closed_road,
+            },
+        );
+    }
+}
+
+// Traffic that still has a closed road ahead detours from its next intersection and rejoins the
+// route past the wreck. An emergency vehicle can be sent from a nearby building to drive through
+// the scene behind the wreck and back again.
+fn respond_to_accidents(
+    mut trips: TripSpawner,
+    mut accident_query: Query<&mut Accident, Added<Accident>>,
+    mut vehicle_query: Query<&mut Vehicle>,
+    settings: Res<AccidentSettings>,
+    mut sim_rng: ResMut<SimRng>,
+) {
+    for mut accident in &mut accident_query {
+        let Some(segment) = trips.network.segment(accident.segment) else {
+            continue;
+        };
+
+        if accident.closed_road {
+            for &observer in &segment.observers {
+                let Ok(mut vehicle) = vehicle_query.get_mut(observer) else {
+                    continue;
+                };
+
+                if let Some(detour) = detour_around(&mut vehicle, accident.segment, &trips.network) {
+                    observe_path(&mut trips.commands, observer, detour);
+                }
+            }
+        }
+
+        if !settings.dispatch_emergency {
+            continue;
+        }
+
+        let Ok(crashed) = vehicle_query.get(accident.vehicles[0]) else {
+            continue;
+        };
+
+        let Some(&ahead) =
+            crashed.path.get(crashed.path_index + 1).filter(|&&step| trips.network.intersection(step).is_some())
+        else {
+            continue;
+        };
+
+        let Some(behind) = segment.ends.iter().flatten().copied().find(|&end| end != ahead) else {
+            continue;
+        };
+
+        let site = segment.pos();
+        let mut origins: Vec<(Entity, f32)> =
+            trips.network.buildings().map(|(entity, building)| (entity, building.pos().distance_squared(site))).collect();
+        origins.sort_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let route = origins.into_iter().take(RESPONDER_ORIGINS).find_map(|(origin, _)| {
+            let inbound = trips.network.path(origin, behind)?;
+            let outbound = trips.network.path(ahead, origin)?;
+            Some(inbound.into_iter().chain([accident.segment]).chain(outbound).collect::<Vec<Entity>>())
+        });
+
+        if let Some(path) = route {
+            let rng = sim_rng.rng();
+            accident.responder = trips.spawn(rng, VehicleKind::Emergency, path);
+        }
+    }
+}
+
+// Replaces the stretch of the route from the last intersection before the closed road up to the
+// step after it.
+fn detour_around(vehicle: &mut Vehicle, closed: Entity, network: &RoadNetwork) -> Option<Vec<Entity>> {
+    let blocked =
+        vehicle.path.iter().skip(vehicle.path_index + 1).position(|&step| step == closed)? + vehicle.path_index + 1;
+    let rejoin = *vehicle.path.get(blocked + 1)?;
+    let from = (vehicle.path_index..blocked).rev().find(|&index| network.intersection(vehicle.path[index]).is_some())?;
+    let detour = network.path(vehicle.path[from], rejoin)?;
+
+    vehicle.path.splice(from..=blocked + 1, detour.iter().copied());
+    Some(detour)
+}
+
+// Wrecks are towed away once the blockage runs out, or shortly after the responder reaches the
+// blocked road. A closed road reopens when its last wreck is gone, unless it is also under
+// construction.
+fn clear_accidents(
+    mut commands: Commands,
+    mut accident_query: Query<(Entity, &mut Accident)>,
+    mut segment_query: Query<(&mut RoadSegment, Has<WorkZone>)>,
+    vehicle_query: Query<&Vehicle>,
+    time: Res<Time>,
+    mut cleared: EventWriter<OnAccidentCleared>,
+) {
+    let mut reopened = Vec::new();
+    let mut blocked = Vec::new();
+
+    for (entity, mut accident) in &mut accident_query {
+        let on_scene = accident
+            .responder
+            .and_then(|responder| vehicle_query.get(responder).ok())
+            .is_some_and(|responder| responder.path.get(responder.path_index) == Some(&accident.segment));
+
+        if on_scene {
+            accident.remaining = accident.remaining.min(RESPONDER_CLEAR_SECONDS);
+        }
+
+        accident.remaining -= time.delta_seconds();
+
+        let wrecked = accident.vehicles.iter().any(|&vehicle| vehicle_query.contains(vehicle));
+        if accident.remaining > 0.0 && wrecked {
+            blocked.push(accident.segment);
+            continue;
+        }
+
+        for &vehicle in &accident.vehicles {
+            if let Some(vehicle) = commands.get_entity(vehicle) {
+                vehicle.despawn_recursive();
+            }
+        }
+
+        commands.entity(entity).despawn_recursive();
+
+        if accident.closed_road {
+            reopened.push(accident.segment);
+        }
+
+        cleared.send(OnAccidentCleared {
+            segment: accident.segment,
+        });
+    }
+
+    for entity in reopened {
+        if let Ok((mut segment, under_construction)) = segment_query.get_mut(entity) {
+            if !under_construction && !blocked.contains(&entity) {
+                segment.closed = false;
+            }
+        }
+    }
+}
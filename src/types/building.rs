@@ -1,19 +1,147 @@
-use crate::grid::grid_area::*;
-use bevy::{prelude::*, utils::HashSet};
+use crate::grid::{grid_area::*, grid_cell::GridCell};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+const RESIDENTS_PER_CELL: f32 = 4.0;
+const COMMERCIAL_JOBS_PER_CELL: f32 = 2.0;
+const INDUSTRIAL_JOBS_PER_CELL: f32 = 3.0;
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum Zone {
+    #[default]
+    Residential,
+    Commercial,
+    Industrial,
+}
+
+impl Zone {
+    pub const ALL: [Zone; 3] = [Zone::Residential, Zone::Commercial, Zone::Industrial];
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum BuildingKind {
+    #[default]
+    House,
+    Shop,
+    Office,
+    Factory,
+    Parking,
+    Stadium,
+    Mall,
+}
+
+impl BuildingKind {
+    pub const ALL: [BuildingKind; 7] = [
+        BuildingKind::House,
+        BuildingKind::Shop,
+        BuildingKind::Office,
+        BuildingKind::Factory,
+        BuildingKind::Parking,
+        BuildingKind::Stadium,
+        BuildingKind::Mall,
+    ];
+
+    pub fn zone(&self) -> Zone {
+        match self {
+            BuildingKind::House => Zone::Residential,
+            BuildingKind::Shop
+            | BuildingKind::Office
+            | BuildingKind::Parking
+            | BuildingKind::Stadium
+            | BuildingKind::Mall => Zone::Commercial,
+            BuildingKind::Factory => Zone::Industrial,
+        }
+    }
+
+    // Landmarks draw a crowd now and then, on top of their everyday trips.
+    pub fn is_landmark(&self) -> bool {
+        matches!(self, BuildingKind::Stadium | BuildingKind::Mall)
+    }
+
+    // Relative chance of being picked as a trip origin and destination at the given hour of the game clock.
+    pub fn trip_weights(&self, hour: f32) -> (f32, f32) {
+        let morning = (6.0..10.0).contains(&hour);
+        let evening = (16.0..20.0).contains(&hour);
+
+        match self {
+            BuildingKind::Parking => (0.0, 0.0),
+            BuildingKind::Stadium => (0.1, 0.25),
+            BuildingKind::House if morning => (4.0, 0.25),
+            BuildingKind::House if evening => (0.25, 4.0),
+            BuildingKind::House => (1.0, 1.0),
+            BuildingKind::Shop | BuildingKind::Mall if (10.0..20.0).contains(&hour) => (1.0, 2.0),
+            BuildingKind::Office | BuildingKind::Factory if morning => (0.25, 3.0),
+            BuildingKind::Office | BuildingKind::Factory if evening => (3.0, 0.25),
+            _ => (0.5, 0.5),
+        }
+    }
+}
 
 #[derive(Component, Debug)]
 pub struct Building {
     pub area: GridArea,
+    pub kind: BuildingKind,
+    pub seed: u64,
     pub roads: HashSet<Entity>,
+    pub entrances: HashMap<Entity, GridCell>,
     pub observers: HashSet<Entity>,
+    // Set from the district policy of the cells the building stands on.
+    pub spawn_multiplier: f32,
 }
 
 impl Building {
-    pub fn new(area: GridArea) -> Self {
+    pub fn new(area: GridArea, kind: BuildingKind, seed: u64) -> Self {
         Self {
             area,
+            kind,
+            seed,
             roads: HashSet::new(),
+            entrances: HashMap::new(),
             observers: HashSet::new(),
+            spawn_multiplier: 1.0,
+        }
+    }
+
+    // Saves from before buildings carried a seed derive one from their area, so they keep a stable
+    // look and are written back in the old format.
+    pub fn legacy_seed(area: GridArea) -> u64 {
+        let [a, b, c, d] = [area.min.pos.x, area.min.pos.y, area.max.pos.x, area.max.pos.y].map(|v| v as u32 as u64);
+        (a << 48 ^ b << 32 ^ c << 16 ^ d).wrapping_mul(0x9E37_79B9_7F4A_7C15)
+    }
+
+    pub fn has_legacy_seed(&self) -> bool {
+        self.seed == Self::legacy_seed(self.area)
+    }
+
+    pub fn zone(&self) -> Zone {
+        self.kind.zone()
+    }
+
+    // Residents for homes and jobs for workplaces. Parking lots hold neither.
+    pub fn occupants(&self) -> f32 {
+        let cells = self.area.cell_dimensions().element_product() as f32;
+        match (self.kind, self.zone()) {
+            (BuildingKind::Parking, _) => 0.0,
+            (_, Zone::Residential) => cells * RESIDENTS_PER_CELL,
+            (_, Zone::Commercial) => cells * COMMERCIAL_JOBS_PER_CELL,
+            (_, Zone::Industrial) => cells * INDUSTRIAL_JOBS_PER_CELL,
+        }
+    }
+
+    pub fn residents(&self) -> u32 {
+        match self.zone() {
+            Zone::Residential => self.occupants() as u32,
+            _ => 0,
+        }
+    }
+
+    pub fn jobs(&self) -> u32 {
+        match self.zone() {
+            Zone::Residential => 0,
+            _ => self.occupants() as u32,
         }
     }
 
@@ -24,4 +152,32 @@ impl Building {
     pub fn pos(&self) -> Vec3 {
         self.area.center()
     }
+
+    pub fn connect(&mut self, road: Entity, road_area: GridArea) {
+        self.roads.insert(road);
+        self.entrances.insert(road, self.entrance_facing(road_area));
+    }
+
+    pub fn disconnect(&mut self, road: Entity) {
+        self.roads.remove(&road);
+        self.entrances.remove(&road);
+    }
+
+    // Where vehicles turn in from `road` and pull out onto it, falling back to the middle of the lot.
+    pub fn entrance(&self, road: Entity) -> Vec3 {
+        self.entrances.get(&road).map_or(self.pos(), |cell| cell.center())
+    }
+
+    // The cell on the building's edge facing a road it borders, halfway along the stretch they share.
+    fn entrance_facing(&self, road_area: GridArea) -> GridCell {
+        let (min, max) = (self.area.min.pos, self.area.max.pos);
+        let shared = |low: i32, high: i32, road_low: i32, road_high: i32| {
+            (low.max(road_low) + high.min(road_high)).div_euclid(2).clamp(low, high)
+        };
+
+        GridCell::new(
+            shared(min.x, max.x, road_area.min.pos.x, road_area.max.pos.x),
+            shared(min.y, max.y, road_area.min.pos.y, road_area.max.pos.y),
+        )
+    }
 }
@@ -0,0 +1,332 @@
+use crate::{
+    graph::congestion::Congestion,
+    grid::{
+        grid_area::GridArea,
+        grid_cell::GridCell,
+        ground::{GroundLayer, GroundPaint},
+    },
+    schedule::UpdateStage,
+    types::{
+        building::{Building, Zone},
+        road_segment::RoadSegment,
+        vehicle::Vehicle,
+    },
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+use serde::{Deserialize, Serialize};
+
+const STATS_SECONDS: f32 = 1.0;
+const OVERLAY_ALPHA: f32 = 0.45;
+
+pub struct DistrictPlugin;
+
+impl Plugin for DistrictPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestDistrictPaint>()
+            .insert_resource(Districts::default())
+            .insert_resource(DistrictStats::default())
+            .add_systems(
+                Update,
+                (
+                    paint_districts.in_set(UpdateStage::Spawning),
+                    (apply_district_policies, count_district_trips, update_district_stats).in_set(UpdateStage::Analyze),
+                    paint_district_overlay.in_set(UpdateStage::Visualize),
+                ),
+            );
+    }
+}
+
+// Paints `area` into a district, or takes it out of any district when `district` is None.
+#[derive(Event, Debug)]
+pub struct RequestDistrictPaint {
+    pub area: GridArea,
+    pub district: Option<u32>,
+}
+
+// Multipliers applied to the roads and buildings inside a district. Speed limits scale the limit of
+// each road, spawning scales how many trips the buildings start.
+#[derive(Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct DistrictPolicy {
+    pub speed_limit_multiplier: f32,
+    pub spawn_multiplier: f32,
+}
+
+impl Default for DistrictPolicy {
+    fn default() -> Self {
+        Self {
+            speed_limit_multiplier: 1.0,
+            spawn_multiplier: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, PartialEq, Debug, Serialize, Deserialize)]
+pub struct District {
+    pub id: u32,
+    pub name: String,
+    pub policy: DistrictPolicy,
+}
+
+impl District {
+    // Spread around the color wheel so neighbouring ids are easy to tell apart.
+    pub fn color(&self) -> LinearRgba {
+        Color::hsl((self.id as f32 * 137.5) % 360.0, 0.7, 0.5).into()
+    }
+}
+
+// Saved as horizontal runs of cells rather than cell by cell.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct DistrictRecord {
+    districts: Vec<District>,
+    runs: Vec<(GridArea, u32)>,
+}
+
+// Named regions of the grid. Every cell is in at most one district, and cells whose district changed
+// are kept in `changed` until the overlay has been painted again.
+#[derive(Resource, Debug, Default)]
+pub struct Districts {
+    pub districts: Vec<District>,
+    cells: HashMap<IVec2, u32>,
+    next_id: u32,
+    changed: HashSet<IVec2>,
+}
+
+impl Districts {
+    pub fn add(&mut self) -> u32 {
+        let id = self.next_id;
+        self.next_id += 1;
+        self.districts.push(District {
+            id,
+            name: format!("District {}", id + 1),
+            policy: DistrictPolicy::default(),
+        });
+        id
+    }
+
+    pub fn remove(&mut self, id: u32) {
+        self.districts.retain(|district| district.id != id);
+        let cells: Vec<IVec2> = self.cells.iter().filter(|&(_, &owner)| owner == id).map(|(&pos, _)| pos).collect();
+        for pos in cells {
+            self.cells.remove(&pos);
+            self.changed.insert(pos);
+        }
+    }
+
+    pub fn get(&self, id: u32) -> Option<&District> {
+        self.districts.iter().find(|district| district.id == id)
+    }
+
+    pub fn at(&self, cell: GridCell) -> Option<&District> {
+        self.get(*self.cells.get(&cell.pos)?)
+    }
+
+    // Roads and buildings belong to the district their middle cell is in.
+    pub fn of(&self, area: GridArea) -> Option<&District> {
+        self.at(GridCell::at(area.center()))
+    }
+
+    pub fn cell_count(&self, id: u32) -> usize {
+        self.cells.values().filter(|&&owner| owner == id).count()
+    }
+
+    fn paint(&mut self, area: GridArea, district: Option<u32>) {
+        let district = district.filter(|&id| self.get(id).is_some());
+        for cell in area.iter() {
+            let changed = match district {
+                Some(id) => self.cells.insert(cell.pos, id) != Some(id),
+                None => self.cells.remove(&cell.pos).is_some(),
+            };
+            if changed {
+                self.changed.insert(cell.pos);
+            }
+        }
+    }
+
+    pub fn record(&self) -> DistrictRecord {
+        let mut cells: Vec<(IVec2, u32)> = self.cells.iter().map(|(&pos, &id)| (pos, id)).collect();
+        cells.sort_by_key(|&(pos, _)| (pos.y, pos.x));
+
+        let mut runs: Vec<(GridArea, u32)> = Vec::new();
+        for (pos, id) in cells {
+            match runs.last_mut() {
+                Some((run, run_id)) if *run_id == id && run.max.pos + IVec2::X == pos => run.max.pos = pos,
+                _ => runs.push((GridArea::new(GridCell { pos }, GridCell { pos }), id)),
+            }
+        }
+
+        DistrictRecord {
+            districts: self.districts.clone(),
+            runs,
+        }
+    }
+
+    pub fn restore(&mut self, record: DistrictRecord) {
+        self.changed.extend(self.cells.keys().copied());
+        self.cells.clear();
+        self.next_id = record.districts.iter().map(|district| district.id + 1).max().unwrap_or(0);
+        self.districts = record.districts;
+
+        for (area, id) in record.runs {
+            self.paint(area, Some(id));
+        }
+    }
+}
+
+#[derive(Debug, Default, Clone)]
+pub struct DistrictSummary {
+    pub id: u32,
+    pub name: String,
+    pub cells: usize,
+    pub buildings: usize,
+    pub population: f32,
+    pub jobs: f32,
+    pub trips: u32,
+    pub congestion: f32,
+}
+
+// A summary per district, refreshed every `STATS_SECONDS`, and the trips started in each since the
+// game began.
+#[derive(Resource, Debug, Default)]
+pub struct DistrictStats {
+    pub summaries: Vec<DistrictSummary>,
+    trips: HashMap<u32, u32>,
+    elapsed: f32,
+}
+
+fn paint_districts(mut request: EventReader<RequestDistrictPaint>, mut districts: ResMut<Districts>) {
+    for &RequestDistrictPaint { area, district } in request.read() {
+        districts.paint(area, district);
+    }
+}
+
+// Roads and buildings pick up their district's policy when they are built and whenever districts
+// are edited.
+fn apply_district_policies(
+    districts: Res<Districts>,
+    mut segment_query: Query<&mut RoadSegment>,
+    mut building_query: Query<&mut Building>,
+) {
+    let policy = |area: GridArea| districts.of(area).map(|district| district.policy).unwrap_or_default();
+
+    for mut segment in &mut segment_query {
+        if !districts.is_changed() && !segment.is_added() {
+            continue;
+        }
+
+        let multiplier = policy(segment.area).speed_limit_multiplier;
+        if segment.speed_limit_multiplier != multiplier {
+            segment.speed_limit_multiplier = multiplier;
+        }
+    }
+
+    for mut building in &mut building_query {
+        if !districts.is_changed() && !building.is_added() {
+            continue;
+        }
+
+        let multiplier = policy(building.area).spawn_multiplier;
+        if building.spawn_multiplier != multiplier {
+            building.spawn_multiplier = multiplier;
+        }
+    }
+}
+
+// A trip counts towards the district of the building it leaves from. Vehicles restored from a save
+// partway along their path were already counted.
+fn count_district_trips(
+    vehicle_query: Query<&Vehicle, Added<Vehicle>>,
+    building_query: Query<&Building>,
+    districts: Res<Districts>,
+    mut stats: ResMut<DistrictStats>,
+) {
+    for vehicle in vehicle_query.iter().filter(|vehicle| vehicle.path_index == 0) {
+        let Some(building) = vehicle.path.first().and_then(|&start| building_query.get(start).ok()) else {
+            continue;
+        };
+
+        if let Some(district) = districts.of(building.area) {
+            *stats.trips.entry(district.id).or_default() += 1;
+        }
+    }
+}
+
+fn update_district_stats(
+    mut stats: ResMut<DistrictStats>,
+    districts: Res<Districts>,
+    building_query: Query<&Building>,
+    segment_query: Query<(&RoadSegment, Option<&Congestion>)>,
+    time: Res<Time>,
+) {
+    stats.elapsed += time.delta_seconds();
+    if stats.elapsed < STATS_SECONDS {
+        return;
+    }
+    stats.elapsed = 0.0;
+
+    let mut summaries: HashMap<u32, DistrictSummary> = districts
+        .districts
+        .iter()
+        .map(|district| {
+            let summary = DistrictSummary {
+                id: district.id,
+                name: district.name.clone(),
+                cells: districts.cell_count(district.id),
+                trips: stats.trips.get(&district.id).copied().unwrap_or(0),
+                ..default()
+            };
+            (district.id, summary)
+        })
+        .collect();
+
+    for building in &building_query {
+        let Some(summary) = districts.of(building.area).and_then(|district| summaries.get_mut(&district.id)) else {
+            continue;
+        };
+
+        summary.buildings += 1;
+        match building.zone() {
+            Zone::Residential => summary.population += building.occupants(),
+            _ => summary.jobs += building.occupants(),
+        }
+    }
+
+    // Congestion is the mean over the district's roads that have been measured.
+    let mut measured: HashMap<u32, u32> = HashMap::new();
+    for (segment, congestion) in &segment_query {
+        let Some(district) = districts.of(segment.area) else {
+            continue;
+        };
+        let (Some(summary), Some(congestion)) = (summaries.get_mut(&district.id), congestion) else {
+            continue;
+        };
+
+        summary.congestion += congestion.ratio;
+        *measured.entry(district.id).or_default() += 1;
+    }
+
+    stats.summaries = districts
+        .districts
+        .iter()
+        .filter_map(|district| {
+            let mut summary = summaries.remove(&district.id)?;
+            summary.congestion /= measured.get(&district.id).copied().unwrap_or(1).max(1) as f32;
+            Some(summary)
+        })
+        .collect();
+}
+
+fn paint_district_overlay(mut districts: ResMut<Districts>, mut paint: ResMut<GroundPaint>) {
+    if districts.changed.is_empty() {
+        return;
+    }
+
+    let changed: Vec<IVec2> = districts.changed.drain().collect();
+    for pos in changed {
+        let cell = GridCell { pos };
+        let color = districts.at(cell).map(|district| district.color().with_alpha(OVERLAY_ALPHA));
+        paint.set(GroundLayer::District, cell, color);
+    }
+}
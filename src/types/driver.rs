@@ -0,0 +1,127 @@
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use std::{f32::consts::TAU, fmt::Debug};
+
+const ERRATIC_SWAY: f32 = 0.25;
+const ERRATIC_SWAY_HZ: f32 = 0.2;
+
+// How a driver behaves on the road. Each vehicle gets one at spawn and keeps it for its whole trip.
+// Values of 1 are the norm the rest of the simulation is tuned for.
+pub trait DriverProfile: Send + Sync + Debug {
+    fn name(&self) -> &'static str;
+
+    // From 0 for the most careful driver to 1 for the most reckless, with 0.5 the norm. Sets how hard
+    // they accelerate and how small a gap they will cut into when changing lanes.
+    fn aggressiveness(&self) -> f32;
+
+    // Scales how far back they stay from the vehicle ahead.
+    fn following_distance(&self) -> f32;
+
+    // Scales how readily they leave their lane to get past slower traffic.
+    fn lane_change_willingness(&self) -> f32;
+
+    // The share of the speed limit they aim for.
+    fn speed_compliance(&self) -> f32;
+
+    // Drift of their target speed over the trip, as a factor on it.
+    fn speed_sway(&self, _trip_time: f32) -> f32 {
+        1.0
+    }
+}
+
+#[derive(Debug)]
+pub struct Calm;
+
+impl DriverProfile for Calm {
+    fn name(&self) -> &'static str {
+        "Calm"
+    }
+
+    fn aggressiveness(&self) -> f32 {
+        0.3
+    }
+
+    fn following_distance(&self) -> f32 {
+        1.2
+    }
+
+    fn lane_change_willingness(&self) -> f32 {
+        0.6
+    }
+
+    fn speed_compliance(&self) -> f32 {
+        0.95
+    }
+}
+
+#[derive(Debug)]
+pub struct Aggressive;
+
+impl DriverProfile for Aggressive {
+    fn name(&self) -> &'static str {
+        "Aggressive"
+    }
+
+    fn aggressiveness(&self) -> f32 {
+        0.9
+    }
+
+    fn following_distance(&self) -> f32 {
+        0.7
+    }
+
+    fn lane_change_willingness(&self) -> f32 {
+        1.8
+    }
+
+    fn speed_compliance(&self) -> f32 {
+        1.15
+    }
+}
+
+// Speeds up and slows down for no reason, and swaps lanes at the first excuse.
+#[derive(Debug)]
+pub struct Erratic;
+
+impl DriverProfile for Erratic {
+    fn name(&self) -> &'static str {
+        "Erratic"
+    }
+
+    fn aggressiveness(&self) -> f32 {
+        0.6
+    }
+
+    fn following_distance(&self) -> f32 {
+        0.9
+    }
+
+    fn lane_change_willingness(&self) -> f32 {
+        2.5
+    }
+
+    fn speed_compliance(&self) -> f32 {
+        1.0
+    }
+
+    fn speed_sway(&self, trip_time: f32) -> f32 {
+        1.0 + ERRATIC_SWAY * (trip_time * ERRATIC_SWAY_HZ * TAU).sin()
+    }
+}
+
+// Every profile drivers are drawn from, matched up with `VehicleSpawnConfig::driver_mix`. The first is
+// the fallback for saves that name a profile no longer here.
+pub static DRIVER_PROFILES: [&dyn DriverProfile; 3] = [&Calm, &Aggressive, &Erratic];
+
+pub fn driver_profile(name: &str) -> &'static dyn DriverProfile {
+    DRIVER_PROFILES.iter().copied().find(|profile| profile.name() == name).unwrap_or(DRIVER_PROFILES[0])
+}
+
+pub fn choose_driver(mix: &[f32; 3], rng: &mut impl Rng) -> &'static dyn DriverProfile {
+    match WeightedIndex::new(mix.map(|weight| weight.max(0.0))) {
+        Ok(distribution) => DRIVER_PROFILES[distribution.sample(rng)],
+        Err(_) => DRIVER_PROFILES[0],
+    }
+}
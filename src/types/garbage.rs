@@ -0,0 +1,320 @@
+use crate::{
+    determinism::determinism::SimRng,
+    graph::road_network::RoadNetwork,
+    graphics::models::Models,
+    grid::{grid::Grid, grid_area::GridArea, grid_cell::GridCell, land_value::LandLayers},
+    schedule::UpdateStage,
+    types::{
+        building::{Building, BuildingKind},
+        vehicle::{update_vehicles, TripSpawner, Vehicle, VehicleKind},
+    },
+};
+use bevy::{prelude::*, utils::HashMap};
+
+// Each stop's next leg is planned to whichever of this many nearest remaining buildings is the
+// shortest drive away.
+const ROUTE_CANDIDATES: usize = 3;
+const MAX_BAGS: usize = 3;
+const BAG_SPACING: f32 = 0.16;
+const BAG_RADIUS: f32 = 0.08;
+const LITTER_RADIUS: i32 = 2;
+const LITTER_REFRESH_SECONDS: f32 = 1.0;
+
+pub struct GarbagePlugin;
+
+impl Plugin for GarbagePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(GarbageSettings::default()).insert_resource(GarbageService::default()).add_systems(
+            Update,
+            (
+                (track_garbage, update_litter).chain().in_set(UpdateStage::AfterSpawning),
+                collect_garbage.before(update_vehicles).in_set(UpdateStage::AiBehavior),
+                dispatch_garbage_trucks
+                    .in_set(UpdateStage::UpdatePathing)
+                    .run_if(|settings: Res<GarbageSettings>| settings.enabled),
+                show_garbage.in_set(UpdateStage::Visualize),
+            ),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct GarbageSettings {
+    pub enabled: bool,
+    pub dispatch_seconds: f32,
+    pub fill_seconds: f32,
+    pub stop_seconds: f32,
+    pub litter_penalty: f32,
+}
+
+impl Default for GarbageSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            dispatch_seconds: 20.0,
+            fill_seconds: 240.0,
+            stop_seconds: 2.0,
+            litter_penalty: 0.4,
+        }
+    }
+}
+
+// How full a building's bins are, from empty at 0 to overflowing at 1, and the pile last shown for it.
+#[derive(Component, Debug, Default)]
+pub struct Garbage {
+    pub level: f32,
+    bags: usize,
+    pile: Option<Entity>,
+}
+
+// A truck on its round of one district. `stops` are its buildings in the order they are visited, and
+// `dwell` counts down while it is stopped at one.
+#[derive(Component, Debug)]
+pub struct GarbageTruck {
+    pub district: IVec2,
+    pub stops: Vec<Entity>,
+    pub next_stop: usize,
+    dwell: Option<f32>,
+}
+
+#[derive(Resource, Debug, Default)]
+pub struct GarbageService {
+    since_dispatch: f32,
+    since_litter: f32,
+    pub collected: u32,
+}
+
+fn track_garbage(
+    mut commands: Commands,
+    mut garbage_query: Query<&mut Garbage>,
+    new_buildings: Query<Entity, (With<Building>, Without<Garbage>)>,
+    settings: Res<GarbageSettings>,
+    time: Res<Time>,
+) {
+    for entity in &new_buildings {
+        commands.entity(entity).insert(Garbage::default());
+    }
+
+    let fill = time.delta_seconds() / settings.fill_seconds.max(f32::EPSILON);
+    for mut garbage in &mut garbage_query {
+        garbage.level = (garbage.level + fill).min(1.0);
+    }
+}
+
+fn update_litter(
+    mut layers: ResMut<LandLayers>,
+    mut service: ResMut<GarbageService>,
+    building_query: Query<(&Building, &Garbage)>,
+    settings: Res<GarbageSettings>,
+    time: Res<Time>,
+) {
+    service.since_litter += time.delta_seconds();
+    if service.since_litter < LITTER_REFRESH_SECONDS {
+        return;
+    }
+    service.since_litter = 0.0;
+
+    layers.set_litter(building_query.iter().map(|(building, garbage)| {
+        let area = GridArea::new(
+            GridCell::new(
+                building.area.min.pos.x - LITTER_RADIUS,
+                building.area.min.pos.y - LITTER_RADIUS,
+            ),
+            GridCell::new(
+                building.area.max.pos.x + LITTER_RADIUS,
+                building.area.max.pos.y + LITTER_RADIUS,
+            ),
+        );
+        (area, garbage.level * settings.litter_penalty)
+    }));
+}
+
+// Trucks stand still for a while at each building on their round and empty its bins when they leave.
+// Standing counts as parked, so other vehicles queue behind them and the watchdog leaves them be.
+fn collect_garbage(
+    mut truck_query: Query<(&mut Vehicle, &mut GarbageTruck)>,
+    mut garbage_query: Query<&mut Garbage>,
+    mut service: ResMut<GarbageService>,
+    settings: Res<GarbageSettings>,
+    time: Res<Time>,
+) {
+    for (mut vehicle, mut truck) in &mut truck_query {
+        let Some(&stop) = truck.stops.get(truck.next_stop) else {
+            continue;
+        };
+
+        match truck.dwell {
+            Some(remaining) if remaining > 0.0 => {
+                truck.dwell = Some(remaining - time.delta_seconds());
+                vehicle.speed = 0.0;
+                continue;
+            }
+            Some(_) => {
+                if let Ok(mut garbage) = garbage_query.get_mut(stop) {
+                    garbage.level = 0.0;
+                    service.collected += 1;
+                }
+                truck.dwell = None;
+                truck.next_stop += 1;
+                vehicle.parked = None;
+                vehicle.waiting = false;
+            }
+            None if vehicle.path.get(vehicle.path_index) == Some(&stop) && vehicle.path_index + 1 < vehicle.path.len() => {
+                truck.dwell = Some(settings.stop_seconds);
+                vehicle.parked = Some(settings.stop_seconds);
+                vehicle.waiting = true;
+                vehicle.speed = 0.0;
+            }
+            None => {}
+        }
+    }
+}
+
+// Every so often the district with the most garbage that has no truck on it yet gets one, setting off
+// from the depot that serves it.
+fn dispatch_garbage_trucks(
+    mut trips: TripSpawner,
+    mut service: ResMut<GarbageService>,
+    garbage_query: Query<&Garbage>,
+    truck_query: Query<&GarbageTruck>,
+    mut sim_rng: ResMut<SimRng>,
+    settings: Res<GarbageSettings>,
+    time: Res<Time>,
+) {
+    service.since_dispatch += time.delta_seconds();
+    if service.since_dispatch < settings.dispatch_seconds {
+        return;
+    }
+    service.since_dispatch = 0.0;
+
+    let mut districts = HashMap::<IVec2, (f32, Vec<Entity>)>::new();
+    for (entity, building) in trips.network.buildings() {
+        let district = districts.entry(Grid::chunk_of(GridCell::at(building.pos()))).or_default();
+        district.0 += garbage_query.get(entity).map_or(0.0, |garbage| garbage.level);
+        district.1.push(entity);
+    }
+
+    let served: Vec<IVec2> = truck_query.iter().map(|truck| truck.district).collect();
+    let Some((district, (_, buildings))) = districts
+        .into_iter()
+        .filter(|(district, (total, _))| *total > 0.0 && !served.contains(district))
+        .max_by(|(a, (a_total, _)), (b, (b_total, _))| a_total.total_cmp(b_total).then((b.x, b.y).cmp(&(a.x, a.y))))
+    else {
+        return;
+    };
+
+    let Some(depot) = depot_for(&trips.network, &buildings) else {
+        return;
+    };
+
+    let Some((stops, path)) = plan_round(&trips.network, depot, buildings) else {
+        return;
+    };
+
+    let truck = trips.spawn(sim_rng.rng(), VehicleKind::Truck, path);
+
+    if let Some(truck) = truck {
+        trips.commands.entity(truck).insert(GarbageTruck {
+            district,
+            stops,
+            next_stop: 0,
+            dwell: None,
+        });
+    }
+}
+
+// The factory closest to the district, or failing any, the district's most central building.
+fn depot_for(network: &RoadNetwork, buildings: &[Entity]) -> Option<Entity> {
+    let positions: Vec<Vec3> = buildings.iter().filter_map(|&entity| network.position(entity)).collect();
+    let center = positions.iter().sum::<Vec3>() / positions.len().max(1) as f32;
+    let distance = |entity: Entity| network.position(entity).map_or(f32::INFINITY, |pos| pos.distance(center));
+
+    network
+        .buildings()
+        .filter(|(_, building)| building.kind == BuildingKind::Factory)
+        .map(|(entity, _)| entity)
+        .min_by(|&a, &b| distance(a).total_cmp(&distance(b)))
+        .or_else(|| buildings.iter().copied().min_by(|&a, &b| distance(a).total_cmp(&distance(b))))
+}
+
+fn path_length(network: &RoadNetwork, path: &[Entity]) -> f32 {
+    path.windows(2).filter_map(|pair| Some(network.position(pair[0])?.distance(network.position(pair[1])?))).sum()
+}
+
+// A greedy tour: from each stop, drive to whichever of the few nearest unvisited buildings is the
+// shortest drive away. Buildings that cannot be reached are left out, and the round ends back at
+// the depot.
+fn plan_round(network: &RoadNetwork, depot: Entity, mut remaining: Vec<Entity>) -> Option<(Vec<Entity>, Vec<Entity>)> {
+    remaining.retain(|&entity| entity != depot);
+
+    let mut current = depot;
+    let mut stops = Vec::new();
+    let mut path = vec![depot];
+
+    while !remaining.is_empty() {
+        let from = network.position(current)?;
+        remaining.sort_by(|&a, &b| {
+            let distance = |entity: Entity| network.position(entity).map_or(f32::INFINITY, |pos| pos.distance(from));
+            distance(a).total_cmp(&distance(b))
+        });
+
+        let candidates = remaining.len().min(ROUTE_CANDIDATES);
+        let best = remaining[..candidates]
+            .iter()
+            .enumerate()
+            .filter_map(|(index, &next)| network.path(current, next).map(|leg| (index, leg)))
+            .min_by(|(_, a), (_, b)| path_length(network, a).total_cmp(&path_length(network, b)));
+
+        let Some((index, leg)) = best else {
+            remaining.drain(..candidates);
+            continue;
+        };
+
+        current = remaining.remove(index);
+        stops.push(current);
+        path.extend(leg.into_iter().skip(1));
+    }
+
+    if stops.is_empty() {
+        return None;
+    }
+
+    path.extend(network.path(current, depot)?.into_iter().skip(1));
+    Some((stops, path))
+}
+
+// A pile of bags by the corner of each building, one more for every third of the way its bins fill.
+fn show_garbage(mut commands: Commands, mut garbage_query: Query<(Entity, &Building, &mut Garbage)>, models: Res<Models>) {
+    for (entity, building, mut garbage) in &mut garbage_query {
+        let bags = ((garbage.level * (MAX_BAGS + 1) as f32) as usize).min(MAX_BAGS);
+        if bags == garbage.bags {
+            continue;
+        }
+
+        if let Some(pile) = garbage.pile.take() {
+            commands.entity(pile).despawn_recursive();
+        }
+        garbage.bags = bags;
+
+        if bags == 0 {
+            continue;
+        }
+
+        let corner = (-building.area.dimensions() / 2.0 + Vec2::splat(BAG_SPACING)).extend(BAG_RADIUS).xzy();
+        let pile = commands
+            .spawn(SpatialBundle::from_transform(Transform::from_translation(corner)))
+            .with_children(|parent| {
+                for bag in 0..bags {
+                    parent.spawn(PbrBundle {
+                        mesh: models.garbage_bag_mesh.clone(),
+                        material: models.garbage_bag_material.clone(),
+                        transform: Transform::from_translation(Vec3::X * bag as f32 * BAG_SPACING),
+                        ..default()
+                    });
+                }
+            })
+            .id();
+        commands.entity(entity).add_child(pile);
+        garbage.pile = Some(pile);
+    }
+}
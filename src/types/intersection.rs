@@ -1,11 +1,40 @@
 use crate::grid::grid_area::*;
 use bevy::{prelude::*, utils::HashSet};
 
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default)]
+pub enum IntersectionControl {
+    Uncontrolled,
+    StopSign,
+    #[default]
+    Signal,
+}
+
+impl IntersectionControl {
+    pub fn next(self) -> Self {
+        match self {
+            IntersectionControl::Uncontrolled => IntersectionControl::StopSign,
+            IntersectionControl::StopSign => IntersectionControl::Signal,
+            IntersectionControl::Signal => IntersectionControl::Uncontrolled,
+        }
+    }
+}
+
 #[derive(Component, Debug)]
 pub struct Intersection {
     pub area: GridArea,
     pub roads: [Option<Entity>; 4],
     pub observers: HashSet<Entity>,
+    pub control: IntersectionControl,
+    // Stop sign bookkeeping: vehicles halted at the stop line in arrival order, and the vehicles
+    // currently allowed to cross.
+    pub arrivals: Vec<Entity>,
+    pub reservations: HashSet<Entity>,
+    // Turns vehicles may not make, as (approach slot, exit slot) pairs.
+    pub restricted_turns: HashSet<(usize, usize)>,
+    // Vehicles holding a place inside, whether already in or let in from the stop line, and how many
+    // are queued on the roads leading in.
+    pub occupants: HashSet<Entity>,
+    pub queue: usize,
 }
 
 impl Intersection {
@@ -14,6 +43,12 @@ impl Intersection {
             area,
             roads: [None; 4],
             observers: HashSet::new(),
+            control: IntersectionControl::default(),
+            arrivals: Vec::new(),
+            reservations: HashSet::new(),
+            restricted_turns: HashSet::new(),
+            occupants: HashSet::new(),
+            queue: 0,
         }
     }
 
@@ -24,4 +59,25 @@ impl Intersection {
     pub fn pos(&self) -> Vec3 {
         self.area.center()
     }
+
+    // How many vehicles fit inside at once, one for every `cells_per_vehicle` cells of its area.
+    pub fn capacity(&self, cells_per_vehicle: f32) -> usize {
+        (self.area.cell_count() as f32 / cells_per_vehicle.max(1.0)).floor().max(1.0) as usize
+    }
+
+    // Three roads in: one ending against the side of another that runs on through.
+    pub fn is_tee(&self) -> bool {
+        self.roads.iter().flatten().count() == 3
+    }
+
+    pub fn slot_of(&self, road: Entity) -> Option<usize> {
+        self.roads.iter().position(|slot| *slot == Some(road))
+    }
+
+    pub fn allows_turn(&self, from: Entity, to: Entity) -> bool {
+        match (self.slot_of(from), self.slot_of(to)) {
+            (Some(from), Some(to)) => !self.restricted_turns.contains(&(from, to)),
+            _ => true,
+        }
+    }
 }
@@ -0,0 +1,273 @@
+use crate::{
+    determinism::determinism::SimRng,
+    graph::{path_cache::PathCache, road_network::RoadNetwork},
+    schedule::UpdateStage,
+    types::{
+        building::{BuildingKind, Zone},
+        vehicle::{update_vehicles, TripSpawner, Vehicle, VehicleKind},
+    },
+};
+use bevy::prelude::*;
+use rand::{
+    distributions::{Distribution, WeightedIndex},
+    Rng,
+};
+use std::collections::VecDeque;
+
+const MAX_SURGE_SPAWNS_PER_FRAME: usize = 4;
+const RECENT_SURGES: usize = 8;
+const RATE_WINDOW_SECONDS: f32 = 60.0;
+
+pub struct LandmarkPlugin;
+
+impl Plugin for LandmarkPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OnSurgeStarted>()
+            .insert_resource(LandmarkSettings::default())
+            .insert_resource(LandmarkEvents::default())
+            .add_systems(
+                Update,
+                (
+                    count_surge_arrivals.before(update_vehicles).in_set(UpdateStage::AiBehavior),
+                    (schedule_surges, dispatch_surge_trips)
+                        .chain()
+                        .in_set(UpdateStage::UpdatePathing)
+                        .run_if(|settings: Res<LandmarkSettings>| settings.enabled),
+                ),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct LandmarkSettings {
+    pub enabled: bool,
+    pub interval_seconds: f32,
+    pub duration_seconds: f32,
+    pub attendees_per_cell: f32,
+}
+
+impl Default for LandmarkSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            interval_seconds: 240.0,
+            duration_seconds: 60.0,
+            attendees_per_cell: 1.5,
+        }
+    }
+}
+
+#[derive(Event, Debug)]
+pub struct OnSurgeStarted {
+    pub landmark: Entity,
+    pub kind: BuildingKind,
+    pub attendance: u32,
+}
+
+// A vehicle on its way to a surge, until it reaches the landmark.
+#[derive(Component, Debug)]
+pub struct Attendee {
+    surge: u32,
+}
+
+// One event at a landmark. Trips towards it are sent out evenly between `starts_at` and `ends_at`,
+// and each arrival is kept on the events clock so the rate can be read back afterwards.
+#[derive(Debug, Clone)]
+pub struct Surge {
+    pub id: u32,
+    pub landmark: Entity,
+    pub kind: BuildingKind,
+    pub starts_at: f32,
+    pub ends_at: f32,
+    pub attendance: u32,
+    pub dispatched: u32,
+    pub failed: u32,
+    pub arrivals: Vec<f32>,
+    pub trip_time: f32,
+}
+
+impl Surge {
+    pub fn started(&self, clock: f32) -> bool {
+        clock >= self.starts_at
+    }
+
+    pub fn arrived(&self) -> u32 {
+        self.arrivals.len() as u32
+    }
+
+    pub fn mean_trip_time(&self) -> f32 {
+        self.trip_time / self.arrivals.len().max(1) as f32
+    }
+
+    // Arrivals per minute over the last minute.
+    pub fn arrival_rate(&self, clock: f32) -> f32 {
+        let recent = self.arrivals.iter().filter(|&&time| clock - time < RATE_WINDOW_SECONDS).count();
+        recent as f32 * 60.0 / RATE_WINDOW_SECONDS
+    }
+
+    // Arrivals per minute over the busiest minute of the event.
+    pub fn peak_rate(&self) -> f32 {
+        let mut end = 0;
+        let mut peak = 0;
+        for (start, &time) in self.arrivals.iter().enumerate() {
+            while end < self.arrivals.len() && self.arrivals[end] - time < RATE_WINDOW_SECONDS {
+                end += 1;
+            }
+            peak = peak.max(end - start);
+        }
+        peak as f32 * 60.0 / RATE_WINDOW_SECONDS
+    }
+}
+
+// Surges that are scheduled or underway, one per landmark, and the last few that have finished.
+// `clock` only runs while events are enabled.
+#[derive(Resource, Debug, Default)]
+pub struct LandmarkEvents {
+    pub clock: f32,
+    next_id: u32,
+    pub surges: Vec<Surge>,
+    pub finished: VecDeque<Surge>,
+}
+
+impl LandmarkEvents {
+    fn finish(&mut self, surge: Surge) {
+        if surge.started(self.clock) {
+            self.finished.push_front(surge);
+            self.finished.truncate(RECENT_SURGES);
+        }
+    }
+}
+
+fn count_surge_arrivals(
+    mut commands: Commands,
+    vehicle_query: Query<(Entity, &Vehicle, &Attendee)>,
+    mut events: ResMut<LandmarkEvents>,
+) {
+    let clock = events.clock;
+
+    for (entity, vehicle, attendee) in &vehicle_query {
+        let Some(surge) = events.surges.iter_mut().find(|surge| surge.id == attendee.surge) else {
+            commands.entity(entity).remove::<Attendee>();
+            continue;
+        };
+
+        let reached = vehicle.path.iter().take(vehicle.path_index + 1).any(|&step| step == surge.landmark);
+        if reached {
+            surge.arrivals.push(clock);
+            surge.trip_time += vehicle.trip_time;
+            commands.entity(entity).remove::<Attendee>();
+        }
+    }
+}
+
+// Every landmark always has its next event lined up, somewhere between half and one and a half
+// intervals ahead. An event is over once its window has passed and the last of its attendees has
+// arrived or given up, and is dropped early if its landmark is removed.
+fn schedule_surges(
+    mut events: ResMut<LandmarkEvents>,
+    attendee_query: Query<&Attendee>,
+    network: RoadNetwork,
+    settings: Res<LandmarkSettings>,
+    mut sim_rng: ResMut<SimRng>,
+    time: Res<Time>,
+    mut started: EventWriter<OnSurgeStarted>,
+) {
+    let previous = events.clock;
+    events.clock += time.delta_seconds();
+    let clock = events.clock;
+
+    let (kept, done): (Vec<Surge>, Vec<Surge>) = events.surges.drain(..).partition(|surge| {
+        let en_route = attendee_query.iter().any(|attendee| attendee.surge == surge.id);
+        network.building(surge.landmark).is_some() && (clock < surge.ends_at || en_route)
+    });
+    events.surges = kept;
+    for surge in done {
+        events.finish(surge);
+    }
+
+    for surge in events.surges.iter().filter(|surge| surge.started(clock) && !surge.started(previous)) {
+        started.send(OnSurgeStarted {
+            landmark: surge.landmark,
+            kind: surge.kind,
+            attendance: surge.attendance,
+        });
+    }
+
+    let unscheduled: Vec<(Entity, BuildingKind, f32)> = network
+        .buildings()
+        .filter(|(entity, building)| {
+            building.kind.is_landmark() && !events.surges.iter().any(|surge| surge.landmark == *entity)
+        })
+        .map(|(entity, building)| (entity, building.kind, building.area.cell_count() as f32))
+        .collect();
+
+    for (landmark, kind, cells) in unscheduled {
+        let starts_at = clock + settings.interval_seconds * sim_rng.rng().gen_range(0.5..1.5);
+        let id = events.next_id;
+        events.next_id += 1;
+        events.surges.push(Surge {
+            id,
+            landmark,
+            kind,
+            starts_at,
+            ends_at: starts_at + settings.duration_seconds,
+            attendance: (cells * settings.attendees_per_cell).ceil() as u32,
+            dispatched: 0,
+            failed: 0,
+            arrivals: Vec::new(),
+            trip_time: 0.0,
+        });
+    }
+
+    events.surges.sort_by(|a, b| a.starts_at.total_cmp(&b.starts_at));
+}
+
+// Attendees leave from homes across the city, picked by how many people live in each, and are sent
+// out at an even pace over the event's window. Trips with no route still count towards the pace.
+fn dispatch_surge_trips(
+    mut trips: TripSpawner,
+    mut events: ResMut<LandmarkEvents>,
+    mut sim_rng: ResMut<SimRng>,
+    mut cache: ResMut<PathCache>,
+    settings: Res<LandmarkSettings>,
+    time: Res<Time>,
+) {
+    let clock = events.clock;
+    let now = time.elapsed_seconds();
+
+    let homes: Vec<(Entity, f32)> = trips
+        .network
+        .buildings()
+        .filter(|(_, building)| building.zone() == Zone::Residential)
+        .map(|(entity, building)| (entity, building.occupants()))
+        .collect();
+    let Ok(origins) = WeightedIndex::new(homes.iter().map(|&(_, residents)| residents)) else {
+        return;
+    };
+
+    let mut spawned = 0;
+    for surge in events.surges.iter_mut().filter(|surge| surge.started(clock)) {
+        let progress = ((clock - surge.starts_at) / settings.duration_seconds.max(f32::EPSILON)).min(1.0);
+        let due = (surge.attendance as f32 * progress).ceil() as u32;
+
+        while surge.dispatched + surge.failed < due && spawned < MAX_SURGE_SPAWNS_PER_FRAME {
+            spawned += 1;
+
+            let rng = sim_rng.rng();
+            let origin = homes[origins.sample(rng)].0;
+            let Some(path) = cache.trip(&trips.network, origin, surge.landmark, now) else {
+                surge.failed += 1;
+                continue;
+            };
+
+            let kind = VehicleKind::Car;
+            match trips.spawn(rng, kind, path) {
+                Some(vehicle) => {
+                    trips.commands.entity(vehicle).insert(Attendee { surge: surge.id });
+                    surge.dispatched += 1;
+                }
+                None => surge.failed += 1,
+            }
+        }
+    }
+}
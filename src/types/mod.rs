@@ -1,4 +1,21 @@
+pub mod accident;
 pub mod building;
+pub mod district;
+pub mod driver;
+pub mod garbage;
 pub mod intersection;
+pub mod landmark;
+pub mod parking;
+pub mod pedestrian;
+pub mod prop;
 pub mod road_segment;
+pub mod spatial_hash;
+pub mod traffic_signal;
+#[cfg(test)]
+mod traffic_signal_tests;
+pub mod trailer;
 pub mod vehicle;
+pub mod vehicle_lod;
+pub mod watchdog;
+pub mod water;
+pub mod work_zone;
@@ -0,0 +1,150 @@
+use crate::{
+    graph::road_network::RoadNetwork,
+    grid::grid_area::GridArea,
+    schedule::UpdateStage,
+    types::{
+        building::{Building, BuildingKind},
+        vehicle::{update_vehicles, OnTripCompleted, Vehicle, VehicleKind},
+    },
+};
+use bevy::{prelude::*, utils::HashSet};
+
+const PARKING_SEARCH_RADIUS: f32 = 12.0;
+const PARKING_DWELL_SECONDS: f32 = 20.0;
+
+pub struct ParkingPlugin;
+
+impl Plugin for ParkingPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (handle_parking.before(update_vehicles), dwell_parked_vehicles).in_set(UpdateStage::AiBehavior),
+        );
+    }
+}
+
+// One slot per cell. Cars reserve a slot when they set off for the lot so a full lot turns others away.
+#[derive(Component, Debug)]
+pub struct ParkingLot {
+    pub slots: Vec<Option<Entity>>,
+    pub reserved: HashSet<Entity>,
+}
+
+impl ParkingLot {
+    pub fn new(area: GridArea) -> Self {
+        Self {
+            slots: vec![None; area.cell_count() as usize],
+            reserved: HashSet::new(),
+        }
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    pub fn occupied(&self) -> usize {
+        self.slots.iter().flatten().count()
+    }
+
+    pub fn free(&self) -> usize {
+        self.capacity().saturating_sub(self.occupied() + self.reserved.len())
+    }
+}
+
+// Cars that reach their destination look for a lot close by and drive on to it. Once there they take
+// a slot, which is when the trip counts as complete.
+fn handle_parking(
+    mut vehicle_query: Query<(Entity, &mut Vehicle, &mut Transform)>,
+    mut lot_query: Query<&mut ParkingLot>,
+    network: RoadNetwork,
+    mut completed: EventWriter<OnTripCompleted>,
+) {
+    for (entity, mut vehicle, mut transform) in &mut vehicle_query {
+        if vehicle.parked.is_some() || vehicle.kind != VehicleKind::Car || vehicle.path_index + 1 < vehicle.path.len() {
+            continue;
+        }
+
+        let Some(destination) = vehicle.destination() else {
+            continue;
+        };
+
+        if let Ok(mut lot) = lot_query.get_mut(destination) {
+            let Some(slot) = lot.slots.iter().position(Option::is_none).filter(|_| lot.reserved.remove(&entity)) else {
+                continue;
+            };
+            lot.slots[slot] = Some(entity);
+
+            let Some(cell) = network.building(destination).and_then(|building| building.area.iter().nth(slot)) else {
+                continue;
+            };
+            transform.translation = cell.center().with_y(transform.translation.y);
+            vehicle.follow = transform.translation + transform.forward().as_vec3();
+            vehicle.speed = 0.0;
+            vehicle.waiting = true;
+            vehicle.parked = Some(PARKING_DWELL_SECONDS);
+
+            completed.send(OnTripCompleted {
+                kind: vehicle.kind,
+                duration: vehicle.trip_time,
+                distance: vehicle.trip_distance,
+            });
+            continue;
+        }
+
+        if vehicle.parking_sought {
+            continue;
+        }
+        vehicle.parking_sought = true;
+
+        let Some(target) = network.building(destination).map(Building::pos) else {
+            continue;
+        };
+
+        let nearest = network
+            .buildings()
+            .filter(|(_, building)| building.kind == BuildingKind::Parking)
+            .filter(|(lot, _)| lot_query.get(*lot).is_ok_and(|lot| lot.free() > 0))
+            .map(|(lot, building)| (lot, building.pos().distance(target)))
+            .filter(|&(_, distance)| distance <= PARKING_SEARCH_RADIUS)
+            .min_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        let Some((lot, _)) = nearest else {
+            continue;
+        };
+
+        if let (Some(path), Ok(mut parking)) = (network.path(destination, lot), lot_query.get_mut(lot)) {
+            vehicle.path.extend(path.into_iter().skip(1));
+            parking.reserved.insert(entity);
+        }
+    }
+}
+
+fn dwell_parked_vehicles(
+    mut commands: Commands,
+    mut vehicle_query: Query<&mut Vehicle>,
+    mut lot_query: Query<&mut ParkingLot>,
+    time: Res<Time>,
+) {
+    for mut lot in &mut lot_query {
+        for slot in lot.slots.iter_mut() {
+            let Some(entity) = *slot else {
+                continue;
+            };
+
+            let Ok(mut vehicle) = vehicle_query.get_mut(entity) else {
+                *slot = None;
+                continue;
+            };
+
+            let remaining = vehicle.parked.unwrap_or(0.0) - time.delta_seconds();
+            vehicle.parked = Some(remaining);
+
+            if remaining <= 0.0 {
+                commands.entity(entity).despawn_recursive();
+                *slot = None;
+            }
+        }
+
+        lot.reserved.retain(|&entity| vehicle_query.contains(entity));
+    }
+}
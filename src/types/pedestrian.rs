@@ -0,0 +1,187 @@
+use crate::{
+    determinism::determinism::SimRng,
+    graph::{
+        pathfinding::{DistanceCost, FilterCost, PathGraph, Pathfinder},
+        road_network::RoadNetwork,
+    },
+    graphics::models::Models,
+    grid::terrain::Terrain,
+    schedule::UpdateStage,
+    tools::road_tool::ROAD_HEIGHT,
+    types::{building::*, vehicle::*},
+};
+use bevy::prelude::*;
+use rand::{seq::IteratorRandom, Rng};
+
+const PEDESTRIAN_HEIGHT: f32 = 0.125;
+const PEDESTRIAN_SPEED: f32 = 0.4;
+const PEDESTRIAN_SPEED_VARIATION: f32 = 0.15;
+const PEDESTRIAN_SPAWN_SECONDS: f32 = 1.0;
+const BUILDINGS_PER_PEDESTRIAN: usize = 4;
+const WAYPOINT_RADIUS: f32 = 0.05;
+const PEDESTRIAN_MAX_LANES: i32 = 3;
+
+pub struct PedestrianPlugin;
+
+impl Plugin for PedestrianPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestPedestrianSpawn>()
+            .insert_resource(PedestrianPathfinder(
+                Pathfinder::default().with_cost(DistanceCost, 1.0).with_cost(
+                    FilterCost(|graph: &dyn PathGraph, to| {
+                        graph.segment(to).is_none_or(|segment| segment.num_lanes() <= PEDESTRIAN_MAX_LANES)
+                    }),
+                    1.0,
+                ),
+            ))
+            .insert_resource(PedestrianSpawnTimer {
+                timer: Timer::from_seconds(PEDESTRIAN_SPAWN_SECONDS, TimerMode::Repeating),
+            })
+            .add_systems(
+                Update,
+                (
+                    spawn_pedestrian_on_timer.in_set(UpdateStage::UserInput),
+                    spawn_pedestrian.run_if(in_state(VehicleSpawnState::On)).in_set(UpdateStage::Spawning),
+                    update_pedestrians.in_set(UpdateStage::AiBehavior),
+                ),
+            );
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct Pedestrian {
+    pub path: Vec<Entity>,
+    pub path_index: usize,
+    pub speed: f32,
+}
+
+#[derive(Event, Debug)]
+pub struct RequestPedestrianSpawn;
+
+#[derive(Resource)]
+struct PedestrianPathfinder(Pathfinder);
+
+#[derive(Resource, Debug)]
+struct PedestrianSpawnTimer {
+    timer: Timer,
+}
+
+fn spawn_pedestrian_on_timer(
+    mut request: EventWriter<RequestPedestrianSpawn>,
+    time: Res<Time>,
+    mut spawn_timer: ResMut<PedestrianSpawnTimer>,
+    building_query: Query<(), With<Building>>,
+    pedestrian_query: Query<(), With<Pedestrian>>,
+) {
+    spawn_timer.timer.tick(time.delta());
+    if spawn_timer.timer.just_finished() {
+        let max_pedestrians = building_query.iter().count() / BUILDINGS_PER_PEDESTRIAN;
+
+        if pedestrian_query.iter().count() < max_pedestrians {
+            request.send(RequestPedestrianSpawn);
+        }
+    }
+}
+
+fn spawn_pedestrian(
+    network: RoadNetwork,
+    mut commands: Commands,
+    mut request: EventReader<RequestPedestrianSpawn>,
+    models: Res<Models>,
+    pathfinder: Res<PedestrianPathfinder>,
+    mut sim_rng: ResMut<SimRng>,
+    terrain: Res<Terrain>,
+) {
+    for _ in request.read() {
+        let rng = sim_rng.rng();
+        let choose = network.buildings().map(|(entity, _)| entity).choose_multiple(rng, 2);
+
+        if choose.len() < 2 {
+            return;
+        }
+
+        let Some(path) = network.path_with(&pathfinder.0, choose[0], choose[1]) else {
+            continue;
+        };
+
+        let Some(building) = network.building(path[0]) else {
+            continue;
+        };
+
+        let Some(segment) = network.segment(path[1]) else {
+            continue;
+        };
+
+        let start = segment.nearest_sidewalk_point(building.pos(), building.pos());
+        let start = start.with_y(terrain.road_height(start) + ROAD_HEIGHT + PEDESTRIAN_HEIGHT);
+        let speed = PEDESTRIAN_SPEED + rng.gen_range(-PEDESTRIAN_SPEED_VARIATION..PEDESTRIAN_SPEED_VARIATION);
+
+        let spawn = commands
+            .spawn((
+                PbrBundle {
+                    mesh: models.pedestrian_mesh.clone(),
+                    material: models.pedestrian_material.clone(),
+                    transform: Transform::from_translation(start),
+                    ..default()
+                },
+                Pedestrian {
+                    path: path.clone(),
+                    path_index: 1,
+                    speed,
+                },
+            ))
+            .id();
+
+        observe_path(&mut commands, spawn, path);
+    }
+}
+
+fn update_pedestrians(
+    mut commands: Commands,
+    mut pedestrian_query: Query<(Entity, &mut Pedestrian, &mut Transform)>,
+    network: RoadNetwork,
+    terrain: Res<Terrain>,
+    time: Res<Time>,
+) {
+    for (entity, mut pedestrian, mut transform) in &mut pedestrian_query {
+        if pedestrian.path_index >= pedestrian.path.len() - 1 {
+            commands.entity(entity).despawn_recursive();
+            continue;
+        }
+
+        let pos = transform.translation;
+        let curr = pedestrian.path[pedestrian.path_index];
+        let next = pedestrian.path[pedestrian.path_index + 1];
+
+        let target = if let Some(segment) = network.segment(curr) {
+            match network.position(next) {
+                Some(toward) => segment.nearest_sidewalk_point(toward, pos),
+                None => pos,
+            }
+        } else if let Some(intersection) = network.intersection(curr) {
+            if let Some(segment) = network.segment(next) {
+                let toward = pedestrian
+                    .path
+                    .get(pedestrian.path_index + 2)
+                    .and_then(|&after| network.building(after))
+                    .map_or(pos, |building| building.pos());
+                segment.nearest_sidewalk_point(intersection.pos(), toward)
+            } else {
+                pos
+            }
+        } else {
+            pos
+        };
+
+        let target = target.with_y(pos.y);
+        let step = pedestrian.speed * time.delta_seconds();
+
+        if pos.distance(target) <= step.max(WAYPOINT_RADIUS) {
+            transform.translation = target;
+            pedestrian.path_index += 1;
+        } else {
+            transform.translation += (target - pos).normalize() * step;
+        }
+        transform.translation.y = terrain.road_height(transform.translation) + ROAD_HEIGHT + PEDESTRIAN_HEIGHT;
+    }
+}
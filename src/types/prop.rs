@@ -0,0 +1,39 @@
+use crate::grid::grid_area::*;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum PropKind {
+    Tree,
+    Bench,
+    Fountain,
+}
+
+impl PropKind {
+    pub const ALL: [PropKind; 3] = [PropKind::Tree, PropKind::Bench, PropKind::Fountain];
+
+    pub fn size(&self) -> IVec2 {
+        match self {
+            PropKind::Tree | PropKind::Bench => IVec2::ONE,
+            PropKind::Fountain => IVec2::splat(2),
+        }
+    }
+}
+
+// Decoration that takes up its cells on the grid like anything else built, but is not part of the
+// road network.
+#[derive(Component, Debug)]
+pub struct Prop {
+    pub area: GridArea,
+    pub kind: PropKind,
+}
+
+impl Prop {
+    pub fn new(area: GridArea, kind: PropKind) -> Self {
+        Self { area, kind }
+    }
+
+    pub fn area(&self) -> GridArea {
+        self.area
+    }
+}
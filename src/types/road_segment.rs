@@ -1,27 +1,111 @@
 use crate::{grid::grid_area::*, grid::grid_cell::*, grid::orientation::*};
 use bevy::prelude::*;
 use bevy::utils::HashSet;
+use serde::{Deserialize, Serialize};
+use std::f32::consts::FRAC_PI_2;
 
 const LANE_MEDIAN_SIZE: f32 = 0.5;
 const LANE_CURB: f32 = 0.5;
+const SIDEWALK_OFFSET: f32 = 0.35;
+const CURVE_RESOLUTION: usize = 16;
+
+#[derive(Copy, Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum RoadShape {
+    #[default]
+    Straight,
+    // Rows along the orientation axis shift sideways by `shift` cells, producing a 45 degree band.
+    Diagonal {
+        shift: i32,
+        width: i32,
+    },
+    // Quarter arc around the `pivot` corner of a square area; each component is -1 (min edge) or 1 (max edge).
+    Curve {
+        pivot: IVec2,
+        width: i32,
+    },
+}
+
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum RoadSurface {
+    #[default]
+    Asphalt,
+    Cobblestone,
+}
+
+impl RoadSurface {
+    pub const ALL: [RoadSurface; 2] = [RoadSurface::Asphalt, RoadSurface::Cobblestone];
+
+    pub fn speed_factor(&self) -> f32 {
+        match self {
+            RoadSurface::Asphalt => 1.0,
+            RoadSurface::Cobblestone => 0.8,
+        }
+    }
+
+    pub fn next(&self) -> Self {
+        Self::ALL[(*self as usize + 1) % Self::ALL.len()]
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct LaneOccupant {
+    pub entity: Entity,
+    pub pos: Vec3,
+    pub heading: Vec3,
+    pub lane: i32,
+    pub speed: f32,
+    pub length: f32,
+}
+
+#[derive(Component, Default, Debug)]
+pub struct LaneOccupancy {
+    pub occupants: Vec<LaneOccupant>,
+}
+
+impl LaneOccupancy {
+    pub fn in_lane(&self, lane: i32, heading: Vec3) -> impl Iterator<Item = &LaneOccupant> {
+        self.occupants.iter().filter(move |other| other.lane == lane && other.heading.dot(heading) > 0.0)
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<&LaneOccupant> {
+        self.occupants.iter().find(|other| other.entity == entity)
+    }
+}
 
 #[derive(Component, Debug)]
 pub struct RoadSegment {
     pub orientation: GAxis,
+    pub shape: RoadShape,
+    pub surface: RoadSurface,
     pub area: GridArea,
     pub ends: [Option<Entity>; 2],
     pub dests: HashSet<Entity>,
     pub observers: HashSet<Entity>,
+    pub closed: bool,
+    pub speed_limit_override: Option<f32>,
+    // Index into `ends` of the end traffic may leave a one-way road through.
+    pub one_way: Option<usize>,
+    // Set from the district policy of the cells the road runs through.
+    pub speed_limit_multiplier: f32,
+    // Share of the road taken up by queues, its own or ones backing up into it from the roads it feeds.
+    pub spillback: f32,
 }
 
 impl RoadSegment {
-    pub fn new(area: GridArea, orientation: GAxis) -> Self {
+    pub fn shaped(area: GridArea, orientation: GAxis, shape: RoadShape) -> Self {
         Self {
             orientation,
+            shape,
+            surface: RoadSurface::Asphalt,
             area,
             ends: [None; 2],
             dests: HashSet::new(),
             observers: HashSet::new(),
+            closed: false,
+            speed_limit_override: None,
+            one_way: None,
+            speed_limit_multiplier: 1.0,
+            spillback: 0.0,
         }
     }
 
@@ -30,10 +114,16 @@ impl RoadSegment {
     }
 
     pub fn pos(&self) -> Vec3 {
-        self.area.center()
+        match self.shape {
+            RoadShape::Straight => self.area.center(),
+            _ => self.centerline_point(0.5),
+        }
+    }
+
+    pub fn is_straight(&self) -> bool {
+        self.shape == RoadShape::Straight
     }
 
-    #[allow(dead_code)]
     pub fn drive_length(&self) -> i32 {
         match self.orientation {
             GAxis::Z => self.area.cell_dimensions().y,
@@ -42,9 +132,12 @@ impl RoadSegment {
     }
 
     pub fn drive_width(&self) -> i32 {
-        match self.orientation {
-            GAxis::Z => self.area.cell_dimensions().x,
-            GAxis::X => self.area.cell_dimensions().y,
+        match self.shape {
+            RoadShape::Diagonal { width, .. } | RoadShape::Curve { width, .. } => width,
+            RoadShape::Straight => match self.orientation {
+                GAxis::Z => self.area.cell_dimensions().x,
+                GAxis::X => self.area.cell_dimensions().y,
+            },
         }
     }
 
@@ -52,8 +145,45 @@ impl RoadSegment {
         self.drive_width() / 2
     }
 
+    pub fn capacity(&self) -> f32 {
+        (self.drive_length() * self.num_lanes()).max(1) as f32
+    }
+
     pub fn speed_limit(&self) -> f32 {
-        self.drive_width() as f32 * 0.25
+        self.base_speed_limit() * self.speed_limit_multiplier
+    }
+
+    // The limit before any district policy is applied.
+    pub fn base_speed_limit(&self) -> f32 {
+        self.speed_limit_override.unwrap_or(self.default_speed_limit())
+    }
+
+    pub fn default_speed_limit(&self) -> f32 {
+        self.drive_width() as f32 * 0.25 * self.surface.speed_factor()
+    }
+
+    // Whether traffic on the road may drive on to `end`.
+    pub fn leads_to(&self, end: Entity) -> bool {
+        self.one_way.is_none_or(|exit| self.ends[exit] == Some(end))
+    }
+
+    // Whether traffic at `node` may turn onto the road, which a one-way road only allows away from its exit.
+    pub fn enterable_from(&self, node: Entity) -> bool {
+        self.one_way.is_none_or(|exit| self.ends[exit] != Some(node))
+    }
+
+    pub fn is_dead_end(&self) -> bool {
+        self.dead_end().is_some()
+    }
+
+    // The cap at the open end of a road joined to an intersection at its other end only, where
+    // vehicles turn around.
+    pub fn dead_end(&self) -> Option<(GridArea, GDir)> {
+        if self.ends.iter().flatten().count() != 1 {
+            return None;
+        }
+
+        self.caps().into_iter().find(|&(_, gdir)| self.end_slot(gdir).is_some_and(|slot| self.ends[slot].is_none()))
     }
 
     pub fn get_intersection_area(&self, turn_to_area: GridArea) -> GridArea {
@@ -69,6 +199,39 @@ impl RoadSegment {
         }
     }
 
+    // Where a road ending against this one's side cuts across it. None unless the branch lies wholly
+    // alongside, and leaves this road's pieces between the junction and any intersection at its ends,
+    // as two intersections side by side do not connect.
+    pub fn tee_area(&self, branch: GridArea) -> Option<GridArea> {
+        if !self.is_straight() {
+            return None;
+        }
+
+        let junction = self.get_intersection_area(branch);
+        let (low, high, from, to, low_end, high_end) = match self.orientation {
+            GAxis::Z => (
+                self.area.min.pos.y,
+                self.area.max.pos.y,
+                junction.min.pos.y,
+                junction.max.pos.y,
+                self.ends[1],
+                self.ends[0],
+            ),
+            GAxis::X => (
+                self.area.min.pos.x,
+                self.area.max.pos.x,
+                junction.min.pos.x,
+                junction.max.pos.x,
+                self.ends[0],
+                self.ends[1],
+            ),
+        };
+
+        let alongside = low <= from && to <= high;
+        let clear = (from > low || low_end.is_none()) && (to < high || high_end.is_none());
+        (alongside && clear).then_some(junction)
+    }
+
     pub fn get_lane_pos(&self, start_pos: Vec3) -> Vec3 {
         match self.orientation {
             GAxis::Z => start_pos.with_x(self.area.center().x),
@@ -76,10 +239,355 @@ impl RoadSegment {
         }
     }
 
+    pub fn cells(&self) -> Vec<GridCell> {
+        match self.shape {
+            RoadShape::Straight => self.area.iter().collect(),
+            RoadShape::Diagonal { shift, width } => {
+                let rows = self.diagonal_rows();
+                let first = self.diagonal_first_row_start();
+                let mut cells = Vec::new();
+                for i in 0..rows {
+                    for k in 0..width {
+                        cells.push(match self.orientation {
+                            GAxis::Z => GridCell::new(first + shift * i + k, self.area.min.pos.y + i),
+                            GAxis::X => GridCell::new(self.area.min.pos.x + i, first + shift * i + k),
+                        });
+                    }
+                }
+                cells
+            }
+            RoadShape::Curve { width, .. } => {
+                let (pivot, _) = self.curve_pivot();
+                let outer = self.area.cell_dimensions().x as f32;
+                let inner = outer - width as f32;
+                self.area
+                    .iter()
+                    .filter(|cell| {
+                        let distance = cell.center().distance(pivot);
+                        distance > inner - 0.5 && distance < outer + 0.5
+                    })
+                    .collect()
+            }
+        }
+    }
+
+    pub fn end_areas(&self) -> [(GridArea, GDir); 2] {
+        self.caps().map(|(cap, gdir)| {
+            let adjacent = match gdir {
+                GDir::North => cap.adjacent_top(),
+                GDir::South => cap.adjacent_bottom(),
+                GDir::West => cap.adjacent_left(),
+                GDir::East => cap.adjacent_right(),
+            };
+            (adjacent, gdir)
+        })
+    }
+
+    pub fn caps(&self) -> [(GridArea, GDir); 2] {
+        let area = self.area;
+        match self.shape {
+            RoadShape::Straight => match self.orientation {
+                GAxis::Z => [
+                    (
+                        GridArea::new(area.min, GridCell::new(area.max.pos.x, area.min.pos.y)),
+                        GDir::South,
+                    ),
+                    (
+                        GridArea::new(GridCell::new(area.min.pos.x, area.max.pos.y), area.max),
+                        GDir::North,
+                    ),
+                ],
+                GAxis::X => [
+                    (
+                        GridArea::new(area.min, GridCell::new(area.min.pos.x, area.max.pos.y)),
+                        GDir::West,
+                    ),
+                    (
+                        GridArea::new(GridCell::new(area.max.pos.x, area.min.pos.y), area.max),
+                        GDir::East,
+                    ),
+                ],
+            },
+            RoadShape::Diagonal { shift, width } => {
+                let first = self.diagonal_first_row_start();
+                let last = first + shift * (self.diagonal_rows() - 1);
+                match self.orientation {
+                    GAxis::Z => [
+                        (
+                            GridArea::new(
+                                GridCell::new(first, area.min.pos.y),
+                                GridCell::new(first + width - 1, area.min.pos.y),
+                            ),
+                            GDir::South,
+                        ),
+                        (
+                            GridArea::new(
+                                GridCell::new(last, area.max.pos.y),
+                                GridCell::new(last + width - 1, area.max.pos.y),
+                            ),
+                            GDir::North,
+                        ),
+                    ],
+                    GAxis::X => [
+                        (
+                            GridArea::new(
+                                GridCell::new(area.min.pos.x, first),
+                                GridCell::new(area.min.pos.x, first + width - 1),
+                            ),
+                            GDir::West,
+                        ),
+                        (
+                            GridArea::new(
+                                GridCell::new(area.max.pos.x, last),
+                                GridCell::new(area.max.pos.x, last + width - 1),
+                            ),
+                            GDir::East,
+                        ),
+                    ],
+                }
+            }
+            RoadShape::Curve { pivot, width } => {
+                let (cap_min_x, cap_max_x) = match pivot.x > 0 {
+                    true => (area.min.pos.x, area.min.pos.x + width - 1),
+                    false => (area.max.pos.x - width + 1, area.max.pos.x),
+                };
+                let (cap_min_z, cap_max_z) = match pivot.y > 0 {
+                    true => (area.min.pos.y, area.min.pos.y + width - 1),
+                    false => (area.max.pos.y - width + 1, area.max.pos.y),
+                };
+                let (z_row, z_dir) = match pivot.y > 0 {
+                    true => (area.max.pos.y, GDir::North),
+                    false => (area.min.pos.y, GDir::South),
+                };
+                let (x_col, x_dir) = match pivot.x > 0 {
+                    true => (area.max.pos.x, GDir::East),
+                    false => (area.min.pos.x, GDir::West),
+                };
+                [
+                    (
+                        GridArea::new(GridCell::new(cap_min_x, z_row), GridCell::new(cap_max_x, z_row)),
+                        z_dir,
+                    ),
+                    (
+                        GridArea::new(GridCell::new(x_col, cap_min_z), GridCell::new(x_col, cap_max_z)),
+                        x_dir,
+                    ),
+                ]
+            }
+        }
+    }
+
+    pub fn end_slot(&self, gdir: GDir) -> Option<usize> {
+        match self.shape {
+            RoadShape::Straight => Some(gdir.binary_index()),
+            _ => self.caps().iter().position(|(_, cap_dir)| *cap_dir == gdir),
+        }
+    }
+
+    // The direction traffic drives out of the end in `slot`.
+    pub fn end_heading(&self, slot: usize) -> Option<GDir> {
+        self.caps().into_iter().map(|(_, gdir)| gdir).find(|&gdir| self.end_slot(gdir) == Some(slot))
+    }
+
+    pub fn axis_towards(&self, area: GridArea) -> GAxis {
+        match self.shape {
+            RoadShape::Curve { .. } => {
+                let [(z_cap, _), (x_cap, _)] = self.caps();
+                if area.center().distance(z_cap.center()) <= area.center().distance(x_cap.center()) {
+                    GAxis::Z
+                } else {
+                    GAxis::X
+                }
+            }
+            _ => self.orientation,
+        }
+    }
+
+    pub fn centerline_samples(&self) -> Vec<(Vec3, Vec3)> {
+        let half_width = self.drive_width() as f32 / 2.0;
+        match self.shape {
+            RoadShape::Curve { .. } => {
+                let (pivot, _) = self.curve_pivot();
+                (0..=CURVE_RESOLUTION)
+                    .map(|i| {
+                        let point = self.centerline_point(i as f32 / CURVE_RESOLUTION as f32);
+                        (point, (point - pivot).normalize() * half_width)
+                    })
+                    .collect()
+            }
+            _ => {
+                let lateral = match self.orientation {
+                    GAxis::Z => Vec3::X * half_width,
+                    GAxis::X => Vec3::Z * half_width,
+                };
+                vec![(self.centerline_point(0.0), lateral), (self.centerline_point(1.0), lateral)]
+            }
+        }
+    }
+
     pub fn clamp_to_lane(&self, dir: GDir, num: i32, pos: Vec3) -> Vec3 {
-        let cmax = self.area.max.max_corner();
+        match self.shape {
+            RoadShape::Straight => {
+                self.clamp_to_lane_bounds(dir, num, pos, self.area.min.min_corner(), self.area.max.max_corner())
+            }
+            RoadShape::Diagonal { .. } => {
+                let cmin = self.area.min.min_corner();
+                let cmax = self.area.max.max_corner();
+                let half_width = self.drive_width() as f32 / 2.0;
+                let (start, end) = (self.centerline_point(0.0), self.centerline_point(1.0));
+
+                match self.orientation {
+                    GAxis::Z => {
+                        let z = pos.z.clamp(cmin.z, cmax.z);
+                        let center = start.x.lerp(end.x, (z - cmin.z) / (cmax.z - cmin.z));
+                        let lower = cmin.with_x(center - half_width);
+                        let upper = cmax.with_x(center + half_width);
+                        self.clamp_to_lane_bounds(dir, num, pos.with_z(z), lower, upper)
+                    }
+                    GAxis::X => {
+                        let x = pos.x.clamp(cmin.x, cmax.x);
+                        let center = start.z.lerp(end.z, (x - cmin.x) / (cmax.x - cmin.x));
+                        let lower = cmin.with_z(center - half_width);
+                        let upper = cmax.with_z(center + half_width);
+                        self.clamp_to_lane_bounds(dir, num, pos.with_x(x), lower, upper)
+                    }
+                }
+            }
+            RoadShape::Curve { .. } => {
+                let (_, theta) = self.curve_projection(pos);
+                self.curve_lane_point(theta, self.curve_heads_forward(dir), num).with_y(pos.y)
+            }
+        }
+    }
+
+    pub fn lane_follow_point(&self, dir: GDir, num: i32, pos: Vec3, distance: f32) -> Vec3 {
+        match self.shape {
+            RoadShape::Curve { .. } => {
+                let (radius, theta) = self.curve_projection(pos);
+                let forward = self.curve_heads_forward(dir);
+                let step = if forward { distance / radius } else { -distance / radius };
+                self.curve_lane_point((theta + step).clamp(0.0, FRAC_PI_2), forward, num).with_y(pos.y)
+            }
+            _ => self.clamp_to_lane(dir, num, pos + dir.as_vec3() * distance),
+        }
+    }
+
+    pub fn lane_exit_point(&self, dir: GDir, num: i32, pos: Vec3) -> Vec3 {
+        let reach = self.area.dimensions().max_element() * 2.0;
+        self.lane_follow_point(dir, num, pos, reach)
+    }
+
+    pub fn sidewalk_point(&self, dir: GDir, pos: Vec3) -> Vec3 {
+        let curb = self.clamp_to_lane(dir, 0, pos);
+        let across = self.clamp_to_lane(dir.inverse(), 0, pos);
+        curb + (curb - across).with_y(0.0).normalize_or_zero() * SIDEWALK_OFFSET
+    }
+
+    pub fn nearest_sidewalk_point(&self, pos: Vec3, toward: Vec3) -> Vec3 {
+        [GDir::North, GDir::South, GDir::West, GDir::East]
+            .into_iter()
+            .map(|dir| self.sidewalk_point(dir, pos))
+            .min_by(|a, b| a.distance(toward).total_cmp(&b.distance(toward)))
+            .unwrap_or(pos)
+    }
+
+    fn lane_offset(&self, num: i32) -> f32 {
+        let lanesf = self.num_lanes() as f32 - 1.0;
+        let t = if lanesf == 0.0 { 0.0 } else { num as f32 / lanesf };
+        (self.num_lanes() as f32 - LANE_CURB).lerp(LANE_MEDIAN_SIZE, t)
+    }
+
+    fn centerline_point(&self, t: f32) -> Vec3 {
+        match self.shape {
+            RoadShape::Curve { pivot, .. } => {
+                let (center, radius) = self.curve_pivot();
+                let theta = t * FRAC_PI_2;
+                center + Vec3::new(-pivot.x as f32 * theta.cos(), 0.0, -pivot.y as f32 * theta.sin()) * radius
+            }
+            RoadShape::Diagonal { shift, width } => {
+                let first = self.diagonal_first_row_start() as f32 + width as f32 / 2.0;
+                let last = first + (shift * (self.diagonal_rows() - 1)) as f32;
+                let cmin = self.area.min.min_corner();
+                let cmax = self.area.max.max_corner();
+                match self.orientation {
+                    GAxis::Z => Vec3::new(first.lerp(last, t), 0.0, cmin.z.lerp(cmax.z, t)),
+                    GAxis::X => Vec3::new(cmin.x.lerp(cmax.x, t), 0.0, first.lerp(last, t)),
+                }
+            }
+            RoadShape::Straight => self.area.center(),
+        }
+    }
+
+    fn diagonal_rows(&self) -> i32 {
+        match self.orientation {
+            GAxis::Z => self.area.cell_dimensions().y,
+            GAxis::X => self.area.cell_dimensions().x,
+        }
+    }
+
+    fn diagonal_first_row_start(&self) -> i32 {
+        let RoadShape::Diagonal { shift, width } = self.shape else {
+            return 0;
+        };
+
+        match (self.orientation, shift > 0) {
+            (GAxis::Z, true) => self.area.min.pos.x,
+            (GAxis::Z, false) => self.area.max.pos.x - width + 1,
+            (GAxis::X, true) => self.area.min.pos.y,
+            (GAxis::X, false) => self.area.max.pos.y - width + 1,
+        }
+    }
+
+    fn curve_pivot(&self) -> (Vec3, f32) {
+        let RoadShape::Curve { pivot, width } = self.shape else {
+            return (self.area.center(), 0.0);
+        };
+
         let cmin = self.area.min.min_corner();
+        let cmax = self.area.max.max_corner();
+        let corner = Vec3::new(
+            if pivot.x > 0 { cmax.x } else { cmin.x },
+            0.0,
+            if pivot.y > 0 { cmax.z } else { cmin.z },
+        );
+        (corner, self.area.dimensions().x - width as f32 / 2.0)
+    }
+
+    fn curve_projection(&self, pos: Vec3) -> (f32, f32) {
+        let RoadShape::Curve { pivot, .. } = self.shape else {
+            return (1.0, 0.0);
+        };
+
+        let (center, radius) = self.curve_pivot();
+        let local = pos - center;
+        let theta = (-pivot.y as f32 * local.z).atan2(-pivot.x as f32 * local.x).clamp(0.0, FRAC_PI_2);
+        (radius, theta)
+    }
+
+    fn curve_tangent(&self, theta: f32) -> Vec3 {
+        let RoadShape::Curve { pivot, .. } = self.shape else {
+            return Vec3::ZERO;
+        };
+
+        Vec3::new(pivot.x as f32 * theta.sin(), 0.0, -pivot.y as f32 * theta.cos())
+    }
+
+    fn curve_heads_forward(&self, dir: GDir) -> bool {
+        let reference = match dir {
+            GDir::North | GDir::South => self.curve_tangent(0.0),
+            GDir::West | GDir::East => self.curve_tangent(FRAC_PI_2),
+        };
+        reference.dot(dir.as_vec3()) > 0.0
+    }
+
+    fn curve_lane_point(&self, theta: f32, forward: bool, num: i32) -> Vec3 {
+        let tangent = self.curve_tangent(theta);
+        let heading = if forward { tangent } else { -tangent };
+        let right = Vec3::new(-heading.z, 0.0, heading.x);
+        self.centerline_point(theta / FRAC_PI_2) + right * self.lane_offset(num)
+    }
 
+    fn clamp_to_lane_bounds(&self, dir: GDir, num: i32, pos: Vec3, cmin: Vec3, cmax: Vec3) -> Vec3 {
         let lanesf = self.num_lanes() as f32 - 1.0;
         let lane_ind = (num) as f32;
         let curbf = LANE_CURB;
@@ -0,0 +1,49 @@
+use bevy::{prelude::*, utils::HashMap};
+
+const CELL_SIZE: f32 = 2.0;
+
+#[derive(Debug, Clone, Copy)]
+pub struct HashedVehicle {
+    pub entity: Entity,
+    pub pos: Vec3,
+    pub heading: Vec3,
+    pub step: Option<Entity>,
+    pub lane: i32,
+    pub length: f32,
+}
+
+// Buckets vehicles by position on the ground plane so neighbor lookups only touch the few
+// buckets around the query point. Rebuilt from scratch every frame.
+#[derive(Resource, Debug, Default)]
+pub struct VehicleSpatialHash {
+    buckets: HashMap<IVec2, Vec<HashedVehicle>>,
+}
+
+impl VehicleSpatialHash {
+    fn bucket_of(pos: Vec3) -> IVec2 {
+        (pos.xz() / CELL_SIZE).floor().as_ivec2()
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.retain(|_, bucket| {
+            let occupied = !bucket.is_empty();
+            bucket.clear();
+            occupied
+        });
+    }
+
+    pub fn insert(&mut self, vehicle: HashedVehicle) {
+        self.buckets.entry(VehicleSpatialHash::bucket_of(vehicle.pos)).or_default().push(vehicle);
+    }
+
+    pub fn nearby(&self, pos: Vec3, radius: f32) -> impl Iterator<Item = &HashedVehicle> {
+        let min = VehicleSpatialHash::bucket_of(pos - Vec3::new(radius, 0.0, radius));
+        let max = VehicleSpatialHash::bucket_of(pos + Vec3::new(radius, 0.0, radius));
+
+        (min.y..=max.y)
+            .flat_map(move |y| (min.x..=max.x).map(move |x| IVec2::new(x, y)))
+            .filter_map(|bucket| self.buckets.get(&bucket))
+            .flatten()
+            .filter(move |other| other.pos.xz().distance_squared(pos.xz()) <= radius * radius)
+    }
+}
@@ -0,0 +1,483 @@
+use crate::{
+    grid::{grid_area::GridArea, terrain::Terrain},
+    schedule::UpdateStage,
+    tools::road_tool::ROAD_HEIGHT,
+    types::{
+        intersection::{Intersection, IntersectionControl},
+        road_segment::LaneOccupancy,
+    },
+};
+use bevy::prelude::*;
+
+const GREEN_SECONDS: f32 = 8.0;
+const CLEARANCE_SECONDS: f32 = 1.5;
+const MIN_SIGNALIZED_ROADS: usize = 3;
+const MOVEMENT_SAMPLES: usize = 8;
+const GREEN_COLOR: Color = Color::linear_rgb(0.0, 1.0, 0.0);
+const RED_COLOR: Color = Color::linear_rgb(1.0, 0.0, 0.0);
+const FLASH_COLOR: Color = Color::linear_rgb(1.0, 0.6, 0.0);
+const FLASH_HZ: f32 = 1.0;
+const STOP_SIGN_COLOR: Color = Color::linear_rgb(0.8, 0.0, 0.0);
+const QUEUE_SPEED: f32 = 0.1;
+const QUEUE_SMOOTHING: f32 = 0.05;
+
+pub struct TrafficSignalPlugin;
+
+impl Plugin for TrafficSignalPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<RequestSignalOverride>()
+            .add_event::<RequestIntersectionControl>()
+            .add_event::<RequestAdaptiveTiming>()
+            .insert_resource(AdaptiveTimingSettings::default())
+            .add_systems(
+                Update,
+                (
+                    (
+                        apply_control_requests,
+                        apply_signal_overrides,
+                        apply_adaptive_timing_requests,
+                        advance_signal_phases,
+                    )
+                        .chain()
+                        .in_set(UpdateStage::HighLevelSideEffects),
+                    measure_signal_queues.in_set(UpdateStage::Analyze),
+                    update_signal_plans.in_set(UpdateStage::UpdatePathing),
+                    (visualize_signals, visualize_stop_signs).in_set(UpdateStage::Visualize),
+                ),
+            );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Turn {
+    Straight,
+    Right,
+    Left,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Movement {
+    pub from: usize,
+    pub to: usize,
+}
+
+impl Movement {
+    pub fn turn(&self) -> Turn {
+        let heading = -slot_normal(self.from);
+        let exit = slot_normal(self.to);
+
+        if heading.dot(exit) > 0.5 {
+            Turn::Straight
+        } else if heading.perp_dot(exit) < 0.0 {
+            Turn::Left
+        } else {
+            Turn::Right
+        }
+    }
+
+    fn geometry(&self, area: GridArea) -> Vec<Vec2> {
+        let entry = lane_point(area, self.from, -slot_normal(self.from));
+        let exit = lane_point(area, self.to, slot_normal(self.to));
+
+        let control = match self.turn() {
+            Turn::Straight => (entry + exit) / 2.0,
+            Turn::Left | Turn::Right => {
+                let heading = -slot_normal(self.from);
+                entry + heading * heading.dot(exit - entry)
+            }
+        };
+
+        (0..=MOVEMENT_SAMPLES)
+            .map(|i| {
+                let t = i as f32 / MOVEMENT_SAMPLES as f32;
+                entry.lerp(control, t).lerp(control.lerp(exit, t), t)
+            })
+            .collect()
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum SignalOverride {
+    AllRed,
+    Flash,
+    Phase(usize),
+}
+
+#[derive(Event, Debug)]
+pub struct RequestSignalOverride {
+    pub entity: Entity,
+    pub mode: Option<SignalOverride>,
+    pub seconds: f32,
+}
+
+#[derive(Event, Debug)]
+pub struct RequestIntersectionControl {
+    pub entity: Entity,
+    pub control: IntersectionControl,
+}
+
+#[derive(Event, Debug)]
+pub struct RequestAdaptiveTiming {
+    pub entity: Entity,
+    pub enabled: bool,
+}
+
+// Bounds for adaptive signals. Each cycle keeps the same length and its green time is shared out
+// between the phases in proportion to the queues they serve.
+#[derive(Resource, Debug)]
+pub struct AdaptiveTimingSettings {
+    pub cycle_seconds: f32,
+    pub min_green_seconds: f32,
+    pub max_green_seconds: f32,
+}
+
+impl Default for AdaptiveTimingSettings {
+    fn default() -> Self {
+        Self {
+            cycle_seconds: 40.0,
+            min_green_seconds: 4.0,
+            max_green_seconds: 20.0,
+        }
+    }
+}
+
+// `greens` holds each phase's green time. It stays at the fixed default unless `adaptive` is set,
+// in which case it is worked out again from the smoothed queue on each approach as every cycle
+// starts.
+#[derive(Component, Debug)]
+pub struct TrafficSignal {
+    pub roads: [Option<Entity>; 4],
+    pub movements: Vec<Movement>,
+    pub conflicts: Vec<Vec<bool>>,
+    pub phases: Vec<Vec<usize>>,
+    pub phase: usize,
+    pub timer: f32,
+    pub manual: Option<SignalOverride>,
+    pub manual_remaining: f32,
+    pub adaptive: bool,
+    pub greens: Vec<f32>,
+    pub queues: [f32; 4],
+}
+
+impl TrafficSignal {
+    pub fn new(intersection: &Intersection) -> Self {
+        let slots: Vec<usize> = (0..4).filter(|&slot| intersection.roads[slot].is_some()).collect();
+        let movements: Vec<Movement> = slots
+            .iter()
+            .flat_map(|&from| slots.iter().filter(move |&&to| to != from).map(move |&to| Movement { from, to }))
+            .collect();
+
+        let geometry: Vec<Vec<Vec2>> = movements.iter().map(|movement| movement.geometry(intersection.area())).collect();
+        let conflicts = movements
+            .iter()
+            .enumerate()
+            .map(|(i, a)| {
+                movements
+                    .iter()
+                    .enumerate()
+                    .map(|(j, b)| i != j && movements_conflict(a, b, &geometry[i], &geometry[j]))
+                    .collect()
+            })
+            .collect();
+
+        let mut signal = Self {
+            roads: intersection.roads,
+            movements,
+            conflicts,
+            phases: Vec::new(),
+            phase: 0,
+            timer: 0.0,
+            manual: None,
+            manual_remaining: 0.0,
+            adaptive: false,
+            greens: Vec::new(),
+            queues: [0.0; 4],
+        };
+        signal.phases = signal.plan_phases();
+        signal.greens = vec![GREEN_SECONDS; signal.phases.len()];
+        signal
+    }
+
+    fn plan_phases(&self) -> Vec<Vec<usize>> {
+        let mut order: Vec<usize> = (0..self.movements.len()).collect();
+        order.sort_by_key(|&i| self.movements[i].turn() as usize);
+
+        let compatible = |phase: &Vec<usize>, candidate: usize| phase.iter().all(|&other| !self.conflicts[candidate][other]);
+
+        let mut assigned = vec![false; self.movements.len()];
+        let mut phases = Vec::<Vec<usize>>::new();
+
+        for &first in &order {
+            if assigned[first] {
+                continue;
+            }
+
+            let mut phase = vec![first];
+            assigned[first] = true;
+
+            for &candidate in &order {
+                if !assigned[candidate] && compatible(&phase, candidate) {
+                    phase.push(candidate);
+                    assigned[candidate] = true;
+                }
+            }
+
+            phases.push(phase);
+        }
+
+        for phase in &mut phases {
+            for &candidate in &order {
+                if !phase.contains(&candidate) && compatible(phase, candidate) {
+                    phase.push(candidate);
+                }
+            }
+        }
+
+        phases
+    }
+
+    pub fn green(&self) -> f32 {
+        self.greens.get(self.phase).copied().unwrap_or(GREEN_SECONDS)
+    }
+
+    pub fn phase_seconds(&self, phase: usize) -> f32 {
+        self.greens.get(phase).copied().unwrap_or(GREEN_SECONDS) + CLEARANCE_SECONDS
+    }
+
+    pub fn in_clearance(&self) -> bool {
+        self.manual.is_none() && self.timer >= self.green()
+    }
+
+    // The longest queue among the approaches a phase lets go.
+    pub fn phase_demand(&self, phase: usize) -> f32 {
+        self.phases[phase].iter().map(|&i| self.queues[self.movements[i].from]).fold(0.0, f32::max)
+    }
+
+    pub fn retime(&mut self, settings: &AdaptiveTimingSettings) {
+        let count = self.phases.len();
+        let available = (settings.cycle_seconds - CLEARANCE_SECONDS * count as f32).max(0.0);
+        let demands: Vec<f32> = (0..count).map(|phase| self.phase_demand(phase)).collect();
+        let total: f32 = demands.iter().sum();
+
+        self.greens = demands
+            .iter()
+            .map(|&demand| match total > 0.0 {
+                true => (available * demand / total).clamp(settings.min_green_seconds, settings.max_green_seconds),
+                false => GREEN_SECONDS,
+            })
+            .collect();
+    }
+
+    fn active_phase(&self) -> Option<&Vec<usize>> {
+        match self.manual {
+            Some(SignalOverride::AllRed) => None,
+            Some(SignalOverride::Phase(phase)) => self.phases.get(phase),
+            _ if self.in_clearance() => None,
+            _ => self.phases.get(self.phase),
+        }
+    }
+
+    pub fn allows(&self, from: usize, to: usize) -> bool {
+        if self.manual == Some(SignalOverride::Flash) {
+            return true;
+        }
+
+        self.active_phase().is_some_and(|phase| phase.iter().any(|&i| self.movements[i] == Movement { from, to }))
+    }
+
+    pub fn allows_from(&self, from: usize) -> bool {
+        if self.manual == Some(SignalOverride::Flash) {
+            return true;
+        }
+
+        self.active_phase().is_some_and(|phase| phase.iter().any(|&i| self.movements[i].from == from))
+    }
+}
+
+fn slot_normal(slot: usize) -> Vec2 {
+    match slot {
+        0 => Vec2::Y,
+        1 => Vec2::NEG_Y,
+        2 => Vec2::NEG_X,
+        _ => Vec2::X,
+    }
+}
+
+fn lane_point(area: GridArea, slot: usize, heading: Vec2) -> Vec2 {
+    let center = area.center().xz();
+    let half = area.dimensions() / 2.0;
+    let normal = slot_normal(slot);
+    let (depth, breadth) = if normal.x != 0.0 { (half.x, half.y) } else { (half.y, half.x) };
+    let right = Vec2::new(-heading.y, heading.x);
+    center + normal * depth + right * breadth / 2.0
+}
+
+fn movements_conflict(a: &Movement, b: &Movement, a_path: &[Vec2], b_path: &[Vec2]) -> bool {
+    if a.from == b.from {
+        return false;
+    }
+
+    if a.to == b.to {
+        return true;
+    }
+
+    a_path
+        .windows(2)
+        .any(|a_edge| b_path.windows(2).any(|b_edge| edges_cross(a_edge[0], a_edge[1], b_edge[0], b_edge[1])))
+}
+
+fn edges_cross(p1: Vec2, p2: Vec2, q1: Vec2, q2: Vec2) -> bool {
+    let d1 = (p2 - p1).perp_dot(q1 - p1);
+    let d2 = (p2 - p1).perp_dot(q2 - p1);
+    let d3 = (q2 - q1).perp_dot(p1 - q1);
+    let d4 = (q2 - q1).perp_dot(p2 - q1);
+    d1 * d2 < 0.0 && d3 * d4 < 0.0
+}
+
+fn update_signal_plans(
+    mut commands: Commands,
+    mut inter_query: Query<(Entity, &Intersection, Option<&mut TrafficSignal>), Changed<Intersection>>,
+) {
+    for (entity, intersection, signal) in &mut inter_query {
+        let connected = intersection.roads.iter().filter(|slot| slot.is_some()).count();
+
+        if connected < MIN_SIGNALIZED_ROADS || intersection.control != IntersectionControl::Signal {
+            if signal.is_some() {
+                commands.entity(entity).remove::<TrafficSignal>();
+            }
+            continue;
+        }
+
+        match signal {
+            Some(mut signal) if signal.roads != intersection.roads => {
+                let (phase, adaptive) = (signal.phase, signal.adaptive);
+                *signal = TrafficSignal::new(intersection);
+                signal.phase = phase % signal.phases.len().max(1);
+                signal.adaptive = adaptive;
+            }
+            Some(_) => {}
+            None => {
+                commands.entity(entity).insert(TrafficSignal::new(intersection));
+            }
+        }
+    }
+}
+
+fn apply_control_requests(mut event: EventReader<RequestIntersectionControl>, mut inter_query: Query<&mut Intersection>) {
+    for &RequestIntersectionControl { entity, control } in event.read() {
+        if let Ok(mut intersection) = inter_query.get_mut(entity) {
+            intersection.control = control;
+            intersection.arrivals.clear();
+            intersection.reservations.clear();
+        }
+    }
+}
+
+fn apply_signal_overrides(mut event: EventReader<RequestSignalOverride>, mut signal_query: Query<&mut TrafficSignal>) {
+    for &RequestSignalOverride { entity, mode, seconds } in event.read() {
+        if let Ok(mut signal) = signal_query.get_mut(entity) {
+            signal.manual = mode;
+            signal.manual_remaining = seconds;
+            signal.timer = 0.0;
+        }
+    }
+}
+
+fn apply_adaptive_timing_requests(
+    mut event: EventReader<RequestAdaptiveTiming>,
+    mut signal_query: Query<&mut TrafficSignal>,
+) {
+    for &RequestAdaptiveTiming { entity, enabled } in event.read() {
+        if let Ok(mut signal) = signal_query.get_mut(entity) {
+            signal.adaptive = enabled;
+            signal.greens = vec![GREEN_SECONDS; signal.phases.len()];
+        }
+    }
+}
+
+// Vehicles at a crawl on an approach and heading into the intersection count as queued.
+fn measure_signal_queues(
+    mut signal_query: Query<(&Intersection, &mut TrafficSignal)>,
+    occupancy_query: Query<&LaneOccupancy>,
+) {
+    for (intersection, mut signal) in &mut signal_query {
+        for slot in 0..4 {
+            let queued = intersection.roads[slot].and_then(|road| occupancy_query.get(road).ok()).map_or(0, |occupancy| {
+                occupancy
+                    .occupants
+                    .iter()
+                    .filter(|occupant| {
+                        occupant.speed < QUEUE_SPEED && occupant.heading.dot(intersection.pos() - occupant.pos) > 0.0
+                    })
+                    .count()
+            });
+
+            signal.queues[slot] = signal.queues[slot].lerp(queued as f32, QUEUE_SMOOTHING);
+        }
+    }
+}
+
+fn advance_signal_phases(
+    mut signal_query: Query<&mut TrafficSignal>,
+    settings: Res<AdaptiveTimingSettings>,
+    time: Res<Time>,
+) {
+    for mut signal in &mut signal_query {
+        if signal.manual.is_some() {
+            signal.manual_remaining -= time.delta_seconds();
+
+            if signal.manual_remaining <= 0.0 {
+                signal.manual = None;
+                signal.timer = 0.0;
+            }
+            continue;
+        }
+
+        signal.timer += time.delta_seconds();
+
+        if signal.timer >= signal.green() + CLEARANCE_SECONDS {
+            signal.timer = 0.0;
+            signal.phase = (signal.phase + 1) % signal.phases.len().max(1);
+
+            if signal.adaptive && signal.phase == 0 {
+                signal.retime(&settings);
+            }
+        }
+    }
+}
+
+fn visualize_signals(
+    signal_query: Query<(&Intersection, &TrafficSignal)>,
+    terrain: Res<Terrain>,
+    time: Res<Time>,
+    mut gizmos: Gizmos,
+) {
+    let flash_on = (time.elapsed_seconds() * FLASH_HZ).fract() < 0.5;
+
+    for (intersection, signal) in &signal_query {
+        for slot in (0..4).filter(|&slot| signal.roads[slot].is_some()) {
+            let point = lane_point(intersection.area(), slot, -slot_normal(slot));
+            let color = match signal.manual {
+                Some(SignalOverride::Flash) if flash_on => FLASH_COLOR,
+                Some(SignalOverride::Flash) => continue,
+                _ if signal.allows_from(slot) => GREEN_COLOR,
+                _ => RED_COLOR,
+            };
+            gizmos.circle(marker_position(&terrain, point), Dir3::Y, 0.15, color);
+        }
+    }
+}
+
+fn visualize_stop_signs(inter_query: Query<&Intersection>, terrain: Res<Terrain>, mut gizmos: Gizmos) {
+    for intersection in inter_query.iter().filter(|intersection| intersection.control == IntersectionControl::StopSign) {
+        for slot in (0..4).filter(|&slot| intersection.roads[slot].is_some()) {
+            let point = lane_point(intersection.area(), slot, -slot_normal(slot));
+            gizmos.circle(marker_position(&terrain, point), Dir3::Y, 0.15, STOP_SIGN_COLOR).resolution(8);
+        }
+    }
+}
+
+fn marker_position(terrain: &Terrain, point: Vec2) -> Vec3 {
+    let ground = Vec3::new(point.x, 0.0, point.y);
+    ground.with_y(terrain.road_height(ground) + ROAD_HEIGHT + 0.01)
+}
@@ -0,0 +1,147 @@
+use crate::{
+    save::save_tests::area,
+    types::{
+        intersection::Intersection,
+        traffic_signal::{AdaptiveTimingSettings, Movement, TrafficSignal, Turn},
+    },
+};
+use bevy::prelude::*;
+
+const FOUR_WAY: [usize; 4] = [0, 1, 2, 3];
+const TEE: [usize; 3] = [0, 1, 3];
+
+fn signal(slots: &[usize]) -> TrafficSignal {
+    let mut intersection = Intersection::new(area((0, 0), (1, 1)));
+    for &slot in slots {
+        intersection.roads[slot] = Some(Entity::from_raw(slot as u32 + 1));
+    }
+    TrafficSignal::new(&intersection)
+}
+
+// Slots come in opposite pairs: 0 faces 1, and 2 faces 3.
+fn oncoming(movement: Movement) -> usize {
+    movement.from ^ 1
+}
+
+fn oncoming_through(signal: &TrafficSignal, left: Movement) -> Vec<usize> {
+    (0..signal.movements.len())
+        .filter(|&i| signal.movements[i].from == oncoming(left) && signal.movements[i].turn() == Turn::Straight)
+        .collect()
+}
+
+fn lefts(signal: &TrafficSignal) -> Vec<usize> {
+    (0..signal.movements.len()).filter(|&i| signal.movements[i].turn() == Turn::Left).collect()
+}
+
+fn check_conflicts(slots: &[usize]) {
+    let signal = signal(slots);
+    assert_eq!(signal.movements.len(), slots.len() * (slots.len() - 1));
+
+    for (i, a) in signal.movements.iter().enumerate() {
+        assert!(!signal.conflicts[i][i]);
+        for (j, b) in signal.movements.iter().enumerate() {
+            assert_eq!(signal.conflicts[i][j], signal.conflicts[j][i], "{:?} {:?}", a, b);
+            if i != j && a.from == b.from {
+                assert!(!signal.conflicts[i][j], "{:?} {:?}", a, b);
+            }
+            if i != j && a.to == b.to {
+                assert!(signal.conflicts[i][j], "{:?} {:?}", a, b);
+            }
+        }
+    }
+
+    for left in lefts(&signal) {
+        for through in oncoming_through(&signal, signal.movements[left]) {
+            assert!(signal.conflicts[left][through], "{:?}", signal.movements[left]);
+        }
+    }
+}
+
+fn check_phases(slots: &[usize]) {
+    let signal = signal(slots);
+
+    for i in 0..signal.movements.len() {
+        assert!(
+            signal.phases.iter().any(|phase| phase.contains(&i)),
+            "{:?}",
+            signal.movements[i]
+        );
+    }
+
+    for phase in &signal.phases {
+        for &a in phase {
+            assert!(phase.iter().all(|&b| !signal.conflicts[a][b]), "{:?}", phase);
+        }
+
+        for &left in phase.iter().filter(|&&i| signal.movements[i].turn() == Turn::Left) {
+            let through = oncoming_through(&signal, signal.movements[left]);
+            assert!(phase.iter().all(|i| !through.contains(i)), "{:?}", phase);
+        }
+    }
+}
+
+#[test]
+fn four_way_conflicts() {
+    check_conflicts(&FOUR_WAY);
+}
+
+#[test]
+fn tee_conflicts() {
+    check_conflicts(&TEE);
+}
+
+// Every movement gets a green, and a protected left never runs against oncoming through traffic.
+#[test]
+fn four_way_phases() {
+    let signal = signal(&FOUR_WAY);
+    assert!(!lefts(&signal).is_empty());
+    check_phases(&FOUR_WAY);
+}
+
+#[test]
+fn tee_phases() {
+    let signal = signal(&TEE);
+    assert!(!lefts(&signal).is_empty());
+    check_phases(&TEE);
+}
+
+// Green time follows the queues within the configured bounds, and retiming never changes which
+// movements each phase serves.
+fn check_retime(slots: &[usize]) {
+    let settings = AdaptiveTimingSettings::default();
+    let mut signal = signal(slots);
+    let phases = signal.phases.clone();
+    let fixed = signal.greens.clone();
+
+    signal.retime(&settings);
+    assert_eq!(signal.greens, fixed);
+
+    signal.queues[slots[0]] = 12.0;
+    signal.queues[slots[1]] = 2.0;
+    signal.retime(&settings);
+
+    assert_eq!(signal.phases, phases);
+    assert_eq!(signal.greens.len(), phases.len());
+    for (phase, &green) in signal.greens.iter().enumerate() {
+        assert!((settings.min_green_seconds..=settings.max_green_seconds).contains(&green));
+        for other in 0..phases.len() {
+            if signal.phase_demand(phase) > signal.phase_demand(other) {
+                assert!(green >= signal.greens[other], "{:?}", signal.greens);
+            }
+        }
+    }
+
+    let busiest = (0..phases.len()).max_by(|&a, &b| signal.phase_demand(a).total_cmp(&signal.phase_demand(b))).unwrap();
+    let quietest = (0..phases.len()).min_by(|&a, &b| signal.phase_demand(a).total_cmp(&signal.phase_demand(b))).unwrap();
+    assert!(signal.greens[busiest] > signal.greens[quietest], "{:?}", signal.greens);
+}
+
+#[test]
+fn four_way_retime() {
+    check_retime(&FOUR_WAY);
+}
+
+#[test]
+fn tee_retime() {
+    check_retime(&TEE);
+}
@@ -0,0 +1,99 @@
+use crate::{
+    graphics::models::Models,
+    schedule::UpdateStage,
+    types::vehicle::{follow_terrain, Vehicle},
+};
+use bevy::prelude::*;
+
+pub const TRAILER_LENGTH: f32 = 0.9;
+pub const HITCH_GAP: f32 = 0.05;
+
+pub struct TrailerPlugin;
+
+impl Plugin for TrailerPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            Update,
+            (
+                follow_hitches.after(follow_terrain).in_set(UpdateStage::AiBehavior),
+                despawn_orphaned_trailers.in_set(UpdateStage::DestroyEntities),
+            ),
+        );
+    }
+}
+
+// A trailer is its own entity rather than a child of the tractor, since it swings freely around
+// the hitch instead of staying fixed in the tractor's frame.
+#[derive(Component, Debug)]
+pub struct Trailer {
+    pub tractor: Entity,
+    pub length: f32,
+}
+
+impl Trailer {
+    pub fn rear(&self, transform: &Transform) -> Vec3 {
+        transform.translation - transform.forward().as_vec3() * self.length / 2.0
+    }
+}
+
+fn hitch_point(tractor_length: f32, transform: &Transform) -> Vec3 {
+    transform.translation - transform.forward().as_vec3() * (tractor_length / 2.0 + HITCH_GAP)
+}
+
+// Fills in the reserved `trailer` entity in line behind the tractor. The tractor links back to it
+// through `Vehicle::trailer`.
+pub fn spawn_trailer(
+    commands: &mut Commands,
+    models: &Models,
+    trailer: Entity,
+    tractor: Entity,
+    tractor_length: f32,
+    transform: &Transform,
+) {
+    let forward = transform.forward().as_vec3();
+
+    commands.entity(trailer).insert((
+        PbrBundle {
+            mesh: models.trailer_mesh.clone(),
+            material: models.trailer_material.clone(),
+            transform: Transform::from_translation(hitch_point(tractor_length, transform) - forward * TRAILER_LENGTH / 2.0)
+                .looking_to(forward, Vec3::Y),
+            ..default()
+        },
+        Trailer {
+            tractor,
+            length: TRAILER_LENGTH,
+        },
+    ));
+}
+
+// The front of the trailer stays on the tractor's hitch and the body turns to point at it, so the
+// trailer tracks inside the tractor's path through corners.
+fn follow_hitches(
+    tractor_query: Query<(&Vehicle, &Transform), Without<Trailer>>,
+    mut trailer_query: Query<(&Trailer, &mut Transform)>,
+) {
+    trailer_query.par_iter_mut().for_each(|(trailer, mut transform)| {
+        let Ok((vehicle, tractor)) = tractor_query.get(trailer.tractor) else {
+            return;
+        };
+
+        let hitch = hitch_point(vehicle.length, tractor);
+        let heading = (hitch - transform.translation).with_y(0.0).try_normalize().unwrap_or(tractor.forward().as_vec3());
+
+        transform.translation = hitch - heading * trailer.length / 2.0;
+        transform.look_to(heading, Vec3::Y);
+    });
+}
+
+fn despawn_orphaned_trailers(
+    mut commands: Commands,
+    trailer_query: Query<(Entity, &Trailer)>,
+    vehicle_query: Query<(), With<Vehicle>>,
+) {
+    for (entity, trailer) in &trailer_query {
+        if !vehicle_query.contains(trailer.tractor) {
+            commands.entity(entity).despawn_recursive();
+        }
+    }
+}
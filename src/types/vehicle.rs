@@ -1,28 +1,86 @@
 use crate::{
-    graph::road_graph_events::{OnBuildingDestroyed, OnIntersectionDestroyed, OnRoadDestroyed},
-    graphics::models::Models,
-    grid::{grid_area::GridArea, orientation::*},
+    determinism::determinism::SimRng,
+    economy::commute::Commutes,
+    graph::{
+        path_cache::PathCache,
+        pathfinding::Pathfinder,
+        road_graph_events::{OnBuildingDestroyed, OnIntersectionDestroyed, OnRoadDestroyed},
+        road_network::RoadNetwork,
+    },
+    graphics::{
+        models::{Models, VehicleModelData},
+        weather::{TimeOfDay, WeatherSettings, WeatherState},
+    },
+    grid::{grid_area::GridArea, orientation::*, terrain::Terrain},
+    input::keymap::{Action, Actions},
     schedule::UpdateStage,
     tools::road_tool::ROAD_HEIGHT,
-    types::{building::*, intersection::*, road_segment::*},
+    types::{
+        building::*,
+        driver::{choose_driver, DriverProfile},
+        intersection::*,
+        pedestrian::Pedestrian,
+        road_segment::*,
+        spatial_hash::{HashedVehicle, VehicleSpatialHash},
+        traffic_signal::{Movement, TrafficSignal, Turn},
+        trailer::{spawn_trailer, Trailer, HITCH_GAP, TRAILER_LENGTH},
+        vehicle_lod::Dormant,
+    },
 };
 use bevy::{
+    ecs::system::SystemParam,
     prelude::*,
     utils::{HashMap, HashSet},
 };
-use bevy_mod_raycast::prelude::*;
 use rand::{
-    seq::{IteratorRandom, SliceRandom},
+    distributions::{Distribution, WeightedIndex},
+    seq::SliceRandom,
     Rng,
 };
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
 
 const VEHICLE_HEIGHT: f32 = 0.25;
+const WORK_ZONE_SLOWDOWN: f32 = 0.5;
 const VEHICLE_MAX_SPEED: f32 = 1.5;
 const VEHICLE_MIN_SPEED: f32 = 0.01;
-const MAX_SPEED_VARIATION: f32 = 0.5;
-const SPAWN_TIME_SECONDS: f32 = 0.5;
-const BUILDINGS_PER_VEHICLE: usize = 5;
+const RUSH_HOUR_VEHICLE_FACTOR: f32 = 2.0;
 const INTERSECTION_OFFSET: f32 = 0.2;
+const LANE_CHANGE_INTERSECTION_BUFFER: f32 = 3.0;
+const LANE_CHANGE_GAP_AHEAD: f32 = 2.0;
+const LANE_CHANGE_GAP_BEHIND: f32 = 3.0;
+const OVERTAKE_PATIENCE_SECONDS: f32 = 2.0;
+const OVERTAKE_SPEED_RATIO: f32 = 0.9;
+const LANE_CHANGE_SECONDS: f32 = 1.2;
+const SIGNAL_STOP_DISTANCE: f32 = 1.5;
+const SIGNAL_COMMIT_DISTANCE: f32 = 0.2;
+const STOP_SIGN_HALT_SPEED: f32 = 0.05;
+const RIGHT_ON_RED_SPEED: f32 = 0.1;
+const PEDESTRIAN_YIELD_DISTANCE: f32 = 1.0;
+// Gap left between one vehicle's front bumper and the rear of the one ahead.
+const FOLLOW_DISTANCE: f32 = 2.5;
+const CAR_LENGTH: f32 = 0.5;
+const MAX_BODY_LENGTH: f32 = CAR_LENGTH * 2.0;
+const TRAILER_CHANCE: f64 = 0.5;
+pub const QUEUED_SPEED: f32 = 0.1;
+const EXIT_ROOM_MARGIN: f32 = 0.5;
+const FOLLOW_LANE_TOLERANCE: f32 = 0.4;
+const EMERGENCY_SPEED_LIMIT: f32 = 2.0;
+const EMERGENCY_CLEARANCE_DISTANCE: f32 = 4.0;
+const BUS_ROUTE_STOPS: usize = 4;
+const SPILLBACK_CHECK_SECONDS: f32 = 2.0;
+const SPILLBACK_LOOKAHEAD: usize = 6;
+const MAX_SPILLBACK_REROUTES: usize = 20;
+const INDICATOR_DISTANCE: f32 = 2.5;
+const TURNAROUND_REACH: f32 = 0.5;
+const TURNAROUND_SPEED: f32 = 0.2;
+const INDICATOR_HZ: f32 = 1.5;
+const INDICATOR_OFFSETS: [(Indicator, Vec3); 4] = [
+    (Indicator::Left, Vec3::new(-0.26, 0.0, -0.2)),
+    (Indicator::Left, Vec3::new(-0.26, 0.0, 0.2)),
+    (Indicator::Right, Vec3::new(0.26, 0.0, -0.2)),
+    (Indicator::Right, Vec3::new(0.26, 0.0, 0.2)),
+];
 
 #[derive(States, Default, Debug, Clone, PartialEq, Eq, Hash)]
 pub enum AiVisualizationState {
@@ -42,13 +100,22 @@ pub struct VehiclePlugin;
 
 impl Plugin for VehiclePlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(DeferredRaycastingPlugin::<VehicleRaycastSet>::default())
-            .insert_resource(RaycastPluginState::<VehicleRaycastSet>::default())
-            .init_state::<AiVisualizationState>()
+        app.init_state::<AiVisualizationState>()
             .init_state::<VehicleSpawnState>()
             .add_event::<RequestVehicleSpawn>()
+            .add_event::<OnTripCompleted>()
+            .add_event::<OnTripFailed>()
+            .add_event::<RequestVehicleRestore>()
+            .insert_resource(LaneChangeSettings::default())
+            .insert_resource(GapAcceptanceSettings::default())
+            .insert_resource(IntersectionCapacitySettings::default())
+            .insert_resource(VehicleKindSettings::default())
+            .insert_resource(VehicleSpawnConfig::default())
+            .insert_resource(BusRoute::default())
+            .insert_resource(VehicleSpatialHash::default())
+            .insert_resource(Pathfinder::vehicles())
             .insert_resource(SpawnTimer {
-                timer: Timer::from_seconds(SPAWN_TIME_SECONDS, TimerMode::Repeating),
+                timer: Timer::from_seconds(1.0 / VehicleSpawnConfig::default().spawns_per_second, TimerMode::Repeating),
             })
             .add_systems(
                 Update,
@@ -60,24 +127,202 @@ impl Plugin for VehiclePlugin {
                         spawn_vehicle_on_timer,
                     )
                         .in_set(UpdateStage::UserInput),
-                    (spawn_vehicle.run_if(in_state(VehicleSpawnState::On))).in_set(UpdateStage::Spawning),
-                    (update_vehicles, update_speed, execute_movement, execute_turning).in_set(UpdateStage::AiBehavior),
+                    (spawn_vehicle.run_if(in_state(VehicleSpawnState::On)), restore_vehicles).in_set(UpdateStage::Spawning),
                     (
-                        handle_building_destroyed,
-                        handle_road_segment_destroyed,
-                        handle_intersection_destroyed,
+                        track_lane_occupancy.before(update_vehicles),
+                        hash_vehicle_positions.before(update_speed),
+                        update_vehicles,
+                        update_stop_sign_queues.after(update_vehicles),
+                        update_intersection_capacity.after(update_vehicles),
+                        update_speed,
+                        execute_movement,
+                        execute_turning,
+                        follow_terrain.after(execute_movement),
                     )
-                        .in_set(UpdateStage::UpdatePathing),
+                        .in_set(UpdateStage::AiBehavior),
+                    (reroute_vehicles, reroute_around_spillback, plan_bus_route).in_set(UpdateStage::UpdatePathing),
                     (visualize_path, visualize_vehicle_ai)
                         .in_set(UpdateStage::Visualize)
                         .run_if(in_state(AiVisualizationState::Visualize)),
+                    blink_indicators.in_set(UpdateStage::Visualize),
                 ),
             );
     }
 }
 
-#[derive(Reflect)]
-struct VehicleRaycastSet;
+#[derive(Resource, Debug)]
+pub struct LaneChangeSettings {
+    pub aggressiveness: f32,
+}
+
+impl Default for LaneChangeSettings {
+    fn default() -> Self {
+        Self { aggressiveness: 0.5 }
+    }
+}
+
+// The city-wide setting is shifted by each driver's own aggressiveness, 0.5 leaving it as is.
+impl LaneChangeSettings {
+    fn aggressiveness(&self, driver: &dyn DriverProfile) -> f32 {
+        (self.aggressiveness + driver.aggressiveness() - 0.5).clamp(0.0, 1.0)
+    }
+
+    fn gap_scale(&self, driver: &dyn DriverProfile) -> f32 {
+        1.5 - self.aggressiveness(driver)
+    }
+
+    fn patience(&self, driver: &dyn DriverProfile) -> f32 {
+        OVERTAKE_PATIENCE_SECONDS * (1.1 - self.aggressiveness(driver)) / driver.lane_change_willingness()
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct GapAcceptanceSettings {
+    pub critical_gap_seconds: f32,
+    pub right_on_red: bool,
+}
+
+impl Default for GapAcceptanceSettings {
+    fn default() -> Self {
+        Self {
+            critical_gap_seconds: 2.5,
+            right_on_red: true,
+        }
+    }
+}
+
+// How many vehicles an intersection lets in at once, and how backed up a road ahead has to be before
+// vehicles try to find a way around it.
+#[derive(Resource, Debug)]
+pub struct IntersectionCapacitySettings {
+    pub enabled: bool,
+    pub cells_per_vehicle: f32,
+    pub reroute_spillback: f32,
+}
+
+impl Default for IntersectionCapacitySettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            cells_per_vehicle: 1.0,
+            reroute_spillback: 0.8,
+        }
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct VehicleKindSettings {
+    pub weights: [f32; 4],
+}
+
+impl Default for VehicleKindSettings {
+    fn default() -> Self {
+        Self {
+            weights: [0.85, 0.08, 0.04, 0.03],
+        }
+    }
+}
+
+// How often vehicles are spawned, how many buildings it takes to keep one more on the road, how
+// far each vehicle's top speed may stray from the norm, and the weights each driver profile is drawn
+// with. Kept in the save, so a reloaded city has the same traffic.
+#[derive(Resource, Clone, Copy, PartialEq, Debug, Serialize, Deserialize)]
+pub struct VehicleSpawnConfig {
+    pub spawns_per_second: f32,
+    pub buildings_per_vehicle: f32,
+    pub speed_variation: f32,
+    #[serde(default = "default_driver_mix")]
+    pub driver_mix: [f32; 3],
+}
+
+fn default_driver_mix() -> [f32; 3] {
+    [0.6, 0.25, 0.15]
+}
+
+impl Default for VehicleSpawnConfig {
+    fn default() -> Self {
+        Self {
+            spawns_per_second: 2.0,
+            buildings_per_vehicle: 5.0,
+            speed_variation: 0.5,
+            driver_mix: default_driver_mix(),
+        }
+    }
+}
+
+// Stops the buses currently serve, visited in order and back to the first.
+#[derive(Resource, Debug, Default)]
+pub struct BusRoute {
+    pub stops: Vec<Entity>,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Default, Serialize, Deserialize)]
+pub enum VehicleKind {
+    #[default]
+    Car,
+    Truck,
+    Bus,
+    Emergency,
+}
+
+impl VehicleKind {
+    pub const ALL: [VehicleKind; 4] = [VehicleKind::Car, VehicleKind::Truck, VehicleKind::Bus, VehicleKind::Emergency];
+
+    fn speed_factor(self) -> f32 {
+        match self {
+            VehicleKind::Car | VehicleKind::Emergency => 1.0,
+            VehicleKind::Truck => 0.7,
+            VehicleKind::Bus => 0.8,
+        }
+    }
+
+    // Body length relative to a car. Longer vehicles also leave more room to the one ahead.
+    fn length(self) -> f32 {
+        match self {
+            VehicleKind::Car => 1.0,
+            VehicleKind::Truck => 1.6,
+            VehicleKind::Bus => 2.0,
+            VehicleKind::Emergency => 1.1,
+        }
+    }
+
+    pub fn model_tag(self) -> Option<&'static str> {
+        match self {
+            VehicleKind::Car => None,
+            VehicleKind::Truck => Some("truck"),
+            VehicleKind::Bus => Some("bus"),
+            VehicleKind::Emergency => Some("emergency"),
+        }
+    }
+
+    fn has_right_of_way(self) -> bool {
+        self == VehicleKind::Emergency
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Indicator {
+    Left,
+    Right,
+}
+
+#[derive(Component, Debug)]
+pub struct IndicatorLamp(pub Indicator);
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum TripPurpose {
+    Home,
+    Business,
+}
+
+impl TripPurpose {
+    pub fn of(destination: &Building) -> Self {
+        match destination.zone() {
+            Zone::Residential => TripPurpose::Home,
+            Zone::Commercial | Zone::Industrial => TripPurpose::Business,
+        }
+    }
+}
 
 #[derive(Component, Debug)]
 pub struct Vehicle {
@@ -88,10 +333,27 @@ pub struct Vehicle {
     pub follow: Vec3,
     pub checkpoint: Vec3,
     pub lane: i32,
+    pub lane_change_from: Option<i32>,
+    pub lane_change_progress: f32,
+    pub blocked_by: Option<Entity>,
+    pub obstructed_time: f32,
+    pub trip_time: f32,
+    pub trip_distance: f32,
+    pub model: usize,
+    pub kind: VehicleKind,
+    pub waiting: bool,
+    pub held: bool,
+    pub indicator: Option<Indicator>,
+    pub parked: Option<f32>,
+    pub parking_sought: bool,
+    pub crashed: bool,
+    pub length: f32,
+    pub trailer: Option<Entity>,
+    pub driver: &'static dyn DriverProfile,
 }
 
 impl Vehicle {
-    fn new(path: Vec<Entity>, max_speed: f32) -> Self {
+    fn new(path: Vec<Entity>, max_speed: f32, model: usize, kind: VehicleKind, driver: &'static dyn DriverProfile) -> Self {
         Self {
             path,
             path_index: 0,
@@ -100,7 +362,71 @@ impl Vehicle {
             follow: Vec3::ZERO,
             checkpoint: Vec3::ZERO,
             lane: 0,
+            lane_change_from: None,
+            lane_change_progress: 0.0,
+            blocked_by: None,
+            obstructed_time: 0.0,
+            trip_time: 0.0,
+            trip_distance: 0.0,
+            model,
+            kind,
+            waiting: false,
+            held: false,
+            indicator: None,
+            parked: None,
+            parking_sought: false,
+            crashed: false,
+            length: CAR_LENGTH * kind.length(),
+            trailer: None,
+            driver,
+        }
+    }
+
+    // Bumper to bumper, including the trailer when towing one.
+    pub fn full_length(&self) -> f32 {
+        match self.trailer {
+            Some(_) => self.length + HITCH_GAP + TRAILER_LENGTH,
+            None => self.length,
+        }
+    }
+
+    // How far ahead the vehicle looks for a leader to slow down behind.
+    pub fn look_ahead(&self) -> f32 {
+        FOLLOW_DISTANCE * self.kind.length() * self.driver.following_distance()
+    }
+
+    pub fn cruising_speed(&self, segment: &RoadSegment) -> f32 {
+        let limit = match self.kind.has_right_of_way() {
+            true => EMERGENCY_SPEED_LIMIT,
+            false => segment.speed_limit(),
+        };
+
+        limit * self.speed_multiplier * self.driver.speed_compliance()
+    }
+
+    pub fn destination(&self) -> Option<Entity> {
+        self.path.last().copied()
+    }
+
+    pub fn remaining_route(&self, pos: Vec3, network: &RoadNetwork) -> (f32, f32) {
+        let mut distance = 0.0;
+        let mut seconds = 0.0;
+        let mut last = pos;
+
+        for &step in self.path.iter().skip(self.path_index + 1) {
+            let (next, speed) = match (network.segment(step), network.position(step)) {
+                (Some(segment), _) => (segment.pos(), self.cruising_speed(segment)),
+                (None, Some(pos)) => (pos, self.speed_multiplier),
+                (None, None) => continue,
+            };
+
+            let leg = last.with_y(0.0).distance(next.with_y(0.0));
+            distance += leg;
+            seconds += leg / speed.max(VEHICLE_MIN_SPEED);
+            last = next;
         }
+
+        (distance, seconds)
     }
 }
 
@@ -121,8 +447,8 @@ fn get_step_type(step_entity: Entity, dest_query: &Query<&Building>, edge_query:
     }
 }
 
-fn direction_to_area(segment: &RoadSegment, area: GridArea) -> GDir {
-    match segment.orientation {
+pub fn direction_to_area(segment: &RoadSegment, area: GridArea) -> GDir {
+    match segment.axis_towards(area) {
         GAxis::Z => {
             if area.center().z > segment.area.center().z {
                 GDir::North
@@ -140,17 +466,17 @@ fn direction_to_area(segment: &RoadSegment, area: GridArea) -> GDir {
     }
 }
 
-fn direction_to_building(segment: &RoadSegment, building: &Building, pos: Vec3) -> GDir {
+fn direction_to_building(segment: &RoadSegment, entrance: Vec3, pos: Vec3) -> GDir {
     match segment.orientation {
         GAxis::Z => {
-            if building.area.center().z > pos.z {
+            if entrance.z > pos.z {
                 GDir::North
             } else {
                 GDir::South
             }
         }
         GAxis::X => {
-            if building.area.center().x > pos.x {
+            if entrance.x > pos.x {
                 GDir::West
             } else {
                 GDir::East
@@ -168,7 +494,7 @@ fn get_intersection_goal(intersection: &Intersection, direction: GDir, start_pos
     }
 }
 
-fn get_lane_for_turn(curr: &RoadSegment, next: &RoadSegment, clamp: &RoadSegment, prev: i32) -> i32 {
+pub fn get_lane_for_turn(curr: &RoadSegment, next: &RoadSegment, clamp: &RoadSegment, prev: i32) -> i32 {
     let z_less = next.area().center().z < curr.area().center().z;
     let x_less = next.area().center().x < curr.area().center().x;
     if curr.orientation == next.orientation {
@@ -198,7 +524,205 @@ fn get_lane_for_turn(curr: &RoadSegment, next: &RoadSegment, clamp: &RoadSegment
     }
 }
 
-fn execute_turning(mut vehicle_query: Query<(&Vehicle, &mut Transform)>, time: Res<Time>) {
+// Straight-through traffic keeps out of the left turn lane, except where there is no road to turn
+// left into, as along the top of a T.
+pub fn has_left_arm(intersection: &Intersection, from: usize) -> bool {
+    (0..4).any(|to| intersection.roads[to].is_some() && Movement { from, to }.turn() == Turn::Left)
+}
+
+fn lane_across(
+    intersection: &Intersection,
+    curr: Entity,
+    curr_segment: &RoadSegment,
+    next: &RoadSegment,
+    clamp: &RoadSegment,
+    prev: i32,
+) -> i32 {
+    let open_left = intersection.slot_of(curr).is_some_and(|from| !has_left_arm(intersection, from));
+    match curr_segment.orientation == next.orientation && open_left {
+        true => prev.clamp(0, clamp.num_lanes() - 1),
+        false => get_lane_for_turn(curr_segment, next, clamp, prev),
+    }
+}
+
+fn turn_indicator(intersection: &Intersection, from: Entity, to: Entity) -> Option<Indicator> {
+    let from = intersection.slot_of(from)?;
+    let to = intersection.slot_of(to)?;
+
+    match (Movement { from, to }).turn() {
+        Turn::Left => Some(Indicator::Left),
+        Turn::Right => Some(Indicator::Right),
+        Turn::Straight => None,
+    }
+}
+
+fn lane_change_indicator(segment: &RoadSegment, dir: GDir, from: i32, to: i32, transform: &Transform) -> Option<Indicator> {
+    if from == to || !segment.is_straight() {
+        return None;
+    }
+
+    let shift =
+        segment.clamp_to_lane(dir, to, transform.translation) - segment.clamp_to_lane(dir, from, transform.translation);
+    if shift.dot(transform.right().as_vec3()) > 0.0 {
+        Some(Indicator::Right)
+    } else {
+        Some(Indicator::Left)
+    }
+}
+
+fn pedestrian_ahead(transform: &Transform, walkers: Option<&Vec<Vec3>>) -> bool {
+    let heading = transform.forward().as_vec3();
+    walkers.is_some_and(|walkers| {
+        walkers.iter().any(|&walker| {
+            let offset = (walker - transform.translation).with_y(0.0);
+            offset.length() < PEDESTRIAN_YIELD_DISTANCE && offset.normalize_or_zero().dot(heading) > 0.5
+        })
+    })
+}
+
+fn beside_work_zone(segment: &RoadSegment, inter_query: &Query<&Intersection>, segment_query: &Query<&RoadSegment>) -> bool {
+    segment.ends.iter().flatten().filter_map(|&end| inter_query.get(end).ok()).any(|intersection| {
+        intersection.roads.iter().flatten().any(|&road| segment_query.get(road).is_ok_and(|other| other.closed))
+    })
+}
+
+// Seconds until the nearest vehicle on a conflicting approach reaches the intersection. Cross
+// streets always conflict; oncoming traffic only matters when turning left across it.
+fn cross_traffic_gap(
+    entity: Entity,
+    intersection: &Intersection,
+    movement: Movement,
+    occupancy_query: &Query<&LaneOccupancy>,
+) -> f32 {
+    let opposite = movement.from ^ 1;
+    // Along the top of a T the through road has priority, and only the road ending there gives way.
+    let through = intersection.is_tee() && intersection.roads[opposite].is_some();
+
+    (0..4)
+        .filter(|&slot| slot != movement.from && (slot != opposite || movement.turn() == Turn::Left))
+        .filter(|&slot| !through || slot == opposite)
+        .filter_map(|slot| intersection.roads[slot])
+        .filter_map(|road| occupancy_query.get(road).ok())
+        .flat_map(|occupancy| occupancy.occupants.iter())
+        .filter(|other| other.entity != entity && other.heading.dot(intersection.pos() - other.pos) > 0.0)
+        .map(|other| intersection.area.distance_to_point_3d(other.pos) / other.speed.max(VEHICLE_MIN_SPEED))
+        .fold(f32::INFINITY, f32::min)
+}
+
+// Whether at least one lane of the exit road has room for our whole length behind the queue on it,
+// so a long vehicle never comes to a stop with its tail still across the intersection.
+fn exit_has_room(
+    vehicle: &Vehicle,
+    intersection: &Intersection,
+    exit: Entity,
+    segment_query: &Query<&RoadSegment>,
+    occupancy_query: &Query<&LaneOccupancy>,
+) -> bool {
+    let (Ok(segment), Ok(occupancy)) = (segment_query.get(exit), occupancy_query.get(exit)) else {
+        return true;
+    };
+
+    let needed = vehicle.full_length() + EXIT_ROOM_MARGIN;
+    let mut full_lanes: Vec<i32> = occupancy
+        .occupants
+        .iter()
+        .filter(|other| other.speed < QUEUED_SPEED && other.heading.dot(other.pos - intersection.pos()) > 0.0)
+        .filter(|other| intersection.area.distance_to_point_3d(other.pos) - other.length < needed)
+        .map(|other| other.lane)
+        .collect();
+    full_lanes.sort();
+    full_lanes.dedup();
+
+    (full_lanes.len() as i32) < segment.num_lanes()
+}
+
+fn rear_of(vehicle: &Vehicle, transform: &Transform, trailer_query: &Query<(&Trailer, &Transform)>) -> Vec3 {
+    match vehicle.trailer.and_then(|trailer| trailer_query.get(trailer).ok()) {
+        Some((trailer, trailer_transform)) => trailer.rear(trailer_transform),
+        None => transform.translation - transform.forward().as_vec3() * vehicle.length / 2.0,
+    }
+}
+
+// Overtaking is not allowed close to either end of a segment. A lane change the route needs still is,
+// so short segments do not strand vehicles in the wrong lane; the gap check keeps it safe.
+fn lane_change_is_legal(segment: &RoadSegment, dir: GDir, pos: Vec3, required: bool) -> bool {
+    if !segment.is_straight() {
+        return false;
+    }
+
+    if required {
+        return true;
+    }
+
+    let axis = dir.as_vec3();
+    let bound_a = segment.area.min.min_corner().dot(axis);
+    let bound_b = segment.area.max.max_corner().dot(axis);
+    let along = pos.dot(axis);
+
+    along - bound_a.min(bound_b) > LANE_CHANGE_INTERSECTION_BUFFER
+        && bound_a.max(bound_b) - along > LANE_CHANGE_INTERSECTION_BUFFER
+}
+
+fn lane_gap_is_clear(
+    entity: Entity,
+    pos: Vec3,
+    dir: GDir,
+    lane: i32,
+    occupancy: Option<&LaneOccupancy>,
+    settings: &LaneChangeSettings,
+    driver: &dyn DriverProfile,
+) -> bool {
+    let axis = dir.as_vec3();
+    let gap_ahead = LANE_CHANGE_GAP_AHEAD * settings.gap_scale(driver);
+    let gap_behind = LANE_CHANGE_GAP_BEHIND * settings.gap_scale(driver);
+
+    occupancy.into_iter().flat_map(|occupancy| occupancy.in_lane(lane, axis)).all(|other| {
+        let offset = (other.pos - pos).dot(axis);
+        other.entity == entity || offset >= gap_ahead || offset <= -gap_behind
+    })
+}
+
+fn blocked_by_slower(vehicle: &Vehicle, segment: &RoadSegment, occupancy: Option<&LaneOccupancy>) -> bool {
+    let cruising = vehicle.cruising_speed(segment);
+
+    vehicle
+        .blocked_by
+        .and_then(|blocker| occupancy.and_then(|occupancy| occupancy.get(blocker)))
+        .is_some_and(|blocker| blocker.speed < cruising * OVERTAKE_SPEED_RATIO)
+}
+
+fn track_lane_occupancy(
+    vehicle_query: Query<(Entity, &Vehicle, &Transform), Without<Dormant>>,
+    mut occupancy_query: Query<&mut LaneOccupancy>,
+) {
+    for mut occupancy in &mut occupancy_query {
+        occupancy.occupants.clear();
+    }
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        let Some(mut occupancy) = vehicle.path.get(vehicle.path_index).and_then(|&step| occupancy_query.get_mut(step).ok())
+        else {
+            continue;
+        };
+
+        let occupant = LaneOccupant {
+            entity,
+            pos: transform.translation,
+            heading: transform.forward().as_vec3(),
+            lane: vehicle.lane,
+            speed: vehicle.speed,
+            length: vehicle.full_length(),
+        };
+
+        occupancy.occupants.push(occupant);
+
+        if let Some(from) = vehicle.lane_change_from {
+            occupancy.occupants.push(LaneOccupant { lane: from, ..occupant });
+        }
+    }
+}
+
+fn execute_turning(mut vehicle_query: Query<(&Vehicle, &mut Transform), Without<Dormant>>, time: Res<Time>) {
     vehicle_query.par_iter_mut().for_each(|(vehicle, mut transform)| {
         let follow_vec = vehicle.follow.with_y(0.0) - transform.translation.with_y(0.0);
         let follow_dir = follow_vec.normalize();
@@ -211,57 +735,159 @@ fn execute_turning(mut vehicle_query: Query<(&Vehicle, &mut Transform)>, time: R
             let scalar = follow_dir.angle_between(transform.left().as_vec3());
             transform.rotate_y(-1.0 * scalar * time.delta_seconds());
         } else {
-            transform.look_at(vehicle.follow, Vec3::new(0.0, 1.0, 0.0));
+            let level = vehicle.follow.with_y(transform.translation.y);
+            transform.look_at(level, Vec3::new(0.0, 1.0, 0.0));
         }
     });
 }
 
-fn update_speed(
-    mut vehicle_query: Query<(Entity, &mut Vehicle, &RaycastSource<VehicleRaycastSet>)>,
-    other_query: Query<&RaycastSource<VehicleRaycastSet>, With<Vehicle>>,
+// Trailers are hashed under their tractor's entity, step and lane, so traffic behind queues up
+// behind the trailer and a tractor never mistakes its own trailer for a vehicle ahead.
+fn hash_vehicle_positions(
+    vehicle_query: Query<(Entity, &Vehicle, &Transform), Without<Dormant>>,
+    trailer_query: Query<(&Trailer, &Transform)>,
+    mut hash: ResMut<VehicleSpatialHash>,
+) {
+    hash.clear();
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        hash.insert(HashedVehicle {
+            entity,
+            pos: transform.translation,
+            heading: transform.forward().as_vec3(),
+            step: vehicle.path.get(vehicle.path_index).copied(),
+            lane: vehicle.lane,
+            length: vehicle.length,
+        });
+    }
+
+    for (trailer, transform) in &trailer_query {
+        let Ok((entity, vehicle, _)) = vehicle_query.get(trailer.tractor) else {
+            continue;
+        };
+
+        hash.insert(HashedVehicle {
+            entity,
+            pos: transform.translation,
+            heading: transform.forward().as_vec3(),
+            step: vehicle.path.get(vehicle.path_index).copied(),
+            lane: vehicle.lane,
+            length: trailer.length,
+        });
+    }
+}
+
+// The closest vehicle ahead that shares our lane, and the gap from our front to its rear, as long
+// as that gap is under `range`. Vehicles on a different path step (for example already inside the
+// next intersection) count when they sit roughly in line with us.
+fn leader_ahead(
+    entity: Entity,
+    vehicle: &Vehicle,
+    transform: &Transform,
+    hash: &VehicleSpatialHash,
+    range: f32,
+) -> Option<(Entity, f32)> {
+    let heading = transform.forward().as_vec3();
+    let step = vehicle.path.get(vehicle.path_index).copied();
+
+    hash.nearby(transform.translation, range + (vehicle.length + MAX_BODY_LENGTH) / 2.0)
+        .filter(|other| other.entity != entity && other.heading.dot(heading) > 0.0)
+        .filter_map(|other| {
+            let offset = (other.pos - transform.translation).with_y(0.0);
+            let ahead = offset.dot(heading);
+            let lateral = (offset - heading * ahead).length();
+
+            let same_lane = match other.step == step {
+                true => other.lane == vehicle.lane || lateral < FOLLOW_LANE_TOLERANCE,
+                false => lateral < FOLLOW_LANE_TOLERANCE,
+            };
+
+            let gap = ahead - (vehicle.length + other.length) / 2.0;
+            (ahead > 0.0 && same_lane && gap < range).then_some((other.entity, gap))
+        })
+        .min_by(|(_, a), (_, b)| a.total_cmp(b))
+}
+
+pub fn update_speed(
+    mut vehicle_query: Query<(Entity, &mut Vehicle, &Transform), Without<Dormant>>,
+    hash: Res<VehicleSpatialHash>,
     time: Res<Time>,
     segment_query: Query<&RoadSegment>,
+    inter_query: Query<&Intersection>,
+    weather: Res<WeatherState>,
+    weather_settings: Res<WeatherSettings>,
 ) {
-    vehicle_query.par_iter_mut().for_each(|(ent, mut vehicle, raycast)| {
-        let mut target_speed = 1.0 * vehicle.speed_multiplier;
+    let speed_factor = weather_settings.speed_factor(*weather);
+    let acceleration_factor = weather_settings.acceleration_factor(*weather);
 
-        if let Ok(segment) = segment_query.get(vehicle.path[vehicle.path_index]) {
-            target_speed = segment.speed_limit() * vehicle.speed_multiplier;
+    vehicle_query.par_iter_mut().for_each(|(ent, mut vehicle, transform)| {
+        if vehicle.crashed {
+            vehicle.speed = 0.0;
+            return;
         }
 
-        vehicle.speed = vehicle.speed.lerp(target_speed, time.delta_seconds() * 0.5);
+        if vehicle.waiting {
+            vehicle.obstructed_time = 0.0;
+            return;
+        }
 
-        let slow_dist = 3.0;
-        if let Some((other, hit)) = raycast.get_nearest_intersection() {
-            if let Ok(other_raycast) = other_query.get(other) {
-                if let Some((other2, _)) = other_raycast.get_nearest_intersection() {
-                    if other2 == ent {
-                        return;
-                    }
-                }
-            }
+        let mut target_speed = 1.0 * vehicle.speed_multiplier;
 
-            if hit.distance() < slow_dist {
-                vehicle.speed -= (slow_dist - hit.distance()).max(0.0) * time.delta_seconds();
-                vehicle.speed = vehicle.speed.max(VEHICLE_MIN_SPEED);
+        if let Ok(segment) = segment_query.get(vehicle.path[vehicle.path_index]) {
+            target_speed = vehicle.cruising_speed(segment) * speed_factor;
+
+            if !vehicle.kind.has_right_of_way() && beside_work_zone(segment, &inter_query, &segment_query) {
+                target_speed *= WORK_ZONE_SLOWDOWN;
             }
         }
+
+        target_speed *= vehicle.driver.speed_sway(vehicle.trip_time);
+
+        let acceleration = if target_speed > vehicle.speed {
+            acceleration_factor * (0.5 + vehicle.driver.aggressiveness()) / 2.0
+        } else {
+            0.5
+        };
+        vehicle.speed = vehicle.speed.lerp(target_speed, time.delta_seconds() * acceleration);
+
+        let slow_gap = vehicle.look_ahead();
+        let obstructed_time = vehicle.obstructed_time;
+        vehicle.obstructed_time = 0.0;
+        vehicle.blocked_by = None;
+
+        if let Some((other, gap)) = leader_ahead(ent, &vehicle, transform, &hash, slow_gap) {
+            vehicle.speed -= (slow_gap - gap).max(0.0) * time.delta_seconds();
+            vehicle.speed = vehicle.speed.max(VEHICLE_MIN_SPEED);
+            vehicle.obstructed_time = obstructed_time + time.delta_seconds();
+            vehicle.blocked_by = Some(other);
+        }
     });
 }
 
-fn execute_movement(mut vehicle_query: Query<(&Vehicle, &mut Transform)>, time: Res<Time>) {
+fn execute_movement(mut vehicle_query: Query<(&Vehicle, &mut Transform), Without<Dormant>>, time: Res<Time>) {
     vehicle_query.par_iter_mut().for_each(|(vehicle, mut transform)| {
         let translate_dir = transform.forward().as_vec3();
         transform.translation += vehicle.speed * translate_dir * time.delta_seconds();
     });
 }
 
+pub fn follow_terrain(
+    mut vehicle_query: Query<(&Vehicle, &mut Transform), Without<Dormant>>,
+    terrain: Res<Terrain>,
+    models: Res<Models>,
+) {
+    vehicle_query.par_iter_mut().for_each(|(vehicle, mut transform)| {
+        let offset = models.vehicle_models.get(vehicle.model).map_or(0.0, |model| model.vertical_offset);
+        transform.translation.y = terrain.road_height(transform.translation) + ROAD_HEIGHT + VEHICLE_HEIGHT + offset;
+    });
+}
+
 fn toggle_ai_vizualization(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     mut next_state: ResMut<NextState<AiVisualizationState>>,
     state: Res<State<AiVisualizationState>>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyV) {
+    if actions.just_pressed(Action::ToggleAiView) {
         next_state.set({
             match state.get() {
                 AiVisualizationState::Hide => AiVisualizationState::Visualize,
@@ -272,11 +898,11 @@ fn toggle_ai_vizualization(
 }
 
 fn toggle_vehicle_spawning(
-    keyboard: Res<ButtonInput<KeyCode>>,
+    actions: Actions,
     mut next_state: ResMut<NextState<VehicleSpawnState>>,
     state: Res<State<VehicleSpawnState>>,
 ) {
-    if keyboard.just_pressed(KeyCode::KeyL) {
+    if actions.just_pressed(Action::ToggleSpawning) {
         next_state.set({
             match state.get() {
                 VehicleSpawnState::On => VehicleSpawnState::Off,
@@ -293,23 +919,91 @@ fn visualize_vehicle_ai(mut vehicle_query: Query<(&Vehicle, &Transform)>, mut gi
     }
 }
 
-fn update_vehicles(
+// What a driver can see of the road around them.
+#[derive(SystemParam)]
+pub struct Surroundings<'w, 's> {
+    segment_query: Query<'w, 's, &'static RoadSegment>,
+    intersection_query: Query<'w, 's, &'static Intersection>,
+    building_query: Query<'w, 's, &'static Building>,
+    signal_query: Query<'w, 's, &'static TrafficSignal>,
+    occupancy_query: Query<'w, 's, &'static LaneOccupancy>,
+    pedestrian_query: Query<'w, 's, (&'static Pedestrian, &'static Transform), Without<Vehicle>>,
+}
+
+#[derive(SystemParam)]
+pub struct DrivingSettings<'w> {
+    lane_change: Res<'w, LaneChangeSettings>,
+    gap_acceptance: Res<'w, GapAcceptanceSettings>,
+    capacity: Res<'w, IntersectionCapacitySettings>,
+}
+
+pub fn update_vehicles(
     mut commands: Commands,
-    mut vehicle_query: Query<(Entity, &mut Vehicle, &mut Transform)>,
-    segment_query: Query<&RoadSegment>,
-    intersection_query: Query<&Intersection>,
-    building_query: Query<&Building>,
+    mut vehicle_query: Query<(Entity, &mut Vehicle, &mut Transform), Without<Dormant>>,
+    surroundings: Surroundings,
+    driving: DrivingSettings,
+    time: Res<Time>,
+    mut completed: EventWriter<OnTripCompleted>,
 ) {
+    let Surroundings {
+        segment_query,
+        intersection_query,
+        building_query,
+        signal_query,
+        occupancy_query,
+        pedestrian_query,
+    } = surroundings;
+    let DrivingSettings {
+        lane_change: settings,
+        gap_acceptance: gap_settings,
+        capacity: capacity_settings,
+    } = driving;
+
     for (entity, vehicle, _) in &vehicle_query {
-        if vehicle.path_index >= vehicle.path.len() - 1 {
+        if vehicle.path_index >= vehicle.path.len() - 1 && vehicle.parked.is_none() {
+            completed.send(OnTripCompleted {
+                kind: vehicle.kind,
+                duration: vehicle.trip_time,
+                distance: vehicle.trip_distance,
+            });
             commands.entity(entity).despawn_recursive();
         }
     }
-    vehicle_query.par_iter_mut().for_each(|(_, mut vehicle, mut transform)| {
-        if vehicle.path_index >= vehicle.path.len() - 1 {
+
+    let mut crossing = HashMap::<Entity, Vec<Vec3>>::new();
+    let mut cleared = HashMap::<Entity, Entity>::new();
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        if !vehicle.kind.has_right_of_way() {
+            continue;
+        }
+
+        for &step in vehicle.path.iter().skip(vehicle.path_index).take(2) {
+            let near = intersection_query.get(step).is_ok_and(|intersection| {
+                intersection.area.distance_to_point_3d(transform.translation) < EMERGENCY_CLEARANCE_DISTANCE
+            });
+
+            if near {
+                cleared.insert(step, entity);
+            }
+        }
+    }
+
+    for (pedestrian, transform) in &pedestrian_query {
+        let step = pedestrian.path[pedestrian.path_index];
+        if intersection_query.contains(step) {
+            crossing.entry(step).or_default().push(transform.translation);
+        }
+    }
+
+    vehicle_query.par_iter_mut().for_each(|(entity, mut vehicle, mut transform)| {
+        if vehicle.crashed || vehicle.parked.is_some() || vehicle.path_index >= vehicle.path.len() - 1 {
             return;
         }
 
+        vehicle.trip_time += time.delta_seconds();
+        vehicle.trip_distance += vehicle.speed * time.delta_seconds();
+
         let curr = vehicle.path[vehicle.path_index];
         let next = vehicle.path[vehicle.path_index + 1];
 
@@ -318,6 +1012,9 @@ fn update_vehicles(
 
         vehicle.checkpoint = transform.translation;
         vehicle.follow = transform.translation;
+        vehicle.waiting = false;
+        vehicle.held = false;
+        vehicle.indicator = None;
 
         if curr_type == StepType::Building && next_type == StepType::Road {
             if let Ok(segment) = segment_query.get(next) {
@@ -326,11 +1023,34 @@ fn update_vehicles(
                 vehicle.path_index += 1;
                 return;
             }
+        } else if curr_type == StepType::Road && next == curr {
+            // Doubling back, so drive on to the dead end and turn around there. If the road has since been
+            // joined up at that end the turn is made on the spot.
+            if let Ok(segment) = segment_query.get(curr) {
+                let Some((cap, gdir)) = segment.dead_end() else {
+                    vehicle.path_index += 1;
+                    return;
+                };
+
+                vehicle.checkpoint = segment.clamp_to_lane(gdir, vehicle.lane, cap.center().with_y(transform.translation.y));
+                vehicle.follow = segment.lane_follow_point(gdir, vehicle.lane, transform.translation, 0.5);
+
+                let distance = transform.translation.distance(vehicle.checkpoint);
+                if distance < SIGNAL_STOP_DISTANCE {
+                    vehicle.indicator = Some(Indicator::Left);
+                    vehicle.waiting = true;
+                    vehicle.speed = vehicle.speed.min(distance.max(TURNAROUND_SPEED));
+                }
+
+                if distance < TURNAROUND_REACH {
+                    vehicle.path_index += 1;
+                }
+            }
         } else if curr_type == StepType::Road && next_type == StepType::Building {
             if let Ok(building) = building_query.get(next) {
                 if let Ok(segment) = segment_query.get(curr) {
-                    let approach_dir = direction_to_building(segment, building, transform.translation);
-                    let target = building.area.center().with_y(transform.translation.y);
+                    let target = building.entrance(curr).with_y(transform.translation.y);
+                    let approach_dir = direction_to_building(segment, target, transform.translation);
                     vehicle.checkpoint = segment.clamp_to_lane(approach_dir, 0, target);
 
                     let lane_pos = segment.clamp_to_lane(approach_dir, 0, transform.translation);
@@ -352,16 +1072,149 @@ fn update_vehicles(
                     let approach_dir = direction_to_area(segment, intersection.area());
                     vehicle.checkpoint = get_intersection_goal(intersection, approach_dir, transform.translation);
 
-                    if let Ok(next_segment) = segment_query.get(vehicle.path[vehicle.path_index + 2]) {
-                        vehicle.lane = get_lane_for_turn(segment, next_segment, segment, vehicle.lane);
+                    let occupancy = occupancy_query.get(curr).ok();
+
+                    if let Some(from) = vehicle.lane_change_from {
+                        vehicle.lane_change_progress += time.delta_seconds() / LANE_CHANGE_SECONDS;
+
+                        if vehicle.lane_change_progress >= 1.0 {
+                            vehicle.lane_change_from = None;
+                        } else {
+                            vehicle.indicator = lane_change_indicator(segment, approach_dir, from, vehicle.lane, &transform);
+                        }
+                    } else if let Ok(next_segment) = segment_query.get(vehicle.path[vehicle.path_index + 2]) {
+                        let desired = lane_across(intersection, curr, segment, next_segment, segment, vehicle.lane);
+                        let mut target = desired;
+
+                        if desired == vehicle.lane
+                            && vehicle.obstructed_time > settings.patience(vehicle.driver)
+                            && blocked_by_slower(&vehicle, segment, occupancy)
+                        {
+                            if vehicle.lane + 1 < segment.num_lanes() {
+                                target = vehicle.lane + 1;
+                            } else if vehicle.lane > 0 {
+                                target = vehicle.lane - 1;
+                            }
+                        }
+
+                        let step = (vehicle.lane + (target - vehicle.lane).signum()).clamp(0, segment.num_lanes() - 1);
+                        vehicle.indicator = lane_change_indicator(segment, approach_dir, vehicle.lane, step, &transform);
+
+                        if step != vehicle.lane
+                            && lane_change_is_legal(segment, approach_dir, transform.translation, desired != vehicle.lane)
+                            && lane_gap_is_clear(
+                                entity,
+                                transform.translation,
+                                approach_dir,
+                                step,
+                                occupancy,
+                                &settings,
+                                vehicle.driver,
+                            )
+                        {
+                            vehicle.lane_change_from = Some(vehicle.lane);
+                            vehicle.lane_change_progress = 0.0;
+                            vehicle.lane = step;
+                            vehicle.obstructed_time = 0.0;
+                        }
                     }
 
-                    let lane_pos = segment.clamp_to_lane(approach_dir, vehicle.lane, transform.translation);
-                    let current_vec = transform.translation - vehicle.checkpoint;
-                    let desired_vec = lane_pos - vehicle.checkpoint;
-                    let proj = vehicle.checkpoint + (current_vec).project_onto(desired_vec);
-                    let interp_proj = proj + (vehicle.checkpoint - proj).normalize() * 0.5;
-                    vehicle.follow = interp_proj;
+                    if segment.is_straight() {
+                        let mut lane_pos = segment.clamp_to_lane(approach_dir, vehicle.lane, transform.translation);
+                        if let Some(from) = vehicle.lane_change_from {
+                            let from_pos = segment.clamp_to_lane(approach_dir, from, transform.translation);
+                            let t = vehicle.lane_change_progress.clamp(0.0, 1.0);
+                            lane_pos = from_pos.lerp(lane_pos, t * t * (3.0 - 2.0 * t));
+                        }
+
+                        let current_vec = transform.translation - vehicle.checkpoint;
+                        let desired_vec = lane_pos - vehicle.checkpoint;
+                        let proj = vehicle.checkpoint + (current_vec).project_onto(desired_vec);
+                        let interp_proj = proj + (vehicle.checkpoint - proj).normalize() * 0.5;
+                        vehicle.follow = interp_proj;
+                    } else {
+                        vehicle.checkpoint = segment.lane_exit_point(approach_dir, vehicle.lane, transform.translation);
+                        vehicle.follow = segment.lane_follow_point(approach_dir, vehicle.lane, transform.translation, 0.5);
+                    }
+
+                    if intersection.area.distance_to_point_3d(transform.translation) < INDICATOR_DISTANCE {
+                        if let Some(&exit) = vehicle.path.get(vehicle.path_index + 2) {
+                            vehicle.indicator = turn_indicator(intersection, curr, exit);
+                        }
+                    }
+
+                    if pedestrian_ahead(&transform, crossing.get(&next)) {
+                        let distance = intersection.area.distance_to_point_3d(transform.translation);
+                        vehicle.waiting = true;
+                        vehicle.speed = vehicle.speed.min((distance - SIGNAL_COMMIT_DISTANCE).max(0.0) * 2.0);
+                    }
+
+                    let distance = intersection.area.distance_to_point_3d(transform.translation);
+                    let stop_sign = intersection.control == IntersectionControl::StopSign;
+
+                    if stop_sign
+                        && distance < SIGNAL_STOP_DISTANCE
+                        && !intersection.reservations.contains(&entity)
+                        && !vehicle.kind.has_right_of_way()
+                    {
+                        vehicle.waiting = true;
+                        vehicle.speed = vehicle.speed.min((distance - SIGNAL_COMMIT_DISTANCE).max(0.0) * 2.0);
+                    }
+
+                    let yield_to_emergency = cleared.get(&next).is_some_and(|&other| other != entity);
+                    let from = intersection.slot_of(curr);
+                    let to = vehicle.path.get(vehicle.path_index + 2).and_then(|&exit| intersection.slot_of(exit));
+
+                    if let (Some(from), Some(to)) = (from, to) {
+                        if distance > SIGNAL_COMMIT_DISTANCE && distance < SIGNAL_STOP_DISTANCE {
+                            let movement = Movement { from, to };
+                            let gap = cross_traffic_gap(entity, intersection, movement, &occupancy_query);
+                            let arrival = distance / vehicle.speed.max(VEHICLE_MIN_SPEED);
+                            let gap_is_clear = gap >= gap_settings.critical_gap_seconds || gap > arrival;
+
+                            let must_stop = match signal_query.get(next) {
+                                Ok(signal) if !signal.allows(from, to) => {
+                                    let right_on_red = gap_settings.right_on_red
+                                        && movement.turn() == Turn::Right
+                                        && vehicle.speed < RIGHT_ON_RED_SPEED;
+                                    !(right_on_red && gap_is_clear)
+                                }
+                                Ok(_) => false,
+                                Err(_) => !stop_sign && !gap_is_clear,
+                            };
+
+                            if (must_stop || yield_to_emergency) && !vehicle.kind.has_right_of_way() {
+                                vehicle.waiting = true;
+                                vehicle.speed = vehicle.speed.min((distance - SIGNAL_COMMIT_DISTANCE) * 2.0);
+                            }
+                        }
+                    }
+
+                    let blocks_exit = vehicle
+                        .path
+                        .get(vehicle.path_index + 2)
+                        .is_some_and(|&exit| !exit_has_room(&vehicle, intersection, exit, &segment_query, &occupancy_query));
+
+                    if blocks_exit
+                        && distance > SIGNAL_COMMIT_DISTANCE
+                        && distance < SIGNAL_STOP_DISTANCE
+                        && !vehicle.kind.has_right_of_way()
+                    {
+                        vehicle.waiting = true;
+                        vehicle.speed = vehicle.speed.min((distance - SIGNAL_COMMIT_DISTANCE) * 2.0);
+                    }
+
+                    // Free to go but not yet let in, so the intersection is full or has yet to see us.
+                    if capacity_settings.enabled
+                        && !vehicle.waiting
+                        && distance < SIGNAL_STOP_DISTANCE
+                        && !intersection.occupants.contains(&entity)
+                        && !vehicle.kind.has_right_of_way()
+                    {
+                        vehicle.waiting = true;
+                        vehicle.held = true;
+                        vehicle.speed = vehicle.speed.min((distance - SIGNAL_COMMIT_DISTANCE).max(0.0) * 2.0);
+                    }
 
                     if intersection.area.contains_point_3d(transform.translation) {
                         vehicle.path_index += 1;
@@ -375,15 +1228,26 @@ fn update_vehicles(
                     let approach_dir = direction_to_area(next_segment, intersection.area()).inverse();
 
                     if let Ok(prev_segment) = segment_query.get(vehicle.path[vehicle.path_index - 1]) {
-                        vehicle.lane = get_lane_for_turn(prev_segment, next_segment, next_segment, vehicle.lane);
+                        let prev = vehicle.path[vehicle.path_index - 1];
+                        vehicle.lane =
+                            lane_across(intersection, prev, prev_segment, next_segment, next_segment, vehicle.lane);
                     }
 
+                    vehicle.lane_change_from = None;
+
+                    vehicle.indicator = turn_indicator(intersection, vehicle.path[vehicle.path_index - 1], next);
+
                     vehicle.checkpoint = next_segment.clamp_to_lane(approach_dir, vehicle.lane, transform.translation);
                     vehicle.checkpoint += approach_dir.as_vec3() * INTERSECTION_OFFSET;
 
                     let interp_proj = transform.translation + (vehicle.checkpoint - transform.translation).normalize() * 0.5;
                     vehicle.follow = interp_proj;
 
+                    if pedestrian_ahead(&transform, crossing.get(&curr)) {
+                        vehicle.waiting = true;
+                        vehicle.speed = 0.0;
+                    }
+
                     if next_segment.area.contains_point_3d(transform.translation) {
                         vehicle.path_index += 1;
                         return;
@@ -394,222 +1258,647 @@ fn update_vehicles(
     });
 }
 
+// Admits stop sign traffic one vehicle at a time, in the order vehicles came to a halt at the line.
+// A vehicle holds the intersection until its rear, or its trailer's, is out of it.
+fn update_stop_sign_queues(
+    mut inter_query: Query<(Entity, &mut Intersection)>,
+    vehicle_query: Query<(Entity, &Vehicle, &Transform), Without<Dormant>>,
+    trailer_query: Query<(&Trailer, &Transform)>,
+) {
+    let mut present = HashMap::<Entity, Vec<Entity>>::new();
+    let mut halted = HashMap::<Entity, Vec<(Entity, Vec3)>>::new();
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        for &step in vehicle.path.iter().skip(vehicle.path_index).take(2) {
+            present.entry(step).or_default().push(entity);
+        }
+
+        if let Some(&behind) = vehicle.path_index.checked_sub(1).and_then(|index| vehicle.path.get(index)) {
+            let rear = rear_of(vehicle, transform, &trailer_query);
+            if inter_query.get(behind).is_ok_and(|(_, intersection)| intersection.area.contains_point_3d(rear)) {
+                present.entry(behind).or_default().push(entity);
+            }
+        }
+
+        if vehicle.waiting && vehicle.speed < STOP_SIGN_HALT_SPEED {
+            if let Some(&next) = vehicle.path.get(vehicle.path_index + 1) {
+                halted.entry(next).or_default().push((entity, transform.translation));
+            }
+        }
+    }
+
+    for (entity, mut intersection) in &mut inter_query {
+        if intersection.control != IntersectionControl::StopSign {
+            continue;
+        }
+
+        let here = present.get(&entity).map_or(&[][..], |vehicles| vehicles.as_slice());
+        let mut arrivals: Vec<Entity> = intersection.arrivals.iter().copied().filter(|v| here.contains(v)).collect();
+        let mut reservations: HashSet<Entity> =
+            intersection.reservations.iter().copied().filter(|v| here.contains(v)).collect();
+
+        let mut newcomers: Vec<Entity> = halted
+            .get(&entity)
+            .into_iter()
+            .flatten()
+            .filter(|(_, pos)| intersection.area.distance_to_point_3d(*pos) < SIGNAL_STOP_DISTANCE)
+            .map(|&(vehicle, _)| vehicle)
+            .filter(|vehicle| !arrivals.contains(vehicle) && !reservations.contains(vehicle))
+            .collect();
+        newcomers.sort();
+        arrivals.extend(newcomers);
+
+        if reservations.is_empty() && !arrivals.is_empty() {
+            reservations.insert(arrivals.remove(0));
+        }
+
+        if arrivals != intersection.arrivals || reservations != intersection.reservations {
+            intersection.arrivals = arrivals;
+            intersection.reservations = reservations;
+        }
+    }
+}
+
+// Lets vehicles at the stop line into each intersection while it has room, nearest first. A place is
+// kept from being let in until the vehicle's rear, or its trailer's, is out again. Vehicles stopped
+// for a signal, a stop sign or a full exit are passed over, so they never hold a place they cannot use.
+fn update_intersection_capacity(
+    mut inter_query: Query<(Entity, &mut Intersection)>,
+    vehicle_query: Query<(Entity, &Vehicle, &Transform), Without<Dormant>>,
+    trailer_query: Query<(&Trailer, &Transform)>,
+    settings: Res<IntersectionCapacitySettings>,
+) {
+    let mut inside = HashMap::<Entity, Vec<Entity>>::new();
+    let mut approaching = HashMap::<Entity, HashSet<Entity>>::new();
+    let mut at_line = HashMap::<Entity, Vec<(Entity, f32)>>::new();
+    let mut queued = HashMap::<Entity, usize>::new();
+
+    for (entity, vehicle, transform) in &vehicle_query {
+        if let Some(&curr) = vehicle.path.get(vehicle.path_index).filter(|&&curr| inter_query.contains(curr)) {
+            inside.entry(curr).or_default().push(entity);
+        }
+
+        if let Some(&behind) = vehicle.path_index.checked_sub(1).and_then(|index| vehicle.path.get(index)) {
+            let rear = rear_of(vehicle, transform, &trailer_query);
+            if inter_query.get(behind).is_ok_and(|(_, intersection)| intersection.area.contains_point_3d(rear)) {
+                inside.entry(behind).or_default().push(entity);
+            }
+        }
+
+        let Some(&next) = vehicle.path.get(vehicle.path_index + 1) else {
+            continue;
+        };
+        let Ok((_, intersection)) = inter_query.get(next) else {
+            continue;
+        };
+
+        approaching.entry(next).or_default().insert(entity);
+        if vehicle.speed < QUEUED_SPEED {
+            *queued.entry(next).or_default() += 1;
+        }
+
+        let distance = intersection.area.distance_to_point_3d(transform.translation);
+        if distance < SIGNAL_STOP_DISTANCE && (!vehicle.waiting || vehicle.held) {
+            at_line.entry(next).or_default().push((entity, distance));
+        }
+    }
+
+    for (entity, mut intersection) in &mut inter_query {
+        let mut occupants: HashSet<Entity> = intersection
+            .occupants
+            .iter()
+            .copied()
+            .filter(|vehicle| approaching.get(&entity).is_some_and(|vehicles| vehicles.contains(vehicle)))
+            .chain(inside.remove(&entity).into_iter().flatten())
+            .collect();
+
+        if settings.enabled {
+            let mut waiting = at_line.remove(&entity).unwrap_or_default();
+            waiting.retain(|(vehicle, _)| !occupants.contains(vehicle));
+            waiting.sort_by(|(a, a_distance), (b, b_distance)| a_distance.total_cmp(b_distance).then(a.cmp(b)));
+
+            let room = intersection.capacity(settings.cells_per_vehicle).saturating_sub(occupants.len());
+            occupants.extend(waiting.into_iter().take(room).map(|(vehicle, _)| vehicle));
+        }
+
+        let queue = queued.get(&entity).copied().unwrap_or(0);
+        if occupants != intersection.occupants || queue != intersection.queue {
+            intersection.occupants = occupants;
+            intersection.queue = queue;
+        }
+    }
+}
+
 #[derive(Event, Debug)]
 pub struct RequestVehicleSpawn;
 
+#[derive(Event, Debug)]
+pub struct RequestVehicleRestore {
+    pub path: Vec<Entity>,
+    pub path_index: usize,
+    pub translation: Vec3,
+    pub rotation: Quat,
+    pub speed: f32,
+    pub speed_multiplier: f32,
+    pub lane: i32,
+    pub model: usize,
+    pub kind: VehicleKind,
+    pub trip_time: f32,
+    pub trip_distance: f32,
+    pub trailer: bool,
+    pub driver: &'static dyn DriverProfile,
+}
+
+#[derive(Event, Debug)]
+pub struct OnTripCompleted {
+    pub kind: VehicleKind,
+    pub duration: f32,
+    pub distance: f32,
+}
+
+// A trip that could not be routed, or lost its route to a removed road with no way around.
+#[derive(Event, Debug)]
+pub struct OnTripFailed;
+
 #[derive(Resource, Debug)]
 pub struct SpawnTimer {
     timer: Timer,
 }
 
-fn spawn_vehicle_on_key_press(keyboard: Res<ButtonInput<KeyCode>>, mut request: EventWriter<RequestVehicleSpawn>) {
-    if keyboard.just_pressed(KeyCode::KeyP) {
+fn spawn_vehicle_on_key_press(actions: Actions, mut request: EventWriter<RequestVehicleSpawn>) {
+    if actions.just_pressed(Action::SpawnVehicle) {
         request.send(RequestVehicleSpawn);
     }
 }
 
+// Commuters who are due on the road raise the vehicle cap, which is what makes rush hours busier.
+#[derive(SystemParam)]
+struct VehicleCap<'w, 's> {
+    building_query: Query<'w, 's, &'static Building>,
+    vehicle_query: Query<'w, 's, &'static Vehicle>,
+    commutes: Res<'w, Commutes>,
+    time_of_day: Res<'w, TimeOfDay>,
+    config: Res<'w, VehicleSpawnConfig>,
+}
+
+impl VehicleCap<'_, '_> {
+    fn has_room(&self) -> bool {
+        // District policies make some buildings count for more or less than one.
+        let num_buildings: f32 = self.building_query.iter().map(|building| building.spawn_multiplier).sum();
+        let max_vehicles = match self.commutes.due(self.time_of_day.hour) {
+            0 => num_buildings / self.config.buildings_per_vehicle,
+            _ => num_buildings * RUSH_HOUR_VEHICLE_FACTOR / self.config.buildings_per_vehicle,
+        } as usize;
+
+        self.vehicle_query.iter().count() < max_vehicles
+    }
+}
+
 fn spawn_vehicle_on_timer(
     mut request: EventWriter<RequestVehicleSpawn>,
     time: Res<Time>,
     mut spawn_timer: ResMut<SpawnTimer>,
-    building_query: Query<(), With<Building>>,
-    vehicle_query: Query<&Vehicle>,
+    cap: VehicleCap,
 ) {
-    spawn_timer.timer.tick(time.delta());
-    if spawn_timer.timer.just_finished() {
-        let num_buildings = building_query.iter().count();
-        let max_vehicles = num_buildings / BUILDINGS_PER_VEHICLE;
-        let num_vehicles = vehicle_query.iter().count();
+    let interval = Duration::from_secs_f32(1.0 / cap.config.spawns_per_second.max(f32::EPSILON));
+    if spawn_timer.timer.duration() != interval {
+        spawn_timer.timer.set_duration(interval);
+    }
 
-        if num_vehicles < max_vehicles {
-            request.send(RequestVehicleSpawn);
-        }
+    spawn_timer.timer.tick(time.delta());
+    if spawn_timer.timer.just_finished() && cap.has_room() {
+        request.send(RequestVehicleSpawn);
     }
 }
 
+// What decides which kind of vehicle the next trip uses and where it goes.
+#[derive(SystemParam)]
+struct TripDemand<'w> {
+    kind_settings: Res<'w, VehicleKindSettings>,
+    bus_route: Res<'w, BusRoute>,
+    commutes: ResMut<'w, Commutes>,
+}
+
 fn spawn_vehicle(
-    mut building_query: Query<(Entity, &mut Building)>,
-    mut segment_query: Query<(Entity, &mut RoadSegment)>,
-    mut inter_query: Query<(Entity, &mut Intersection)>,
-    mut commands: Commands,
+    mut trips: TripSpawner,
     mut request: EventReader<RequestVehicleSpawn>,
-    models: Res<Models>,
+    mut sim_rng: ResMut<SimRng>,
+    mut demand: TripDemand,
+    mut cache: ResMut<PathCache>,
+    time: Res<Time>,
+    mut failed: EventWriter<OnTripFailed>,
 ) {
+    let now = time.elapsed_seconds();
+    let hour = trips.time_of_day.hour;
+
     for _ in request.read() {
-        let mut rng = rand::thread_rng();
-        let mut choose = building_query.iter().choose_multiple(&mut rng, 2);
-        choose.shuffle(&mut rng);
+        let rng = sim_rng.rng();
+        let mut kind = match WeightedIndex::new(demand.kind_settings.weights.map(|weight| weight.max(0.0))) {
+            Ok(distribution) => VehicleKind::ALL[distribution.sample(rng)],
+            Err(_) => VehicleKind::Car,
+        };
+
+        if kind == VehicleKind::Bus {
+            if let Some(path) = bus_path(&trips.network, &demand.bus_route.stops) {
+                trips.spawn(rng, kind, path);
+                continue;
+            }
 
-        if choose.len() < 2 {
-            println!("not enough buildings to make a path");
-            return;
+            kind = VehicleKind::Car;
         }
 
-        let start_entity = choose[0].0;
-        let end_entity = choose[1].0;
+        // Commuters heading to or from work come first, random errands fill in the rest of the day.
+        if let Some((from, to)) = demand.commutes.next_trip(hour, rng) {
+            if let Some(path) = cache.trip(&trips.network, from, to, now) {
+                trips.spawn(rng, kind, path);
+                continue;
+            }
 
-        let mut frontier = Vec::<Entity>::new();
-        let mut visited = HashSet::<Entity>::new();
-        let mut parent_map = HashMap::<Entity, Entity>::new();
+            failed.send(OnTripFailed);
+        }
 
-        frontier.push(start_entity);
+        let candidates: Vec<(Entity, BuildingKind, f32)> =
+            trips.network.buildings().map(|(entity, building)| (entity, building.kind, building.spawn_multiplier)).collect();
 
-        let mut path_found = false;
+        if candidates.len() < 2 {
+            println!("not enough buildings to make a path");
+            return;
+        }
 
-        while let Some(curr) = frontier.pop() {
-            visited.insert(curr);
-            // if curr is destination
-            if let Ok((e, dest)) = building_query.get(curr) {
-                if e == end_entity {
-                    path_found = true;
-                    break;
-                }
+        let weights: Vec<(f32, f32)> = candidates
+            .iter()
+            .map(|&(_, kind, multiplier)| {
+                let (origin, destination) = kind.trip_weights(hour);
+                (origin * multiplier, destination)
+            })
+            .collect();
+        let Ok(origins) = WeightedIndex::new(weights.iter().map(|&(origin, _)| origin)) else {
+            continue;
+        };
+        let start_index = origins.sample(rng);
+        let destination_weights = weights
+            .iter()
+            .enumerate()
+            .map(|(index, &(_, destination))| if index == start_index { 0.0 } else { destination });
+        let Ok(destinations) = WeightedIndex::new(destination_weights) else {
+            continue;
+        };
+
+        let start_entity = candidates[start_index].0;
+        let end_entity = candidates[destinations.sample(rng)].0;
+
+        let Some(path) = cache.trip(&trips.network, start_entity, end_entity, now) else {
+            failed.send(OnTripFailed);
+            continue;
+        };
+
+        trips.spawn(rng, kind, path);
+    }
+}
 
-                if !dest.roads.is_empty() {
-                    let start_road = dest.roads.iter().take(1).next().unwrap();
-                    frontier.push(*start_road);
-                    parent_map.insert(*start_road, curr);
-                }
-            }
-            // if curr is edge
-            else if let Ok((_e, edge)) = segment_query.get(curr) {
-                // if end goal is a destination here, go to it
-                if edge.dests.contains(&end_entity) {
-                    frontier.push(end_entity);
-                    parent_map.insert(end_entity, curr);
-                }
-                // Add endpoints of this edge
-                else {
-                    let mut choices = [0, 1];
-                    choices.shuffle(&mut rng);
-                    if let Some(endpoint0) = edge.ends[choices[0]] {
-                        if let Ok((en0, _n0)) = inter_query.get(endpoint0) {
-                            if !visited.contains(&en0) {
-                                frontier.push(en0);
-                                parent_map.insert(en0, curr);
-                            }
-                        }
-                    }
-                    if let Some(endpoint1) = edge.ends[choices[1]] {
-                        if let Ok((en1, _n1)) = inter_query.get(endpoint1) {
-                            if !visited.contains(&en1) {
-                                frontier.push(en1);
-                                parent_map.insert(en1, curr);
-                            }
-                        }
-                    }
-                }
-            }
-            // if curr is a node, add connected edges
-            else if let Ok((_e, node)) = inter_query.get(curr) {
-                let mut choices = node.roads.clone();
-                choices.shuffle(&mut rng);
-
-                for slot in &choices {
-                    if let Some(road) = slot {
-                        if !visited.contains(road) {
-                            frontier.push(*road);
-                            parent_map.insert(*road, curr);
-                        }
-                    }
-                }
-            }
-        }
+// Chains the legs between consecutive stops into one trip that ends back at the first stop.
+pub fn bus_path(network: &RoadNetwork, stops: &[Entity]) -> Option<Vec<Entity>> {
+    if stops.len() < 2 {
+        return None;
+    }
 
-        if path_found {
-            let mut path = Vec::<Entity>::new();
-            let mut curr = end_entity;
+    let mut path = vec![stops[0]];
+    for (&from, &to) in stops.iter().zip(stops.iter().cycle().skip(1)) {
+        path.extend(network.path(from, to)?.into_iter().skip(1));
+    }
 
-            while curr != start_entity {
-                path.push(curr);
-                curr = parent_map[&curr];
-            }
+    Some(path)
+}
 
-            path.push(start_entity);
-            path.reverse();
+// Everything a system needs to start trips with `TripSpawner::spawn`.
+#[derive(SystemParam)]
+pub struct TripSpawner<'w, 's> {
+    pub commands: Commands<'w, 's>,
+    pub network: RoadNetwork<'w, 's>,
+    pub time_of_day: Res<'w, TimeOfDay>,
+    models: Res<'w, Models>,
+    spawn_config: Res<'w, VehicleSpawnConfig>,
+}
+
+impl TripSpawner<'_, '_> {
+    pub fn spawn(&mut self, rng: &mut impl Rng, kind: VehicleKind, path: Vec<Entity>) -> Option<Entity> {
+        let network = &self.network;
+        let (Some(start), Some(end)) = (network.building(path[0]), path.last().and_then(|&end| network.building(end)))
+        else {
+            return None;
+        };
+
+        let spawn_config = &self.spawn_config;
+        let start_location =
+            path.get(1).map_or(start.pos(), |&road| start.entrance(road)).with_y(ROAD_HEIGHT + (VEHICLE_HEIGHT));
+        let max_speed =
+            VEHICLE_MAX_SPEED + rng.gen_range(1.0 - spawn_config.speed_variation..=1.0 + spawn_config.speed_variation);
+
+        let purpose = TripPurpose::of(end);
+        let model_index = self.models.choose_vehicle(kind, purpose, self.time_of_day.hour, rng);
+        let model = &self.models.vehicle_models[model_index];
+        let transform = Transform::from_translation(start_location.with_y(start_location.y + model.vertical_offset))
+            .with_scale(vehicle_scale(model, kind));
+        let towing = kind == VehicleKind::Truck && rng.gen_bool(TRAILER_CHANCE);
+        let spawn = spawn_vehicle_entity(
+            &mut self.commands,
+            &self.models,
+            model,
+            Vehicle::new(
+                path.clone(),
+                max_speed * kind.speed_factor(),
+                model_index,
+                kind,
+                choose_driver(&spawn_config.driver_mix, rng),
+            ),
+            transform,
+            towing,
+        );
+
+        observe_path(&mut self.commands, spawn, path);
+        Some(spawn)
+    }
+}
 
-            let start_location = building_query.get(path[0]).unwrap().1.pos().with_y(ROAD_HEIGHT + (VEHICLE_HEIGHT));
-            let max_speed =
-                VEHICLE_MAX_SPEED + rand::thread_rng().gen_range(1.0 - MAX_SPEED_VARIATION..1.0 + MAX_SPEED_VARIATION);
+fn vehicle_scale(model: &VehicleModelData, kind: VehicleKind) -> Vec3 {
+    (Vec3::ONE * model.scale).with_z(model.scale * kind.length())
+}
 
-            let model = &models.vehicle_models.choose(&mut rng).unwrap();
-            let spawn = commands
-                .spawn((
+fn spawn_vehicle_entity(
+    commands: &mut Commands,
+    models: &Models,
+    model: &VehicleModelData,
+    mut vehicle: Vehicle,
+    transform: Transform,
+    towing: bool,
+) -> Entity {
+    vehicle.trailer = towing.then(|| commands.spawn_empty().id());
+    let (trailer, length) = (vehicle.trailer, vehicle.length);
+
+    let spawn = commands
+        .spawn((
+            PbrBundle {
+                mesh: model.mesh.clone(),
+                material: model.material.clone(),
+                transform,
+                ..default()
+            },
+            vehicle,
+        ))
+        .with_children(|builder| {
+            for (side, offset) in INDICATOR_OFFSETS {
+                builder.spawn((
                     PbrBundle {
-                        mesh: model.mesh.clone(),
-                        material: model.material.clone(),
-                        transform: Transform::from_translation(
-                            start_location.with_y(start_location.y + model.vertical_offset),
-                        )
-                        .with_scale(Vec3::ONE * model.scale),
+                        mesh: models.indicator_mesh.clone(),
+                        material: models.indicator_off_material.clone(),
+                        transform: Transform::from_translation(offset),
                         ..default()
                     },
-                    Vehicle::new(path.clone(), max_speed),
-                    RaycastMesh::<VehicleRaycastSet>::default(),
-                    RaycastSource::<VehicleRaycastSet>::new_transform(Mat4::from_translation(Vec3::new(0.0, 0.0, 10.0))),
-                ))
-                .with_children(|builder| {
-                    builder.spawn(SpotLightBundle { ..Default::default() });
-                })
-                .id();
-
-            for step in path {
-                if let Ok((_, mut building)) = building_query.get_mut(step) {
-                    building.observers.insert(spawn);
-                } else if let Ok((_, mut segment)) = segment_query.get_mut(step) {
-                    segment.observers.insert(spawn);
-                } else if let Ok((_, mut inter)) = inter_query.get_mut(step) {
-                    inter.observers.insert(spawn);
-                }
+                    IndicatorLamp(side),
+                ));
+            }
+        })
+        .id();
+
+    if let Some(trailer) = trailer {
+        spawn_trailer(commands, models, trailer, spawn, length, &transform);
+    }
+
+    spawn
+}
+
+// Deferred so spawners only need the read-only road network to plan routes.
+pub fn observe_path(commands: &mut Commands, spawn: Entity, path: Vec<Entity>) {
+    commands.add(move |world: &mut World| {
+        for step in path {
+            if let Some(mut building) = world.get_mut::<Building>(step) {
+                building.observers.insert(spawn);
+            } else if let Some(mut segment) = world.get_mut::<RoadSegment>(step) {
+                segment.observers.insert(spawn);
+            } else if let Some(mut inter) = world.get_mut::<Intersection>(step) {
+                inter.observers.insert(spawn);
             }
         }
+    });
+}
+
+fn restore_vehicles(mut commands: Commands, mut request: EventReader<RequestVehicleRestore>, models: Res<Models>) {
+    for restore in request.read() {
+        // Models that are no longer in the manifest fall back to the last one.
+        let Some(model) = models.vehicle_models.get(restore.model).or_else(|| models.vehicle_models.last()) else {
+            continue;
+        };
+        let model_index = restore.model.min(models.vehicle_models.len() - 1);
+        let transform = Transform::from_translation(restore.translation)
+            .with_rotation(restore.rotation)
+            .with_scale(vehicle_scale(model, restore.kind));
+
+        let mut vehicle = Vehicle::new(
+            restore.path.clone(),
+            restore.speed_multiplier,
+            model_index,
+            restore.kind,
+            restore.driver,
+        );
+        vehicle.path_index = restore.path_index;
+        vehicle.speed = restore.speed;
+        vehicle.lane = restore.lane;
+        vehicle.trip_time = restore.trip_time;
+        vehicle.trip_distance = restore.trip_distance;
+
+        let spawn = spawn_vehicle_entity(&mut commands, &models, model, vehicle, transform, restore.trailer);
+        observe_path(&mut commands, spawn, restore.path.clone());
     }
 }
 
-fn handle_building_destroyed(
-    mut event: EventReader<OnBuildingDestroyed>,
-    building_query: Query<&Building>,
+// Vehicles whose remaining route runs over something that was just removed keep their route up to
+// the last step still standing and plan the rest again. Only vehicles that can no longer reach
+// their next stop, or that have no road left under them, are despawned.
+fn reroute_vehicles(
     mut commands: Commands,
+    mut vehicle_query: Query<(&mut Vehicle, &Transform)>,
+    network: RoadNetwork,
+    mut building_destroyed: EventReader<OnBuildingDestroyed>,
+    mut road_destroyed: EventReader<OnRoadDestroyed>,
+    mut intersection_destroyed: EventReader<OnIntersectionDestroyed>,
+    mut failed: EventWriter<OnTripFailed>,
 ) {
-    for &OnBuildingDestroyed(ent) in event.read() {
-        if let Ok(building) = building_query.get(ent) {
-            for observer in &building.observers {
-                if let Some(vehicle_ref) = commands.get_entity(*observer) {
-                    vehicle_ref.despawn_recursive();
-                }
+    let destroyed: HashSet<Entity> = building_destroyed
+        .read()
+        .map(|event| event.0)
+        .chain(road_destroyed.read().map(|event| event.0))
+        .chain(intersection_destroyed.read().map(|event| event.0))
+        .collect();
+
+    let mut affected: Vec<Entity> = destroyed
+        .iter()
+        .flat_map(|&entity| {
+            let observers = match (
+                network.building(entity),
+                network.segment(entity),
+                network.intersection(entity),
+            ) {
+                (Some(building), _, _) => Some(&building.observers),
+                (_, Some(segment), _) => Some(&segment.observers),
+                (_, _, Some(intersection)) => Some(&intersection.observers),
+                _ => None,
+            };
+            observers.into_iter().flatten().copied()
+        })
+        .collect();
+    affected.sort();
+    affected.dedup();
+
+    for entity in affected {
+        let Ok((mut vehicle, transform)) = vehicle_query.get_mut(entity) else {
+            continue;
+        };
+
+        if !vehicle.path.iter().skip(vehicle.path_index).any(|step| destroyed.contains(step)) {
+            continue;
+        }
+
+        match replan_route(&vehicle, transform.translation, &destroyed, &network) {
+            Some(path) => {
+                observe_path(&mut commands, entity, path.clone());
+                vehicle.path = path;
+            }
+            None => {
+                commands.entity(entity).despawn_recursive();
+                failed.send(OnTripFailed);
             }
         }
     }
 }
 
-fn handle_road_segment_destroyed(
-    mut event: EventReader<OnRoadDestroyed>,
-    segment_query: Query<&RoadSegment>,
+// Replans from the step before the first removed one to the next stop along the route, then
+// continues with the rest of the original route from there.
+fn replan_route(vehicle: &Vehicle, pos: Vec3, destroyed: &HashSet<Entity>, network: &RoadNetwork) -> Option<Vec<Entity>> {
+    let broken =
+        vehicle.path.iter().skip(vehicle.path_index).position(|step| destroyed.contains(step))? + vehicle.path_index;
+    let stop = (broken..vehicle.path.len()).find(|&index| network.building(vehicle.path[index]).is_some())?;
+
+    if destroyed.contains(&vehicle.path[stop]) {
+        return None;
+    }
+
+    let (keep, from) = match broken > vehicle.path_index {
+        true => (broken - 1, vehicle.path[broken - 1]),
+        false => {
+            let (road, _) = network
+                .segments()
+                .find(|(road, segment)| !destroyed.contains(road) && segment.area.contains_point_3d(pos))?;
+            (broken, road)
+        }
+    };
+
+    let detour = network.path(from, vehicle.path[stop])?;
+    Some(vehicle.path[..keep].iter().copied().chain(detour).chain(vehicle.path[stop + 1..].iter().copied()).collect())
+}
+
+// Vehicles with a badly backed up road a few steps ahead plan again from the road they are on to
+// their next stop. Spillback weighs on the route costs, so the new plan steers around the queue when
+// there is a reasonable way to. Checked every few seconds, for a limited number of vehicles at a time.
+fn reroute_around_spillback(
     mut commands: Commands,
+    mut vehicle_query: Query<(Entity, &mut Vehicle), Without<Dormant>>,
+    network: RoadNetwork,
+    settings: Res<IntersectionCapacitySettings>,
+    time: Res<Time>,
+    mut since_check: Local<f32>,
 ) {
-    for &OnRoadDestroyed(ent) in event.read() {
-        if let Ok(segment) = segment_query.get(ent) {
-            for observer in &segment.observers {
-                if let Some(vehicle_ref) = commands.get_entity(*observer) {
-                    vehicle_ref.despawn_recursive();
-                }
-            }
+    *since_check += time.delta_seconds();
+    if *since_check < SPILLBACK_CHECK_SECONDS {
+        return;
+    }
+    *since_check = 0.0;
+
+    let mut rerouted = 0;
+    for (entity, mut vehicle) in &mut vehicle_query {
+        if rerouted == MAX_SPILLBACK_REROUTES {
+            break;
+        }
+
+        let curr = vehicle.path[vehicle.path_index];
+        if vehicle.crashed || vehicle.parked.is_some() || network.segment(curr).is_none() {
+            continue;
+        }
+
+        let backed_up = vehicle
+            .path
+            .iter()
+            .skip(vehicle.path_index + 1)
+            .take(SPILLBACK_LOOKAHEAD)
+            .filter_map(|&step| network.segment(step))
+            .any(|segment| segment.spillback >= settings.reroute_spillback);
+        if !backed_up {
+            continue;
+        }
+
+        let Some(stop) =
+            (vehicle.path_index + 1..vehicle.path.len()).find(|&index| network.building(vehicle.path[index]).is_some())
+        else {
+            continue;
+        };
+        let Some(detour) = network.path(curr, vehicle.path[stop]) else {
+            continue;
+        };
+
+        let path: Vec<Entity> = vehicle.path[..vehicle.path_index]
+            .iter()
+            .copied()
+            .chain(detour)
+            .chain(vehicle.path[stop + 1..].iter().copied())
+            .collect();
+        if path != vehicle.path {
+            observe_path(&mut commands, entity, path.clone());
+            vehicle.path = path;
+            rerouted += 1;
         }
     }
 }
 
-fn handle_intersection_destroyed(
-    mut event: EventReader<OnIntersectionDestroyed>,
-    inter_query: Query<&Intersection>,
-    mut commands: Commands,
+fn plan_bus_route(mut route: ResMut<BusRoute>, network: RoadNetwork, mut sim_rng: ResMut<SimRng>) {
+    if route.stops.len() >= 2 && route.stops.iter().all(|&stop| network.building(stop).is_some()) {
+        return;
+    }
+
+    let mut stops: Vec<Entity> =
+        network.buildings().filter(|(_, building)| building.zone() == Zone::Commercial).map(|(entity, _)| entity).collect();
+
+    if stops.len() < 2 {
+        route.stops.clear();
+        return;
+    }
+
+    stops.sort();
+    stops.shuffle(sim_rng.rng());
+    stops.truncate(BUS_ROUTE_STOPS);
+    route.stops = stops;
+}
+
+fn blink_indicators(
+    mut lamp_query: Query<(&Parent, &IndicatorLamp, &mut Handle<StandardMaterial>)>,
+    vehicle_query: Query<&Vehicle>,
+    models: Res<Models>,
+    time: Res<Time>,
 ) {
-    for &OnIntersectionDestroyed(ent) in event.read() {
-        if let Ok(inter) = inter_query.get(ent) {
-            for observer in &inter.observers {
-                if let Some(vehicle_ref) = commands.get_entity(*observer) {
-                    vehicle_ref.despawn_recursive();
-                }
-            }
+    let flash_on = (time.elapsed_seconds() * INDICATOR_HZ).fract() < 0.5;
+
+    for (parent, &IndicatorLamp(side), mut material) in &mut lamp_query {
+        // Emergency vehicles run with hazards on so they stand out in traffic.
+        let lit = flash_on
+            && vehicle_query
+                .get(parent.get())
+                .is_ok_and(|vehicle| vehicle.indicator == Some(side) || vehicle.kind == VehicleKind::Emergency);
+        let target = if lit {
+            &models.indicator_on_material
+        } else {
+            &models.indicator_off_material
+        };
+
+        if *material != *target {
+            *material = target.clone();
         }
     }
 }
@@ -0,0 +1,150 @@
+use crate::{
+    graph::road_network::RoadNetwork,
+    graphics::camera::PlayerCameraController,
+    schedule::UpdateStage,
+    types::vehicle::{update_vehicles, Vehicle},
+};
+use bevy::prelude::*;
+
+// Vehicles wake this much closer than they fall asleep, so ones right on the edge do not flicker.
+const WAKE_MARGIN: f32 = 10.0;
+// Dormant vehicles drive at this share of their cruising speed, standing in for the time the full
+// simulation would spend at junctions and behind other traffic.
+const DORMANT_PACE: f32 = 0.7;
+
+pub struct VehicleLodPlugin;
+
+impl Plugin for VehicleLodPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(SimulationLodSettings::default()).add_systems(
+            Update,
+            (
+                advance_dormant_vehicles.before(update_vehicles).in_set(UpdateStage::AiBehavior),
+                update_simulation_lod.in_set(UpdateStage::UpdatePathing),
+            ),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct SimulationLodSettings {
+    pub enabled: bool,
+    pub radius: f32,
+}
+
+impl Default for SimulationLodSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            radius: 150.0,
+        }
+    }
+}
+
+// A vehicle far from the camera, left out of steering, following and lane keeping. It moves from one
+// step of its path to the next once it has had time to cover the distance, and is hidden meanwhile.
+#[derive(Component, Debug, Default)]
+pub struct Dormant {
+    travelled: f32,
+}
+
+fn advance_dormant_vehicles(
+    mut vehicle_query: Query<(&mut Vehicle, &mut Dormant, &mut Transform)>,
+    network: RoadNetwork,
+    time: Res<Time>,
+) {
+    for (mut vehicle, mut dormant, mut transform) in &mut vehicle_query {
+        if vehicle.crashed || vehicle.parked.is_some() || vehicle.path_index + 1 >= vehicle.path.len() {
+            continue;
+        }
+
+        let Some(next) = network.position(vehicle.path[vehicle.path_index + 1]) else {
+            continue;
+        };
+
+        let cruising = network
+            .segment(vehicle.path[vehicle.path_index])
+            .map_or(vehicle.speed_multiplier, |segment| vehicle.cruising_speed(segment));
+        vehicle.speed = cruising * DORMANT_PACE;
+
+        let distance = vehicle.speed * time.delta_seconds();
+        vehicle.trip_time += time.delta_seconds();
+        vehicle.trip_distance += distance;
+        dormant.travelled += distance;
+
+        let leg = transform.translation.with_y(0.0).distance(next.with_y(0.0));
+        if dormant.travelled >= leg {
+            dormant.travelled -= leg;
+            vehicle.path_index += 1;
+            transform.translation = next.with_y(transform.translation.y);
+        }
+    }
+}
+
+// Vehicles out past the radius around the camera's focus fall asleep. They wake on the next road they
+// reach once the camera comes back, placed on it facing their next step so the full simulation picks
+// them up from there. Without a camera everything stays awake.
+fn update_simulation_lod(
+    mut commands: Commands,
+    settings: Res<SimulationLodSettings>,
+    camera_query: Query<&PlayerCameraController>,
+    mut vehicle_query: Query<(Entity, &mut Vehicle, &mut Transform, Has<Dormant>)>,
+    network: RoadNetwork,
+) {
+    let Ok(controller) = camera_query.get_single() else {
+        return;
+    };
+
+    let focus = controller.ground_focus().with_y(0.0);
+
+    for (entity, mut vehicle, mut transform, dormant) in &mut vehicle_query {
+        let distance = transform.translation.with_y(0.0).distance(focus);
+        let visibility = |visible: bool| match visible {
+            true => Visibility::Inherited,
+            false => Visibility::Hidden,
+        };
+
+        if !dormant {
+            let far = distance > settings.radius && controller.following != Some(entity);
+            if settings.enabled && far && !vehicle.crashed && vehicle.parked.is_none() {
+                commands.entity(entity).insert((Dormant::default(), visibility(false)));
+                if let Some(trailer) = vehicle.trailer {
+                    commands.entity(trailer).insert(visibility(false));
+                }
+            }
+            continue;
+        }
+
+        // Trips are finished by the full simulation, however far away they end.
+        let near = distance < settings.radius - WAKE_MARGIN;
+        let at_end = vehicle.path_index + 1 >= vehicle.path.len();
+        if settings.enabled && !near && !at_end {
+            continue;
+        }
+
+        let segment = network.segment(vehicle.path[vehicle.path_index]);
+        if segment.is_none() && !at_end {
+            continue;
+        }
+
+        if let Some(segment) = segment {
+            transform.translation = segment.get_lane_pos(transform.translation).with_y(transform.translation.y);
+        }
+
+        if let Some(next) = vehicle.path.get(vehicle.path_index + 1).and_then(|&next| network.position(next)) {
+            if let Some(heading) = (next - transform.translation).with_y(0.0).try_normalize() {
+                transform.look_to(heading, Vec3::Y);
+            }
+        }
+
+        vehicle.lane = 0;
+        vehicle.lane_change_from = None;
+        vehicle.follow = transform.translation;
+        vehicle.checkpoint = transform.translation;
+
+        commands.entity(entity).remove::<Dormant>().insert(visibility(true));
+        if let Some(trailer) = vehicle.trailer {
+            commands.entity(trailer).insert(visibility(true));
+        }
+    }
+}
@@ -0,0 +1,180 @@
+use crate::{
+    graph::road_network::RoadNetwork,
+    schedule::UpdateStage,
+    types::{
+        vehicle::{observe_path, Vehicle},
+        vehicle_lod::Dormant,
+    },
+};
+use bevy::{
+    prelude::*,
+    utils::{HashMap, HashSet},
+};
+
+pub struct VehicleWatchdogPlugin;
+
+impl Plugin for VehicleWatchdogPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<OnVehicleUnstuck>()
+            .insert_resource(WatchdogSettings::default())
+            .insert_resource(VehicleWatchdog::default())
+            .add_systems(
+                Update,
+                watch_stuck_vehicles
+                    .in_set(UpdateStage::UpdatePathing)
+                    .run_if(|settings: Res<WatchdogSettings>| settings.enabled),
+            );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct WatchdogSettings {
+    pub enabled: bool,
+    pub min_progress: f32,
+    pub stuck_seconds: f32,
+}
+
+impl Default for WatchdogSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            min_progress: 0.25,
+            stuck_seconds: 45.0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StuckResolution {
+    Rerouted,
+    Teleported,
+    Despawned,
+}
+
+#[derive(Event, Debug)]
+pub struct OnVehicleUnstuck {
+    pub entity: Entity,
+    pub resolution: StuckResolution,
+    pub stuck_seconds: f32,
+}
+
+#[derive(Debug)]
+struct Progress {
+    anchor: Vec3,
+    still_seconds: f32,
+    attempts: u32,
+}
+
+// How far each vehicle has got since it last made progress, and how often each remedy was needed.
+#[derive(Resource, Debug, Default)]
+pub struct VehicleWatchdog {
+    progress: HashMap<Entity, Progress>,
+    pub rerouted: u32,
+    pub teleported: u32,
+    pub despawned: u32,
+}
+
+impl VehicleWatchdog {
+    fn count(&mut self, resolution: StuckResolution) {
+        match resolution {
+            StuckResolution::Rerouted => self.rerouted += 1,
+            StuckResolution::Teleported => self.teleported += 1,
+            StuckResolution::Despawned => self.despawned += 1,
+        }
+    }
+}
+
+// A vehicle that has not moved far enough for `stuck_seconds` is first sent another way to its next
+// stop, then, if still stuck, moved on to its next checkpoint, and finally removed. Each remedy that
+// does not apply, like a reroute that finds the same route, is skipped in favour of the next one.
+// Parked and crashed vehicles are left alone, since they stand still on purpose.
+fn watch_stuck_vehicles(
+    mut commands: Commands,
+    mut watchdog: ResMut<VehicleWatchdog>,
+    settings: Res<WatchdogSettings>,
+    mut vehicle_query: Query<(Entity, &mut Vehicle, &mut Transform), Without<Dormant>>,
+    network: RoadNetwork,
+    time: Res<Time>,
+    mut unstuck: EventWriter<OnVehicleUnstuck>,
+) {
+    let mut present = HashSet::new();
+
+    for (entity, mut vehicle, mut transform) in &mut vehicle_query {
+        if vehicle.parked.is_some() || vehicle.crashed {
+            continue;
+        }
+
+        present.insert(entity);
+        let position = transform.translation;
+        let progress = watchdog.progress.entry(entity).or_insert(Progress {
+            anchor: position,
+            still_seconds: 0.0,
+            attempts: 0,
+        });
+
+        if progress.anchor.distance(position) > settings.min_progress {
+            *progress = Progress {
+                anchor: position,
+                still_seconds: 0.0,
+                attempts: 0,
+            };
+            continue;
+        }
+
+        progress.still_seconds += time.delta_seconds();
+        if progress.still_seconds < settings.stuck_seconds * (progress.attempts + 1) as f32 {
+            continue;
+        }
+
+        let stuck_seconds = progress.still_seconds;
+        let mut resolution = None;
+
+        if progress.attempts == 0 {
+            progress.attempts += 1;
+            if let Some(path) = reroute(&vehicle, &network) {
+                observe_path(&mut commands, entity, path.clone());
+                vehicle.path = path;
+                resolution = Some(StuckResolution::Rerouted);
+            }
+        }
+
+        if resolution.is_none() && progress.attempts == 1 {
+            progress.attempts += 1;
+            if vehicle.checkpoint.distance(position) > settings.min_progress {
+                transform.translation = vehicle.checkpoint.with_y(position.y);
+                progress.anchor = transform.translation;
+                resolution = Some(StuckResolution::Teleported);
+            }
+        }
+
+        let resolution = resolution.unwrap_or_else(|| {
+            commands.entity(entity).despawn_recursive();
+            StuckResolution::Despawned
+        });
+
+        watchdog.count(resolution);
+        unstuck.send(OnVehicleUnstuck {
+            entity,
+            resolution,
+            stuck_seconds,
+        });
+    }
+
+    watchdog.progress.retain(|entity, _| present.contains(entity));
+}
+
+// Plans again from the current step to the next stop along the route. Gives up when that finds
+// the route the vehicle already has.
+fn reroute(vehicle: &Vehicle, network: &RoadNetwork) -> Option<Vec<Entity>> {
+    let stop =
+        (vehicle.path_index + 1..vehicle.path.len()).find(|&index| network.building(vehicle.path[index]).is_some())?;
+    let detour = network.path(vehicle.path[vehicle.path_index], vehicle.path[stop])?;
+    let path: Vec<Entity> = vehicle.path[..vehicle.path_index]
+        .iter()
+        .copied()
+        .chain(detour)
+        .chain(vehicle.path[stop + 1..].iter().copied())
+        .collect();
+
+    (path != vehicle.path).then_some(path)
+}
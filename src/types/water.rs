@@ -0,0 +1,17 @@
+use crate::grid::grid_area::*;
+use bevy::prelude::*;
+
+#[derive(Component, Debug)]
+pub struct WaterBody {
+    pub area: GridArea,
+}
+
+impl WaterBody {
+    pub fn new(area: GridArea) -> Self {
+        Self { area }
+    }
+
+    pub fn area(&self) -> GridArea {
+        self.area
+    }
+}
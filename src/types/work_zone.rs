@@ -0,0 +1,134 @@
+use crate::{
+    graphics::models::Models, grid::terrain::Terrain, schedule::UpdateStage, tools::road_tool::ROAD_HEIGHT,
+    types::road_segment::RoadSegment,
+};
+use bevy::prelude::*;
+
+const DEFAULT_SECONDS_PER_CELL: f32 = 1.0;
+const PROP_HEIGHT: f32 = 0.15;
+const PROGRESS_Y: f32 = 0.6;
+const PROGRESS_COLOR: Color = Color::linear_rgb(1.0, 0.6, 0.0);
+const REMAINING_COLOR: Color = Color::linear_rgb(0.3, 0.3, 0.3);
+
+pub struct WorkZonePlugin;
+
+impl Plugin for WorkZonePlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(ConstructionSettings::default()).add_systems(
+            Update,
+            (
+                spawn_work_zone_props.in_set(UpdateStage::AfterSpawning),
+                advance_work_zones.in_set(UpdateStage::HighLevelSideEffects),
+                visualize_work_zones.in_set(UpdateStage::Visualize),
+            ),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct ConstructionSettings {
+    pub enabled: bool,
+    pub seconds_per_cell: f32,
+}
+
+impl Default for ConstructionSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            seconds_per_cell: DEFAULT_SECONDS_PER_CELL,
+        }
+    }
+}
+
+#[derive(Component, Debug)]
+pub struct WorkZone {
+    pub elapsed: f32,
+    pub duration: f32,
+}
+
+impl WorkZone {
+    pub fn new(segment: &RoadSegment, settings: &ConstructionSettings) -> Self {
+        Self {
+            elapsed: 0.0,
+            duration: segment.cells().len() as f32 * settings.seconds_per_cell,
+        }
+    }
+
+    pub fn progress(&self) -> f32 {
+        (self.elapsed / self.duration.max(f32::EPSILON)).min(1.0)
+    }
+}
+
+#[derive(Component, Debug)]
+struct WorkZoneProp;
+
+fn spawn_work_zone_props(
+    mut commands: Commands,
+    zone_query: Query<(Entity, &RoadSegment, &Transform), Added<WorkZone>>,
+    models: Res<Models>,
+    terrain: Res<Terrain>,
+) {
+    for (entity, segment, transform) in &zone_query {
+        let inverse = transform.compute_affine().inverse();
+
+        commands.entity(entity).with_children(|parent| {
+            for (point, lateral) in segment.centerline_samples().into_iter().step_by(4) {
+                for side in [point + lateral, point - lateral] {
+                    parent.spawn((
+                        PbrBundle {
+                            mesh: models.cone_mesh.clone(),
+                            material: models.cone_material.clone(),
+                            transform: Transform::from_translation(
+                                inverse.transform_point3(
+                                    side.with_y(terrain.road_height(side) + ROAD_HEIGHT + PROP_HEIGHT / 2.0),
+                                ),
+                            )
+                            .with_rotation(transform.rotation.inverse()),
+                            ..default()
+                        },
+                        WorkZoneProp,
+                    ));
+                }
+            }
+        });
+    }
+}
+
+fn advance_work_zones(
+    mut commands: Commands,
+    mut zone_query: Query<(Entity, &mut WorkZone, &mut RoadSegment, Option<&Children>)>,
+    prop_query: Query<(), With<WorkZoneProp>>,
+    time: Res<Time>,
+) {
+    for (entity, mut zone, mut segment, children) in &mut zone_query {
+        zone.elapsed += time.delta_seconds();
+
+        if zone.elapsed < zone.duration {
+            continue;
+        }
+
+        segment.closed = false;
+        commands.entity(entity).remove::<WorkZone>();
+
+        for &child in children.into_iter().flatten() {
+            if prop_query.contains(child) {
+                commands.entity(child).despawn_recursive();
+            }
+        }
+    }
+}
+
+fn visualize_work_zones(zone_query: Query<(&RoadSegment, &WorkZone)>, mut gizmos: Gizmos) {
+    for (segment, zone) in &zone_query {
+        let samples = segment.centerline_samples();
+        let (Some(&(start, _)), Some(&(end, _))) = (samples.first(), samples.last()) else {
+            continue;
+        };
+
+        let start = start.with_y(PROGRESS_Y);
+        let end = end.with_y(PROGRESS_Y);
+        let reached = start.lerp(end, zone.progress());
+        gizmos.line(start, reached, PROGRESS_COLOR);
+        gizmos.line(reached, end, REMAINING_COLOR);
+    }
+}
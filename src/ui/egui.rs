@@ -1,25 +1,131 @@
-use bevy::prelude::*;
+use bevy::{ecs::system::SystemParam, prelude::*};
 use bevy_egui::egui::{epaint, Align2};
 use bevy_egui::{egui, EguiContexts, EguiPlugin};
+use std::collections::VecDeque;
 
-use crate::save::save_events::SaveRequest;
+use crate::analytics::{
+    analytics_events::{MetricsFormat, OnMetricsExported, RequestMetricsExport},
+    metrics::MetricsControls,
+    trip_stats::TripStats,
+};
+use crate::audio::audio::{TrafficAudioSettings, MAX_ENGINE_VOICES};
+use crate::capture::{
+    capture::{ui_visible, TimelapseSettings},
+    capture_events::OnScreenshotSaved,
+};
+use crate::determinism::determinism::SimulationSettings;
+use crate::economy::commute::Commutes;
+use crate::economy::demand::ZoneDemand;
+use crate::economy::economy::Funds;
+use crate::economy::economy_events::OnInsufficientFunds;
+use crate::economy::growth::GrowthSettings;
+use crate::export::export_events::{OnCityExported, RequestCityExport};
+use crate::generator::{
+    generator::{NewCitySetup, MAX_BLOCKS, MAX_BLOCK_SIZE, MAX_LANDMARKS, MIN_BLOCKS, MIN_BLOCK_SIZE},
+    generator_events::RequestNewCity,
+};
+use crate::graph::{
+    congestion::{Congestion, CongestionStats},
+    path_cache::PathCache,
+    road_network::RoadNetwork,
+    validator::GraphValidatorSettings,
+};
+use crate::graphics::building_lod::BuildingLodSettings;
+use crate::graphics::camera::{CameraPanSettings, PanMode};
+use crate::graphics::camera_events::FocusOn;
+use crate::graphics::quality::{DisplayMode, GraphicsSettings, QualityPreset, MAX_SHADOW_CASCADES, SHADOW_RESOLUTIONS};
+use crate::graphics::vehicle_instancing::VehicleInstancingSettings;
+use crate::graphics::weather::{DayCycleSettings, HeadlightSettings, TimeOfDay, WeatherSettings, WeatherState};
+use crate::grid::grid_area::GridArea;
+use crate::input::keymap::{Action, Actions, Keymap};
+use crate::profile::profile::{Profile, ACHIEVEMENTS};
+use crate::report::report_events::{OnBugReportWritten, RequestBugReport};
+use crate::save::save::{AutosaveSettings, SaveProgress};
+use crate::save::save_events::{OnGameSaved, SaveRequest};
+use crate::scenario::{
+    scenario::{Scenario, ScenarioLog},
+    scenario_events::OnScenarioCompleted,
+};
+use crate::types::accident::{Accident, AccidentSettings, OnAccident, OnAccidentCleared};
+use crate::types::district::{DistrictStats, Districts};
+use crate::types::work_zone::ConstructionSettings;
+use crate::ui::{
+    notify::MessageLog,
+    notify_events::{Notify, Severity},
+};
 use crate::{
-    schedule::UpdateStage, tools::toolbar::ToolState, tools::toolbar_events::ChangeToolRequest, types::building::*,
-    types::intersection::*, types::road_segment::*, types::vehicle::*,
+    graphics::camera::PlayerCameraController,
+    schedule::UpdateStage,
+    tools::toolbar::ToolState,
+    tools::toolbar_events::ChangeToolRequest,
+    tools::view_tool::{Hovered, SignalOverrideSettings},
+    tools::{
+        blueprint_tool::{BlueprintTool, Blueprints},
+        building_tool::{BuildingTool, MAX_BUILDING_GAP},
+        context_menu::ContextMenu,
+        context_menu_events::RequestDemolish,
+        district_tool::DistrictTool,
+        inspect_events::{RequestOneWay, RequestSpeedLimit, RequestTurnRestriction, RequestVehicleDespawn},
+        road_events::RoadJoin,
+        road_tool::{RoadTool, RoadUpgrader},
+        selection::Selection,
+        transit_tool::{TransitLines, TransitTool},
+        vehicle_debug::VehicleDebug,
+    },
+    types::building::*,
+    types::driver::DRIVER_PROFILES,
+    types::intersection::*,
+    types::landmark::{LandmarkEvents, OnSurgeStarted},
+    types::parking::ParkingLot,
+    types::road_segment::*,
+    types::traffic_signal::*,
+    types::vehicle::*,
+    types::vehicle_lod::{Dormant, SimulationLodSettings},
+    types::watchdog::{OnVehicleUnstuck, StuckResolution, VehicleWatchdog},
 };
 
+const SLOT_NAMES: [&str; 4] = ["North", "South", "West", "East"];
+const TOAST_SECONDS: f32 = 4.0;
+const TOAST_FADE_SECONDS: f32 = 1.0;
+const MAX_TOASTS: usize = 5;
+const UNREACHABLE_COOLDOWN_SECONDS: f32 = 60.0;
+const CONGESTED_ROADS_SHOWN: usize = 10;
+const TRIP_HISTOGRAM_BINS: usize = 12;
+
 pub struct UiPlugin;
 
 impl Plugin for UiPlugin {
     fn build(&self, app: &mut App) {
-        app.add_plugins(EguiPlugin).init_state::<MouseOver>().add_systems(Startup, ui_theme_selection).add_systems(
-            Update,
-            (
-                update_ui_state.in_set(UpdateStage::UpdateView),
-                update_toolbar_window,
-                update_stats_window,
-            ),
-        );
+        app.add_plugins(EguiPlugin)
+            .init_state::<MouseOver>()
+            .add_systems(Startup, ui_theme_selection)
+            .add_systems(
+                Update,
+                (
+                    update_ui_state.in_set(UpdateStage::UpdateView),
+                    update_toolbar_window,
+                    update_stats_window,
+                    update_scenario_window,
+                    update_achievements_window,
+                    update_follow_hud,
+                    update_inspector_window,
+                    update_settings_window,
+                    update_transit_window,
+                    update_blueprint_window,
+                    update_inspect_panel,
+                    update_context_menu,
+                    update_toasts,
+                    update_message_log_window,
+                    update_vehicle_debug_window,
+                    update_new_city_window,
+                    update_simulation_window,
+                    update_save_progress,
+                    update_events_window,
+                    (update_road_join_hint, update_hover_tooltip, update_district_window),
+                )
+                    .run_if(ui_visible),
+            )
+            .add_systems(Update, notify_game_events.in_set(UpdateStage::Analyze));
     }
 }
 
@@ -56,13 +162,53 @@ fn ui_theme_selection(mut contexts: EguiContexts) {
     });
 }
 
+#[derive(SystemParam)]
+pub struct ToolPicker<'w, 's> {
+    change_tool: EventWriter<'w, ChangeToolRequest>,
+    scenario: Res<'w, Scenario>,
+    building_tool_query: Query<'w, 's, &'static mut BuildingTool>,
+    road_tool_query: Query<'w, 's, &'static RoadTool>,
+}
+
+#[derive(SystemParam)]
+pub struct ToolbarSettings<'w> {
+    next_state: ResMut<'w, NextState<VehicleSpawnState>>,
+    state: Res<'w, State<VehicleSpawnState>>,
+    lane_change: ResMut<'w, LaneChangeSettings>,
+    growth: ResMut<'w, GrowthSettings>,
+    new_city: ResMut<'w, NewCitySetup>,
+}
+
+#[derive(SystemParam)]
+pub struct CityDemand<'w> {
+    demand: Res<'w, ZoneDemand>,
+    commutes: Res<'w, Commutes>,
+}
+
 pub fn update_toolbar_window(
     mut contexts: EguiContexts,
-    mut change_tool: EventWriter<ChangeToolRequest>,
+    picker: ToolPicker,
+    settings: ToolbarSettings,
+    city_demand: CityDemand,
     mut save: EventWriter<SaveRequest>,
-    mut next_state: ResMut<NextState<VehicleSpawnState>>,
-    state: Res<State<VehicleSpawnState>>,
+    mut export: EventWriter<RequestCityExport>,
+    mut bake_textures: Local<bool>,
 ) {
+    let ToolPicker {
+        mut change_tool,
+        scenario,
+        mut building_tool_query,
+        road_tool_query,
+    } = picker;
+    let ToolbarSettings {
+        mut next_state,
+        state,
+        mut lane_change,
+        mut growth,
+        mut new_city,
+    } = settings;
+    let CityDemand { demand, commutes } = city_demand;
+
     let Some(ctx) = contexts.try_ctx_mut() else {
         return;
     };
@@ -79,31 +225,160 @@ pub fn update_toolbar_window(
 
             #[cfg(not(target_arch = "wasm32"))]
             if ui.add(egui::Button::new("[ F5 ] Save Game").min_size(tool_button_size)).clicked() {
-                save.send(SaveRequest);
+                save.send(SaveRequest::World);
+            }
+
+            #[cfg(not(target_arch = "wasm32"))]
+            ui.horizontal(|ui| {
+                if ui.add(egui::Button::new("Export City (OBJ)").min_size(tool_button_size)).clicked() {
+                    export.send(RequestCityExport {
+                        bake_textures: *bake_textures,
+                    });
+                }
+                ui.checkbox(&mut bake_textures, "Bake Textures");
+            });
+
+            if ui.add(egui::Button::new("New City").min_size(tool_button_size)).clicked() {
+                new_city.open = true;
             }
 
             ui.add_space(20.0);
 
-            if ui.add(egui::Button::new("[ ` ] View").min_size(tool_button_size)).clicked() {
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::View),
+                    egui::Button::new("[ ` ] View").min_size(tool_button_size),
+                )
+                .clicked()
+            {
                 change_tool.send(ChangeToolRequest(ToolState::View));
             }
 
-            if ui.add(egui::Button::new("[ 1 ] Building").min_size(tool_button_size)).clicked() {
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Building),
+                    egui::Button::new("[ 1 ] Building").min_size(tool_button_size),
+                )
+                .clicked()
+            {
                 change_tool.send(ChangeToolRequest(ToolState::Building));
             }
 
-            if ui.add(egui::Button::new("[ 2 ] Road").min_size(tool_button_size)).clicked() {
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Road),
+                    egui::Button::new("[ 2 ] Road").min_size(tool_button_size),
+                )
+                .clicked()
+            {
                 change_tool.send(ChangeToolRequest(ToolState::Road));
             }
 
-            if ui.add(egui::Button::new("[ 3 ] Bulldozer").min_size(tool_button_size)).clicked() {
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Eraser),
+                    egui::Button::new("[ 3 ] Bulldozer").min_size(tool_button_size),
+                )
+                .clicked()
+            {
                 change_tool.send(ChangeToolRequest(ToolState::Eraser));
             }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Connect),
+                    egui::Button::new("[ 4 ] Connect").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::Connect));
+            }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Transit),
+                    egui::Button::new("[ 5 ] Transit").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::Transit));
+            }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Inspect),
+                    egui::Button::new("[ 6 ] Inspect").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::Inspect));
+            }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Water),
+                    egui::Button::new("[ 7 ] Water").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::Water));
+            }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Blueprint),
+                    egui::Button::new("[ 8 ] Blueprint").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::Blueprint));
+            }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::District),
+                    egui::Button::new("[ 9 ] Districts").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::District));
+            }
+
+            if ui
+                .add_enabled(
+                    !scenario.is_locked(ToolState::Props),
+                    egui::Button::new("[ 0 ] Props").min_size(tool_button_size),
+                )
+                .clicked()
+            {
+                change_tool.send(ChangeToolRequest(ToolState::Props));
+            }
+            let mut building_tool = building_tool_query.single_mut();
+            ui.label(format!(
+                "[Z/X/C/B/J/,/.]: House/Shop/Office/Factory/Parking/Stadium/Mall ({:?})",
+                building_tool.kind
+            ));
+            ui.add(egui::Slider::new(&mut building_tool.gap, 0..=MAX_BUILDING_GAP).text("Building Gap"));
+            ui.label("[Z/X/C]: Tree/Bench/Fountain (Props)");
             ui.label("[TAB]: Rotate Tool");
+            ui.label("[C]: Cycle Road Shape (Bridge spans water)");
             ui.label("[R/F]: Adjust Tool Size");
+            ui.label(format!("[Y]: Cycle Road Surface ({:?})", road_tool_query.single().surface));
+            ui.label("[U]: Resurface District");
+            ui.label("[Shift]: Hold to end a road at a junction instead of merging");
+            ui.label("[ENTER]: Build Suggested Connection");
             ui.label("[H]: Toggle road graph");
             ui.label("[G]: Toggle grid");
             ui.label("[V]: Toggle ai view");
+            ui.label("[O]: Follow nearest vehicle");
+            ui.label("[Ctrl + 1-9]: Store camera bookmark");
+            ui.label("[1-9]: Recall bookmark (View)");
+            ui.add_space(20.0);
+            draw_demand_bars(ui, &demand);
+            ui.label(format!("Population: {:.0} Jobs: {:.0}", demand.population, demand.jobs));
+            ui.label(format!("Trip Success: {:.0}%", demand.trip_success * 100.0));
+            ui.checkbox(&mut growth.enabled, "City Growth");
+            ui.label(format!("Commuters: {}", commutes.commuters()));
             ui.add_space(20.0);
 
             let spawn_text = match state.get() {
@@ -119,6 +394,7 @@ pub fn update_toolbar_window(
                     }
                 });
             }
+            ui.add(egui::Slider::new(&mut lane_change.aggressiveness, 0.0..=1.0).text("Overtaking"));
             ui.add_space(20.0);
             ui.label("[Left Mouse]: Use tool");
             ui.label("[Middle Mouse]: Rotate");
@@ -132,16 +408,97 @@ pub fn update_toolbar_window(
             ui.label("[WASD]: Pan");
             ui.add_space(20.0);
             ui.label("[K/M]: Adjust Sunlight");
+            ui.label("[N]: Cycle Weather");
+            ui.label("[T]: Traffic Heatmap");
+            ui.label("[F7]: Cycle Noise / Land Value Overlay");
+            ui.label("[I]: Cycle Intersection Control");
         });
 }
 
+fn draw_demand_bars(ui: &mut egui::Ui, demand: &ZoneDemand) {
+    let bars = [
+        ("R", demand.of(Zone::Residential), egui::Color32::from_rgb(80, 200, 80)),
+        ("C", demand.of(Zone::Commercial), egui::Color32::from_rgb(80, 130, 230)),
+        ("I", demand.of(Zone::Industrial), egui::Color32::from_rgb(230, 190, 60)),
+    ];
+
+    let (response, painter) = ui.allocate_painter(egui::Vec2::new(90.0, 60.0), egui::Sense::hover());
+    let rect = response.rect;
+    let mid = rect.center().y;
+    let bar_width = rect.width() / bars.len() as f32;
+
+    painter.hline(rect.x_range(), mid, egui::Stroke::new(1.0, ui.visuals().text_color()));
+
+    for (index, (label, value, color)) in bars.into_iter().enumerate() {
+        let left = rect.left() + bar_width * index as f32 + bar_width * 0.2;
+        let right = left + bar_width * 0.6;
+        let top = mid - value.max(0.0) * (rect.height() / 2.0 - 10.0);
+        let bottom = mid - value.min(0.0) * (rect.height() / 2.0 - 10.0);
+
+        painter.rect_filled(egui::Rect::from_x_y_ranges(left..=right, top..=bottom), 0.0, color);
+        painter.text(
+            egui::pos2((left + right) / 2.0, rect.bottom()),
+            Align2::CENTER_BOTTOM,
+            label,
+            egui::FontId::monospace(10.0),
+            ui.visuals().text_color(),
+        );
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum StatsTab {
+    #[default]
+    Overview,
+    Roads,
+    Trips,
+    Districts,
+}
+
+#[derive(SystemParam)]
+pub struct CityQueries<'w, 's> {
+    building_query: Query<'w, 's, &'static Building>,
+    road_query: Query<'w, 's, (Entity, &'static RoadSegment, Option<&'static Congestion>)>,
+    inter_query: Query<'w, 's, &'static Intersection>,
+    vehicle_query: Query<'w, 's, &'static Vehicle>,
+    lot_query: Query<'w, 's, &'static ParkingLot>,
+}
+
+#[derive(SystemParam)]
+pub struct CityStats<'w> {
+    funds: Res<'w, Funds>,
+    congestion: Res<'w, CongestionStats>,
+    time_of_day: Res<'w, TimeOfDay>,
+    trips: Res<'w, TripStats>,
+    path_cache: Res<'w, PathCache>,
+    watchdog: Res<'w, VehicleWatchdog>,
+    district_stats: Res<'w, DistrictStats>,
+}
+
 pub fn update_stats_window(
     mut contexts: EguiContexts,
-    building_query: Query<&Building>,
-    road_query: Query<&RoadSegment>,
-    inter_query: Query<&Intersection>,
-    vehicle_query: Query<&Vehicle>,
+    queries: CityQueries,
+    stats: CityStats,
+    mut focus: EventWriter<FocusOn>,
+    mut tab: Local<StatsTab>,
 ) {
+    let CityQueries {
+        building_query,
+        road_query,
+        inter_query,
+        vehicle_query,
+        lot_query,
+    } = queries;
+    let CityStats {
+        funds,
+        congestion,
+        time_of_day,
+        trips,
+        path_cache,
+        watchdog,
+        district_stats,
+    } = stats;
+
     let Some(ctx) = contexts.try_ctx_mut() else {
         return;
     };
@@ -154,9 +511,1718 @@ pub fn update_stats_window(
         .constrain(true)
         .movable(false)
         .show(ctx, |ui| {
-            ui.label(format!("Buidings: {:?}", building_query.iter().count()));
-            ui.label(format!("Road Segments: {:?}", road_query.iter().count()));
-            ui.label(format!("Intersections: {:?}", inter_query.iter().count()));
-            ui.label(format!("Vehicles: {:?}", vehicle_query.iter().count()));
+            ui.horizontal(|ui| {
+                ui.selectable_value(&mut *tab, StatsTab::Overview, "Overview");
+                ui.selectable_value(&mut *tab, StatsTab::Roads, "Roads");
+                ui.selectable_value(&mut *tab, StatsTab::Trips, "Trips");
+                ui.selectable_value(&mut *tab, StatsTab::Districts, "Districts");
+            });
+            ui.separator();
+
+            match *tab {
+                StatsTab::Overview => {
+                    ui.label(format!(
+                        "Time: {:02}:{:02}",
+                        time_of_day.hour as u32,
+                        (time_of_day.hour.fract() * 60.0) as u32
+                    ));
+                    ui.label(format!("Buidings: {:?}", building_query.iter().count()));
+                    ui.label(format!("Road Segments: {:?}", road_query.iter().count()));
+                    ui.label(format!("Intersections: {:?}", inter_query.iter().count()));
+                    ui.label(format!("Vehicles: {:?}", vehicle_query.iter().count()));
+                    ui.label(format!(
+                        "Parking: {}/{}",
+                        lot_query.iter().map(ParkingLot::occupied).sum::<usize>(),
+                        lot_query.iter().map(ParkingLot::capacity).sum::<usize>()
+                    ));
+                    ui.label(format!("Funds: ${}", funds.balance));
+                    ui.label(format!("Income: ${}/day", funds.income_per_day));
+                    ui.label(format!(
+                        "Congestion: {:.0}% (Peak {:.0}%)",
+                        congestion.average * 100.0,
+                        congestion.peak * 100.0
+                    ));
+                    ui.label(format!("Trips Completed: {}", trips.total_trips));
+                    ui.label(format!("Mean Trip Time: {:.1}s", trips.mean_trip_time()));
+                    ui.label(format!("Mean Trip Length: {:.1}", trips.mean_trip_length()));
+                    ui.label(format!("Mean Trip Speed: {:.2}", trips.mean_speed()));
+                    ui.label(format!("Trips per Minute: {:.1}", trips.trips_per_minute()));
+                    ui.label(format!(
+                        "Cached Routes: {} ({} hits, {} misses)",
+                        path_cache.cached_routes(),
+                        path_cache.hits,
+                        path_cache.misses
+                    ));
+                    ui.label(format!(
+                        "Stuck Vehicles: {} rerouted, {} moved on, {} removed",
+                        watchdog.rerouted, watchdog.teleported, watchdog.despawned
+                    ));
+                    ui.separator();
+                    for kind in VehicleKind::ALL {
+                        ui.label(format!(
+                            "{:?}: {} trips, {:.1}s mean",
+                            kind,
+                            trips.trips_by_kind[kind as usize],
+                            trips.mean_trip_time_of(kind)
+                        ));
+                    }
+                }
+                StatsTab::Roads => {
+                    let mut congested: Vec<(Entity, &RoadSegment, f32)> = road_query
+                        .iter()
+                        .filter_map(|(entity, segment, congestion)| Some((entity, segment, congestion?.ratio)))
+                        .collect();
+                    congested.sort_by(|(_, _, a), (_, _, b)| b.total_cmp(a));
+
+                    if congested.is_empty() {
+                        ui.label("No traffic measured yet");
+                    }
+
+                    for (entity, segment, ratio) in congested.into_iter().take(CONGESTED_ROADS_SHOWN) {
+                        ui.horizontal(|ui| {
+                            let pos = segment.pos();
+                            ui.label(format!("({:.0}, {:.0}) {:.0}%", pos.x, pos.z, ratio * 100.0));
+                            if ui.small_button("Focus").clicked() {
+                                focus.send(FocusOn(entity));
+                            }
+                        });
+                    }
+                }
+                StatsTab::Trips => {
+                    ui.label(format!("Trips Completed: {}", trips.total_trips));
+                    ui.label(format!("Mean Trip Time: {:.1}s", trips.mean_trip_time()));
+                    ui.label(format!("Recent Trips: {}", trips.recent.len()));
+                    draw_trip_histogram(ui, &trips);
+                }
+                StatsTab::Districts => {
+                    if district_stats.summaries.is_empty() {
+                        ui.label("No districts painted yet");
+                    }
+
+                    egui::Grid::new("district_stats").striped(true).show(ui, |ui| {
+                        for heading in ["District", "Cells", "Buildings", "Population", "Jobs", "Trips", "Congestion"] {
+                            ui.strong(heading);
+                        }
+                        ui.end_row();
+
+                        for summary in &district_stats.summaries {
+                            ui.label(&summary.name);
+                            ui.label(summary.cells.to_string());
+                            ui.label(summary.buildings.to_string());
+                            ui.label(format!("{:.0}", summary.population));
+                            ui.label(format!("{:.0}", summary.jobs));
+                            ui.label(summary.trips.to_string());
+                            ui.label(format!("{:.0}%", summary.congestion * 100.0));
+                            ui.end_row();
+                        }
+                    });
+                }
+            }
+        });
+}
+
+pub fn update_scenario_window(mut contexts: EguiContexts, log: Res<ScenarioLog>, scenario: Res<Scenario>) {
+    if log.messages.is_empty() && scenario.status.is_empty() {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Scenario")
+        .resizable(false)
+        .collapsible(true)
+        .anchor(Align2::CENTER_TOP, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            if !scenario.status.is_empty() {
+                if !scenario.name.is_empty() {
+                    ui.heading(&scenario.name);
+                }
+                for status in &scenario.status {
+                    let mark = if status.met { "[x]" } else { "[ ]" };
+                    ui.label(format!("{} {}", mark, status.label));
+                }
+                if scenario.completed {
+                    ui.label("Scenario complete!");
+                }
+                ui.separator();
+            }
+            for message in log.messages.iter().rev().take(5) {
+                ui.label(message);
+            }
+        });
+}
+
+pub fn update_achievements_window(mut contexts: EguiContexts, profile: Res<Profile>) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Achievements")
+        .resizable(false)
+        .collapsible(true)
+        .default_open(false)
+        .anchor(Align2::LEFT_BOTTOM, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("Roads Built: {}", profile.stats.roads_built));
+            ui.label(format!("Trips Completed: {}", profile.stats.trips_completed));
+            ui.label(format!("Longest Commute: {:.1}s", profile.stats.longest_commute));
+            ui.label(format!("Sessions: {}", profile.stats.sessions));
+            ui.separator();
+
+            for achievement in &ACHIEVEMENTS {
+                let badge = if profile.is_unlocked(achievement) { "[x]" } else { "[ ]" };
+                ui.label(format!("{} {}: {}", badge, achievement.name, achievement.description));
+            }
+        });
+}
+
+pub fn update_simulation_window(
+    mut contexts: EguiContexts,
+    mut spawn_config: ResMut<VehicleSpawnConfig>,
+    mut lod: ResMut<SimulationLodSettings>,
+    mut capacity: ResMut<IntersectionCapacitySettings>,
+    dormant_query: Query<(), With<Dormant>>,
+) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Simulation")
+        .resizable(false)
+        .collapsible(true)
+        .default_open(false)
+        .anchor(Align2::RIGHT_CENTER, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.add(
+                egui::Slider::new(&mut spawn_config.spawns_per_second, 0.1..=20.0)
+                    .logarithmic(true)
+                    .text("Spawns per Second"),
+            );
+            ui.add(egui::Slider::new(&mut spawn_config.buildings_per_vehicle, 1.0..=20.0).text("Buildings per Vehicle"));
+            ui.add(egui::Slider::new(&mut spawn_config.speed_variation, 0.0..=0.9).text("Speed Variation"));
+            for (weight, profile) in spawn_config.driver_mix.iter_mut().zip(DRIVER_PROFILES) {
+                ui.add(egui::Slider::new(weight, 0.0..=1.0).text(format!("{} Drivers", profile.name())));
+            }
+            if ui.button("Reset").clicked() {
+                *spawn_config = VehicleSpawnConfig::default();
+            }
+            ui.separator();
+            ui.checkbox(&mut lod.enabled, "Sleep Distant Vehicles");
+            ui.add_enabled(
+                lod.enabled,
+                egui::Slider::new(&mut lod.radius, 30.0..=500.0).text("Full Simulation Radius"),
+            );
+            ui.label(format!("Sleeping: {}", dormant_query.iter().count()));
+            ui.separator();
+            ui.checkbox(&mut capacity.enabled, "Intersection Capacity");
+            ui.add_enabled(
+                capacity.enabled,
+                egui::Slider::new(&mut capacity.cells_per_vehicle, 1.0..=8.0).text("Cells per Vehicle"),
+            );
+            ui.add(egui::Slider::new(&mut capacity.reroute_spillback, 0.2..=1.0).text("Reroute at Spillback"));
+        });
+}
+
+pub fn update_follow_hud(
+    mut contexts: EguiContexts,
+    controller_query: Query<&PlayerCameraController>,
+    vehicle_query: Query<(&Vehicle, &Transform)>,
+    network: RoadNetwork,
+) {
+    let Some(entity) = controller_query.single().following else {
+        return;
+    };
+
+    let Ok((vehicle, transform)) = vehicle_query.get(entity) else {
+        return;
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    let destination = vehicle.destination().and_then(|dest| network.building(dest));
+    let limit = network.segment(vehicle.path[vehicle.path_index]).map(|segment| segment.speed_limit());
+    let (distance, eta) = vehicle.remaining_route(transform.translation, &network);
+
+    egui::Window::new("Following")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(Align2::RIGHT_TOP, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            if let Some(building) = destination {
+                let cell = building.area().min.pos;
+                ui.label(format!("Destination: Building at ({}, {})", cell.x, cell.y));
+
+                let local = transform.rotation.inverse() * (building.pos() - transform.translation);
+                let heading = egui::Vec2::new(local.x, local.z).normalized();
+                let (rect, _) = ui.allocate_exact_size(egui::Vec2::splat(48.0), egui::Sense::hover());
+                let stroke = egui::Stroke::new(3.0, ui.visuals().strong_text_color());
+                ui.painter().arrow(rect.center() - heading * 18.0, heading * 36.0, stroke);
+            }
+
+            match limit {
+                Some(limit) => ui.label(format!("Speed: {:.2} / {:.2} limit", vehicle.speed, limit)),
+                None => ui.label(format!("Speed: {:.2}", vehicle.speed)),
+            };
+            ui.label(format!("Kind: {:?}", vehicle.kind));
+            ui.label(format!("Driver: {}", vehicle.driver.name()));
+            ui.label(format!("Remaining: {:.1}", distance));
+            ui.label(format!("ETA: {:.0}s", eta));
+        });
+}
+
+pub fn update_inspector_window(
+    mut contexts: EguiContexts,
+    selection: Res<Selection>,
+    mut override_settings: ResMut<SignalOverrideSettings>,
+    inter_query: Query<(&Intersection, Option<&TrafficSignal>)>,
+    mut override_event: EventWriter<RequestSignalOverride>,
+    mut control_event: EventWriter<RequestIntersectionControl>,
+    mut adaptive_event: EventWriter<RequestAdaptiveTiming>,
+) {
+    let Some(entity) = selection.entity else {
+        return;
+    };
+
+    let Ok((intersection, signal)) = inter_query.get(entity) else {
+        return;
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Inspector")
+        .resizable(false)
+        .collapsible(true)
+        .anchor(Align2::RIGHT_TOP, (0.0, 150.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(format!("Control: {:?}", intersection.control));
+                if ui.button("[ I ] Cycle").clicked() {
+                    control_event.send(RequestIntersectionControl {
+                        entity,
+                        control: intersection.control.next(),
+                    });
+                }
+            });
+
+            if intersection.control == IntersectionControl::StopSign {
+                ui.label(format!("Waiting: {}", intersection.arrivals.len()));
+            }
+
+            let Some(signal) = signal else {
+                ui.label("Unsignalized intersection");
+                return;
+            };
+
+            let status = match signal.manual {
+                Some(SignalOverride::AllRed) => "All Red".to_string(),
+                Some(SignalOverride::Flash) => "Flashing".to_string(),
+                Some(SignalOverride::Phase(phase)) => format!("Holding Phase {}", phase + 1),
+                None => format!("Phase {} of {}", signal.phase + 1, signal.phases.len()),
+            };
+            ui.label(format!("Signal: {}", status));
+
+            if signal.manual.is_some() {
+                ui.label(format!("Resumes in {:.0}s", signal.manual_remaining.max(0.0)));
+            }
+
+            let mut adaptive = signal.adaptive;
+            if ui.checkbox(&mut adaptive, "Adaptive timing").changed() {
+                adaptive_event.send(RequestAdaptiveTiming {
+                    entity,
+                    enabled: adaptive,
+                });
+            }
+
+            phase_split(ui, signal);
+            let greens: Vec<String> = signal.greens.iter().map(|green| format!("{:.0}s", green)).collect();
+            ui.label(format!("Greens: {}", greens.join(" / ")));
+
+            ui.add(egui::Slider::new(&mut override_settings.seconds, 5.0..=120.0).text("Override (s)"));
+            let seconds = override_settings.seconds;
+            let mut request = |mode: Option<SignalOverride>| {
+                override_event.send(RequestSignalOverride { entity, mode, seconds });
+            };
+
+            ui.horizontal(|ui| {
+                if ui.button("All Red").clicked() {
+                    request(Some(SignalOverride::AllRed));
+                }
+                if ui.button("Flash").clicked() {
+                    request(Some(SignalOverride::Flash));
+                }
+                if ui.button("Resume").clicked() {
+                    request(None);
+                }
+            });
+
+            ui.horizontal_wrapped(|ui| {
+                for phase in 0..signal.phases.len() {
+                    if ui.button(format!("Phase {}", phase + 1)).clicked() {
+                        request(Some(SignalOverride::Phase(phase)));
+                    }
+                }
+            });
+        });
+}
+
+// A bar across the cycle with a block per phase, sized by its green and clearance time. The current
+// phase is highlighted and marked with how far through it the signal is.
+fn phase_split(ui: &mut egui::Ui, signal: &TrafficSignal) {
+    let (rect, _) = ui.allocate_exact_size(egui::vec2(ui.available_width(), 14.0), egui::Sense::hover());
+    let cycle: f32 = (0..signal.phases.len()).map(|phase| signal.phase_seconds(phase)).sum();
+    if cycle <= 0.0 {
+        return;
+    }
+
+    let mut left = rect.left();
+    for phase in 0..signal.phases.len() {
+        let width = rect.width() * signal.phase_seconds(phase) / cycle;
+        let block = egui::Rect::from_min_size(egui::pos2(left, rect.top()), egui::vec2(width, rect.height()));
+        let current = phase == signal.phase && signal.manual.is_none();
+        let fill = match current {
+            true => ui.visuals().selection.bg_fill,
+            false => ui.visuals().widgets.inactive.bg_fill,
+        };
+        ui.painter().rect_filled(block.shrink(1.0), 2.0, fill);
+
+        if current {
+            let x = left + width * (signal.timer / signal.phase_seconds(phase)).min(1.0);
+            let stroke = egui::Stroke::new(2.0, ui.visuals().strong_text_color());
+            ui.painter().vline(x, rect.y_range(), stroke);
+        }
+        left += width;
+    }
+}
+
+#[derive(SystemParam)]
+pub struct ContextMenuRequests<'w, 's> {
+    demolish: EventWriter<'w, RequestDemolish>,
+    one_way: EventWriter<'w, RequestOneWay>,
+    change_tool: EventWriter<'w, ChangeToolRequest>,
+    upgrade: RoadUpgrader<'w, 's>,
+}
+
+pub fn update_context_menu(
+    mut contexts: EguiContexts,
+    mut menu: ResMut<ContextMenu>,
+    network: RoadNetwork,
+    mut selection: ResMut<Selection>,
+    requests: ContextMenuRequests,
+) {
+    let ContextMenuRequests {
+        mut demolish,
+        mut one_way,
+        mut change_tool,
+        mut upgrade,
+    } = requests;
+
+    let Some(target) = menu.target else {
+        return;
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    let title = match target.entity {
+        Some(entity) if network.building(entity).is_some() => "Building",
+        Some(entity) if network.segment(entity).is_some() => "Road",
+        Some(entity) if network.intersection(entity).is_some() => "Intersection",
+        _ => "Empty Cell",
+    };
+    let segment = target.entity.and_then(|entity| network.segment(entity));
+
+    let area = egui::Area::new(egui::Id::new("context_menu"))
+        .fixed_pos(egui::pos2(target.screen_position.x, target.screen_position.y))
+        .order(egui::Order::Foreground)
+        .show(ctx, |ui| {
+            egui::Frame::menu(ui.style())
+                .show(ui, |ui| {
+                    ui.label(format!("{} ({}, {})", title, target.cell.pos.x, target.cell.pos.y));
+                    ui.separator();
+
+                    let Some(entity) = target.entity else {
+                        return false;
+                    };
+
+                    if ui.button("Demolish Here").clicked() {
+                        demolish.send(RequestDemolish {
+                            entity,
+                            area: GridArea::new(target.cell, target.cell),
+                        });
+                        return true;
+                    }
+
+                    if ui.button("Inspect").clicked() {
+                        selection.entity = Some(entity);
+                        change_tool.send(ChangeToolRequest(ToolState::Inspect));
+                        return true;
+                    }
+
+                    let Some(segment) = segment else {
+                        return false;
+                    };
+
+                    if upgrade.widened_area(segment).is_some() && ui.button("Upgrade Road").clicked() {
+                        upgrade.upgrade(entity, segment);
+                        return true;
+                    }
+
+                    let heading =
+                        |exit: usize| segment.end_heading(exit).map_or(String::new(), |gdir| format!(" ({:?})", gdir));
+                    let exits = match segment.one_way {
+                        None => vec![(format!("Set One-Way{}", heading(1)), Some(1))],
+                        Some(exit) => vec![
+                            (format!("Reverse One-Way{}", heading(1 - exit)), Some(1 - exit)),
+                            ("Make Two-Way".to_string(), None),
+                        ],
+                    };
+                    for (label, exit) in exits {
+                        if ui.button(label).clicked() {
+                            one_way.send(RequestOneWay { entity, exit });
+                            return true;
+                        }
+                    }
+
+                    false
+                })
+                .inner
+        });
+
+    if area.inner || area.response.clicked_elsewhere() {
+        menu.close();
+    }
+}
+
+#[derive(SystemParam)]
+pub struct InspectDetails<'w, 's> {
+    vehicle_query: Query<'w, 's, &'static Vehicle>,
+    lot_query: Query<'w, 's, &'static ParkingLot>,
+    accident_query: Query<'w, 's, &'static Accident>,
+    commutes: Res<'w, Commutes>,
+    capacity: Res<'w, IntersectionCapacitySettings>,
+}
+
+#[derive(SystemParam)]
+pub struct InspectRequests<'w> {
+    speed_limit: EventWriter<'w, RequestSpeedLimit>,
+    turn_restriction: EventWriter<'w, RequestTurnRestriction>,
+    despawn: EventWriter<'w, RequestVehicleDespawn>,
+    focus: EventWriter<'w, FocusOn>,
+}
+
+pub fn update_inspect_panel(
+    mut contexts: EguiContexts,
+    state: Res<State<ToolState>>,
+    selection: Res<Selection>,
+    network: RoadNetwork,
+    details: InspectDetails,
+    requests: InspectRequests,
+) {
+    let InspectDetails {
+        vehicle_query,
+        lot_query,
+        accident_query,
+        commutes,
+        capacity,
+    } = details;
+    let InspectRequests {
+        mut speed_limit,
+        mut turn_restriction,
+        mut despawn,
+        mut focus,
+    } = requests;
+
+    if *state.get() != ToolState::Inspect {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::SidePanel::right("inspect_panel").resizable(false).show(ctx, |ui| {
+        let Some(entity) = selection.entity else {
+            ui.label("Click a building, road, intersection or vehicle");
+            return;
+        };
+
+        ui.horizontal(|ui| {
+            ui.heading(format!("{:?}", entity));
+            if ui.button("Focus").clicked() {
+                focus.send(FocusOn(entity));
+            }
         });
+
+        if let Ok(vehicle) = vehicle_query.get(entity) {
+            ui.label(format!("Kind: {:?}", vehicle.kind));
+            ui.label(format!("Driver: {}", vehicle.driver.name()));
+            ui.label(format!("Speed: {:.2}", vehicle.speed));
+            ui.label(format!("Lane: {}", vehicle.lane));
+            ui.label(format!("Waiting: {}", vehicle.waiting));
+            ui.label(format!("Crashed: {}", vehicle.crashed));
+            ui.label(format!("Trip: {:.0}s, {:.1} units", vehicle.trip_time, vehicle.trip_distance));
+            ui.label(format!("Path: step {} of {}", vehicle.path_index + 1, vehicle.path.len()));
+            egui::ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                for (index, step) in vehicle.path.iter().enumerate() {
+                    let marker = if index == vehicle.path_index { ">" } else { " " };
+                    ui.monospace(format!("{} {:?}", marker, step));
+                }
+            });
+            if ui.button("Despawn").clicked() {
+                despawn.send(RequestVehicleDespawn(entity));
+            }
+        } else if let Some(building) = network.building(entity) {
+            ui.label(format!("Kind: {:?}", building.kind));
+            ui.label(format!("Area: {:?}", building.area));
+            ui.label(format!("Roads: {:?}", building.roads));
+            ui.label(format!("Observers: {}", building.observers.len()));
+            match building.zone() {
+                Zone::Residential => ui.label(format!(
+                    "Residents: {} ({} commuting)",
+                    building.residents(),
+                    commutes.living_at(entity)
+                )),
+                _ => ui.label(format!("Jobs: {}/{} filled", commutes.working_at(entity), building.jobs())),
+            };
+            if let Ok(lot) = lot_query.get(entity) {
+                ui.label(format!("Parked: {}/{}", lot.occupied(), lot.capacity()));
+                ui.label(format!("Reserved: {}", lot.reserved.len()));
+            }
+        } else if let Some(segment) = network.segment(entity) {
+            ui.label(format!("Area: {:?}", segment.area));
+            ui.label(format!("Shape: {:?} ({:?})", segment.shape, segment.orientation));
+            ui.label(format!("Surface: {:?}", segment.surface));
+            ui.label(format!("Lanes: {}", segment.num_lanes()));
+            ui.label(format!("Closed: {}", segment.closed));
+            ui.label(format!("Spillback: {:.0}%", segment.spillback * 100.0));
+            for accident in accident_query.iter().filter(|accident| accident.segment == entity) {
+                ui.label(format!(
+                    "Accident in lane {} ({:.0}s left)",
+                    accident.lane,
+                    accident.remaining.max(0.0)
+                ));
+            }
+            ui.label(format!("Ends: {:?}", segment.ends));
+            ui.label(format!("Observers: {}", segment.observers.len()));
+
+            let mut limit = segment.base_speed_limit();
+            if ui.add(egui::Slider::new(&mut limit, 0.1..=3.0).text("Speed Limit")).changed() {
+                speed_limit.send(RequestSpeedLimit {
+                    entity,
+                    limit: Some(limit),
+                });
+            }
+            if segment.speed_limit_override.is_some() && ui.button("Reset Speed Limit").clicked() {
+                speed_limit.send(RequestSpeedLimit { entity, limit: None });
+            }
+        } else if let Some(intersection) = network.intersection(entity) {
+            ui.label(format!("Area: {:?}", intersection.area));
+            ui.label(format!("Control: {:?}", intersection.control));
+            ui.label(format!("Roads: {:?}", intersection.roads));
+            ui.label(format!("Observers: {}", intersection.observers.len()));
+            ui.label(format!(
+                "Inside: {}/{}",
+                intersection.occupants.len(),
+                intersection.capacity(capacity.cells_per_vehicle)
+            ));
+            ui.label(format!("Queued: {}", intersection.queue));
+            ui.separator();
+            ui.label("Allowed Turns");
+
+            let slots = || (0..4).filter(|&slot| intersection.roads[slot].is_some());
+            for from in slots() {
+                ui.horizontal(|ui| {
+                    ui.label(format!("From {}:", SLOT_NAMES[from]));
+                    for to in slots().filter(|&to| to != from) {
+                        let arrow = match (Movement { from, to }).turn() {
+                            Turn::Left => "\u{2190} Left",
+                            Turn::Straight => "\u{2191} Straight",
+                            Turn::Right => "\u{2192} Right",
+                        };
+                        let restricted = intersection.restricted_turns.contains(&(from, to));
+                        if ui.selectable_label(!restricted, arrow).clicked() {
+                            turn_restriction.send(RequestTurnRestriction {
+                                entity,
+                                from,
+                                to,
+                                restricted: !restricted,
+                            });
+                        }
+                    }
+                });
+            }
+        } else {
+            ui.label("Nothing to inspect");
+        }
+    });
+}
+
+#[derive(SystemParam)]
+pub struct WorldSettings<'w> {
+    autosave: ResMut<'w, AutosaveSettings>,
+    construction: ResMut<'w, ConstructionSettings>,
+    accidents: ResMut<'w, AccidentSettings>,
+    day_cycle: ResMut<'w, DayCycleSettings>,
+    weather: ResMut<'w, WeatherState>,
+    weather_settings: ResMut<'w, WeatherSettings>,
+    headlights: ResMut<'w, HeadlightSettings>,
+}
+
+#[derive(SystemParam)]
+pub struct TrafficSettings<'w> {
+    simulation: Res<'w, SimulationSettings>,
+    gap_acceptance: ResMut<'w, GapAcceptanceSettings>,
+    kind_settings: ResMut<'w, VehicleKindSettings>,
+    audio: ResMut<'w, TrafficAudioSettings>,
+}
+
+#[derive(SystemParam)]
+pub struct DisplaySettings<'w> {
+    building_lod: ResMut<'w, BuildingLodSettings>,
+    graphics: ResMut<'w, GraphicsSettings>,
+    timelapse: ResMut<'w, TimelapseSettings>,
+    panning: ResMut<'w, CameraPanSettings>,
+    keymap: ResMut<'w, Keymap>,
+    instancing: ResMut<'w, VehicleInstancingSettings>,
+}
+
+#[derive(SystemParam)]
+pub struct DiagnosticSettings<'w> {
+    graph_validator: ResMut<'w, GraphValidatorSettings>,
+    metrics: MetricsControls<'w>,
+    bug_report: EventWriter<'w, RequestBugReport>,
+}
+
+pub fn update_settings_window(
+    mut contexts: EguiContexts,
+    world: WorldSettings,
+    traffic: TrafficSettings,
+    display: DisplaySettings,
+    diagnostics: DiagnosticSettings,
+) {
+    let WorldSettings {
+        mut autosave,
+        mut construction,
+        mut accidents,
+        mut day_cycle,
+        mut weather,
+        mut weather_settings,
+        mut headlights,
+    } = world;
+    let TrafficSettings {
+        simulation,
+        mut gap_acceptance,
+        mut kind_settings,
+        mut audio,
+    } = traffic;
+    let DisplaySettings {
+        mut building_lod,
+        mut graphics,
+        mut timelapse,
+        mut panning,
+        mut keymap,
+        mut instancing,
+    } = display;
+    let DiagnosticSettings {
+        mut graph_validator,
+        mut metrics,
+        mut bug_report,
+    } = diagnostics;
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Settings")
+        .resizable(false)
+        .collapsible(true)
+        .default_open(false)
+        .anchor(Align2::LEFT_TOP, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.checkbox(&mut autosave.enabled, "Autosave");
+            ui.add_enabled(
+                autosave.enabled,
+                egui::Slider::new(&mut autosave.interval_minutes, 1.0..=30.0).text("Minutes"),
+            );
+            ui.separator();
+            ui.checkbox(&mut construction.enabled, "Road Construction Time");
+            ui.add_enabled(
+                construction.enabled,
+                egui::Slider::new(&mut construction.seconds_per_cell, 0.1..=5.0).text("Seconds per Cell"),
+            );
+            ui.separator();
+            ui.checkbox(&mut accidents.enabled, "Traffic Accidents");
+            ui.add_enabled(
+                accidents.enabled,
+                egui::Slider::new(&mut accidents.chance_per_second, 0.0..=1.0).text("Crash Chance per Second"),
+            );
+            ui.add_enabled(
+                accidents.enabled,
+                egui::Slider::new(&mut accidents.overlap_distance, 0.1..=1.0).text("Overlap Distance"),
+            );
+            ui.add_enabled(
+                accidents.enabled,
+                egui::Slider::new(&mut accidents.block_seconds, 5.0..=120.0).text("Blocked Seconds"),
+            );
+            ui.add_enabled(
+                accidents.enabled,
+                egui::Checkbox::new(&mut accidents.dispatch_emergency, "Dispatch Emergency Vehicles"),
+            );
+            ui.separator();
+            ui.checkbox(&mut day_cycle.enabled, "Day/Night Cycle");
+            ui.add_enabled(
+                day_cycle.enabled,
+                egui::Slider::new(&mut day_cycle.day_length_seconds, 60.0..=3600.0).text("Seconds per Day"),
+            );
+            ui.separator();
+            ui.horizontal(|ui| {
+                for state in WeatherState::ALL {
+                    if ui.selectable_label(*weather == state, format!("{:?}", state)).clicked() && *weather != state {
+                        *weather = state;
+                    }
+                }
+            });
+            ui.add(egui::Slider::new(&mut weather_settings.rain_speed_factor, 0.3..=1.0).text("Rain Speed Factor"));
+            ui.add(
+                egui::Slider::new(&mut weather_settings.snow_acceleration_factor, 0.1..=1.0)
+                    .text("Snow Acceleration Factor"),
+            );
+            ui.checkbox(&mut headlights.enabled, "Headlights");
+            ui.add_enabled(
+                headlights.enabled,
+                egui::Slider::new(&mut headlights.budget, 0..=256).text("Max Lit Vehicles"),
+            );
+            ui.add_enabled(
+                headlights.enabled,
+                egui::Slider::new(&mut headlights.lod_distance, 5.0..=150.0).text("Headlight Distance"),
+            );
+            ui.checkbox(&mut instancing.enabled, "Instanced Vehicles");
+            ui.label("Faster for large fleets, but vehicles lose shadows and go dark at night.");
+            ui.checkbox(&mut building_lod.enabled, "Merge Distant Buildings");
+            ui.add_enabled(
+                building_lod.enabled,
+                egui::Slider::new(&mut building_lod.distance, 20.0..=300.0).text("Building LOD Distance"),
+            );
+            ui.separator();
+            ui.checkbox(&mut audio.enabled, "Traffic Sounds");
+            ui.add_enabled(audio.enabled, egui::Slider::new(&mut audio.volume, 0.0..=1.0).text("Volume"));
+            ui.add_enabled(
+                audio.enabled,
+                egui::Slider::new(&mut audio.engine_voices, 0..=MAX_ENGINE_VOICES).text("Engine Voices"),
+            );
+            ui.add_enabled(
+                audio.enabled,
+                egui::Slider::new(&mut audio.horn_after_seconds, 1.0..=15.0).text("Horn After Seconds"),
+            );
+            ui.separator();
+            ui.add(egui::Slider::new(&mut gap_acceptance.critical_gap_seconds, 0.5..=6.0).text("Critical Gap Seconds"));
+            ui.checkbox(&mut gap_acceptance.right_on_red, "Right Turn on Red");
+            ui.separator();
+            for kind in VehicleKind::ALL {
+                ui.add(
+                    egui::Slider::new(&mut kind_settings.weights[kind as usize], 0.0..=1.0)
+                        .text(format!("{:?} Spawn Weight", kind)),
+                );
+            }
+            ui.separator();
+            ui.label(format!("Seed: {}", simulation.seed));
+            ui.label(match simulation.fixed_step {
+                Some(hz) => format!("Fixed Timestep: {} Hz", hz),
+                None => "Fixed Timestep: Off".to_string(),
+            });
+            ui.checkbox(&mut graph_validator.enabled, "Validate Road Graph");
+            ui.add_enabled(
+                graph_validator.enabled,
+                egui::Checkbox::new(&mut graph_validator.auto_repair, "Auto Repair Road Graph"),
+            );
+            ui.separator();
+            ui.checkbox(&mut timelapse.enabled, "Timelapse");
+            ui.add_enabled(
+                timelapse.enabled,
+                egui::Slider::new(&mut timelapse.interval_minutes, 5.0..=240.0).text("In-Game Minutes Per Frame"),
+            );
+            ui.add_enabled(
+                timelapse.enabled,
+                egui::Slider::new(&mut timelapse.bookmark, 1..=9).text("Camera Bookmark"),
+            );
+            ui.separator();
+            ui.checkbox(&mut metrics.settings.enabled, "Record Traffic Metrics");
+            ui.add_enabled(
+                metrics.settings.enabled,
+                egui::Slider::new(&mut metrics.settings.interval_seconds, 10.0..=300.0).text("Seconds per Sample"),
+            );
+            ui.horizontal(|ui| {
+                let recorded = metrics.recorder.samples.len();
+                ui.label(format!("Samples: {}", recorded));
+                if ui.add_enabled(recorded > 0, egui::Button::new("Export CSV")).clicked() {
+                    metrics.export.send(RequestMetricsExport(MetricsFormat::Csv));
+                }
+                if ui.add_enabled(recorded > 0, egui::Button::new("Export JSON")).clicked() {
+                    metrics.export.send(RequestMetricsExport(MetricsFormat::Json));
+                }
+            });
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Mouse Pan");
+                for mode in PanMode::ALL {
+                    ui.selectable_value(&mut panning.mode, mode, format!("{:?}", mode));
+                }
+            });
+            ui.checkbox(&mut panning.edge_panning, "Screen Edge Panning");
+            ui.add_enabled(
+                panning.edge_panning,
+                egui::Slider::new(&mut panning.edge_margin, 2.0..=64.0).text("Edge Margin Pixels"),
+            );
+            ui.add_enabled(
+                panning.edge_panning,
+                egui::Slider::new(&mut panning.edge_speed, 2.0..=50.0).text("Edge Pan Speed"),
+            );
+            ui.separator();
+            ui.collapsing("Graphics", |ui| {
+                // Edit a copy so the settings are only applied and written out when something changed.
+                let mut edited = graphics.clone();
+
+                ui.horizontal(|ui| {
+                    for preset in QualityPreset::ALL {
+                        if ui.selectable_label(edited.preset() == Some(preset), format!("{:?}", preset)).clicked() {
+                            edited.apply_preset(preset);
+                        }
+                    }
+                });
+                ui.checkbox(&mut edited.fog, "Fog");
+                ui.checkbox(&mut edited.bloom, "Bloom");
+                ui.checkbox(&mut edited.fxaa, "FXAA");
+                ui.add(egui::Slider::new(&mut edited.shadow_cascades, 1..=MAX_SHADOW_CASCADES).text("Shadow Cascades"));
+                ui.horizontal(|ui| {
+                    ui.label("Shadow Resolution");
+                    for resolution in SHADOW_RESOLUTIONS {
+                        if ui.selectable_label(edited.shadow_resolution == resolution, resolution.to_string()).clicked() {
+                            edited.shadow_resolution = resolution;
+                        }
+                    }
+                });
+                ui.separator();
+                ui.checkbox(&mut edited.vsync, "VSync");
+                ui.horizontal(|ui| {
+                    for mode in DisplayMode::ALL {
+                        if ui.selectable_label(edited.display_mode == mode, format!("{:?}", mode)).clicked() {
+                            edited.display_mode = mode;
+                        }
+                    }
+                });
+
+                if edited != *graphics {
+                    *graphics = edited;
+                }
+            });
+            ui.separator();
+            if ui.button("Report Bug (F9)").clicked() {
+                bug_report.send(RequestBugReport);
+            }
+            ui.separator();
+            ui.collapsing("Key Bindings", |ui| {
+                let mut rebind = None;
+
+                egui::ScrollArea::vertical().max_height(300.0).show(ui, |ui| {
+                    egui::Grid::new("key_bindings").striped(true).show(ui, |ui| {
+                        for (action, key) in keymap.bindings() {
+                            ui.label(format!("{:?}", action));
+                            let text = match (keymap.rebinding == Some(action), key) {
+                                (true, _) => "Press a key...".to_string(),
+                                (false, Some(key)) => format!("{:?}", key),
+                                (false, None) => "Unbound".to_string(),
+                            };
+                            if ui.button(text).clicked() {
+                                rebind = Some(action);
+                            }
+                            ui.end_row();
+                        }
+                    });
+                });
+
+                if rebind.is_some() {
+                    keymap.rebinding = rebind;
+                }
+
+                if ui.button("Reset to Defaults").clicked() {
+                    keymap.reset();
+                }
+            });
+        });
+}
+
+pub fn update_transit_window(
+    mut contexts: EguiContexts,
+    state: Res<State<ToolState>>,
+    mut tool: ResMut<TransitTool>,
+    mut lines: ResMut<TransitLines>,
+) {
+    if *state.get() != ToolState::Transit {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Transit")
+        .resizable(false)
+        .collapsible(true)
+        .anchor(Align2::CENTER_BOTTOM, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.label("[Left Mouse]: Add stop");
+            ui.label("[BACKSPACE]: Remove last stop");
+            ui.label("[ENTER]: Finish line");
+            ui.label("[ESC]: Clear stops");
+            ui.separator();
+            ui.horizontal(|ui| {
+                ui.label("Name");
+                ui.text_edit_singleline(&mut tool.name);
+            });
+            ui.label(format!("Stops: {}", tool.stops.len()));
+            if ui.add_enabled(tool.stops.len() >= 2, egui::Button::new("Finish Line")).clicked() {
+                tool.finish(&mut lines);
+            }
+            ui.separator();
+
+            let mut removed = None;
+            for line in &lines.lines {
+                ui.horizontal(|ui| {
+                    let [r, g, b, _] = line.color().to_srgba().to_u8_array();
+                    ui.colored_label(egui::Color32::from_rgb(r, g, b), &line.name);
+                    ui.label(match line.route.is_empty() {
+                        true => format!("{} stops (no route)", line.stops.len()),
+                        false => format!("{} stops", line.stops.len()),
+                    });
+                    if ui.small_button("Remove").clicked() {
+                        removed = Some(line.id);
+                    }
+                });
+            }
+
+            if let Some(id) = removed {
+                lines.remove(id);
+            }
+        });
+}
+
+pub fn update_blueprint_window(
+    mut contexts: EguiContexts,
+    state: Res<State<ToolState>>,
+    mut tool: ResMut<BlueprintTool>,
+    mut blueprints: ResMut<Blueprints>,
+) {
+    if *state.get() != ToolState::Blueprint {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Blueprints")
+        .resizable(false)
+        .collapsible(true)
+        .anchor(Align2::CENTER_BOTTOM, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            match tool.selected() {
+                Some(_) => {
+                    ui.label("[Left Mouse]: Stamp blueprint");
+                    ui.label("[TAB]: Rotate blueprint");
+                    ui.label("[ESC]: Back to copying");
+                }
+                None => {
+                    ui.label("[Left Mouse] drag: Copy area into a blueprint");
+                    ui.horizontal(|ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut tool.name);
+                    });
+                }
+            }
+            ui.separator();
+
+            let mut removed = None;
+            for (index, blueprint) in blueprints.blueprints.iter().enumerate() {
+                ui.horizontal(|ui| {
+                    let selected = tool.selected() == Some(index);
+                    if ui.selectable_label(selected, &blueprint.name).clicked() {
+                        tool.select(if selected { None } else { Some(index) });
+                    }
+                    ui.label(format!(
+                        "{}x{}, {} pieces, ${}",
+                        blueprint.size.x,
+                        blueprint.size.y,
+                        blueprint.pieces.len(),
+                        blueprint.cost()
+                    ));
+                    if ui.small_button("Delete").clicked() {
+                        removed = Some(index);
+                    }
+                });
+            }
+
+            if let Some(index) = removed {
+                blueprints.remove(index);
+                tool.forget(index);
+            }
+        });
+}
+
+pub fn update_district_window(
+    mut contexts: EguiContexts,
+    state: Res<State<ToolState>>,
+    mut tool_query: Query<&mut DistrictTool>,
+    mut districts: ResMut<Districts>,
+) {
+    if *state.get() != ToolState::District {
+        return;
+    }
+
+    let (Some(ctx), Ok(mut tool)) = (contexts.try_ctx_mut(), tool_query.get_single_mut()) else {
+        return;
+    };
+
+    egui::Window::new("Districts")
+        .resizable(false)
+        .collapsible(true)
+        .anchor(Align2::CENTER_BOTTOM, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.label("[Left Mouse] drag: Paint the selected district");
+            ui.label("[R/F]: Adjust Tool Size");
+            ui.separator();
+
+            if ui.selectable_label(tool.selected.is_none(), "Clear Districts").clicked() {
+                tool.selected = None;
+            }
+
+            // Edit a copy so policies are only applied again when something changed.
+            let mut edited = districts.districts.clone();
+            let mut removed = None;
+            for district in &mut edited {
+                ui.horizontal(|ui| {
+                    let color = Color::from(district.color()).to_srgba().to_u8_array();
+                    let (rect, _) = ui.allocate_exact_size(egui::vec2(12.0, 12.0), egui::Sense::hover());
+                    ui.painter().rect_filled(rect, 2.0, egui::Color32::from_rgb(color[0], color[1], color[2]));
+
+                    let selected = tool.selected == Some(district.id);
+                    if ui.selectable_label(selected, "Select").clicked() {
+                        tool.selected = Some(district.id);
+                    }
+                    ui.text_edit_singleline(&mut district.name);
+                    if ui.small_button("Delete").clicked() {
+                        removed = Some(district.id);
+                    }
+                });
+                ui.add(
+                    egui::Slider::new(&mut district.policy.speed_limit_multiplier, 0.25..=2.0)
+                        .text("Speed Limit Multiplier"),
+                );
+                ui.add(egui::Slider::new(&mut district.policy.spawn_multiplier, 0.0..=3.0).text("Vehicle Spawn Multiplier"));
+            }
+
+            if edited != districts.districts {
+                districts.districts = edited;
+            }
+
+            if let Some(id) = removed {
+                districts.remove(id);
+                tool.selected = tool.selected.filter(|&selected| selected != id);
+            }
+
+            ui.separator();
+            if ui.button("New District").clicked() {
+                tool.selected = Some(districts.add());
+            }
+        });
+}
+
+// Turns finished saves, reports, accidents and the like into notifications, whether or not the UI
+// is showing, so they all reach the message log.
+#[derive(SystemParam)]
+pub struct GameEvents<'w, 's> {
+    saved: EventReader<'w, 's, OnGameSaved>,
+    reported: EventReader<'w, 's, OnBugReportWritten>,
+    rejected: EventReader<'w, 's, OnInsufficientFunds>,
+    accidents: EventReader<'w, 's, OnAccident>,
+    accidents_cleared: EventReader<'w, 's, OnAccidentCleared>,
+    screenshots: EventReader<'w, 's, OnScreenshotSaved>,
+    scenarios: EventReader<'w, 's, OnScenarioCompleted>,
+    exports: EventReader<'w, 's, OnCityExported>,
+    metrics_exports: EventReader<'w, 's, OnMetricsExported>,
+    trips_failed: EventReader<'w, 's, OnTripFailed>,
+    unstuck: EventReader<'w, 's, OnVehicleUnstuck>,
+    surges: EventReader<'w, 's, OnSurgeStarted>,
+}
+
+pub fn notify_game_events(
+    events: GameEvents,
+    mut last_unreachable: Local<Option<f32>>,
+    mut notify: EventWriter<Notify>,
+    time: Res<Time>,
+) {
+    let GameEvents {
+        mut saved,
+        mut reported,
+        mut rejected,
+        mut accidents,
+        mut accidents_cleared,
+        mut screenshots,
+        mut scenarios,
+        mut exports,
+        mut metrics_exports,
+        mut trips_failed,
+        mut unstuck,
+        mut surges,
+    } = events;
+
+    for event in saved.read() {
+        let message = if event.autosave { "Autosaved" } else { "Saved" };
+        notify.send(match &event.conflict {
+            Some(copy) => Notify::warning(format!("{} to {} (kept conflicting copy {})", message, event.path, copy)),
+            None => Notify::info(format!("{} to {}", message, event.path)),
+        });
+    }
+
+    for event in reported.read() {
+        notify.send(Notify::info(format!("Wrote bug report to {}", event.path)));
+    }
+
+    for event in rejected.read() {
+        notify.send(Notify::warning(format!("Not enough funds (costs ${})", event.cost)));
+    }
+
+    for event in accidents.read() {
+        notify.send(Notify::warning(format!(
+            "Accident blocking lane {} of road {}",
+            event.lane, event.segment
+        )));
+    }
+
+    for event in accidents_cleared.read() {
+        notify.send(Notify::info(format!("Accident cleared from road {}", event.segment)));
+    }
+
+    for event in screenshots.read().filter(|event| !event.timelapse) {
+        notify.send(Notify::info(format!("Saved screenshot to {}", event.path)));
+    }
+
+    for event in scenarios.read() {
+        notify.send(Notify::info(format!("Completed scenario {}", event.name)));
+    }
+
+    for event in exports.read() {
+        notify.send(Notify::info(format!("Exported {} meshes to {}", event.meshes, event.path)));
+    }
+
+    for event in metrics_exports.read() {
+        notify.send(Notify::info(format!(
+            "Exported {} metrics samples to {}",
+            event.samples, event.path
+        )));
+    }
+
+    for event in unstuck.read().filter(|event| event.resolution == StuckResolution::Despawned) {
+        notify.send(Notify::warning(format!(
+            "Removed vehicle {} after it was stuck for {:.0}s",
+            event.entity, event.stuck_seconds
+        )));
+    }
+
+    for event in surges.read() {
+        notify.send(Notify::info(format!(
+            "Event at the {:?} {}, {} visitors on their way",
+            event.kind, event.landmark, event.attendance
+        )));
+    }
+
+    // Failed trips come in bursts once part of the city is cut off, so only warn now and then.
+    let now = time.elapsed_seconds();
+    if trips_failed.read().count() > 0 && !last_unreachable.is_some_and(|last| now - last < UNREACHABLE_COOLDOWN_SECONDS) {
+        *last_unreachable = Some(now);
+        notify.send(Notify::warning(
+            "Some trips have no route, part of the city is unreachable by road",
+        ));
+    }
+}
+
+// New notifications stack up above the bottom of the screen and fade out once they expire. The
+// oldest are dropped first when too many arrive at once.
+pub fn update_toasts(
+    mut contexts: EguiContexts,
+    mut notifications: EventReader<Notify>,
+    mut toasts: Local<Vec<(Notify, f32)>>,
+    time: Res<Time>,
+) {
+    toasts.extend(notifications.read().map(|notify| (notify.clone(), TOAST_SECONDS)));
+
+    let excess = toasts.len().saturating_sub(MAX_TOASTS);
+    toasts.drain(..excess);
+
+    for (_, remaining) in toasts.iter_mut() {
+        *remaining -= time.delta_seconds();
+    }
+    toasts.retain(|(_, remaining)| *remaining > 0.0);
+
+    if toasts.is_empty() {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("toasts")).anchor(Align2::CENTER_BOTTOM, (0.0, -40.0)).interactable(false).show(
+        ctx,
+        |ui| {
+            for (notify, remaining) in toasts.iter() {
+                ui.scope(|ui| {
+                    ui.set_opacity((remaining / TOAST_FADE_SECONDS).min(1.0));
+                    egui::Frame::popup(ui.style()).show(ui, |ui| {
+                        ui.colored_label(severity_color(notify.severity), notify.text.as_str());
+                    });
+                });
+            }
+        },
+    );
+}
+
+// A note beside the cursor while a road drag lines up with a road of its width, saying whether the
+// two will merge or meet at a junction.
+pub fn update_road_join_hint(
+    mut contexts: EguiContexts,
+    road_tool_query: Query<&RoadTool>,
+    tool_state: Res<State<ToolState>>,
+    actions: Actions,
+) {
+    if *tool_state.get() != ToolState::Road {
+        return;
+    }
+
+    let (Ok(tool), Some(cursor)) = (road_tool_query.get_single(), actions.pointer_position()) else {
+        return;
+    };
+
+    let key = actions.key(Action::ForceJunction).map_or("the junction key".to_string(), |key| format!("{:?}", key));
+    let hint = match tool.join_hint() {
+        Some(RoadJoin::Merge) => format!("Merge into road (hold {} for a junction)", key),
+        Some(RoadJoin::Junction) => "Junction".to_string(),
+        None => return,
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("road_join_hint"))
+        .fixed_pos(egui::pos2(cursor.x + 16.0, cursor.y + 16.0))
+        .order(egui::Order::Tooltip)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.label(hint);
+            });
+        });
+}
+
+// Key facts about whatever the pointer has rested on in View mode, shown beside the cursor.
+pub fn update_hover_tooltip(
+    mut contexts: EguiContexts,
+    hovered: Res<Hovered>,
+    tool_state: Res<State<ToolState>>,
+    network: RoadNetwork,
+    vehicle_query: Query<&Vehicle>,
+    occupancy_query: Query<&LaneOccupancy>,
+    actions: Actions,
+) {
+    if *tool_state.get() != ToolState::View {
+        return;
+    }
+
+    let (Some(entity), Some(cursor)) = (hovered.settled(), actions.pointer_position()) else {
+        return;
+    };
+
+    let size = |area: GridArea| format!("Size: {} x {}", area.cell_dimensions().x, area.cell_dimensions().y);
+    let lines = if let Some(building) = network.building(entity) {
+        vec![
+            format!("{:?}", building.kind),
+            size(building.area),
+            format!("Trips headed here: {}", building.observers.len()),
+        ]
+    } else if let Some(segment) = network.segment(entity) {
+        let vehicles = occupancy_query.get(entity).map_or(0, |occupancy| occupancy.occupants.len());
+        vec![
+            "Road".to_string(),
+            size(segment.area()),
+            format!("Lanes: {} each way", segment.num_lanes()),
+            format!("Speed limit: {:.2}", segment.speed_limit()),
+            format!("Vehicles: {}", vehicles),
+        ]
+    } else if let Some(intersection) = network.intersection(entity) {
+        vec![
+            "Intersection".to_string(),
+            size(intersection.area()),
+            format!("Control: {:?}", intersection.control),
+            format!("Vehicles: {}", intersection.occupants.len()),
+        ]
+    } else if let Ok(vehicle) = vehicle_query.get(entity) {
+        vec![
+            format!("{:?}", vehicle.kind),
+            format!("Speed: {:.2}", vehicle.speed),
+            format!("Driver: {}", vehicle.driver.name()),
+        ]
+    } else {
+        return;
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("hover_tooltip"))
+        .fixed_pos(egui::pos2(cursor.x + 16.0, cursor.y + 16.0))
+        .order(egui::Order::Tooltip)
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                for line in lines {
+                    ui.label(line);
+                }
+            });
+        });
+}
+
+// A spinner under the toasts while a save is being written or a loaded city is still being built.
+pub fn update_save_progress(mut contexts: EguiContexts, progress: Res<SaveProgress>) {
+    let status = match (progress.saving(), progress.records_to_load()) {
+        (_, left) if left > 0 => format!("Loading... {} left", left),
+        (true, _) => "Saving...".to_string(),
+        (false, _) => return,
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Area::new(egui::Id::new("save_progress"))
+        .anchor(Align2::CENTER_BOTTOM, (0.0, -8.0))
+        .interactable(false)
+        .show(ctx, |ui| {
+            egui::Frame::popup(ui.style()).show(ui, |ui| {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(status);
+                });
+            });
+        });
+}
+
+fn severity_color(severity: Severity) -> egui::Color32 {
+    match severity {
+        Severity::Info => egui::Color32::from_rgb(202, 211, 245),
+        Severity::Warning => egui::Color32::from_rgb(238, 212, 159),
+        Severity::Error => egui::Color32::from_rgb(237, 135, 150),
+    }
+}
+
+// Upcoming events at landmarks with how long until they start, the ones underway with how fast
+// visitors are arriving, and how the last few went.
+pub fn update_events_window(mut contexts: EguiContexts, events: Res<LandmarkEvents>) {
+    if events.surges.is_empty() && events.finished.is_empty() {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    let clock = events.clock;
+    egui::Window::new("Events")
+        .resizable(false)
+        .collapsible(true)
+        .default_open(false)
+        .anchor(Align2::LEFT_TOP, (0.0, 40.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.strong("Underway");
+            for surge in events.surges.iter().filter(|surge| surge.started(clock)) {
+                ui.label(format!(
+                    "{:?} {}: {}/{} arrived, {:.1}/min, {:.0}s left",
+                    surge.kind,
+                    surge.landmark,
+                    surge.arrived(),
+                    surge.attendance,
+                    surge.arrival_rate(clock),
+                    (surge.ends_at - clock).max(0.0)
+                ));
+            }
+
+            ui.separator();
+            ui.strong("Upcoming");
+            for surge in events.surges.iter().filter(|surge| !surge.started(clock)) {
+                ui.label(format!(
+                    "{:?} {}: in {:.0}s, {} expected",
+                    surge.kind,
+                    surge.landmark,
+                    surge.starts_at - clock,
+                    surge.attendance
+                ));
+            }
+
+            ui.separator();
+            ui.strong("Recent");
+            for surge in &events.finished {
+                ui.label(format!(
+                    "{:?} {}: {}/{} arrived, {} unroutable, peak {:.1}/min, avg trip {:.0}s",
+                    surge.kind,
+                    surge.landmark,
+                    surge.arrived(),
+                    surge.dispatched,
+                    surge.failed,
+                    surge.peak_rate(),
+                    surge.mean_trip_time()
+                ));
+            }
+        });
+}
+
+pub fn update_message_log_window(mut contexts: EguiContexts, log: Res<MessageLog>) {
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Messages")
+        .resizable(false)
+        .collapsible(true)
+        .default_open(false)
+        .anchor(Align2::LEFT_CENTER, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            if log.entries.is_empty() {
+                ui.label("No messages yet");
+                return;
+            }
+
+            egui::ScrollArea::vertical().max_height(240.0).stick_to_bottom(true).show(ui, |ui| {
+                for entry in &log.entries {
+                    let hour = entry.hour.rem_euclid(24.0);
+                    ui.colored_label(
+                        severity_color(entry.severity),
+                        format!("[{:02}:{:02}] {}", hour as u32, (hour.fract() * 60.0) as u32, entry.text),
+                    );
+                }
+            });
+        });
+}
+
+// Shown before a generated city is placed. The simulation stays paused until it is closed.
+pub fn update_new_city_window(
+    mut contexts: EguiContexts,
+    mut setup: ResMut<NewCitySetup>,
+    mut request: EventWriter<RequestNewCity>,
+) {
+    if !setup.open {
+        return;
+    }
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("New City")
+        .resizable(false)
+        .collapsible(false)
+        .anchor(Align2::CENTER_CENTER, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            let params = &mut setup.params;
+            ui.horizontal(|ui| {
+                ui.label("Seed");
+                ui.add(egui::DragValue::new(&mut params.seed));
+                if ui.button("Randomize").clicked() {
+                    params.seed = rand::random();
+                }
+            });
+            ui.add(egui::Slider::new(&mut params.blocks, MIN_BLOCKS..=MAX_BLOCKS).text("Blocks"));
+            ui.add(egui::Slider::new(&mut params.block_size, MIN_BLOCK_SIZE..=MAX_BLOCK_SIZE).text("Block Size"));
+            ui.add(egui::Slider::new(&mut params.density, 0.0..=1.0).text("Density"));
+            ui.add(egui::Slider::new(&mut params.irregularity, 0.0..=1.0).text("Grid Irregularity"));
+            ui.add(egui::Slider::new(&mut params.landmarks, 0..=MAX_LANDMARKS).text("Landmarks"));
+            ui.label("Generating replaces everything built so far.");
+
+            ui.horizontal(|ui| {
+                if ui.button("Generate").clicked() {
+                    request.send(RequestNewCity(setup.params));
+                    setup.open = false;
+                }
+                if ui.button("Cancel").clicked() {
+                    setup.open = false;
+                }
+            });
+        });
+}
+
+pub fn update_vehicle_debug_window(
+    mut contexts: EguiContexts,
+    mut debug: ResMut<VehicleDebug>,
+    mut selection: ResMut<Selection>,
+    vehicle_query: Query<&Vehicle>,
+    mut time: ResMut<Time<Virtual>>,
+) {
+    let Some(entity) = selection.entity else {
+        return;
+    };
+
+    let Ok(vehicle) = vehicle_query.get(entity) else {
+        return;
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Vehicle Debug")
+        .resizable(false)
+        .collapsible(true)
+        .anchor(Align2::CENTER_BOTTOM, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            ui.label(format!("{:?} ({:?})", entity, vehicle.kind));
+            ui.label(format!("Speed: {:.2} (max {:.2})", vehicle.speed, vehicle.speed_multiplier));
+            match vehicle.lane_change_from {
+                Some(from) => ui.label(format!(
+                    "Lane: {} -> {} ({:.0}%)",
+                    from,
+                    vehicle.lane,
+                    vehicle.lane_change_progress * 100.0
+                )),
+                None => ui.label(format!("Lane: {}", vehicle.lane)),
+            };
+            ui.label(format!("Lane Target: {:.1} {:.1}", vehicle.follow.x, vehicle.follow.z));
+            ui.label(format!("Checkpoint: {:.1} {:.1}", vehicle.checkpoint.x, vehicle.checkpoint.z));
+            ui.label(format!("Path: step {} of {}", vehicle.path_index + 1, vehicle.path.len()));
+            match vehicle.blocked_by {
+                Some(leader) => ui.label(format!("Ahead: {:?} for {:.1}s", leader, vehicle.obstructed_time)),
+                None => ui.label("Ahead: clear"),
+            };
+            ui.label(format!("Waiting: {}", vehicle.waiting));
+
+            draw_speed_graph(ui, &debug.speeds);
+
+            ui.horizontal(|ui| {
+                let pause_text = if time.is_paused() { "Resume" } else { "Pause" };
+                if ui.button(pause_text).clicked() {
+                    match time.is_paused() {
+                        true => time.unpause(),
+                        false => time.pause(),
+                    }
+                }
+                if ui.add_enabled(time.is_paused(), egui::Button::new("Step Frame")).clicked() {
+                    debug.step_requested = true;
+                }
+                if ui.button("Clear").clicked() {
+                    selection.entity = None;
+                }
+            });
+        });
+}
+
+// Durations of the recent trips, binned from zero up to the longest.
+fn draw_trip_histogram(ui: &mut egui::Ui, trips: &TripStats) {
+    let (response, painter) = ui.allocate_painter(egui::Vec2::new(240.0, 80.0), egui::Sense::hover());
+    let rect = response.rect;
+    let color = ui.visuals().text_color();
+
+    painter.hline(rect.x_range(), rect.bottom(), egui::Stroke::new(1.0, color));
+
+    let longest = trips.recent.iter().map(|trip| trip.duration).fold(1.0, f32::max);
+    let mut bins = [0usize; TRIP_HISTOGRAM_BINS];
+    for trip in &trips.recent {
+        let bin = (trip.duration / longest * TRIP_HISTOGRAM_BINS as f32) as usize;
+        bins[bin.min(TRIP_HISTOGRAM_BINS - 1)] += 1;
+    }
+
+    let tallest = bins.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = rect.width() / TRIP_HISTOGRAM_BINS as f32;
+    for (index, &count) in bins.iter().enumerate() {
+        let left = rect.left() + bar_width * index as f32;
+        let top = rect.bottom() - count as f32 / tallest as f32 * (rect.height() - 12.0);
+        painter.rect_filled(
+            egui::Rect::from_x_y_ranges(left + 1.0..=left + bar_width - 1.0, top..=rect.bottom()),
+            0.0,
+            egui::Color32::from_rgb(90, 200, 250),
+        );
+    }
+
+    painter.text(
+        rect.left_top(),
+        Align2::LEFT_TOP,
+        format!("{}", tallest),
+        egui::FontId::monospace(10.0),
+        color,
+    );
+    painter.text(
+        rect.right_bottom(),
+        Align2::RIGHT_TOP,
+        format!("{:.0}s", longest),
+        egui::FontId::monospace(10.0),
+        color,
+    );
+}
+
+fn draw_speed_graph(ui: &mut egui::Ui, speeds: &VecDeque<f32>) {
+    let (response, painter) = ui.allocate_painter(egui::Vec2::new(240.0, 60.0), egui::Sense::hover());
+    let rect = response.rect;
+    let stroke = egui::Stroke::new(1.0, ui.visuals().text_color());
+
+    painter.hline(rect.x_range(), rect.bottom(), stroke);
+
+    let top = speeds.iter().copied().fold(0.1, f32::max);
+    let step = rect.width() / speeds.len().max(2).saturating_sub(1) as f32;
+    let points: Vec<egui::Pos2> = speeds
+        .iter()
+        .enumerate()
+        .map(|(index, speed)| egui::pos2(rect.left() + step * index as f32, rect.bottom() - speed / top * rect.height()))
+        .collect();
+
+    painter.add(egui::Shape::line(
+        points,
+        egui::Stroke::new(1.5, egui::Color32::from_rgb(90, 200, 250)),
+    ));
+    painter.text(
+        rect.left_top(),
+        Align2::LEFT_TOP,
+        format!("{:.2}", top),
+        egui::FontId::monospace(10.0),
+        ui.visuals().text_color(),
+    );
 }
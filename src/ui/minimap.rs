@@ -0,0 +1,173 @@
+use crate::{
+    capture::capture::ui_visible,
+    graphics::camera::PlayerCameraController,
+    grid::{grid::*, grid_cell::GridCell},
+    schedule::UpdateStage,
+    types::{building::*, intersection::Intersection, road_segment::RoadSegment},
+};
+use bevy::{
+    prelude::*,
+    render::{
+        render_asset::RenderAssetUsages,
+        render_resource::{Extent3d, TextureDimension, TextureFormat},
+        texture::ImageSampler,
+    },
+};
+use bevy_egui::{egui, EguiContexts};
+
+const MINIMAP_SIZE: f32 = 200.0;
+const GROUND_COLOR: [u8; 4] = [51, 102, 51, 255];
+const ROAD_COLOR: [u8; 4] = [70, 70, 75, 255];
+const INTERSECTION_COLOR: [u8; 4] = [95, 95, 100, 255];
+
+pub struct MinimapPlugin;
+
+impl Plugin for MinimapPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, create_minimap).add_systems(
+            Update,
+            (
+                redraw_dirty_chunks.in_set(UpdateStage::Visualize),
+                update_minimap_window.run_if(ui_visible),
+            ),
+        );
+    }
+}
+
+#[derive(Resource, Debug)]
+pub struct Minimap {
+    pub image: Handle<Image>,
+    pub texture: egui::TextureId,
+    pub chunks_redrawn: usize,
+}
+
+fn create_minimap(mut commands: Commands, mut images: ResMut<Assets<Image>>, mut contexts: EguiContexts) {
+    let mut image = Image::new_fill(
+        Extent3d {
+            width: GRID_DIAMETER as u32,
+            height: GRID_DIAMETER as u32,
+            depth_or_array_layers: 1,
+        },
+        TextureDimension::D2,
+        &GROUND_COLOR,
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.sampler = ImageSampler::nearest();
+
+    let image = images.add(image);
+    let texture = contexts.add_image(image.clone_weak());
+
+    commands.insert_resource(Minimap {
+        image,
+        texture,
+        chunks_redrawn: 0,
+    });
+}
+
+fn cell_color(
+    entity: Option<Entity>,
+    building_query: &Query<&Building>,
+    segment_query: &Query<(), With<RoadSegment>>,
+    inter_query: &Query<(), With<Intersection>>,
+) -> [u8; 4] {
+    let Some(entity) = entity else {
+        return GROUND_COLOR;
+    };
+
+    if let Ok(building) = building_query.get(entity) {
+        match building.zone() {
+            Zone::Residential => [80, 200, 80, 255],
+            Zone::Commercial => [80, 130, 230, 255],
+            Zone::Industrial => [230, 190, 60, 255],
+        }
+    } else if segment_query.contains(entity) {
+        ROAD_COLOR
+    } else if inter_query.contains(entity) {
+        INTERSECTION_COLOR
+    } else {
+        GROUND_COLOR
+    }
+}
+
+fn redraw_dirty_chunks(
+    mut grid_query: Query<&mut Grid>,
+    building_query: Query<&Building>,
+    segment_query: Query<(), With<RoadSegment>>,
+    inter_query: Query<(), With<Intersection>>,
+    minimap: Option<ResMut<Minimap>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let (Some(mut minimap), Ok(mut grid)) = (minimap, grid_query.get_single_mut()) else {
+        return;
+    };
+
+    let dirty = grid.take_dirty_chunks();
+    if dirty.is_empty() {
+        return;
+    }
+
+    let Some(image) = images.get_mut(&minimap.image) else {
+        return;
+    };
+
+    for &chunk in &dirty {
+        for cell in Grid::chunk_area(chunk).iter() {
+            let color = cell_color(
+                grid.entity_at(cell).ok().flatten(),
+                &building_query,
+                &segment_query,
+                &inter_query,
+            );
+            let pixel = IVec2::new(cell.pos.x + GRID_RADIUS, GRID_RADIUS - 1 - cell.pos.y);
+            let index = ((pixel.y * GRID_DIAMETER + pixel.x) * 4) as usize;
+            image.data[index..index + 4].copy_from_slice(&color);
+        }
+    }
+
+    minimap.chunks_redrawn += dirty.len();
+}
+
+fn update_minimap_window(
+    mut contexts: EguiContexts,
+    minimap: Option<Res<Minimap>>,
+    mut camera_query: Query<(&Transform, &mut PlayerCameraController)>,
+) {
+    let Some(minimap) = minimap else {
+        return;
+    };
+
+    let Some(ctx) = contexts.try_ctx_mut() else {
+        return;
+    };
+
+    egui::Window::new("Minimap")
+        .resizable(false)
+        .collapsible(true)
+        .default_open(false)
+        .anchor(egui::Align2::RIGHT_CENTER, (0.0, 0.0))
+        .constrain(true)
+        .movable(false)
+        .show(ctx, |ui| {
+            let response = ui.add(
+                egui::Image::new(egui::load::SizedTexture::new(
+                    minimap.texture,
+                    egui::Vec2::splat(MINIMAP_SIZE),
+                ))
+                .sense(egui::Sense::click()),
+            );
+
+            if let Some(click) = response.interact_pointer_pos().filter(|_| response.clicked()) {
+                let uv = (click - response.rect.min) / response.rect.size();
+                let pixel = IVec2::new((uv.x * GRID_DIAMETER as f32) as i32, (uv.y * GRID_DIAMETER as f32) as i32);
+                let cell = GridCell::new(pixel.x - GRID_RADIUS, GRID_RADIUS - 1 - pixel.y);
+
+                if let Ok((transform, mut controller)) = camera_query.get_single_mut() {
+                    controller.focus(transform, cell.center());
+                }
+            }
+
+            ui.label(format!("Chunks redrawn: {}", minimap.chunks_redrawn));
+            ui.label("Click to jump");
+        });
+}
@@ -1 +1,4 @@
 pub mod egui;
+pub mod minimap;
+pub mod notify;
+pub mod notify_events;
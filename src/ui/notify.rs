@@ -0,0 +1,45 @@
+use crate::{
+    graphics::weather::TimeOfDay,
+    schedule::UpdateStage,
+    ui::notify_events::{Notify, Severity},
+};
+use bevy::prelude::*;
+use std::collections::VecDeque;
+
+const MAX_LOG_ENTRIES: usize = 300;
+
+pub struct NotifyPlugin;
+
+impl Plugin for NotifyPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<Notify>()
+            .insert_resource(MessageLog::default())
+            .add_systems(Update, record_notifications.in_set(UpdateStage::Visualize));
+    }
+}
+
+#[derive(Debug)]
+pub struct LoggedMessage {
+    pub severity: Severity,
+    pub text: String,
+    pub hour: f32,
+}
+
+// Every notification sent this session, oldest first. Only the most recent few hundred are kept.
+#[derive(Resource, Debug, Default)]
+pub struct MessageLog {
+    pub entries: VecDeque<LoggedMessage>,
+}
+
+fn record_notifications(mut event: EventReader<Notify>, mut log: ResMut<MessageLog>, time_of_day: Res<TimeOfDay>) {
+    for notify in event.read() {
+        log.entries.push_back(LoggedMessage {
+            severity: notify.severity,
+            text: notify.text.clone(),
+            hour: time_of_day.hour,
+        });
+    }
+
+    let excess = log.entries.len().saturating_sub(MAX_LOG_ENTRIES);
+    log.entries.drain(..excess);
+}